@@ -39,6 +39,7 @@ use std::{
     },
     time::Duration,
 };
+use tauri_bevy_demo_lib::tauri_bridge::frame_export::frame_path;
 
 // =============================================================================
 // Channel Communication Between Main World and Render World
@@ -459,7 +460,7 @@ fn update(
                     println!("[Capture] Saving to: {:?}", images_dir);
                     std::fs::create_dir_all(&images_dir).unwrap();
 
-                    let image_path = images_dir.join(format!("{:03}.png", file_number.deref()));
+                    let image_path = frame_path(&images_dir, *file_number.deref(), "png");
                     *file_number.deref_mut() += 1;
 
                     if let Err(e) = img.save(&image_path) {
@@ -0,0 +1,299 @@
+//! End-to-end frame latency benchmark
+//!
+//! Runs the real render pipeline without a Tauri window and measures, for
+//! each transport and several stream resolutions, how long a frame takes
+//! from publication to being decoded by a client. The results are written
+//! to a CSV report.
+//!
+//! Transports:
+//! - `invoke`: `get_frame` response, JSON-serialized and Base64-decoded
+//! - `jpeg`: `frame://frame`, JPEG-decoded
+//! - `raw`: `frame://frame.raw`, no decoding needed
+//!
+//! The IPC hop of `invoke` and the webview fetch of `frame://` are not
+//! included, only the work both ends do around them.
+//!
+//! Usage: `cargo run --release --example latency_bench [output.csv]`
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::ImageFormat;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use tauri_bevy_demo_lib::{
+    bevy::start_bevy,
+    config::{RENDER_HEIGHT, RENDER_WIDTH},
+    tauri_bridge::{
+        commands::frame_response,
+        encoder::start_encoder,
+        protocol::handle_frame_protocol,
+        shared_state::{BridgeCommand, Frame, FrameResponse},
+        BridgeState,
+    },
+};
+
+// =============================================================================
+// Configuration
+// =============================================================================
+
+/// Stream resolutions to measure, as divisors of the render resolution
+const SCALES: [u32; 3] = [1, 2, 4];
+
+/// Measured frames per transport and resolution
+const SAMPLES: usize = 100;
+
+/// Frames skipped after a resolution change before measuring
+const WARMUP_FRAMES: usize = 10;
+
+/// How long to wait for the first frame (shader compilation, pre-roll)
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy)]
+enum Transport {
+    Invoke,
+    Jpeg,
+    Raw,
+}
+
+impl Transport {
+    const ALL: [Transport; 3] = [Transport::Invoke, Transport::Jpeg, Transport::Raw];
+
+    fn name(self) -> &'static str {
+        match self {
+            Transport::Invoke => "invoke",
+            Transport::Jpeg => "jpeg",
+            Transport::Raw => "raw",
+        }
+    }
+}
+
+// =============================================================================
+// Measurement
+// =============================================================================
+
+/// One measured frame
+struct Sample {
+    frame_id: u64,
+    payload_bytes: usize,
+    /// Server-side encoding (Base64 or JPEG)
+    encode_ms: f64,
+    /// Producing and parsing the response
+    transport_ms: f64,
+    /// Turning the response back into pixels
+    decode_ms: f64,
+    /// Frame publication to decoded pixels
+    publish_to_decoded_ms: f64,
+    gpu_render_ms: f64,
+    gpu_pack_ms: f64,
+    cpu_process_ms: f64,
+}
+
+fn main() {
+    let output = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "latency_report.csv".into());
+
+    println!("=== Frame Latency Benchmark ===");
+
+    let bridge = BridgeState::default();
+    start_bevy(bridge.clone());
+    start_encoder(bridge.clone());
+
+    if wait_for_frame(&bridge, RENDER_WIDTH, RENDER_HEIGHT, STARTUP_TIMEOUT).is_none() {
+        eprintln!("[Bench] No frame within {:?}, giving up", STARTUP_TIMEOUT);
+        std::process::exit(1);
+    }
+
+    let file = File::create(&output).expect("Failed to create report file");
+    let mut report = BufWriter::new(file);
+    writeln!(
+        report,
+        "transport,width,height,sample,frame_id,payload_bytes,gpu_render_ms,gpu_pack_ms,\
+         cpu_process_ms,encode_ms,transport_ms,decode_ms,publish_to_decoded_ms"
+    )
+    .unwrap();
+
+    for scale in SCALES {
+        let (width, height) = (RENDER_WIDTH / scale, RENDER_HEIGHT / scale);
+        bridge
+            .commands
+            .send(BridgeCommand::SetStreamResolution { width, height })
+            .expect("Bevy stopped");
+        if wait_for_frame(&bridge, width, height, STARTUP_TIMEOUT).is_none() {
+            eprintln!("[Bench] Stream never switched to {}x{}, skipping", width, height);
+            continue;
+        }
+        for _ in 0..WARMUP_FRAMES {
+            next_frame(&bridge);
+        }
+
+        for transport in Transport::ALL {
+            let mut total_ms = 0.0;
+            let mut measured = 0;
+            for sample_index in 0..SAMPLES {
+                let frame = next_frame(&bridge);
+                let Some(sample) = measure(&bridge, &frame, transport) else {
+                    continue;
+                };
+                total_ms += sample.publish_to_decoded_ms;
+                measured += 1;
+                writeln!(
+                    report,
+                    "{},{},{},{},{},{},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3}",
+                    transport.name(),
+                    width,
+                    height,
+                    sample_index,
+                    sample.frame_id,
+                    sample.payload_bytes,
+                    sample.gpu_render_ms,
+                    sample.gpu_pack_ms,
+                    sample.cpu_process_ms,
+                    sample.encode_ms,
+                    sample.transport_ms,
+                    sample.decode_ms,
+                    sample.publish_to_decoded_ms,
+                )
+                .unwrap();
+            }
+            println!(
+                "[Bench] {:>6} {:>4}x{:<4} avg publish->decoded: {:.2}ms",
+                transport.name(),
+                width,
+                height,
+                total_ms / measured.max(1) as f64
+            );
+        }
+    }
+
+    report.flush().unwrap();
+    println!("[Bench] Report written to {}", output);
+}
+
+/// Measure one frame over `transport`
+fn measure(bridge: &BridgeState, frame: &Frame, transport: Transport) -> Option<Sample> {
+    let (encode_ms, transport_ms, decode_ms, payload_bytes) = match transport {
+        Transport::Invoke => {
+            bridge.fetch_stats.record_fetch(frame.id);
+            let encode_start = Instant::now();
+            let response = frame_response(frame);
+            let encode_ms = elapsed_ms(encode_start);
+
+            // Tauri serializes command results to JSON, the webview parses it
+            let transport_start = Instant::now();
+            let json = serde_json::to_vec(&response).ok()?;
+            let response: FrameResponse = serde_json::from_slice(&json).ok()?;
+            let transport_ms = elapsed_ms(transport_start);
+
+            let decode_start = Instant::now();
+            STANDARD.decode(&response.data).ok()?;
+            (encode_ms, transport_ms, elapsed_ms(decode_start), json.len())
+        }
+        Transport::Jpeg => {
+            wait_for_encoded(bridge, frame.id)?;
+            let encode_ms = bridge.perf_stats.0.lock().ok()?.jpeg_encode_ms;
+
+            let transport_start = Instant::now();
+            let response = handle_frame_protocol("/frame", bridge);
+            let transport_ms = elapsed_ms(transport_start);
+
+            let decode_start = Instant::now();
+            image::load_from_memory_with_format(response.body(), ImageFormat::Jpeg)
+                .ok()?
+                .to_rgb8();
+            (encode_ms, transport_ms, elapsed_ms(decode_start), response.body().len())
+        }
+        Transport::Raw => {
+            let transport_start = Instant::now();
+            let response = handle_frame_protocol("/frame.raw", bridge);
+            (0.0, elapsed_ms(transport_start), 0.0, response.body().len())
+        }
+    };
+
+    let publish_to_decoded_ms = now_ms() - frame.timestamp_ms;
+    let stats = bridge.perf_stats.0.lock().ok()?;
+    Some(Sample {
+        frame_id: frame.id,
+        payload_bytes,
+        encode_ms,
+        transport_ms,
+        decode_ms,
+        publish_to_decoded_ms,
+        gpu_render_ms: stats.gpu_render_ms,
+        gpu_pack_ms: stats.gpu_copy_ms,
+        cpu_process_ms: stats.data_processing_ms,
+    })
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Block until a frame newer than the current one is published
+fn next_frame(bridge: &BridgeState) -> Arc<Frame> {
+    let current = bridge.frame_buffer.latest().map(|frame| frame.id).unwrap_or(0);
+    loop {
+        bridge.frame_signal.wait_newer(current).expect("Frame signal lost");
+        if let Some(frame) = bridge.frame_buffer.latest() {
+            if frame.id > current {
+                return frame;
+            }
+        }
+    }
+}
+
+/// Wait for a frame of the given size, fetching frames so the adaptive
+/// frame rate doesn't throttle the benchmark while waiting
+fn wait_for_frame(
+    bridge: &BridgeState,
+    width: u32,
+    height: u32,
+    timeout: Duration,
+) -> Option<Arc<Frame>> {
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if let Some(frame) = bridge.frame_buffer.latest() {
+            bridge.fetch_stats.record_fetch(frame.id);
+            if (frame.width, frame.height) == (width, height) {
+                return Some(frame);
+            }
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    None
+}
+
+/// Wait until the encoder has caught up with `frame_id`
+fn wait_for_encoded(bridge: &BridgeState, frame_id: u64) -> Option<()> {
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(1) {
+        let encoded_id = bridge
+            .encoded_frame
+            .0
+            .lock()
+            .ok()?
+            .as_ref()
+            .map(|encoded| encoded.frame_id);
+        if encoded_id >= Some(frame_id) {
+            return Some(());
+        }
+        thread::sleep(Duration::from_micros(200));
+    }
+    None
+}
+
+fn elapsed_ms(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+fn now_ms() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64() * 1000.0)
+        .unwrap_or_default()
+}
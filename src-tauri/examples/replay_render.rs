@@ -0,0 +1,154 @@
+//! Offline replay of a recorded session
+//!
+//! Re-renders a session recorded with `start_input_recording` /
+//! `stop_input_recording` in deterministic mode, at any resolution and
+//! anti-aliasing level, and writes every frame as a numbered PNG. The
+//! camera follows exactly the path it took live, and the animation shows
+//! the same poses, so the frames can be assembled into a polished video:
+//!
+//! ```sh
+//! cargo run --release --example replay_render -- session.json frames --size 1920x1080 --fps 60
+//! ffmpeg -framerate 60 -i frames/%05d.png -pix_fmt yuv420p session.mp4
+//! ```
+//!
+//! Options:
+//! - `--size WxH`: render resolution (default: the app's render size)
+//! - `--fps N`: frames per second of scene time (default: 60)
+//! - `--msaa 1|2|4|8`: MSAA samples (default: 4)
+
+use bevy::prelude::Msaa;
+use std::{fs::File, io::BufReader, path::PathBuf, process::ExitCode};
+
+use tauri_bevy_demo_lib::{
+    bevy::{start_bevy_with, AppOptions},
+    tauri_bridge::{
+        frame_export::{export_frames, ImageFileFormat},
+        shared_state::InputRecording,
+        BridgeState,
+    },
+};
+
+struct Args {
+    recording: PathBuf,
+    output_dir: PathBuf,
+    size: Option<(u32, u32)>,
+    fps: f64,
+    msaa: Msaa,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut positional = Vec::new();
+    let mut size = None;
+    let mut fps = 60.0;
+    let mut msaa = Msaa::Sample4;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--size" => {
+                let value = value()?;
+                let (width, height) = value
+                    .split_once('x')
+                    .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                    .ok_or_else(|| format!("Invalid size '{}', expected WxH", value))?;
+                size = Some((width, height));
+            }
+            "--fps" => {
+                fps = value()?
+                    .parse()
+                    .ok()
+                    .filter(|fps: &f64| *fps > 0.0)
+                    .ok_or("Invalid --fps")?;
+            }
+            "--msaa" => {
+                msaa = match value()?.as_str() {
+                    "1" => Msaa::Off,
+                    "2" => Msaa::Sample2,
+                    "4" => Msaa::Sample4,
+                    "8" => Msaa::Sample8,
+                    other => return Err(format!("Invalid --msaa '{}'", other)),
+                };
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    let [recording, output_dir] = <[String; 2]>::try_from(positional)
+        .map_err(|_| "Usage: replay_render <recording.json> <output_dir> [options]".to_string())?;
+    Ok(Args {
+        recording: recording.into(),
+        output_dir: output_dir.into(),
+        size,
+        fps,
+        msaa,
+    })
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("[Replay] {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let recording: InputRecording = match File::open(&args.recording)
+        .map_err(|e| e.to_string())
+        .and_then(|file| serde_json::from_reader(BufReader::new(file)).map_err(|e| e.to_string()))
+    {
+        Ok(recording) => recording,
+        Err(e) => {
+            eprintln!("[Replay] {}: {}", args.recording.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // One frame per fixed step, covering the whole recording
+    let duration_s = recording.end_time_s - recording.start_time_s;
+    let frame_count = (duration_s * args.fps).ceil().max(1.0) as u32;
+    println!(
+        "[Replay] {:.1}s session, {} frames at {}fps",
+        duration_s, frame_count, args.fps
+    );
+
+    let bridge = BridgeState::default();
+    start_bevy_with(
+        bridge.clone(),
+        AppOptions {
+            render_size: args.size,
+            msaa: Some(args.msaa),
+            fixed_fps: Some(args.fps),
+            replay: Some(recording),
+            ..Default::default()
+        },
+    );
+
+    match export_frames(
+        &bridge,
+        &args.output_dir,
+        frame_count,
+        ImageFileFormat::Png,
+        false,
+    ) {
+        Ok(summary) if summary.frames_missed > 0 => {
+            eprintln!(
+                "[Replay] {} frames were missed while writing, the video will skip",
+                summary.frames_missed
+            );
+            ExitCode::FAILURE
+        }
+        Ok(summary) => {
+            println!(
+                "[Replay] Wrote {} frames to {}",
+                summary.frames_written, summary.dir
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("[Replay] {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
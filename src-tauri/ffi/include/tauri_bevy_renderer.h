@@ -0,0 +1,87 @@
+/*
+ * C API for the headless renderer, see ffi/src/lib.rs
+ *
+ *     TbrRenderer *renderer = tbr_create(1280, 720);
+ *     TbrFrame frame;
+ *     if (tbr_pull_frame(renderer, 0, 1000, &frame) == TBR_OK) {
+ *         upload(frame.data, frame.width, frame.height, frame.format);
+ *         tbr_frame_release(&frame);
+ *     }
+ *     tbr_push_mouse_input(renderer, 12.0f, 0.0f, 0.0f, TBR_BUTTON_LEFT);
+ *     tbr_send_command(renderer, "{\"command\": \"frame_all\"}", NULL);
+ *     tbr_destroy(renderer);
+ *
+ * Functions returning int32_t return TBR_OK on success and TBR_ERROR on
+ * failure, with the message from tbr_last_error().
+ */
+
+#ifndef TAURI_BEVY_RENDERER_H
+#define TAURI_BEVY_RENDERER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define TBR_OK 0
+#define TBR_NO_FRAME 1
+#define TBR_ERROR (-1)
+
+#define TBR_BUTTON_LEFT 1u
+#define TBR_BUTTON_RIGHT 2u
+#define TBR_BUTTON_MIDDLE 4u
+
+#define TBR_FORMAT_RGBA8 0u
+#define TBR_FORMAT_RGB8 1u
+#define TBR_FORMAT_RGB565 2u
+
+typedef struct TbrRenderer TbrRenderer;
+
+typedef struct TbrFrame {
+    uint64_t id;
+    /* Publication time in milliseconds since the Unix epoch */
+    double timestamp_ms;
+    uint32_t width;
+    uint32_t height;
+    /* One of the TBR_FORMAT_* constants */
+    uint32_t format;
+    /* Tightly packed pixel rows, valid until tbr_frame_release() */
+    const uint8_t *data;
+    size_t len;
+    /* Owned by the library */
+    void *handle;
+} TbrFrame;
+
+/* Start a renderer, at the default render size if width or height is 0 */
+TbrRenderer *tbr_create(uint32_t width, uint32_t height);
+
+/* Stop a renderer and wait for its thread to end */
+void tbr_destroy(TbrRenderer *renderer);
+
+/* Add mouse input, buttons being the current TBR_BUTTON_* bits */
+int32_t tbr_push_mouse_input(TbrRenderer *renderer, float delta_x, float delta_y,
+                             float scroll_delta, uint32_t buttons);
+
+/* Get the latest frame with an id above after_id, waiting up to
+ * timeout_ms; TBR_NO_FRAME if none arrived */
+int32_t tbr_pull_frame(TbrRenderer *renderer, uint64_t after_id, uint32_t timeout_ms,
+                       TbrFrame *frame);
+
+void tbr_frame_release(TbrFrame *frame);
+
+/* Send a control server command as JSON; reply, if not NULL, receives the
+ * result as JSON, to be freed with tbr_string_free() */
+int32_t tbr_send_command(TbrRenderer *renderer, const char *json, char **reply);
+
+void tbr_string_free(char *string);
+
+/* Message of the last error on this thread, or NULL */
+const char *tbr_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TAURI_BEVY_RENDERER_H */
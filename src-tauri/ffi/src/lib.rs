@@ -0,0 +1,331 @@
+//! C API for the headless renderer
+//!
+//! Embeds the same Bevy app the Tauri frontend drives in any host that can
+//! call C, e.g. a Qt application or an Electron N-API addon. The host
+//! creates a renderer, pushes mouse input, pulls frames and sends commands;
+//! rendering runs on the renderer's own thread. See
+//! `include/tauri_bevy_renderer.h` for the C declarations.
+//!
+//! Functions returning `int32_t` return [`TBR_OK`] on success and
+//! [`TBR_ERROR`] on failure, with a message for [`tbr_last_error`].
+//! Only one renderer should exist per process at a time, since Bevy keeps
+//! global state such as its task pools.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use tauri_bevy_demo_lib::bevy::{start_bevy_with, AppOptions};
+use tauri_bevy_demo_lib::tauri_bridge::control_server;
+use tauri_bevy_demo_lib::tauri_bridge::shared_state::{
+    BridgeCommand, Frame, MouseInput, PixelFormat,
+};
+use tauri_bevy_demo_lib::tauri_bridge::BridgeState;
+
+/// Success
+pub const TBR_OK: i32 = 0;
+/// No frame newer than the one asked about arrived in time
+pub const TBR_NO_FRAME: i32 = 1;
+/// Failure, see [`tbr_last_error`]
+pub const TBR_ERROR: i32 = -1;
+
+/// `buttons` bit of the left mouse button
+pub const TBR_BUTTON_LEFT: u32 = 1;
+/// `buttons` bit of the right mouse button
+pub const TBR_BUTTON_RIGHT: u32 = 2;
+/// `buttons` bit of the middle mouse button
+pub const TBR_BUTTON_MIDDLE: u32 = 4;
+
+/// [`TbrFrame::format`] of 4 bytes per pixel, R G B A
+pub const TBR_FORMAT_RGBA8: u32 = 0;
+/// [`TbrFrame::format`] of 3 bytes per pixel, R G B
+pub const TBR_FORMAT_RGB8: u32 = 1;
+/// [`TbrFrame::format`] of 2 bytes per pixel, little-endian 5-6-5 bit R G B
+pub const TBR_FORMAT_RGB565: u32 = 2;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// A running renderer, opaque to C
+pub struct TbrRenderer {
+    bridge: BridgeState,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// A frame pulled with [`tbr_pull_frame`]
+///
+/// `data` stays valid until the frame is passed to [`tbr_frame_release`].
+#[repr(C)]
+pub struct TbrFrame {
+    pub id: u64,
+    /// Publication time in milliseconds since the Unix epoch
+    pub timestamp_ms: f64,
+    pub width: u32,
+    pub height: u32,
+    /// One of the `TBR_FORMAT_*` constants
+    pub format: u32,
+    /// Tightly packed pixel rows
+    pub data: *const u8,
+    pub len: usize,
+    /// Keeps `data` alive, owned by the library
+    pub handle: *mut std::ffi::c_void,
+}
+
+/// Run `body`, turning errors and panics into [`TBR_ERROR`] and the last
+/// error message
+fn guard(body: impl FnOnce() -> Result<i32, String>) -> i32 {
+    let result = panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|panic| {
+        Err(panic
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Panicked".to_string()))
+    });
+    match result {
+        Ok(status) => status,
+        Err(message) => {
+            set_last_error(message);
+            TBR_ERROR
+        }
+    }
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+}
+
+/// Start a renderer at `width` x `height`, or the default render size if
+/// either is 0
+///
+/// Returns null on failure. Frames are available once the renderer has
+/// started up, which takes a moment.
+#[no_mangle]
+pub extern "C" fn tbr_create(width: u32, height: u32) -> *mut TbrRenderer {
+    let mut renderer = ptr::null_mut();
+    guard(|| {
+        let bridge = BridgeState::default();
+        let options = AppOptions {
+            render_size: (width > 0 && height > 0).then_some((width, height)),
+            ..Default::default()
+        };
+        let thread = start_bevy_with(bridge.clone(), options);
+        renderer = Box::into_raw(Box::new(TbrRenderer {
+            bridge,
+            thread: Some(thread),
+        }));
+        Ok(TBR_OK)
+    });
+    renderer
+}
+
+/// Stop a renderer and wait for its thread to end
+///
+/// # Safety
+///
+/// `renderer` must be null or come from [`tbr_create`], and must not be
+/// used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn tbr_destroy(renderer: *mut TbrRenderer) {
+    if renderer.is_null() {
+        return;
+    }
+    let mut renderer = Box::from_raw(renderer);
+    guard(|| {
+        // Fails if the thread already ended, which is fine
+        let _ = renderer.bridge.commands.send(BridgeCommand::Exit);
+        if let Some(thread) = renderer.thread.take() {
+            thread
+                .join()
+                .map_err(|_| "Render thread panicked".to_string())?;
+        }
+        Ok(TBR_OK)
+    });
+}
+
+/// Add mouse input for the camera, applied next frame
+///
+/// Deltas add up until the next frame reads them; `buttons` is the current
+/// state of the `TBR_BUTTON_*` bits.
+///
+/// # Safety
+///
+/// `renderer` must come from [`tbr_create`].
+#[no_mangle]
+pub unsafe extern "C" fn tbr_push_mouse_input(
+    renderer: *mut TbrRenderer,
+    delta_x: f32,
+    delta_y: f32,
+    scroll_delta: f32,
+    buttons: u32,
+) -> i32 {
+    guard(|| {
+        let renderer = renderer.as_ref().ok_or("renderer is null")?;
+        if ![delta_x, delta_y, scroll_delta]
+            .iter()
+            .all(|value| value.is_finite())
+        {
+            return Err("Input deltas must be finite".to_string());
+        }
+        renderer.bridge.mouse_input.add(&MouseInput {
+            delta_x,
+            delta_y,
+            scroll_delta,
+            left_button: buttons & TBR_BUTTON_LEFT != 0,
+            right_button: buttons & TBR_BUTTON_RIGHT != 0,
+            middle_button: buttons & TBR_BUTTON_MIDDLE != 0,
+        })?;
+        Ok(TBR_OK)
+    })
+}
+
+/// Get the latest frame if its id is above `after_id`, waiting up to
+/// `timeout_ms` for one
+///
+/// Returns [`TBR_NO_FRAME`] if there is none, leaving `frame` untouched.
+/// Pass 0 as `after_id` for whatever frame is latest.
+///
+/// # Safety
+///
+/// `renderer` must come from [`tbr_create`] and `frame` point to a
+/// writable [`TbrFrame`].
+#[no_mangle]
+pub unsafe extern "C" fn tbr_pull_frame(
+    renderer: *mut TbrRenderer,
+    after_id: u64,
+    timeout_ms: u32,
+    frame: *mut TbrFrame,
+) -> i32 {
+    guard(|| {
+        let renderer = renderer.as_ref().ok_or("renderer is null")?;
+        let out = frame.as_mut().ok_or("frame is null")?;
+        let Some(latest) = wait_for_frame(
+            &renderer.bridge,
+            after_id,
+            Duration::from_millis(timeout_ms.into()),
+        ) else {
+            return Ok(TBR_NO_FRAME);
+        };
+        *out = TbrFrame {
+            id: latest.id,
+            timestamp_ms: latest.timestamp_ms,
+            width: latest.width,
+            height: latest.height,
+            format: match latest.format {
+                PixelFormat::Rgba8 => TBR_FORMAT_RGBA8,
+                PixelFormat::Rgb8 => TBR_FORMAT_RGB8,
+                PixelFormat::Rgb565 => TBR_FORMAT_RGB565,
+            },
+            data: latest.data.as_ptr(),
+            len: latest.data.len(),
+            handle: Arc::into_raw(latest) as *mut _,
+        };
+        Ok(TBR_OK)
+    })
+}
+
+/// The latest frame with an id above `after`, waiting up to `timeout`
+fn wait_for_frame(bridge: &BridgeState, after: u64, timeout: Duration) -> Option<Arc<Frame>> {
+    let deadline = Instant::now() + timeout;
+    // Publications seen so far, so waiting only returns for newer ones
+    let mut seen = bridge
+        .frame_signal
+        .wait_newer_timeout(0, Duration::ZERO)
+        .unwrap_or(0);
+    loop {
+        if let Some(frame) = bridge
+            .frame_buffer
+            .latest()
+            .filter(|frame| frame.id > after)
+        {
+            return Some(frame);
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return None;
+        }
+        seen = bridge.frame_signal.wait_newer_timeout(seen, remaining)?;
+    }
+}
+
+/// Release a frame from [`tbr_pull_frame`]
+///
+/// # Safety
+///
+/// `frame` must be null or filled in by [`tbr_pull_frame`], and not be
+/// released twice.
+#[no_mangle]
+pub unsafe extern "C" fn tbr_frame_release(frame: *mut TbrFrame) {
+    let Some(frame) = frame.as_mut() else {
+        return;
+    };
+    if !frame.handle.is_null() {
+        drop(Arc::from_raw(frame.handle as *const Frame));
+    }
+    frame.handle = ptr::null_mut();
+    frame.data = ptr::null();
+    frame.len = 0;
+}
+
+/// Send a command as a JSON object, the same a control server client
+/// sends, e.g. `{"command": "set_standard_view", "view": "front"}`
+///
+/// If `reply` isn't null, it receives the result as a JSON string, `null`
+/// for commands without one, to be freed with [`tbr_string_free`].
+///
+/// # Safety
+///
+/// `renderer` must come from [`tbr_create`], `json` be a NUL-terminated
+/// string and `reply` be null or point to a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn tbr_send_command(
+    renderer: *mut TbrRenderer,
+    json: *const c_char,
+    reply: *mut *mut c_char,
+) -> i32 {
+    guard(|| {
+        let renderer = renderer.as_ref().ok_or("renderer is null")?;
+        if json.is_null() {
+            return Err("json is null".to_string());
+        }
+        let request = CStr::from_ptr(json).to_str().map_err(|e| e.to_string())?;
+        let result = control_server::execute(request, &renderer.bridge)?;
+        if let Some(reply) = reply.as_mut() {
+            let result = serde_json::to_string(&result).map_err(|e| e.to_string())?;
+            *reply = CString::new(result).map_err(|e| e.to_string())?.into_raw();
+        }
+        Ok(TBR_OK)
+    })
+}
+
+/// Free a string returned by this library
+///
+/// # Safety
+///
+/// `string` must be null or come from this library, and not be freed
+/// twice.
+#[no_mangle]
+pub unsafe extern "C" fn tbr_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Message of the last error on this thread, or null
+///
+/// The string is owned by the library and valid until the next call that
+/// fails on this thread.
+#[no_mangle]
+pub extern "C" fn tbr_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| {
+        error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
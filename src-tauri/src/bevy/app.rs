@@ -4,78 +4,383 @@
 //! including plugin registration and system scheduling.
 
 use bevy::{
-    app::{App, ScheduleRunnerPlugin},
+    app::{App, AppExit, PluginsState, TaskPoolOptions, TaskPoolPlugin},
+    asset::UnapprovedPathMode,
+    camera::visibility::VisibilitySystems,
     prelude::*,
+    render::render_asset::RenderAssetBytesPerFrame,
     window::ExitCondition,
 };
+use std::sync::Arc;
 use std::time::Duration;
-use std::thread;
+use std::thread::{self, JoinHandle};
 
-use crate::config::{TARGET_FPS, PRE_ROLL_FRAMES};
+use crate::config::{adaptive, model, simulation, threading, TARGET_FPS, PRE_ROLL_FRAMES};
+use crate::platform;
+use crate::startup::{self, StartupPhase};
 use crate::tauri_bridge::shared_state::{
-    SharedFrameBuffer, SharedMouseInput, SharedPerfStats,
+    BevyThreadState, BridgeState, InputRecording, SharedRendererHealth,
 };
-use crate::bevy::plugins::ImageCopyPlugin;
+use crate::bevy::gpu::{publish_gpu_info, GpuSelection};
+use crate::bevy::gpu_errors::report_uncaptured_errors;
+use crate::bevy::pacing::FramePacer;
+use crate::bevy::plugins::{
+    ColorLutPlugin, CustomShaderPlugin, DebugViewPlugin, DownscalePlugin, EnvironmentMapPlugin,
+    GpuMemoryPlugin, GpuTimingPlugin, IdBufferPlugin, ImageCopyPlugin, ModelFormatsPlugin,
+    ShadowCatcherPlugin, TerrainPlugin, TextureSharePlugin, WaterPlugin,
+};
+use crate::bevy::plugins::texture_share::TextureShare;
 use crate::bevy::resources::*;
 use crate::bevy::systems::*;
 
+/// Settings for running the app other than as the interactive stream
+#[derive(Default)]
+pub struct AppOptions {
+    /// Render target size, `RENDER_WIDTH` x `RENDER_HEIGHT` if `None`
+    pub render_size: Option<(u32, u32)>,
+    /// Anti-aliasing of the render camera, Bevy's default if `None`
+    pub msaa: Option<Msaa>,
+    /// Deterministic mode: advance time by exactly `1 / fixed_fps` per
+    /// frame, however long the frame actually took. Defaults to
+    /// `simulation::FIXED_FPS`
+    pub fixed_fps: Option<f64>,
+    /// Drive the camera from this recording instead of live input
+    pub replay: Option<InputRecording>,
+    /// Hand the stream texture to these on the GPU, see
+    /// [`TextureSharePlugin`]
+    pub texture_shares: Vec<Box<dyn TextureShare>>,
+}
+
 /// Create and configure the Bevy application
-pub fn create_app(
-    frame_buffer: SharedFrameBuffer,
-    perf_stats: SharedPerfStats,
-    mouse_input: SharedMouseInput,
-) -> App {
+pub fn create_app(bridge: BridgeState) -> App {
+    create_app_with(bridge, AppOptions::default())
+}
+
+/// Create the Bevy application with non-default [`AppOptions`]
+pub fn create_app_with(bridge: BridgeState, options: AppOptions) -> App {
     let mut app = App::new();
 
     // Use DefaultPlugins but configure for headless operation
-    app.add_plugins(
-        DefaultPlugins
-            .set(WindowPlugin {
-                primary_window: None,
-                exit_condition: ExitCondition::DontExit,
-                ..default()
-            })
-            .set(ImagePlugin::default_nearest()),
-    );
+    let plugins = DefaultPlugins
+        .set(WindowPlugin {
+            primary_window: None,
+            exit_condition: ExitCondition::DontExit,
+            ..default()
+        })
+        .set(ImagePlugin::default_nearest())
+        // Files outside the assets folder only load where a command opts
+        // in, see `model_loading::approve_model_dir`
+        .set(AssetPlugin {
+            unapproved_path_mode: UnapprovedPathMode::Deny,
+            ..default()
+        })
+        .set(task_pool_plugin())
+        .set(GpuSelection::from_args(std::env::args().skip(1)).render_plugin());
+    // Bevy's log plugin owns the tracing subscriber, record traces through it
+    #[cfg(feature = "bevy_log")]
+    let plugins = plugins.set(bevy::log::LogPlugin {
+        custom_layer: crate::profiling::bevy_log_layer,
+        ..default()
+    });
+    app.add_plugins(plugins);
 
-    // Add schedule runner for controlled frame rate
-    app.add_plugins(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
-        1.0 / TARGET_FPS,
-    )));
+    // Run the schedule in a loop paced by the adaptive frame rate
+    app.set_runner(adaptive_runner);
 
     // Add custom plugins
-    app.add_plugins(ImageCopyPlugin);
+    app.add_plugins(ImageCopyPlugin {
+        buffer_pool: bridge.buffer_pool.clone(),
+        gpu_errors: bridge.gpu_errors.clone(),
+    });
+    // Must come after ImageCopyPlugin, it hooks in before the copy node
+    app.add_plugins(DownscalePlugin {
+        gpu_errors: bridge.gpu_errors.clone(),
+    });
+    app.add_plugins(GpuTimingPlugin {
+        perf_stats: bridge.perf_stats.clone(),
+    });
+    app.add_plugins(GpuMemoryPlugin {
+        perf_stats: bridge.perf_stats.clone(),
+    });
+    app.add_plugins(DebugViewPlugin);
+    app.add_plugins(IdBufferPlugin);
+    app.add_plugins(ModelFormatsPlugin);
+    // Spreads the upload of a large model over several frames
+    app.insert_resource(RenderAssetBytesPerFrame::new(model::UPLOAD_BYTES_PER_FRAME));
+    app.add_plugins(EnvironmentMapPlugin);
+    app.add_plugins(ShadowCatcherPlugin);
+    app.add_plugins(ColorLutPlugin {
+        gpu_errors: bridge.gpu_errors.clone(),
+    });
+    app.add_plugins(CustomShaderPlugin {
+        gpu_errors: bridge.gpu_errors.clone(),
+    });
+    app.add_plugins(TerrainPlugin);
+    app.add_plugins(WaterPlugin);
 
     // Register systems
     app.add_systems(Startup, setup_scene);
+    app.add_systems(Startup, publish_gpu_info);
+    app.add_systems(Startup, watch_device_lost);
+    app.add_systems(Startup, report_uncaptured_errors);
+    app.add_systems(First, publish_heartbeat);
+    app.add_systems(First, mark_frame_start);
+    app.add_systems(First, apply_bridge_commands);
+    app.add_systems(First, advance_scene_time);
+    app.add_systems(Update, update_stream_target);
     app.add_systems(Update, rotate_cubes);
+    app.add_systems(Update, follow_camera_focus.before(update_camera_from_input));
     app.add_systems(Update, update_camera_from_input);
+    app.add_systems(Update, advance_camera_path.after(update_camera_from_input));
+    app.add_systems(Update, apply_camera_shake.after(advance_camera_path));
+    app.add_systems(Update, update_debug_overlay.after(apply_camera_shake));
+    app.add_systems(Update, update_scale_bars.after(apply_camera_shake));
+    app.add_systems(Update, despawn_orphaned_ui_overlays);
+    app.add_systems(Update, sync_orthographic_scale.after(update_camera_from_input));
+    app.add_systems(Update, layout_quad_view.after(update_camera_from_input));
+    app.add_systems(Update, place_model_proxy.before(track_model_load));
+    app.add_systems(Update, watch_model_reload.before(track_model_load));
+    app.add_systems(Update, track_model_load);
+    app.add_systems(Update, reveal_model.after(track_model_load));
+    app.add_systems(Update, report_asset_progress);
+    app.add_systems(Update, run_prefetch_queue.after(track_model_load));
+    app.add_systems(Update, track_environment_loads);
+    app.add_systems(Update, track_color_lut_loads);
+    app.add_systems(Update, emit_particles.before(update_particles));
+    app.add_systems(Update, update_particles);
+    app.add_systems(Update, ease_morph_weights);
+    app.add_systems(PostUpdate, apply_debug_view_to_new_meshes);
+    app.add_systems(
+        PostUpdate,
+        update_reflection_camera.before(TransformSystems::Propagate),
+    );
+    app.add_systems(PostUpdate, resize_reflection_target);
+    app.add_systems(
+        PostUpdate,
+        face_billboards.before(TransformSystems::Propagate),
+    );
+    app.add_systems(
+        PostUpdate,
+        hold_frozen_frusta
+            .after(VisibilitySystems::UpdateFrusta)
+            .before(VisibilitySystems::CheckVisibility),
+    );
+    app.add_systems(Last, enforce_memory_limit.before(extract_and_process_frame));
     app.add_systems(Last, extract_and_process_frame);
+    app.add_systems(Last, record_rendered_view);
+    app.add_systems(Last, record_stats_history.after(extract_and_process_frame));
+    app.add_systems(Last, detect_slow_frames.after(extract_and_process_frame));
+    app.add_observer(warn_memory_limit_exceeded);
+    app.add_observer(finish_screenshot);
+    app.add_observer(advance_turntable);
+    app.add_observer(finish_readback_check);
+    app.add_observer(finish_gpu_pick);
+    app.add_observer(mark_model_scene_ready);
+    if adaptive::ENABLED {
+        app.add_systems(Last, adapt_frame_rate.after(extract_and_process_frame));
+    }
 
     // Insert resources
-    app.insert_resource(FrameBufferRes(frame_buffer));
-    app.insert_resource(PerfStatsRes(perf_stats));
-    app.insert_resource(MouseInputRes(mouse_input));
+    app.insert_resource(MemoryWatchdog::new(bridge.encoded_frame));
+    app.insert_resource(GpuInfoRes(bridge.gpu_info));
+    app.insert_resource(GpuErrorsRes(bridge.gpu_errors));
+    app.insert_resource(FrameBufferRes(bridge.frame_buffer));
+    app.insert_resource(FrameSignalRes(bridge.frame_signal));
+    app.insert_resource(BufferPoolRes(bridge.buffer_pool));
+    app.insert_resource(PerfStatsRes(bridge.perf_stats));
+    app.insert_resource(HealthRes(bridge.health));
+    app.insert_resource(StatsHistoryRes(bridge.stats_history));
+    app.insert_resource(FetchStatsRes(bridge.fetch_stats));
+    app.insert_resource(SharedSurfaceRes(bridge.shared_surface));
+    app.insert_resource(SceneSyncRes(bridge.scene_sync));
+    app.insert_resource(MouseInputRes(bridge.mouse_input));
+    app.insert_resource(CommandQueueRes(bridge.commands));
+    app.insert_resource(StreamResolution::default());
+    app.insert_resource(StreamPixelFormat::default());
+    app.insert_resource(StreamRegion::default());
     app.insert_resource(OrbitCameraState::default());
+    app.insert_resource(CameraPresets::default());
+    app.insert_resource(CameraTransitionStyle::default());
+    app.insert_resource(CameraSensitivityRes::default());
     app.insert_resource(FrameCount::default());
     app.insert_resource(PreRollFrames(PRE_ROLL_FRAMES));
     app.insert_resource(FrameTimings::default());
-    app.insert_resource(FrameRateLimiter::default());
+    app.insert_resource(FrameStart::default());
+    app.insert_resource(PipelineCounters::default());
+    app.insert_resource(LastFrameHash::default());
+    app.insert_resource(AdaptiveFrameRate::default());
+    app.insert_resource(SceneTime::default());
+    app.insert_resource(FixedTimestep::default());
+    app.insert_resource(ActiveLightingPreset::default());
+    app.insert_resource(ActiveFog::default());
+    app.insert_resource(EditHistory::default());
+    app.insert_resource(RemoteEdits::default());
+    app.insert_resource(Snapping::default());
+    app.insert_resource(ActiveUiOverlay::default());
+    app.insert_resource(EnvironmentLoads::default());
+    app.insert_resource(ColorLutLoads::default());
+    app.insert_resource(PrefetchQueue::default());
+    app.insert_resource(ApprovedModelDirs::default());
+    app.insert_resource(SelectionSets::default());
+    app.insert_resource(Selection::default());
+    app.insert_resource(CustomShaders::default());
+    app.insert_resource(TerrainSeed::default());
+    apply_app_options(&mut app, options);
 
     println!("[Bevy] App configured (headless mode with proper GPU-CPU pipeline)");
     app
 }
 
+/// Insert the resources that differ from the interactive defaults
+fn apply_app_options(app: &mut App, options: AppOptions) {
+    let mut render_settings = RenderSettings::default();
+    if let Some((width, height)) = options.render_size {
+        render_settings.width = width;
+        render_settings.height = height;
+        app.insert_resource(StreamResolution { width, height });
+    }
+    if let Some(msaa) = options.msaa {
+        render_settings.msaa = msaa;
+    }
+    app.insert_resource(render_settings);
+
+    // Hooks in after the downscale pass, added above
+    if !options.texture_shares.is_empty() {
+        app.add_plugins(TextureSharePlugin::new(options.texture_shares));
+    }
+
+    if let Some(fps) = options.fixed_fps.or(simulation::FIXED_FPS) {
+        set_fixed_timestep(app.world_mut(), Some(fps));
+    }
+
+    if let Some(recording) = options.replay {
+        let mut orbit = OrbitCameraState::default();
+        orbit.set_pose(recording.start_orbit);
+        app.insert_resource(orbit);
+        app.insert_resource(SceneTime(recording.start_time_s));
+        println!(
+            "[Bevy] Replaying {} input samples over {:.1}s",
+            recording.samples.len(),
+            recording.end_time_s - recording.start_time_s
+        );
+        app.insert_resource(InputReplay {
+            recording,
+            next_sample: 0,
+        });
+    }
+}
+
+/// Run the app in a loop paced by [`AdaptiveFrameRate`]
+///
+/// Works like `ScheduleRunnerPlugin::run_loop`, except the frame interval is
+/// re-read every frame, so the frame rate can change at runtime, and frames
+/// are timed by a [`FramePacer`] for a steady cadence. This is the only
+/// place the frame rate is enforced.
+fn adaptive_runner(mut app: App) -> AppExit {
+    finish_plugins(&mut app);
+    startup::mark(StartupPhase::RenderDeviceReady);
+
+    let mut pacer = FramePacer::default();
+    loop {
+        app.update();
+
+        if let Some(exit) = app.should_exit() {
+            return exit;
+        }
+
+        let frame_interval = app
+            .world()
+            .get_resource::<AdaptiveFrameRate>()
+            .map(AdaptiveFrameRate::frame_interval)
+            .unwrap_or(Duration::from_secs_f64(1.0 / TARGET_FPS));
+        pacer.wait(frame_interval);
+    }
+}
+
+/// Wait for plugins to become ready, e.g. the render device, and finish
+/// them, as a runner must before the first update
+pub(crate) fn finish_plugins(app: &mut App) {
+    if app.plugins_state() != PluginsState::Cleaned {
+        while app.plugins_state() == PluginsState::Adding {
+            bevy::tasks::tick_global_task_pools_on_main_thread();
+        }
+        app.finish();
+        app.cleanup();
+    }
+}
+
 /// Start Bevy in a background thread
-pub fn start_bevy(
-    buffer: SharedFrameBuffer,
-    perf_stats: SharedPerfStats,
-    mouse_input: SharedMouseInput,
-) {
+pub fn start_bevy(bridge: BridgeState) {
+    start_bevy_with(bridge, AppOptions::default());
+}
+
+/// Start Bevy in a background thread with non-default [`AppOptions`]
+///
+/// The thread runs until `BridgeCommand::Exit` is sent, or forever.
+pub fn start_bevy_with(bridge: BridgeState, options: AppOptions) -> JoinHandle<()> {
     thread::spawn(move || {
+        let _exit_guard = ThreadExitGuard(bridge.health.clone());
         println!("[Bevy] Thread started");
-        let mut app = create_app(buffer, perf_stats, mouse_input);
+        startup::mark(StartupPhase::BevyThreadStarted);
+        configure_bevy_thread();
+        let mut app = create_app_with(bridge, options);
+        startup::mark(StartupPhase::AppCreated);
         println!("[Bevy] Running render loop...");
         app.run();
-    });
+    })
+}
+
+/// Records in the renderer health how the Bevy thread ended
+struct ThreadExitGuard(SharedRendererHealth);
+
+impl Drop for ThreadExitGuard {
+    fn drop(&mut self) {
+        let state = if thread::panicking() {
+            BevyThreadState::Panicked
+        } else {
+            BevyThreadState::Exited
+        };
+        eprintln!("[Bevy] Thread ended: {:?}", state);
+        self.0.set_thread_state(state);
+    }
+}
+
+/// Apply the configured priority and core affinity to the Bevy main thread
+fn configure_bevy_thread() {
+    if let Err(e) = platform::set_current_thread_priority(threading::BEVY_THREAD_PRIORITY) {
+        eprintln!("[Bevy] Failed to set thread priority: {}", e);
+    }
+    if let Some(core) = threading::BEVY_THREAD_CORE {
+        match platform::pin_current_thread(core) {
+            Ok(()) => println!("[Bevy] Main thread pinned to core {}", core),
+            Err(e) => eprintln!("[Bevy] Failed to pin main thread: {}", e),
+        }
+    }
+}
+
+/// Size Bevy's task pools so some cores stay free for the webview
+fn task_pool_plugin() -> TaskPoolPlugin {
+    let mut options = TaskPoolOptions::default();
+    options.max_total_threads = platform::logical_cores()
+        .saturating_sub(threading::RESERVED_CORES)
+        .max(1);
+
+    if threading::TASK_POOL_PRIORITY != platform::ThreadPriority::Normal {
+        let set_priority: Arc<dyn Fn() + Send + Sync> = Arc::new(|| {
+            if let Err(e) = platform::set_current_thread_priority(threading::TASK_POOL_PRIORITY) {
+                eprintln!("[Bevy] Failed to set task pool thread priority: {}", e);
+            }
+        });
+        for policy in [&mut options.io, &mut options.async_compute, &mut options.compute] {
+            policy.on_thread_spawn = Some(set_priority.clone());
+        }
+    }
+
+    println!(
+        "[Bevy] Task pools limited to {} threads",
+        options.max_total_threads
+    );
+    TaskPoolPlugin {
+        task_pool_options: options,
+    }
 }
@@ -3,7 +3,16 @@
 //! This module contains all component markers and data structures used
 //! to tag and identify entities in the Bevy ECS (Entity Component System).
 
+use bevy::camera::primitives::Frustum;
 use bevy::prelude::*;
+use bevy::render::render_resource::TextureFormat;
+use crossbeam_channel::Sender;
+use std::collections::{BTreeSet, VecDeque};
+use std::path::PathBuf;
+
+use crate::bevy::resources::OrbitCameraState;
+use crate::config::readback::MAX_PENDING_VIEWS;
+use crate::tauri_bridge::shared_state::{CapturedImage, FrameMetadata, StandardView};
 
 /// Marker component for the offscreen rendering camera
 ///
@@ -12,6 +21,50 @@ use bevy::prelude::*;
 #[derive(Component)]
 pub struct OffscreenCamera;
 
+/// Views rendered by the stream copier whose readbacks are still in flight
+///
+/// Views are numbered, and each copy carries the number of the view its
+/// frame was rendered from. Views of frames that were never copied, e.g.
+/// because every staging buffer was in flight, are dropped once a later
+/// copy arrives.
+#[derive(Component, Default)]
+pub struct PendingViews {
+    views: VecDeque<(u64, FrameMetadata)>,
+    /// Number of the next view recorded
+    next: u64,
+}
+
+impl PendingViews {
+    /// Record the view of the frame about to be rendered
+    pub fn push(&mut self, metadata: FrameMetadata) {
+        // Copies that never arrive must not pile up views
+        if self.views.len() >= MAX_PENDING_VIEWS {
+            self.views.pop_front();
+        }
+        self.views.push_back((self.next, metadata));
+        self.next += 1;
+    }
+
+    /// Number of the last view recorded
+    pub fn latest(&self) -> Option<u64> {
+        self.next.checked_sub(1)
+    }
+
+    /// Take view `number`, dropping the views before it
+    pub fn take(&mut self, number: u64) -> Option<FrameMetadata> {
+        while let Some(&(next, metadata)) = self.views.front() {
+            if next > number {
+                return None;
+            }
+            self.views.pop_front();
+            if next == number {
+                return Some(metadata);
+            }
+        }
+        None
+    }
+}
+
 /// Marker component for cameras that can be controlled by user input
 ///
 /// Entities with this component will respond to mouse input for
@@ -19,9 +72,208 @@ pub struct OffscreenCamera;
 #[derive(Component)]
 pub struct CameraController;
 
+/// Frustum a camera culls with while culling is frozen, put back each frame
+/// after Bevy updates the camera's own
+#[derive(Component)]
+pub struct FrozenFrustum(pub Frustum);
+
+/// Orthographic camera drawing one quadrant of the quad view
+///
+/// Looks at the orbit center from a fixed standard view, at the orbit
+/// distance, into the stream target.
+#[derive(Component)]
+pub struct QuadViewCamera(pub StandardView);
+
+/// Text node of the debug overlay drawn into the stream camera's target
+#[derive(Component)]
+pub struct DebugOverlayText;
+
+/// Root node of one UI overlay element, drawn into `camera`'s target
+///
+/// Each capture camera gets its own copy of the elements, which is
+/// despawned with the camera.
+#[derive(Component)]
+pub struct UiOverlayRoot {
+    pub camera: Entity,
+}
+
+/// Bar of a UI overlay scale bar, `length` world units long at the orbit
+/// center's distance from `camera`
+#[derive(Component)]
+pub struct ScaleBar {
+    pub length: f32,
+    pub camera: Entity,
+}
+
+/// Standard material of a mesh drawn with the debug view material, put
+/// back when the debug view is turned off
+#[derive(Component)]
+pub struct DebugViewOriginal(pub Handle<StandardMaterial>);
+
+/// Standard material of a mesh drawn with a custom shader, put back by
+/// `set_custom_shader_material` with no shader
+#[derive(Component)]
+pub struct CustomShaderOriginal(pub Handle<StandardMaterial>);
+
+/// Standard material of a mesh drawn with a `set_submesh_material`
+/// override, put back when the override is removed
+#[derive(Component)]
+pub struct MaterialOverrideOriginal(pub Handle<StandardMaterial>);
+
+/// Tags set with `set_tags`, for addressing groups of entities
+#[derive(Component, Default)]
+pub struct Tags(pub BTreeSet<String>);
+
+/// Instance and entity id an entity was spawned with by a synced edit,
+/// which other instances address it by
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub struct SyncKey {
+    pub instance: u64,
+    pub entity: u64,
+}
+
 /// Marker component for rotating cube objects
 ///
 /// Entities with this component will be automatically rotated
 /// by the animation system.
 #[derive(Component)]
 pub struct RotatingCube;
+
+/// Particle of the emitter started by `set_particle_emitter`
+#[derive(Component)]
+pub struct Particle {
+    pub velocity: Vec3,
+    /// Seconds since it was emitted
+    pub age: f32,
+}
+
+/// Weights set with `set_morph_weight` that the entity's `MorphWeights`
+/// are easing towards, `None` for weights left alone; removed once all
+/// are reached
+#[derive(Component)]
+pub struct MorphWeightTargets(pub Vec<Option<f32>>);
+
+/// Marker added with `add_billboard`, turned to face the stream camera
+/// every frame
+#[derive(Component)]
+pub struct Billboard {
+    /// Size in logical pixels to keep on screen, `None` to keep the size in
+    /// world units
+    pub screen_size: Option<f32>,
+}
+
+/// Root of the terrain scene, its chunks are children, despawned when
+/// another scene or a model replaces it
+#[derive(Component)]
+pub struct Terrain;
+
+/// Light of the rig spawned by `set_lighting_preset`, despawned when
+/// another preset replaces it
+#[derive(Component)]
+pub struct LightRig;
+
+/// Light probe added by `set_reflection_probes`, despawned when they are
+/// set again
+#[derive(Component)]
+pub struct ReflectionProbe;
+
+/// Shadow catcher ground added by `set_ground_plane`
+#[derive(Component)]
+pub struct GroundPlane;
+
+/// Water plane added by `set_water`
+#[derive(Component)]
+pub struct WaterPlane;
+
+/// Camera rendering the scene mirrored in the water plane at `height`,
+/// for the water to show as its reflection
+#[derive(Component)]
+pub struct ReflectionCamera {
+    pub height: f32,
+}
+
+/// Root of a model opened with `load_model` from this file, despawned when
+/// another one is opened
+#[derive(Component)]
+pub struct LoadedModel(pub PathBuf);
+
+/// Translucent box spanning a glTF model while it loads, see
+/// `model_loading`
+#[derive(Component)]
+pub struct ModelProxy;
+
+/// Pending single-image render: a screenshot or a batch render job
+///
+/// Sits on an entity with a `Readback` of the image's own target, next
+/// to a temporary camera rendering into it. Both are despawned once the
+/// image has been sent.
+#[derive(Component)]
+pub struct ScreenshotCapture {
+    /// Camera rendering the screenshot
+    pub camera: Entity,
+    pub width: u32,
+    pub height: u32,
+    /// Readbacks still to skip before the target holds a finished frame
+    pub warmup_frames: u32,
+    pub reply: Sender<Result<CapturedImage, String>>,
+}
+
+/// Readback of the pixel under the cursor for a GPU pick
+///
+/// Sits on an entity with a 1×1 `ImageCopier`, whose readbacks are decoded
+/// by `finish_gpu_pick` instead of being streamed.
+#[derive(Component)]
+pub struct PickReadback {
+    /// Readbacks still to skip before the ID buffer is drawn
+    pub warmup_frames: u32,
+    /// Readbacks still to wait for the copies' pipelines if the pixel
+    /// shows none of them
+    pub wait_frames: u32,
+}
+
+/// Copy of a mesh drawn only into the ID buffer of a GPU pick, as a child
+/// of the mesh it stands for
+#[derive(Component)]
+pub struct PickProxy;
+
+/// Readback of one readback self-test pattern
+///
+/// Sits on an entity with an `ImageCopier`, whose readbacks are checked
+/// against `expected` instead of being streamed.
+#[derive(Component)]
+pub struct ReadbackCheck {
+    /// Position of the check in the report
+    pub index: usize,
+    pub name: &'static str,
+    /// Texture format of the pattern
+    pub source_format: TextureFormat,
+    /// Pattern pixels as RGBA, in the copier's region
+    pub expected: Vec<[u8; 4]>,
+    /// Difference allowed per channel
+    pub tolerance: u8,
+    /// Readbacks still to skip before the pattern is uploaded or rendered
+    pub warmup_frames: u32,
+}
+
+/// Marks the readback of a turntable export's capture camera
+///
+/// The camera steps around the orbit each time a frame was sent. Both are
+/// despawned after the last frame.
+#[derive(Component)]
+pub struct TurntableCapture {
+    /// Camera rendering the turntable
+    pub camera: Entity,
+    pub width: u32,
+    pub height: u32,
+    /// Orbit at the start, the camera turns around its center
+    pub orbit: OrbitCameraState,
+    /// Scene time of the first frame
+    pub start_time_s: f64,
+    pub fps: f64,
+    /// Index of the frame being rendered
+    pub frame: u32,
+    pub frames: u32,
+    /// Readbacks still to skip before the target shows the current step
+    pub settle_frames: u32,
+    pub frames_out: Sender<Result<CapturedImage, String>>,
+}
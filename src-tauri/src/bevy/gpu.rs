@@ -0,0 +1,220 @@
+//! GPU backend and adapter selection
+//!
+//! Bevy picks the wgpu backend and adapter on its own, which on hybrid
+//! laptops or machines with broken drivers is not always the right one.
+//! The choice can be made in `config::gpu` or overridden on the command
+//! line:
+//!
+//! - `--gpu-backend vulkan|dx12|metal|gl`
+//! - `--gpu-power low|high` (integrated vs discrete adapter)
+//!
+//! Without either, Bevy's defaults apply, including the `WGPU_BACKEND` and
+//! `WGPU_POWER_PREF` environment variables.
+
+use bevy::{
+    prelude::*,
+    render::{
+        renderer::{RenderAdapter, RenderAdapterInfo, RenderDevice},
+        settings::{Backends, PowerPreference, WgpuSettings},
+        RenderPlugin,
+    },
+};
+use wgpu::{TextureFormat, TextureFormatFeatureFlags, TextureUsages};
+
+use crate::bevy::resources::GpuInfoRes;
+use crate::config::gpu::{BACKEND, POWER_PREFERENCE};
+use crate::tauri_bridge::shared_state::{GpuInfo, GpuLimits, TextureFormatSupport};
+
+/// Graphics API used by wgpu
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GpuBackend {
+    Vulkan,
+    Dx12,
+    Metal,
+    Gl,
+}
+
+impl GpuBackend {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "vulkan" => Some(Self::Vulkan),
+            "dx12" => Some(Self::Dx12),
+            "metal" => Some(Self::Metal),
+            "gl" | "opengl" => Some(Self::Gl),
+            _ => None,
+        }
+    }
+
+    fn backends(self) -> Backends {
+        match self {
+            Self::Vulkan => Backends::VULKAN,
+            Self::Dx12 => Backends::DX12,
+            Self::Metal => Backends::METAL,
+            Self::Gl => Backends::GL,
+        }
+    }
+}
+
+/// Which adapter to prefer when several are available
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GpuPower {
+    /// Usually the integrated GPU
+    Low,
+    /// Usually the discrete GPU
+    High,
+}
+
+impl GpuPower {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "low" | "integrated" => Some(Self::Low),
+            "high" | "discrete" => Some(Self::High),
+            _ => None,
+        }
+    }
+}
+
+/// Backend and adapter preference used to create the render device
+#[derive(Clone, Copy, Debug)]
+pub struct GpuSelection {
+    pub backend: Option<GpuBackend>,
+    pub power: Option<GpuPower>,
+}
+
+impl GpuSelection {
+    /// Selection from `config::gpu`, overridden by command line arguments
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut selection = Self {
+            backend: BACKEND,
+            power: POWER_PREFERENCE,
+        };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            match flag.as_str() {
+                "--gpu-backend" => {
+                    let value = value.or_else(|| args.next()).unwrap_or_default();
+                    match GpuBackend::parse(&value) {
+                        Some(backend) => selection.backend = Some(backend),
+                        None => eprintln!("[Bevy] Unknown GPU backend '{}', ignoring", value),
+                    }
+                }
+                "--gpu-power" => {
+                    let value = value.or_else(|| args.next()).unwrap_or_default();
+                    match GpuPower::parse(&value) {
+                        Some(power) => selection.power = Some(power),
+                        None => {
+                            eprintln!("[Bevy] Unknown GPU power preference '{}', ignoring", value)
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        selection
+    }
+
+    /// Render plugin creating the device according to this selection
+    pub fn render_plugin(&self) -> RenderPlugin {
+        let mut settings = WgpuSettings::default();
+        if let Some(backend) = self.backend {
+            settings.backends = Some(backend.backends());
+        }
+        if let Some(power) = self.power {
+            settings.power_preference = match power {
+                GpuPower::Low => PowerPreference::LowPower,
+                GpuPower::High => PowerPreference::HighPerformance,
+            };
+        }
+
+        RenderPlugin {
+            render_creation: settings.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Texture formats reported by `get_gpu_info`: the stream and readback
+/// formats, HDR targets and depth
+const REPORTED_FORMATS: [TextureFormat; 10] = [
+    TextureFormat::Rgba8Unorm,
+    TextureFormat::Rgba8UnormSrgb,
+    TextureFormat::Bgra8Unorm,
+    TextureFormat::Bgra8UnormSrgb,
+    TextureFormat::Rgb10a2Unorm,
+    TextureFormat::Rg11b10Ufloat,
+    TextureFormat::Rgba16Float,
+    TextureFormat::Rgba32Float,
+    TextureFormat::R32Uint,
+    TextureFormat::Depth32Float,
+];
+
+/// Share the adapter Bevy ended up on with the `get_gpu_info` command
+pub fn publish_gpu_info(
+    adapter_info: Option<Res<RenderAdapterInfo>>,
+    adapter: Option<Res<RenderAdapter>>,
+    device: Option<Res<RenderDevice>>,
+    gpu_info: Res<GpuInfoRes>,
+) {
+    let (Some(adapter_info), Some(adapter), Some(device)) = (adapter_info, adapter, device) else {
+        return;
+    };
+
+    let limits = device.limits();
+    let texture_formats = REPORTED_FORMATS
+        .into_iter()
+        .map(|format| {
+            let features = adapter.get_texture_format_features(format);
+            TextureFormatSupport {
+                format: format!("{:?}", format),
+                render_attachment: features
+                    .allowed_usages
+                    .contains(TextureUsages::RENDER_ATTACHMENT),
+                storage: features
+                    .allowed_usages
+                    .contains(TextureUsages::STORAGE_BINDING),
+                filterable: features
+                    .flags
+                    .contains(TextureFormatFeatureFlags::FILTERABLE),
+                sample_counts: features.flags.supported_sample_counts(),
+            }
+        })
+        .collect();
+
+    let info = GpuInfo {
+        name: adapter_info.name.clone(),
+        backend: format!("{:?}", adapter_info.backend),
+        device_type: format!("{:?}", adapter_info.device_type),
+        driver: adapter_info.driver.clone(),
+        driver_info: adapter_info.driver_info.clone(),
+        vendor_id: adapter_info.vendor,
+        device_id: adapter_info.device,
+        limits: GpuLimits {
+            max_texture_dimension_2d: limits.max_texture_dimension_2d,
+            max_buffer_size: limits.max_buffer_size,
+            max_storage_buffer_binding_size: limits.max_storage_buffer_binding_size,
+            max_uniform_buffer_binding_size: limits.max_uniform_buffer_binding_size,
+            max_bind_groups: limits.max_bind_groups,
+            max_compute_workgroup_size_x: limits.max_compute_workgroup_size_x,
+            max_compute_invocations_per_workgroup: limits.max_compute_invocations_per_workgroup,
+        },
+        features: device
+            .features()
+            .iter_names()
+            .map(|(name, _)| name.to_string())
+            .collect(),
+        texture_formats,
+    };
+    println!(
+        "[Bevy] Using GPU: {} ({}, {})",
+        info.name, info.backend, info.device_type
+    );
+    if let Ok(mut slot) = gpu_info.0 .0.lock() {
+        *slot = Some(info);
+    }
+}
@@ -0,0 +1,65 @@
+//! wgpu error capture
+//!
+//! wgpu panics on errors nobody handles, which takes the whole render
+//! thread down for what is often a recoverable problem, such as one failed
+//! allocation after a resolution change. Device calls we make ourselves run
+//! inside error scopes, everything else goes to an uncaptured error handler,
+//! and both report to the shared [`SharedGpuErrors`] channel read by
+//! `get_gpu_errors` and `get_health`.
+//!
+//! Errors in recorded commands only surface when Bevy submits them, outside
+//! any scope, so they arrive through the uncaptured handler.
+
+use bevy::prelude::*;
+use bevy::render::renderer::RenderDevice;
+use bevy::tasks::block_on;
+use wgpu::ErrorFilter;
+
+use crate::bevy::resources::GpuErrorsRes;
+use crate::tauri_bridge::shared_state::{GpuErrorKind, SharedGpuErrors};
+
+/// Run `f` inside validation and out-of-memory error scopes, reporting
+/// what they caught as coming from `source`
+pub fn with_error_scopes<T>(
+    render_device: &RenderDevice,
+    errors: &SharedGpuErrors,
+    source: &str,
+    f: impl FnOnce() -> T,
+) -> T {
+    let device = render_device.wgpu_device();
+    device.push_error_scope(ErrorFilter::OutOfMemory);
+    device.push_error_scope(ErrorFilter::Validation);
+    let result = f();
+    // Scopes pop innermost first; native backends resolve them immediately
+    for scope in [device.pop_error_scope(), device.pop_error_scope()] {
+        if let Some(error) = block_on(scope) {
+            report(errors, source, error);
+        }
+    }
+    result
+}
+
+/// Report errors outside our error scopes instead of panicking
+pub fn report_uncaptured_errors(
+    render_device: Option<Res<RenderDevice>>,
+    errors: Res<GpuErrorsRes>,
+) {
+    let Some(render_device) = render_device else {
+        return;
+    };
+    let errors = errors.0.clone();
+    render_device
+        .wgpu_device()
+        .on_uncaptured_error(Box::new(move |error| {
+            report(&errors, "uncaptured", error);
+        }));
+}
+
+fn report(errors: &SharedGpuErrors, source: &str, error: wgpu::Error) {
+    let kind = match &error {
+        wgpu::Error::OutOfMemory { .. } => GpuErrorKind::OutOfMemory,
+        wgpu::Error::Validation { .. } => GpuErrorKind::Validation,
+        wgpu::Error::Internal { .. } => GpuErrorKind::Internal,
+    };
+    errors.report(kind, source, error.to_string());
+}
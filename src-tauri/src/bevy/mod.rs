@@ -7,7 +7,10 @@ pub mod components;
 pub mod resources;
 pub mod plugins;
 pub mod systems;
+pub mod pacing;
+pub mod gpu;
+pub mod gpu_errors;
 pub mod app;
 
 // Re-export commonly used items
-pub use app::start_bevy;
+pub use app::{start_bevy, start_bevy_with, AppOptions};
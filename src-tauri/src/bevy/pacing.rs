@@ -0,0 +1,53 @@
+//! Frame pacing for the app runner
+//!
+//! `thread::sleep` alone overshoots by up to a scheduler tick, and sleeping
+//! for "interval minus update time" lets those errors add up. The pacer
+//! instead keeps an absolute deadline per frame: it sleeps until shortly
+//! before the deadline, spins for the rest, and schedules the next deadline
+//! one interval after the previous one rather than after the wake-up time.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::pacing::{MAX_LAG_FRAMES, SPIN_THRESHOLD};
+
+/// Drift-compensated frame deadline tracker
+pub struct FramePacer {
+    next_deadline: Instant,
+}
+
+impl Default for FramePacer {
+    fn default() -> Self {
+        Self {
+            next_deadline: Instant::now(),
+        }
+    }
+}
+
+impl FramePacer {
+    /// Block until the next frame is due, `interval` after the previous one
+    ///
+    /// When the app falls more than `MAX_LAG_FRAMES` behind (e.g. after a
+    /// stall), the schedule restarts from now instead of rushing through
+    /// the missed frames.
+    pub fn wait(&mut self, interval: Duration) {
+        self.next_deadline += interval;
+
+        let now = Instant::now();
+        if now > self.next_deadline + interval * MAX_LAG_FRAMES {
+            self.next_deadline = now;
+            return;
+        }
+
+        if let Some(sleep) = self
+            .next_deadline
+            .checked_duration_since(now)
+            .and_then(|remaining| remaining.checked_sub(SPIN_THRESHOLD))
+        {
+            thread::sleep(sleep);
+        }
+        while Instant::now() < self.next_deadline {
+            std::hint::spin_loop();
+        }
+    }
+}
@@ -0,0 +1,399 @@
+//! Color grading with 3D LUTs
+//!
+//! Grading tools export looks as `.cube` files: a table mapping every
+//! input color to a graded one. The loader here turns one into a 3D
+//! texture, and a pass right after tonemapping looks up every pixel of a
+//! camera with a [`ColorLut`] in it, so the streamed image, screenshots
+//! and exports all get the look. Bevy's own `ColorGrading` only has
+//! exposure, saturation and the like, which can't express a film look.
+
+use bevy::{
+    app::{App, Plugin},
+    asset::{io::Reader, AssetLoader, LoadContext, RenderAssetUsages},
+    core_pipeline::core_3d::graph::{Core3d, Node3d},
+    ecs::query::QueryItem,
+    prelude::*,
+    render::{
+        extract_component::{ExtractComponent, ExtractComponentPlugin},
+        render_asset::RenderAssets,
+        render_graph::{
+            NodeRunError, RenderGraphContext, RenderGraphExt, RenderLabel, ViewNode, ViewNodeRunner,
+        },
+        renderer::{RenderContext, RenderDevice},
+        texture::GpuImage,
+        view::ViewTarget,
+        RenderApp,
+    },
+};
+use std::io;
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, ColorTargetState, ColorWrites, Extent3d,
+    FilterMode, FragmentState, LoadOp, MultisampleState, Operations, PipelineLayoutDescriptor,
+    PrimitiveState, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages, StoreOp, TextureDimension, TextureFormat,
+    TextureSampleType, TextureViewDimension, VertexState,
+};
+
+use crate::bevy::gpu_errors::with_error_scopes;
+use crate::bevy::resources::GpuErrorsRes;
+use crate::config::color_lut::MAX_SIZE;
+use crate::tauri_bridge::shared_state::SharedGpuErrors;
+
+// =============================================================================
+// Plugin Definition
+// =============================================================================
+
+pub struct ColorLutPlugin {
+    /// Channel errors of the LUT pass are reported to
+    pub gpu_errors: SharedGpuErrors,
+}
+
+impl Plugin for ColorLutPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset_loader::<CubeLutLoader>()
+            .add_plugins(ExtractComponentPlugin::<ColorLut>::default());
+
+        // Tonemapping -> LUT -> the rest of post-processing
+        app.sub_app_mut(RenderApp)
+            .insert_resource(GpuErrorsRes(self.gpu_errors.clone()))
+            .add_render_graph_node::<ViewNodeRunner<ColorLutNode>>(Core3d, ColorLutLabel)
+            .add_render_graph_edges(
+                Core3d,
+                (
+                    Node3d::Tonemapping,
+                    ColorLutLabel,
+                    Node3d::EndMainPassPostProcessing,
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+        let render_device = render_app.world().resource::<RenderDevice>();
+        let pipeline = with_error_scopes(
+            render_device,
+            &self.gpu_errors,
+            "color_lut_pipeline",
+            || ColorLutPipeline::new(render_device),
+        );
+        render_app.insert_resource(pipeline);
+    }
+}
+
+/// Grades a camera's image through a 3D LUT loaded from a `.cube` file
+#[derive(Component, Clone, ExtractComponent)]
+pub struct ColorLut(pub Handle<Image>);
+
+// =============================================================================
+// .cube Loader
+// =============================================================================
+
+/// Loads a `.cube` 3D LUT as a 3D texture, red varying fastest
+#[derive(Default)]
+pub struct CubeLutLoader;
+
+impl AssetLoader for CubeLutLoader {
+    type Asset = Image;
+    type Settings = ();
+    type Error = io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Image, io::Error> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text).await?;
+        let (size, entries) =
+            parse_cube(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        // 10 bits per channel is finer than the 8-bit stream, and filterable
+        // everywhere unlike 32-bit floats
+        let data: Vec<u8> = entries
+            .iter()
+            .flat_map(|&rgb| rgb10a2(rgb).to_le_bytes())
+            .collect();
+        Ok(Image::new(
+            Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: size,
+            },
+            TextureDimension::D3,
+            data,
+            TextureFormat::Rgb10a2Unorm,
+            RenderAssetUsages::RENDER_WORLD,
+        ))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["cube"]
+    }
+}
+
+/// Table size and entries of a `.cube` 3D LUT
+fn parse_cube(text: &str) -> Result<(u32, Vec<[f32; 3]>), String> {
+    let mut size = None;
+    let mut entries = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let keyword = words.next().unwrap_or_default();
+        let values: Vec<f32> = words.filter_map(|word| word.parse().ok()).collect();
+        match keyword {
+            "LUT_3D_SIZE" => match values.as_slice() {
+                &[n] if (2.0..=MAX_SIZE as f32).contains(&n) && n.fract() == 0.0 => {
+                    size = Some(n as u32)
+                }
+                _ => return Err(format!("Unsupported LUT_3D_SIZE on line {}", number + 1)),
+            },
+            "LUT_1D_SIZE" => return Err("1D LUTs are not supported".to_string()),
+            "DOMAIN_MIN" if values != [0.0; 3] => {
+                return Err("Only LUTs with a DOMAIN_MIN of 0 are supported".to_string())
+            }
+            "DOMAIN_MAX" if values != [1.0; 3] => {
+                return Err("Only LUTs with a DOMAIN_MAX of 1 are supported".to_string())
+            }
+            // TITLE and other keywords don't change the table
+            _ if keyword.starts_with(|c: char| c.is_ascii_alphabetic()) => {}
+            _ => {
+                let rgb: Vec<f32> = line
+                    .split_whitespace()
+                    .map(|word| word.parse::<f32>())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| format!("Invalid entry on line {}", number + 1))?;
+                match rgb.as_slice() {
+                    &[r, g, b] => entries.push([r, g, b]),
+                    _ => return Err(format!("Invalid entry on line {}", number + 1)),
+                }
+            }
+        }
+    }
+
+    let size = size.ok_or("Missing LUT_3D_SIZE")?;
+    let expected = (size * size * size) as usize;
+    if entries.len() != expected {
+        return Err(format!(
+            "Expected {} entries for a {}³ LUT, found {}",
+            expected,
+            size,
+            entries.len()
+        ));
+    }
+    Ok((size, entries))
+}
+
+/// Pack `rgb` as 10-bit unsigned normalized channels with an opaque alpha
+fn rgb10a2([r, g, b]: [f32; 3]) -> u32 {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 1023.0).round() as u32;
+    channel(r) | channel(g) << 10 | channel(b) << 20 | 3 << 30
+}
+
+// =============================================================================
+// Pipeline (Render World)
+// =============================================================================
+
+/// Fullscreen-triangle pipelines for the view target formats, sampling
+/// the image and the LUT with a linear filter
+#[derive(Resource)]
+struct ColorLutPipeline {
+    pipelines: Vec<(TextureFormat, RenderPipeline)>,
+    bind_group_layout: BindGroupLayout,
+    sampler: Sampler,
+}
+
+impl ColorLutPipeline {
+    fn new(render_device: &RenderDevice) -> Self {
+        let device = render_device.wgpu_device();
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("color_lut_shader"),
+            source: ShaderSource::Wgsl(include_str!("../shaders/color_lut.wgsl").into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("color_lut_bind_group_layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D3,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("color_lut_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // The stream camera renders to the default format, HDR cameras to
+        // the HDR one
+        let pipelines = [
+            TextureFormat::bevy_default(),
+            ViewTarget::TEXTURE_FORMAT_HDR,
+        ]
+        .into_iter()
+        .map(|format| {
+            let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("color_lut_pipeline"),
+                layout: Some(&layout),
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_main"),
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                fragment: Some(FragmentState {
+                    module: &shader,
+                    entry_point: Some("fs_main"),
+                    compilation_options: Default::default(),
+                    targets: &[Some(ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: ColorWrites::ALL,
+                    })],
+                }),
+                primitive: PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            });
+            (format, pipeline)
+        })
+        .collect();
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("color_lut_sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            pipelines,
+            bind_group_layout,
+            sampler,
+        }
+    }
+}
+
+// =============================================================================
+// Render Graph Node
+// =============================================================================
+
+/// Render graph label of the LUT pass
+#[derive(Debug, PartialEq, Eq, Clone, Hash, RenderLabel)]
+pub struct ColorLutLabel;
+
+#[derive(Default)]
+struct ColorLutNode;
+
+impl ViewNode for ColorLutNode {
+    type ViewQuery = (&'static ViewTarget, &'static ColorLut);
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (view_target, lut): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let (Some(pipeline), Some(gpu_errors)) = (
+            world.get_resource::<ColorLutPipeline>(),
+            world.get_resource::<GpuErrorsRes>(),
+        ) else {
+            return Ok(());
+        };
+        let format = view_target.main_texture_format();
+        let Some((_, render_pipeline)) = pipeline.pipelines.iter().find(|(f, _)| *f == format)
+        else {
+            return Ok(());
+        };
+        // The LUT shows up once it is loaded and uploaded
+        let Some(lut) = world.resource::<RenderAssets<GpuImage>>().get(&lut.0) else {
+            return Ok(());
+        };
+        let render_device = render_context.render_device().clone();
+
+        let _span = tracing::info_span!("record_color_lut").entered();
+        // Reads the current main texture and makes the one written here
+        // the main texture for the passes after
+        let post_process = view_target.post_process_write();
+        let bind_group = with_error_scopes(&render_device, &gpu_errors.0, "color_lut", || {
+            render_device
+                .wgpu_device()
+                .create_bind_group(&BindGroupDescriptor {
+                    label: Some("color_lut_bind_group"),
+                    layout: &pipeline.bind_group_layout,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: BindingResource::TextureView(&**post_process.source),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: BindingResource::Sampler(&pipeline.sampler),
+                        },
+                        BindGroupEntry {
+                            binding: 2,
+                            resource: BindingResource::TextureView(&*lut.texture_view),
+                        },
+                    ],
+                })
+        });
+
+        let mut render_pass =
+            render_context
+                .command_encoder()
+                .begin_render_pass(&RenderPassDescriptor {
+                    label: Some("color_lut_pass"),
+                    color_attachments: &[Some(RenderPassColorAttachment {
+                        view: &**post_process.destination,
+                        depth_slice: None,
+                        resolve_target: None,
+                        ops: Operations {
+                            load: LoadOp::Clear(wgpu::Color::BLACK),
+                            store: StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+        render_pass.set_pipeline(render_pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
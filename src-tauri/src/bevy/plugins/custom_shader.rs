@@ -0,0 +1,162 @@
+//! Custom WGSL shader materials
+//!
+//! The frontend registers fragment shaders by name as WGSL source, written
+//! like any Bevy material shader (see `custom_shader.wgsl`), and draws
+//! meshes with them through [`CustomShaderMaterial`]. Bevy compiles
+//! material shaders when a pipeline first needs them, so a shader that
+//! doesn't compile only fails once a mesh uses it; those errors are read
+//! from the pipeline cache and reported to the GPU error channel, where
+//! `get_gpu_errors` shows them.
+
+use bevy::{
+    app::{App, Plugin},
+    asset::{uuid::Uuid, uuid_handle},
+    mesh::MeshVertexBufferLayoutRef,
+    pbr::{Material, MaterialPipeline, MaterialPipelineKey, MaterialPlugin},
+    prelude::*,
+    render::{
+        render_resource::{
+            AsBindGroup, CachedPipelineState, PipelineCache, PipelineDescriptor,
+            RenderPipelineDescriptor, SpecializedMeshPipelineError,
+        },
+        Extract, ExtractSchedule, RenderApp,
+    },
+    shader::{PipelineCacheError, Shader, ShaderRef},
+};
+use std::collections::HashSet;
+use std::marker::PhantomData;
+
+use crate::bevy::resources::{CustomShaders, GpuErrorsRes};
+use crate::tauri_bridge::shared_state::{GpuErrorKind, SharedGpuErrors};
+
+/// The template shader, swapped for the registered one when specializing
+const TEMPLATE_SHADER_HANDLE: Handle<Shader> = uuid_handle!("3c8a5e17-2f6d-4b90-a1e4-7d2c9f05b386");
+
+/// Registered shader `n` is stored under this UUID plus `n`
+const SHADER_UUID_BASE: u128 = 0x9e41_d0b7_5a2c_4f18_b3e6_0000_0000_0000;
+
+pub struct CustomShaderPlugin {
+    /// Channel shader compilation errors are reported to
+    pub gpu_errors: SharedGpuErrors,
+}
+
+impl Plugin for CustomShaderPlugin {
+    fn build(&self, app: &mut App) {
+        let shader = Shader::from_wgsl(
+            include_str!("../shaders/custom_shader.wgsl"),
+            "bevy/shaders/custom_shader.wgsl",
+        );
+        let _ = app
+            .world_mut()
+            .resource_mut::<Assets<Shader>>()
+            .insert(TEMPLATE_SHADER_HANDLE.id(), shader);
+        app.add_plugins(MaterialPlugin::<CustomShaderMaterial>::default());
+
+        app.sub_app_mut(RenderApp)
+            .insert_resource(GpuErrorsRes(self.gpu_errors.clone()))
+            .add_systems(ExtractSchedule, report_shader_errors);
+    }
+}
+
+/// Handle registered shader `index` is stored under
+pub fn shader_handle(index: u32) -> Handle<Shader> {
+    Handle::Uuid(
+        Uuid::from_u128(SHADER_UUID_BASE + index as u128),
+        PhantomData,
+    )
+}
+
+/// Material drawn with a registered shader, which gets `color` and
+/// `params` as uniforms 0 and 1 of the material bind group
+#[derive(Asset, TypePath, AsBindGroup, Clone, Debug)]
+#[bind_group_data(CustomShaderKey)]
+pub struct CustomShaderMaterial {
+    #[uniform(0)]
+    pub color: LinearRgba,
+    /// Free for the shader to use
+    #[uniform(1)]
+    pub params: Vec4,
+    /// Index of the registered shader, see [`shader_handle`]
+    pub shader: u32,
+    pub alpha_mode: AlphaMode,
+}
+
+/// Pipelines are specialized per registered shader
+#[repr(C)]
+#[derive(Eq, PartialEq, Hash, Copy, Clone)]
+pub struct CustomShaderKey {
+    shader: u32,
+}
+
+impl From<&CustomShaderMaterial> for CustomShaderKey {
+    fn from(material: &CustomShaderMaterial) -> Self {
+        Self {
+            shader: material.shader,
+        }
+    }
+}
+
+impl Material for CustomShaderMaterial {
+    fn fragment_shader() -> ShaderRef {
+        TEMPLATE_SHADER_HANDLE.into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayoutRef,
+        key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        // Prepass and shadow pipelines keep Bevy's own fragment shader
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            if fragment.shader == TEMPLATE_SHADER_HANDLE {
+                fragment.shader = shader_handle(key.bind_group_data.shader);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Report pipelines that failed to compile a registered shader, each
+/// error once
+fn report_shader_errors(
+    shaders: Extract<Res<CustomShaders>>,
+    pipeline_cache: Res<PipelineCache>,
+    gpu_errors: Res<GpuErrorsRes>,
+    mut reported: Local<HashSet<(String, String)>>,
+) {
+    for pipeline in pipeline_cache.pipelines() {
+        let CachedPipelineState::Err(error) = &pipeline.state else {
+            continue;
+        };
+        // Still being processed, not an error in the source
+        if matches!(error, PipelineCacheError::ShaderNotLoaded(_)) {
+            continue;
+        }
+        let PipelineDescriptor::RenderPipelineDescriptor(descriptor) = &pipeline.descriptor else {
+            continue;
+        };
+        let Some(fragment) = &descriptor.fragment else {
+            continue;
+        };
+        let Some(name) = shaders
+            .0
+            .iter()
+            .find_map(|(name, index)| (shader_handle(*index) == fragment.shader).then_some(name))
+        else {
+            continue;
+        };
+        let message = error.to_string();
+        if reported.insert((name.clone(), message.clone())) {
+            gpu_errors.0.report(
+                GpuErrorKind::Shader,
+                &format!("custom_shader:{}", name),
+                message,
+            );
+        }
+    }
+}
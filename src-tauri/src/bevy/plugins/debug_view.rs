@@ -0,0 +1,95 @@
+//! Debug view material
+//!
+//! While a debug view is on, meshes are drawn with [`DebugViewMaterial`]
+//! instead of their `StandardMaterial`, which shows one property of the
+//! geometry instead of the lit surface: depth, normals, texture
+//! coordinates, overdraw or the directional light's shadow cascades.
+
+use bevy::{
+    app::{App, Plugin},
+    asset::uuid_handle,
+    pbr::{Material, MaterialPlugin},
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderType},
+    shader::{Shader, ShaderRef},
+};
+
+use crate::config::debug_view::{DEPTH_RANGE, OVERDRAW_STEP};
+use crate::tauri_bridge::shared_state::DebugView;
+
+const SHADER_HANDLE: Handle<Shader> = uuid_handle!("6f3d2a4e-9c1b-4d7e-8a5f-2b0c9e1d4a73");
+
+pub struct DebugViewPlugin;
+
+impl Plugin for DebugViewPlugin {
+    fn build(&self, app: &mut App) {
+        let shader = Shader::from_wgsl(
+            include_str!("../shaders/debug_view.wgsl"),
+            "bevy/shaders/debug_view.wgsl",
+        );
+        let _ = app
+            .world_mut()
+            .resource_mut::<Assets<Shader>>()
+            .insert(SHADER_HANDLE.id(), shader);
+        app.add_plugins(MaterialPlugin::<DebugViewMaterial>::default());
+    }
+}
+
+/// Material drawing one debug view, see `debug_view.wgsl`
+#[derive(Asset, TypePath, AsBindGroup, Clone, Debug)]
+pub struct DebugViewMaterial {
+    #[uniform(0)]
+    pub params: DebugViewParams,
+    /// Additive for overdraw, so overlapping surfaces add up
+    pub alpha_mode: AlphaMode,
+}
+
+/// Uniform of [`DebugViewMaterial`]
+#[derive(Clone, Copy, Debug, ShaderType)]
+pub struct DebugViewParams {
+    pub mode: u32,
+    /// Distance in world units the depth view fades to black over
+    pub depth_range: f32,
+    /// Brightness each overlapping surface adds in the overdraw view
+    pub overdraw_step: f32,
+}
+
+impl DebugViewMaterial {
+    /// Material for `view`, `None` for [`DebugView::Off`]
+    pub fn new(view: DebugView) -> Option<Self> {
+        let mode = Self::mode_index(view)?;
+        Some(Self {
+            params: DebugViewParams {
+                mode,
+                depth_range: DEPTH_RANGE,
+                overdraw_step: OVERDRAW_STEP,
+            },
+            alpha_mode: match view {
+                DebugView::Overdraw => AlphaMode::Add,
+                _ => AlphaMode::Opaque,
+            },
+        })
+    }
+
+    /// Mode number in the shader
+    fn mode_index(view: DebugView) -> Option<u32> {
+        match view {
+            DebugView::Off => None,
+            DebugView::Depth => Some(0),
+            DebugView::Normals => Some(1),
+            DebugView::Uvs => Some(2),
+            DebugView::Overdraw => Some(3),
+            DebugView::ShadowCascades => Some(4),
+        }
+    }
+}
+
+impl Material for DebugViewMaterial {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_HANDLE.into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+}
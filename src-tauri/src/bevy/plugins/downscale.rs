@@ -0,0 +1,263 @@
+//! GPU-side downscaling before readback
+//!
+//! When the stream resolution is smaller than the render target, a small
+//! render pass draws the render target into a stream-sized texture, and only
+//! that texture is copied to the CPU. Streaming a 1080p render at 540p then
+//! reads back and encodes a quarter of the pixels.
+
+use bevy::{
+    app::{App, Plugin},
+    prelude::*,
+    render::{
+        render_asset::RenderAssets,
+        render_graph::{self, NodeRunError, RenderGraph, RenderGraphContext, RenderLabel},
+        renderer::{RenderContext, RenderDevice},
+        texture::GpuImage,
+        Extract, ExtractSchedule, RenderApp,
+    },
+};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, ColorTargetState, ColorWrites,
+    FilterMode, FragmentState, LoadOp, MultisampleState, Operations, PipelineLayoutDescriptor,
+    PrimitiveState, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor, ShaderModuleDescriptor,
+    ShaderSource, ShaderStages, StoreOp, TextureFormat, TextureSampleType, TextureViewDimension,
+    VertexState,
+};
+
+use crate::bevy::gpu_errors::with_error_scopes;
+use crate::bevy::plugins::image_copy::ImageCopy;
+use crate::bevy::resources::GpuErrorsRes;
+use crate::tauri_bridge::shared_state::SharedGpuErrors;
+
+// =============================================================================
+// Plugin Definition
+// =============================================================================
+
+pub struct DownscalePlugin {
+    /// Channel errors of the downscale pass are reported to
+    pub gpu_errors: SharedGpuErrors,
+}
+
+impl Plugin for DownscalePlugin {
+    fn build(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+
+        // Camera pass -> downscale -> copy to staging buffer
+        let mut graph = render_app.world_mut().resource_mut::<RenderGraph>();
+        graph.add_node(Downscale, DownscaleDriver);
+        graph.add_node_edge(bevy::render::graph::CameraDriverLabel, Downscale);
+        graph.add_node_edge(Downscale, ImageCopy);
+
+        render_app
+            .insert_resource(GpuErrorsRes(self.gpu_errors.clone()))
+            .add_systems(ExtractSchedule, downscale_extract);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+        let render_device = render_app.world().resource::<RenderDevice>();
+        let pipeline = with_error_scopes(
+            render_device,
+            &self.gpu_errors,
+            "downscale_pipeline",
+            || DownscalePipeline::new(render_device),
+        );
+        render_app.insert_resource(pipeline);
+    }
+}
+
+// =============================================================================
+// Downscale Pass Component
+// =============================================================================
+
+/// Draws `source` into the smaller `target` texture every frame
+#[derive(Clone, Component)]
+pub struct DownscalePass {
+    pub source: Handle<Image>,
+    pub target: Handle<Image>,
+}
+
+/// Downscale passes extracted into the render world
+#[derive(Clone, Default, Resource, Deref)]
+struct DownscalePasses(Vec<DownscalePass>);
+
+/// Extract downscale passes from main world to render world
+fn downscale_extract(mut commands: Commands, passes: Extract<Query<&DownscalePass>>) {
+    commands.insert_resource(DownscalePasses(passes.iter().cloned().collect()));
+}
+
+// =============================================================================
+// Pipeline (Render World)
+// =============================================================================
+
+/// Fullscreen-triangle pipeline sampling the source with a linear filter
+#[derive(Resource)]
+struct DownscalePipeline {
+    pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    sampler: Sampler,
+}
+
+impl DownscalePipeline {
+    fn new(render_device: &RenderDevice) -> Self {
+        let device = render_device.wgpu_device();
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("downscale_shader"),
+            source: ShaderSource::Wgsl(include_str!("../shaders/downscale.wgsl").into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("downscale_bind_group_layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("downscale_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("downscale_pipeline"),
+            layout: Some(&layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(ColorTargetState {
+                    // Must match the stream target created in `update_stream_target`
+                    format: TextureFormat::Rgba8UnormSrgb,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("downscale_sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            sampler,
+        }
+    }
+}
+
+// =============================================================================
+// Render Graph Node
+// =============================================================================
+
+/// Render graph label of the downscale pass
+#[derive(Debug, PartialEq, Eq, Clone, Hash, RenderLabel)]
+pub struct Downscale;
+
+#[derive(Default)]
+struct DownscaleDriver;
+
+impl render_graph::Node for DownscaleDriver {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let (Some(passes), Some(pipeline), Some(gpu_errors)) = (
+            world.get_resource::<DownscalePasses>(),
+            world.get_resource::<DownscalePipeline>(),
+            world.get_resource::<GpuErrorsRes>(),
+        ) else {
+            return Ok(());
+        };
+        let gpu_images = world.resource::<RenderAssets<GpuImage>>();
+        let render_device = render_context.render_device().clone();
+
+        let _span = tracing::info_span!("record_downscale").entered();
+        for pass in passes.iter() {
+            // Images may not be uploaded yet right after a resolution change
+            let (Some(source), Some(target)) =
+                (gpu_images.get(&pass.source), gpu_images.get(&pass.target))
+            else {
+                continue;
+            };
+
+            let bind_group = with_error_scopes(&render_device, &gpu_errors.0, "downscale", || {
+                render_device
+                    .wgpu_device()
+                    .create_bind_group(&BindGroupDescriptor {
+                        label: Some("downscale_bind_group"),
+                        layout: &pipeline.bind_group_layout,
+                        entries: &[
+                            BindGroupEntry {
+                                binding: 0,
+                                resource: BindingResource::TextureView(&*source.texture_view),
+                            },
+                            BindGroupEntry {
+                                binding: 1,
+                                resource: BindingResource::Sampler(&pipeline.sampler),
+                            },
+                        ],
+                    })
+            });
+
+            let mut render_pass =
+                render_context
+                    .command_encoder()
+                    .begin_render_pass(&RenderPassDescriptor {
+                        label: Some("downscale_pass"),
+                        color_attachments: &[Some(RenderPassColorAttachment {
+                            view: &*target.texture_view,
+                            depth_slice: None,
+                            resolve_target: None,
+                            ops: Operations {
+                                load: LoadOp::Clear(wgpu::Color::BLACK),
+                                store: StoreOp::Store,
+                            },
+                        })],
+                        depth_stencil_attachment: None,
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                    });
+            render_pass.set_pipeline(&pipeline.pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,336 @@
+//! HDR environment loader
+//!
+//! Bevy lights a scene from its surroundings with a pair of prefiltered
+//! cubemaps: a blurry diffuse one, and a specular one whose mip levels get
+//! blurrier for rougher surfaces. Tools usually bake these ahead of time;
+//! this loader builds them from a plain equirectangular `.hdr` instead, so
+//! HDRIs can be used as downloaded. Filtering runs on the CPU in the asset
+//! loader, off the Bevy thread, at the sizes in `config::environment`.
+//!
+//! The loaded image is the background cubemap, for a `Skybox`. The
+//! filtered maps are its `diffuse` and `specular` labeled assets.
+
+use bevy::{
+    app::{App, Plugin},
+    asset::{io::Reader, AssetLoader, LoadContext, RenderAssetUsages},
+    image::{Image, ImageSampler, ImageSamplerDescriptor},
+    math::Vec3,
+    render::render_resource::{
+        Extent3d, TextureDimension, TextureFormat, TextureViewDescriptor, TextureViewDimension,
+    },
+};
+use std::f32::consts::{PI, TAU};
+use std::io;
+
+use crate::config::environment::{
+    DIFFUSE_SAMPLES, DIFFUSE_SIZE, SKYBOX_SIZE, SPECULAR_SAMPLES, SPECULAR_SIZE,
+};
+
+pub struct EnvironmentMapPlugin;
+
+impl Plugin for EnvironmentMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset_loader::<HdrEnvironmentLoader>();
+    }
+}
+
+/// Loads an equirectangular `.hdr` as a background cubemap with filtered
+/// `diffuse` and `specular` cubemaps
+#[derive(Default)]
+pub struct HdrEnvironmentLoader;
+
+impl AssetLoader for HdrEnvironmentLoader {
+    type Asset = Image;
+    type Settings = ();
+    type Error = io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Image, io::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let source = image::load_from_memory_with_format(&bytes, image::ImageFormat::Hdr)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?
+            .into_rgb32f();
+
+        let mut pyramid = vec![Equirect {
+            width: source.width() as usize,
+            height: source.height() as usize,
+            pixels: source
+                .pixels()
+                .map(|pixel| Vec3::from_array(pixel.0))
+                .collect(),
+        }];
+        while let Some(smaller) = pyramid.last().and_then(Equirect::downsample) {
+            pyramid.push(smaller);
+        }
+
+        load_context.add_labeled_asset(
+            "diffuse".to_string(),
+            cubemap(DIFFUSE_SIZE, &[diffuse_level(&pyramid)]),
+        );
+        load_context.add_labeled_asset(
+            "specular".to_string(),
+            cubemap(SPECULAR_SIZE, &specular_levels(&pyramid)),
+        );
+        let background = &pyramid[level_for(&pyramid, SKYBOX_SIZE as f32)];
+        Ok(cubemap(
+            SKYBOX_SIZE,
+            &[cube_level(SKYBOX_SIZE, |direction| {
+                background.sample(direction)
+            })],
+        ))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["hdr"]
+    }
+}
+
+// =============================================================================
+// Filtering
+// =============================================================================
+
+/// Equirectangular image in linear RGB
+struct Equirect {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+}
+
+impl Equirect {
+    /// Bilinear sample in `direction`, with -Z at the center of the image
+    fn sample(&self, direction: Vec3) -> Vec3 {
+        let u = 0.5 + direction.x.atan2(-direction.z) / TAU;
+        let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+        let x = u * self.width as f32 - 0.5;
+        let y = (v * self.height as f32 - 0.5).clamp(0.0, (self.height - 1) as f32);
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+        // Wraps around horizontally
+        let x0 = (x.floor() as isize).rem_euclid(self.width as isize) as usize;
+        let x1 = (x0 + 1) % self.width;
+        let y0 = y as usize;
+        let y1 = (y0 + 1).min(self.height - 1);
+        let pixel = |x: usize, y: usize| self.pixels[y * self.width + x];
+        let top = pixel(x0, y0).lerp(pixel(x1, y0), fx);
+        let bottom = pixel(x0, y1).lerp(pixel(x1, y1), fx);
+        top.lerp(bottom, fy)
+    }
+
+    /// Half the size, each pixel the average of four, `None` once too
+    /// small to be worth sampling
+    fn downsample(&self) -> Option<Self> {
+        if self.width <= 8 || self.height <= 4 {
+            return None;
+        }
+        let (width, height) = (self.width / 2, self.height / 2);
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let (x, y) = (x * 2, y * 2);
+                let row = y * self.width;
+                let below = (y + 1) * self.width;
+                (self.pixels[row + x]
+                    + self.pixels[row + x + 1]
+                    + self.pixels[below + x]
+                    + self.pixels[below + x + 1])
+                    / 4.0
+            })
+            .collect();
+        Some(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+/// Index of the smallest pyramid level with at least `face_size` cube
+/// texels per quarter turn
+fn level_for(pyramid: &[Equirect], face_size: f32) -> usize {
+    pyramid
+        .iter()
+        .rposition(|level| level.width as f32 >= 4.0 * face_size)
+        .unwrap_or(0)
+}
+
+/// Direction through the center of texel `(x, y)` of cube face `face`, in
+/// the order +X, -X, +Y, -Y, +Z, -Z
+fn face_direction(face: usize, x: u32, y: u32, size: u32) -> Vec3 {
+    let s = 2.0 * (x as f32 + 0.5) / size as f32 - 1.0;
+    let t = 2.0 * (y as f32 + 0.5) / size as f32 - 1.0;
+    match face {
+        0 => Vec3::new(1.0, -t, -s),
+        1 => Vec3::new(-1.0, -t, s),
+        2 => Vec3::new(s, 1.0, t),
+        3 => Vec3::new(s, -1.0, -t),
+        4 => Vec3::new(s, -t, 1.0),
+        _ => Vec3::new(-s, -t, -1.0),
+    }
+    .normalize()
+}
+
+/// The six faces of a `size` cubemap level, each texel computed from its
+/// direction
+fn cube_level(size: u32, texel: impl Fn(Vec3) -> Vec3) -> [Vec<Vec3>; 6] {
+    std::array::from_fn(|face| {
+        (0..size)
+            .flat_map(|y| (0..size).map(move |x| (x, y)))
+            .map(|(x, y)| texel(face_direction(face, x, y, size)))
+            .collect()
+    })
+}
+
+/// Low-discrepancy sample `i` of `count` in the unit square
+fn hammersley(i: u32, count: u32) -> (f32, f32) {
+    (
+        i as f32 / count as f32,
+        i.reverse_bits() as f32 / 4_294_967_296.0,
+    )
+}
+
+/// `local` in the frame with `n` as its Z axis
+fn around(n: Vec3, (t, b): (Vec3, Vec3), local: Vec3) -> Vec3 {
+    t * local.x + b * local.y + n * local.z
+}
+
+/// Cosine-weighted average of the environment around each direction
+fn diffuse_level(pyramid: &[Equirect]) -> [Vec<Vec3>; 6] {
+    let source = &pyramid[level_for(pyramid, (DIFFUSE_SIZE / 4) as f32)];
+    let samples: Vec<Vec3> = (0..DIFFUSE_SAMPLES)
+        .map(|i| {
+            let (u1, u2) = hammersley(i, DIFFUSE_SAMPLES);
+            let r = u2.sqrt();
+            let phi = TAU * u1;
+            Vec3::new(r * phi.cos(), r * phi.sin(), (1.0 - u2).sqrt())
+        })
+        .collect();
+    cube_level(DIFFUSE_SIZE, |n| {
+        let frame = n.any_orthonormal_pair();
+        let sum: Vec3 = samples
+            .iter()
+            .map(|&local| source.sample(around(n, frame, local)))
+            .sum();
+        sum / samples.len() as f32
+    })
+}
+
+/// A GGX sample direction around +Z, its weight and the pyramid level
+/// matching the solid angle it covers
+struct GgxSample {
+    direction: Vec3,
+    weight: f32,
+    level: usize,
+}
+
+/// Mip levels of the specular cubemap, Bevy reads level `roughness *
+/// (levels - 1)` for a surface's perceptual roughness
+fn specular_levels(pyramid: &[Equirect]) -> Vec<[Vec<Vec3>; 6]> {
+    let count = SPECULAR_SIZE.ilog2() + 1;
+    (0..count)
+        .map(|level| {
+            let size = SPECULAR_SIZE >> level;
+            if level == 0 {
+                let source = &pyramid[level_for(pyramid, size as f32)];
+                return cube_level(size, |direction| source.sample(direction));
+            }
+            let samples = ggx_samples(pyramid, level as f32 / (count - 1) as f32);
+            cube_level(size, |n| {
+                let frame = n.any_orthonormal_pair();
+                let mut sum = Vec3::ZERO;
+                let mut weight = 0.0;
+                for sample in &samples {
+                    let direction = around(n, frame, sample.direction);
+                    sum += pyramid[sample.level].sample(direction) * sample.weight;
+                    weight += sample.weight;
+                }
+                sum / weight
+            })
+        })
+        .collect()
+}
+
+/// Light directions reflected toward a viewer along +Z by a GGX surface of
+/// `roughness`, importance sampled so each covers a similar share of the
+/// lobe
+fn ggx_samples(pyramid: &[Equirect], roughness: f32) -> Vec<GgxSample> {
+    let alpha = roughness * roughness;
+    let alpha2 = alpha * alpha;
+    (0..SPECULAR_SAMPLES)
+        .filter_map(|i| {
+            let (u1, u2) = hammersley(i, SPECULAR_SAMPLES);
+            let phi = TAU * u1;
+            let cos_theta = ((1.0 - u2) / (1.0 + (alpha2 - 1.0) * u2)).sqrt();
+            let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+            let half = Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+            let direction = 2.0 * cos_theta * half - Vec3::Z;
+            if direction.z <= 0.0 {
+                return None;
+            }
+            // With the view along the normal, the pdf of the light
+            // direction is D / 4
+            let d = (cos_theta * cos_theta * (alpha2 - 1.0) + 1.0).powi(2);
+            let pdf = alpha2 / (PI * d) / 4.0;
+            let solid_angle = 1.0 / (SPECULAR_SAMPLES as f32 * pdf);
+            let face_size = (4.0 * PI / (6.0 * solid_angle)).sqrt();
+            Some(GgxSample {
+                direction,
+                weight: direction.z,
+                level: level_for(pyramid, face_size),
+            })
+        })
+        .collect()
+}
+
+// =============================================================================
+// Encoding
+// =============================================================================
+
+/// Cube texture of `levels` mip levels, in RGB9E5 so HDR values take four
+/// bytes and stay filterable everywhere
+fn cubemap(size: u32, levels: &[[Vec<Vec3>; 6]]) -> Image {
+    // Layer-major: every level of a face before the next face
+    let data = (0..6)
+        .flat_map(|face| levels.iter().flat_map(move |level| &level[face]))
+        .flat_map(|&texel| rgb9e5(texel).to_le_bytes())
+        .collect();
+    let mut image = Image::new_uninit(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 6,
+        },
+        TextureDimension::D2,
+        TextureFormat::Rgb9e5Ufloat,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.texture_descriptor.mip_level_count = levels.len() as u32;
+    image.data = Some(data);
+    image.texture_view_descriptor = Some(TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::Cube),
+        ..Default::default()
+    });
+    image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor::linear());
+    image
+}
+
+/// Pack `rgb` as three 9-bit mantissas sharing a 5-bit exponent
+fn rgb9e5(rgb: Vec3) -> u32 {
+    const MANTISSA_BITS: i32 = 9;
+    const BIAS: i32 = 15;
+    // (511 / 512) * 2^16, the largest representable value
+    const MAX: f32 = 65408.0;
+
+    let [r, g, b] = rgb.to_array().map(|c| c.clamp(0.0, MAX));
+    let max = r.max(g).max(b);
+    let mut exponent = max.log2().floor().max(-(BIAS as f32) - 1.0) as i32 + 1 + BIAS;
+    let scale = |exponent: i32| 2f32.powi(exponent - BIAS - MANTISSA_BITS);
+    if (max / scale(exponent) + 0.5).floor() as i32 == 1 << MANTISSA_BITS {
+        exponent += 1;
+    }
+    let mantissa = |c: f32| (c / scale(exponent) + 0.5).floor() as u32;
+    mantissa(r) | mantissa(g) << 9 | mantissa(b) << 18 | (exponent as u32) << 27
+}
@@ -0,0 +1,110 @@
+//! GPU memory usage reporting
+//!
+//! Reports two kinds of numbers in `PerformanceStats`:
+//! - Allocator totals from wgpu, on backends that track them (Vulkan, DX12)
+//! - Sizes of the render targets and readback buffers this app created
+//!
+//! Together they make leaks visible, e.g. when the render resolution changes
+//! at runtime and old targets are not released.
+
+use bevy::{
+    app::{App, Plugin},
+    prelude::*,
+    render::{renderer::RenderDevice, Render, RenderApp, RenderSystems},
+};
+
+use crate::bevy::plugins::image_copy::ImageCopier;
+use crate::bevy::plugins::pixel_pack::packed_size;
+use crate::bevy::resources::{PerfStatsRes, RenderTargetHandle, StreamTarget};
+use crate::config::performance::MEMORY_REPORT_INTERVAL_FRAMES;
+use crate::tauri_bridge::shared_state::SharedPerfStats;
+
+const BYTES_PER_MB: f64 = 1024.0 * 1024.0;
+
+pub struct GpuMemoryPlugin {
+    /// Stats the memory figures are written into
+    pub perf_stats: SharedPerfStats,
+}
+
+impl Plugin for GpuMemoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Last, report_tracked_allocations);
+
+        app.sub_app_mut(RenderApp)
+            .insert_resource(PerfStatsRes(self.perf_stats.clone()))
+            .add_systems(Render, report_allocator_usage.after(RenderSystems::Render));
+    }
+}
+
+/// Sum the sizes of the render targets and readback buffers we created
+fn report_tracked_allocations(
+    copiers: Query<&ImageCopier>,
+    render_target: Option<Res<RenderTargetHandle>>,
+    stream_target: Option<Res<StreamTarget>>,
+    images: Res<Assets<Image>>,
+    perf_stats: Res<PerfStatsRes>,
+    mut frames: Local<u32>,
+) {
+    *frames += 1;
+    if *frames % MEMORY_REPORT_INTERVAL_FRAMES != 0 {
+        return;
+    }
+
+    let readback_bytes: u64 = copiers
+        .iter()
+        .map(|copier| {
+            let packed = copier.packed_buffer.as_ref().map_or(0, |_| {
+                packed_size(copier.size.width, copier.size.height, copier.pixel_format)
+            });
+            copier.staging_bytes() + packed
+        })
+        .sum();
+
+    let image_bytes = |handle: &Handle<Image>| {
+        images
+            .get(handle)
+            .and_then(|image| {
+                let size = image.texture_descriptor.size;
+                let block_size = image.texture_descriptor.format.block_copy_size(None)?;
+                Some(size.width as u64 * size.height as u64 * block_size as u64)
+            })
+            .unwrap_or_default()
+    };
+
+    let render_target = render_target.map(|target| target.0.clone());
+    let mut render_target_bytes = render_target.as_ref().map(image_bytes).unwrap_or_default();
+    // A separate stream texture only exists while downscaling
+    if let Some(stream_target) = stream_target {
+        if Some(&stream_target.image) != render_target.as_ref() {
+            render_target_bytes += image_bytes(&stream_target.image);
+        }
+    }
+
+    if let Ok(mut stats) = perf_stats.0 .0.lock() {
+        stats.readback_buffers_mb = readback_bytes as f64 / BYTES_PER_MB;
+        stats.render_targets_mb = render_target_bytes as f64 / BYTES_PER_MB;
+    }
+}
+
+/// Query the wgpu allocator for device-wide memory totals
+///
+/// Not every backend keeps an allocator report; the values stay at zero there.
+fn report_allocator_usage(
+    render_device: Res<RenderDevice>,
+    perf_stats: Res<PerfStatsRes>,
+    mut frames: Local<u32>,
+) {
+    *frames += 1;
+    if *frames % MEMORY_REPORT_INTERVAL_FRAMES != 0 {
+        return;
+    }
+
+    let Some(report) = render_device.wgpu_device().generate_allocator_report() else {
+        return;
+    };
+
+    if let Ok(mut stats) = perf_stats.0 .0.lock() {
+        stats.gpu_allocated_mb = report.total_allocated_bytes as f64 / BYTES_PER_MB;
+        stats.gpu_reserved_mb = report.total_reserved_bytes as f64 / BYTES_PER_MB;
+    }
+}
@@ -0,0 +1,254 @@
+//! GPU timestamp queries
+//!
+//! Measures how long the GPU actually spends rendering the camera pass and
+//! copying the render target into the staging buffer. CPU-side timers can
+//! only see when commands were submitted, not when the GPU executed them.
+//!
+//! Timestamps are written inside command encoders, which needs the
+//! `TIMESTAMP_QUERY_INSIDE_ENCODERS` feature. On adapters without it the
+//! plugin does nothing and the GPU timings stay at zero.
+
+use bevy::{
+    app::{App, Plugin},
+    prelude::*,
+    render::{
+        render_graph::{self, NodeRunError, RenderGraph, RenderGraphContext, RenderLabel},
+        render_resource::{Buffer, BufferDescriptor, BufferUsages, MapMode},
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        Render, RenderApp, RenderSystems,
+    },
+};
+use std::sync::{
+    atomic::{AtomicU8, Ordering},
+    Arc,
+};
+use wgpu::{CommandEncoder, Features, QuerySet, QuerySetDescriptor, QueryType};
+
+use crate::bevy::resources::PerfStatsRes;
+use crate::tauri_bridge::shared_state::SharedPerfStats;
+
+/// Timestamp written before the camera pass
+pub const RENDER_START: u32 = 0;
+/// Timestamp written after the camera pass, before the readback copy
+pub const COPY_START: u32 = 1;
+/// Timestamp written after the readback copy
+pub const COPY_END: u32 = 2;
+
+const TIMESTAMP_COUNT: u32 = 3;
+const TIMESTAMP_BYTES: u64 = TIMESTAMP_COUNT as u64 * std::mem::size_of::<u64>() as u64;
+
+/// Readback buffer is free, no timestamps recorded this frame
+const READBACK_IDLE: u8 = 0;
+/// Timestamps are being recorded this frame
+const READBACK_RECORDING: u8 = 1;
+/// `map_async` has been requested for the resolved timestamps
+const READBACK_MAPPING: u8 = 2;
+/// Resolved timestamps are mapped and can be read
+const READBACK_MAPPED: u8 = 3;
+
+// =============================================================================
+// Plugin Definition
+// =============================================================================
+
+pub struct GpuTimingPlugin {
+    /// Stats the measured GPU times are written into
+    pub perf_stats: SharedPerfStats,
+}
+
+impl Plugin for GpuTimingPlugin {
+    fn build(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+
+        let mut graph = render_app.world_mut().resource_mut::<RenderGraph>();
+        graph.add_node(GpuTimestampBegin, GpuTimestampBeginDriver);
+        graph.add_node_edge(GpuTimestampBegin, bevy::render::graph::CameraDriverLabel);
+
+        render_app
+            .insert_resource(PerfStatsRes(self.perf_stats.clone()))
+            .add_systems(
+                Render,
+                read_gpu_timestamps.after(RenderSystems::Render),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+        let render_device = render_app.world().resource::<RenderDevice>();
+        let render_queue = render_app.world().resource::<RenderQueue>();
+
+        let required = Features::TIMESTAMP_QUERY | Features::TIMESTAMP_QUERY_INSIDE_ENCODERS;
+        if !render_device.features().contains(required) {
+            println!("[Bevy] GPU timestamp queries not supported, GPU timings disabled");
+            return;
+        }
+
+        let timestamps = GpuTimestamps::new(render_device, render_queue);
+        render_app.insert_resource(timestamps);
+    }
+}
+
+// =============================================================================
+// Timestamp Resource (Render World)
+// =============================================================================
+
+/// Query set and buffers used to read GPU timestamps back to the CPU
+#[derive(Resource)]
+pub struct GpuTimestamps {
+    query_set: QuerySet,
+    resolve_buffer: Buffer,
+    readback_buffer: Buffer,
+    state: Arc<AtomicU8>,
+    /// Nanoseconds per timestamp tick
+    period_ns: f32,
+}
+
+impl GpuTimestamps {
+    fn new(render_device: &RenderDevice, render_queue: &RenderQueue) -> Self {
+        let query_set = render_device
+            .wgpu_device()
+            .create_query_set(&QuerySetDescriptor {
+                label: Some("gpu_timestamps"),
+                ty: QueryType::Timestamp,
+                count: TIMESTAMP_COUNT,
+            });
+
+        let resolve_buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("gpu_timestamps_resolve"),
+            size: TIMESTAMP_BYTES,
+            usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let readback_buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("gpu_timestamps_readback"),
+            size: TIMESTAMP_BYTES,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            state: Arc::new(AtomicU8::new(READBACK_IDLE)),
+            period_ns: render_queue.get_timestamp_period(),
+        }
+    }
+
+    /// Start recording timestamps for this frame
+    ///
+    /// Skipped while the previous frame's results are still being read back.
+    fn begin(&self, encoder: &mut CommandEncoder) {
+        if self
+            .state
+            .compare_exchange(
+                READBACK_IDLE,
+                READBACK_RECORDING,
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            )
+            .is_ok()
+        {
+            encoder.write_timestamp(&self.query_set, RENDER_START);
+        }
+    }
+
+    /// Write timestamp `index` if this frame is being recorded
+    pub fn write(&self, encoder: &mut CommandEncoder, index: u32) {
+        if self.state.load(Ordering::Acquire) == READBACK_RECORDING {
+            encoder.write_timestamp(&self.query_set, index);
+        }
+    }
+
+    /// Resolve this frame's timestamps into the readback buffer
+    ///
+    /// Must be called after the last [`GpuTimestamps::write`] of the frame.
+    pub fn resolve(&self, encoder: &mut CommandEncoder) {
+        if self.state.load(Ordering::Acquire) != READBACK_RECORDING {
+            return;
+        }
+        encoder.resolve_query_set(&self.query_set, 0..TIMESTAMP_COUNT, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            TIMESTAMP_BYTES,
+        );
+    }
+
+    /// Convert a tick difference to milliseconds
+    fn ticks_to_ms(&self, start: u64, end: u64) -> f64 {
+        end.saturating_sub(start) as f64 * self.period_ns as f64 / 1_000_000.0
+    }
+}
+
+// =============================================================================
+// Render Graph Node
+// =============================================================================
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash, RenderLabel)]
+struct GpuTimestampBegin;
+
+#[derive(Default)]
+struct GpuTimestampBeginDriver;
+
+impl render_graph::Node for GpuTimestampBeginDriver {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        if let Some(timestamps) = world.get_resource::<GpuTimestamps>() {
+            timestamps.begin(render_context.command_encoder());
+        }
+        Ok(())
+    }
+}
+
+/// Read back resolved timestamps and publish the GPU times
+///
+/// Mirrors the staging buffer ring in the copy plugin: results mapped since
+/// the last frame are read first, then mapping is requested for this frame.
+fn read_gpu_timestamps(timestamps: Option<Res<GpuTimestamps>>, perf_stats: Res<PerfStatsRes>) {
+    let Some(timestamps) = timestamps else {
+        return;
+    };
+
+    match timestamps.state.load(Ordering::Acquire) {
+        READBACK_MAPPED => {
+            let ticks: Vec<u64> = timestamps
+                .readback_buffer
+                .slice(..)
+                .get_mapped_range()
+                .chunks_exact(8)
+                .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+                .collect();
+            timestamps.readback_buffer.unmap();
+            timestamps.state.store(READBACK_IDLE, Ordering::Release);
+
+            if let Ok(mut stats) = perf_stats.0 .0.lock() {
+                stats.gpu_render_ms = timestamps
+                    .ticks_to_ms(ticks[RENDER_START as usize], ticks[COPY_START as usize]);
+                stats.gpu_copy_ms =
+                    timestamps.ticks_to_ms(ticks[COPY_START as usize], ticks[COPY_END as usize]);
+            }
+        }
+        READBACK_RECORDING => {
+            timestamps.state.store(READBACK_MAPPING, Ordering::Release);
+            let state = timestamps.state.clone();
+            timestamps
+                .readback_buffer
+                .slice(..)
+                .map_async(MapMode::Read, move |result| match result {
+                    Ok(()) => state.store(READBACK_MAPPED, Ordering::Release),
+                    Err(err) => {
+                        eprintln!("[GpuTiming] Failed to map timestamp buffer: {err}");
+                        state.store(READBACK_IDLE, Ordering::Release);
+                    }
+                });
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,73 @@
+//! Object-ID material for GPU picking
+//!
+//! Meshes drawn with [`IdMaterial`] come out in a flat color encoding
+//! their pick id, see [`encode_id`]. Only the fragment shader is replaced,
+//! so Bevy's mesh vertex shader still skins them and applies their morph
+//! targets, and the ID buffer matches what the stream shows.
+
+use bevy::{
+    app::{App, Plugin},
+    asset::uuid_handle,
+    pbr::{Material, MaterialPlugin},
+    prelude::*,
+    render::render_resource::AsBindGroup,
+    shader::{Shader, ShaderRef},
+};
+
+use crate::config::picking::CHANNEL_BITS;
+
+const SHADER_HANDLE: Handle<Shader> = uuid_handle!("0ad64319-4efe-4c65-9b5c-54c6de58aaac");
+
+/// Largest value a channel holds
+const CHANNEL_MAX: u32 = (1 << CHANNEL_BITS) - 1;
+
+/// Largest pick id that can be encoded
+pub const MAX_PICK_ID: u32 = (1 << (3 * CHANNEL_BITS)) - 1;
+
+pub struct IdBufferPlugin;
+
+impl Plugin for IdBufferPlugin {
+    fn build(&self, app: &mut App) {
+        let shader = Shader::from_wgsl(
+            include_str!("../shaders/id_buffer.wgsl"),
+            "bevy/shaders/id_buffer.wgsl",
+        );
+        let _ = app
+            .world_mut()
+            .resource_mut::<Assets<Shader>>()
+            .insert(SHADER_HANDLE.id(), shader);
+        app.add_plugins(MaterialPlugin::<IdMaterial>::default());
+    }
+}
+
+/// Material drawing a mesh's pick id, see `id_buffer.wgsl`
+#[derive(Asset, TypePath, AsBindGroup, Clone, Debug)]
+pub struct IdMaterial {
+    /// From [`encode_id`]
+    #[uniform(0)]
+    pub color: LinearRgba,
+}
+
+impl Material for IdMaterial {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_HANDLE.into()
+    }
+}
+
+/// Color the ID buffer holds where the mesh with pick id `id` is drawn
+///
+/// Each channel holds `CHANNEL_BITS` of the id spread over its 8 bits, so
+/// a value rounded by one step in the sRGB target still decodes. Pick ids
+/// start at 1, 0 is the background.
+pub fn encode_id(id: u32) -> LinearRgba {
+    let channel = |shift: u32| (((id >> shift) & CHANNEL_MAX) * 255 / CHANNEL_MAX) as u8;
+    Color::srgb_u8(channel(2 * CHANNEL_BITS), channel(CHANNEL_BITS), channel(0)).to_linear()
+}
+
+/// Pick id of an ID buffer pixel read back as RGBA, see [`encode_id`]
+pub fn decode_id(pixel: [u8; 4]) -> u32 {
+    let channel = |value: u8| (value as u32 * CHANNEL_MAX + 127) / 255;
+    (channel(pixel[0]) << (2 * CHANNEL_BITS))
+        | (channel(pixel[1]) << CHANNEL_BITS)
+        | channel(pixel[2])
+}
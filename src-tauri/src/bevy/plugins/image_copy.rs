@@ -2,6 +2,21 @@
 //!
 //! This plugin handles the complex task of copying rendered frames from
 //! GPU memory to CPU memory, making them accessible to the Tauri frontend.
+//! Each copier owns a small ring of staging buffers, so the copy of the next
+//! frame never waits on the mapping of the previous one, and the data read
+//! back goes into buffers taken from the shared pool.
+//!
+//! Textures that are not already in the requested pixel format, or of which
+//! only a region is wanted, go through the pack pass in
+//! [`super::pixel_pack`] first, and the packed storage buffer is copied
+//! instead of the texture.
+//!
+//! Bevy's `Readback` is not used for the stream, only for screenshots and
+//! turntables. It maps a new buffer and allocates a new `Vec` for every
+//! readback, outside the shared pool, and has no ring to skip frames while
+//! mapping lags behind. Its copy can't be tied to the view it was rendered
+//! from, and it can't run the pack pass or the copy timestamps in the same
+//! encoder, ahead of the copy.
 
 use bevy::{
     app::{App, Plugin},
@@ -14,104 +29,383 @@ use bevy::{
     image::Image,
     prelude::*,
     render::{
-        render_asset::RenderAssets,
+        render_asset::{RenderAssetUsages, RenderAssets},
         render_graph::{self, NodeRunError, RenderGraph, RenderGraphContext, RenderLabel},
         render_resource::{
-            Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d, MapMode,
-            PollType, TexelCopyBufferInfo, TexelCopyBufferLayout,
+            Buffer, BufferDescriptor, BufferUsages, ComputePassDescriptor, Extent3d, MapMode,
+            PollType, TexelCopyBufferInfo, TexelCopyBufferLayout, TextureFormat,
         },
-        renderer::{RenderContext, RenderDevice, RenderQueue},
+        renderer::{RenderContext, RenderDevice},
+        storage::{GpuShaderStorageBuffer, ShaderStorageBuffer},
+        texture::GpuImage,
         ExtractSchedule, Render, RenderApp, RenderSystems,
     },
 };
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+    atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering},
+    Arc, OnceLock,
 };
-
-use crate::bevy::resources::{MainWorldReceiver, RenderWorldSender};
+use tracing::info_span;
+
+use crate::bevy::components::{PendingViews, PickReadback, ReadbackCheck};
+use crate::bevy::gpu_errors::with_error_scopes;
+use crate::bevy::plugins::gpu_timing::{GpuTimestamps, COPY_END, COPY_START};
+use crate::bevy::plugins::pixel_pack::{needs_pack, packed_size, PixelPackPipeline};
+use crate::bevy::resources::{
+    BufferPoolRes, CopiedImage, GpuErrorsRes, MainWorldReceiver, QueuedBytes, ReadbackFrame,
+    ReadbackSender, RenderWorldReceiver, RenderWorldSender,
+};
+use crate::config::readback::STAGING_BUFFER_COUNT;
+use crate::tauri_bridge::shared_state::{PixelFormat, SharedBufferPool, SharedGpuErrors};
 
 // =============================================================================
 // Plugin Definition
 // =============================================================================
 
-pub struct ImageCopyPlugin;
+pub struct ImageCopyPlugin {
+    /// Pool the readback buffers are taken from and recycled into
+    pub buffer_pool: SharedBufferPool,
+    /// Channel errors of the pack pass are reported to
+    pub gpu_errors: SharedGpuErrors,
+}
 
 impl Plugin for ImageCopyPlugin {
     fn build(&self, app: &mut App) {
-        let (s, r) = crossbeam_channel::unbounded();
+        let (render_sender, render_receiver) = crossbeam_channel::unbounded();
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let queued_bytes = QueuedBytes::default();
+
+        app.insert_resource(RenderWorldReceiver(render_receiver))
+            .insert_resource(MainWorldReceiver {
+                receiver,
+                queued_bytes: queued_bytes.clone(),
+            })
+            .insert_resource(ReadbackSender {
+                sender,
+                queued_bytes,
+            })
+            .add_systems(PreUpdate, dispatch_copies);
 
-        let render_app = app
-            .insert_resource(MainWorldReceiver(r))
-            .sub_app_mut(RenderApp);
+        let render_app = app.sub_app_mut(RenderApp);
+
+        render_app.insert_resource(BufferPoolRes(self.buffer_pool.clone()));
 
         let mut graph = render_app.world_mut().resource_mut::<RenderGraph>();
         graph.add_node(ImageCopy, ImageCopyDriver);
         graph.add_node_edge(bevy::render::graph::CameraDriverLabel, ImageCopy);
 
         render_app
-            .insert_resource(RenderWorldSender(s))
+            .insert_resource(RenderWorldSender(render_sender))
+            .insert_resource(GpuErrorsRes(self.gpu_errors.clone()))
             .add_systems(ExtractSchedule, image_copy_extract)
             .add_systems(
                 Render,
                 receive_image_from_buffer.after(RenderSystems::Render),
             );
     }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+        let render_device = render_app.world().resource::<RenderDevice>();
+        let pipeline = with_error_scopes(
+            render_device,
+            &self.gpu_errors,
+            "pixel_pack_pipeline",
+            || PixelPackPipeline::new(render_device),
+        );
+        render_app.insert_resource(pipeline);
+    }
+}
+
+// =============================================================================
+// Staging Buffer Ring
+// =============================================================================
+
+/// Staging buffer is free and can receive the next copy
+const SLOT_IDLE: u8 = 0;
+/// A copy has been recorded into this buffer
+const SLOT_COPYING: u8 = 1;
+/// `map_async` has been requested, waiting for the GPU to finish
+const SLOT_MAPPING: u8 = 2;
+/// Buffer is mapped and its contents can be read on the CPU
+const SLOT_MAPPED: u8 = 3;
+
+/// Stored in place of a view number for copiers that don't track views
+const NO_VIEW: u64 = u64::MAX;
+
+/// One CPU-readable buffer in an image copier's ring
+///
+/// The state lives behind an `Arc` because the copier is re-extracted into
+/// the render world every frame, and the map callback runs on a wgpu thread.
+#[derive(Clone)]
+pub struct StagingBuffer {
+    pub buffer: Buffer,
+    state: Arc<AtomicU8>,
+    /// Sequence number of the frame copied into this buffer
+    sequence: Arc<AtomicU64>,
+    /// Pending view of the frame copied into this buffer, or `NO_VIEW`
+    view: Arc<AtomicU64>,
+}
+
+impl StagingBuffer {
+    fn new(buffer: Buffer) -> Self {
+        Self {
+            buffer,
+            state: Arc::new(AtomicU8::new(SLOT_IDLE)),
+            sequence: Arc::new(AtomicU64::new(0)),
+            view: Arc::new(AtomicU64::new(NO_VIEW)),
+        }
+    }
+
+    fn state(&self) -> u8 {
+        self.state.load(Ordering::Acquire)
+    }
+
+    fn set_state(&self, state: u8) {
+        self.state.store(state, Ordering::Release);
+    }
+
+    fn sequence(&self) -> u64 {
+        self.sequence.load(Ordering::Relaxed)
+    }
+
+    fn view(&self) -> Option<u64> {
+        Some(self.view.load(Ordering::Relaxed)).filter(|&view| view != NO_VIEW)
+    }
 }
 
 // =============================================================================
 // Image Copier Component
 // =============================================================================
 
+/// Copies a render target into a ring of staging buffers
+///
+/// While frame N is mapped and read on the CPU, the copy for frame N+1 goes
+/// into another buffer, so the render loop never waits on readback. The
+/// ring is created by the render graph node on the first copy, once the
+/// GPU texture exists.
 #[derive(Clone, Component)]
 pub struct ImageCopier {
-    pub buffer: Buffer,
     pub enabled: Arc<AtomicBool>,
     pub src_image: Handle<Image>,
+    /// Top-left corner of the region of `src_image` read back
+    pub origin: UVec2,
+    /// Size of the region read back, needed to interpret the data
+    pub size: Extent3d,
+    /// Pixel layout of the data read back
+    pub pixel_format: PixelFormat,
+    /// Output of the pack pass, `None` when the texture is copied directly
+    pub packed_buffer: Option<Handle<ShaderStorageBuffer>>,
+    staging: Arc<OnceLock<Vec<StagingBuffer>>>,
+    next_sequence: Arc<AtomicU64>,
+    /// Frames not copied because every staging buffer was still in flight
+    skipped_frames: Arc<AtomicU64>,
 }
 
 impl ImageCopier {
+    /// Create a copier reading `src_image` back as `pixel_format`
+    ///
+    /// `texture_size` and `source_format` describe `src_image`. Only
+    /// `region` of it is read back when given, which must lie within it.
     pub fn new(
         src_image: Handle<Image>,
-        size: Extent3d,
-        render_device: &RenderDevice,
+        texture_size: Extent3d,
+        region: Option<URect>,
+        source_format: TextureFormat,
+        pixel_format: PixelFormat,
+        storage_buffers: &mut Assets<ShaderStorageBuffer>,
     ) -> ImageCopier {
-        let padded_bytes_per_row =
-            RenderDevice::align_copy_bytes_per_row((size.width) as usize) * 4;
-
-        let cpu_buffer = render_device.create_buffer(&BufferDescriptor {
-            label: Some("image_copy_buffer"),
-            size: padded_bytes_per_row as u64 * size.height as u64,
-            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
-            mapped_at_creation: false,
+        let full = URect::new(0, 0, texture_size.width, texture_size.height);
+        let region = region.unwrap_or(full);
+        let size = Extent3d {
+            width: region.width(),
+            height: region.height(),
+            depth_or_array_layers: 1,
+        };
+
+        let packed_buffer = needs_pack(source_format, pixel_format, region != full).then(|| {
+            let mut buffer = ShaderStorageBuffer::with_size(
+                packed_size(size.width, size.height, pixel_format) as usize,
+                RenderAssetUsages::RENDER_WORLD,
+            );
+            buffer.buffer_description.usage |= BufferUsages::COPY_SRC;
+            storage_buffers.add(buffer)
         });
 
         ImageCopier {
-            buffer: cpu_buffer,
             src_image,
+            origin: region.min,
+            size,
+            pixel_format,
+            packed_buffer,
             enabled: Arc::new(AtomicBool::new(true)),
+            staging: Arc::default(),
+            next_sequence: Arc::new(AtomicU64::new(0)),
+            skipped_frames: Arc::new(AtomicU64::new(0)),
         }
     }
 
     pub fn enabled(&self) -> bool {
         self.enabled.load(Ordering::Relaxed)
     }
+
+    /// Number of frames skipped because the staging ring was full
+    pub fn skipped_frames(&self) -> u64 {
+        self.skipped_frames.load(Ordering::Relaxed)
+    }
+
+    /// Total size of the staging ring, zero before the first copy
+    pub fn staging_bytes(&self) -> u64 {
+        self.staging
+            .get()
+            .map(|buffers| buffers.iter().map(|staging| staging.buffer.size()).sum())
+            .unwrap_or_default()
+    }
+
+    /// Whether a readback of `len` bytes still carries row alignment
+    /// padding
+    ///
+    /// Texture copies may. Packed buffers never do, but are rounded up to
+    /// whole shader invocations.
+    pub fn is_padded(&self, len: usize) -> bool {
+        let frame_bytes = self.size.width as usize
+            * self.size.height as usize
+            * self.pixel_format.bytes_per_pixel();
+        self.packed_buffer.is_none() && len > frame_bytes
+    }
+
+    /// Claim a free staging buffer of `size` bytes for this frame's copy
+    ///
+    /// Returns `None` when every buffer is still in flight; the frame is then
+    /// skipped rather than stalling the GPU.
+    fn acquire_buffer(
+        &self,
+        render_device: &RenderDevice,
+        size: u64,
+        view: Option<u64>,
+    ) -> Option<&StagingBuffer> {
+        let buffers = self.staging.get_or_init(|| {
+            (0..STAGING_BUFFER_COUNT)
+                .map(|i| {
+                    StagingBuffer::new(render_device.create_buffer(&BufferDescriptor {
+                        label: Some(&format!("image_copy_buffer_{i}")),
+                        size,
+                        usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                    }))
+                })
+                .collect()
+        });
+        let Some(slot) = buffers.iter().find(|slot| {
+            slot.state
+                .compare_exchange(SLOT_IDLE, SLOT_COPYING, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+        }) else {
+            self.skipped_frames.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+        let sequence = self.next_sequence.fetch_add(1, Ordering::Relaxed);
+        slot.sequence.store(sequence, Ordering::Relaxed);
+        slot.view.store(view.unwrap_or(NO_VIEW), Ordering::Relaxed);
+        Some(slot)
+    }
+}
+
+/// Copy of a self-test pattern or a GPU pick, read back into `data` with
+/// its row padding
+///
+/// Triggered on the copier's entity instead of sending a frame, and
+/// observed by `finish_readback_check` and `finish_gpu_pick`.
+#[derive(EntityEvent)]
+pub struct CopyComplete {
+    pub entity: Entity,
+    pub data: Vec<u8>,
+}
+
+/// Route copies received from the render world
+///
+/// Copies of self-test patterns and GPU picks are triggered as
+/// [`CopyComplete`], all others go to frame extraction with the view they
+/// were rendered from.
+fn dispatch_copies(
+    receiver: Res<RenderWorldReceiver>,
+    mut copiers: Query<(
+        &ImageCopier,
+        Option<&mut PendingViews>,
+        Has<ReadbackCheck>,
+        Has<PickReadback>,
+    )>,
+    sender: Res<ReadbackSender>,
+    buffer_pool: Res<BufferPoolRes>,
+    mut commands: Commands,
+) {
+    for copied in receiver.try_iter() {
+        let Ok((copier, pending_views, check, pick)) = copiers.get_mut(copied.copier) else {
+            buffer_pool.0.recycle(copied.data);
+            continue;
+        };
+        if check || pick {
+            commands.trigger(CopyComplete {
+                entity: copied.copier,
+                data: copied.data,
+            });
+            continue;
+        }
+
+        // Taken even when disabled, to stay in step with the copies
+        let metadata = pending_views
+            .zip(copied.view)
+            .and_then(|(mut views, view)| views.take(view));
+        if !copier.enabled() {
+            buffer_pool.0.recycle(copied.data);
+            continue;
+        }
+
+        let _span = info_span!("dispatch_copies").entered();
+        let padded = copier.is_padded(copied.data.len());
+        sender.send_frame(ReadbackFrame {
+            copier: copied.copier,
+            width: copier.size.width,
+            height: copier.size.height,
+            format: copier.pixel_format,
+            padded,
+            data: copied.data,
+            metadata,
+        });
+    }
 }
 
 // =============================================================================
 // Image Copiers Resource (Render World)
 // =============================================================================
 
+/// Image copier extracted into the render world
+#[derive(Clone)]
+pub struct ExtractedCopier {
+    /// Main world entity of the copier
+    pub entity: Entity,
+    pub copier: ImageCopier,
+    /// Pending view of the frame about to be rendered
+    pub view: Option<u64>,
+}
+
 #[derive(Clone, Default, Resource, Deref)]
-pub struct ImageCopiers(pub Vec<ImageCopier>);
+pub struct ImageCopiers(pub Vec<ExtractedCopier>);
 
 /// Extract image copiers from main world to render world
 fn image_copy_extract(
     mut commands: Commands,
-    image_copiers: bevy::render::Extract<Query<&ImageCopier>>,
+    image_copiers: bevy::render::Extract<Query<(Entity, &ImageCopier, Option<&PendingViews>)>>,
 ) {
     commands.insert_resource(ImageCopiers(
-        image_copiers.iter().cloned().collect::<Vec<ImageCopier>>(),
+        image_copiers
+            .iter()
+            .map(|(entity, copier, views)| ExtractedCopier {
+                entity,
+                copier: copier.clone(),
+                view: views.and_then(PendingViews::latest),
+            })
+            .collect(),
     ));
 }
 
@@ -119,8 +413,9 @@ fn image_copy_extract(
 // Render Graph Node
 // =============================================================================
 
+/// Render graph label of the node copying images into staging buffers
 #[derive(Debug, PartialEq, Eq, Clone, Hash, RenderLabel)]
-struct ImageCopy;
+pub struct ImageCopy;
 
 #[derive(Default)]
 struct ImageCopyDriver;
@@ -132,82 +427,174 @@ impl render_graph::Node for ImageCopyDriver {
         render_context: &mut RenderContext,
         world: &World,
     ) -> Result<(), NodeRunError> {
-        let image_copiers = world.get_resource::<ImageCopiers>().unwrap();
-        let gpu_images = world
-            .get_resource::<RenderAssets<bevy::render::texture::GpuImage>>()
-            .unwrap();
-
-        for image_copier in image_copiers.iter() {
+        let (Some(image_copiers), Some(pack_pipeline), Some(gpu_errors)) = (
+            world.get_resource::<ImageCopiers>(),
+            world.get_resource::<PixelPackPipeline>(),
+            world.get_resource::<GpuErrorsRes>(),
+        ) else {
+            return Ok(());
+        };
+        let gpu_images = world.resource::<RenderAssets<GpuImage>>();
+        let gpu_buffers = world.resource::<RenderAssets<GpuShaderStorageBuffer>>();
+        let timestamps = world.get_resource::<GpuTimestamps>();
+        let render_device = render_context.render_device().clone();
+
+        // Claim a staging buffer for every copier first. Assets may not be
+        // uploaded yet right after a resolution change.
+        let mut copies = Vec::new();
+        for extracted in image_copiers.iter() {
+            let image_copier = &extracted.copier;
             if !image_copier.enabled() {
                 continue;
             }
+            let Some(src_image) = gpu_images.get(&image_copier.src_image) else {
+                continue;
+            };
+            let packed = match &image_copier.packed_buffer {
+                Some(packed) => match gpu_buffers.get(packed) {
+                    Some(packed) => Some(&packed.buffer),
+                    None => continue,
+                },
+                None => None,
+            };
+            let size = match packed {
+                Some(packed) => packed.size(),
+                None => padded_bytes_per_row(src_image) as u64 * src_image.size.height as u64,
+            };
+            if let Some(staging) = image_copier.acquire_buffer(&render_device, size, extracted.view)
+            {
+                copies.push((image_copier, src_image, packed, staging));
+            }
+        }
 
-            let src_image = gpu_images.get(&image_copier.src_image).unwrap();
-
-            let mut encoder = render_context
-                .render_device()
-                .create_command_encoder(&CommandEncoderDescriptor::default());
-
-            let block_dimensions = src_image.texture_format.block_dimensions();
-            let block_size = src_image.texture_format.block_copy_size(None).unwrap();
+        // Record into the graph's encoder so the copies run after this
+        // frame's camera pass, in the same submission
+        let encoder = render_context.command_encoder();
+        if let Some(timestamps) = timestamps {
+            timestamps.write(encoder, COPY_START);
+        }
 
-            let padded_bytes_per_row = RenderDevice::align_copy_bytes_per_row(
-                (src_image.size.width as usize / block_dimensions.0 as usize) * block_size as usize,
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("pixel_pack_pass"),
+            timestamp_writes: None,
+        });
+        for &(image_copier, src_image, packed, _) in &copies {
+            let Some(packed) = packed else {
+                continue;
+            };
+            let _span = info_span!("record_pixel_pack").entered();
+            let region = URect::from_corners(
+                image_copier.origin,
+                image_copier.origin + UVec2::new(image_copier.size.width, image_copier.size.height),
             );
+            with_error_scopes(&render_device, &gpu_errors.0, "pixel_pack", || {
+                pack_pipeline.record(
+                    &render_device,
+                    &mut pass,
+                    src_image,
+                    region,
+                    packed,
+                    image_copier.pixel_format,
+                );
+            });
+        }
+        drop(pass);
+
+        for (_, src_image, packed, staging) in copies {
+            if let Some(packed) = packed {
+                encoder.copy_buffer_to_buffer(packed, 0, &staging.buffer, 0, packed.size());
+                continue;
+            }
 
             encoder.copy_texture_to_buffer(
                 src_image.texture.as_image_copy(),
                 TexelCopyBufferInfo {
-                    buffer: &image_copier.buffer,
+                    buffer: &staging.buffer,
                     layout: TexelCopyBufferLayout {
                         offset: 0,
-                        bytes_per_row: Some(
-                            std::num::NonZero::<u32>::new(padded_bytes_per_row as u32)
-                                .unwrap()
-                                .into(),
-                        ),
+                        bytes_per_row: Some(padded_bytes_per_row(src_image)),
                         rows_per_image: None,
                     },
                 },
                 src_image.size,
             );
+        }
 
-            let render_queue = world.get_resource::<RenderQueue>().unwrap();
-            render_queue.submit(std::iter::once(encoder.finish()));
+        if let Some(timestamps) = timestamps {
+            timestamps.write(encoder, COPY_END);
+            timestamps.resolve(encoder);
         }
 
         Ok(())
     }
 }
 
-/// Receive image data from GPU buffer and send to main world
+/// Row stride of a direct texture copy, aligned as wgpu requires
+fn padded_bytes_per_row(src_image: &GpuImage) -> u32 {
+    let block_dimensions = src_image.texture_format.block_dimensions();
+    let block_size = src_image.texture_format.block_copy_size(None).unwrap_or(4);
+    RenderDevice::align_copy_bytes_per_row(
+        (src_image.size.width as usize / block_dimensions.0 as usize) * block_size as usize,
+    ) as u32
+}
+
+/// Receive image data from GPU buffers and send to main world
+///
+/// Buffers whose mapping completed since the last frame are read (oldest
+/// first) and recycled, then mapping is requested for the buffers copied
+/// this frame. Nothing here blocks on the GPU.
 fn receive_image_from_buffer(
     image_copiers: Res<ImageCopiers>,
     render_device: Res<RenderDevice>,
     sender: Res<RenderWorldSender>,
+    buffer_pool: Res<BufferPoolRes>,
 ) {
-    for image_copier in image_copiers.iter() {
-        if !image_copier.enabled() {
+    // Let wgpu run the callbacks of any finished mappings without waiting
+    let _ = render_device.poll(PollType::Poll);
+
+    for extracted in image_copiers.iter() {
+        let Some(buffers) = extracted.copier.staging.get() else {
             continue;
+        };
+
+        let mut mapped: Vec<&StagingBuffer> = buffers
+            .iter()
+            .filter(|slot| slot.state() == SLOT_MAPPED)
+            .collect();
+        mapped.sort_by_key(|slot| slot.sequence());
+
+        for slot in mapped {
+            let data = {
+                let view = slot.buffer.slice(..).get_mapped_range();
+                let mut data = buffer_pool.0.take(view.len());
+                data.extend_from_slice(&view);
+                data
+            };
+            slot.buffer.unmap();
+            slot.set_state(SLOT_IDLE);
+            let _ = sender.send(CopiedImage {
+                copier: extracted.entity,
+                view: slot.view(),
+                data,
+            });
         }
 
-        let buffer_slice = image_copier.buffer.slice(..);
-
-        let (s, r) = crossbeam_channel::bounded(1);
-
-        buffer_slice.map_async(MapMode::Read, move |r| match r {
-            Ok(r) => s.send(r).expect("Failed to send map update"),
-            Err(err) => panic!("Failed to map buffer {err}"),
-        });
-
-        render_device
-            .poll(PollType::wait())
-            .expect("Failed to poll device for map async");
-
-        r.recv().expect("Failed to receive the map_async message");
-
-        let _ = sender.send(buffer_slice.get_mapped_range().to_vec());
-
-        image_copier.buffer.unmap();
+        for slot in buffers {
+            if slot.state() != SLOT_COPYING {
+                continue;
+            }
+            slot.set_state(SLOT_MAPPING);
+
+            let state = slot.state.clone();
+            slot.buffer
+                .slice(..)
+                .map_async(MapMode::Read, move |result| match result {
+                    Ok(()) => state.store(SLOT_MAPPED, Ordering::Release),
+                    Err(err) => {
+                        eprintln!("[ImageCopy] Failed to map staging buffer: {err}");
+                        state.store(SLOT_IDLE, Ordering::Release);
+                    }
+                });
+        }
     }
 }
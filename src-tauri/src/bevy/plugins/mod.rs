@@ -4,5 +4,32 @@
 //! functionality for our specific use case.
 
 pub mod image_copy;
+pub mod gpu_timing;
+pub mod gpu_memory;
+pub mod downscale;
+pub mod pixel_pack;
+pub mod debug_view;
+pub mod id_buffer;
+pub mod texture_share;
+pub mod model_formats;
+pub mod environment_map;
+pub mod shadow_catcher;
+pub mod color_lut;
+pub mod custom_shader;
+pub mod terrain;
+pub mod water;
 
 pub use image_copy::ImageCopyPlugin;
+pub use gpu_timing::GpuTimingPlugin;
+pub use gpu_memory::GpuMemoryPlugin;
+pub use downscale::DownscalePlugin;
+pub use debug_view::DebugViewPlugin;
+pub use id_buffer::IdBufferPlugin;
+pub use texture_share::TextureSharePlugin;
+pub use model_formats::ModelFormatsPlugin;
+pub use environment_map::EnvironmentMapPlugin;
+pub use shadow_catcher::ShadowCatcherPlugin;
+pub use color_lut::ColorLutPlugin;
+pub use custom_shader::CustomShaderPlugin;
+pub use terrain::TerrainPlugin;
+pub use water::WaterPlugin;
@@ -0,0 +1,218 @@
+//! OBJ, STL and point cloud asset loaders
+//!
+//! Bevy loads glTF itself; these turn OBJ and STL files into a single
+//! `Mesh`, so `load_model` can open the formats CAD tools and 3D printing
+//! workflows export. Materials aren't loaded: OBJ `.mtl` files are
+//! ignored and STL has none, the model gets a default material instead.
+//! XYZ and PTS point clouds from scanners become a point list mesh with
+//! the points' colors.
+
+use bevy::{
+    app::{App, Plugin},
+    asset::{io::Reader, AssetLoader, LoadContext, RenderAssetUsages},
+    color::{Color, ColorToComponents, LinearRgba},
+    mesh::{Indices, Mesh, PrimitiveTopology},
+};
+use std::io::{self, Cursor};
+
+pub struct ModelFormatsPlugin;
+
+impl Plugin for ModelFormatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset_loader::<ObjLoader>()
+            .init_asset_loader::<StlLoader>()
+            .init_asset_loader::<PointCloudLoader>();
+    }
+}
+
+/// Parse errors are reported as invalid data, with the parser's message
+fn invalid_data(error: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+// =============================================================================
+// OBJ
+// =============================================================================
+
+/// Loads all objects of an OBJ file as one triangulated mesh
+#[derive(Default)]
+pub struct ObjLoader;
+
+impl AssetLoader for ObjLoader {
+    type Asset = Mesh;
+    type Settings = ();
+    type Error = io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Mesh, io::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let (models, _materials) =
+            tobj::load_obj_buf(&mut Cursor::new(bytes), &tobj::GPU_LOAD_OPTIONS, |_| {
+                Err(tobj::LoadError::OpenFileFailed)
+            })
+            .map_err(invalid_data)?;
+
+        let mut positions: Vec<[f32; 3]> = Vec::new();
+        let mut normals: Vec<[f32; 3]> = Vec::new();
+        let mut uvs: Vec<[f32; 2]> = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+        // Normals and UVs are only kept if every object has them
+        let has_normals = models.iter().all(|model| !model.mesh.normals.is_empty());
+        let has_uvs = models.iter().all(|model| !model.mesh.texcoords.is_empty());
+        for model in &models {
+            let mesh = &model.mesh;
+            let offset = positions.len() as u32;
+            positions.extend(mesh.positions.chunks_exact(3).map(|p| [p[0], p[1], p[2]]));
+            if has_normals {
+                normals.extend(mesh.normals.chunks_exact(3).map(|n| [n[0], n[1], n[2]]));
+            }
+            if has_uvs {
+                // OBJ has V pointing up, Bevy down
+                uvs.extend(mesh.texcoords.chunks_exact(2).map(|t| [t[0], 1.0 - t[1]]));
+            }
+            indices.extend(mesh.indices.iter().map(|index| index + offset));
+        }
+        if indices.is_empty() {
+            return Err(invalid_data("no faces"));
+        }
+
+        let mut mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        );
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        if has_uvs {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        }
+        mesh.insert_indices(Indices::U32(indices));
+        if has_normals {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        } else {
+            mesh.duplicate_vertices();
+            mesh.compute_flat_normals();
+        }
+        Ok(mesh)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["obj"]
+    }
+}
+
+// =============================================================================
+// STL
+// =============================================================================
+
+/// Loads a binary or ASCII STL file as a flat-shaded mesh
+#[derive(Default)]
+pub struct StlLoader;
+
+impl AssetLoader for StlLoader {
+    type Asset = Mesh;
+    type Settings = ();
+    type Error = io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Mesh, io::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let stl = stl_io::read_stl(&mut Cursor::new(bytes))?;
+        if stl.faces.is_empty() {
+            return Err(invalid_data("no faces"));
+        }
+
+        // Each triangle gets its own vertices, so edges stay sharp
+        let mut positions: Vec<[f32; 3]> = Vec::with_capacity(stl.faces.len() * 3);
+        for face in &stl.faces {
+            for &index in &face.vertices {
+                let vertex = &stl.vertices[index];
+                positions.push([vertex[0], vertex[1], vertex[2]]);
+            }
+        }
+
+        let mut mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        );
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        // Stored normals are often zero or wrong, derive them from the
+        // winding instead
+        mesh.compute_flat_normals();
+        Ok(mesh)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["stl"]
+    }
+}
+
+// =============================================================================
+// Point clouds
+// =============================================================================
+
+/// Loads an ASCII XYZ or PTS point cloud as a point list mesh
+///
+/// Each line holds `x y z`, optionally followed by an intensity and 0-255
+/// `r g b`, which become vertex colors. Lines with fewer than three
+/// numbers, such as the point count PTS files start with, are skipped.
+#[derive(Default)]
+pub struct PointCloudLoader;
+
+impl AssetLoader for PointCloudLoader {
+    type Asset = Mesh;
+    type Settings = ();
+    type Error = io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Mesh, io::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let text = String::from_utf8(bytes).map_err(invalid_data)?;
+
+        let mut positions: Vec<[f32; 3]> = Vec::new();
+        let mut colors: Vec<[f32; 4]> = Vec::new();
+        let mut values = Vec::new();
+        for line in text.lines() {
+            values.clear();
+            values.extend(
+                line.split_whitespace()
+                    .map_while(|value| value.parse::<f32>().ok()),
+            );
+            let [x, y, z, ..] = values[..] else {
+                continue;
+            };
+            positions.push([x, y, z]);
+            // The last three values, after an intensity in PTS files
+            let color = match values[3..] {
+                [.., r, g, b] => Color::srgb_u8(r as u8, g as u8, b as u8),
+                _ => Color::WHITE,
+            };
+            colors.push(LinearRgba::from(color).to_f32_array());
+        }
+        if positions.is_empty() {
+            return Err(invalid_data("no points"));
+        }
+
+        let mut mesh = Mesh::new(PrimitiveTopology::PointList, RenderAssetUsages::default());
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        Ok(mesh)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["xyz", "pts"]
+    }
+}
@@ -0,0 +1,218 @@
+//! GPU-side pixel packing before readback
+//!
+//! The JPEG encoder wants tightly packed RGB, but render targets are RGBA
+//! (or BGRA on some backends). Instead of converting every frame on the CPU,
+//! a small compute pass swizzles the texture to RGBA order and optionally
+//! drops alpha or reduces it to RGB565, writing the result into a storage
+//! buffer that is then copied into the staging ring. The packed buffer has
+//! no row padding either, and may cover only a region of the texture.
+
+use bevy::{
+    image::Image,
+    prelude::*,
+    render::{
+        render_resource::{Buffer, BufferInitDescriptor, BufferUsages},
+        renderer::RenderDevice,
+        texture::GpuImage,
+    },
+};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BufferBindingType, ComputePass,
+    ComputePipeline, ComputePipelineDescriptor, PipelineLayoutDescriptor,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages, TextureFormat, TextureSampleType,
+    TextureViewDescriptor, TextureViewDimension,
+};
+
+use crate::tauri_bridge::shared_state::PixelFormat;
+
+/// Invocations per workgroup, must match `@workgroup_size` in the shader
+const WORKGROUP_SIZE: u32 = 64;
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Whether frames of `source` format must go through the pack pass
+///
+/// `cropped` is set when only a region of the texture is read back.
+pub fn needs_pack(source: TextureFormat, format: PixelFormat, cropped: bool) -> bool {
+    cropped
+        || format != PixelFormat::Rgba8
+        || matches!(
+            source,
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+        )
+}
+
+/// Size of the packed output, rounded up to whole shader invocations
+pub fn packed_size(width: u32, height: u32, format: PixelFormat) -> u64 {
+    let pixels = width as u64 * height as u64;
+    match format {
+        PixelFormat::Rgba8 => pixels * 4,
+        // Every invocation writes four pixels as three words
+        PixelFormat::Rgb8 => pixels.div_ceil(4) * 12,
+        // Every invocation writes two pixels as one word
+        PixelFormat::Rgb565 => pixels.div_ceil(2) * 4,
+    }
+}
+
+/// Allow `image` to be viewed without sRGB encoding
+///
+/// The pack pass reads through such a view so the stored bytes come back
+/// unchanged instead of being decoded to linear values.
+pub fn allow_raw_view(image: &mut Image) {
+    image.texture_descriptor.view_formats = match image.texture_descriptor.format {
+        TextureFormat::Rgba8UnormSrgb => &[TextureFormat::Rgba8Unorm],
+        TextureFormat::Bgra8UnormSrgb => &[TextureFormat::Bgra8Unorm],
+        _ => &[],
+    };
+}
+
+// =============================================================================
+// Pipeline (Render World)
+// =============================================================================
+
+/// Compute pipelines packing a texture into RGBA, RGB or RGB565 words
+#[derive(Resource)]
+pub struct PixelPackPipeline {
+    rgba: ComputePipeline,
+    rgb: ComputePipeline,
+    rgb565: ComputePipeline,
+    bind_group_layout: BindGroupLayout,
+}
+
+impl PixelPackPipeline {
+    pub fn new(render_device: &RenderDevice) -> Self {
+        let device = render_device.wgpu_device();
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("pixel_pack_shader"),
+            source: ShaderSource::Wgsl(include_str!("../shaders/pixel_pack.wgsl").into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("pixel_pack_bind_group_layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("pixel_pack_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        Self {
+            rgba: pipeline("pack_rgba"),
+            rgb: pipeline("pack_rgb"),
+            rgb565: pipeline("pack_rgb565"),
+            bind_group_layout,
+        }
+    }
+
+    /// Record a dispatch packing `region` of `source` into `output` as
+    /// `format`
+    ///
+    /// All copiers share one compute pass, so their dispatches are submitted
+    /// together and may overlap on the GPU.
+    pub fn record(
+        &self,
+        render_device: &RenderDevice,
+        pass: &mut ComputePass,
+        source: &GpuImage,
+        region: URect,
+        output: &Buffer,
+        format: PixelFormat,
+    ) {
+        let view = source.texture.create_view(&TextureViewDescriptor {
+            label: Some("pixel_pack_source_view"),
+            format: Some(source.texture_format.remove_srgb_suffix()),
+            ..Default::default()
+        });
+
+        let size = region.size();
+        let region_data: Vec<u8> = [region.min.x, region.min.y, size.x, size.y]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let region_buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("pixel_pack_region"),
+            contents: &region_data,
+            usage: BufferUsages::UNIFORM,
+        });
+
+        let bind_group = render_device
+            .wgpu_device()
+            .create_bind_group(&BindGroupDescriptor {
+                label: Some("pixel_pack_bind_group"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(&*view),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: output.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: region_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+
+        let pixels = size.x * size.y;
+        let (pipeline, invocations) = match format {
+            PixelFormat::Rgba8 => (&self.rgba, pixels),
+            PixelFormat::Rgb8 => (&self.rgb, pixels.div_ceil(4)),
+            PixelFormat::Rgb565 => (&self.rgb565, pixels.div_ceil(2)),
+        };
+
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(invocations.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+}
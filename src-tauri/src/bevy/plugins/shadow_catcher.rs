@@ -0,0 +1,65 @@
+//! Shadow catcher material
+//!
+//! A ground for product shots: the surface itself is invisible and only
+//! the shadows falling on it are drawn, blended over whatever is behind
+//! it, the clear color, a skybox or a transparent background.
+
+use bevy::{
+    app::{App, Plugin},
+    asset::uuid_handle,
+    pbr::{ExtendedMaterial, MaterialExtension, MaterialPlugin},
+    prelude::*,
+    render::render_resource::AsBindGroup,
+    shader::{Shader, ShaderRef},
+};
+
+const SHADER_HANDLE: Handle<Shader> = uuid_handle!("b2e7c4a1-5d3f-4e8b-9a6c-1f0d7e3b5c92");
+
+/// Standard material drawn as a shadow catcher, the base gives the
+/// surface's lighting response
+pub type ShadowCatcherMaterial = ExtendedMaterial<StandardMaterial, ShadowCatcher>;
+
+pub struct ShadowCatcherPlugin;
+
+impl Plugin for ShadowCatcherPlugin {
+    fn build(&self, app: &mut App) {
+        let shader = Shader::from_wgsl(
+            include_str!("../shaders/shadow_catcher.wgsl"),
+            "bevy/shaders/shadow_catcher.wgsl",
+        );
+        let _ = app
+            .world_mut()
+            .resource_mut::<Assets<Shader>>()
+            .insert(SHADER_HANDLE.id(), shader);
+        app.add_plugins(MaterialPlugin::<ShadowCatcherMaterial>::default());
+    }
+}
+
+/// Extension drawing only shadows, see `shadow_catcher.wgsl`
+#[derive(Asset, AsBindGroup, Reflect, Clone, Debug)]
+pub struct ShadowCatcher {
+    /// Shadow tint, alpha is the opacity of a full shadow
+    #[uniform(100)]
+    pub color: LinearRgba,
+}
+
+impl MaterialExtension for ShadowCatcher {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_HANDLE.into()
+    }
+}
+
+/// Shadow catcher with shadows of `opacity`, blended over the background
+pub fn shadow_catcher_material(opacity: f32) -> ShadowCatcherMaterial {
+    ExtendedMaterial {
+        base: StandardMaterial {
+            perceptual_roughness: 1.0,
+            reflectance: 0.0,
+            alpha_mode: AlphaMode::Blend,
+            ..default()
+        },
+        extension: ShadowCatcher {
+            color: LinearRgba::new(0.0, 0.0, 0.0, opacity),
+        },
+    }
+}
@@ -0,0 +1,72 @@
+//! Terrain splat material
+//!
+//! Terrain meshes carry splat weights for four ground layers (sand, grass,
+//! rock and snow) in their vertex colors. The material blends the layers'
+//! tiling textures, stored as one array texture, by those weights, and
+//! lights the result like any standard material.
+
+use bevy::{
+    app::{App, Plugin},
+    asset::uuid_handle,
+    pbr::{ExtendedMaterial, MaterialExtension, MaterialPlugin},
+    prelude::*,
+    render::render_resource::AsBindGroup,
+    shader::{Shader, ShaderRef},
+};
+
+use crate::config::terrain::TEXTURE_TILE;
+
+const SHADER_HANDLE: Handle<Shader> = uuid_handle!("6f1d8b2e-4c7a-4e95-b3d0-8a5e2c19f764");
+
+/// Standard material textured by splatting, the base gives the surface's
+/// lighting response
+pub type TerrainMaterial = ExtendedMaterial<StandardMaterial, TerrainSplat>;
+
+pub struct TerrainPlugin;
+
+impl Plugin for TerrainPlugin {
+    fn build(&self, app: &mut App) {
+        let shader = Shader::from_wgsl(
+            include_str!("../shaders/terrain.wgsl"),
+            "bevy/shaders/terrain.wgsl",
+        );
+        let _ = app
+            .world_mut()
+            .resource_mut::<Assets<Shader>>()
+            .insert(SHADER_HANDLE.id(), shader);
+        app.add_plugins(MaterialPlugin::<TerrainMaterial>::default());
+    }
+}
+
+/// Extension blending the splat layers, see `terrain.wgsl`
+#[derive(Asset, AsBindGroup, Reflect, Clone, Debug)]
+pub struct TerrainSplat {
+    /// Sand, grass, rock and snow, in the order of the splat weights
+    #[texture(100, dimension = "2d_array")]
+    #[sampler(101)]
+    pub layers: Handle<Image>,
+    /// Layer texture repeats per world unit
+    #[uniform(102)]
+    pub tiling: f32,
+}
+
+impl MaterialExtension for TerrainSplat {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_HANDLE.into()
+    }
+}
+
+/// Terrain material splatting the array texture `layers`
+pub fn terrain_material(layers: Handle<Image>) -> TerrainMaterial {
+    ExtendedMaterial {
+        base: StandardMaterial {
+            perceptual_roughness: 0.9,
+            reflectance: 0.2,
+            ..default()
+        },
+        extension: TerrainSplat {
+            layers,
+            tiling: 1.0 / TEXTURE_TILE,
+        },
+    }
+}
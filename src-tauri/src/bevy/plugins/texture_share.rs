@@ -0,0 +1,156 @@
+//! GPU texture sharing before readback
+//!
+//! This plugin gives registered [`TextureShare`]s the stream texture in
+//! the render graph, after the camera and downscale passes and before
+//! readback, to record a copy into a texture they export to another
+//! process without going through the CPU. No share is built in: exporting
+//! needs a platform SDK, which implementations bring through their
+//! bindings, reaching the native texture with wgpu's `as_hal`.
+
+use bevy::{
+    app::{App, Plugin},
+    prelude::*,
+    render::{
+        render_asset::RenderAssets,
+        render_graph::{self, NodeRunError, RenderGraph, RenderGraphContext, RenderLabel},
+        renderer::RenderContext,
+        texture::GpuImage,
+        Extract, ExtractSchedule, RenderApp,
+    },
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tracing::info_span;
+
+use crate::bevy::plugins::downscale::Downscale;
+use crate::bevy::plugins::image_copy::ImageCopy;
+use crate::bevy::resources::StreamTarget;
+
+/// Destination of the stream texture on the GPU, e.g. a sender built on
+/// a platform's texture sharing SDK
+pub trait TextureShare: Send + Sync + 'static {
+    /// Short name for log messages
+    fn name(&self) -> &str;
+
+    /// Record commands sharing `texture` into the render graph's encoder
+    fn share(&self, render_context: &mut RenderContext, texture: &GpuImage) -> Result<(), String>;
+}
+
+// =============================================================================
+// Plugin Definition
+// =============================================================================
+
+pub struct TextureSharePlugin {
+    /// Taken when the plugin is built
+    shares: Mutex<Vec<Box<dyn TextureShare>>>,
+}
+
+impl TextureSharePlugin {
+    pub fn new(shares: Vec<Box<dyn TextureShare>>) -> Self {
+        Self {
+            shares: Mutex::new(shares),
+        }
+    }
+}
+
+impl Plugin for TextureSharePlugin {
+    fn build(&self, app: &mut App) {
+        let shares: Vec<RegisteredShare> = self
+            .shares
+            .lock()
+            .map(|mut shares| std::mem::take(&mut *shares))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|share| RegisteredShare {
+                share,
+                failing: AtomicBool::new(false),
+            })
+            .collect();
+        if shares.is_empty() {
+            return;
+        }
+        let names: Vec<&str> = shares.iter().map(|shared| shared.share.name()).collect();
+        println!(
+            "[Bevy] Sharing the stream texture with {}",
+            names.join(", ")
+        );
+
+        let render_app = app.sub_app_mut(RenderApp);
+
+        // Camera pass -> downscale -> share, before the stream is read back
+        let mut graph = render_app.world_mut().resource_mut::<RenderGraph>();
+        graph.add_node(TextureShareLabel, TextureShareDriver);
+        graph.add_node_edge(bevy::render::graph::CameraDriverLabel, TextureShareLabel);
+        graph.add_node_edge(Downscale, TextureShareLabel);
+        graph.add_node_edge(TextureShareLabel, ImageCopy);
+
+        render_app
+            .insert_resource(TextureShares(shares))
+            .init_resource::<SharedTexture>()
+            .add_systems(ExtractSchedule, texture_share_extract);
+    }
+}
+
+struct RegisteredShare {
+    share: Box<dyn TextureShare>,
+    /// Whether the last frame failed, so errors are logged once
+    failing: AtomicBool,
+}
+
+/// Registered texture shares (render world)
+#[derive(Resource)]
+struct TextureShares(Vec<RegisteredShare>);
+
+/// Stream texture of this frame (render world)
+#[derive(Resource, Default)]
+struct SharedTexture(Option<Handle<Image>>);
+
+fn texture_share_extract(
+    mut shared: ResMut<SharedTexture>,
+    target: Extract<Option<Res<StreamTarget>>>,
+) {
+    shared.0 = target.as_ref().map(|target| target.image.clone());
+}
+
+// =============================================================================
+// Render Graph Node
+// =============================================================================
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash, RenderLabel)]
+struct TextureShareLabel;
+
+#[derive(Default)]
+struct TextureShareDriver;
+
+impl render_graph::Node for TextureShareDriver {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let (Some(shares), Some(SharedTexture(Some(image)))) = (
+            world.get_resource::<TextureShares>(),
+            world.get_resource::<SharedTexture>(),
+        ) else {
+            return Ok(());
+        };
+        // Not uploaded yet right after a resolution change
+        let Some(texture) = world.resource::<RenderAssets<GpuImage>>().get(image) else {
+            return Ok(());
+        };
+
+        for registered in &shares.0 {
+            let name = registered.share.name();
+            let _span = info_span!("texture_share", share = name).entered();
+            let result = registered.share.share(render_context, texture);
+            let was_failing = registered.failing.swap(result.is_err(), Ordering::Relaxed);
+            match result {
+                Err(e) if !was_failing => eprintln!("[Bevy] Sharing with {} failed: {}", name, e),
+                Ok(()) if was_failing => println!("[Bevy] Sharing with {} recovered", name),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
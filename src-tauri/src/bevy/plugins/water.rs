@@ -0,0 +1,78 @@
+//! Water material
+//!
+//! Animated water with planar reflections: a second camera renders the
+//! scene mirrored in the water plane into a texture each frame, before the
+//! stream camera, and the water samples it at its own screen position,
+//! bent by the waves and blended with the lit surface by the Fresnel term.
+
+use bevy::{
+    app::{App, Plugin},
+    asset::uuid_handle,
+    pbr::{ExtendedMaterial, MaterialExtension, MaterialPlugin},
+    prelude::*,
+    render::render_resource::AsBindGroup,
+    shader::{Shader, ShaderRef},
+};
+
+use crate::config::water::{WAVELENGTH, WAVE_SPEED};
+
+const SHADER_HANDLE: Handle<Shader> = uuid_handle!("d4a97c30-1b6e-4f82-9c5d-3e70a81b26f9");
+
+/// Standard material drawn as water, the base gives the surface's tint
+/// and lighting response
+pub type WaterMaterial = ExtendedMaterial<StandardMaterial, Water>;
+
+pub struct WaterPlugin;
+
+impl Plugin for WaterPlugin {
+    fn build(&self, app: &mut App) {
+        let shader = Shader::from_wgsl(
+            include_str!("../shaders/water.wgsl"),
+            "bevy/shaders/water.wgsl",
+        );
+        let _ = app
+            .world_mut()
+            .resource_mut::<Assets<Shader>>()
+            .insert(SHADER_HANDLE.id(), shader);
+        app.add_plugins(MaterialPlugin::<WaterMaterial>::default());
+    }
+}
+
+/// Extension adding waves and the reflection, see `water.wgsl`
+#[derive(Asset, AsBindGroup, Reflect, Clone, Debug)]
+pub struct Water {
+    /// Target of the reflection camera
+    #[texture(100)]
+    #[sampler(101)]
+    pub reflection: Handle<Image>,
+    /// Wave strength, wavelength and speed, the last component unused
+    #[uniform(102)]
+    pub waves: Vec4,
+}
+
+impl MaterialExtension for Water {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_HANDLE.into()
+    }
+}
+
+/// Water tinted `color`, with waves of `wave_strength`, showing the
+/// reflection rendered into `reflection`
+pub fn water_material(
+    color: Color,
+    wave_strength: f32,
+    reflection: Handle<Image>,
+) -> WaterMaterial {
+    ExtendedMaterial {
+        base: StandardMaterial {
+            base_color: color,
+            perceptual_roughness: 0.08,
+            reflectance: 0.5,
+            ..default()
+        },
+        extension: Water {
+            reflection,
+            waves: Vec4::new(wave_strength, WAVELENGTH, WAVE_SPEED, 0.0),
+        },
+    }
+}
@@ -3,19 +3,48 @@
 //! This module contains all global resources used by Bevy systems.
 //! Resources are singleton data that can be accessed by any system.
 
+use bevy::asset::{LoadedUntypedAsset, UntypedAssetId};
 use bevy::prelude::*;
-use std::time::Duration;
+use bevy::tasks::Task;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::f32::consts::{PI, TAU};
+use std::path::PathBuf;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use std::time::{Duration, Instant};
 
+use crate::bevy::plugins::custom_shader::CustomShaderMaterial;
+use crate::bevy::plugins::debug_view::DebugViewMaterial;
+use crate::config::{
+    camera::TRANSITION_SECONDS,
+    history::DEPTH as HISTORY_DEPTH,
+    performance::{FRAME_INTERVAL_SAMPLES, FRAME_TIMING_SAMPLES},
+    readback::{PIPELINE_MEMORY_LIMIT_MB, PIXEL_FORMAT},
+    stream::{STREAM_HEIGHT, STREAM_WIDTH},
+    terrain::DEFAULT_SEED as TERRAIN_SEED,
+    RENDER_HEIGHT, RENDER_WIDTH, TARGET_FPS,
+};
+use crate::tauri_bridge::scene_sync::SceneSync;
 use crate::tauri_bridge::shared_state::{
-    SharedFrameBuffer, SharedMouseInput, SharedPerfStats,
+    AssetProgress, BridgeCommand, CameraEasing, CameraKeyframe, CameraPathEnd, CameraPresetMap,
+    CameraSensitivity, CameraTransitionSettings, CameraView, DebugView, FogSettings, FrameMetadata,
+    FrameStages, InputRecording, InputSample, LightingPreset, ModelLoadProgress, OrbitPose,
+    PickedEntity, PixelFormat, PrefetchAsset, PrefetchKind, ReadbackCheckResult, ReadbackSelfTest,
+    RegionOfInterest, SampleWindow, SceneObject, SharedBufferPool, SharedCommandQueue,
+    SharedEncodedFrame, SharedFetchStats, SharedFrameBuffer, SharedFrameSignal, SharedGpuErrors,
+    SharedGpuInfo, SharedMouseInput, SharedPerfStats, SharedRendererHealth, SharedStatsHistory,
+    SlowFrameAlert, SnapSettings, UiOverlaySettings,
 };
+use crate::tauri_bridge::shared_surface::SharedSurface;
 
 // =============================================================================
 // Camera Control
 // =============================================================================
 
 /// Orbit camera state for spherical coordinate camera control
-#[derive(Resource)]
+#[derive(Resource, Clone, Copy)]
 pub struct OrbitCameraState {
     /// Horizontal rotation angle (radians)
     pub yaw: f32,
@@ -23,6 +52,8 @@ pub struct OrbitCameraState {
     pub pitch: f32,
     /// Distance from the camera to the center point
     pub distance: f32,
+    /// Rotation around the viewing direction (radians), in `-PI..PI`
+    pub roll: f32,
     /// The point the camera orbits around
     pub center: Vec3,
 }
@@ -33,27 +64,363 @@ impl Default for OrbitCameraState {
             yaw: 0.0,
             pitch: 0.4, // Slight downward angle
             distance: 6.5,
+            roll: 0.0,
             center: Vec3::ZERO,
         }
     }
 }
 
+impl OrbitCameraState {
+    pub fn pose(&self) -> OrbitPose {
+        OrbitPose {
+            yaw: self.yaw,
+            pitch: self.pitch,
+            distance: self.distance,
+            roll: self.roll,
+        }
+    }
+
+    pub fn set_pose(&mut self, pose: OrbitPose) {
+        self.yaw = pose.yaw;
+        self.pitch = pose.pitch;
+        self.distance = pose.distance;
+        self.roll = pose.roll;
+    }
+
+    /// Camera transform looking at `center` from the orbit position
+    ///
+    /// Built from the angles directly rather than with `looking_at`, so it
+    /// stays well defined looking straight down or up.
+    pub fn camera_transform(&self) -> Transform {
+        // yaw: rotation around Y axis
+        // pitch: rotation around X axis (elevation)
+        // roll: rotation around the viewing direction
+        let rotation = Quat::from_euler(EulerRot::YXZ, self.yaw, -self.pitch, self.roll);
+        let offset = rotation * Vec3::new(0.0, 0.0, self.distance);
+        Transform::from_translation(self.center + offset).with_rotation(rotation)
+    }
+
+    /// Snapshot of the orbit as a serializable view
+    pub fn view(&self) -> CameraView {
+        CameraView {
+            pose: self.pose(),
+            center: Some(self.center.to_array()),
+        }
+    }
+
+    /// Move to `view`, keeping the current center if it has none
+    pub fn set_view(&mut self, view: CameraView) {
+        self.set_pose(view.pose);
+        if let Some(center) = view.center {
+            self.center = Vec3::from(center);
+        }
+    }
+
+    /// Orbit around `target` with the camera at `position`
+    pub fn looking_from(position: Vec3, target: Vec3) -> Self {
+        let offset = position - target;
+        let distance = offset.length();
+        let pitch = if distance > 0.0 {
+            (offset.y / distance).clamp(-1.0, 1.0).asin()
+        } else {
+            0.0
+        };
+        OrbitCameraState {
+            yaw: offset.x.atan2(offset.z),
+            pitch,
+            distance,
+            roll: 0.0,
+            center: target,
+        }
+    }
+
+    /// Orbit `t` of the way from `self` to `to`, `t` in `0..=1`
+    ///
+    /// Yaw and roll turn the short way around.
+    pub fn lerp(&self, to: &OrbitCameraState, t: f32) -> OrbitCameraState {
+        let yaw_delta = (to.yaw - self.yaw + PI).rem_euclid(TAU) - PI;
+        let roll_delta = (to.roll - self.roll + PI).rem_euclid(TAU) - PI;
+        OrbitCameraState {
+            yaw: self.yaw + yaw_delta * t,
+            pitch: self.pitch + (to.pitch - self.pitch) * t,
+            distance: self.distance + (to.distance - self.distance) * t,
+            roll: self.roll + roll_delta * t,
+            center: self.center.lerp(to.center, t),
+        }
+    }
+}
+
+/// Mouse camera speeds set with `set_camera_sensitivity`
+#[derive(Resource, Default)]
+pub struct CameraSensitivityRes(pub CameraSensitivity);
+
+/// Named camera views to switch between, see `save_camera_preset`
+#[derive(Resource, Default)]
+pub struct CameraPresets(pub CameraPresetMap);
+
+/// Assets listed in the open project and their paths, kept loaded while
+/// it is open
+#[derive(Resource, Default)]
+pub struct ProjectAssets(pub Vec<(String, Handle<LoadedUntypedAsset>)>);
+
+/// Folders outside the assets folder models may be opened from, see
+/// `model_loading::approve_model_dir`
+#[derive(Resource, Default)]
+pub struct ApprovedModelDirs(pub Vec<PathBuf>);
+
+/// Objects of the open project's model, placed once `load_model` loaded
+/// `model`
+#[derive(Resource)]
+pub struct ProjectModelObjects {
+    pub model: PathBuf,
+    pub objects: Vec<SceneObject>,
+}
+
+/// Model being loaded by `load_model`, see `model_loading`
+#[derive(Resource)]
+pub struct ModelLoad {
+    /// Path as the frontend gave it, for progress events
+    pub path: String,
+    /// Path sent to other instances once loaded
+    pub file: PathBuf,
+    /// Instance and edit the load was sent by, `None` for loads made here
+    pub remote: Option<(u64, Option<u64>)>,
+    /// Models and terrain the model replaced and demo cubes it hid, recorded
+    /// with it once it is loaded
+    pub replaced: Vec<EditChange>,
+    pub asset: ModelAsset,
+    /// Whether a file opened before is being read again, its asset still
+    /// holding the old contents until it is modified
+    pub reloading: bool,
+    pub started: Instant,
+    /// When the last `loading` event was sent
+    pub reported: Instant,
+    pub progress: Sender<ModelLoadProgress>,
+    /// Bounds of a glTF model read from its header off the Bevy thread,
+    /// `None` once read
+    pub bounds: Option<Task<Option<(Vec3, Vec3)>>>,
+    /// Box standing in for a glTF model until all of it shows
+    pub proxy: Option<Entity>,
+    /// Set once the model is spawned and its meshes are being shown
+    pub reveal: Option<ModelReveal>,
+}
+
+/// Meshes of a spawned glTF model still hidden, shown a chunk per frame so
+/// large models don't stall one frame
+pub struct ModelReveal {
+    pub root: Entity,
+    /// Meshes to show with the visibility they were spawned with, filled
+    /// once the scene is spawned
+    pub hidden: VecDeque<(Entity, Visibility)>,
+    pub meshes: usize,
+    /// Whether `SceneInstanceReady` arrived for `root`
+    pub ready: bool,
+    /// Whether the scene was spawned and its meshes hidden
+    pub started: bool,
+}
+
+/// What a model file loads as
+pub enum ModelAsset {
+    /// glTF, whose first scene is spawned
+    Scene(Handle<Scene>),
+    /// OBJ or STL, a single mesh given a default material
+    Mesh(Handle<Mesh>),
+}
+
+impl ModelAsset {
+    pub fn id(&self) -> UntypedAssetId {
+        match self {
+            ModelAsset::Scene(handle) => handle.id().untyped(),
+            ModelAsset::Mesh(handle) => handle.id().untyped(),
+        }
+    }
+
+    pub fn untyped(&self) -> UntypedHandle {
+        match self {
+            ModelAsset::Scene(handle) => handle.clone().untyped(),
+            ModelAsset::Mesh(handle) => handle.clone().untyped(),
+        }
+    }
+}
+
+/// Animated move of the orbit camera, to a preset, entity, standard view
+/// or picked point
+///
+/// Live input cancels it.
+#[derive(Resource)]
+pub struct CameraTransition {
+    pub from: OrbitCameraState,
+    pub to: OrbitCameraState,
+    /// Seconds since startup the move started at
+    pub start_s: f64,
+    pub duration_s: f64,
+    pub easing: CameraEasing,
+}
+
+/// Duration and easing of the next [`CameraTransition`]s
+#[derive(Resource)]
+pub struct CameraTransitionStyle(pub CameraTransitionSettings);
+
+impl Default for CameraTransitionStyle {
+    fn default() -> Self {
+        Self(CameraTransitionSettings {
+            duration_s: TRANSITION_SECONDS,
+            easing: CameraEasing::default(),
+        })
+    }
+}
+
+/// Running camera shake, see `camera_effects`
+#[derive(Resource)]
+pub struct CameraShake {
+    /// Peak rotation at the start (radians)
+    pub amplitude: f32,
+    pub frequency_hz: f32,
+    /// Exponential decay rate per second, `0` for a constant shake
+    pub decay: f32,
+    /// Seconds since startup the shake started at
+    pub start_s: f64,
+}
+
+/// Entity the orbit camera stays centered on as it moves, see
+/// `look_at_entity`
+///
+/// Dropped when the entity is despawned, a preset is applied or a camera
+/// path starts.
+#[derive(Resource)]
+pub struct CameraFocus(pub Entity);
+
+/// Camera flythrough being played, see `camera_path`
+#[derive(Resource)]
+pub struct CameraPath {
+    /// At least two, in time order
+    pub keyframes: Vec<CameraKeyframe>,
+    /// Current position on the path in seconds
+    pub time_s: f64,
+    pub paused: bool,
+    pub finished: Sender<CameraPathEnd>,
+}
+
 /// Resource to hold shared mouse input in Bevy
 #[derive(Resource)]
 pub struct MouseInputRes(pub SharedMouseInput);
 
+/// Input applied to the camera since `start_time_s`, while recording
+#[derive(Resource)]
+pub struct InputRecorder {
+    pub start_time_s: f64,
+    pub start_orbit: OrbitPose,
+    pub samples: Vec<InputSample>,
+    pub sensitivity: CameraSensitivity,
+}
+
+/// Recorded input driving the camera instead of live input
+#[derive(Resource)]
+pub struct InputReplay {
+    pub recording: InputRecording,
+    /// Index of the first sample not applied yet
+    pub next_sample: usize,
+}
+
+// =============================================================================
+// Scene Time
+// =============================================================================
+
+/// Seconds of animation played so far
+///
+/// Advances only while frames are streamed (not during pre-roll), and by a
+/// fixed step per frame in deterministic mode, so animation and recorded
+/// input can be lined up again in a replay.
+#[derive(Resource, Default)]
+pub struct SceneTime(pub f64);
+
+/// Frame rate of deterministic mode, `None` while time follows the clock
+///
+/// Changed together with Bevy's `TimeUpdateStrategy`, see
+/// `set_fixed_timestep`.
+#[derive(Resource, Default, Clone, Copy)]
+pub struct FixedTimestep(pub Option<f64>);
+
 // =============================================================================
 // Rendering
 // =============================================================================
 
+/// Size and anti-aliasing of the offscreen render target
+#[derive(Resource, Clone, Copy)]
+pub struct RenderSettings {
+    pub width: u32,
+    pub height: u32,
+    pub msaa: Msaa,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            width: RENDER_WIDTH,
+            height: RENDER_HEIGHT,
+            msaa: Msaa::default(),
+        }
+    }
+}
+
 /// Handle to the offscreen render target texture
 #[derive(Resource)]
 pub struct RenderTargetHandle(pub Handle<Image>);
 
+/// Resolution frames are streamed at
+///
+/// When smaller than the render target, the render target is downscaled on
+/// the GPU before readback.
+#[derive(Resource, Clone, Copy, PartialEq, Eq)]
+pub struct StreamResolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for StreamResolution {
+    fn default() -> Self {
+        Self {
+            width: STREAM_WIDTH,
+            height: STREAM_HEIGHT,
+        }
+    }
+}
+
+/// Pixel layout frames are read back in
+#[derive(Resource, Clone, Copy)]
+pub struct StreamPixelFormat(pub PixelFormat);
+
+impl Default for StreamPixelFormat {
+    fn default() -> Self {
+        Self(PIXEL_FORMAT)
+    }
+}
+
+/// Part of the stream target that is read back, `None` for all of it
+#[derive(Resource, Clone, Copy, Default)]
+pub struct StreamRegion(pub Option<RegionOfInterest>);
+
+/// Texture that is read back and streamed, and the copier reading it
+///
+/// This is the render target itself when no downscaling is needed.
+#[derive(Resource)]
+pub struct StreamTarget {
+    pub image: Handle<Image>,
+    pub copier: Entity,
+}
+
 /// Shared frame buffer resource for Bevy
 #[derive(Resource, Clone)]
 pub struct FrameBufferRes(pub SharedFrameBuffer);
 
+/// Signal used to wake the encoder thread when a frame is published
+#[derive(Resource, Clone)]
+pub struct FrameSignalRes(pub SharedFrameSignal);
+
+/// Shared buffer pool, present in both the main and the render world
+#[derive(Resource, Clone)]
+pub struct BufferPoolRes(pub SharedBufferPool);
+
 // =============================================================================
 // Frame Management
 // =============================================================================
@@ -66,25 +433,35 @@ pub struct FrameCount(pub u32);
 #[derive(Resource, Default)]
 pub struct PreRollFrames(pub u32);
 
-/// Frame rate limiter to control output FPS
+/// Frame rate target that follows consumer demand
+///
+/// Read by the app runner every frame to decide how long to wait between
+/// updates, and lowered when clients fetch fewer frames than are rendered.
 #[derive(Resource)]
-pub struct FrameRateLimiter {
-    pub last_frame_time: std::time::Instant,
-    pub min_frame_interval: Duration,
+pub struct AdaptiveFrameRate {
+    pub target_fps: f64,
+    /// Time of the last demand evaluation (seconds since startup)
+    pub last_evaluation: f64,
+    /// Client fetch count at the last evaluation
+    pub last_fetch_count: u64,
+    /// Published frame count at the last evaluation
+    pub last_frame_count: u32,
 }
 
-impl FrameRateLimiter {
-    pub fn new(target_fps: f64) -> Self {
-        Self {
-            last_frame_time: std::time::Instant::now(),
-            min_frame_interval: Duration::from_secs_f64(1.0 / target_fps),
-        }
+impl AdaptiveFrameRate {
+    pub fn frame_interval(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.target_fps)
     }
 }
 
-impl Default for FrameRateLimiter {
+impl Default for AdaptiveFrameRate {
     fn default() -> Self {
-        Self::new(60.0) // Default to 60 FPS
+        Self {
+            target_fps: TARGET_FPS,
+            last_evaluation: 0.0,
+            last_fetch_count: 0,
+            last_frame_count: 0,
+        }
     }
 }
 
@@ -93,26 +470,480 @@ impl Default for FrameRateLimiter {
 // =============================================================================
 
 /// Performance timing tracker for frame processing
-#[derive(Resource, Default)]
+#[derive(Resource)]
 pub struct FrameTimings {
     pub last_print_time: f64,
-    pub frame_times: Vec<f64>,
+    /// Time spent processing each published frame (ms)
+    pub frame_times: SampleWindow,
+    /// Time between consecutive published frames (ms)
+    pub frame_intervals: SampleWindow,
+    pub last_publish: Option<Instant>,
+}
+
+impl Default for FrameTimings {
+    fn default() -> Self {
+        Self {
+            last_print_time: 0.0,
+            frame_times: SampleWindow::new(FRAME_TIMING_SAMPLES),
+            frame_intervals: SampleWindow::new(FRAME_INTERVAL_SAMPLES),
+            last_publish: None,
+        }
+    }
+}
+
+/// When the current main world frame started, for slow frame detection
+#[derive(Resource, Default)]
+pub struct FrameStart(pub Option<Instant>);
+
+/// Slow frame watch set up by `watch_slow_frames`
+#[derive(Resource)]
+pub struct SlowFrameWatch {
+    pub threshold_ms: f64,
+    /// Consecutive slow frames that trigger an alert
+    pub frames: u32,
+    /// Current run of slow frames
+    pub streak: u32,
+    /// Stage times summed over the current run
+    pub streak_stages: FrameStages,
+    /// Whether the current run was already reported
+    pub alerted: bool,
+    pub alerts: Sender<SlowFrameAlert>,
 }
 
+/// Environments being loaded for `set_environment_map` and
+/// `set_reflection_probes`, see `track_environment_loads`
+#[derive(Resource, Default)]
+pub struct EnvironmentLoads(pub Vec<EnvironmentLoad>);
+
+/// Environments one command waits for
+pub struct EnvironmentLoad {
+    pub environments: Vec<Handle<Image>>,
+    pub reply: Sender<Result<(), String>>,
+}
+
+/// LUTs being loaded for `set_color_lut` and the replies waiting for
+/// them, see `track_color_lut_loads`
+#[derive(Resource, Default)]
+pub struct ColorLutLoads(pub Vec<(Handle<Image>, Sender<Result<(), String>>)>);
+
+/// Where `report_asset_progress` sends progress and the assets it counts
+#[derive(Resource)]
+pub struct AssetProgressWatch {
+    pub updates: Sender<AssetProgress>,
+    /// Assets loading, and those finished since, until all finished
+    pub assets: Vec<PendingAsset>,
+    /// Loading steps done at the last report
+    pub reported_steps: Option<usize>,
+    /// Finished assets whose handles are still waited on, not to count
+    /// them again
+    pub reported: HashSet<UntypedAssetId>,
+}
+
+/// An asset counted by `report_asset_progress`
+pub struct PendingAsset {
+    pub id: UntypedAssetId,
+    pub path: String,
+    /// Whether its failure was reported
+    pub failed: bool,
+}
+
+/// Selection sets by name, each with its members in the order they were
+/// added
+#[derive(Resource, Default)]
+pub struct SelectionSets(pub BTreeMap<String, Vec<Entity>>);
+
+/// Entities selected with `select_selection_set`
+#[derive(Resource, Default)]
+pub struct Selection(pub Vec<Entity>);
+
+/// GPU pick waiting for the ID buffer's readback, see `picking`
+#[derive(Resource)]
+pub struct GpuPick {
+    /// Camera drawing the pixel under the cursor into the ID buffer
+    pub camera: Entity,
+    /// Mesh entity of each pick id, starting at id 1
+    pub meshes: Vec<Entity>,
+    /// Copies of the meshes drawn into the ID buffer
+    pub proxies: Vec<Entity>,
+    /// Mesh the CPU ray cast hit, replied if the ID buffer stays empty
+    pub cpu_hit: Option<Entity>,
+    pub reply: Sender<Result<Option<PickedEntity>, String>>,
+}
+
+/// Files queued with `prefetch_assets`, loaded one at a time while nothing
+/// else loads, and the handles keeping those loaded in memory
+#[derive(Resource, Default)]
+pub struct PrefetchQueue {
+    pub queued: VecDeque<PrefetchAsset>,
+    pub loading: Option<PrefetchedAsset>,
+    pub done: Vec<PrefetchedAsset>,
+}
+
+/// A file started by `run_prefetch_queue`
+pub struct PrefetchedAsset {
+    pub path: PathBuf,
+    pub kind: PrefetchKind,
+    /// Every handle later uses of the file load, so they find it loaded
+    pub handles: Vec<UntypedHandle>,
+    pub error: Option<String>,
+    /// Whether `load_model` opened it, after which opening it again
+    /// reloads the file like for any other model
+    pub claimed: bool,
+}
+
+/// Custom shaders registered with `register_custom_shader`, by name, with
+/// the index their handle is derived from
+#[derive(Resource, Default)]
+pub struct CustomShaders(pub HashMap<String, u32>);
+
+/// Particle emitter started by `set_particle_emitter`, absent while it is
+/// stopped
+#[derive(Resource)]
+pub struct ParticleEmitter {
+    /// Particles per second
+    pub rate: f32,
+    pub gravity: Vec3,
+    pub position: Vec3,
+    /// Shared by every particle, so their draws are batched
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+    /// Fraction of a particle left over from previous frames
+    pub pending: f32,
+    /// Particles emitted so far, picks each one's launch direction
+    pub emitted: u64,
+}
+
+/// Seed the terrain scene is generated from, see `regenerate_terrain`
+#[derive(Resource, Clone, Copy)]
+pub struct TerrainSeed(pub u32);
+
+impl Default for TerrainSeed {
+    fn default() -> Self {
+        Self(TERRAIN_SEED)
+    }
+}
+
+/// Light rig last set with `set_lighting_preset`
+#[derive(Resource, Default, Clone, Copy)]
+pub struct ActiveLightingPreset(pub LightingPreset);
+
+/// Fog last set with `set_fog`, `None` while it is off
+#[derive(Resource, Default, Clone, Copy)]
+pub struct ActiveFog(pub Option<FogSettings>);
+
+/// UI overlay last set with `set_ui_overlay`, `None` while it is off
+///
+/// Kept so capture cameras can be given their own copy of the UI.
+#[derive(Resource, Default, Clone)]
+pub struct ActiveUiOverlay(pub Option<UiOverlaySettings>);
+
+/// Debug view set by `set_debug_view`, absent while it is off
+#[derive(Resource)]
+pub struct ActiveDebugView {
+    pub view: DebugView,
+    /// Material all meshes with a standard material are drawn with
+    pub material: Handle<DebugViewMaterial>,
+}
+
+/// Readback self-test started by `run_readback_self_test`
+#[derive(Resource)]
+pub struct ReadbackSelfTestRun {
+    /// Checks still waiting for their readback
+    pub remaining: usize,
+    /// Results in check order, `None` until the check's readback arrives
+    pub results: Vec<Option<ReadbackCheckResult>>,
+    /// Camera rendering the clear color pattern
+    pub camera: Entity,
+    pub reply: Sender<Result<ReadbackSelfTest, String>>,
+}
+
+/// Adapter info shared with the `get_gpu_info` command
+#[derive(Resource)]
+pub struct GpuInfoRes(pub SharedGpuInfo);
+
+/// wgpu error channel, present in both the main and the render world
+#[derive(Resource)]
+pub struct GpuErrorsRes(pub SharedGpuErrors);
+
+/// Commands queued by Tauri for the Bevy thread
+#[derive(Resource)]
+pub struct CommandQueueRes(pub SharedCommandQueue);
+
 /// Shared performance statistics resource
 #[derive(Resource)]
 pub struct PerfStatsRes(pub SharedPerfStats);
 
+/// Bevy thread and GPU device liveness shared with the `get_health` command
+#[derive(Resource)]
+pub struct HealthRes(pub SharedRendererHealth);
+
+/// History of performance statistics shared with the `get_stats_history`
+/// command and the `stats/history` endpoint
+#[derive(Resource)]
+pub struct StatsHistoryRes(pub SharedStatsHistory);
+
+/// Frame fetch counters updated by Tauri when clients fetch frames
+#[derive(Resource)]
+pub struct FetchStatsRes(pub SharedFetchStats);
+
+/// Native presentation of the stream, a consumer that never fetches
+#[derive(Resource)]
+pub struct SharedSurfaceRes(pub SharedSurface);
+
+/// Connections to other instances that scene edits are sent to
+#[derive(Resource)]
+pub struct SceneSyncRes(pub SceneSync);
+
+/// Running counters of frames lost somewhere in the pipeline
+#[derive(Resource, Default)]
+pub struct PipelineCounters {
+    pub dropped_stale: u64,
+    pub unfetched: u64,
+    pub skipped_identical: u64,
+    pub max_channel_depth: u32,
+}
+
+/// Hash of the last published frame's pixels, see `SKIP_IDENTICAL_FRAMES`
+#[derive(Resource, Default)]
+pub struct LastFrameHash(pub Option<u64>);
+
 // =============================================================================
 // Channel Communication (Main World <-> Render World)
 // =============================================================================
 
 use crossbeam_channel::{Receiver, Sender};
 
+/// Raw image data read back from a staging buffer
+pub struct CopiedImage {
+    /// Entity of the `ImageCopier` that produced this copy
+    pub copier: Entity,
+    /// Number of the pending view the copied frame was rendered from
+    pub view: Option<u64>,
+    pub data: Vec<u8>,
+}
+
 /// Receives data from render world
 #[derive(Resource, Deref)]
-pub struct MainWorldReceiver(pub Receiver<Vec<u8>>);
+pub struct RenderWorldReceiver(pub Receiver<CopiedImage>);
 
 /// Sends data to main world
 #[derive(Resource, Deref)]
-pub struct RenderWorldSender(pub Sender<Vec<u8>>);
+pub struct RenderWorldSender(pub Sender<CopiedImage>);
+
+/// Image data ready for frame extraction
+pub struct ReadbackFrame {
+    /// Entity of the `ImageCopier` that produced this readback
+    pub copier: Entity,
+    /// Size of the copied texture
+    pub width: u32,
+    pub height: u32,
+    pub format: PixelFormat,
+    /// Whether rows in `data` carry copy alignment padding
+    pub padded: bool,
+    pub data: Vec<u8>,
+    /// View the frame was rendered from, if the copier tracks it
+    pub metadata: Option<FrameMetadata>,
+}
+
+/// Bytes of readback data waiting in the channel
+#[derive(Clone, Default)]
+pub struct QueuedBytes(pub Arc<AtomicUsize>);
+
+impl QueuedBytes {
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Receives completed readbacks
+#[derive(Resource, Deref)]
+pub struct MainWorldReceiver {
+    #[deref]
+    pub receiver: Receiver<ReadbackFrame>,
+    pub queued_bytes: QueuedBytes,
+}
+
+impl MainWorldReceiver {
+    /// Take the oldest queued readback, if any
+    pub fn recv_frame(&self) -> Option<ReadbackFrame> {
+        let frame = self.receiver.try_recv().ok()?;
+        self.queued_bytes
+            .0
+            .fetch_sub(frame.data.len(), Ordering::Relaxed);
+        Some(frame)
+    }
+}
+
+/// Sends completed readbacks to frame extraction
+#[derive(Resource)]
+pub struct ReadbackSender {
+    pub sender: Sender<ReadbackFrame>,
+    pub queued_bytes: QueuedBytes,
+}
+
+impl ReadbackSender {
+    pub fn send_frame(&self, frame: ReadbackFrame) {
+        let bytes = frame.data.len();
+        if self.sender.send(frame).is_ok() {
+            self.queued_bytes.0.fetch_add(bytes, Ordering::Relaxed);
+        }
+    }
+}
+
+// =============================================================================
+// Memory Watchdog
+// =============================================================================
+
+/// Caps the frame data held across the pipeline
+///
+/// Checked once per frame by `enforce_memory_limit`.
+#[derive(Resource)]
+pub struct MemoryWatchdog {
+    pub limit_bytes: usize,
+    /// JPEG slot filled by the encoder thread, counted toward the limit
+    pub encoded_frame: SharedEncodedFrame,
+    /// Whether the last check was over the limit
+    pub over_limit: bool,
+    pub times_exceeded: u64,
+}
+
+impl MemoryWatchdog {
+    pub fn new(encoded_frame: SharedEncodedFrame) -> Self {
+        Self {
+            limit_bytes: PIPELINE_MEMORY_LIMIT_MB * 1024 * 1024,
+            encoded_frame,
+            over_limit: false,
+            times_exceeded: 0,
+        }
+    }
+}
+
+/// Triggered when the pipeline goes over its memory limit
+#[derive(Event, Debug)]
+pub struct MemoryLimitExceeded {
+    /// Bytes held before anything was dropped
+    pub held_bytes: usize,
+    pub limit_bytes: usize,
+    /// Bytes freed by dropping pooled buffers and queued readbacks
+    pub freed_bytes: usize,
+}
+
+// =============================================================================
+// Scene Editing
+// =============================================================================
+
+/// Snapping set with `set_snapping`, off by default
+#[derive(Resource, Default)]
+pub struct Snapping(pub SnapSettings);
+
+/// Scene edits that `undo` and `redo` step through, oldest first
+#[derive(Resource)]
+pub struct EditHistory {
+    pub undo: VecDeque<SceneEdit>,
+    /// Undone edits, most recently undone last
+    pub redo: Vec<SceneEdit>,
+    /// Most edits kept in `undo`
+    pub depth: usize,
+    pub next_id: u64,
+    /// Edit the command being applied recorded into, for publishing it
+    pub last_recorded: Option<u64>,
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            depth: HISTORY_DEPTH,
+            next_id: 0,
+            last_recorded: None,
+        }
+    }
+}
+
+/// Changes made by one command, undone and redone together
+pub struct SceneEdit {
+    /// Command that made them, reported by `undo` and `redo`
+    pub label: &'static str,
+    pub changes: Vec<EditChange>,
+    pub recorded: Instant,
+    /// Numbers the edits of this instance, other instances undo it by this
+    pub id: u64,
+    /// Whether it was sent to other instances
+    pub synced: bool,
+}
+
+/// Changes made by edits from other instances, kept out of `EditHistory`
+/// so undo only steps through this instance's own edits
+#[derive(Resource, Default)]
+pub struct RemoteEdits {
+    /// Changes of the edit being applied, collected by `record`
+    pub capturing: Option<Vec<EditChange>>,
+    /// Oldest first, at most `REMOTE_HISTORY_DEPTH`
+    pub edits: VecDeque<RemoteEdit>,
+    /// `ApplySceneOp` commands received while the scene is locked, applied
+    /// in order once it is unlocked
+    pub held: Vec<BridgeCommand>,
+}
+
+/// An edit made in another instance, undone there by its id
+pub struct RemoteEdit {
+    pub instance: u64,
+    pub id: u64,
+    pub changes: Vec<EditChange>,
+    pub undone: bool,
+}
+
+/// One change with what it takes to reverse it
+pub enum EditChange {
+    /// Entities added, disabled while undone
+    Spawned(Vec<Entity>),
+    /// Entities removed, kept disabled while the edit can still be undone
+    Removed(Vec<Entity>),
+    Transform {
+        entity: Entity,
+        before: Transform,
+        after: Transform,
+    },
+    Material {
+        entity: Entity,
+        before: MaterialState,
+        after: MaterialState,
+    },
+    Visibility {
+        entity: Entity,
+        before: Visibility,
+        after: Visibility,
+    },
+    /// `None` for a root entity
+    Parent {
+        entity: Entity,
+        before: Option<Entity>,
+        after: Option<Entity>,
+    },
+    /// `None` while the mesh is drawn with its own material
+    CustomShader {
+        entity: Entity,
+        before: Option<Handle<CustomShaderMaterial>>,
+        after: Option<Handle<CustomShaderMaterial>>,
+    },
+    /// Weight morph target `index` eases towards
+    MorphWeight {
+        entity: Entity,
+        index: usize,
+        before: f32,
+        after: f32,
+    },
+    /// Empty for an untagged entity
+    Tags {
+        entity: Entity,
+        before: BTreeSet<String>,
+        after: BTreeSet<String>,
+    },
+}
+
+/// Standard material of a mesh and the original a material override keeps
+pub struct MaterialState {
+    pub material: Handle<StandardMaterial>,
+    pub original: Option<Handle<StandardMaterial>>,
+}
@@ -0,0 +1,49 @@
+// Color LUT pass: grades the tonemapped image through a 3D lookup table
+//
+// .cube LUTs map sRGB-encoded colors to sRGB-encoded colors, so the
+// linear image is encoded before the lookup and decoded after it. The
+// lookup is trilinear, between the centers of the table's texels.
+
+@group(0) @binding(0) var source_texture: texture_2d<f32>;
+@group(0) @binding(1) var linear_sampler: sampler;
+@group(0) @binding(2) var lut_texture: texture_3d<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    // Vertices (0,0), (2,0), (0,2) in UV space cover the whole screen
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    let low = c * 12.92;
+    let high = 1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, c <= vec3<f32>(0.0031308));
+}
+
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    let low = c / 12.92;
+    let high = pow((c + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, c <= vec3<f32>(0.04045));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source_texture, linear_sampler, in.uv);
+    let encoded = linear_to_srgb(clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0)));
+
+    // 0 and 1 land on the centers of the first and last texels
+    let size = f32(textureDimensions(lut_texture).x);
+    let coords = (encoded * (size - 1.0) + 0.5) / size;
+    let graded = textureSampleLevel(lut_texture, linear_sampler, coords, 0.0).rgb;
+    return vec4<f32>(srgb_to_linear(graded), color.a);
+}
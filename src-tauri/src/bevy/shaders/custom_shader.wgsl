@@ -0,0 +1,16 @@
+// Custom shader material template
+//
+// Registered shaders replace this fragment shader and bind the same
+// uniforms. It shades `color` by how much the surface faces up, so a
+// mesh reads as a shape without any lights.
+
+#import bevy_pbr::forward_io::VertexOutput
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(0) var<uniform> color: vec4<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(1) var<uniform> params: vec4<f32>;
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let up = normalize(in.world_normal).y * 0.5 + 0.5;
+    return vec4<f32>(color.rgb * mix(0.3, 1.0, up), color.a);
+}
@@ -0,0 +1,66 @@
+// Debug views of the scene geometry, see `DebugViewMaterial`
+
+#import bevy_pbr::{
+    forward_io::VertexOutput,
+    mesh_view_bindings::{lights, view},
+    shadows::get_cascade_index,
+}
+
+struct DebugViewParams {
+    mode: u32,
+    depth_range: f32,
+    overdraw_step: f32,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(0) var<uniform> params: DebugViewParams;
+
+// Must match `DebugViewMaterial::mode_index`
+const MODE_DEPTH: u32 = 0u;
+const MODE_NORMALS: u32 = 1u;
+const MODE_UVS: u32 = 2u;
+const MODE_OVERDRAW: u32 = 3u;
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    // View space z, negative in front of the camera
+    let view_z = dot(vec4<f32>(
+        view.view_from_world[0].z,
+        view.view_from_world[1].z,
+        view.view_from_world[2].z,
+        view.view_from_world[3].z,
+    ), in.world_position);
+
+    if params.mode == MODE_DEPTH {
+        // White at the camera, black at `depth_range` and beyond
+        let depth = saturate(-view_z / params.depth_range);
+        return vec4<f32>(vec3<f32>(1.0 - depth), 1.0);
+    }
+    if params.mode == MODE_NORMALS {
+        return vec4<f32>(normalize(in.world_normal) * 0.5 + 0.5, 1.0);
+    }
+    if params.mode == MODE_UVS {
+#ifdef VERTEX_UVS_A
+        return vec4<f32>(fract(in.uv), 0.0, 1.0);
+#else
+        // No texture coordinates
+        return vec4<f32>(1.0, 0.0, 1.0, 1.0);
+#endif
+    }
+    if params.mode == MODE_OVERDRAW {
+        // Blended additively, so brightness counts the surfaces drawn
+        return vec4<f32>(vec3<f32>(params.overdraw_step), 0.0);
+    }
+
+    // Shadow cascades of the first directional light
+    if lights.n_directional_lights == 0u {
+        return vec4<f32>(0.5, 0.5, 0.5, 1.0);
+    }
+    switch get_cascade_index(0u, view_z) {
+        case 0u: { return vec4<f32>(1.0, 0.2, 0.2, 1.0); }
+        case 1u: { return vec4<f32>(0.2, 1.0, 0.2, 1.0); }
+        case 2u: { return vec4<f32>(0.2, 0.4, 1.0, 1.0); }
+        case 3u: { return vec4<f32>(1.0, 1.0, 0.2, 1.0); }
+        // Beyond the last cascade, unshadowed
+        default: { return vec4<f32>(0.1, 0.1, 0.1, 1.0); }
+    }
+}
@@ -0,0 +1,32 @@
+// Downscale pass: draws the render target into a smaller stream texture
+//
+// A single fullscreen triangle takes one bilinear sample of the source
+// per output pixel, so each output pixel blends the 2x2 source pixels
+// nearest its center. Halving the size puts every sample exactly between
+// four source pixels, which makes it their plain average; other factors
+// weight the four unevenly, and factors above two leave the rest of each
+// output pixel's footprint out, so fine detail can alias.
+
+@group(0) @binding(0) var source_texture: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    // Vertices (0,0), (2,0), (0,2) in UV space cover the whole screen
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(source_texture, source_sampler, in.uv);
+}
@@ -0,0 +1,10 @@
+// Pick ids as flat colors for GPU picking, see `IdMaterial`
+
+#import bevy_pbr::forward_io::VertexOutput
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(0) var<uniform> color: vec4<f32>;
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    return color;
+}
@@ -0,0 +1,73 @@
+// Pixel pack pass: writes a texture into a tightly packed storage buffer
+//
+// The texture is bound through a non-sRGB view, so the loaded values are
+// the stored bytes and `pack4x8unorm` reproduces them exactly. BGRA
+// textures load in RGBA order, which does the channel swizzle for free.
+// Only the pixels inside `region` are packed, row by row.
+
+struct Region {
+    origin: vec2<u32>,
+    size: vec2<u32>,
+}
+
+@group(0) @binding(0) var source_texture: texture_2d<f32>;
+@group(0) @binding(1) var<storage, read_write> output: array<u32>;
+@group(0) @binding(2) var<uniform> region: Region;
+
+fn load_pixel(index: u32, size: vec2<u32>) -> vec4<f32> {
+    if index >= size.x * size.y {
+        return vec4<f32>(0.0);
+    }
+    let position = region.origin + vec2<u32>(index % size.x, index / size.x);
+    return textureLoad(source_texture, position, 0);
+}
+
+// One invocation per pixel, one word per pixel
+@compute @workgroup_size(64)
+fn pack_rgba(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = region.size;
+    if id.x >= size.x * size.y {
+        return;
+    }
+    output[id.x] = pack4x8unorm(load_pixel(id.x, size));
+}
+
+// One invocation per four pixels, which fit exactly into three words
+@compute @workgroup_size(64)
+fn pack_rgb(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = region.size;
+    let first = id.x * 4u;
+    if first >= size.x * size.y {
+        return;
+    }
+
+    let p0 = load_pixel(first, size);
+    let p1 = load_pixel(first + 1u, size);
+    let p2 = load_pixel(first + 2u, size);
+    let p3 = load_pixel(first + 3u, size);
+
+    output[id.x * 3u] = pack4x8unorm(vec4<f32>(p0.rgb, p1.r));
+    output[id.x * 3u + 1u] = pack4x8unorm(vec4<f32>(p1.gb, p2.rg));
+    output[id.x * 3u + 2u] = pack4x8unorm(vec4<f32>(p2.b, p3.rgb));
+}
+
+fn to_rgb565(pixel: vec4<f32>) -> u32 {
+    let r = u32(round(pixel.r * 31.0));
+    let g = u32(round(pixel.g * 63.0));
+    let b = u32(round(pixel.b * 31.0));
+    return (r << 11u) | (g << 5u) | b;
+}
+
+// One invocation per two pixels, 16 bits each, first pixel in the low half
+@compute @workgroup_size(64)
+fn pack_rgb565(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = region.size;
+    let first = id.x * 2u;
+    if first >= size.x * size.y {
+        return;
+    }
+
+    let low = to_rgb565(load_pixel(first, size));
+    let high = to_rgb565(load_pixel(first + 1u, size));
+    output[id.x] = low | (high << 16u);
+}
@@ -0,0 +1,47 @@
+// Shadow catcher: transparent where lit, darkened where shadowed, see
+// `ShadowCatcherMaterial`
+
+#import bevy_pbr::{
+    mesh_types::MESH_FLAGS_SHADOW_RECEIVER_BIT,
+    pbr_fragment::pbr_input_from_standard_material,
+}
+
+#ifdef PREPASS_PIPELINE
+#import bevy_pbr::{
+    prepass_io::{VertexOutput, FragmentOutput},
+    pbr_deferred_functions::deferred_output,
+}
+#else
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_functions::apply_pbr_lighting,
+}
+#endif
+
+struct ShadowCatcher {
+    // Shadow tint, alpha is the opacity of a full shadow
+    color: vec4<f32>,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(100) var<uniform> shadow_catcher: ShadowCatcher;
+
+@fragment
+fn fragment(in: VertexOutput, @builtin(front_facing) is_front: bool) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+
+#ifdef PREPASS_PIPELINE
+    let out = deferred_output(in, pbr_input);
+#else
+    var out: FragmentOutput;
+    // Light the surface with and without shadows; their ratio is how much
+    // light the shadows take away, ambient and environment light included
+    let shadowed = apply_pbr_lighting(pbr_input).rgb;
+    pbr_input.flags &= ~MESH_FLAGS_SHADOW_RECEIVER_BIT;
+    let unshadowed = apply_pbr_lighting(pbr_input).rgb;
+    let luminance = vec3<f32>(0.2126, 0.7152, 0.0722);
+    let lit = dot(shadowed, luminance) / max(dot(unshadowed, luminance), 0.0001);
+    let shadow = 1.0 - saturate(lit);
+    out.color = vec4<f32>(shadow_catcher.color.rgb, shadow * shadow_catcher.color.a);
+#endif
+    return out;
+}
@@ -0,0 +1,46 @@
+// Terrain splatting: blends four tiling ground layers by the splat weights
+// in the vertex colors, see `TerrainMaterial`
+
+#import bevy_pbr::pbr_fragment::pbr_input_from_standard_material
+
+#ifdef PREPASS_PIPELINE
+#import bevy_pbr::{
+    prepass_io::{VertexOutput, FragmentOutput},
+    pbr_deferred_functions::deferred_output,
+}
+#else
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+#endif
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(100) var layers: texture_2d_array<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(101) var layers_sampler: sampler;
+// Layer texture repeats per world unit
+@group(#{MATERIAL_BIND_GROUP}) @binding(102) var<uniform> tiling: f32;
+
+@fragment
+fn fragment(in: VertexOutput, @builtin(front_facing) is_front: bool) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+
+#ifdef VERTEX_COLORS
+    // Projected from above, so the layers don't depend on the mesh's UVs
+    let uv = in.world_position.xz * tiling;
+    let weights = in.color / max(dot(in.color, vec4<f32>(1.0)), 0.0001);
+    let splat = textureSample(layers, layers_sampler, uv, 0) * weights.x
+        + textureSample(layers, layers_sampler, uv, 1) * weights.y
+        + textureSample(layers, layers_sampler, uv, 2) * weights.z
+        + textureSample(layers, layers_sampler, uv, 3) * weights.w;
+    pbr_input.material.base_color = vec4<f32>(splat.rgb, 1.0);
+#endif
+
+#ifdef PREPASS_PIPELINE
+    let out = deferred_output(in, pbr_input);
+#else
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+#endif
+    return out;
+}
@@ -0,0 +1,74 @@
+// Water: wave normals animated over time, reflecting the mirrored scene
+// rendered by the reflection camera, see `WaterMaterial`
+
+#import bevy_pbr::{
+    mesh_view_bindings::{globals, view},
+    pbr_fragment::pbr_input_from_standard_material,
+}
+
+#ifdef PREPASS_PIPELINE
+#import bevy_pbr::{
+    prepass_io::{VertexOutput, FragmentOutput},
+    pbr_deferred_functions::deferred_output,
+}
+#else
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+#endif
+
+struct Water {
+    // Wave strength, wavelength and speed
+    waves: vec4<f32>,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(100) var reflection: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(101) var reflection_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(102) var<uniform> water: Water;
+
+// Slope of one sine wave travelling along `direction`
+fn wave_slope(position: vec2<f32>, direction: vec2<f32>, wavelength: f32, time: f32) -> vec2<f32> {
+    let k = 6.2831853 / wavelength;
+    let phase = (dot(direction, position) - water.waves.z * time) * k;
+    return direction * cos(phase);
+}
+
+// Surface normal of a few overlapping waves of different lengths
+fn wave_normal(position: vec2<f32>, time: f32) -> vec3<f32> {
+    let wavelength = water.waves.y;
+    let slope = wave_slope(position, vec2<f32>(1.0, 0.0), wavelength, time) * 0.4
+        + wave_slope(position, vec2<f32>(0.6, 0.8), wavelength * 0.61, time) * 0.3
+        + wave_slope(position, vec2<f32>(-0.7, 0.7), wavelength * 0.37, time) * 0.2
+        + wave_slope(position, vec2<f32>(0.2, -0.98), wavelength * 0.23, time) * 0.1;
+    let strength = water.waves.x;
+    return normalize(vec3<f32>(-slope.x * strength, 1.0, -slope.y * strength));
+}
+
+@fragment
+fn fragment(in: VertexOutput, @builtin(front_facing) is_front: bool) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+    let normal = wave_normal(in.world_position.xz, globals.time);
+    pbr_input.N = normal;
+    pbr_input.world_normal = normal;
+
+#ifdef PREPASS_PIPELINE
+    let out = deferred_output(in, pbr_input);
+#else
+    var out: FragmentOutput;
+    let lit = apply_pbr_lighting(pbr_input);
+
+    // The reflection camera's image is mirrored left to right, see
+    // `update_reflection_camera`
+    let screen_uv = (in.position.xy - view.viewport.xy) / view.viewport.zw;
+    let uv = vec2<f32>(1.0 - screen_uv.x, screen_uv.y) + normal.xz * 0.1;
+    let reflected = textureSample(reflection, reflection_sampler, clamp(uv, vec2<f32>(0.0), vec2<f32>(1.0))).rgb;
+
+    // Schlick's Fresnel, with some reflection even looking straight down
+    let facing = 1.0 - saturate(dot(normal, pbr_input.V));
+    let fresnel = mix(0.25, 1.0, pow(facing, 5.0));
+    out.color = vec4<f32>(mix(lit.rgb, reflected, fresnel), 1.0);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+#endif
+    return out;
+}
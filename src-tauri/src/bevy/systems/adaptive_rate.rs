@@ -0,0 +1,71 @@
+//! Adaptive frame rate system
+//!
+//! This module compares how many frames clients fetch against how many Bevy
+//! renders, and lowers the render and readback rate when nobody is keeping
+//! up, instead of rendering 60 FPS into a 15 FPS consumer. A shared surface
+//! presents every frame without fetching, so it keeps the full rate; the
+//! readback still runs at that rate for the CPU consumers.
+
+use bevy::{prelude::*, time::Time};
+
+use crate::bevy::resources::{
+    AdaptiveFrameRate, FetchStatsRes, FixedTimestep, FrameCount, PerfStatsRes, SharedSurfaceRes,
+};
+use crate::config::{adaptive::*, TARGET_FPS};
+
+/// Re-evaluate consumer demand and adjust the frame rate target
+pub fn adapt_frame_rate(
+    time: Res<Time>,
+    fetch_stats: Res<FetchStatsRes>,
+    shared_surface: Res<SharedSurfaceRes>,
+    count: Res<FrameCount>,
+    perf_stats: Option<Res<PerfStatsRes>>,
+    fixed_timestep: Res<FixedTimestep>,
+    mut rate: ResMut<AdaptiveFrameRate>,
+) {
+    // Deterministic mode renders at its fixed rate
+    if fixed_timestep.0.is_some() {
+        return;
+    }
+    let now = time.elapsed_secs_f64();
+    let window = now - rate.last_evaluation;
+    if window < EVALUATION_INTERVAL {
+        return;
+    }
+
+    let fetch_count = fetch_stats.0.fetch_count();
+    let produce_rate = count.0.saturating_sub(rate.last_frame_count) as f64 / window;
+    let fetch_rate = if shared_surface.0.is_presenting() {
+        produce_rate
+    } else {
+        fetch_count.saturating_sub(rate.last_fetch_count) as f64 / window
+    };
+    rate.last_evaluation = now;
+    rate.last_fetch_count = fetch_count;
+    rate.last_frame_count = count.0;
+
+    let target = if fetch_rate < produce_rate * 0.8 {
+        // Clients are falling behind: render just a bit faster than they fetch
+        fetch_rate * HEADROOM
+    } else if fetch_rate >= produce_rate * 0.95 {
+        // Clients take (almost) every frame: demand may be higher, ramp up
+        rate.target_fps * RAMP_UP_FACTOR
+    } else {
+        rate.target_fps
+    }
+    .clamp(MIN_FPS, TARGET_FPS);
+
+    if (target - rate.target_fps).abs() >= 1.0 {
+        println!(
+            "[Bevy] Adaptive frame rate: {:.1} -> {:.1} FPS (fetching {:.1}/s, producing {:.1}/s)",
+            rate.target_fps, target, fetch_rate, produce_rate
+        );
+        rate.target_fps = target;
+    }
+
+    if let Some(perf_res) = &perf_stats {
+        if let Ok(mut stats) = perf_res.0 .0.lock() {
+            stats.target_fps = rate.target_fps;
+        }
+    }
+}
@@ -4,16 +4,64 @@
 
 use bevy::{
     prelude::*,
-    time::Time,
+    time::{Time, TimeUpdateStrategy},
 };
+use std::time::Duration;
 
-use crate::bevy::components::RotatingCube;
+use crate::bevy::components::{RotatingCube, TurntableCapture};
+use crate::bevy::resources::{AdaptiveFrameRate, FixedTimestep, PreRollFrames, SceneTime};
+use crate::config::TARGET_FPS;
+
+/// Switch deterministic mode on at `fps`, or back to the wall clock
+///
+/// In deterministic mode Bevy's clock advances by exactly `1 / fps` per
+/// frame, however long the frame actually took, and frames are rendered at
+/// that rate instead of an adaptive one.
+pub fn set_fixed_timestep(world: &mut World, fps: Option<f64>) {
+    world.insert_resource(FixedTimestep(fps));
+    match fps {
+        Some(fps) => {
+            let step = Duration::from_secs_f64(1.0 / fps);
+            world.insert_resource(TimeUpdateStrategy::ManualDuration(step));
+            world.resource_mut::<AdaptiveFrameRate>().target_fps = fps;
+            println!(
+                "[Bevy] Deterministic mode, {:.3}ms per frame",
+                step.as_secs_f64() * 1000.0
+            );
+        }
+        None => {
+            world.insert_resource(TimeUpdateStrategy::Automatic);
+            world.resource_mut::<AdaptiveFrameRate>().target_fps = TARGET_FPS;
+            println!("[Bevy] Time follows the wall clock");
+        }
+    }
+}
+
+/// Advance [`SceneTime`] by the frame time once pre-roll is over
+///
+/// Paused during a turntable export, which sets the scene time per step.
+pub fn advance_scene_time(
+    time: Res<Time>,
+    pre_roll: Res<PreRollFrames>,
+    turntables: Query<(), With<TurntableCapture>>,
+    mut scene_time: ResMut<SceneTime>,
+) {
+    if pre_roll.0 == 0 && turntables.is_empty() {
+        scene_time.0 += time.delta_secs_f64();
+    }
+}
 
 /// Rotate all cubes marked with RotatingCube component
-pub fn rotate_cubes(time: Res<Time>, mut query: Query<&mut Transform, With<RotatingCube>>) {
-    let dt = time.delta_secs();
+///
+/// The orientation is computed from [`SceneTime`] instead of accumulated
+/// per frame, so it does not depend on the frame rate it was rendered at.
+pub fn rotate_cubes(
+    scene_time: Res<SceneTime>,
+    mut query: Query<&mut Transform, With<RotatingCube>>,
+) {
+    let t = scene_time.0 as f32;
+    let rotation = Quat::from_rotation_x(t * 0.25) * Quat::from_rotation_y(t * 0.7);
     for mut transform in query.iter_mut() {
-        transform.rotate_y(dt * 0.7);
-        transform.rotate_x(dt * 0.25);
+        transform.rotation = rotation;
     }
 }
@@ -0,0 +1,132 @@
+//! Asset loading progress
+//!
+//! Collects the handles models, environments, color LUTs and projects wait
+//! for and reports how far they loaded whenever that changes, which Tauri
+//! emits as `asset-progress` events for progress bars. An asset counts as
+//! half loaded once its file was read and fully loaded once everything it
+//! references, e.g. a glTF's textures, loaded too. Progress runs over every
+//! asset added until all of them finished, then starts over.
+
+use bevy::asset::{LoadState, RecursiveDependencyLoadState, UntypedAssetId};
+use bevy::prelude::*;
+use crossbeam_channel::Sender;
+use std::collections::HashSet;
+
+use crate::bevy::resources::{
+    AssetProgressWatch, ColorLutLoads, EnvironmentLoads, ModelLoad, PendingAsset, ProjectAssets,
+};
+use crate::tauri_bridge::shared_state::{AssetLoadError, AssetProgress};
+
+/// Report asset loading progress to `updates` from now on
+pub fn watch_asset_progress(world: &mut World, updates: Sender<AssetProgress>) {
+    world.insert_resource(AssetProgressWatch {
+        updates,
+        assets: Vec::new(),
+        reported_steps: None,
+        reported: HashSet::new(),
+    });
+}
+
+/// Pick up handles waited for since the last frame and report progress if
+/// any of them moved on
+pub fn report_asset_progress(
+    watch: Option<ResMut<AssetProgressWatch>>,
+    asset_server: Res<AssetServer>,
+    model: Option<Res<ModelLoad>>,
+    environments: Res<EnvironmentLoads>,
+    luts: Res<ColorLutLoads>,
+    project: Option<Res<ProjectAssets>>,
+) {
+    let Some(mut watch) = watch else {
+        return;
+    };
+    let ids = model
+        .iter()
+        .map(|load| load.asset.id())
+        .chain(
+            environments
+                .0
+                .iter()
+                .flat_map(|load| load.environments.iter().map(|handle| handle.id().untyped())),
+        )
+        .chain(luts.0.iter().map(|(lut, _)| lut.id().untyped()))
+        .chain(
+            project
+                .iter()
+                .flat_map(|project| project.0.iter().map(|(_, handle)| handle.id().untyped())),
+        )
+        .collect::<HashSet<_>>();
+    // Handles stay around after they loaded, e.g. a model being shown
+    watch.reported.retain(|id| ids.contains(id));
+    for id in ids {
+        if !watch.reported.contains(&id) && !watch.assets.iter().any(|asset| asset.id == id) {
+            watch.assets.push(pending(&asset_server, id));
+        }
+    }
+    if watch.assets.is_empty() {
+        return;
+    }
+
+    // Two steps per asset: its file read, then its dependencies loaded
+    let mut steps = 0;
+    let mut finished = 0;
+    let mut current = None;
+    let mut errors = Vec::new();
+    for asset in &mut watch.assets {
+        let asset_steps = match asset_server.get_load_states(asset.id) {
+            Some(
+                (LoadState::Failed(e), _, _) | (_, _, RecursiveDependencyLoadState::Failed(e)),
+            ) => {
+                if !asset.failed {
+                    asset.failed = true;
+                    errors.push(AssetLoadError {
+                        path: asset.path.clone(),
+                        error: e.to_string(),
+                    });
+                }
+                2
+            }
+            Some((LoadState::Loaded, _, RecursiveDependencyLoadState::Loaded)) => 2,
+            Some((LoadState::Loaded, _, _)) => 1,
+            Some(_) => 0,
+            // Dropped before it finished, nothing waits for it anymore
+            None => 2,
+        };
+        if asset_steps == 2 {
+            finished += 1;
+        } else if current.is_none() {
+            current = Some(asset.path.clone());
+        }
+        steps += asset_steps;
+    }
+    if watch.reported_steps == Some(steps) && errors.is_empty() {
+        return;
+    }
+
+    let total = watch.assets.len();
+    let _ = watch.updates.send(AssetProgress {
+        percent: steps as f32 * 50.0 / total as f32,
+        finished,
+        total,
+        current,
+        errors,
+    });
+    if finished == total {
+        let assets = std::mem::take(&mut watch.assets);
+        watch.reported.extend(assets.iter().map(|asset| asset.id));
+        watch.reported_steps = None;
+    } else {
+        watch.reported_steps = Some(steps);
+    }
+}
+
+fn pending(asset_server: &AssetServer, id: UntypedAssetId) -> PendingAsset {
+    PendingAsset {
+        id,
+        path: asset_server.get_path(id).map_or_else(
+            || format!("{:?}", id),
+            |path| path.path().display().to_string(),
+        ),
+        failed: false,
+    }
+}
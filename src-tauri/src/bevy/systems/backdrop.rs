@@ -0,0 +1,75 @@
+//! Product-shot backdrop
+//!
+//! A shadow catcher ground grounds models without showing a floor: only
+//! the shadows falling on it are drawn, over the clear color, a skybox or,
+//! with a transparent background, nothing at all. Transparency survives in
+//! RGBA raw frames and PNG captures; JPEG frames have no alpha and show
+//! black instead.
+
+use bevy::light::NotShadowCaster;
+use bevy::prelude::*;
+
+use crate::bevy::components::{CameraController, GroundPlane};
+use crate::bevy::plugins::shadow_catcher::{shadow_catcher_material, ShadowCatcherMaterial};
+use crate::bevy::systems::camera::scene_bounds;
+use crate::config::ground_plane::{SHADOW_OPACITY, SIZE};
+use crate::tauri_bridge::shared_state::GroundPlaneSettings;
+
+/// Add a shadow catcher ground under the scene, replacing the previous
+/// one, or remove it if `settings` is `None`
+pub fn set_ground_plane(world: &mut World, settings: Option<GroundPlaneSettings>) {
+    let previous: Vec<Entity> = world
+        .query_filtered::<Entity, With<GroundPlane>>()
+        .iter(world)
+        .collect();
+    for entity in previous {
+        world.despawn(entity);
+    }
+    let Some(settings) = settings else {
+        println!("[Bevy] Ground plane removed");
+        return;
+    };
+
+    // Centered under the scene, at its lowest point unless told otherwise
+    let (min, max) = scene_bounds(world).unwrap_or((Vec3::ZERO, Vec3::ZERO));
+    let center = (min + max) / 2.0;
+    let height = settings.height.unwrap_or(min.y);
+    let size = settings.size.unwrap_or(SIZE);
+    let opacity = settings.shadow_opacity.unwrap_or(SHADOW_OPACITY);
+
+    let mesh = world
+        .resource_mut::<Assets<Mesh>>()
+        .add(Plane3d::default().mesh().size(size, size));
+    let material = world
+        .resource_mut::<Assets<ShadowCatcherMaterial>>()
+        .add(shadow_catcher_material(opacity));
+    world.spawn((
+        Mesh3d(mesh),
+        MeshMaterial3d(material),
+        Transform::from_xyz(center.x, height, center.z),
+        NotShadowCaster,
+        GroundPlane,
+        Name::new("ground_plane"),
+    ));
+    println!(
+        "[Bevy] Ground plane at height {:.2}, {:.0}% shadows",
+        height,
+        opacity * 100.0
+    );
+}
+
+/// Clear the stream camera to transparent, or back to its opaque
+/// background color
+pub fn set_transparent_background(world: &mut World, transparent: bool) {
+    let alpha = if transparent { 0.0 } else { 1.0 };
+    let mut cameras = world.query_filtered::<&mut Camera, With<CameraController>>();
+    for mut camera in cameras.iter_mut(world) {
+        if let ClearColorConfig::Custom(color) = camera.clear_color {
+            camera.clear_color = ClearColorConfig::Custom(color.with_alpha(alpha));
+        }
+    }
+    println!(
+        "[Bevy] Background {}",
+        if transparent { "transparent" } else { "opaque" }
+    );
+}
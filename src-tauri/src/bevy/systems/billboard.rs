@@ -0,0 +1,125 @@
+//! Camera-facing billboards
+//!
+//! Markers and icons placed at world positions, drawn as unlit quads that
+//! turn to face the stream camera every frame. They are on their own render
+//! layer, so the water's reflection leaves them out; capture cameras copy
+//! the stream camera's layers and show them, facing the stream camera.
+
+use bevy::{
+    asset::AssetPath, camera::visibility::RenderLayers, ecs::entity_disabling::Disabled,
+    light::NotShadowCaster, prelude::*,
+};
+
+use crate::bevy::components::{Billboard, CameraController};
+use crate::bevy::resources::EditChange;
+use crate::bevy::systems::camera::{show_render_layer, world_units_per_pixel};
+use crate::bevy::systems::history::{record, remove};
+use crate::config::billboard::{RENDER_LAYER, SCREEN_SIZE, SIZE};
+use crate::tauri_bridge::shared_state::AddBillboard;
+
+/// Add a billboard at `billboard.position` and return its entity id
+pub fn add_billboard(world: &mut World, billboard: AddBillboard) -> Result<u64, String> {
+    let base_color = match billboard.color.as_deref() {
+        None => Color::WHITE,
+        Some(&[r, g, b]) => Color::srgb(r, g, b),
+        Some(&[r, g, b, a]) => Color::srgba(r, g, b, a),
+        Some(_) => return Err("color must have 3 or 4 components".to_string()),
+    };
+    let (size, screen_size) = if billboard.screen_size {
+        let pixels = billboard.size.unwrap_or(SCREEN_SIZE);
+        // Scaled by `face_billboards` before it is drawn
+        (1.0, Some(pixels))
+    } else {
+        (billboard.size.unwrap_or(SIZE), None)
+    };
+
+    let image = billboard.image.as_ref().map(|path| {
+        world
+            .resource::<AssetServer>()
+            .load_override::<Image>(AssetPath::from(path.clone()))
+    });
+    let mesh = world
+        .resource_mut::<Assets<Mesh>>()
+        .add(if image.is_some() {
+            Mesh::from(Rectangle::new(1.0, 1.0))
+        } else {
+            Mesh::from(Circle::new(0.5))
+        });
+    let material = world
+        .resource_mut::<Assets<StandardMaterial>>()
+        .add(StandardMaterial {
+            base_color,
+            base_color_texture: image,
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            cull_mode: None,
+            ..default()
+        });
+    let name = billboard.name.unwrap_or_else(|| "billboard".to_string());
+    let entity = world
+        .spawn((
+            Mesh3d(mesh),
+            MeshMaterial3d(material),
+            Transform::from_translation(Vec3::from(billboard.position))
+                .with_scale(Vec3::splat(size)),
+            NotShadowCaster,
+            RenderLayers::layer(RENDER_LAYER),
+            Billboard { screen_size },
+            Name::new(name.clone()),
+        ))
+        .id();
+    let change = EditChange::Spawned(vec![entity]);
+    record(world, "add_billboard", vec![change]);
+    show_render_layer(world, RENDER_LAYER, true);
+    println!("[Bevy] Added billboard {} as {}", name, entity);
+    Ok(entity.to_bits())
+}
+
+/// Remove billboard `id`
+pub fn remove_billboard(world: &mut World, id: u64) -> Result<(), String> {
+    let entity = Entity::try_from_bits(id).ok_or_else(|| format!("Invalid entity id {}", id))?;
+    // Removed ones stay until their edit leaves the undo history
+    if !world
+        .get_entity(entity)
+        .is_ok_and(|entity| entity.contains::<Billboard>() && !entity.contains::<Disabled>())
+    {
+        return Err(format!("No billboard with id {}", id));
+    }
+    let change = remove(world, &[entity]);
+    record(world, "remove_billboard", vec![change]);
+    Ok(())
+}
+
+/// Remove all billboards
+pub fn clear_billboards(world: &mut World) {
+    let billboards: Vec<Entity> = world
+        .query_filtered::<Entity, With<Billboard>>()
+        .iter(world)
+        .collect();
+    let change = remove(world, &billboards);
+    record(world, "clear_billboards", vec![change]);
+    println!("[Bevy] Removed {} billboards", billboards.len());
+}
+
+/// Turn billboards to face the stream camera, and scale the ones with a
+/// constant screen size for their distance
+pub fn face_billboards(
+    cameras: Query<(&Camera, &Transform, &Projection), With<CameraController>>,
+    mut billboards: Query<(&Billboard, &mut Transform), Without<CameraController>>,
+) {
+    let Some((camera, camera_transform, projection)) = cameras.iter().next() else {
+        return;
+    };
+    let forward = camera_transform.forward().as_vec3();
+    for (billboard, mut transform) in &mut billboards {
+        // The quad faces +Z, the camera looks along its -Z
+        transform.rotation = camera_transform.rotation;
+        let Some(pixels) = billboard.screen_size else {
+            continue;
+        };
+        let depth = (transform.translation - camera_transform.translation).dot(forward);
+        if let Some(units_per_pixel) = world_units_per_pixel(camera, projection, depth) {
+            transform.scale = Vec3::splat(pixels * units_per_pixel);
+        }
+    }
+}
@@ -0,0 +1,616 @@
+//! Bridge command processing
+//!
+//! This module applies commands queued by Tauri command handlers. It runs as
+//! an exclusive system, so each command has full access to the Bevy world.
+
+use bevy::prelude::*;
+
+use crate::bevy::resources::{
+    CameraPath, CameraPresets, CameraSensitivityRes, CameraTransitionStyle, CommandQueueRes,
+    EditHistory, FrameTimings, InputRecorder, OrbitCameraState, PipelineCounters, RemoteEdits,
+    SceneTime, StreamPixelFormat, StreamRegion, StreamResolution,
+};
+use crate::bevy::systems::animation::set_fixed_timestep;
+use crate::bevy::systems::asset_progress::watch_asset_progress;
+use crate::bevy::systems::billboard::{add_billboard, clear_billboards, remove_billboard};
+use crate::bevy::systems::camera::{
+    apply_camera_preset, camera_state, frame_all, look_at_entity, reset_roll, set_orbit_pivot,
+    set_projection, set_standard_view,
+};
+use crate::bevy::systems::camera_effects::{
+    start_camera_shake, start_push_in, stop_camera_effects,
+};
+use crate::bevy::systems::backdrop::{set_ground_plane, set_transparent_background};
+use crate::bevy::systems::camera_path::{play_camera_path, seek_camera_path, stop_camera_path};
+use crate::bevy::systems::collision::collision_meshes;
+use crate::bevy::systems::color_grading::set_color_lut;
+use crate::bevy::systems::culling::{culling_stats, freeze_culling};
+use crate::bevy::systems::custom_shaders::{register_custom_shader, set_custom_shader_material};
+use crate::bevy::systems::debug_overlay::set_debug_overlay;
+use crate::bevy::systems::debug_view::set_debug_view;
+use crate::bevy::systems::depth_of_field::{focus_depth_of_field, set_depth_of_field};
+use crate::bevy::systems::fog::set_fog;
+use crate::bevy::systems::environment::{set_environment_map, set_reflection_probes};
+use crate::bevy::systems::exposure::set_exposure;
+use crate::bevy::systems::gltf_export::export_gltf;
+use crate::bevy::systems::history::{redo, set_history_depth, undo};
+use crate::bevy::systems::inspector::{inspect_entity, list_entities, list_resources};
+use crate::bevy::systems::lighting::set_lighting_preset;
+use crate::bevy::systems::material_override::{list_submeshes, set_submesh_material};
+use crate::bevy::systems::model_loading::{approve_model_dir, load_model};
+use crate::bevy::systems::morph::{list_morph_targets, set_morph_weight};
+use crate::bevy::systems::particles::{set_particle_emitter, stop_particle_emitter};
+use crate::bevy::systems::picking::pick_entity;
+use crate::bevy::systems::prefetch::{clear_prefetched_assets, prefetch_assets, prefetch_status};
+use crate::bevy::systems::project::{open_project, project};
+use crate::bevy::systems::quad_view::set_quad_view;
+use crate::bevy::systems::raycast::raycast;
+use crate::bevy::systems::readback_self_test::start_readback_self_test;
+use crate::bevy::systems::scene_editing::{
+    detach, set_entity_transform, set_parent, set_snapping, snap_transform, spawn_primitive,
+};
+use crate::bevy::systems::scene_sync::{
+    apply_scene_op, publish, publish_for, publish_step, target_for,
+};
+use crate::bevy::systems::screenshot::{start_capture, start_screenshot};
+use crate::bevy::systems::selection_sets::{
+    add_to_selection_set, create_selection_set, delete_selection_set, list_selection_sets,
+    remove_from_selection_set, select_selection_set, selection, update_selection_set,
+};
+use crate::bevy::systems::slow_frames::{stop_slow_frame_alerts, watch_slow_frames};
+use crate::bevy::systems::tags::{query_entities, set_tags, update_tagged};
+use crate::bevy::systems::terrain::{regenerate_terrain, set_demo_scene};
+use crate::bevy::systems::text3d::spawn_text3d;
+use crate::bevy::systems::turntable::start_turntable;
+use crate::bevy::systems::ui_overlay::set_ui_overlay;
+use crate::bevy::systems::usdz_export::export_usd;
+use crate::bevy::systems::water::set_water;
+use crate::tauri_bridge::scene_sync::SceneOp;
+use crate::tauri_bridge::shared_state::{BridgeCommand, InputRecording};
+
+/// Apply all commands queued since the last frame
+pub fn apply_bridge_commands(world: &mut World) {
+    let locked = world.resource::<CommandQueueRes>().0.is_locked();
+    // Edits from other instances held while locked go before newer commands
+    let mut commands = if locked {
+        Vec::new()
+    } else {
+        std::mem::take(&mut world.resource_mut::<RemoteEdits>().held)
+    };
+    commands.extend(world.resource::<CommandQueueRes>().0.receiver.try_iter());
+
+    for command in commands {
+        // Published edits carry the id of the edit their command recorded
+        world.resource_mut::<EditHistory>().last_recorded = None;
+        match command {
+            BridgeCommand::SetStreamResolution { width, height } => {
+                world.insert_resource(StreamResolution { width, height });
+            }
+            BridgeCommand::SetPixelFormat(format) => {
+                world.insert_resource(StreamPixelFormat(format));
+            }
+            BridgeCommand::SetRegionOfInterest(region) => {
+                world.insert_resource(StreamRegion(region));
+            }
+            BridgeCommand::ResetStats => {
+                world.insert_resource(FrameTimings::default());
+                world.insert_resource(PipelineCounters::default());
+                println!("[Bevy] Statistics reset");
+            }
+            BridgeCommand::CaptureScreenshot { scale, reply } => {
+                start_screenshot(world, scale, reply);
+            }
+            BridgeCommand::StartInputRecording => {
+                let recorder = InputRecorder {
+                    start_time_s: world.resource::<SceneTime>().0,
+                    start_orbit: world.resource::<OrbitCameraState>().pose(),
+                    samples: Vec::new(),
+                    sensitivity: world.resource::<CameraSensitivityRes>().0,
+                };
+                world.insert_resource(recorder);
+                println!("[Bevy] Input recording started");
+            }
+            BridgeCommand::StopInputRecording { reply } => {
+                let end_time_s = world.resource::<SceneTime>().0;
+                let recorder = world.remove_resource::<InputRecorder>();
+                let _ = reply.send(recorder.map(|recorder| InputRecording {
+                    start_time_s: recorder.start_time_s,
+                    end_time_s,
+                    start_orbit: recorder.start_orbit,
+                    samples: recorder.samples,
+                    sensitivity: recorder.sensitivity,
+                }));
+            }
+            BridgeCommand::RenderView {
+                view,
+                width,
+                height,
+                reply,
+            } => {
+                let transform = view.map(|view| {
+                    let mut orbit = *world.resource::<OrbitCameraState>();
+                    orbit.set_view(view);
+                    orbit.camera_transform()
+                });
+                start_capture(world, width, height, transform, reply);
+            }
+            BridgeCommand::StartTurntable {
+                frames,
+                fps,
+                frames_out,
+            } => {
+                start_turntable(world, frames, fps, frames_out);
+            }
+            BridgeCommand::SetFixedTimestep { fps, reset_time } => {
+                set_fixed_timestep(world, fps);
+                if reset_time {
+                    world.insert_resource(SceneTime::default());
+                }
+            }
+            BridgeCommand::LoadCameraPresets(presets) => {
+                world.insert_resource(CameraPresets(presets));
+            }
+            BridgeCommand::GetProject { reply } => {
+                let _ = reply.send(project(world));
+            }
+            BridgeCommand::OpenProject {
+                project,
+                progress,
+                reply,
+            } => {
+                let _ = reply.send(open_project(world, *project, progress));
+            }
+            BridgeCommand::ApproveModelDir(dir) => approve_model_dir(world, dir),
+            BridgeCommand::LoadModel { path, progress } => {
+                load_model(world, path, progress, None);
+            }
+            BridgeCommand::ExportGltf { reply } => {
+                let _ = reply.send(export_gltf(world));
+            }
+            BridgeCommand::ExportUsdz { names, reply } => {
+                let _ = reply.send(export_usd(world, names));
+            }
+            BridgeCommand::GenerateCollisionMeshes { settings, reply } => {
+                let _ = reply.send(collision_meshes(world, settings));
+            }
+            BridgeCommand::SpawnPrimitive { primitive, reply } => {
+                let result = spawn_primitive(world, primitive.clone());
+                if let Ok(entity) = result {
+                    publish(world, SceneOp::SpawnPrimitive { entity, primitive });
+                }
+                let _ = reply.send(result);
+            }
+            BridgeCommand::SpawnText3d { text, reply } => {
+                let result = spawn_text3d(world, text.clone());
+                if let Ok(entity) = result {
+                    publish(world, SceneOp::SpawnText3d { entity, text });
+                }
+                let _ = reply.send(result);
+            }
+            BridgeCommand::AddBillboard { billboard, reply } => {
+                let result = add_billboard(world, billboard.clone());
+                if let Ok(entity) = result {
+                    publish(world, SceneOp::AddBillboard { entity, billboard });
+                }
+                let _ = reply.send(result);
+            }
+            BridgeCommand::RemoveBillboard { id, reply } => {
+                let result = remove_billboard(world, id);
+                if result.is_ok() {
+                    publish_for(world, id, |target| SceneOp::RemoveBillboard { target });
+                }
+                let _ = reply.send(result);
+            }
+            BridgeCommand::ClearBillboards => {
+                clear_billboards(world);
+                publish(world, SceneOp::ClearBillboards);
+            }
+            BridgeCommand::SetEntityTransform {
+                id,
+                mut transform,
+                reply,
+            } => {
+                // Before publishing, so other instances get the snapped values
+                snap_transform(world, &mut transform);
+                let result = set_entity_transform(world, id, &transform);
+                if result.is_ok() {
+                    publish_for(world, id, |target| SceneOp::SetEntityTransform {
+                        target,
+                        transform,
+                    });
+                }
+                let _ = reply.send(result);
+            }
+            BridgeCommand::SetParent {
+                child,
+                parent,
+                reply,
+            } => {
+                let targets = target_for(world, child).zip(target_for(world, parent));
+                let result = set_parent(world, child, parent);
+                if let (Ok(()), Some((target, parent))) = (&result, targets) {
+                    publish(world, SceneOp::SetParent { target, parent });
+                }
+                let _ = reply.send(result);
+            }
+            BridgeCommand::Detach { id, reply } => {
+                let target = target_for(world, id);
+                let result = detach(world, id);
+                if let (Ok(()), Some(target)) = (&result, target) {
+                    publish(world, SceneOp::Detach { target });
+                }
+                let _ = reply.send(result);
+            }
+            BridgeCommand::SetSnapping(settings) => {
+                set_snapping(world, settings);
+            }
+            BridgeCommand::Raycast {
+                origin,
+                direction,
+                max_distance,
+                reply,
+            } => {
+                let _ = reply.send(raycast(
+                    world,
+                    Vec3::from_array(origin),
+                    Vec3::from_array(direction),
+                    max_distance,
+                ));
+            }
+            BridgeCommand::PickEntity {
+                x,
+                y,
+                method,
+                reply,
+            } => {
+                pick_entity(world, x, y, method, reply);
+            }
+            BridgeCommand::Undo { reply } => {
+                let label = undo(world);
+                if label.is_some() {
+                    publish_step(world, true);
+                }
+                let _ = reply.send(label.map(String::from));
+            }
+            BridgeCommand::Redo { reply } => {
+                let label = redo(world);
+                if label.is_some() {
+                    publish_step(world, false);
+                }
+                let _ = reply.send(label.map(String::from));
+            }
+            BridgeCommand::SetHistoryDepth(depth) => {
+                set_history_depth(world, depth);
+            }
+            BridgeCommand::ApplySceneOp { instance, edit, op } if locked => {
+                world
+                    .resource_mut::<RemoteEdits>()
+                    .held
+                    .push(BridgeCommand::ApplySceneOp { instance, edit, op });
+            }
+            BridgeCommand::ApplySceneOp { instance, edit, op } => {
+                if let Err(e) = apply_scene_op(world, instance, edit, op) {
+                    eprintln!(
+                        "[Bevy] Failed to apply an edit from another instance: {}",
+                        e
+                    );
+                }
+            }
+            BridgeCommand::RegisterCustomShader { name, source } => {
+                register_custom_shader(world, name.clone(), source.clone());
+                publish(world, SceneOp::RegisterCustomShader { name, source });
+            }
+            BridgeCommand::SetCustomShaderMaterial {
+                id,
+                material,
+                reply,
+            } => {
+                let result = set_custom_shader_material(world, id, material.clone());
+                if result.is_ok() {
+                    publish_for(world, id, |target| SceneOp::SetCustomShaderMaterial {
+                        target,
+                        material,
+                    });
+                }
+                let _ = reply.send(result);
+            }
+            BridgeCommand::ListSubmeshes { id, reply } => {
+                let _ = reply.send(list_submeshes(world, id));
+            }
+            BridgeCommand::SetSubmeshMaterial {
+                id,
+                material,
+                reply,
+            } => {
+                let result = set_submesh_material(world, id, material.clone());
+                if result.is_ok() {
+                    publish_for(world, id, |target| SceneOp::SetSubmeshMaterial {
+                        target,
+                        material,
+                    });
+                }
+                let _ = reply.send(result);
+            }
+            BridgeCommand::SetMorphWeight {
+                id,
+                target,
+                weight,
+                reply,
+            } => {
+                let result = set_morph_weight(world, id, &target, weight);
+                if result.is_ok() {
+                    publish_for(world, id, |sync_target| SceneOp::SetMorphWeight {
+                        target: sync_target,
+                        morph_target: target,
+                        weight,
+                    });
+                }
+                let _ = reply.send(result);
+            }
+            BridgeCommand::ListMorphTargets { id, reply } => {
+                let _ = reply.send(list_morph_targets(world, id));
+            }
+            BridgeCommand::SetParticleEmitter(update) => {
+                set_particle_emitter(world, update);
+            }
+            BridgeCommand::StopParticleEmitter => {
+                stop_particle_emitter(world);
+            }
+            BridgeCommand::SetDemoScene(scene) => {
+                set_demo_scene(world, scene);
+            }
+            BridgeCommand::RegenerateTerrain { seed, reply } => {
+                let _ = reply.send(regenerate_terrain(world, seed));
+            }
+            BridgeCommand::SaveCameraPreset { name, reply } => {
+                let view = world.resource::<OrbitCameraState>().view();
+                let mut presets = world.resource_mut::<CameraPresets>();
+                presets.0.insert(name, view);
+                let _ = reply.send(presets.0.clone());
+            }
+            BridgeCommand::ApplyCameraPreset {
+                name,
+                animate,
+                reply,
+            } => {
+                let _ = reply.send(apply_camera_preset(world, &name, animate));
+            }
+            BridgeCommand::LookAtEntity {
+                name,
+                track,
+                animate,
+                reply,
+            } => {
+                let _ = reply.send(look_at_entity(world, &name, track, animate));
+            }
+            BridgeCommand::SetOrbitPivot {
+                x,
+                y,
+                animate,
+                reply,
+            } => {
+                let _ = reply.send(set_orbit_pivot(world, x, y, animate).map(Vec3::to_array));
+            }
+            BridgeCommand::SetCameraTransition(settings) => {
+                world.insert_resource(CameraTransitionStyle(settings));
+            }
+            BridgeCommand::SetStandardView {
+                view,
+                animate,
+                projection,
+            } => {
+                if let Some(projection) = projection {
+                    set_projection(world, projection);
+                }
+                set_standard_view(world, view, animate);
+            }
+            BridgeCommand::SetProjection(kind) => {
+                set_projection(world, kind);
+            }
+            BridgeCommand::ShakeCamera {
+                amplitude_deg,
+                frequency_hz,
+                decay,
+            } => {
+                start_camera_shake(world, amplitude_deg, frequency_hz, decay);
+            }
+            BridgeCommand::PushIn { amount, duration_s } => {
+                start_push_in(world, amount, duration_s);
+            }
+            BridgeCommand::StopCameraEffects => {
+                stop_camera_effects(world);
+            }
+            BridgeCommand::SetCameraSensitivity(sensitivity) => {
+                world.insert_resource(CameraSensitivityRes(sensitivity));
+            }
+            BridgeCommand::SetQuadView(enabled) => {
+                set_quad_view(world, enabled);
+            }
+            BridgeCommand::GetCameraState { reply } => {
+                let _ = reply.send(camera_state(world));
+            }
+            BridgeCommand::ResetRoll => {
+                reset_roll(world);
+            }
+            BridgeCommand::SetDebugOverlay(enabled) => {
+                set_debug_overlay(world, enabled);
+            }
+            BridgeCommand::SetUiOverlay(settings) => {
+                set_ui_overlay(world, settings);
+            }
+            BridgeCommand::SetDebugView(view) => {
+                set_debug_view(world, view);
+            }
+            BridgeCommand::GetCullingStats { reply } => {
+                let _ = reply.send(culling_stats(world));
+            }
+            BridgeCommand::FreezeCulling(frozen) => {
+                freeze_culling(world, frozen);
+            }
+            BridgeCommand::RunReadbackSelfTest { reply } => {
+                start_readback_self_test(world, reply);
+            }
+            BridgeCommand::WatchSlowFrames {
+                threshold_ms,
+                frames,
+                alerts,
+            } => {
+                watch_slow_frames(world, threshold_ms, frames, alerts);
+            }
+            BridgeCommand::StopSlowFrameAlerts => {
+                stop_slow_frame_alerts(world);
+            }
+            BridgeCommand::WatchAssetProgress(updates) => {
+                watch_asset_progress(world, updates);
+            }
+            BridgeCommand::PrefetchAssets(assets) => {
+                prefetch_assets(world, assets);
+            }
+            BridgeCommand::GetPrefetchStatus { reply } => {
+                let _ = reply.send(prefetch_status(world));
+            }
+            BridgeCommand::ClearPrefetchedAssets => {
+                clear_prefetched_assets(world);
+            }
+            BridgeCommand::ListEntities { reply } => {
+                let _ = reply.send(list_entities(world));
+            }
+            BridgeCommand::InspectEntity { id, reply } => {
+                let _ = reply.send(inspect_entity(world, id));
+            }
+            BridgeCommand::ListResources { reply } => {
+                let _ = reply.send(list_resources(world));
+            }
+            BridgeCommand::SetTags { id, tags, reply } => {
+                let result = set_tags(world, id, tags.clone());
+                if result.is_ok() {
+                    publish_for(world, id, |target| SceneOp::SetTags { target, tags });
+                }
+                let _ = reply.send(result);
+            }
+            BridgeCommand::QueryEntities { tag, reply } => {
+                let _ = reply.send(query_entities(world, &tag));
+            }
+            BridgeCommand::UpdateTagged {
+                tag,
+                operation,
+                reply,
+            } => {
+                let result = update_tagged(world, &tag, operation.clone());
+                if result.is_ok() {
+                    publish(world, SceneOp::UpdateTagged { tag, operation });
+                }
+                let _ = reply.send(result);
+            }
+            BridgeCommand::CreateSelectionSet {
+                name,
+                entities,
+                reply,
+            } => {
+                let _ = reply.send(create_selection_set(world, name, entities));
+            }
+            BridgeCommand::AddToSelectionSet {
+                name,
+                entities,
+                reply,
+            } => {
+                let _ = reply.send(add_to_selection_set(world, &name, entities));
+            }
+            BridgeCommand::RemoveFromSelectionSet {
+                name,
+                entities,
+                reply,
+            } => {
+                let _ = reply.send(remove_from_selection_set(world, &name, entities));
+            }
+            BridgeCommand::DeleteSelectionSet { name, reply } => {
+                let _ = reply.send(delete_selection_set(world, &name));
+            }
+            BridgeCommand::ListSelectionSets { reply } => {
+                let _ = reply.send(list_selection_sets(world));
+            }
+            BridgeCommand::SelectSelectionSet { name, reply } => {
+                let _ = reply.send(select_selection_set(world, &name));
+            }
+            BridgeCommand::GetSelection { reply } => {
+                let _ = reply.send(selection(world));
+            }
+            BridgeCommand::UpdateSelectionSet {
+                name,
+                operation,
+                reply,
+            } => {
+                let result = update_selection_set(world, &name, operation.clone());
+                // Sets aren't synced, the members they stand for are
+                if let Ok(members) = &result {
+                    let targets: Vec<_> = members
+                        .iter()
+                        .filter_map(|member| target_for(world, member.to_bits()))
+                        .collect();
+                    if !targets.is_empty() {
+                        publish(world, SceneOp::UpdateEntities { targets, operation });
+                    }
+                }
+                let _ = reply.send(result.map(|members| members.len()));
+            }
+            BridgeCommand::Exit => {
+                println!("[Bevy] Exit requested");
+                world.write_message(AppExit::Success);
+            }
+            BridgeCommand::SetExposure(ev100) => {
+                set_exposure(world, ev100);
+            }
+            BridgeCommand::SetLightingPreset(preset) => {
+                set_lighting_preset(world, preset);
+            }
+            BridgeCommand::SetEnvironmentMap { environment, reply } => {
+                set_environment_map(world, environment, reply);
+            }
+            BridgeCommand::SetReflectionProbes { probes, reply } => {
+                set_reflection_probes(world, probes, reply);
+            }
+            BridgeCommand::SetGroundPlane(settings) => {
+                set_ground_plane(world, settings);
+            }
+            BridgeCommand::SetWater(settings) => {
+                set_water(world, settings);
+            }
+            BridgeCommand::SetTransparentBackground(transparent) => {
+                set_transparent_background(world, transparent);
+            }
+            BridgeCommand::SetDepthOfField(settings) => {
+                set_depth_of_field(world, settings);
+            }
+            BridgeCommand::FocusDepthOfField { x, y, reply } => {
+                let _ = reply.send(focus_depth_of_field(world, x, y));
+            }
+            BridgeCommand::SetFog(settings) => {
+                set_fog(world, settings);
+            }
+            BridgeCommand::SetColorLut { path, reply } => {
+                set_color_lut(world, path, reply);
+            }
+            BridgeCommand::FrameAll { animate, reply } => {
+                let _ = reply.send(frame_all(world, animate));
+            }
+            BridgeCommand::PlayCameraPath {
+                keyframes,
+                reply,
+                finished,
+            } => {
+                let _ = reply.send(play_camera_path(world, keyframes, finished));
+            }
+            BridgeCommand::SetCameraPathPaused(paused) => {
+                if let Some(mut path) = world.get_resource_mut::<CameraPath>() {
+                    path.paused = paused;
+                }
+            }
+            BridgeCommand::SeekCameraPath(time_s) => {
+                seek_camera_path(world, time_s);
+            }
+            BridgeCommand::StopCameraPath => {
+                stop_camera_path(world, false);
+            }
+        }
+    }
+}
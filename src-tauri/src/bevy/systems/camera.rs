@@ -1,25 +1,60 @@
 //! Camera control system
 //!
 //! This module implements orbit camera controls that respond to mouse input
-//! from the frontend, allowing users to rotate and zoom the camera.
+//! from the frontend, allowing users to rotate and zoom the camera. The
+//! input applied each frame can be recorded, and a recording replayed in
+//! place of live input. Saved camera presets, named entities, standard
+//! views, picked surface points and the whole scene can be jumped or
+//! smoothly moved to, with the duration and easing of the move set by
+//! `CameraTransitionStyle`.
 
-use bevy::{
-    math::Vec3,
-    prelude::*,
-};
+use bevy::camera::primitives::Aabb;
+use bevy::camera::visibility::RenderLayers;
+use bevy::camera::ScalingMode;
+use bevy::ecs::system::SystemState;
+use bevy::picking::mesh_picking::ray_cast::{MeshRayCast, MeshRayCastSettings};
+use bevy::prelude::*;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI, TAU};
 
 use crate::config::camera::*;
-use crate::bevy::components::CameraController;
-use crate::bevy::resources::{MouseInputRes, OrbitCameraState};
+use crate::bevy::components::{
+    Billboard, CameraController, GroundPlane, Particle, PickProxy, WaterPlane,
+};
+use crate::bevy::resources::{
+    CameraFocus, CameraPresets, CameraSensitivityRes, CameraTransition, CameraTransitionStyle,
+    InputRecorder, InputReplay, MouseInputRes, OrbitCameraState, SceneTime, StreamRegion,
+};
+use crate::tauri_bridge::shared_state::{
+    CameraSensitivity, CameraState, CameraView, InputSample, MouseInput, OrbitPose, ProjectionKind,
+    RegionOfInterest, StandardView,
+};
 
 /// Update camera transform based on mouse input
 /// Implements orbit camera control:
 /// - Left button drag: rotate camera (yaw/pitch)
+/// - Right button horizontal drag: roll camera around the view direction
+/// - Middle button drag: pan (move the center in the view plane)
 /// - Scroll wheel: zoom (adjust distance)
+///
+/// Speeds are scaled by [`CameraSensitivityRes`].
+///
+/// During a replay live input is discarded and the recorded samples due by
+/// the current scene time are applied instead, one at a time, exactly as
+/// they were applied live, with the sensitivity of the recording.
+///
+/// A running animated move is advanced first, live input stops it where it
+/// is.
 pub fn update_camera_from_input(
     mouse_input_res: Option<Res<MouseInputRes>>,
+    scene_time: Res<SceneTime>,
+    time: Res<Time>,
+    recorder: Option<ResMut<InputRecorder>>,
+    replay: Option<ResMut<InputReplay>>,
+    transition: Option<Res<CameraTransition>>,
+    sensitivity: Res<CameraSensitivityRes>,
     mut orbit_state: ResMut<OrbitCameraState>,
     mut camera_query: Query<&mut Transform, With<CameraController>>,
+    mut commands: Commands,
 ) {
     let Some(mouse_res) = mouse_input_res else {
         return;
@@ -39,32 +74,499 @@ pub fn update_camera_from_input(
         input
     };
 
+    if let Some(transition) = transition {
+        let elapsed = time.elapsed_secs_f64() - transition.start_s;
+        let t = (elapsed / transition.duration_s).min(1.0) as f32;
+        let eased = transition.easing.apply(t);
+        *orbit_state = transition.from.lerp(&transition.to, eased);
+        if t >= 1.0 || has_effect(&input) {
+            commands.remove_resource::<CameraTransition>();
+        }
+    }
+
+    if let Some(mut replay) = replay {
+        let sensitivity = replay.recording.sensitivity;
+        while let Some(sample) = replay.recording.samples.get(replay.next_sample) {
+            if sample.time_s > scene_time.0 {
+                break;
+            }
+            apply_mouse_input(&mut orbit_state, &sample.input, &sensitivity);
+            replay.next_sample += 1;
+        }
+    } else if has_effect(&input) {
+        apply_mouse_input(&mut orbit_state, &input, &sensitivity.0);
+        if let Some(mut recorder) = recorder {
+            recorder.samples.push(InputSample {
+                time_s: scene_time.0,
+                input,
+            });
+        }
+    }
+
+    // Update camera transform based on orbit state
+    let camera_transform = orbit_state.camera_transform();
+    for mut transform in camera_query.iter_mut() {
+        *transform = camera_transform;
+    }
+}
+
+/// Whether `input` moves the camera at all
+fn has_effect(input: &MouseInput) -> bool {
+    let dragged = input.delta_x != 0.0 || input.delta_y != 0.0;
+    (input.left_button && dragged)
+        || (input.right_button && !input.left_button && input.delta_x != 0.0)
+        || (input.middle_button && dragged)
+        || input.scroll_delta != 0.0
+}
+
+/// Apply one frame's worth of input to the orbit
+pub fn apply_mouse_input(
+    orbit_state: &mut OrbitCameraState,
+    input: &MouseInput,
+    sensitivity: &CameraSensitivity,
+) {
+    let dragged = input.delta_x != 0.0 || input.delta_y != 0.0;
+
     // Apply rotation when left button is held
-    if input.left_button && (input.delta_x != 0.0 || input.delta_y != 0.0) {
-        orbit_state.yaw -= input.delta_x * ROTATION_SPEED;
-        orbit_state.pitch -= input.delta_y * ROTATION_SPEED;
+    if input.left_button && dragged {
+        let previous_pitch = orbit_state.pitch;
+        let delta_y = if sensitivity.invert_y {
+            -input.delta_y
+        } else {
+            input.delta_y
+        };
+        orbit_state.yaw -= input.delta_x * ROTATION_SPEED * sensitivity.rotate;
+        orbit_state.pitch -= delta_y * ROTATION_SPEED * sensitivity.rotate;
 
-        // Clamp pitch to prevent camera flipping
-        orbit_state.pitch = orbit_state.pitch.clamp(MIN_PITCH, MAX_PITCH);
+        // Clamp pitch to prevent camera flipping, without snapping back
+        // from the top and bottom standard views past the limit
+        orbit_state.pitch = orbit_state
+            .pitch
+            .clamp(MIN_PITCH.min(previous_pitch), MAX_PITCH.max(previous_pitch));
+    } else if input.right_button && input.delta_x != 0.0 {
+        // Apply roll when only the right button is held, kept in -PI..PI
+        let roll = orbit_state.roll + input.delta_x * ROLL_SPEED;
+        orbit_state.roll = (roll + PI).rem_euclid(TAU) - PI;
+    } else if input.middle_button && dragged {
+        // Pan in the view plane, faster further out so the scene roughly
+        // follows the cursor
+        let rotation = orbit_state.camera_transform().rotation;
+        let scale = orbit_state.distance * PAN_SPEED * sensitivity.pan;
+        orbit_state.center += rotation * Vec3::new(-input.delta_x, input.delta_y, 0.0) * scale;
     }
 
     // Apply zoom from scroll wheel
     if input.scroll_delta != 0.0 {
-        orbit_state.distance -= input.scroll_delta * ZOOM_SPEED;
+        orbit_state.distance -= input.scroll_delta * ZOOM_SPEED * sensitivity.zoom;
         orbit_state.distance = orbit_state.distance.clamp(MIN_DISTANCE, MAX_DISTANCE);
     }
+}
 
-    // Update camera transform based on orbit state
-    for mut transform in camera_query.iter_mut() {
-        // Calculate camera position using spherical coordinates
-        // yaw: rotation around Y axis
-        // pitch: rotation around X axis (elevation)
-        let x = orbit_state.distance * orbit_state.pitch.cos() * orbit_state.yaw.sin();
-        let y = orbit_state.distance * orbit_state.pitch.sin();
-        let z = orbit_state.distance * orbit_state.pitch.cos() * orbit_state.yaw.cos();
-
-        let camera_position = orbit_state.center + Vec3::new(x, y, z);
-        *transform =
-            Transform::from_translation(camera_position).looking_at(orbit_state.center, Vec3::Y);
+/// Move the orbit camera to preset `name`, smoothly if `animate`
+///
+/// Stops following a focused entity.
+pub fn apply_camera_preset(world: &mut World, name: &str, animate: bool) -> Result<(), String> {
+    let view = *world
+        .resource::<CameraPresets>()
+        .0
+        .get(name)
+        .ok_or_else(|| format!("No camera preset named \"{}\"", name))?;
+    set_camera_view(world, view, animate);
+    println!("[Bevy] Camera preset \"{}\" applied", name);
+    Ok(())
+}
+
+/// Move the orbit camera to `view`, smoothly if `animate`
+pub fn set_camera_view(world: &mut World, view: CameraView, animate: bool) {
+    let mut to = *world.resource::<OrbitCameraState>();
+    to.set_view(view);
+
+    world.remove_resource::<CameraFocus>();
+    move_orbit(world, to, animate);
+}
+
+/// Center the orbit camera on the entity called `name`, smoothly if
+/// `animate`
+///
+/// With `track` the camera stays centered on the entity as it moves,
+/// otherwise it stays at the entity's current position.
+pub fn look_at_entity(
+    world: &mut World,
+    name: &str,
+    track: bool,
+    animate: bool,
+) -> Result<(), String> {
+    let (entity, center) = world
+        .query::<(Entity, &Name, &GlobalTransform)>()
+        .iter(world)
+        .find(|(_, entity_name, _)| entity_name.as_str() == name)
+        .map(|(entity, _, transform)| (entity, transform.translation()))
+        .ok_or_else(|| format!("No entity named \"{}\"", name))?;
+    let to = OrbitCameraState {
+        center,
+        ..*world.resource::<OrbitCameraState>()
+    };
+
+    if track {
+        world.insert_resource(CameraFocus(entity));
+    } else {
+        world.remove_resource::<CameraFocus>();
+    }
+    move_orbit(world, to, animate);
+    println!(
+        "[Bevy] Looking at \"{}\"{}",
+        name,
+        if track { ", tracking" } else { "" }
+    );
+    Ok(())
+}
+
+/// Current placement and projection of the stream camera
+///
+/// Reflects the last rendered frame, transforms are propagated after the
+/// camera moves.
+pub fn camera_state(world: &mut World) -> Result<CameraState, String> {
+    let view = world.resource::<OrbitCameraState>().view();
+    let mut cameras =
+        world.query_filtered::<(&Camera, &GlobalTransform, &Projection), With<CameraController>>();
+    let (camera, transform, projection) = cameras.single(world).map_err(|e| e.to_string())?;
+
+    let (fov_y_deg, near, far) = match projection {
+        Projection::Perspective(p) => (Some(p.fov.to_degrees()), p.near, p.far),
+        Projection::Orthographic(o) => (None, o.near, o.far),
+        _ => (None, 0.0, 0.0),
+    };
+    let view_from_world = Mat4::from(transform.affine().inverse());
+    let (_, rotation, translation) = transform.to_scale_rotation_translation();
+    Ok(CameraState {
+        view,
+        translation: translation.to_array(),
+        rotation: rotation.to_array(),
+        fov_y_deg,
+        near,
+        far,
+        viewport: camera
+            .physical_viewport_size()
+            .unwrap_or_default()
+            .to_array(),
+        view_projection: (camera.clip_from_view() * view_from_world).to_cols_array(),
+    })
+}
+
+/// Turn the orbit camera to `view`, smoothly if `animate`
+///
+/// Keeps the center and distance, and levels the camera. Top and bottom
+/// look exactly along the Y axis, past the pitch limit of mouse control.
+pub fn set_standard_view(world: &mut World, view: StandardView, animate: bool) {
+    let (yaw, pitch) = standard_angles(view);
+    let mut to = *world.resource::<OrbitCameraState>();
+    to.set_pose(OrbitPose {
+        yaw,
+        pitch,
+        distance: to.distance,
+        roll: 0.0,
+    });
+    move_orbit(world, to, animate);
+    println!("[Bevy] Standard view {:?}", view);
+}
+
+/// Yaw and pitch of a standard view
+pub(crate) fn standard_angles(view: StandardView) -> (f32, f32) {
+    match view {
+        StandardView::Front => (0.0, 0.0),
+        StandardView::Back => (PI, 0.0),
+        StandardView::Left => (-FRAC_PI_2, 0.0),
+        StandardView::Right => (FRAC_PI_2, 0.0),
+        StandardView::Top => (0.0, FRAC_PI_2),
+        StandardView::Bottom => (0.0, -FRAC_PI_2),
+        // Pitch at which all three axes appear equally foreshortened
+        StandardView::Isometric => (FRAC_PI_4, (1.0 / 2.0f32.sqrt()).atan()),
+    }
+}
+
+/// Switch the stream camera between perspective and orthographic projection
+///
+/// The orthographic view shows as much as the perspective one does at the
+/// orbit center, and zooms with the orbit distance, see
+/// [`sync_orthographic_scale`].
+pub fn set_projection(world: &mut World, kind: ProjectionKind) {
+    let projection = match kind {
+        ProjectionKind::Perspective => Projection::Perspective(PerspectiveProjection::default()),
+        ProjectionKind::Orthographic => Projection::Orthographic(OrthographicProjection {
+            scaling_mode: orthographic_scaling(world.resource::<OrbitCameraState>().distance),
+            ..OrthographicProjection::default_3d()
+        }),
+    };
+    let mut cameras = world.query_filtered::<&mut Projection, With<CameraController>>();
+    for mut camera_projection in cameras.iter_mut(world) {
+        *camera_projection = projection.clone();
+    }
+    println!("[Bevy] Projection set to {:?}", kind);
+}
+
+/// Scale an orthographic stream camera to the orbit distance
+///
+/// Scrolling changes the distance, which would otherwise not change an
+/// orthographic view at all.
+pub fn sync_orthographic_scale(
+    orbit_state: Res<OrbitCameraState>,
+    mut projections: Query<&mut Projection, With<CameraController>>,
+) {
+    for mut projection in projections.iter_mut() {
+        if let Projection::Orthographic(orthographic) = projection.as_mut() {
+            orthographic.scaling_mode = orthographic_scaling(orbit_state.distance);
+        }
+    }
+}
+
+/// Orthographic view height matching the default perspective view at
+/// `distance`
+pub(crate) fn orthographic_scaling(distance: f32) -> ScalingMode {
+    let half_fov = PerspectiveProjection::default().fov / 2.0;
+    ScalingMode::FixedVertical {
+        viewport_height: 2.0 * distance * half_fov.tan(),
+    }
+}
+
+/// Level the camera smoothly, undoing any roll
+pub fn reset_roll(world: &mut World) {
+    let to = OrbitCameraState {
+        roll: 0.0,
+        ..*world.resource::<OrbitCameraState>()
+    };
+    move_orbit(world, to, true);
+}
+
+/// Orbit around the mesh surface under point `(x, y)` of the streamed image,
+/// smoothly if `animate`
+///
+/// The camera stays where it is and turns to face the new center. Returns
+/// the picked point.
+pub fn set_orbit_pivot(world: &mut World, x: f32, y: f32, animate: bool) -> Result<Vec3, String> {
+    let pivot = pick_surface(world, x, y)?;
+    let orbit = *world.resource::<OrbitCameraState>();
+    let to = OrbitCameraState {
+        roll: orbit.roll,
+        ..OrbitCameraState::looking_from(orbit.camera_transform().translation, pivot)
+    };
+    world.remove_resource::<CameraFocus>();
+    move_orbit(world, to, animate);
+    println!(
+        "[Bevy] Orbit pivot set to ({:.2}, {:.2}, {:.2})",
+        pivot.x, pivot.y, pivot.z
+    );
+    Ok(pivot)
+}
+
+/// Mesh surface point under point `(x, y)` of the streamed image
+///
+/// `x` and `y` run from `0` to `1`, left to right and top to bottom, over
+/// the region of interest if one is set.
+pub(crate) fn pick_surface(world: &mut World, x: f32, y: f32) -> Result<Vec3, String> {
+    let region = world.resource::<StreamRegion>().0;
+    let mut state = SystemState::<(
+        Query<(&Camera, &GlobalTransform), With<CameraController>>,
+        Query<(), With<PickProxy>>,
+        MeshRayCast,
+    )>::new(world);
+    let (cameras, proxies, mut ray_cast) = state.get_mut(world);
+
+    let (camera, camera_transform) = cameras.single().map_err(|e| e.to_string())?;
+    let cursor = stream_cursor(camera, region, x, y)?;
+    let ray = camera
+        .viewport_to_world(camera_transform, cursor)
+        .map_err(|e| e.to_string())?;
+    let filter = |entity: Entity| !proxies.contains(entity);
+    ray_cast
+        .cast_ray(ray, &MeshRayCastSettings::default().with_filter(&filter))
+        .first()
+        .map(|(_, hit)| hit.point)
+        .ok_or_else(|| "Nothing under the cursor".to_string())
+}
+
+/// Viewport position of point `(x, y)` of the streamed image, see
+/// [`pick_surface`]
+pub(crate) fn stream_cursor(
+    camera: &Camera,
+    region: Option<RegionOfInterest>,
+    x: f32,
+    y: f32,
+) -> Result<Vec2, String> {
+    let viewport = match region {
+        Some(region) => Rect::new(
+            region.x as f32,
+            region.y as f32,
+            (region.x + region.width) as f32,
+            (region.y + region.height) as f32,
+        ),
+        None => Rect::from_corners(
+            Vec2::ZERO,
+            camera
+                .logical_viewport_size()
+                .ok_or("Camera has no viewport yet")?,
+        ),
+    };
+    Ok(viewport.min + viewport.size() * Vec2::new(x, y))
+}
+
+/// Center and zoom the orbit camera so every visible mesh fits in view,
+/// smoothly if `animate`
+///
+/// Keeps the camera's angle and stops following a focused entity.
+pub fn frame_all(world: &mut World, animate: bool) -> Result<(), String> {
+    let (min, max) = scene_bounds(world).ok_or("Nothing visible to frame")?;
+    let projection = world
+        .query_filtered::<&Projection, With<CameraController>>()
+        .iter(world)
+        .next()
+        .cloned();
+
+    // Fit the bounding sphere into the narrower field of view
+    let radius = (max - min).length() / 2.0;
+    let half_fov = narrowest_fov(projection) / 2.0;
+    let distance = radius * (1.0 + FRAME_MARGIN) / half_fov.sin();
+    let to = OrbitCameraState {
+        center: (min + max) / 2.0,
+        distance: distance.clamp(MIN_DISTANCE, MAX_DISTANCE),
+        ..*world.resource::<OrbitCameraState>()
+    };
+
+    world.remove_resource::<CameraFocus>();
+    move_orbit(world, to, animate);
+    println!(
+        "[Bevy] Framed scene, radius {:.2} at distance {:.2}",
+        radius, to.distance
+    );
+    Ok(())
+}
+
+/// World-space bounds of every visible mesh as min and max, leaving out
+/// the ground plane, water, particles, billboards and GPU pick copies,
+/// `None` if nothing is visible
+pub(crate) fn scene_bounds(world: &mut World) -> Option<(Vec3, Vec3)> {
+    world
+        .query_filtered::<(&Aabb, &GlobalTransform, &InheritedVisibility), (
+            Without<GroundPlane>,
+            Without<WaterPlane>,
+            Without<Particle>,
+            Without<Billboard>,
+            Without<PickProxy>,
+        )>()
+        .iter(world)
+        .filter(|(_, _, visibility)| visibility.get())
+        .map(|(aabb, transform, _)| world_bounds(aabb, transform))
+        .reduce(|(min_a, max_a), (min_b, max_b)| (min_a.min(min_b), max_a.max(max_b)))
+}
+
+/// World-space corners of `aabb` under `transform`, as min and max
+fn world_bounds(aabb: &Aabb, transform: &GlobalTransform) -> (Vec3, Vec3) {
+    let center = Vec3::from(aabb.center);
+    let half_extents = Vec3::from(aabb.half_extents);
+    let mut min = Vec3::splat(f32::MAX);
+    let mut max = Vec3::splat(f32::MIN);
+    for corner in 0..8 {
+        let sign = Vec3::new(
+            if corner & 1 == 0 { -1.0 } else { 1.0 },
+            if corner & 2 == 0 { -1.0 } else { 1.0 },
+            if corner & 4 == 0 { -1.0 } else { 1.0 },
+        );
+        let point = transform.transform_point(center + half_extents * sign);
+        min = min.min(point);
+        max = max.max(point);
+    }
+    (min, max)
+}
+
+/// World units one logical pixel of `camera`'s view spans at `depth` in
+/// front of it, `None` until the size of its target is known
+pub(crate) fn world_units_per_pixel(
+    camera: &Camera,
+    projection: &Projection,
+    depth: f32,
+) -> Option<f32> {
+    let viewport = camera.logical_viewport_size()?;
+    let visible_height = match projection {
+        Projection::Perspective(perspective) => 2.0 * depth * (perspective.fov / 2.0).tan(),
+        Projection::Orthographic(orthographic) => orthographic.area.height(),
+        _ => return None,
+    };
+    (viewport.y > 0.0 && visible_height > 0.0).then(|| visible_height / viewport.y)
+}
+
+/// Make the stream camera draw render `layer` as well as the default one,
+/// or stop drawing it
+pub(crate) fn show_render_layer(world: &mut World, layer: usize, visible: bool) {
+    let mut cameras =
+        world.query_filtered::<(Entity, Option<&RenderLayers>), With<CameraController>>();
+    let updated: Vec<(Entity, RenderLayers)> = cameras
+        .iter(world)
+        .map(|(camera, layers)| {
+            let layers = layers.cloned().unwrap_or_default();
+            let layers = if visible {
+                layers.with(layer)
+            } else {
+                layers.without(layer)
+            };
+            (camera, layers)
+        })
+        .collect();
+    for (camera, layers) in updated {
+        world.entity_mut(camera).insert(layers);
+    }
+}
+
+/// Smaller of the vertical and horizontal field of view (radians)
+///
+/// Falls back to Bevy's default perspective for other projections.
+fn narrowest_fov(projection: Option<Projection>) -> f32 {
+    let perspective = match projection {
+        Some(Projection::Perspective(perspective)) => perspective,
+        _ => PerspectiveProjection::default(),
+    };
+    let horizontal = 2.0 * ((perspective.fov / 2.0).tan() * perspective.aspect_ratio).atan();
+    perspective.fov.min(horizontal)
+}
+
+/// Keep the orbit centered on the [`CameraFocus`] entity
+///
+/// During an animated move the move's end is updated instead, so the
+/// camera eases onto the entity wherever it has got to.
+pub fn follow_camera_focus(
+    focus: Option<Res<CameraFocus>>,
+    targets: Query<&GlobalTransform>,
+    transition: Option<ResMut<CameraTransition>>,
+    mut orbit_state: ResMut<OrbitCameraState>,
+    mut commands: Commands,
+) {
+    let Some(focus) = focus else {
+        return;
+    };
+    let Ok(target) = targets.get(focus.0) else {
+        commands.remove_resource::<CameraFocus>();
+        return;
+    };
+    match transition {
+        Some(mut transition) => transition.to.center = target.translation(),
+        None => orbit_state.center = target.translation(),
+    }
+}
+
+/// Move to `to` as set by [`CameraTransitionStyle`] if `animate`,
+/// otherwise jump there
+fn move_orbit(world: &mut World, to: OrbitCameraState, animate: bool) {
+    let style = world.resource::<CameraTransitionStyle>().0;
+    if animate && style.duration_s > 0.0 {
+        let from = *world.resource::<OrbitCameraState>();
+        let start_s = world.resource::<Time>().elapsed_secs_f64();
+        world.insert_resource(CameraTransition {
+            from,
+            to,
+            start_s,
+            duration_s: style.duration_s as f64,
+            easing: style.easing,
+        });
+    } else {
+        world.remove_resource::<CameraTransition>();
+        world.insert_resource(to);
     }
 }
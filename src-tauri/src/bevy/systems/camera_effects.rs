@@ -0,0 +1,101 @@
+//! Cinematic camera effects
+//!
+//! Small effects that make demo recordings feel less static: a decaying
+//! handheld shake layered on top of whatever drives the camera, and a slow
+//! push-in toward the orbit center. Both follow Bevy's clock, so they render
+//! the same in deterministic mode.
+
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+use crate::bevy::components::CameraController;
+use crate::bevy::resources::{CameraShake, CameraTransition, OrbitCameraState};
+use crate::config::camera::{MIN_DISTANCE, PUSH_IN_AMOUNT, PUSH_IN_SECONDS};
+use crate::tauri_bridge::shared_state::CameraEasing;
+
+/// Shake below this fraction of its starting amplitude ends
+const SHAKE_END_FRACTION: f32 = 0.01;
+
+/// Start shaking the camera, replacing any running shake
+pub fn start_camera_shake(world: &mut World, amplitude_deg: f32, frequency_hz: f32, decay: f32) {
+    let start_s = world.resource::<Time>().elapsed_secs_f64();
+    world.insert_resource(CameraShake {
+        amplitude: amplitude_deg.to_radians(),
+        frequency_hz,
+        decay,
+        start_s,
+    });
+    println!(
+        "[Bevy] Camera shake, {:.2} deg at {:.1} Hz",
+        amplitude_deg, frequency_hz
+    );
+}
+
+/// Move the camera `amount` of the way to the orbit center over
+/// `duration_s`, easing in and out
+///
+/// Runs as an animated camera move, so live input stops it.
+pub fn start_push_in(world: &mut World, amount: Option<f32>, duration_s: Option<f32>) {
+    let from = *world.resource::<OrbitCameraState>();
+    let amount = amount.unwrap_or(PUSH_IN_AMOUNT);
+    let to = OrbitCameraState {
+        distance: (from.distance * (1.0 - amount)).max(MIN_DISTANCE),
+        ..from
+    };
+    let start_s = world.resource::<Time>().elapsed_secs_f64();
+    world.insert_resource(CameraTransition {
+        from,
+        to,
+        start_s,
+        duration_s: duration_s.unwrap_or(PUSH_IN_SECONDS) as f64,
+        easing: CameraEasing::SmoothStep,
+    });
+    println!(
+        "[Bevy] Camera push-in from {:.2} to {:.2}",
+        from.distance, to.distance
+    );
+}
+
+/// End the shake and any push-in or other animated move
+pub fn stop_camera_effects(world: &mut World) {
+    world.remove_resource::<CameraShake>();
+    world.remove_resource::<CameraTransition>();
+}
+
+/// Rotate the camera by the current shake offset
+///
+/// Runs after everything else that places the camera, which sets its
+/// transform from scratch each frame, so offsets never accumulate.
+pub fn apply_camera_shake(
+    time: Res<Time>,
+    shake: Option<Res<CameraShake>>,
+    mut camera_query: Query<&mut Transform, With<CameraController>>,
+    mut commands: Commands,
+) {
+    let Some(shake) = shake else {
+        return;
+    };
+    let t = (time.elapsed_secs_f64() - shake.start_s) as f32;
+    let envelope = (-shake.decay * t).exp();
+    if envelope < SHAKE_END_FRACTION {
+        commands.remove_resource::<CameraShake>();
+        return;
+    }
+
+    let amplitude = shake.amplitude * envelope;
+    let phase = TAU * shake.frequency_hz * t;
+    let offset = Quat::from_euler(
+        EulerRot::YXZ,
+        amplitude * wobble(phase, 0.0),
+        amplitude * wobble(phase, 1.7),
+        amplitude * 0.5 * wobble(phase, 3.1),
+    );
+    for mut transform in camera_query.iter_mut() {
+        transform.rotation *= offset;
+    }
+}
+
+/// Smooth pseudo-random signal in `-1..1`, decorrelated by `seed`
+fn wobble(phase: f32, seed: f32) -> f32 {
+    0.6 * (phase + seed).sin() + 0.4 * (2.31 * phase + 2.0 * seed).sin()
+}
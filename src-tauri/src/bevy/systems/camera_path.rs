@@ -0,0 +1,154 @@
+//! Camera flythrough paths
+//!
+//! The frontend submits keyframes of camera position, look-at target and
+//! time, and the stream camera flies through them along a Catmull-Rom
+//! spline. Path time advances with Bevy's clock, so in deterministic mode
+//! a flythrough renders the same frames on every run. When the path ends,
+//! the orbit controller takes over from the last pose.
+
+use bevy::prelude::*;
+use crossbeam_channel::Sender;
+
+use crate::bevy::components::CameraController;
+use crate::bevy::resources::{CameraFocus, CameraPath, CameraTransition, OrbitCameraState};
+use crate::tauri_bridge::shared_state::{CameraKeyframe, CameraPathEnd};
+
+/// Start flying along `keyframes`, ending any path already running
+pub fn play_camera_path(
+    world: &mut World,
+    mut keyframes: Vec<CameraKeyframe>,
+    finished: Sender<CameraPathEnd>,
+) -> Result<(), String> {
+    keyframes.sort_by(|a, b| a.time_s.total_cmp(&b.time_s));
+    if keyframes.len() < 2 {
+        return Err("A camera path needs at least two keyframes".to_string());
+    }
+    let distinct = keyframes
+        .windows(2)
+        .all(|pair| pair[0].time_s < pair[1].time_s);
+    if !distinct || keyframes.iter().any(|key| !key.time_s.is_finite()) {
+        return Err("Keyframe times must be finite and distinct".to_string());
+    }
+
+    stop_camera_path(world, false);
+    world.remove_resource::<CameraTransition>();
+    world.remove_resource::<CameraFocus>();
+    println!(
+        "[Bevy] Playing camera path with {} keyframes over {:.1}s",
+        keyframes.len(),
+        keyframes[keyframes.len() - 1].time_s - keyframes[0].time_s
+    );
+    let time_s = keyframes[0].time_s;
+    world.insert_resource(CameraPath {
+        keyframes,
+        time_s,
+        paused: false,
+        finished,
+    });
+    Ok(())
+}
+
+/// End the running path, if any, handing the camera back to the orbit
+/// controller at its current pose
+pub fn stop_camera_path(world: &mut World, completed: bool) {
+    let Some(path) = world.remove_resource::<CameraPath>() else {
+        return;
+    };
+    let (position, target) = sample(&path.keyframes, path.time_s);
+    world.insert_resource(OrbitCameraState::looking_from(position, target));
+    let _ = path.finished.send(CameraPathEnd {
+        completed,
+        time_s: path.time_s,
+    });
+}
+
+/// Jump to `time_s` on the running path, clamped to its keyframes
+pub fn seek_camera_path(world: &mut World, time_s: f64) {
+    if let Some(mut path) = world.get_resource_mut::<CameraPath>() {
+        let start_s = path.keyframes[0].time_s;
+        let end_s = path.keyframes[path.keyframes.len() - 1].time_s;
+        path.time_s = time_s.clamp(start_s, end_s);
+    }
+}
+
+/// Move the camera along the running path
+///
+/// Runs after `update_camera_from_input`, so the path overrides the orbit
+/// while it plays.
+pub fn advance_camera_path(
+    time: Res<Time>,
+    path: Option<ResMut<CameraPath>>,
+    mut orbit_state: ResMut<OrbitCameraState>,
+    mut camera_query: Query<&mut Transform, With<CameraController>>,
+    mut commands: Commands,
+) {
+    let Some(mut path) = path else {
+        return;
+    };
+    let end_s = path.keyframes[path.keyframes.len() - 1].time_s;
+    if !path.paused {
+        path.time_s = (path.time_s + time.delta_secs_f64()).min(end_s);
+    }
+
+    let (position, target) = sample(&path.keyframes, path.time_s);
+    let transform = Transform::from_translation(position).looking_at(target, Vec3::Y);
+    for mut camera_transform in camera_query.iter_mut() {
+        *camera_transform = transform;
+    }
+
+    if !path.paused && path.time_s >= end_s {
+        *orbit_state = OrbitCameraState::looking_from(position, target);
+        let _ = path.finished.send(CameraPathEnd {
+            completed: true,
+            time_s: path.time_s,
+        });
+        commands.remove_resource::<CameraPath>();
+        println!("[Bevy] Camera path finished");
+    }
+}
+
+/// Camera position and target at `time_s`, clamped to the path
+fn sample(keyframes: &[CameraKeyframe], time_s: f64) -> (Vec3, Vec3) {
+    let last = keyframes.len() - 1;
+    let segment = keyframes
+        .windows(2)
+        .position(|pair| time_s < pair[1].time_s)
+        .unwrap_or(last - 1);
+    let (start, end) = (&keyframes[segment], &keyframes[segment + 1]);
+    let duration = end.time_s - start.time_s;
+    let s = ((time_s - start.time_s) / duration).clamp(0.0, 1.0) as f32;
+
+    let position = catmull_rom(keyframes, segment, s, |key| Vec3::from(key.position));
+    let target = catmull_rom(keyframes, segment, s, |key| Vec3::from(key.target));
+    (position, target)
+}
+
+/// Point `s` of the way through `segment` on a Catmull-Rom spline through
+/// `value` of each keyframe
+///
+/// Tangents are scaled by keyframe spacing, so uneven keyframe times don't
+/// make the camera overshoot. The spline passes through every keyframe.
+fn catmull_rom(
+    keyframes: &[CameraKeyframe],
+    segment: usize,
+    s: f32,
+    value: impl Fn(&CameraKeyframe) -> Vec3,
+) -> Vec3 {
+    let tangent = |i: usize| {
+        let before = i.saturating_sub(1);
+        let after = (i + 1).min(keyframes.len() - 1);
+        let span = (keyframes[after].time_s - keyframes[before].time_s) as f32;
+        (value(&keyframes[after]) - value(&keyframes[before])) / span
+    };
+    let (p0, p1) = (value(&keyframes[segment]), value(&keyframes[segment + 1]));
+    let (m0, m1) = (tangent(segment), tangent(segment + 1));
+    let h = (keyframes[segment + 1].time_s - keyframes[segment].time_s) as f32;
+
+    // Cubic Hermite basis
+    let s2 = s * s;
+    let s3 = s2 * s;
+    p0 * (2.0 * s3 - 3.0 * s2 + 1.0)
+        + m0 * h * (s3 - 2.0 * s2 + s)
+        + p1 * (-2.0 * s3 + 3.0 * s2)
+        + m1 * h * (s3 - s2)
+}
@@ -0,0 +1,384 @@
+//! Collision meshes for the loaded model
+//!
+//! Game engines collide against simpler shapes than the meshes they draw.
+//! `collision_meshes` builds them from the visible meshes of the model
+//! opened with `load_model`: convex hulls, or decimated copies that merge
+//! the vertices within each cell of a grid. They're written as a .glb with
+//! positions only, in the model's space, one node per mesh or one for the
+//! whole model.
+
+use bevy::asset::RenderAssetUsages;
+use bevy::math::Affine3A;
+use bevy::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
+use bevy::prelude::*;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+
+use crate::bevy::components::LoadedModel;
+use crate::bevy::systems::custom_shaders::descendants;
+use crate::bevy::systems::gltf_export::{mesh_primitive, GlbBuilder};
+use crate::config::collision::{DEFAULT_RESOLUTION, HULL_RESOLUTION, MAX_HULL_VERTICES};
+use crate::tauri_bridge::shared_state::{
+    CollisionExportSummary, CollisionMeshSettings, CollisionShape,
+};
+
+/// Triangles with their own vertices, in the model's space
+#[derive(Default)]
+struct Part {
+    positions: Vec<Vec3>,
+    triangles: Vec<[u32; 3]>,
+}
+
+/// Collision meshes of the loaded model as a .glb file, with what went
+/// into it
+///
+/// The summary's `path` is left for the caller to fill in.
+pub fn collision_meshes(
+    world: &mut World,
+    settings: CollisionMeshSettings,
+) -> Result<(Vec<u8>, CollisionExportSummary), String> {
+    let (root, root_transform) = world
+        .query_filtered::<(Entity, &GlobalTransform), With<LoadedModel>>()
+        .iter(world)
+        .next()
+        .map(|(entity, transform)| (entity, *transform))
+        .ok_or("No model is loaded")?;
+    let in_model: HashSet<Entity> = descendants(world, root).into_iter().collect();
+    let to_model = root_transform.affine().inverse();
+    let root_name = world
+        .get::<Name>(root)
+        .map_or_else(|| "model".to_string(), |name| name.to_string());
+
+    let mut summary = CollisionExportSummary::default();
+    let mut parts: Vec<(String, Part)> = Vec::new();
+    let mut query = world.query::<(
+        Entity,
+        Option<&Name>,
+        &GlobalTransform,
+        &InheritedVisibility,
+        &Mesh3d,
+    )>();
+    let meshes = world.resource::<Assets<Mesh>>();
+    for (entity, name, transform, visibility, mesh) in query.iter(world) {
+        if !visibility.get() || !in_model.contains(&entity) {
+            continue;
+        }
+        let label = name.map_or_else(|| format!("entity_{}", entity.index()), |n| n.to_string());
+        let part = meshes
+            .get(mesh.id())
+            .ok_or_else(|| "mesh data is not kept on the CPU".to_string())
+            .and_then(|mesh| Part::from_mesh(mesh, to_model * transform.affine()));
+        match part {
+            Ok(part) => parts.push((label, part)),
+            Err(e) => summary.skipped.push(format!("{}: {}", label, e)),
+        }
+    }
+    if settings.merge && !parts.is_empty() {
+        let mut merged = Part::default();
+        for (_, part) in parts.drain(..) {
+            let offset = merged.positions.len() as u32;
+            merged.positions.extend(part.positions);
+            merged.triangles.extend(
+                part.triangles
+                    .iter()
+                    .map(|triangle| triangle.map(|index| index + offset)),
+            );
+        }
+        parts.push((root_name, merged));
+    }
+
+    let mut glb = GlbBuilder::default();
+    let mut nodes = Vec::new();
+    let mut gltf_meshes = Vec::new();
+    for (label, part) in parts {
+        let shape = match settings.shape {
+            CollisionShape::ConvexHull => convex_hull(&part.positions),
+            CollisionShape::Decimated => Ok(decimate(
+                &part,
+                settings.resolution.unwrap_or(DEFAULT_RESOLUTION),
+            )),
+        };
+        let shape = match shape {
+            Ok(shape) if !shape.triangles.is_empty() => shape,
+            Ok(_) => {
+                summary
+                    .skipped
+                    .push(format!("{}: no triangles left", label));
+                continue;
+            }
+            Err(e) => {
+                summary.skipped.push(format!("{}: {}", label, e));
+                continue;
+            }
+        };
+        summary.vertices += shape.positions.len();
+        summary.triangles += shape.triangles.len();
+        let primitive = mesh_primitive(&mut glb, &shape.to_mesh())?;
+        gltf_meshes.push(json!({ "primitives": [primitive] }));
+        nodes.push(json!({
+            "name": format!("{}_collision", label),
+            "mesh": gltf_meshes.len() - 1,
+        }));
+    }
+
+    if nodes.is_empty() {
+        return Err("No collision meshes could be built".to_string());
+    }
+    summary.meshes = gltf_meshes.len();
+    let document = json!({
+        "asset": { "version": "2.0", "generator": "Tauri-Bevy Demo" },
+        "scene": 0,
+        "scenes": [{ "nodes": (0..nodes.len()).collect::<Vec<_>>() }],
+        "nodes": nodes,
+        "meshes": gltf_meshes,
+    });
+    let bytes = glb.finish(document)?;
+    println!(
+        "[Bevy] Built {} collision meshes with {} triangles ({} skipped)",
+        summary.meshes,
+        summary.triangles,
+        summary.skipped.len()
+    );
+    Ok((bytes, summary))
+}
+
+impl Part {
+    /// Triangles of `mesh`, transformed by `transform`
+    fn from_mesh(mesh: &Mesh, transform: Affine3A) -> Result<Self, String> {
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            return Err("not a triangle list".to_string());
+        }
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            return Err("no float positions".to_string());
+        };
+        let indices: Vec<u32> = match mesh.indices() {
+            Some(indices) => indices.iter().map(|index| index as u32).collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+        Ok(Self {
+            positions: positions
+                .iter()
+                .map(|position| transform.transform_point3(Vec3::from_array(*position)))
+                .collect(),
+            triangles: indices
+                .chunks_exact(3)
+                .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+                .collect(),
+        })
+    }
+
+    /// Drop vertices no triangle uses
+    fn compact(self) -> Self {
+        let mut remap: HashMap<u32, u32> = HashMap::new();
+        let mut positions = Vec::new();
+        let triangles = self
+            .triangles
+            .iter()
+            .map(|triangle| {
+                triangle.map(|index| {
+                    *remap.entry(index).or_insert_with(|| {
+                        positions.push(self.positions[index as usize]);
+                        positions.len() as u32 - 1
+                    })
+                })
+            })
+            .collect();
+        Self {
+            positions,
+            triangles,
+        }
+    }
+
+    fn to_mesh(&self) -> Mesh {
+        let positions: Vec<[f32; 3]> = self.positions.iter().map(|p| p.to_array()).collect();
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_indices(Indices::U32(self.triangles.concat()))
+    }
+}
+
+/// Convex hull of `points`, with at most `MAX_HULL_VERTICES` vertices
+///
+/// Points are merged on a grid first, keeping the one farthest out in each
+/// cell, and the grid coarsened until the hull is small enough, so the
+/// hull may cut off a little of sharp corners.
+fn convex_hull(points: &[Vec3]) -> Result<Part, String> {
+    let (min, max) = bounds(points);
+    let extent = (max - min).max_element();
+    if extent.is_nan() || extent <= 0.0 {
+        return Err("no volume".to_string());
+    }
+    let center = (min + max) / 2.0;
+    let epsilon = extent * 1e-5;
+    let mut cell = extent / HULL_RESOLUTION as f32;
+    loop {
+        let mut farthest: HashMap<IVec3, Vec3> = HashMap::new();
+        for &point in points {
+            let key = ((point - min) / cell).floor().as_ivec3();
+            let kept = farthest.entry(key).or_insert(point);
+            if point.distance_squared(center) > kept.distance_squared(center) {
+                *kept = point;
+            }
+        }
+        let merged: Vec<Vec3> = farthest.into_values().collect();
+        let triangles = hull(&merged, epsilon).ok_or("flat, so it has no hull")?;
+        let part = Part {
+            positions: merged,
+            triangles,
+        }
+        .compact();
+        if part.positions.len() <= MAX_HULL_VERTICES {
+            return Ok(part);
+        }
+        cell *= 2.0;
+    }
+}
+
+/// A face of a hull being built, wound counter-clockwise seen from outside
+struct Face {
+    corners: [u32; 3],
+    normal: Vec3,
+}
+
+impl Face {
+    fn new(points: &[Vec3], corners: [u32; 3]) -> Self {
+        let [a, b, c] = corners.map(|corner| points[corner as usize]);
+        Self {
+            corners,
+            normal: (b - a).cross(c - a).normalize_or_zero(),
+        }
+    }
+
+    /// Distance of `point` in front of the face
+    fn distance(&self, points: &[Vec3], point: Vec3) -> f32 {
+        self.normal.dot(point - points[self.corners[0] as usize])
+    }
+
+    fn edges(&self) -> [(u32, u32); 3] {
+        let [a, b, c] = self.corners;
+        [(a, b), (b, c), (c, a)]
+    }
+}
+
+/// Triangles of the convex hull of `points`, built by adding one point at
+/// a time, `None` if the points are flat
+fn hull(points: &[Vec3], epsilon: f32) -> Option<Vec<[u32; 3]>> {
+    // Start from a tetrahedron of points far apart
+    let farthest = |distance: &dyn Fn(Vec3) -> f32| {
+        (0..points.len() as u32)
+            .max_by(|a, b| distance(points[*a as usize]).total_cmp(&distance(points[*b as usize])))
+            .filter(|&index| distance(points[index as usize]) > epsilon)
+    };
+    let a = (0..points.len() as u32)
+        .min_by(|a, b| points[*a as usize].x.total_cmp(&points[*b as usize].x))?;
+    let pa = points[a as usize];
+    let b = farthest(&|point| point.distance(pa))?;
+    let pb = points[b as usize];
+    let c = farthest(&|point| (point - pa).cross(pb - pa).length() / pa.distance(pb))?;
+    let normal = (pb - pa).cross(points[c as usize] - pa).normalize();
+    let d = farthest(&|point| normal.dot(point - pa).abs())?;
+
+    let inside = [a, b, c, d]
+        .iter()
+        .map(|&index| points[index as usize])
+        .sum::<Vec3>()
+        / 4.0;
+    let mut faces: Vec<Face> = [[a, b, c], [a, c, d], [a, d, b], [b, d, c]]
+        .into_iter()
+        .map(|[a, b, c]| {
+            let face = Face::new(points, [a, b, c]);
+            if face.distance(points, inside) > 0.0 {
+                Face::new(points, [a, c, b])
+            } else {
+                face
+            }
+        })
+        .collect();
+
+    for index in 0..points.len() as u32 {
+        let point = points[index as usize];
+        let visible: Vec<bool> = faces
+            .iter()
+            .map(|face| face.distance(points, point) > epsilon)
+            .collect();
+        if !visible.contains(&true) {
+            continue;
+        }
+        // Edges between seen and unseen faces are joined to the point
+        let seen_edges: HashSet<(u32, u32)> = faces
+            .iter()
+            .zip(&visible)
+            .filter(|(_, visible)| **visible)
+            .flat_map(|(face, _)| face.edges())
+            .collect();
+        let mut next = Vec::with_capacity(faces.len());
+        for (face, visible) in faces.into_iter().zip(visible) {
+            if !visible {
+                next.push(face);
+                continue;
+            }
+            for (from, to) in face.edges() {
+                if !seen_edges.contains(&(to, from)) {
+                    next.push(Face::new(points, [from, to, index]));
+                }
+            }
+        }
+        faces = next;
+    }
+    Some(faces.into_iter().map(|face| face.corners).collect())
+}
+
+/// `part` with the vertices in each of `resolution` grid cells along its
+/// longest side merged into their average, dropping triangles that
+/// collapse
+fn decimate(part: &Part, resolution: u32) -> Part {
+    let (min, max) = bounds(&part.positions);
+    let cell = ((max - min).max_element() / resolution as f32).max(f32::MIN_POSITIVE);
+    let mut cells: HashMap<IVec3, u32> = HashMap::new();
+    let mut sums: Vec<(Vec3, f32)> = Vec::new();
+    let remap: Vec<u32> = part
+        .positions
+        .iter()
+        .map(|&position| {
+            let key = ((position - min) / cell).floor().as_ivec3();
+            let merged = *cells.entry(key).or_insert_with(|| {
+                sums.push((Vec3::ZERO, 0.0));
+                sums.len() as u32 - 1
+            });
+            let (sum, count) = &mut sums[merged as usize];
+            *sum += position;
+            *count += 1.0;
+            merged
+        })
+        .collect();
+
+    let mut seen: HashSet<[u32; 3]> = HashSet::new();
+    let triangles = part
+        .triangles
+        .iter()
+        .map(|triangle| triangle.map(|index| remap[index as usize]))
+        .filter(|&[a, b, c]| a != b && b != c && c != a)
+        .filter(|triangle| {
+            let mut key = *triangle;
+            key.sort_unstable();
+            seen.insert(key)
+        })
+        .collect();
+    Part {
+        positions: sums.iter().map(|(sum, count)| *sum / *count).collect(),
+        triangles,
+    }
+    .compact()
+}
+
+fn bounds(points: &[Vec3]) -> (Vec3, Vec3) {
+    points.iter().fold(
+        (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+        |(min, max), &point| (min.min(point), max.max(point)),
+    )
+}
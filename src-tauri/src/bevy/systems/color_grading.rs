@@ -0,0 +1,67 @@
+//! Color grading LUTs
+//!
+//! `set_color_lut` loads a `.cube` file through `plugins::color_lut` and
+//! puts it on the stream camera, whose image is graded through it from
+//! the next frame it is loaded in. Screenshots and turntables copy it like
+//! the other camera effects.
+
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+use crossbeam_channel::Sender;
+use std::path::PathBuf;
+
+use crate::bevy::components::CameraController;
+use crate::bevy::plugins::color_lut::ColorLut;
+use crate::bevy::resources::ColorLutLoads;
+
+/// Grade the stream through the LUT at `path`, or stop grading if `None`
+pub fn set_color_lut(world: &mut World, path: Option<PathBuf>, reply: Sender<Result<(), String>>) {
+    let cameras: Vec<Entity> = world
+        .query_filtered::<Entity, With<CameraController>>()
+        .iter(world)
+        .collect();
+    let Some(path) = path else {
+        for camera in cameras {
+            world.entity_mut(camera).remove::<ColorLut>();
+        }
+        println!("[Bevy] Color LUT removed");
+        let _ = reply.send(Ok(()));
+        return;
+    };
+
+    let lut: Handle<Image> = world.resource::<AssetServer>().load_override(path.clone());
+    for camera in cameras {
+        world.entity_mut(camera).insert(ColorLut(lut.clone()));
+    }
+    println!("[Bevy] Loading color LUT {}", path.display());
+    world.resource_mut::<ColorLutLoads>().0.push((lut, reply));
+}
+
+/// Reply to `set_color_lut` once its LUT loaded, or remove the LUT again
+/// if it failed
+pub fn track_color_lut_loads(
+    mut loads: ResMut<ColorLutLoads>,
+    asset_server: Res<AssetServer>,
+    cameras: Query<(Entity, &ColorLut)>,
+    mut commands: Commands,
+) {
+    loads
+        .0
+        .retain(|(lut, reply)| match asset_server.get_load_state(lut.id()) {
+            Some(LoadState::Failed(e)) => {
+                eprintln!("[Bevy] Failed to load color LUT: {}", e);
+                for (camera, active) in &cameras {
+                    if active.0 == *lut {
+                        commands.entity(camera).remove::<ColorLut>();
+                    }
+                }
+                let _ = reply.send(Err(e.to_string()));
+                false
+            }
+            Some(LoadState::Loaded) => {
+                let _ = reply.send(Ok(()));
+                false
+            }
+            _ => true,
+        });
+}
@@ -0,0 +1,115 @@
+//! Frustum culling diagnostics
+//!
+//! Counts how many meshes each camera drew, had hidden by `Visibility` or
+//! culled against its frustum, for tracking down objects that disappear.
+//! Freezing culling keeps the stream camera's frustum where it is while the
+//! camera moves on, so flying around shows what the frozen view culls.
+
+use bevy::camera::{
+    primitives::Frustum,
+    visibility::{RenderLayers, VisibleEntities},
+    CameraProjection,
+};
+use bevy::prelude::*;
+use std::any::TypeId;
+use std::collections::HashSet;
+
+use crate::bevy::components::{CameraController, FrozenFrustum};
+use crate::config::culling::MAX_LISTED_CULLED;
+use crate::tauri_bridge::shared_state::CameraCullingStats;
+
+/// Mesh counts of every camera as of the last frame
+pub fn culling_stats(world: &mut World) -> Vec<CameraCullingStats> {
+    let meshes: Vec<(Entity, bool, RenderLayers)> = world
+        .query_filtered::<(Entity, &InheritedVisibility, Option<&RenderLayers>), With<Mesh3d>>()
+        .iter(world)
+        .map(|(entity, visibility, layers)| {
+            (
+                entity,
+                visibility.get(),
+                layers.cloned().unwrap_or_default(),
+            )
+        })
+        .collect();
+
+    let mut cameras = world.query::<(
+        Entity,
+        &Camera,
+        &VisibleEntities,
+        Option<&RenderLayers>,
+        Option<&Name>,
+        Has<FrozenFrustum>,
+    )>();
+    let mut stats: Vec<CameraCullingStats> = cameras
+        .iter(world)
+        .map(|(entity, camera, visible, layers, name, frozen)| {
+            let layers = layers.cloned().unwrap_or_default();
+            let visible: HashSet<Entity> = visible.iter(TypeId::of::<Mesh3d>()).copied().collect();
+            let mut counts = CameraCullingStats {
+                camera: entity.to_bits(),
+                name: name.map(|name| name.to_string()),
+                active: camera.is_active,
+                meshes: 0,
+                visible: 0,
+                hidden: 0,
+                culled: 0,
+                frozen,
+                culled_entities: Vec::new(),
+            };
+            for (mesh, inherited_visible, mesh_layers) in &meshes {
+                if !layers.intersects(mesh_layers) {
+                    continue;
+                }
+                counts.meshes += 1;
+                if visible.contains(mesh) {
+                    counts.visible += 1;
+                } else if !inherited_visible {
+                    counts.hidden += 1;
+                } else {
+                    counts.culled += 1;
+                    if counts.culled_entities.len() < MAX_LISTED_CULLED {
+                        counts.culled_entities.push(mesh.to_bits());
+                    }
+                }
+            }
+            counts
+        })
+        .collect();
+    stats.sort_by_key(|stats| stats.camera);
+    stats
+}
+
+/// Freeze the stream camera's culling frustum where it is, or let it follow
+/// the camera again
+pub fn freeze_culling(world: &mut World, frozen: bool) {
+    let mut cameras = world.query_filtered::<
+        (Entity, &Frustum, &Projection, &GlobalTransform),
+        With<CameraController>,
+    >();
+    let cameras: Vec<(Entity, Frustum, Frustum)> = cameras
+        .iter(world)
+        .map(|(entity, frustum, projection, transform)| {
+            (entity, *frustum, projection.compute_frustum(transform))
+        })
+        .collect();
+    for (entity, frustum, current) in cameras {
+        let mut camera = world.entity_mut(entity);
+        if frozen {
+            camera.insert(FrozenFrustum(frustum));
+        } else if camera.take::<FrozenFrustum>().is_some() {
+            // Only recomputed when the camera moves
+            camera.insert(current);
+        }
+    }
+    println!(
+        "[Bevy] Culling {}",
+        if frozen { "frozen" } else { "unfrozen" }
+    );
+}
+
+/// Put frozen frusta back after Bevy updated them for the camera's pose
+pub fn hold_frozen_frusta(mut cameras: Query<(&FrozenFrustum, &mut Frustum)>) {
+    for (frozen, mut frustum) in &mut cameras {
+        *frustum = frozen.0;
+    }
+}
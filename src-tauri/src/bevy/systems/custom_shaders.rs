@@ -0,0 +1,140 @@
+//! Custom shader registration and assignment
+//!
+//! `register_custom_shader` stores WGSL source as a shader asset under a
+//! handle derived from its name's index, so registering a name again
+//! replaces the source and Bevy recompiles every pipeline using it.
+//! Assigning a shader to an entity swaps the `StandardMaterial` of the
+//! entity and all meshes below it, such as a whole loaded model, for one
+//! shared [`CustomShaderMaterial`], and keeps the originals to put back.
+
+use bevy::prelude::*;
+use bevy::shader::Shader;
+
+use crate::bevy::components::CustomShaderOriginal;
+use crate::bevy::plugins::custom_shader::{shader_handle, CustomShaderMaterial};
+use crate::bevy::resources::{CustomShaders, EditChange};
+use crate::bevy::systems::history::record;
+use crate::tauri_bridge::shared_state::CustomShaderMaterialSettings;
+
+/// Store `source` as custom shader `name`, replacing its previous source
+pub fn register_custom_shader(world: &mut World, name: String, source: String) {
+    let mut shaders = world.resource_mut::<CustomShaders>();
+    let next = shaders.0.len() as u32;
+    let index = *shaders.0.entry(name.clone()).or_insert(next);
+    let shader = Shader::from_wgsl(source, format!("custom_shaders/{}.wgsl", name));
+    let _ = world
+        .resource_mut::<Assets<Shader>>()
+        .insert(shader_handle(index).id(), shader);
+    println!("[Bevy] Custom shader {} registered", name);
+}
+
+/// Draw the meshes of entity `id` and its descendants with `material`, or
+/// with their own materials again if `None`
+///
+/// Returns how many meshes changed.
+pub fn set_custom_shader_material(
+    world: &mut World,
+    id: u64,
+    material: Option<CustomShaderMaterialSettings>,
+) -> Result<usize, String> {
+    let root = Entity::try_from_bits(id).ok_or_else(|| format!("Invalid entity id {}", id))?;
+    if world.get_entity(root).is_err() {
+        return Err(format!("No entity with id {}", id));
+    }
+    let material = match material {
+        Some(settings) => Some(material_asset(world, settings)?),
+        None => None,
+    };
+
+    let mut changes = Vec::new();
+    for entity in descendants(world, root) {
+        let mut entity = world.entity_mut(entity);
+        let before = entity
+            .get::<MeshMaterial3d<CustomShaderMaterial>>()
+            .map(|material| material.0.clone());
+        if draw_with_custom_material(&mut entity, material.as_ref()) {
+            changes.push(EditChange::CustomShader {
+                entity: entity.id(),
+                before,
+                after: material.clone(),
+            });
+        }
+    }
+    let changed = changes.len();
+    if changed == 0 && material.is_some() {
+        return Err(format!("Entity {} has no meshes to draw", id));
+    }
+    record(world, "set_custom_shader_material", changes);
+    println!("[Bevy] Custom shader material set on {} meshes", changed);
+    Ok(changed)
+}
+
+/// Draw mesh `entity` with `material`, or with its own material again if
+/// `None`, returning false if that leaves it as it was
+pub(crate) fn draw_with_custom_material(
+    entity: &mut EntityWorldMut,
+    material: Option<&Handle<CustomShaderMaterial>>,
+) -> bool {
+    match material {
+        Some(material) => {
+            if let Some(original) = entity.take::<MeshMaterial3d<StandardMaterial>>() {
+                entity.insert(CustomShaderOriginal(original.0));
+            } else if !entity.contains::<CustomShaderOriginal>() {
+                return false;
+            }
+            entity.insert(MeshMaterial3d(material.clone()));
+        }
+        None => {
+            let Some(original) = entity.take::<CustomShaderOriginal>() else {
+                return false;
+            };
+            entity.remove::<MeshMaterial3d<CustomShaderMaterial>>();
+            entity.insert(MeshMaterial3d(original.0));
+        }
+    }
+    true
+}
+
+/// Material asset drawing with the registered shader in `settings`
+fn material_asset(
+    world: &mut World,
+    settings: CustomShaderMaterialSettings,
+) -> Result<Handle<CustomShaderMaterial>, String> {
+    let shader = *world
+        .resource::<CustomShaders>()
+        .0
+        .get(&settings.shader)
+        .ok_or_else(|| format!("No custom shader named {}", settings.shader))?;
+    let color = match settings.color.as_deref() {
+        None => Color::WHITE,
+        Some(&[r, g, b]) => Color::srgb(r, g, b),
+        Some(&[r, g, b, a]) => Color::srgba(r, g, b, a),
+        Some(_) => return Err("color must have 3 or 4 components".to_string()),
+    };
+    let alpha_mode = if color.alpha() < 1.0 {
+        AlphaMode::Blend
+    } else {
+        AlphaMode::Opaque
+    };
+    Ok(world
+        .resource_mut::<Assets<CustomShaderMaterial>>()
+        .add(CustomShaderMaterial {
+            color: color.to_linear(),
+            params: Vec4::from_array(settings.params),
+            shader,
+            alpha_mode,
+        }))
+}
+
+/// `root` and everything below it
+pub(crate) fn descendants(world: &World, root: Entity) -> Vec<Entity> {
+    let mut entities = vec![root];
+    let mut next = 0;
+    while let Some(&entity) = entities.get(next) {
+        if let Some(children) = world.get::<Children>(entity) {
+            entities.extend(children.iter());
+        }
+        next += 1;
+    }
+    entities
+}
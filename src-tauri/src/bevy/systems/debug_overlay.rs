@@ -0,0 +1,92 @@
+//! In-frame debug overlay
+//!
+//! Diagnostics drawn by Bevy's UI into the stream camera's target, so they
+//! show up in everything made from stream frames: the live view,
+//! recordings, clips and raw frame dumps. Screenshots, turntables and batch
+//! renders use their own cameras and stay clean.
+
+use bevy::prelude::*;
+
+use crate::bevy::components::{CameraController, DebugOverlayText};
+use crate::bevy::resources::{
+    FrameCount, OrbitCameraState, PerfStatsRes, RenderSettings, StreamResolution,
+};
+
+/// Show or hide the debug overlay
+pub fn set_debug_overlay(world: &mut World, enabled: bool) {
+    let mut existing = world.query_filtered::<Entity, With<DebugOverlayText>>();
+    let existing: Vec<Entity> = existing.iter(world).collect();
+    if enabled == !existing.is_empty() {
+        return;
+    }
+
+    if !enabled {
+        for entity in existing {
+            world.despawn(entity);
+        }
+        println!("[Bevy] Debug overlay off");
+        return;
+    }
+
+    let mut cameras = world.query_filtered::<Entity, With<CameraController>>();
+    let Some(camera) = cameras.iter(world).next() else {
+        return;
+    };
+    world.spawn((
+        Text::default(),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(8.0),
+            top: Val::Px(8.0),
+            padding: UiRect::all(Val::Px(4.0)),
+            ..default()
+        },
+        UiTargetCamera(camera),
+        DebugOverlayText,
+        Name::new("debug_overlay"),
+    ));
+    println!("[Bevy] Debug overlay on");
+}
+
+/// Refresh the overlay text with this frame's diagnostics
+pub fn update_debug_overlay(
+    frame_count: Res<FrameCount>,
+    perf_stats: Res<PerfStatsRes>,
+    render_settings: Res<RenderSettings>,
+    stream_resolution: Res<StreamResolution>,
+    orbit: Res<OrbitCameraState>,
+    camera_query: Query<&Transform, With<CameraController>>,
+    mut text_query: Query<&mut Text, With<DebugOverlayText>>,
+) {
+    let Ok(mut text) = text_query.single_mut() else {
+        return;
+    };
+    let fps = perf_stats.0 .0.lock().map_or(0.0, |stats| stats.bevy_fps);
+    let position = camera_query
+        .iter()
+        .next()
+        .map(|transform| transform.translation)
+        .unwrap_or_default();
+
+    text.0 = format!(
+        "FPS {:.1} | Frame {}\nRender {}x{} | Stream {}x{}\nCamera ({:.2}, {:.2}, {:.2})\nYaw {:.1}° Pitch {:.1}° Distance {:.2}",
+        fps,
+        frame_count.0,
+        render_settings.width,
+        render_settings.height,
+        stream_resolution.width,
+        stream_resolution.height,
+        position.x,
+        position.y,
+        position.z,
+        orbit.yaw.to_degrees(),
+        orbit.pitch.to_degrees(),
+        orbit.distance,
+    );
+}
@@ -0,0 +1,79 @@
+//! Debug visualization modes
+//!
+//! A debug view swaps the `StandardMaterial` of every mesh for a
+//! [`DebugViewMaterial`] showing depth, normals, texture coordinates,
+//! overdraw or shadow cascades, and swaps the originals back when turned
+//! off. Meshes spawned while a view is on, such as a model that finishes
+//! loading, are swapped as they appear. Materials are per mesh, not per
+//! camera, so screenshots and exports taken meanwhile show the view too.
+
+use bevy::prelude::*;
+
+use crate::bevy::components::DebugViewOriginal;
+use crate::bevy::plugins::debug_view::DebugViewMaterial;
+use crate::bevy::resources::ActiveDebugView;
+use crate::tauri_bridge::shared_state::DebugView;
+
+/// Switch every mesh to the debug view `view`, or back to its own material
+pub fn set_debug_view(world: &mut World, view: DebugView) {
+    let material = DebugViewMaterial::new(view).map(|material| {
+        world
+            .resource_mut::<Assets<DebugViewMaterial>>()
+            .add(material)
+    });
+    // The previous view's material is freed with its last handle
+    match &material {
+        Some(material) => world.insert_resource(ActiveDebugView {
+            view,
+            material: material.clone(),
+        }),
+        None => {
+            world.remove_resource::<ActiveDebugView>();
+        }
+    }
+
+    let mut meshes = world.query_filtered::<Entity, Or<(
+        With<MeshMaterial3d<StandardMaterial>>,
+        With<DebugViewOriginal>,
+    )>>();
+    let meshes: Vec<Entity> = meshes.iter(world).collect();
+    for entity in meshes {
+        let mut entity = world.entity_mut(entity);
+        match &material {
+            Some(material) => {
+                if let Some(original) = entity.take::<MeshMaterial3d<StandardMaterial>>() {
+                    entity.insert(DebugViewOriginal(original.0));
+                }
+                entity.insert(MeshMaterial3d(material.clone()));
+            }
+            None => {
+                entity.remove::<MeshMaterial3d<DebugViewMaterial>>();
+                if let Some(original) = entity.take::<DebugViewOriginal>() {
+                    entity.insert(MeshMaterial3d(original.0));
+                }
+            }
+        }
+    }
+    println!("[Bevy] Debug view {:?}", view);
+}
+
+/// Draw meshes spawned while a debug view is on with its material
+pub fn apply_debug_view_to_new_meshes(
+    mut commands: Commands,
+    active: Option<Res<ActiveDebugView>>,
+    meshes: Query<
+        (Entity, &MeshMaterial3d<StandardMaterial>),
+        Added<MeshMaterial3d<StandardMaterial>>,
+    >,
+) {
+    let Some(active) = active else { return };
+    for (entity, original) in &meshes {
+        commands
+            .entity(entity)
+            .remove::<MeshMaterial3d<StandardMaterial>>()
+            .insert((
+                DebugViewOriginal(original.0.clone()),
+                MeshMaterial3d(active.material.clone()),
+            ));
+    }
+}
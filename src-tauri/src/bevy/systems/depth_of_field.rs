@@ -0,0 +1,61 @@
+//! Depth of field
+//!
+//! The blur is a post-process on the stream camera. Screenshots, turntables
+//! and batch renders copy the stream camera's settings, so stills and
+//! exports are focused the same way as the stream.
+
+use bevy::post_process::dof::DepthOfField;
+use bevy::prelude::*;
+
+use crate::bevy::components::CameraController;
+use crate::bevy::systems::camera::pick_surface;
+use crate::tauri_bridge::shared_state::DepthOfFieldSettings;
+
+/// Turn depth of field on with `settings`, or off
+pub fn set_depth_of_field(world: &mut World, settings: Option<DepthOfFieldSettings>) {
+    let cameras: Vec<Entity> = world
+        .query_filtered::<Entity, With<CameraController>>()
+        .iter(world)
+        .collect();
+    for camera in cameras {
+        let mut camera = world.entity_mut(camera);
+        match settings {
+            Some(settings) => {
+                camera.insert(DepthOfField {
+                    focal_distance: settings.focal_distance,
+                    aperture_f_stops: settings.aperture,
+                    ..default()
+                });
+            }
+            None => {
+                camera.remove::<DepthOfField>();
+            }
+        }
+    }
+    match settings {
+        Some(settings) => println!(
+            "[Bevy] Depth of field on, focused at {:.2} with f/{:.1}",
+            settings.focal_distance, settings.aperture
+        ),
+        None => println!("[Bevy] Depth of field off"),
+    }
+}
+
+/// Focus on the mesh surface under point `(x, y)` of the streamed image,
+/// see [`pick_surface`]
+///
+/// Returns the new focal distance. Depth of field must be on.
+pub fn focus_depth_of_field(world: &mut World, x: f32, y: f32) -> Result<f32, String> {
+    let point = pick_surface(world, x, y)?;
+    let mut cameras =
+        world.query_filtered::<(&GlobalTransform, &mut DepthOfField), With<CameraController>>();
+    let (transform, mut depth_of_field) = cameras
+        .single_mut(world)
+        .map_err(|_| "Depth of field is off".to_string())?;
+
+    // Focal distance is measured along the view direction, not to the point
+    let distance = (point - transform.translation()).dot(*transform.forward());
+    depth_of_field.focal_distance = distance;
+    println!("[Bevy] Depth of field focused at {:.2}", distance);
+    Ok(distance)
+}
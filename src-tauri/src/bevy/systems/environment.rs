@@ -0,0 +1,175 @@
+//! Image-based lighting
+//!
+//! `set_environment_map` lights the whole scene from an HDR environment:
+//! the stream camera gets an `EnvironmentMapLight` with the maps filtered
+//! by `plugins::environment_map`, so metallic and glossy surfaces reflect
+//! it, and a `Skybox` if it should show behind the scene. Reflection
+//! probes light only what is inside their box, from their own
+//! environment, e.g. an interior for a room in an outdoor scene; Bevy
+//! prefers a probe over the camera's environment where both apply.
+//!
+//! Environments light the scene as soon as their maps are filtered. The
+//! commands reply then, or with the error if a file could not be loaded.
+
+use bevy::asset::{AssetPath, LoadState};
+use bevy::core_pipeline::Skybox;
+use bevy::prelude::*;
+use crossbeam_channel::Sender;
+use std::path::Path;
+
+use crate::bevy::components::{CameraController, ReflectionCamera, ReflectionProbe};
+use crate::bevy::resources::{EnvironmentLoad, EnvironmentLoads};
+use crate::config::environment::DEFAULT_INTENSITY;
+use crate::tauri_bridge::shared_state::{EnvironmentMapSettings, ReflectionProbeSettings};
+
+/// Handles of an environment's background, diffuse and specular maps
+struct EnvironmentMaps {
+    skybox: Handle<Image>,
+    diffuse: Handle<Image>,
+    specular: Handle<Image>,
+}
+
+impl EnvironmentMaps {
+    fn load(world: &World, path: &Path) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        // Anywhere the frontend names, e.g. an HDRI library
+        let path = AssetPath::from(path.to_path_buf());
+        Self {
+            skybox: asset_server.load_override(path.clone()),
+            diffuse: asset_server.load_override(path.clone().with_label("diffuse")),
+            specular: asset_server.load_override(path.with_label("specular")),
+        }
+    }
+
+    fn light(&self, intensity: f32, rotation: Quat) -> EnvironmentMapLight {
+        EnvironmentMapLight {
+            diffuse_map: self.diffuse.clone(),
+            specular_map: self.specular.clone(),
+            intensity,
+            rotation,
+            ..default()
+        }
+    }
+}
+
+/// Light the scene from `environment`, or remove the environment lighting
+/// and background if `None`
+pub fn set_environment_map(
+    world: &mut World,
+    environment: Option<EnvironmentMapSettings>,
+    reply: Sender<Result<(), String>>,
+) {
+    // The water's reflection camera too, so reflections match the scene
+    let cameras: Vec<Entity> = world
+        .query_filtered::<Entity, Or<(With<CameraController>, With<ReflectionCamera>)>>()
+        .iter(world)
+        .collect();
+    let Some(environment) = environment else {
+        for camera in cameras {
+            world
+                .entity_mut(camera)
+                .remove::<(EnvironmentMapLight, Skybox)>();
+        }
+        println!("[Bevy] Environment map removed");
+        let _ = reply.send(Ok(()));
+        return;
+    };
+
+    let maps = EnvironmentMaps::load(world, &environment.path);
+    let intensity = environment.intensity.unwrap_or(DEFAULT_INTENSITY);
+    let rotation = Quat::from_rotation_y(environment.rotation_deg.to_radians());
+    for camera in cameras {
+        let mut camera = world.entity_mut(camera);
+        camera.insert(maps.light(intensity, rotation));
+        if environment.skybox {
+            camera.insert(Skybox {
+                image: maps.skybox.clone(),
+                brightness: intensity,
+                rotation,
+            });
+        } else {
+            camera.remove::<Skybox>();
+        }
+    }
+    println!(
+        "[Bevy] Loading environment map {}",
+        environment.path.display()
+    );
+    world
+        .resource_mut::<EnvironmentLoads>()
+        .0
+        .push(EnvironmentLoad {
+            environments: vec![maps.skybox],
+            reply,
+        });
+}
+
+/// Replace the reflection probes with `probes`
+pub fn set_reflection_probes(
+    world: &mut World,
+    probes: Vec<ReflectionProbeSettings>,
+    reply: Sender<Result<(), String>>,
+) {
+    let previous: Vec<Entity> = world
+        .query_filtered::<Entity, With<ReflectionProbe>>()
+        .iter(world)
+        .collect();
+    for entity in previous {
+        world.despawn(entity);
+    }
+
+    let mut environments = Vec::new();
+    for (index, probe) in probes.iter().enumerate() {
+        let maps = EnvironmentMaps::load(world, &probe.path);
+        let name = probe
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("reflection_probe_{}", index));
+        world.spawn((
+            LightProbe,
+            maps.light(probe.intensity.unwrap_or(DEFAULT_INTENSITY), Quat::IDENTITY),
+            // The probe covers a unit cube scaled by its transform
+            Transform::from_translation(Vec3::from_array(probe.center))
+                .with_scale(Vec3::from_array(probe.size)),
+            Name::new(name),
+            ReflectionProbe,
+        ));
+        environments.push(maps.skybox);
+    }
+    println!("[Bevy] {} reflection probes set", probes.len());
+    world
+        .resource_mut::<EnvironmentLoads>()
+        .0
+        .push(EnvironmentLoad {
+            environments,
+            reply,
+        });
+}
+
+/// Reply to commands whose environments finished loading or failed
+pub fn track_environment_loads(
+    mut loads: ResMut<EnvironmentLoads>,
+    asset_server: Res<AssetServer>,
+) {
+    loads.0.retain(|load| {
+        let mut loaded = true;
+        for environment in &load.environments {
+            match asset_server.get_load_state(environment.id()) {
+                Some(LoadState::Failed(e)) => {
+                    let path = asset_server
+                        .get_path(environment.id())
+                        .map_or_else(String::new, |path| path.to_string());
+                    eprintln!("[Bevy] Failed to load environment {}: {}", path, e);
+                    let _ = load.reply.send(Err(e.to_string()));
+                    return false;
+                }
+                Some(LoadState::Loaded) => {}
+                _ => loaded = false,
+            }
+        }
+        if loaded {
+            let _ = load.reply.send(Ok(()));
+        }
+        !loaded
+    });
+}
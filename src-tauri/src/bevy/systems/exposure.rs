@@ -0,0 +1,24 @@
+//! Camera exposure
+//!
+//! Exposure scales the brightness of the whole image, so scenes lit much
+//! brighter or darker than the demo scene, such as ones lit by HDR
+//! environment maps, can be viewed without editing light intensities.
+//! Screenshots, turntables and batch renders copy the stream camera's
+//! exposure.
+
+use bevy::camera::Exposure;
+use bevy::prelude::*;
+
+use crate::bevy::components::CameraController;
+
+/// Set the stream camera's exposure to `ev100`, or back to Bevy's default
+///
+/// Higher values darken the image, each step of `1` halves the brightness.
+pub fn set_exposure(world: &mut World, ev100: Option<f32>) {
+    let exposure = ev100.map_or_else(Exposure::default, |ev100| Exposure { ev100 });
+    let mut cameras = world.query_filtered::<&mut Exposure, With<CameraController>>();
+    for mut camera_exposure in cameras.iter_mut(world) {
+        *camera_exposure = exposure;
+    }
+    println!("[Bevy] Exposure set to EV100 {:.2}", exposure.ev100);
+}
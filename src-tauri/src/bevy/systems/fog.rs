@@ -0,0 +1,58 @@
+//! Distance fog
+//!
+//! Fog fades distant surfaces into its color, so depth reads in the
+//! streamed image of a large scene. It is set on the stream camera and
+//! copied by screenshots, turntables and batch renders like depth of field.
+//! The settings are kept in [`ActiveFog`] because exponential falloffs
+//! can't be turned back into the distances they were set with.
+
+use bevy::prelude::*;
+
+use crate::bevy::components::{CameraController, ReflectionCamera};
+use crate::bevy::resources::ActiveFog;
+use crate::tauri_bridge::shared_state::{FogFalloffMode, FogSettings};
+
+/// Turn distance fog on with `settings`, or off
+pub fn set_fog(world: &mut World, settings: Option<FogSettings>) {
+    // The water's reflection camera too, so reflections match the scene
+    let cameras: Vec<Entity> = world
+        .query_filtered::<Entity, Or<(With<CameraController>, With<ReflectionCamera>)>>()
+        .iter(world)
+        .collect();
+    for camera in cameras {
+        let mut camera = world.entity_mut(camera);
+        match settings {
+            Some(settings) => {
+                camera.insert(distance_fog(settings));
+            }
+            None => {
+                camera.remove::<DistanceFog>();
+            }
+        }
+    }
+    world.insert_resource(ActiveFog(settings));
+    match settings {
+        Some(settings) => println!(
+            "[Bevy] Fog on, {:?} from {:.1} to {:.1}",
+            settings.falloff, settings.start, settings.end
+        ),
+        None => println!("[Bevy] Fog off"),
+    }
+}
+
+fn distance_fog(settings: FogSettings) -> DistanceFog {
+    let [r, g, b] = settings.color;
+    let falloff = match settings.falloff {
+        FogFalloffMode::Linear => FogFalloff::Linear {
+            start: settings.start,
+            end: settings.end,
+        },
+        FogFalloffMode::Exponential => FogFalloff::from_visibility(settings.end),
+        FogFalloffMode::ExponentialSquared => FogFalloff::from_visibility_squared(settings.end),
+    };
+    DistanceFog {
+        color: Color::srgb(r, g, b),
+        falloff,
+        ..default()
+    }
+}
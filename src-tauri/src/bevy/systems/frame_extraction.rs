@@ -4,29 +4,85 @@
 //! preparing them for transfer to the Tauri frontend.
 
 use bevy::{prelude::*, render::renderer::RenderDevice, time::Time};
+use std::sync::Arc;
+use tracing::info_span;
+use xxhash_rust::xxh3::xxh3_64;
 
+use crate::bevy::components::{OffscreenCamera, PendingViews};
+use crate::bevy::plugins::image_copy::ImageCopier;
 use crate::bevy::resources::{
-    FrameBufferRes, FrameCount, FrameRateLimiter, FrameTimings, MainWorldReceiver, PerfStatsRes,
-    PreRollFrames,
+    BufferPoolRes, FetchStatsRes, FrameBufferRes, FrameCount, FrameSignalRes, FrameTimings,
+    LastFrameHash, MainWorldReceiver, PerfStatsRes, PipelineCounters, PreRollFrames, ReadbackFrame,
+    SceneTime, StreamTarget,
 };
-use crate::config::{performance::*, RENDER_HEIGHT, RENDER_WIDTH};
+use crate::config::latency::{STAMP_BITS, STAMP_BLOCK_SIZE, STAMP_FRAME_ID};
+use crate::config::performance::*;
+use crate::startup::{self, StartupPhase};
+use crate::tauri_bridge::shared_state::{
+    Frame, FrameMetadata, FrameTimeSummary, PixelFormat, SharedBufferPool,
+};
+
+/// Remember the view about to be rendered, for the frame read back from it
+///
+/// Runs last in the frame, after all camera and animation updates, so it
+/// sees exactly what the renderer extracts next. The readback carrying the
+/// frame picks it up again in `dispatch_copies`.
+pub fn record_rendered_view(
+    cameras: Query<(&Transform, &Projection), With<OffscreenCamera>>,
+    mut copiers: Query<&mut PendingViews>,
+    scene_time: Res<SceneTime>,
+) {
+    let Some((transform, projection)) = cameras.iter().next() else {
+        return;
+    };
+    let (fov_y_deg, near, far) = match projection {
+        Projection::Perspective(p) => (Some(p.fov.to_degrees()), p.near, p.far),
+        Projection::Orthographic(o) => (None, o.near, o.far),
+        _ => (None, 0.0, 0.0),
+    };
+    let metadata = FrameMetadata {
+        camera_translation: transform.translation.to_array(),
+        camera_rotation: transform.rotation.to_array(),
+        fov_y_deg,
+        near,
+        far,
+        scene_time_s: scene_time.0,
+    };
+
+    for mut views in &mut copiers {
+        views.push(metadata);
+    }
+}
 
 /// Extract and process frame data from the render pipeline
 pub fn extract_and_process_frame(
     receiver: Res<MainWorldReceiver>,
     buffer: Option<Res<FrameBufferRes>>,
+    frame_signal: Res<FrameSignalRes>,
     perf_stats: Option<Res<PerfStatsRes>>,
+    buffer_pool: Res<BufferPoolRes>,
+    fetch_stats: Res<FetchStatsRes>,
+    copiers: Query<&ImageCopier>,
+    mut counters: ResMut<PipelineCounters>,
+    mut last_hash: ResMut<LastFrameHash>,
     mut count: ResMut<FrameCount>,
     mut pre_roll: ResMut<PreRollFrames>,
     mut timings: ResMut<FrameTimings>,
-    mut frame_limiter: ResMut<FrameRateLimiter>,
+    stream_target: Option<Res<StreamTarget>>,
     time: Res<Time>,
 ) {
     let Some(b) = buffer else { return };
+    let pool = &buffer_pool.0;
+
+    if !receiver.is_empty() {
+        startup::mark(StartupPhase::FirstRender);
+    }
 
     // Wait for scene to be fully rendered
     if pre_roll.0 > 0 {
-        while receiver.try_recv().is_ok() {}
+        while let Some(readback) = receiver.recv_frame() {
+            pool.recycle(readback.data);
+        }
         pre_roll.0 -= 1;
         if pre_roll.0 % 10 == 0 && pre_roll.0 > 0 {
             println!("[Bevy] Pre-roll frames remaining: {}", pre_roll.0);
@@ -34,114 +90,218 @@ pub fn extract_and_process_frame(
         return;
     }
 
-    // Frame rate limiting - skip if not enough time has passed
-    let now = std::time::Instant::now();
-    let elapsed = now.duration_since(frame_limiter.last_frame_time);
-    if elapsed < frame_limiter.min_frame_interval {
-        // Drain the receiver but don't process - too early for next frame
-        while receiver.try_recv().is_ok() {}
-        return;
-    }
-    frame_limiter.last_frame_time = now;
-
     let frame_start = std::time::Instant::now();
 
     // Try to receive latest frame data from render world
+    let _span = info_span!("extract_frame").entered();
     let receive_start = std::time::Instant::now();
-    let mut image_data = Vec::new();
-    while let Ok(data) = receiver.try_recv() {
-        image_data = data;
+    let channel_depth = receiver.len() as u32;
+    counters.max_channel_depth = counters.max_channel_depth.max(channel_depth);
+    let stream_copier = stream_target.map(|target| target.copier);
+    let mut latest = None;
+    while let Some(readback) = receiver.recv_frame() {
+        // Readbacks of other copiers, or of a stream target that was just
+        // replaced, are not streamed
+        if Some(readback.copier) != stream_copier {
+            pool.recycle(readback.data);
+            continue;
+        }
+        // Only the newest frame is used, older ones are stale
+        if let Some(stale) = latest.replace(readback) {
+            counters.dropped_stale += 1;
+            pool.recycle(stale.data);
+        }
     }
     let receive_time = receive_start.elapsed().as_secs_f64() * 1000.0;
 
-    if !image_data.is_empty() {
-        // Remove row padding and store raw RGBA data
+    if let Some(ReadbackFrame {
+        width,
+        height,
+        format,
+        padded,
+        data,
+        metadata,
+        ..
+    }) = latest
+    {
+        // Remove row padding and store tightly packed pixel data
         let process_start = std::time::Instant::now();
-        if let Some(rgba) = remove_row_padding(&image_data, RENDER_WIDTH, RENDER_HEIGHT) {
-            let process_time = process_start.elapsed().as_secs_f64() * 1000.0;
-            let data_size = rgba.len();
+        let pixels = info_span!("remove_row_padding")
+            .in_scope(|| remove_row_padding(data, width, height, format, padded, pool));
+        if let Some(mut pixels) = pixels {
+            // An unchanged frame is not published, so the encoder stays idle
+            // and clients asking for a newer frame are told there is none
+            if SKIP_IDENTICAL_FRAMES {
+                let hash = info_span!("hash_frame").in_scope(|| xxh3_64(&pixels));
+                if last_hash.0.replace(hash) == Some(hash) {
+                    pool.recycle(pixels);
+                    counters.skipped_identical += 1;
+                    if let Some(perf_res) = &perf_stats {
+                        if let Ok(mut stats) = perf_res.0 .0.lock() {
+                            stats.frames_skipped_identical = counters.skipped_identical;
+                        }
+                    }
+                    return;
+                }
+            }
 
-            if let Ok(mut guard) = b.0 .0.lock() {
-                *guard = Some(rgba);
-                count.0 += 1;
+            let process_time = process_start.elapsed().as_secs_f64() * 1000.0;
+            let data_size = pixels.len();
 
-                let total_time = frame_start.elapsed().as_secs_f64() * 1000.0;
-                timings.frame_times.push(total_time);
+            count.0 += 1;
+            if STAMP_FRAME_ID {
+                stamp_frame_id(&mut pixels, width, height, format, count.0 as u64);
+            }
+            let mut frame = Frame::new(count.0 as u64, width, height, format, pixels);
+            frame.metadata = metadata;
 
-                // Keep only last N samples for averaging
-                if timings.frame_times.len() > FRAME_TIMING_SAMPLES {
-                    timings.frame_times.remove(0);
+            // The previous frame's allocation goes back to the pool,
+            // unless a reader is still holding on to it
+            let publish_span = info_span!("publish_frame").entered();
+            if let Some(previous) = b.0.publish(frame) {
+                if fetch_stats.0.last_fetched_id() < previous.id {
+                    counters.unfetched += 1;
                 }
-
-                // Update performance stats
-                if let Some(perf_res) = &perf_stats {
-                    if let Ok(mut stats) = perf_res.0 .0.lock() {
-                        stats.gpu_transfer_ms = receive_time;
-                        stats.data_processing_ms = process_time;
-                        stats.frame_encoding_ms = total_time;
-                        stats.frame_count = count.0;
-                        stats.data_size_kb = data_size as f64 / 1024.0;
-
-                        // Calculate FPS from frame times
-                        if !timings.frame_times.is_empty() {
-                            let avg_time = timings.frame_times.iter().sum::<f64>()
-                                / timings.frame_times.len() as f64;
-                            stats.bevy_fps = if avg_time > 0.0 {
-                                1000.0 / avg_time
-                            } else {
-                                0.0
-                            };
-                        }
-                    }
+                if let Ok(previous) = Arc::try_unwrap(previous) {
+                    pool.recycle(previous.data);
                 }
+            }
+            frame_signal.0.notify();
+            drop(publish_span);
+            if count.0 == 1 {
+                startup::mark(StartupPhase::FirstFramePublished);
+            }
+
+            let total_time = frame_start.elapsed().as_secs_f64() * 1000.0;
+            timings.frame_times.push(total_time);
+            let now = std::time::Instant::now();
+            if let Some(last_publish) = timings.last_publish.replace(now) {
+                let interval = (now - last_publish).as_secs_f64() * 1000.0;
+                timings.frame_intervals.push(interval);
+            }
+
+            // Update performance stats
+            if let Some(perf_res) = &perf_stats {
+                if let Ok(mut stats) = perf_res.0 .0.lock() {
+                    stats.gpu_transfer_ms = receive_time;
+                    stats.data_processing_ms = process_time;
+                    stats.frame_encoding_ms = total_time;
+                    stats.frame_count = count.0;
+                    stats.data_size_kb = data_size as f64 / 1024.0;
 
-                // Print detailed stats periodically
-                let current_time = time.elapsed_secs_f64();
-                if current_time - timings.last_print_time >= STATS_PRINT_INTERVAL {
-                    let avg_time =
-                        timings.frame_times.iter().sum::<f64>() / timings.frame_times.len() as f64;
-                    let max_time = timings.frame_times.iter().cloned().fold(0.0f64, f64::max);
-                    let min_time = timings.frame_times.iter().cloned().fold(f64::MAX, f64::min);
-
-                    println!(
-                        "[Bevy] Frame {} | Receive: {:.2}ms | Process: {:.2}ms | Total: {:.2}ms | Avg: {:.2}ms (Min: {:.2}ms, Max: {:.2}ms) | Size: {:.1}KB",
-                        count.0,
-                        receive_time,
-                        process_time,
-                        total_time,
-                        avg_time,
-                        min_time,
-                        max_time,
-                        data_size as f64 / 1024.0
-                    );
-                    timings.last_print_time = current_time;
+                    // Drop and backpressure counters
+                    stats.frames_dropped_stale = counters.dropped_stale;
+                    stats.frames_skipped_readback =
+                        copiers.iter().map(ImageCopier::skipped_frames).sum();
+                    stats.frames_unfetched = counters.unfetched;
+                    stats.frames_skipped_identical = counters.skipped_identical;
+                    stats.frames_fetched = fetch_stats.0.fetch_count();
+                    stats.channel_depth = channel_depth;
+                    stats.max_channel_depth = counters.max_channel_depth;
+
+                    // Frame time distribution, FPS from the average interval
+                    stats.frame_time = FrameTimeSummary::from_window(&timings.frame_intervals);
+                    stats.bevy_fps = if stats.frame_time.avg_ms > 0.0 {
+                        1000.0 / stats.frame_time.avg_ms
+                    } else {
+                        0.0
+                    };
                 }
             }
+
+            // Print detailed stats periodically
+            let current_time = time.elapsed_secs_f64();
+            if current_time - timings.last_print_time >= STATS_PRINT_INTERVAL {
+                let avg_time = timings.frame_times.mean();
+                let max_time = timings.frame_times.max();
+                let min_time = timings.frame_times.min();
+                let frame_time = FrameTimeSummary::from_window(&timings.frame_intervals);
+
+                println!(
+                    "[Bevy] Frame {} | Receive: {:.2}ms | Process: {:.2}ms | Total: {:.2}ms | Avg: {:.2}ms (Min: {:.2}ms, Max: {:.2}ms) | Size: {:.1}KB",
+                    count.0,
+                    receive_time,
+                    process_time,
+                    total_time,
+                    avg_time,
+                    min_time,
+                    max_time,
+                    data_size as f64 / 1024.0
+                );
+                println!(
+                    "[Bevy] Frame time p50: {:.2}ms | p95: {:.2}ms | p99: {:.2}ms | Max: {:.2}ms",
+                    frame_time.p50_ms, frame_time.p95_ms, frame_time.p99_ms, frame_time.max_ms
+                );
+                timings.last_print_time = current_time;
+            }
         }
     }
 }
 
-/// Remove GPU buffer row padding alignment, returning pure RGBA data
-fn remove_row_padding(data: &[u8], width: u32, height: u32) -> Option<Vec<u8>> {
+/// Remove GPU buffer row padding alignment, returning tightly packed pixels
+///
+/// Rows are copied with one `memcpy` each into a buffer taken from `pool`.
+/// When rows carry no padding (including everything written by the pack
+/// pass) the incoming buffer is reused as-is.
+pub(crate) fn remove_row_padding(
+    mut data: Vec<u8>,
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    padded: bool,
+    pool: &SharedBufferPool,
+) -> Option<Vec<u8>> {
     if data.is_empty() {
         return None;
     }
 
     // Handle row padding alignment
-    let row_bytes = width as usize * 4;
-    let aligned_row_bytes = RenderDevice::align_copy_bytes_per_row(row_bytes);
-
-    let rgba_data = if row_bytes == aligned_row_bytes {
-        // No padding, return as-is
-        data.to_vec()
+    let row_bytes = width as usize * format.bytes_per_pixel();
+    let aligned_row_bytes = if padded {
+        RenderDevice::align_copy_bytes_per_row(row_bytes)
     } else {
-        // Remove padding from each row
-        data.chunks(aligned_row_bytes)
-            .take(height as usize)
-            .flat_map(|row| &row[..row_bytes.min(row.len())])
-            .cloned()
-            .collect()
+        row_bytes
     };
+    let frame_bytes = row_bytes * height as usize;
 
-    Some(rgba_data)
+    if row_bytes == aligned_row_bytes {
+        // No padding, only trim any slack at the end of the readback buffer
+        data.truncate(frame_bytes);
+        return Some(data);
+    }
+
+    // Remove padding from each row
+    let mut pixels = pool.take(frame_bytes);
+    for row in data.chunks(aligned_row_bytes).take(height as usize) {
+        pixels.extend_from_slice(&row[..row_bytes.min(row.len())]);
+    }
+    pool.recycle(data);
+
+    Some(pixels)
+}
+
+/// Draw the low bits of `frame_id` into the top-left corner
+///
+/// Each bit is a `STAMP_BLOCK_SIZE` square, white for 1 and black for 0,
+/// least significant bit first. Frames too small for the pattern are left
+/// untouched.
+fn stamp_frame_id(pixels: &mut [u8], width: u32, height: u32, format: PixelFormat, frame_id: u64) {
+    if width < STAMP_BITS * STAMP_BLOCK_SIZE || height < STAMP_BLOCK_SIZE {
+        return;
+    }
+
+    let bytes_per_pixel = format.bytes_per_pixel();
+    let row_bytes = width as usize * bytes_per_pixel;
+    let block_bytes = STAMP_BLOCK_SIZE as usize * bytes_per_pixel;
+
+    for row in pixels.chunks_exact_mut(row_bytes).take(STAMP_BLOCK_SIZE as usize) {
+        for bit in 0..STAMP_BITS as usize {
+            let value = if (frame_id >> bit) & 1 == 1 { 255 } else { 0 };
+            let block = &mut row[bit * block_bytes..(bit + 1) * block_bytes];
+            for pixel in block.chunks_exact_mut(bytes_per_pixel) {
+                // Alpha, if present, is left opaque
+                pixel[..bytes_per_pixel.min(3)].fill(value);
+            }
+        }
+    }
 }
@@ -0,0 +1,459 @@
+//! Scene export to glTF
+//!
+//! `export_gltf` writes the visible scene as a binary glTF (.glb) so it can
+//! be opened in Blender or any other glTF tool: triangle meshes with their
+//! positions, normals and UVs, standard materials as metallic-roughness
+//! factors, and lights through `KHR_lights_punctual`. Every entity becomes
+//! a root node with its world transform, so hierarchies come out flat.
+//! Textures are not exported.
+
+use bevy::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
+use bevy::prelude::*;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use crate::bevy::components::{DebugOverlayText, DebugViewOriginal, Particle};
+use crate::tauri_bridge::shared_state::GltfExportSummary;
+
+/// glTF `componentType` of 32-bit floats
+const FLOAT: u32 = 5126;
+/// glTF `componentType` of 16-bit unsigned indices
+const UNSIGNED_SHORT: u32 = 5123;
+/// glTF `componentType` of 32-bit unsigned indices
+const UNSIGNED_INT: u32 = 5125;
+/// Buffer view `target` of vertex attributes
+const ARRAY_BUFFER: u32 = 34962;
+/// Buffer view `target` of indices
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// The visible scene as a .glb file, with what went into it
+///
+/// The summary's `path` is left for the caller to fill in.
+pub fn export_gltf(world: &mut World) -> Result<(Vec<u8>, GltfExportSummary), String> {
+    let mut glb = GlbBuilder::default();
+    let mut summary = GltfExportSummary::default();
+    let mut nodes: Vec<Value> = Vec::new();
+    let mut mesh_indices: HashMap<(AssetId<Mesh>, Option<usize>), Option<usize>> = HashMap::new();
+    let mut material_indices: HashMap<AssetId<StandardMaterial>, usize> = HashMap::new();
+    let mut meshes: Vec<Value> = Vec::new();
+    let mut materials: Vec<Value> = Vec::new();
+    let mut lights: Vec<Value> = Vec::new();
+
+    let visible = visible_meshes(world);
+    let mesh_assets = world.resource::<Assets<Mesh>>();
+    let material_assets = world.resource::<Assets<StandardMaterial>>();
+    for ExportMesh {
+        name,
+        transform,
+        mesh,
+        material,
+        ..
+    } in visible
+    {
+        let material_index = material.and_then(|id| {
+            if let Some(index) = material_indices.get(&id) {
+                return Some(*index);
+            }
+            let material = material_assets.get(id)?;
+            materials.push(material_json(material));
+            material_indices.insert(id, materials.len() - 1);
+            Some(materials.len() - 1)
+        });
+
+        // A glTF mesh carries its material, so each pairing is one mesh
+        let key = (mesh, material_index);
+        let mesh_index = *mesh_indices.entry(key).or_insert_with(|| {
+            let primitive = mesh_assets
+                .get(mesh)
+                .ok_or_else(|| "mesh data is not kept on the CPU".to_string())
+                .and_then(|mesh| mesh_primitive(&mut glb, mesh));
+            match primitive {
+                Ok(mut primitive) => {
+                    if let Some(material_index) = material_index {
+                        primitive["material"] = json!(material_index);
+                    }
+                    meshes.push(json!({ "primitives": [primitive] }));
+                    Some(meshes.len() - 1)
+                }
+                Err(e) => {
+                    summary.skipped.push(format!("{}: {}", name, e));
+                    None
+                }
+            }
+        });
+        let Some(mesh_index) = mesh_index else {
+            continue;
+        };
+
+        let mut node = transform_json(&transform);
+        node.insert("name".into(), json!(name));
+        node.insert("mesh".into(), json!(mesh_index));
+        nodes.push(Value::Object(node));
+    }
+
+    let mut light_query = world.query::<(
+        Entity,
+        Option<&Name>,
+        &GlobalTransform,
+        Option<&PointLight>,
+        Option<&SpotLight>,
+        Option<&DirectionalLight>,
+    )>();
+    for (entity, name, transform, point, spot, directional) in light_query.iter(world) {
+        // Bevy's glTF loader multiplies candela by 4π to get lumens
+        let light = match (point, spot, directional) {
+            (Some(point), _, _) => json!({
+                "type": "point",
+                "color": rgb(point.color),
+                "intensity": point.intensity / (4.0 * PI),
+                "range": point.range,
+            }),
+            (_, Some(spot), _) => json!({
+                "type": "spot",
+                "color": rgb(spot.color),
+                "intensity": spot.intensity / (4.0 * PI),
+                "range": spot.range,
+                "spot": {
+                    "innerConeAngle": spot.inner_angle,
+                    "outerConeAngle": spot.outer_angle,
+                },
+            }),
+            (_, _, Some(directional)) => json!({
+                "type": "directional",
+                "color": rgb(directional.color),
+                "intensity": directional.illuminance,
+            }),
+            _ => continue,
+        };
+        lights.push(light);
+        let mut node = transform_json(transform);
+        node.insert("name".into(), json!(node_name(entity, name)));
+        node.insert(
+            "extensions".into(),
+            json!({ "KHR_lights_punctual": { "light": lights.len() - 1 } }),
+        );
+        nodes.push(Value::Object(node));
+    }
+
+    if nodes.is_empty() {
+        return Err("Nothing to export".to_string());
+    }
+    summary.nodes = nodes.len();
+    summary.meshes = meshes.len();
+    summary.materials = materials.len();
+    summary.lights = lights.len();
+
+    let mut extensions_used = Vec::new();
+    if !lights.is_empty() {
+        extensions_used.push("KHR_lights_punctual");
+    }
+    if materials
+        .iter()
+        .any(|material| material.get("extensions").is_some())
+    {
+        extensions_used.push("KHR_materials_unlit");
+    }
+    let mut document = json!({
+        "asset": { "version": "2.0", "generator": "Tauri-Bevy Demo" },
+        "scene": 0,
+        "scenes": [{ "nodes": (0..nodes.len()).collect::<Vec<_>>() }],
+        "nodes": nodes,
+    });
+    // glTF arrays may not be empty when present
+    if !meshes.is_empty() {
+        document["meshes"] = json!(meshes);
+    }
+    if !materials.is_empty() {
+        document["materials"] = json!(materials);
+    }
+    if !extensions_used.is_empty() {
+        document["extensionsUsed"] = json!(extensions_used);
+    }
+    if !lights.is_empty() {
+        document["extensions"] = json!({ "KHR_lights_punctual": { "lights": lights } });
+    }
+    let bytes = glb.finish(document)?;
+    println!(
+        "[Bevy] Exported {} nodes, {} meshes, {} lights to glTF ({} skipped)",
+        summary.nodes,
+        summary.meshes,
+        summary.lights,
+        summary.skipped.len()
+    );
+    Ok((bytes, summary))
+}
+
+/// A visible mesh to export, see [`visible_meshes`]
+pub(crate) struct ExportMesh {
+    pub entity: Entity,
+    pub name: String,
+    pub transform: GlobalTransform,
+    pub mesh: AssetId<Mesh>,
+    pub material: Option<AssetId<StandardMaterial>>,
+}
+
+/// The visible meshes of the scene other than the overlay and particles,
+/// with the material each is drawn with outside debug views
+pub(crate) fn visible_meshes(world: &mut World) -> Vec<ExportMesh> {
+    let mut query = world.query_filtered::<(
+        Entity,
+        Option<&Name>,
+        &GlobalTransform,
+        &InheritedVisibility,
+        &Mesh3d,
+        Option<&MeshMaterial3d<StandardMaterial>>,
+        Option<&DebugViewOriginal>,
+    ), (
+        Without<Camera>,
+        Without<DebugOverlayText>,
+        Without<Particle>,
+    )>();
+    query
+        .iter(world)
+        .filter(|(_, _, _, visibility, ..)| visibility.get())
+        .map(
+            |(entity, name, transform, _, mesh, material, original)| ExportMesh {
+                entity,
+                name: node_name(entity, name),
+                transform: *transform,
+                mesh: mesh.id(),
+                // Meshes drawn with a debug view keep their own material aside
+                material: original
+                    .map(|original| original.0.id())
+                    .or_else(|| material.map(|material| material.id())),
+            },
+        )
+        .collect()
+}
+
+pub(crate) fn node_name(entity: Entity, name: Option<&Name>) -> String {
+    name.map_or_else(
+        || format!("entity_{}", entity.index()),
+        |name| name.to_string(),
+    )
+}
+
+/// Translation, rotation and scale of a node
+fn transform_json(transform: &GlobalTransform) -> Map<String, Value> {
+    let (scale, rotation, translation) = transform.to_scale_rotation_translation();
+    let mut node = Map::new();
+    node.insert("translation".into(), json!(translation.to_array()));
+    node.insert("rotation".into(), json!(rotation.to_array()));
+    node.insert("scale".into(), json!(scale.to_array()));
+    node
+}
+
+fn rgb(color: Color) -> [f32; 3] {
+    let linear = color.to_linear();
+    [linear.red, linear.green, linear.blue]
+}
+
+/// A standard material as glTF PBR factors
+fn material_json(material: &StandardMaterial) -> Value {
+    let base = material.base_color.to_linear();
+    let mut json = json!({
+        "pbrMetallicRoughness": {
+            "baseColorFactor": [base.red, base.green, base.blue, base.alpha],
+            "metallicFactor": material.metallic,
+            "roughnessFactor": material.perceptual_roughness,
+        },
+        "doubleSided": material.double_sided,
+    });
+
+    // glTF caps emissive factors at 1, brighter emission needs
+    // KHR_materials_emissive_strength, which this leaves out
+    let emissive = material.emissive;
+    let peak = emissive.red.max(emissive.green).max(emissive.blue);
+    if peak > 0.0 {
+        let scale = peak.max(1.0);
+        json["emissiveFactor"] = json!([
+            emissive.red / scale,
+            emissive.green / scale,
+            emissive.blue / scale
+        ]);
+    }
+    match material.alpha_mode {
+        AlphaMode::Opaque => {}
+        AlphaMode::Mask(cutoff) => {
+            json["alphaMode"] = json!("MASK");
+            json["alphaCutoff"] = json!(cutoff);
+        }
+        _ => json["alphaMode"] = json!("BLEND"),
+    }
+    if material.unlit {
+        json["extensions"] = json!({ "KHR_materials_unlit": {} });
+    }
+    json
+}
+
+/// A mesh as a glTF primitive, its data appended to `glb`
+pub(crate) fn mesh_primitive(glb: &mut GlbBuilder, mesh: &Mesh) -> Result<Value, String> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return Err("not a triangle list".to_string());
+    }
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return Err("no float positions".to_string());
+    };
+
+    // glTF requires the bounds of positions
+    let (min, max) = positions.iter().fold(
+        (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+        |(min, max), position| {
+            let position = Vec3::from_array(*position);
+            (min.min(position), max.max(position))
+        },
+    );
+    let mut attributes = Map::new();
+    let position = glb.accessor(
+        &floats(positions.iter().flatten()),
+        ARRAY_BUFFER,
+        FLOAT,
+        positions.len(),
+        "VEC3",
+    );
+    glb.accessors[position]["min"] = json!(min.to_array());
+    glb.accessors[position]["max"] = json!(max.to_array());
+    attributes.insert("POSITION".into(), json!(position));
+
+    if let Some(VertexAttributeValues::Float32x3(normals)) = mesh.attribute(Mesh::ATTRIBUTE_NORMAL)
+    {
+        let normal = glb.accessor(
+            &floats(normals.iter().flatten()),
+            ARRAY_BUFFER,
+            FLOAT,
+            normals.len(),
+            "VEC3",
+        );
+        attributes.insert("NORMAL".into(), json!(normal));
+    }
+    if let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+        let uv = glb.accessor(
+            &floats(uvs.iter().flatten()),
+            ARRAY_BUFFER,
+            FLOAT,
+            uvs.len(),
+            "VEC2",
+        );
+        attributes.insert("TEXCOORD_0".into(), json!(uv));
+    }
+
+    let mut primitive = json!({ "attributes": attributes, "mode": 4 });
+    let indices = match mesh.indices() {
+        Some(Indices::U16(indices)) => Some((
+            indices
+                .iter()
+                .flat_map(|index| index.to_le_bytes())
+                .collect::<Vec<u8>>(),
+            UNSIGNED_SHORT,
+            indices.len(),
+        )),
+        Some(Indices::U32(indices)) => Some((
+            indices
+                .iter()
+                .flat_map(|index| index.to_le_bytes())
+                .collect(),
+            UNSIGNED_INT,
+            indices.len(),
+        )),
+        None => None,
+    };
+    if let Some((bytes, component_type, count)) = indices {
+        let accessor = glb.accessor(
+            &bytes,
+            ELEMENT_ARRAY_BUFFER,
+            component_type,
+            count,
+            "SCALAR",
+        );
+        primitive["indices"] = json!(accessor);
+    }
+    Ok(primitive)
+}
+
+fn floats<'a>(values: impl Iterator<Item = &'a f32>) -> Vec<u8> {
+    values.flat_map(|value| value.to_le_bytes()).collect()
+}
+
+// =============================================================================
+// GLB Container
+// =============================================================================
+
+/// Binary buffer with its views and accessors, being filled
+#[derive(Default)]
+pub(crate) struct GlbBuilder {
+    bin: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl GlbBuilder {
+    /// Append `bytes` as a buffer view with one accessor, returning the
+    /// accessor's index
+    fn accessor(
+        &mut self,
+        bytes: &[u8],
+        target: u32,
+        component_type: u32,
+        count: usize,
+        kind: &str,
+    ) -> usize {
+        // Accessors must start at a multiple of their component size
+        pad(&mut self.bin, 0);
+        self.buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": self.bin.len(),
+            "byteLength": bytes.len(),
+            "target": target,
+        }));
+        self.bin.extend_from_slice(bytes);
+        self.accessors.push(json!({
+            "bufferView": self.buffer_views.len() - 1,
+            "componentType": component_type,
+            "count": count,
+            "type": kind,
+        }));
+        self.accessors.len() - 1
+    }
+
+    /// The .glb file: header, JSON chunk and binary chunk
+    pub(crate) fn finish(mut self, mut document: Value) -> Result<Vec<u8>, String> {
+        pad(&mut self.bin, 0);
+        // Without mesh data, e.g. only lights, there is no buffer at all
+        if !self.bin.is_empty() {
+            document["buffers"] = json!([{ "byteLength": self.bin.len() }]);
+            document["bufferViews"] = Value::Array(self.buffer_views);
+            document["accessors"] = Value::Array(self.accessors);
+        }
+        let mut json = serde_json::to_vec(&document).map_err(|e| e.to_string())?;
+        pad(&mut json, b' ');
+
+        let bin_chunk = if self.bin.is_empty() {
+            0
+        } else {
+            8 + self.bin.len()
+        };
+        let length = 12 + 8 + json.len() + bin_chunk;
+        let length = u32::try_from(length).map_err(|_| "Scene is too large for a .glb")?;
+        let mut glb = Vec::with_capacity(length as usize);
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&length.to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json);
+        if !self.bin.is_empty() {
+            glb.extend_from_slice(&(self.bin.len() as u32).to_le_bytes());
+            glb.extend_from_slice(b"BIN\0");
+            glb.extend_from_slice(&self.bin);
+        }
+        Ok(glb)
+    }
+}
+
+/// Pad `bytes` to a multiple of 4 with `fill`, as GLB chunks require
+fn pad(bytes: &mut Vec<u8>, fill: u8) {
+    bytes.resize(bytes.len().next_multiple_of(4), fill);
+}
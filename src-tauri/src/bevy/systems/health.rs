@@ -0,0 +1,31 @@
+//! Renderer liveness for the `get_health` command
+//!
+//! Bevy records the start of every frame and whether the GPU device was
+//! lost. Tauri compares the last frame start with the clock, so a hung or
+//! crashed render loop shows up even though it can no longer report
+//! anything itself.
+
+use bevy::prelude::*;
+use bevy::render::renderer::RenderDevice;
+
+use crate::bevy::resources::HealthRes;
+
+/// Record that a new frame started
+pub fn publish_heartbeat(health: Res<HealthRes>) {
+    health.0.heartbeat();
+}
+
+/// Have wgpu report when the render device is lost, e.g. on a driver
+/// reset or when the GPU is unplugged
+pub fn watch_device_lost(render_device: Option<Res<RenderDevice>>, health: Res<HealthRes>) {
+    let Some(render_device) = render_device else {
+        return;
+    };
+    let health = health.0.clone();
+    render_device
+        .wgpu_device()
+        .set_device_lost_callback(move |reason, message| {
+            eprintln!("[Bevy] GPU device lost ({:?}): {}", reason, message);
+            health.set_device_lost(format!("{:?}: {}", reason, message));
+        });
+}
@@ -0,0 +1,307 @@
+//! Undo and redo for scene edits
+//!
+//! Commands that change the scene, such as `spawn_primitive`,
+//! `set_entity_transform` or `set_submesh_material`, record what they
+//! changed in [`EditHistory`]. Removed entities aren't despawned but
+//! disabled, which hides them from rendering and queries and keeps their
+//! ids, so undo can bring them back as they were. They are despawned for
+//! good once their edit falls out of the history, and entities spawned by
+//! an undone edit once a new edit clears the redo stack.
+//!
+//! Edits applied for other instances are collected into [`RemoteEdits`]
+//! instead, see `scene_sync`, so they can be undone when the instance
+//! that made them undoes them, without ever showing up in this one's undo.
+
+use bevy::{ecs::entity_disabling::Disabled, prelude::*};
+use std::time::Instant;
+
+use crate::bevy::components::{CustomShaderOriginal, DebugViewOriginal, MaterialOverrideOriginal};
+use crate::bevy::resources::{EditChange, EditHistory, MaterialState, RemoteEdits, SceneEdit};
+use crate::bevy::systems::custom_shaders::{descendants, draw_with_custom_material};
+use crate::bevy::systems::morph::ease_weight;
+use crate::bevy::systems::tags::set_entity_tags;
+use crate::config::history::MERGE_WINDOW;
+
+/// Record `changes` made by command `label` as one edit
+///
+/// Clears the redo stack. A transform change following one to the same
+/// entity within `MERGE_WINDOW` is merged into it. While an edit from
+/// another instance is applied the changes go to [`RemoteEdits`] instead.
+/// Nothing is recorded if no change changed anything.
+pub(crate) fn record(world: &mut World, label: &'static str, mut changes: Vec<EditChange>) {
+    changes.retain(|change| !is_empty(change));
+    if changes.is_empty() {
+        return;
+    }
+    if let Some(capturing) = &mut world.resource_mut::<RemoteEdits>().capturing {
+        capturing.extend(changes);
+        return;
+    }
+    let dropped = std::mem::take(&mut world.resource_mut::<EditHistory>().redo);
+    for edit in dropped {
+        discard(world, edit.changes, true);
+    }
+
+    let now = Instant::now();
+    let mut history = world.resource_mut::<EditHistory>();
+    if let Some(last) = history.undo.back_mut() {
+        if let (
+            [EditChange::Transform { entity, after, .. }],
+            [EditChange::Transform {
+                entity: next,
+                after: next_after,
+                ..
+            }],
+        ) = (last.changes.as_mut_slice(), changes.as_slice())
+        {
+            if last.label == label && entity == next && now - last.recorded < MERGE_WINDOW {
+                *after = *next_after;
+                last.recorded = now;
+                let id = last.id;
+                history.last_recorded = Some(id);
+                return;
+            }
+        }
+    }
+    let id = history.next_id;
+    history.next_id += 1;
+    history.last_recorded = Some(id);
+    history.undo.push_back(SceneEdit {
+        label,
+        changes,
+        recorded: now,
+        id,
+        synced: false,
+    });
+    trim(world);
+}
+
+/// Undo the last edit and return the command that made it, or `None` if
+/// there is nothing to undo
+pub fn undo(world: &mut World) -> Option<&'static str> {
+    let edit = world.resource_mut::<EditHistory>().undo.pop_back()?;
+    apply_changes(world, &edit.changes, true);
+    println!("[Bevy] Undid {}", edit.label);
+    let label = edit.label;
+    world.resource_mut::<EditHistory>().redo.push(edit);
+    Some(label)
+}
+
+/// Redo the last undone edit and return the command that made it, or
+/// `None` if there is nothing to redo
+pub fn redo(world: &mut World) -> Option<&'static str> {
+    let edit = world.resource_mut::<EditHistory>().redo.pop()?;
+    apply_changes(world, &edit.changes, false);
+    println!("[Bevy] Redid {}", edit.label);
+    let label = edit.label;
+    world.resource_mut::<EditHistory>().undo.push_back(edit);
+    Some(label)
+}
+
+/// Keep at most `depth` edits, dropping the oldest
+pub fn set_history_depth(world: &mut World, depth: usize) {
+    world.resource_mut::<EditHistory>().depth = depth;
+    trim(world);
+    println!("[Bevy] Keeping {} edits to undo", depth);
+}
+
+/// Disable `roots` and their descendants in place of despawning them, and
+/// return the change to record
+pub(crate) fn remove(world: &mut World, roots: &[Entity]) -> EditChange {
+    let mut entities = Vec::new();
+    for &root in roots {
+        if world.get_entity(root).is_ok() {
+            entities.extend(descendants(world, root));
+        }
+    }
+    // Roots may be descendants of each other
+    entities.sort();
+    entities.dedup();
+    for &entity in &entities {
+        world.entity_mut(entity).insert(Disabled);
+    }
+    EditChange::Removed(entities)
+}
+
+/// Standard material of `entity`, `None` if it is drawn with another one
+pub(crate) fn material_state(world: &World, entity: Entity) -> Option<MaterialState> {
+    let entity = world.get_entity(entity).ok()?;
+    Some(MaterialState {
+        material: entity.get::<MeshMaterial3d<StandardMaterial>>()?.0.clone(),
+        original: entity
+            .get::<MaterialOverrideOriginal>()
+            .map(|original| original.0.clone()),
+    })
+}
+
+/// Whether `change` covers no entities, such as removing an empty tag
+fn is_empty(change: &EditChange) -> bool {
+    match change {
+        EditChange::Spawned(entities) | EditChange::Removed(entities) => entities.is_empty(),
+        _ => false,
+    }
+}
+
+/// Drop the oldest edits over the depth, despawning what they removed
+fn trim(world: &mut World) {
+    loop {
+        let mut history = world.resource_mut::<EditHistory>();
+        if history.undo.len() <= history.depth {
+            return;
+        }
+        let Some(edit) = history.undo.pop_front() else {
+            return;
+        };
+        discard(world, edit.changes, false);
+    }
+}
+
+/// Put `changes` into their state before the edit if `undo`, after
+/// otherwise
+pub(crate) fn apply_changes(world: &mut World, changes: &[EditChange], undo: bool) {
+    if undo {
+        for change in changes.iter().rev() {
+            apply(world, change, true);
+        }
+    } else {
+        for change in changes {
+            apply(world, change, false);
+        }
+    }
+}
+
+/// Forget an edit that can no longer be undone or redone, despawning what
+/// it removed, or what it spawned if it is `undone`
+pub(crate) fn discard(world: &mut World, changes: Vec<EditChange>, undone: bool) {
+    for change in changes {
+        match change {
+            EditChange::Spawned(entities) if undone => despawn_all(world, &entities),
+            EditChange::Removed(entities) if !undone => despawn_all(world, &entities),
+            _ => {}
+        }
+    }
+}
+
+/// Put `change` into its state before the edit if `undo`, after otherwise
+///
+/// Entities despawned since, e.g. by loading another model, are skipped.
+fn apply(world: &mut World, change: &EditChange, undo: bool) {
+    match change {
+        EditChange::Spawned(entities) => set_disabled(world, entities, undo),
+        EditChange::Removed(entities) => set_disabled(world, entities, !undo),
+        EditChange::Transform {
+            entity,
+            before,
+            after,
+        } => {
+            if let Ok(mut entity) = world.get_entity_mut(*entity) {
+                entity.insert(if undo { *before } else { *after });
+            }
+        }
+        EditChange::Material {
+            entity,
+            before,
+            after,
+        } => {
+            let state = if undo { before } else { after };
+            let Ok(mut entity) = world.get_entity_mut(*entity) else {
+                return;
+            };
+            // The debug view or custom shader puts it back when turned off
+            if let Some(mut original) = entity.get_mut::<DebugViewOriginal>() {
+                original.0 = state.material.clone();
+            } else if let Some(mut original) = entity.get_mut::<CustomShaderOriginal>() {
+                original.0 = state.material.clone();
+            } else {
+                entity.insert(MeshMaterial3d(state.material.clone()));
+            }
+            match &state.original {
+                Some(original) => {
+                    entity.insert(MaterialOverrideOriginal(original.clone()));
+                }
+                None => {
+                    entity.remove::<MaterialOverrideOriginal>();
+                }
+            }
+        }
+        EditChange::Visibility {
+            entity,
+            before,
+            after,
+        } => {
+            if let Ok(mut entity) = world.get_entity_mut(*entity) {
+                entity.insert(if undo { *before } else { *after });
+            }
+        }
+        EditChange::Parent {
+            entity,
+            before,
+            after,
+        } => {
+            let parent = if undo { *before } else { *after };
+            // A parent despawned since leaves it at the root
+            let parent = parent.filter(|&parent| world.get_entity(parent).is_ok());
+            let Ok(mut entity) = world.get_entity_mut(*entity) else {
+                return;
+            };
+            match parent {
+                Some(parent) => {
+                    entity.insert(ChildOf(parent));
+                }
+                None => {
+                    entity.remove::<ChildOf>();
+                }
+            }
+        }
+        EditChange::CustomShader {
+            entity,
+            before,
+            after,
+        } => {
+            if let Ok(mut entity) = world.get_entity_mut(*entity) {
+                draw_with_custom_material(&mut entity, if undo { before } else { after }.as_ref());
+            }
+        }
+        EditChange::MorphWeight {
+            entity,
+            index,
+            before,
+            after,
+        } => {
+            if let Ok(mut entity) = world.get_entity_mut(*entity) {
+                ease_weight(&mut entity, *index, if undo { *before } else { *after });
+            }
+        }
+        EditChange::Tags {
+            entity,
+            before,
+            after,
+        } => {
+            if let Ok(mut entity) = world.get_entity_mut(*entity) {
+                set_entity_tags(&mut entity, if undo { before } else { after }.clone());
+            }
+        }
+    }
+}
+
+fn set_disabled(world: &mut World, entities: &[Entity], disabled: bool) {
+    for &entity in entities {
+        let Ok(mut entity) = world.get_entity_mut(entity) else {
+            continue;
+        };
+        if disabled {
+            entity.insert(Disabled);
+        } else {
+            entity.remove::<Disabled>();
+        }
+    }
+}
+
+fn despawn_all(world: &mut World, entities: &[Entity]) {
+    for &entity in entities {
+        // Gone already if an ancestor was despawned first
+        if let Ok(entity) = world.get_entity_mut(entity) {
+            entity.despawn();
+        }
+    }
+}
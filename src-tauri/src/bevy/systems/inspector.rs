@@ -0,0 +1,106 @@
+//! World inspector
+//!
+//! Backend of a web inspector panel: lists entities and resources, and
+//! serializes an entity's components through Bevy's reflection. Components
+//! not registered for reflection, like most of the app's own, are listed by
+//! name without a value.
+
+use bevy::ecs::component::ComponentInfo;
+use bevy::prelude::*;
+use bevy::reflect::serde::TypedReflectSerializer;
+use bevy::reflect::{TypeRegistration, TypeRegistry};
+
+use crate::tauri_bridge::shared_state::{
+    EntityInspection, EntitySummary, InspectedComponent, ResourceSummary,
+};
+
+/// Every entity's id, name and component count, in id order
+pub fn list_entities(world: &mut World) -> Vec<EntitySummary> {
+    let mut entities: Vec<EntitySummary> = world
+        .query::<EntityRef>()
+        .iter(world)
+        .map(|entity| EntitySummary {
+            id: entity.id().to_bits(),
+            name: entity.get::<Name>().map(|name| name.to_string()),
+            components: entity.archetype().component_count(),
+        })
+        .collect();
+    entities.sort_by_key(|entity| entity.id);
+    entities
+}
+
+/// The components of entity `id`, serialized where registered for
+/// reflection
+pub fn inspect_entity(world: &World, id: u64) -> Result<EntityInspection, String> {
+    let entity = Entity::try_from_bits(id).ok_or_else(|| format!("Invalid entity id {}", id))?;
+    let entity_ref = world
+        .get_entity(entity)
+        .map_err(|_| format!("No entity with id {}", id))?;
+    let registry = world.resource::<AppTypeRegistry>().read();
+
+    let components = world
+        .inspect_entity(entity)
+        .map_err(|_| format!("No entity with id {}", id))?
+        .map(|info| {
+            let registration = registration(&registry, info);
+            let reflected = registration
+                .and_then(|registration| registration.data::<ReflectComponent>())
+                .and_then(|reflect| reflect.reflect(&entity_ref));
+            let (value, error) = match reflected {
+                Some(value) => match serde_json::to_value(TypedReflectSerializer::new(
+                    value.as_partial_reflect(),
+                    &registry,
+                )) {
+                    Ok(value) => (Some(value), None),
+                    Err(e) => (None, Some(e.to_string())),
+                },
+                None => (None, None),
+            };
+            InspectedComponent {
+                name: type_name(registration, info),
+                reflected: reflected.is_some(),
+                value,
+                error,
+            }
+        })
+        .collect();
+
+    Ok(EntityInspection {
+        id,
+        name: entity_ref.get::<Name>().map(|name| name.to_string()),
+        components,
+    })
+}
+
+/// Names of all resources, sorted
+pub fn list_resources(world: &World) -> Vec<ResourceSummary> {
+    let registry = world.resource::<AppTypeRegistry>().read();
+    let mut resources: Vec<ResourceSummary> = world
+        .iter_resources()
+        .map(|(info, _)| {
+            let registration = registration(&registry, info);
+            ResourceSummary {
+                name: type_name(registration, info),
+                reflected: registration
+                    .is_some_and(|registration| registration.data::<ReflectResource>().is_some()),
+            }
+        })
+        .collect();
+    resources.sort_by(|a, b| a.name.cmp(&b.name));
+    resources
+}
+
+fn registration<'a>(
+    registry: &'a TypeRegistry,
+    info: &ComponentInfo,
+) -> Option<&'a TypeRegistration> {
+    info.type_id().and_then(|type_id| registry.get(type_id))
+}
+
+/// Full type path from the registry, Bevy's debug name otherwise
+fn type_name(registration: Option<&TypeRegistration>, info: &ComponentInfo) -> String {
+    registration.map_or_else(
+        || info.name().to_string(),
+        |registration| registration.type_info().type_path().to_string(),
+    )
+}
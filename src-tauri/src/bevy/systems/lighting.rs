@@ -0,0 +1,163 @@
+//! Lighting presets
+//!
+//! The scene's lights form a rig tagged with [`LightRig`]. A preset
+//! replaces the whole rig and the ambient light in one exclusive world
+//! access, so no frame is rendered with half of the old rig and half of
+//! the new one. Lights are named, so they show in the scene tree and can
+//! be moved like any other object; moved lights are reset by the next
+//! preset.
+
+use bevy::math::EulerRot;
+use bevy::prelude::*;
+
+use crate::bevy::components::LightRig;
+use crate::bevy::resources::ActiveLightingPreset;
+use crate::tauri_bridge::shared_state::LightingPreset;
+
+/// Replace the scene's light rig and ambient light with `preset`
+pub fn set_lighting_preset(world: &mut World, preset: LightingPreset) {
+    let rig: Vec<Entity> = world
+        .query_filtered::<Entity, With<LightRig>>()
+        .iter(world)
+        .collect();
+    for entity in rig {
+        world.despawn(entity);
+    }
+
+    let ambient = match preset {
+        LightingPreset::Default => {
+            spawn_light(
+                world,
+                "key_light",
+                PointLight {
+                    intensity: 2_000_000.0,
+                    shadows_enabled: true,
+                    color: Color::srgb(1.0, 0.95, 0.85),
+                    ..default()
+                },
+                Transform::from_xyz(4.0, 8.0, 4.0),
+            );
+            spawn_light(
+                world,
+                "fill_light",
+                PointLight {
+                    intensity: 800_000.0,
+                    color: Color::srgb(0.4, 0.6, 1.0),
+                    ..default()
+                },
+                Transform::from_xyz(-3.0, 4.0, -2.0),
+            );
+            spawn_light(
+                world,
+                "sun",
+                DirectionalLight {
+                    illuminance: 3000.0,
+                    shadows_enabled: true,
+                    ..default()
+                },
+                sun_angle(-0.6, 0.4),
+            );
+            AmbientLight::default()
+        }
+        LightingPreset::Studio => {
+            spawn_light(
+                world,
+                "key_light",
+                PointLight {
+                    intensity: 1_500_000.0,
+                    shadows_enabled: true,
+                    color: Color::srgb(1.0, 0.97, 0.92),
+                    ..default()
+                },
+                Transform::from_xyz(4.0, 5.0, 4.0),
+            );
+            spawn_light(
+                world,
+                "fill_light",
+                PointLight {
+                    intensity: 400_000.0,
+                    color: Color::srgb(0.9, 0.93, 1.0),
+                    ..default()
+                },
+                Transform::from_xyz(-5.0, 3.0, 3.0),
+            );
+            spawn_light(
+                world,
+                "rim_light",
+                PointLight {
+                    intensity: 1_000_000.0,
+                    ..default()
+                },
+                Transform::from_xyz(0.0, 4.0, -5.0),
+            );
+            AmbientLight {
+                brightness: 40.0,
+                ..default()
+            }
+        }
+        LightingPreset::Outdoor => {
+            spawn_light(
+                world,
+                "sun",
+                DirectionalLight {
+                    illuminance: 6000.0,
+                    shadows_enabled: true,
+                    color: Color::srgb(1.0, 0.96, 0.88),
+                    ..default()
+                },
+                sun_angle(-0.9, 0.5),
+            );
+            AmbientLight {
+                color: Color::srgb(0.6, 0.75, 1.0),
+                brightness: 400.0,
+                ..default()
+            }
+        }
+        LightingPreset::Night => {
+            spawn_light(
+                world,
+                "moon",
+                DirectionalLight {
+                    illuminance: 50.0,
+                    shadows_enabled: true,
+                    color: Color::srgb(0.6, 0.7, 1.0),
+                    ..default()
+                },
+                sun_angle(-1.1, -0.6),
+            );
+            spawn_light(
+                world,
+                "lamp",
+                PointLight {
+                    intensity: 300_000.0,
+                    shadows_enabled: true,
+                    color: Color::srgb(1.0, 0.7, 0.4),
+                    ..default()
+                },
+                Transform::from_xyz(2.0, 2.5, 2.0),
+            );
+            AmbientLight {
+                color: Color::srgb(0.3, 0.4, 0.7),
+                brightness: 15.0,
+                ..default()
+            }
+        }
+        LightingPreset::Flat => AmbientLight {
+            brightness: 600.0,
+            ..default()
+        },
+    };
+    world.insert_resource(ambient);
+    world.insert_resource(ActiveLightingPreset(preset));
+    println!("[Bevy] Lighting preset set to {:?}", preset);
+}
+
+fn spawn_light(world: &mut World, name: &'static str, light: impl Bundle, transform: Transform) {
+    world.spawn((light, transform, Name::new(name), LightRig));
+}
+
+/// Orientation of a directional light pitched by `x` and turned by `y`
+/// radians
+fn sun_angle(x: f32, y: f32) -> Transform {
+    Transform::from_rotation(Quat::from_euler(EulerRot::XYZ, x, y, 0.0))
+}
@@ -0,0 +1,151 @@
+//! Per-submesh material overrides
+//!
+//! A glTF mesh with several primitives, such as one part of an assembly,
+//! is spawned as one entity per primitive, each with its own material.
+//! Overriding one swaps its material for a changed copy and keeps the
+//! original to put back, leaving other meshes sharing that material as
+//! they are.
+
+use bevy::prelude::*;
+
+use crate::bevy::components::MaterialOverrideOriginal;
+use crate::bevy::resources::{EditChange, MaterialState};
+use crate::bevy::systems::custom_shaders::descendants;
+use crate::bevy::systems::history::{material_state, record};
+use crate::tauri_bridge::shared_state::{MaterialOverride, SubmeshInfo};
+
+/// Meshes of entity `id` and its descendants
+pub fn list_submeshes(world: &mut World, id: u64) -> Result<Vec<SubmeshInfo>, String> {
+    let root = Entity::try_from_bits(id).ok_or_else(|| format!("Invalid entity id {}", id))?;
+    if world.get_entity(root).is_err() {
+        return Err(format!("No entity with id {}", id));
+    }
+
+    let meshes = world.resource::<Assets<Mesh>>();
+    let submeshes = descendants(world, root)
+        .into_iter()
+        .filter_map(|entity| {
+            let entity = world.entity(entity);
+            let mesh = entity.get::<Mesh3d>()?;
+            let name = |entity: EntityRef| entity.get::<Name>().map(|name| name.to_string());
+            Some(SubmeshInfo {
+                id: entity.id().to_bits(),
+                name: name(entity),
+                parent: entity
+                    .get::<ChildOf>()
+                    .and_then(|parent| name(world.entity(parent.parent()))),
+                vertices: meshes.get(&mesh.0).map_or(0, Mesh::count_vertices),
+                overridden: entity.contains::<MaterialOverrideOriginal>(),
+            })
+        })
+        .collect();
+    Ok(submeshes)
+}
+
+/// Draw mesh `id` with its own material changed by `material`, or restore
+/// the materials of `id` and its descendants if `None`
+pub fn set_submesh_material(
+    world: &mut World,
+    id: u64,
+    material: Option<MaterialOverride>,
+) -> Result<(), String> {
+    let entity = Entity::try_from_bits(id).ok_or_else(|| format!("Invalid entity id {}", id))?;
+    if world.get_entity(entity).is_err() {
+        return Err(format!("No entity with id {}", id));
+    }
+    let changes = change_submesh_material(world, entity, material)?;
+    record(world, "set_submesh_material", changes);
+    Ok(())
+}
+
+/// [`set_submesh_material`] on an existing entity, returning the changes
+/// for the caller to record
+pub(crate) fn change_submesh_material(
+    world: &mut World,
+    entity: Entity,
+    material: Option<MaterialOverride>,
+) -> Result<Vec<EditChange>, String> {
+    let Some(material) = material else {
+        let mut changes = Vec::new();
+        for entity in descendants(world, entity) {
+            // Put back by the debug view or custom shader when it is off
+            let Some(before) = material_state(world, entity) else {
+                continue;
+            };
+            let mut entity_mut = world.entity_mut(entity);
+            if let Some(original) = entity_mut.take::<MaterialOverrideOriginal>() {
+                entity_mut.insert(MeshMaterial3d(original.0.clone()));
+                let after = MaterialState {
+                    material: original.0,
+                    original: None,
+                };
+                changes.push(EditChange::Material {
+                    entity,
+                    before,
+                    after,
+                });
+            }
+        }
+        println!("[Bevy] Restored {} submesh materials", changes.len());
+        return Ok(changes);
+    };
+
+    // Other materials, such as a debug view's, stay in charge
+    let Some(before) = material_state(world, entity) else {
+        return Err(format!(
+            "Entity {} has no standard material, is a debug view or custom shader on?",
+            entity.to_bits()
+        ));
+    };
+    // Overriding again starts from the original, not the last override
+    let original = before
+        .original
+        .clone()
+        .unwrap_or_else(|| before.material.clone());
+    let mut changed = world
+        .resource::<Assets<StandardMaterial>>()
+        .get(&original)
+        .cloned()
+        .ok_or_else(|| format!("Material of entity {} is not loaded", entity.to_bits()))?;
+    match material.color.as_deref() {
+        None => {}
+        Some(&[r, g, b]) => changed.base_color = Color::srgb(r, g, b),
+        Some(&[r, g, b, a]) => changed.base_color = Color::srgba(r, g, b, a),
+        Some(_) => return Err("color must have 3 or 4 components".to_string()),
+    }
+    if material.color.is_some() {
+        changed.base_color_texture = None;
+        if changed.base_color.alpha() < 1.0 && changed.alpha_mode == AlphaMode::Opaque {
+            changed.alpha_mode = AlphaMode::Blend;
+        }
+    }
+    match material.emissive.as_deref() {
+        None => {}
+        Some(&[r, g, b]) => changed.emissive = Color::srgb(r, g, b).to_linear(),
+        Some(_) => return Err("emissive must have 3 components".to_string()),
+    }
+    if let Some(metallic) = material.metallic {
+        changed.metallic = metallic;
+    }
+    if let Some(roughness) = material.roughness {
+        changed.perceptual_roughness = roughness;
+    }
+
+    let changed = world
+        .resource_mut::<Assets<StandardMaterial>>()
+        .add(changed);
+    world.entity_mut(entity).insert((
+        MeshMaterial3d(changed.clone()),
+        MaterialOverrideOriginal(original.clone()),
+    ));
+    println!("[Bevy] Material of {} overridden", entity);
+    let after = MaterialState {
+        material: changed,
+        original: Some(original),
+    };
+    Ok(vec![EditChange::Material {
+        entity,
+        before,
+        after,
+    }])
+}
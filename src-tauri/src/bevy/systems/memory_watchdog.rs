@@ -0,0 +1,85 @@
+//! Memory watchdog system
+//!
+//! Frame data lives in several places between the GPU and the frontend: the
+//! readback channel, the buffer pool, the published frame and the encoded
+//! JPEG. None of them can grow much on their own, but a stalled consumer
+//! could still pile up readbacks. This system sums them every frame and
+//! drops the oldest idle data once [`MemoryWatchdog::limit_bytes`] is hit.
+
+use bevy::prelude::*;
+
+use crate::bevy::resources::{
+    BufferPoolRes, FrameBufferRes, MainWorldReceiver, MemoryLimitExceeded, MemoryWatchdog,
+    PerfStatsRes,
+};
+
+/// Keep the frame data held by the pipeline under the configured limit
+///
+/// Pooled buffers are freed first since nothing uses them, then queued
+/// readbacks oldest first. The newest readback and the published frames
+/// are never dropped. [`MemoryLimitExceeded`] is triggered when the limit
+/// is first crossed, not on every frame spent above it.
+pub fn enforce_memory_limit(
+    mut commands: Commands,
+    mut watchdog: ResMut<MemoryWatchdog>,
+    receiver: Res<MainWorldReceiver>,
+    buffer_pool: Res<BufferPoolRes>,
+    frame_buffer: Res<FrameBufferRes>,
+    perf_stats: Option<Res<PerfStatsRes>>,
+) {
+    let pool = &buffer_pool.0;
+    let queued = receiver.queued_bytes.get();
+    let pooled = pool.pooled_bytes();
+    let published = frame_buffer
+        .0
+        .latest()
+        .map_or(0, |frame| frame.data.capacity());
+    let encoded = watchdog
+        .encoded_frame
+        .0
+        .lock()
+        .ok()
+        .and_then(|slot| slot.as_ref().map(|encoded| encoded.data.capacity()))
+        .unwrap_or_default();
+
+    let held = queued + pooled + published + encoded;
+    let limit = watchdog.limit_bytes;
+    let mut freed = 0;
+
+    if held > limit {
+        freed += pool.shrink_to(pooled.saturating_sub(held - limit));
+        while held - freed > limit && receiver.len() > 1 {
+            let Some(oldest) = receiver.recv_frame() else {
+                break;
+            };
+            freed += oldest.data.len();
+        }
+
+        if !watchdog.over_limit {
+            watchdog.times_exceeded += 1;
+            commands.trigger(MemoryLimitExceeded {
+                held_bytes: held,
+                limit_bytes: limit,
+                freed_bytes: freed,
+            });
+        }
+    }
+    watchdog.over_limit = held > limit;
+
+    if let Some(perf_res) = &perf_stats {
+        if let Ok(mut stats) = perf_res.0 .0.lock() {
+            stats.pipeline_memory_mb = (held - freed) as f64 / (1024.0 * 1024.0);
+            stats.memory_limit_exceeded = watchdog.times_exceeded;
+        }
+    }
+}
+
+/// Warn when the pipeline goes over its memory limit
+pub fn warn_memory_limit_exceeded(event: On<MemoryLimitExceeded>) {
+    eprintln!(
+        "[Bevy] Warning: frame pipeline holds {:.1}MB, over the {:.1}MB limit; dropped {:.1}MB",
+        event.held_bytes as f64 / (1024.0 * 1024.0),
+        event.limit_bytes as f64 / (1024.0 * 1024.0),
+        event.freed_bytes as f64 / (1024.0 * 1024.0),
+    );
+}
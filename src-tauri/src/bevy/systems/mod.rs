@@ -5,10 +5,86 @@
 
 pub mod scene;
 pub mod camera;
+pub mod camera_path;
+pub mod camera_effects;
+pub mod depth_of_field;
+pub mod fog;
+pub mod color_grading;
+pub mod exposure;
+pub mod lighting;
+pub mod environment;
+pub mod backdrop;
+pub mod quad_view;
 pub mod animation;
 pub mod frame_extraction;
+pub mod adaptive_rate;
+pub mod stream;
+pub mod bridge_commands;
+pub mod memory_watchdog;
+pub mod screenshot;
+pub mod turntable;
+pub mod stats_history;
+pub mod debug_overlay;
+pub mod ui_overlay;
+pub mod debug_view;
+pub mod culling;
+pub mod health;
+pub mod slow_frames;
+pub mod inspector;
+pub mod tags;
+pub mod selection_sets;
+pub mod readback_self_test;
+pub mod project;
+pub mod model_loading;
+pub mod asset_progress;
+pub mod prefetch;
+pub mod gltf_export;
+pub mod usdz_export;
+pub mod collision;
+pub mod scene_editing;
+pub mod raycast;
+pub mod picking;
+pub mod history;
+pub mod scene_sync;
+pub mod text3d;
+pub mod billboard;
+pub mod morph;
+pub mod custom_shaders;
+pub mod material_override;
+pub mod particles;
+pub mod terrain;
+pub mod water;
 
 pub use scene::setup_scene;
-pub use camera::update_camera_from_input;
-pub use animation::rotate_cubes;
-pub use frame_extraction::extract_and_process_frame;
+pub use camera::{follow_camera_focus, sync_orthographic_scale, update_camera_from_input};
+pub use camera_path::advance_camera_path;
+pub use camera_effects::apply_camera_shake;
+pub use animation::{advance_scene_time, rotate_cubes, set_fixed_timestep};
+pub use frame_extraction::{extract_and_process_frame, record_rendered_view};
+pub use adaptive_rate::adapt_frame_rate;
+pub use quad_view::layout_quad_view;
+pub use stream::update_stream_target;
+pub use bridge_commands::apply_bridge_commands;
+pub use memory_watchdog::{enforce_memory_limit, warn_memory_limit_exceeded};
+pub use screenshot::{finish_screenshot, start_screenshot};
+pub use turntable::{advance_turntable, start_turntable};
+pub use stats_history::record_stats_history;
+pub use debug_overlay::update_debug_overlay;
+pub use ui_overlay::{despawn_orphaned_ui_overlays, update_scale_bars};
+pub use debug_view::apply_debug_view_to_new_meshes;
+pub use culling::hold_frozen_frusta;
+pub use health::{publish_heartbeat, watch_device_lost};
+pub use slow_frames::{detect_slow_frames, mark_frame_start};
+pub use readback_self_test::finish_readback_check;
+pub use model_loading::{
+    mark_model_scene_ready, place_model_proxy, reveal_model, track_model_load, watch_model_reload,
+};
+pub use asset_progress::report_asset_progress;
+pub use prefetch::run_prefetch_queue;
+pub use picking::finish_gpu_pick;
+pub use environment::track_environment_loads;
+pub use color_grading::track_color_lut_loads;
+pub use particles::{emit_particles, update_particles};
+pub use billboard::face_billboards;
+pub use morph::ease_morph_weights;
+pub use water::{resize_reflection_target, update_reflection_camera};
@@ -0,0 +1,588 @@
+//! Opening models
+//!
+//! `load_model` starts loading a glTF, OBJ or STL file through the asset
+//! server, which reads and parses it off the Bevy thread so the stream
+//! keeps running. `track_model_load` reports progress while the file and
+//! the buffers and textures it references load, then swaps the model in
+//! for the previous one and hides the demo scene. The previous model is
+//! removed rather than despawned, so undoing the load brings it back.
+//!
+//! Large models would still stall the frames they are uploaded and first
+//! drawn in, so uploads are capped at `UPLOAD_BYTES_PER_FRAME` a frame and
+//! `reveal_model` shows glTF meshes a chunk per frame. Until all of them
+//! show, a translucent box spans the model's bounds, read from the file's
+//! header on an IO thread.
+
+use bevy::asset::io::file::FileAssetReader;
+use bevy::asset::{AssetPath, LoadState, RecursiveDependencyLoadState};
+use bevy::ecs::entity_disabling::Disabled;
+use bevy::prelude::*;
+use bevy::scene::SceneInstanceReady;
+use bevy::tasks::{block_on, futures_lite::future, IoTaskPool};
+use crossbeam_channel::Sender;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::bevy::components::{LoadedModel, ModelProxy, RotatingCube, Terrain};
+use crate::bevy::resources::{ApprovedModelDirs, EditChange, ModelAsset, ModelLoad, ModelReveal};
+use crate::bevy::systems::custom_shaders::descendants;
+use crate::bevy::systems::history::{apply_changes, discard, record};
+use crate::bevy::systems::prefetch::claim_prefetched;
+use crate::bevy::systems::project::place_model_objects;
+use crate::bevy::systems::scene_sync::{publish, remember_remote_edit};
+use crate::config::model::{
+    EXTENSIONS, MAX_HEADER_BYTES, POINT_CLOUD_EXTENSIONS, PROGRESS_INTERVAL, PROXY_COLOR,
+    REVEAL_VERTICES_PER_FRAME,
+};
+use crate::tauri_bridge::scene_sync::SceneOp;
+use crate::tauri_bridge::shared_state::ModelLoadProgress;
+
+/// Start loading the model at `path`, replacing any model still loading
+///
+/// Once it succeeded it is recorded as an edit and other instances are sent
+/// the load, unless `remote` names the instance and edit that sent it.
+pub fn load_model(
+    world: &mut World,
+    path: PathBuf,
+    progress: Sender<ModelLoadProgress>,
+    remote: Option<(u64, Option<u64>)>,
+) {
+    let display = path.display().to_string();
+    let asset_server = world.resource::<AssetServer>().clone();
+    let approved = world.resource::<ApprovedModelDirs>();
+    let asset = match model_asset(&asset_server, approved, &path) {
+        Ok(asset) => asset,
+        Err(error) => {
+            let _ = progress.send(ModelLoadProgress::Failed {
+                path: display,
+                error,
+            });
+            return;
+        }
+    };
+
+    // Large files take a while to parse, the proxy shows once they are
+    let bounds = matches!(asset, ModelAsset::Scene(_)).then(|| {
+        let path = path.clone();
+        IoTaskPool::get().spawn(async move { header_bounds(&path) })
+    });
+
+    // Opening a loaded file again picks up its changes, e.g. a new export
+    // in a watched folder, unless it was prefetched to open quickly
+    let prefetched = claim_prefetched(world, asset.id());
+    let reloading = asset_server.is_loaded(asset.id()) && !prefetched;
+    if reloading {
+        asset_server.reload(path.clone());
+    }
+
+    if let Some(previous) = world.remove_resource::<ModelLoad>() {
+        abandon(
+            world,
+            previous.proxy,
+            previous.reveal.as_ref(),
+            previous.replaced,
+        );
+        let _ = previous.progress.send(ModelLoadProgress::Failed {
+            path: previous.path,
+            error: "Another model was opened".to_string(),
+        });
+    }
+    println!("[Bevy] Loading model {}", display);
+    let _ = progress.send(ModelLoadProgress::Loading {
+        path: display.clone(),
+        elapsed_ms: 0.0,
+    });
+    let now = Instant::now();
+    world.insert_resource(ModelLoad {
+        file: path,
+        remote,
+        replaced: Vec::new(),
+        path: display,
+        asset,
+        reloading,
+        started: now,
+        reported: now,
+        progress,
+        bounds,
+        proxy: None,
+        reveal: None,
+    });
+}
+
+/// Let models be opened from `dir` and the folders in it, for a folder a
+/// model was picked in or that is watched
+pub fn approve_model_dir(world: &mut World, dir: PathBuf) {
+    let dir = dir.canonicalize().unwrap_or(dir);
+    let mut approved = world.resource_mut::<ApprovedModelDirs>();
+    if !approved.0.contains(&dir) {
+        println!("[Bevy] Models may be opened from {}", dir.display());
+        approved.0.push(dir);
+    }
+}
+
+/// Start loading the model at `path` through the asset server, as what
+/// its extension says it is
+///
+/// Outside the assets folder, only files in an approved folder are loaded.
+pub(crate) fn model_asset(
+    asset_server: &AssetServer,
+    approved: &ApprovedModelDirs,
+    path: &Path,
+) -> Result<ModelAsset, String> {
+    let unapproved = AssetPath::from(path.to_path_buf()).is_unapproved();
+    let in_approved_dir = || {
+        path.canonicalize()
+            .is_ok_and(|path| approved.0.iter().any(|dir| path.starts_with(dir)))
+    };
+    if unapproved && !in_approved_dir() {
+        return Err(format!(
+            "{} is outside the assets folder and the folders models were picked in or watched",
+            path.display()
+        ));
+    }
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("gltf" | "glb") => Ok(ModelAsset::Scene(
+            asset_server.load_override(GltfAssetLabel::Scene(0).from_asset(path.to_path_buf())),
+        )),
+        Some("obj" | "stl" | "xyz" | "pts") => Ok(ModelAsset::Mesh(
+            asset_server.load_override(path.to_path_buf()),
+        )),
+        _ => Err(format!(
+            "Unsupported model format, expected {}",
+            EXTENSIONS.join(", ")
+        )),
+    }
+}
+
+/// Show a box spanning the bounds of the glTF model being loaded once they
+/// are read from its header, until the model shows
+pub fn place_model_proxy(
+    mut commands: Commands,
+    load: Option<ResMut<ModelLoad>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let Some(mut load) = load else {
+        return;
+    };
+    let Some(task) = load.bounds.as_mut() else {
+        return;
+    };
+    let Some(bounds) = block_on(future::poll_once(task)) else {
+        return;
+    };
+    load.bounds = None;
+    let Some((min, max)) = bounds else {
+        return;
+    };
+    let [red, green, blue, alpha] = PROXY_COLOR;
+    let proxy = commands
+        .spawn((
+            Mesh3d(meshes.add(Cuboid::from_corners(min, max))),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::srgba(red, green, blue, alpha),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            })),
+            Transform::from_translation((min + max) / 2.0),
+            ModelProxy,
+            Name::new("Model proxy"),
+        ))
+        .id();
+    load.proxy = Some(proxy);
+}
+
+/// Notice when the file of a model opened again was read, which replaces
+/// its asset
+pub fn watch_model_reload(
+    load: Option<ResMut<ModelLoad>>,
+    mut scenes: MessageReader<AssetEvent<Scene>>,
+    mut meshes: MessageReader<AssetEvent<Mesh>>,
+) {
+    let modified = match load.as_ref().map(|load| &load.asset) {
+        Some(ModelAsset::Scene(scene)) => scenes.read().any(|event| event.is_modified(scene)),
+        Some(ModelAsset::Mesh(mesh)) => meshes.read().any(|event| event.is_modified(mesh)),
+        None => false,
+    };
+    // Drained every frame, so a reload only sees events from after it started
+    scenes.clear();
+    meshes.clear();
+    if let Some(mut load) = load.filter(|_| modified) {
+        load.reloading = false;
+    }
+}
+
+/// Whether the file at `path` is a point cloud
+fn is_point_cloud(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            POINT_CLOUD_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+        })
+}
+
+/// Report on the model being loaded and spawn it once it is ready
+pub fn track_model_load(
+    mut commands: Commands,
+    load: Option<ResMut<ModelLoad>>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    models: Query<Entity, Or<(With<LoadedModel>, With<Terrain>)>>,
+    children: Query<&Children>,
+    mut demo_cubes: Query<(Entity, &mut Visibility), With<RotatingCube>>,
+) {
+    let Some(mut load) = load else {
+        return;
+    };
+    if load.reveal.is_some() {
+        return;
+    }
+    let elapsed_ms = load.started.elapsed().as_secs_f64() * 1000.0;
+
+    let state = asset_server.get_load_states(load.asset.id());
+    let error = match &state {
+        Some((LoadState::Failed(e), _, _)) => Some(e.to_string()),
+        Some((_, _, RecursiveDependencyLoadState::Failed(e))) => Some(e.to_string()),
+        _ => None,
+    };
+    if let Some(error) = error {
+        eprintln!("[Bevy] Failed to load model {}: {}", load.path, error);
+        let _ = load.progress.send(ModelLoadProgress::Failed {
+            path: load.path.clone(),
+            error,
+        });
+        if let Some(proxy) = load.proxy {
+            commands.entity(proxy).despawn();
+        }
+        commands.remove_resource::<ModelLoad>();
+        return;
+    }
+    // A reload leaves the old contents loaded until they are replaced
+    let loaded = matches!(state, Some((_, _, RecursiveDependencyLoadState::Loaded)));
+    if load.reloading || !loaded {
+        if load.reported.elapsed() >= PROGRESS_INTERVAL {
+            load.reported = Instant::now();
+            let _ = load.progress.send(ModelLoadProgress::Loading {
+                path: load.path.clone(),
+                elapsed_ms,
+            });
+        }
+        return;
+    }
+
+    // Removed rather than despawned, for undo to bring back
+    let mut removed = Vec::new();
+    for model in &models {
+        removed.push(model);
+        removed.extend(children.iter_descendants(model));
+    }
+    for &entity in &removed {
+        commands.entity(entity).insert(Disabled);
+    }
+    load.replaced.push(EditChange::Removed(removed));
+    for (entity, mut visibility) in &mut demo_cubes {
+        if *visibility != Visibility::Hidden {
+            load.replaced.push(EditChange::Visibility {
+                entity,
+                before: *visibility,
+                after: Visibility::Hidden,
+            });
+            *visibility = Visibility::Hidden;
+        }
+    }
+    let name = Path::new(&load.path).file_stem().map_or_else(
+        || "model".to_string(),
+        |stem| stem.to_string_lossy().into_owned(),
+    );
+    // glTF scenes are shown in turn, meshes at once
+    let (root, reveal) = match &load.asset {
+        ModelAsset::Scene(scene) => {
+            // Hidden until `reveal_model` hid its meshes to show them in turn
+            let root = commands
+                .spawn((
+                    SceneRoot(scene.clone()),
+                    Transform::default(),
+                    Visibility::Hidden,
+                    LoadedModel(load.file.clone()),
+                    Name::new(name),
+                ))
+                .id();
+            (root, true)
+        }
+        ModelAsset::Mesh(mesh) => {
+            // Points have no normals to light them by, their colors show as is
+            let material = if is_point_cloud(&load.path) {
+                StandardMaterial {
+                    unlit: true,
+                    ..default()
+                }
+            } else {
+                StandardMaterial {
+                    base_color: Color::srgb(0.75, 0.75, 0.78),
+                    perceptual_roughness: 0.6,
+                    ..default()
+                }
+            };
+            let entity = commands
+                .spawn((
+                    Mesh3d(mesh.clone()),
+                    MeshMaterial3d(materials.add(material)),
+                    Transform::default(),
+                    LoadedModel(load.file.clone()),
+                    Name::new(name),
+                ))
+                .id();
+            (entity, false)
+        }
+    };
+    if reveal {
+        load.reveal = Some(ModelReveal {
+            root,
+            hidden: VecDeque::new(),
+            meshes: 0,
+            ready: false,
+            started: false,
+        });
+    } else {
+        finish(&mut commands, &mut load, root);
+    }
+}
+
+/// Show the meshes of the model spawned by `track_model_load` a chunk of
+/// `REVEAL_VERTICES_PER_FRAME` vertices per frame, then report it loaded
+pub fn reveal_model(
+    mut commands: Commands,
+    load: Option<ResMut<ModelLoad>>,
+    children: Query<&Children>,
+    mut meshes: Query<(&mut Visibility, &Mesh3d)>,
+    mesh_assets: Res<Assets<Mesh>>,
+) {
+    let Some(mut load) = load else {
+        return;
+    };
+    let Some(reveal) = load.reveal.as_mut() else {
+        return;
+    };
+    if !reveal.started {
+        if !reveal.ready {
+            return;
+        }
+        for entity in children.iter_descendants(reveal.root) {
+            if let Ok((mut visibility, _)) = meshes.get_mut(entity) {
+                reveal.hidden.push_back((entity, *visibility));
+                *visibility = Visibility::Hidden;
+            }
+        }
+        reveal.meshes = reveal.hidden.len();
+        reveal.started = true;
+        commands.entity(reveal.root).insert(Visibility::Inherited);
+    }
+
+    // At least one mesh per frame, however large
+    let mut vertices = 0;
+    while vertices < REVEAL_VERTICES_PER_FRAME {
+        let Some((entity, spawned)) = reveal.hidden.pop_front() else {
+            break;
+        };
+        if let Ok((mut visibility, mesh)) = meshes.get_mut(entity) {
+            *visibility = spawned;
+            vertices += mesh_assets
+                .get(mesh.id())
+                .map_or(0, |mesh| mesh.count_vertices());
+        }
+    }
+    let (shown, total) = (reveal.meshes - reveal.hidden.len(), reveal.meshes);
+    if !reveal.hidden.is_empty() {
+        let _ = load.progress.send(ModelLoadProgress::Showing {
+            path: load.path.clone(),
+            shown,
+            meshes: total,
+            elapsed_ms: load.started.elapsed().as_secs_f64() * 1000.0,
+        });
+        return;
+    }
+    if let Some(proxy) = load.proxy {
+        commands.entity(proxy).despawn();
+    }
+    let root = reveal.root;
+    finish(&mut commands, &mut load, root);
+}
+
+/// Let `reveal_model` show the model once its whole scene is spawned
+pub fn mark_model_scene_ready(event: On<SceneInstanceReady>, load: Option<ResMut<ModelLoad>>) {
+    let Some(mut load) = load else {
+        return;
+    };
+    if let Some(reveal) = load
+        .reveal
+        .as_mut()
+        .filter(|reveal| reveal.root == event.event_target())
+    {
+        reveal.ready = true;
+    }
+}
+
+/// Report the model spawned under `root` loaded and stop tracking it
+///
+/// The load is recorded as an edit with what it replaced, or kept for the
+/// instance that sent it.
+fn finish(commands: &mut Commands, load: &mut ModelLoad, root: Entity) {
+    let elapsed_ms = load.started.elapsed().as_secs_f64() * 1000.0;
+    println!("[Bevy] Loaded model {} in {:.0}ms", load.path, elapsed_ms);
+    let _ = load.progress.send(ModelLoadProgress::Loaded {
+        path: load.path.clone(),
+        elapsed_ms,
+    });
+    let mut changes = std::mem::take(&mut load.replaced);
+    let (path, remote) = (load.file.clone(), load.remote);
+    commands.queue(move |world: &mut World| {
+        changes.push(EditChange::Spawned(descendants(world, root)));
+        changes.extend(place_model_objects(world, &path));
+        match remote {
+            None => {
+                record(world, "load_model", changes);
+                publish(world, SceneOp::LoadModel { path });
+            }
+            Some((instance, Some(id))) => remember_remote_edit(world, instance, id, changes),
+            // Can't be undone from there
+            Some((_, None)) => discard(world, changes, false),
+        }
+    });
+    commands.remove_resource::<ModelLoad>();
+}
+
+/// Clean up after a load replaced by another: despawn what it spawned and
+/// bring back what it `replaced`
+fn abandon(
+    world: &mut World,
+    proxy: Option<Entity>,
+    reveal: Option<&ModelReveal>,
+    replaced: Vec<EditChange>,
+) {
+    let spawned = proxy.into_iter().chain(reveal.map(|reveal| reveal.root));
+    for entity in spawned {
+        if let Ok(entity) = world.get_entity_mut(entity) {
+            entity.despawn();
+        }
+    }
+    apply_changes(world, &replaced, true);
+}
+
+/// Bounds of the first scene of the glTF file at `path` from its JSON,
+/// without reading its buffers, `None` if it has no position bounds
+fn header_bounds(path: &Path) -> Option<(Vec3, Vec3)> {
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        FileAssetReader::get_base_path().join("assets").join(path)
+    };
+    let mut file = File::open(&path).ok()?;
+    let mut magic = [0u8; 4];
+    file.read_exact(&mut magic).ok()?;
+    let json = if &magic == b"glTF" {
+        // Version and length, then the JSON chunk's length and type
+        let mut header = [0u8; 16];
+        file.read_exact(&mut header).ok()?;
+        let length = u32::from_le_bytes(header[8..12].try_into().ok()?) as u64;
+        if &header[12..16] != b"JSON" || length > MAX_HEADER_BYTES {
+            return None;
+        }
+        let mut json = vec![0u8; length as usize];
+        file.read_exact(&mut json).ok()?;
+        json
+    } else {
+        if file.metadata().ok()?.len() > MAX_HEADER_BYTES {
+            return None;
+        }
+        let mut json = magic.to_vec();
+        file.read_to_end(&mut json).ok()?;
+        json
+    };
+    let document: Value = serde_json::from_slice(&json).ok()?;
+
+    let scene = document["scene"].as_u64().unwrap_or(0) as usize;
+    let mut stack: Vec<(u64, Mat4)> = document["scenes"][scene]["nodes"]
+        .as_array()?
+        .iter()
+        .filter_map(Value::as_u64)
+        .map(|node| (node, Mat4::IDENTITY))
+        .collect();
+    let node_count = document["nodes"].as_array().map_or(0, Vec::len);
+    let mut visited = 0;
+    let (mut min, mut max) = (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN));
+    while let Some((index, parent)) = stack.pop() {
+        // Malformed files may have cycles
+        visited += 1;
+        if visited > node_count {
+            break;
+        }
+        let node = &document["nodes"][index as usize];
+        let transform = parent * node_matrix(node);
+        let primitives = node["mesh"]
+            .as_u64()
+            .and_then(|mesh| document["meshes"][mesh as usize]["primitives"].as_array());
+        for primitive in primitives.into_iter().flatten() {
+            let Some(accessor) = primitive["attributes"]["POSITION"].as_u64() else {
+                continue;
+            };
+            let accessor = &document["accessors"][accessor as usize];
+            let (Some(low), Some(high)) = (vec3(&accessor["min"]), vec3(&accessor["max"])) else {
+                continue;
+            };
+            for corner in 0..8 {
+                let point = Vec3::select(
+                    BVec3::new(corner & 1 != 0, corner & 2 != 0, corner & 4 != 0),
+                    high,
+                    low,
+                );
+                let point = transform.transform_point3(point);
+                min = min.min(point);
+                max = max.max(point);
+            }
+        }
+        let children = node["children"].as_array().into_iter().flatten();
+        stack.extend(
+            children
+                .filter_map(Value::as_u64)
+                .map(|child| (child, transform)),
+        );
+    }
+    min.cmple(max).all().then_some((min, max))
+}
+
+/// Local transform of a glTF node, from its matrix or its TRS
+fn node_matrix(node: &Value) -> Mat4 {
+    if let Some(matrix) = floats::<16>(&node["matrix"]) {
+        return Mat4::from_cols_array(&matrix);
+    }
+    let translation = vec3(&node["translation"]).unwrap_or(Vec3::ZERO);
+    let rotation = floats::<4>(&node["rotation"]).map_or(Quat::IDENTITY, Quat::from_array);
+    let scale = vec3(&node["scale"]).unwrap_or(Vec3::ONE);
+    Mat4::from_scale_rotation_translation(scale, rotation, translation)
+}
+
+fn vec3(value: &Value) -> Option<Vec3> {
+    floats::<3>(value).map(Vec3::from_array)
+}
+
+fn floats<const N: usize>(value: &Value) -> Option<[f32; N]> {
+    let values = value.as_array()?;
+    if values.len() != N {
+        return None;
+    }
+    let mut floats = [0.0; N];
+    for (float, value) in floats.iter_mut().zip(values) {
+        *float = value.as_f64()? as f32;
+    }
+    Some(floats)
+}
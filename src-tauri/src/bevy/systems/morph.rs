@@ -0,0 +1,152 @@
+//! Morph target weights
+//!
+//! glTF meshes with morph targets (blendshapes) get a `MorphWeights`
+//! component on their mesh node, which Bevy copies to the primitives below
+//! it. `set_morph_weight` doesn't write the weight directly but sets where
+//! it should end up, and [`ease_morph_weights`] moves it there over a few
+//! frames, so slider input comes out as smooth motion.
+
+use bevy::prelude::*;
+
+use crate::bevy::components::MorphWeightTargets;
+use crate::bevy::resources::EditChange;
+use crate::bevy::systems::custom_shaders::descendants;
+use crate::bevy::systems::history::record;
+use crate::config::morph::{SMOOTHING_RATE, SNAP_DISTANCE};
+use crate::tauri_bridge::shared_state::{MorphTarget, MorphTargetInfo};
+
+/// Ease morph `target` of entity `id` and its descendants towards `weight`
+///
+/// Returns how many meshes have the target.
+pub fn set_morph_weight(
+    world: &mut World,
+    id: u64,
+    target: &MorphTarget,
+    weight: f32,
+) -> Result<usize, String> {
+    let root = Entity::try_from_bits(id).ok_or_else(|| format!("Invalid entity id {}", id))?;
+    if world.get_entity(root).is_err() {
+        return Err(format!("No entity with id {}", id));
+    }
+
+    let mut changes = Vec::new();
+    let mut has_morphs = false;
+    for entity in descendants(world, root) {
+        let Some(weights) = world.get::<MorphWeights>(entity) else {
+            continue;
+        };
+        has_morphs = true;
+        let count = weights.weights().len();
+        let index = match target {
+            MorphTarget::Index(index) => Some(*index).filter(|index| *index < count),
+            MorphTarget::Name(name) => target_names(world, weights)
+                .and_then(|names| names.iter().position(|target| target == name)),
+        };
+        let Some(index) = index else {
+            continue;
+        };
+        let current = weights.weights()[index];
+
+        let mut entity = world.entity_mut(entity);
+        // Where it was easing to, if it hadn't got there yet
+        let before = entity
+            .get::<MorphWeightTargets>()
+            .and_then(|targets| targets.0[index])
+            .unwrap_or(current);
+        ease_weight(&mut entity, index, weight);
+        changes.push(EditChange::MorphWeight {
+            entity: entity.id(),
+            index,
+            before,
+            after: weight,
+        });
+    }
+    if !has_morphs {
+        return Err(format!("Entity {} has no morph targets", id));
+    }
+    if changes.is_empty() {
+        let target = match target {
+            MorphTarget::Index(index) => index.to_string(),
+            MorphTarget::Name(name) => name.clone(),
+        };
+        return Err(format!("Entity {} has no morph target {}", id, target));
+    }
+    let changed = changes.len();
+    record(world, "set_morph_weight", changes);
+    Ok(changed)
+}
+
+/// Ease morph target `index` of mesh `entity` towards `weight`
+pub(crate) fn ease_weight(entity: &mut EntityWorldMut, index: usize, weight: f32) {
+    if let Some(mut targets) = entity.get_mut::<MorphWeightTargets>() {
+        targets.0[index] = Some(weight);
+        return;
+    }
+    let Some(count) = entity
+        .get::<MorphWeights>()
+        .map(|weights| weights.weights().len())
+    else {
+        return;
+    };
+    let mut targets = vec![None; count];
+    targets[index] = Some(weight);
+    entity.insert(MorphWeightTargets(targets));
+}
+
+/// Morph targets of entity `id` and its descendants
+pub fn list_morph_targets(world: &mut World, id: u64) -> Result<Vec<MorphTargetInfo>, String> {
+    let root = Entity::try_from_bits(id).ok_or_else(|| format!("Invalid entity id {}", id))?;
+    if world.get_entity(root).is_err() {
+        return Err(format!("No entity with id {}", id));
+    }
+
+    let mut targets = Vec::new();
+    for entity in descendants(world, root) {
+        let Some(weights) = world.get::<MorphWeights>(entity) else {
+            continue;
+        };
+        let names = target_names(world, weights);
+        targets.extend(weights.weights().iter().enumerate().map(|(index, weight)| {
+            MorphTargetInfo {
+                entity: entity.to_bits(),
+                index,
+                name: names.and_then(|names| names.get(index).cloned()),
+                weight: *weight,
+            }
+        }));
+    }
+    Ok(targets)
+}
+
+/// Names the model gives the targets of `weights`, from its first mesh
+fn target_names<'a>(world: &'a World, weights: &MorphWeights) -> Option<&'a [String]> {
+    let mesh = weights.first_mesh()?;
+    world
+        .resource::<Assets<Mesh>>()
+        .get(mesh)?
+        .morph_target_names()
+}
+
+/// Move morph weights towards the values set with `set_morph_weight`
+pub fn ease_morph_weights(
+    time: Res<Time>,
+    mut morphs: Query<(Entity, &mut MorphWeights, &mut MorphWeightTargets)>,
+    mut commands: Commands,
+) {
+    let step = 1.0 - (-SMOOTHING_RATE * time.delta_secs()).exp();
+    for (entity, mut weights, mut targets) in &mut morphs {
+        for (weight, target) in weights.weights_mut().iter_mut().zip(&mut targets.0) {
+            let Some(value) = *target else {
+                continue;
+            };
+            *weight += (value - *weight) * step;
+            if (value - *weight).abs() < SNAP_DISTANCE {
+                *weight = value;
+                *target = None;
+            }
+        }
+        if targets.0.iter().all(Option::is_none) {
+            commands.entity(entity).remove::<MorphWeightTargets>();
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Particle emitter
+//!
+//! A fountain of small glowing spheres, to show that moving effects come
+//! through readback and streaming intact. Every particle is its own entity,
+//! simulated on the CPU in [`update_particles`]: they fall under the
+//! emitter's gravity and shrink away at the end of their life. Sharing one
+//! mesh and one material lets Bevy batch their draws, but each still costs
+//! a transform update and extraction every frame, hence
+//! `config::particles::MAX_PARTICLES`.
+
+use bevy::light::NotShadowCaster;
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+use crate::bevy::components::Particle;
+use crate::bevy::resources::ParticleEmitter;
+use crate::config::particles::{
+    DEFAULT_COLOR, DEFAULT_GRAVITY, DEFAULT_POSITION, DEFAULT_RATE, LIFETIME, MAX_PARTICLES, SIZE,
+    SPEED, SPREAD,
+};
+use crate::tauri_bridge::shared_state::ParticleEmitterUpdate;
+
+/// Start the emitter with the defaults for what `update` leaves out, or
+/// change the running emitter's settings
+pub fn set_particle_emitter(world: &mut World, update: ParticleEmitterUpdate) {
+    if !world.contains_resource::<ParticleEmitter>() {
+        let mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Sphere::new(SIZE / 2.0).mesh().uv(8, 6));
+        let material = world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(particle_material(DEFAULT_COLOR));
+        world.insert_resource(ParticleEmitter {
+            rate: DEFAULT_RATE,
+            gravity: Vec3::from_array(DEFAULT_GRAVITY),
+            position: Vec3::from_array(DEFAULT_POSITION),
+            mesh,
+            material,
+            pending: 0.0,
+            emitted: 0,
+        });
+    }
+
+    let mut emitter = world.resource_mut::<ParticleEmitter>();
+    if let Some(rate) = update.rate {
+        emitter.rate = rate;
+    }
+    if let Some(gravity) = update.gravity {
+        emitter.gravity = Vec3::from_array(gravity);
+    }
+    if let Some(position) = update.position {
+        emitter.position = Vec3::from_array(position);
+    }
+    let (rate, material) = (emitter.rate, emitter.material.clone());
+    if let Some(color) = update.color {
+        let color = match *color.as_slice() {
+            [r, g, b] => [r, g, b, 1.0],
+            [r, g, b, a] => [r, g, b, a],
+            _ => DEFAULT_COLOR,
+        };
+        // Particles already in the air change color too
+        if let Some(asset) = world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .get_mut(&material)
+        {
+            *asset = particle_material(color);
+        }
+    }
+    println!("[Bevy] Particle emitter at {:.0} particles/s", rate);
+}
+
+/// Stop emitting and remove every particle
+pub fn stop_particle_emitter(world: &mut World) {
+    world.remove_resource::<ParticleEmitter>();
+    let particles: Vec<Entity> = world
+        .query_filtered::<Entity, With<Particle>>()
+        .iter(world)
+        .collect();
+    for entity in particles {
+        world.despawn(entity);
+    }
+    println!("[Bevy] Particle emitter stopped");
+}
+
+/// Glowing, unlit material, fading out with the alpha of `color`
+fn particle_material([r, g, b, a]: [f32; 4]) -> StandardMaterial {
+    StandardMaterial {
+        base_color: Color::srgba(r, g, b, a),
+        unlit: true,
+        alpha_mode: if a < 1.0 {
+            AlphaMode::Blend
+        } else {
+            AlphaMode::Opaque
+        },
+        ..default()
+    }
+}
+
+/// Emit the particles due this frame, up to `MAX_PARTICLES` alive
+pub fn emit_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    emitter: Option<ResMut<ParticleEmitter>>,
+    particles: Query<(), With<Particle>>,
+) {
+    let Some(mut emitter) = emitter else {
+        return;
+    };
+    emitter.pending += emitter.rate * time.delta_secs();
+    let due = emitter.pending.floor();
+    emitter.pending -= due;
+    let room = MAX_PARTICLES.saturating_sub(particles.iter().len());
+
+    for _ in 0..(due as usize).min(room) {
+        let velocity = launch_direction(emitter.emitted) * SPEED;
+        emitter.emitted += 1;
+        commands.spawn((
+            Mesh3d(emitter.mesh.clone()),
+            MeshMaterial3d(emitter.material.clone()),
+            Transform::from_translation(emitter.position),
+            NotShadowCaster,
+            Particle { velocity, age: 0.0 },
+        ));
+    }
+}
+
+/// Move particles under gravity, shrink them over the second half of
+/// their life and remove them at the end of it
+pub fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    emitter: Option<Res<ParticleEmitter>>,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform)>,
+) {
+    let gravity = emitter.map_or(Vec3::from_array(DEFAULT_GRAVITY), |e| e.gravity);
+    let dt = time.delta_secs();
+    for (entity, mut particle, mut transform) in &mut particles {
+        particle.age += dt;
+        if particle.age >= LIFETIME {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        particle.velocity += gravity * dt;
+        transform.translation += particle.velocity * dt;
+        let remaining = (LIFETIME - particle.age) / (LIFETIME / 2.0);
+        transform.scale = Vec3::splat(remaining.min(1.0));
+    }
+}
+
+/// Launch direction of particle `n`, inside a cone of `SPREAD` around up
+///
+/// A golden-angle spiral spreads consecutive particles evenly around the
+/// cone without a random number generator, so runs repeat exactly in
+/// deterministic mode.
+fn launch_direction(n: u64) -> Vec3 {
+    const GOLDEN_ANGLE: f32 = 2.399_963;
+    // Cycles through the cone's radius every 64 particles
+    let radius = ((n % 64) as f32 + 0.5) / 64.0;
+    let tilt = SPREAD * radius.sqrt();
+    let turn = (n as f32 * GOLDEN_ANGLE) % TAU;
+    Vec3::new(tilt.sin() * turn.cos(), tilt.cos(), tilt.sin() * turn.sin())
+}
@@ -0,0 +1,323 @@
+//! Entity picking under the cursor
+//!
+//! CPU picking casts a ray against the meshes as loaded, so it misses where
+//! skinning or morph targets moved their vertices. GPU picking instead
+//! renders the one pixel under the cursor with a copy of every visible mesh
+//! drawn in a color encoding its pick id by [`IdMaterial`], deformed like
+//! the original, and reads that pixel back through a 1×1 [`ImageCopier`].
+//! That takes a few frames, so `PickMethod::Auto` only falls back to it
+//! where deformed meshes could be under the cursor.
+//!
+//! Bevy compiles the copies' pipelines in the background, and until they
+//! are ready the pixel shows nothing. So where the CPU ray cast hit a mesh
+//! an empty pixel is read again for up to `PIPELINE_WAIT_FRAMES`, then the
+//! CPU hit is replied.
+
+use bevy::{
+    camera::{visibility::RenderLayers, RenderTarget, SubCameraView},
+    core_pipeline::tonemapping::{DebandDither, Tonemapping},
+    ecs::system::SystemState,
+    light::NotShadowCaster,
+    mesh::{morph::MeshMorphWeights, skinning::SkinnedMesh},
+    picking::mesh_picking::ray_cast::{MeshRayCast, MeshRayCastSettings},
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureFormat, TextureUsages},
+        storage::ShaderStorageBuffer,
+    },
+};
+use crossbeam_channel::Sender;
+
+use crate::bevy::components::{CameraController, PickProxy, PickReadback};
+use crate::bevy::plugins::id_buffer::{decode_id, encode_id, IdMaterial, MAX_PICK_ID};
+use crate::bevy::plugins::image_copy::{CopyComplete, ImageCopier};
+use crate::bevy::resources::{GpuPick, StreamRegion};
+use crate::bevy::systems::camera::stream_cursor;
+use crate::config::picking::{PIPELINE_WAIT_FRAMES, RENDER_LAYER, WARMUP_FRAMES};
+use crate::tauri_bridge::shared_state::{PickMethod, PickedEntity, PixelFormat};
+
+/// Reply with the mesh entity under point `(x, y)` of the streamed image
+///
+/// `x` and `y` run from `0` to `1` like for [`stream_cursor`]. GPU picks
+/// reply from [`finish_gpu_pick`] once the pixel is read back.
+pub fn pick_entity(
+    world: &mut World,
+    x: f32,
+    y: f32,
+    method: PickMethod,
+    reply: Sender<Result<Option<PickedEntity>, String>>,
+) {
+    let hit = match cpu_pick(world, x, y) {
+        Ok(hit) => hit,
+        Err(e) => {
+            let _ = reply.send(Err(e));
+            return;
+        }
+    };
+    if method != PickMethod::Gpu {
+        let trusted = match hit {
+            Some(entity) => !is_deformed(world, entity),
+            None => !any_deformed(world),
+        };
+        if method == PickMethod::Cpu || trusted {
+            let picked =
+                hit.map(|entity| picked(entity, world.get::<Name>(entity), PickMethod::Cpu));
+            let _ = reply.send(Ok(picked));
+            return;
+        }
+    }
+
+    if let Err(e) = start_gpu_pick(world, (x, y), hit, reply.clone()) {
+        let _ = reply.send(Err(e));
+    }
+}
+
+/// Decode the pixel read back for a GPU pick and reply, then remove the
+/// ID buffer's camera and mesh copies
+pub fn finish_gpu_pick(
+    event: On<CopyComplete>,
+    mut readbacks: Query<&mut PickReadback>,
+    pick: Option<Res<GpuPick>>,
+    names: Query<&Name>,
+    mut commands: Commands,
+) {
+    let Ok(mut readback) = readbacks.get_mut(event.entity) else {
+        return;
+    };
+    if readback.warmup_frames > 0 {
+        readback.warmup_frames -= 1;
+        return;
+    }
+    let Some(pick) = pick else {
+        commands.entity(event.entity).despawn();
+        return;
+    };
+
+    // Rows are padded, the pixel is the first four bytes
+    let result = match event.data.get(..4) {
+        Some(&[r, g, b, a]) => Ok(decode_id([r, g, b, a])),
+        _ => Err("The ID buffer was read back empty".to_string()),
+    };
+    let result = match result {
+        // Nothing drawn where the CPU hit a mesh, the pipelines may not be
+        // ready yet
+        Ok(0) if pick.cpu_hit.is_some() && readback.wait_frames > 0 => {
+            readback.wait_frames -= 1;
+            return;
+        }
+        Ok(0) => Ok(pick
+            .cpu_hit
+            .map(|entity| picked(entity, names.get(entity).ok(), PickMethod::Cpu))),
+        Ok(id) => Ok(pick
+            .meshes
+            .get(id as usize - 1)
+            .map(|&entity| picked(entity, names.get(entity).ok(), PickMethod::Gpu))),
+        Err(e) => Err(e),
+    };
+    commands.entity(event.entity).despawn();
+    let _ = pick.reply.send(result);
+
+    commands.entity(pick.camera).despawn();
+    // Copies of meshes despawned since went with them
+    for &proxy in &pick.proxies {
+        if let Ok(mut proxy) = commands.get_entity(proxy) {
+            proxy.despawn();
+        }
+    }
+    commands.remove_resource::<GpuPick>();
+}
+
+/// First mesh hit by the ray through the cursor
+fn cpu_pick(world: &mut World, x: f32, y: f32) -> Result<Option<Entity>, String> {
+    let region = world.resource::<StreamRegion>().0;
+    let mut state = SystemState::<(
+        Query<(&Camera, &GlobalTransform), With<CameraController>>,
+        Query<(), With<PickProxy>>,
+        MeshRayCast,
+    )>::new(world);
+    let (cameras, proxies, mut ray_cast) = state.get_mut(world);
+
+    let (camera, camera_transform) = cameras.single().map_err(|e| e.to_string())?;
+    let cursor = stream_cursor(camera, region, x, y)?;
+    let ray = camera
+        .viewport_to_world(camera_transform, cursor)
+        .map_err(|e| e.to_string())?;
+    let filter = |entity: Entity| !proxies.contains(entity);
+    let settings = MeshRayCastSettings::default().with_filter(&filter);
+    Ok(ray_cast
+        .cast_ray(ray, &settings)
+        .first()
+        .map(|(entity, _)| *entity))
+}
+
+/// Whether the vertices of `entity` are moved on the GPU, so a ray cast
+/// against its mesh can't be trusted
+fn is_deformed(world: &World, entity: Entity) -> bool {
+    world.get::<SkinnedMesh>(entity).is_some() || world.get::<MeshMorphWeights>(entity).is_some()
+}
+
+/// Whether any visible mesh is deformed, see [`is_deformed`]
+fn any_deformed(world: &mut World) -> bool {
+    world
+        .query_filtered::<&InheritedVisibility, (
+            With<Mesh3d>,
+            Or<(With<SkinnedMesh>, With<MeshMorphWeights>)>,
+        )>()
+        .iter(world)
+        .any(|visibility| visibility.get())
+}
+
+/// Spawn a camera drawing the pixel under point `(x, y)` into a 1×1 ID
+/// buffer, the mesh copies it draws, and the copier reading it back
+///
+/// `cpu_hit` is replied if the ID buffer stays empty.
+fn start_gpu_pick(
+    world: &mut World,
+    (x, y): (f32, f32),
+    cpu_hit: Option<Entity>,
+    reply: Sender<Result<Option<PickedEntity>, String>>,
+) -> Result<(), String> {
+    if world.contains_resource::<GpuPick>() {
+        return Err("A GPU pick is already running".to_string());
+    }
+
+    let region = world.resource::<StreamRegion>().0;
+    let mut cameras = world.query_filtered::<(
+        &Camera,
+        &GlobalTransform,
+        &Projection,
+        Option<&RenderLayers>,
+    ), With<CameraController>>();
+    let (camera, camera_transform, projection, layers) =
+        cameras.single(world).map_err(|e| e.to_string())?;
+    let cursor = stream_cursor(camera, region, x, y)?;
+    let (Some(logical), Some(physical)) = (
+        camera.logical_viewport_size(),
+        camera.physical_viewport_size(),
+    ) else {
+        return Err("Camera has no viewport yet".to_string());
+    };
+    let pixel = (cursor * physical.as_vec2() / logical).floor();
+    if pixel.cmplt(Vec2::ZERO).any() || pixel.cmpge(physical.as_vec2()).any() {
+        return Err("The point is outside the image".to_string());
+    }
+    // The stream camera's view, cropped to the one pixel
+    let sub_view = SubCameraView {
+        full_size: physical,
+        offset: pixel,
+        size: UVec2::ONE,
+    };
+    let transform = camera_transform.compute_transform();
+    let projection = projection.clone();
+    let layers = layers.cloned().unwrap_or_default();
+
+    // Everything the stream camera draws
+    let meshes = world
+        .query_filtered::<(
+            Entity,
+            &Mesh3d,
+            &InheritedVisibility,
+            Option<&RenderLayers>,
+            Option<&SkinnedMesh>,
+            Option<&MeshMorphWeights>,
+        ), Without<PickProxy>>()
+        .iter(world)
+        .filter(|(_, _, visibility, mesh_layers, _, _)| {
+            visibility.get() && mesh_layers.cloned().unwrap_or_default().intersects(&layers)
+        })
+        .map(|(entity, mesh, _, _, skin, morph)| {
+            (entity, mesh.clone(), skin.cloned(), morph.cloned())
+        })
+        .collect::<Vec<_>>();
+    if meshes.len() > MAX_PICK_ID as usize {
+        return Err(format!(
+            "{} meshes are visible, GPU picking tells apart at most {}",
+            meshes.len(),
+            MAX_PICK_ID
+        ));
+    }
+
+    let mut proxies = Vec::with_capacity(meshes.len());
+    for (index, (entity, mesh, skin, morph)) in meshes.iter().enumerate() {
+        let material = world.resource_mut::<Assets<IdMaterial>>().add(IdMaterial {
+            color: encode_id(index as u32 + 1),
+        });
+        // A child, so it follows the mesh; skinned meshes follow their joints
+        let mut proxy = world.spawn((
+            PickProxy,
+            mesh.clone(),
+            MeshMaterial3d(material),
+            RenderLayers::layer(RENDER_LAYER),
+            NotShadowCaster,
+            ChildOf(*entity),
+        ));
+        if let Some(skin) = skin {
+            proxy.insert(skin.clone());
+        }
+        if let Some(morph) = morph {
+            proxy.insert(morph.clone());
+        }
+        proxies.push(proxy.id());
+    }
+
+    let format = TextureFormat::bevy_default();
+    let mut image = Image::new_target_texture(1, 1, format);
+    image.texture_descriptor.usage |= TextureUsages::COPY_SRC;
+    let image = world.resource_mut::<Assets<Image>>().add(image);
+    let camera = world
+        .spawn((
+            Camera3d::default(),
+            Camera {
+                target: RenderTarget::Image(image.clone().into()),
+                clear_color: ClearColorConfig::Custom(Color::BLACK),
+                sub_camera_view: Some(sub_view),
+                ..default()
+            },
+            projection,
+            transform,
+            // Anything changing colors would change the ids
+            Tonemapping::None,
+            DebandDither::Disabled,
+            Msaa::Off,
+            RenderLayers::layer(RENDER_LAYER),
+        ))
+        .id();
+
+    let size = Extent3d {
+        width: 1,
+        height: 1,
+        depth_or_array_layers: 1,
+    };
+    let copier = ImageCopier::new(
+        image,
+        size,
+        None,
+        format,
+        PixelFormat::Rgba8,
+        &mut world.resource_mut::<Assets<ShaderStorageBuffer>>(),
+    );
+    world.spawn((
+        copier,
+        PickReadback {
+            warmup_frames: WARMUP_FRAMES,
+            wait_frames: PIPELINE_WAIT_FRAMES,
+        },
+    ));
+
+    world.insert_resource(GpuPick {
+        camera,
+        meshes: meshes.into_iter().map(|(entity, ..)| entity).collect(),
+        proxies,
+        cpu_hit,
+        reply,
+    });
+    Ok(())
+}
+
+fn picked(entity: Entity, name: Option<&Name>, method: PickMethod) -> PickedEntity {
+    PickedEntity {
+        entity: entity.to_bits(),
+        name: name.map(|name| name.to_string()),
+        method,
+    }
+}
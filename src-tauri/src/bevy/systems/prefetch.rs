@@ -0,0 +1,195 @@
+//! Background asset prefetching
+//!
+//! `prefetch_assets` queues models, environments and textures the frontend
+//! expects to need soon, e.g. those of the next scene or preset.
+//! `run_prefetch_queue` loads them one at a time, and only while no model,
+//! environment or LUT the user asked for is loading, so prefetching never
+//! holds those up. The handles are kept, so using a file later finds it
+//! already loaded and it shows at once.
+
+use bevy::asset::{AssetPath, LoadState, RecursiveDependencyLoadState, UntypedAssetId};
+use bevy::prelude::*;
+
+use crate::bevy::resources::{
+    ApprovedModelDirs, ColorLutLoads, EnvironmentLoads, ModelLoad, PrefetchQueue, PrefetchedAsset,
+};
+use crate::bevy::systems::model_loading::model_asset;
+use crate::tauri_bridge::shared_state::{
+    AssetLoadError, PrefetchAsset, PrefetchKind, PrefetchStatus,
+};
+
+/// Queue `assets` behind those already queued, skipping files queued or
+/// prefetched before
+pub fn prefetch_assets(world: &mut World, assets: Vec<PrefetchAsset>) {
+    let mut queue = world.resource_mut::<PrefetchQueue>();
+    for asset in assets {
+        let known = queue
+            .queued
+            .iter()
+            .map(|queued| (&queued.path, queued.kind))
+            .chain(
+                queue
+                    .loading
+                    .iter()
+                    .chain(&queue.done)
+                    .map(|started| (&started.path, started.kind)),
+            )
+            .any(|(path, kind)| *path == asset.path && kind == asset.kind);
+        if !known {
+            queue.queued.push_back(asset);
+        }
+    }
+    println!("[Bevy] {} assets queued for prefetch", queue.queued.len());
+}
+
+/// Files queued, loading, loaded and failed
+pub fn prefetch_status(world: &World) -> PrefetchStatus {
+    let queue = world.resource::<PrefetchQueue>();
+    PrefetchStatus {
+        queued: queue
+            .queued
+            .iter()
+            .map(|asset| asset.path.display().to_string())
+            .collect(),
+        loading: queue
+            .loading
+            .as_ref()
+            .map(|asset| asset.path.display().to_string()),
+        ready: queue
+            .done
+            .iter()
+            .filter(|asset| asset.error.is_none())
+            .map(|asset| asset.path.display().to_string())
+            .collect(),
+        failed: queue
+            .done
+            .iter()
+            .filter_map(|asset| {
+                Some(AssetLoadError {
+                    path: asset.path.display().to_string(),
+                    error: asset.error.clone()?,
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Empty the queue and drop the prefetched handles, freeing the files no
+/// scene uses
+pub fn clear_prefetched_assets(world: &mut World) {
+    let queue = std::mem::take(&mut *world.resource_mut::<PrefetchQueue>());
+    println!(
+        "[Bevy] Prefetch queue cleared, {} assets dropped",
+        queue.done.len() + usize::from(queue.loading.is_some())
+    );
+}
+
+/// Whether the model `id` was prefetched and not opened since, marking it
+/// opened so `load_model` doesn't read the file again
+pub(crate) fn claim_prefetched(world: &mut World, id: UntypedAssetId) -> bool {
+    let mut queue = world.resource_mut::<PrefetchQueue>();
+    let queue = &mut *queue;
+    let Some(asset) = queue
+        .loading
+        .iter_mut()
+        .chain(&mut queue.done)
+        .find(|asset| !asset.claimed && asset.handles.iter().any(|handle| handle.id() == id))
+    else {
+        return false;
+    };
+    asset.claimed = true;
+    true
+}
+
+/// Finish the prefetch in flight, then start the next one if nothing else
+/// is loading
+pub fn run_prefetch_queue(
+    mut queue: ResMut<PrefetchQueue>,
+    asset_server: Res<AssetServer>,
+    approved: Res<ApprovedModelDirs>,
+    model: Option<Res<ModelLoad>>,
+    environments: Res<EnvironmentLoads>,
+    luts: Res<ColorLutLoads>,
+) {
+    if let Some(mut loading) = queue.loading.take() {
+        match load_result(&asset_server, &loading) {
+            None => {
+                queue.loading = Some(loading);
+                return;
+            }
+            Some(Ok(())) => println!("[Bevy] Prefetched {}", loading.path.display()),
+            Some(Err(e)) => {
+                eprintln!(
+                    "[Bevy] Failed to prefetch {}: {}",
+                    loading.path.display(),
+                    e
+                );
+                loading.error = Some(e);
+            }
+        }
+        queue.done.push(loading);
+    }
+
+    // Whatever the user is waiting for gets the asset server to itself
+    if model.is_some() || !environments.0.is_empty() || !luts.0.is_empty() {
+        return;
+    }
+    if let Some(next) = queue.queued.pop_front() {
+        queue.loading = Some(start(&asset_server, &approved, next));
+    }
+}
+
+/// Load every handle a later use of `asset` will ask for
+fn start(
+    asset_server: &AssetServer,
+    approved: &ApprovedModelDirs,
+    asset: PrefetchAsset,
+) -> PrefetchedAsset {
+    let path = AssetPath::from(asset.path.clone());
+    let handles = match asset.kind {
+        PrefetchKind::Model => {
+            model_asset(asset_server, approved, &asset.path).map(|model| vec![model.untyped()])
+        }
+        // Its filtered maps besides the image itself, as `set_environment_map` loads them
+        PrefetchKind::Environment => Ok(vec![
+            asset_server.load_override::<Image>(path.clone()).untyped(),
+            asset_server
+                .load_override::<Image>(path.clone().with_label("diffuse"))
+                .untyped(),
+            asset_server
+                .load_override::<Image>(path.with_label("specular"))
+                .untyped(),
+        ]),
+        PrefetchKind::Texture => Ok(vec![asset_server.load_override::<Image>(path).untyped()]),
+    };
+    println!("[Bevy] Prefetching {}", asset.path.display());
+    let (handles, error) = match handles {
+        Ok(handles) => (handles, None),
+        Err(e) => (Vec::new(), Some(e)),
+    };
+    PrefetchedAsset {
+        path: asset.path,
+        kind: asset.kind,
+        handles,
+        error,
+        claimed: false,
+    }
+}
+
+/// `None` while `asset` is loading, then whether it loaded with everything
+/// it references
+fn load_result(asset_server: &AssetServer, asset: &PrefetchedAsset) -> Option<Result<(), String>> {
+    if let Some(error) = &asset.error {
+        return Some(Err(error.clone()));
+    }
+    for handle in &asset.handles {
+        match asset_server.get_load_states(handle.id()) {
+            Some(
+                (LoadState::Failed(e), _, _) | (_, _, RecursiveDependencyLoadState::Failed(e)),
+            ) => return Some(Err(e.to_string())),
+            Some((_, _, RecursiveDependencyLoadState::Loaded)) | None => {}
+            Some(_) => return None,
+        }
+    }
+    Some(Ok(()))
+}
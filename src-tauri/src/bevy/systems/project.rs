@@ -0,0 +1,313 @@
+//! Project snapshots
+//!
+//! A project is what a viewer session needs to come back to the same
+//! picture: where the named objects of the scene are, the model opened and
+//! other files loaded through the asset server, the camera, its presets,
+//! and the stream and camera settings. Projects are written and read by
+//! `save_project` and `open_project`; this module takes them from and
+//! applies them to the world.
+//!
+//! Names repeat, so objects are matched by name and by which of the
+//! objects with that name they are in hierarchy order. Objects of the
+//! model are counted within it, since they only exist once it is loaded.
+
+use bevy::camera::Exposure;
+use bevy::post_process::dof::DepthOfField;
+use bevy::prelude::*;
+use crossbeam_channel::Sender;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::bevy::components::{CameraController, DebugOverlayText, LoadedModel, QuadViewCamera};
+use crate::bevy::resources::{
+    ActiveDebugView, ActiveFog, ActiveLightingPreset, CameraPresets, EditChange, FixedTimestep,
+    OrbitCameraState, ProjectAssets, ProjectModelObjects, StreamPixelFormat, StreamResolution,
+};
+use crate::bevy::systems::animation::set_fixed_timestep;
+use crate::bevy::systems::camera::{set_camera_view, set_projection};
+use crate::bevy::systems::debug_overlay::set_debug_overlay;
+use crate::bevy::systems::debug_view::set_debug_view;
+use crate::bevy::systems::depth_of_field::set_depth_of_field;
+use crate::bevy::systems::exposure::set_exposure;
+use crate::bevy::systems::fog::set_fog;
+use crate::bevy::systems::history::record;
+use crate::bevy::systems::lighting::set_lighting_preset;
+use crate::bevy::systems::model_loading::{approve_model_dir, load_model};
+use crate::bevy::systems::quad_view::set_quad_view;
+use crate::config::project::VERSION;
+use crate::tauri_bridge::shared_state::{
+    DebugView, DepthOfFieldSettings, ModelLoadProgress, Project, ProjectRenderSettings,
+    ProjectionKind, SceneObject,
+};
+
+/// The current scene, camera and settings as a project
+pub fn project(world: &mut World) -> Result<Project, String> {
+    let camera = world
+        .query_filtered::<Entity, With<CameraController>>()
+        .single(world)
+        .map_err(|e| e.to_string())?;
+    let camera = world.entity(camera);
+    let projection = match camera.get::<Projection>() {
+        Some(Projection::Orthographic(_)) => ProjectionKind::Orthographic,
+        _ => ProjectionKind::Perspective,
+    };
+    let exposure = camera.get::<Exposure>().copied().unwrap_or_default().ev100;
+    let depth_of_field = camera
+        .get::<DepthOfField>()
+        .map(|dof| DepthOfFieldSettings {
+            focal_distance: dof.focal_distance,
+            aperture: dof.aperture_f_stops,
+        });
+
+    let scene: Vec<SceneObject> = named_objects(world)
+        .into_iter()
+        .filter_map(|(key, entity)| {
+            let transform = world.get::<Transform>(entity)?;
+            Some(SceneObject {
+                name: key.name,
+                index: key.index,
+                in_model: key.in_model,
+                translation: transform.translation.to_array(),
+                rotation: transform.rotation.to_array(),
+                scale: transform.scale.to_array(),
+            })
+        })
+        .collect();
+    let model = world
+        .query::<&LoadedModel>()
+        .iter(world)
+        .next()
+        .map(|model| model.0.clone());
+
+    let stream = *world.resource::<StreamResolution>();
+    let render = ProjectRenderSettings {
+        stream_width: stream.width,
+        stream_height: stream.height,
+        pixel_format: world.resource::<StreamPixelFormat>().0,
+        projection,
+        exposure_ev100: exposure,
+        depth_of_field,
+        fog: world.resource::<ActiveFog>().0,
+        debug_view: world
+            .get_resource::<ActiveDebugView>()
+            .map_or(DebugView::Off, |active| active.view),
+        debug_overlay: world
+            .query_filtered::<(), With<DebugOverlayText>>()
+            .iter(world)
+            .next()
+            .is_some(),
+        quad_view: world
+            .query_filtered::<(), With<QuadViewCamera>>()
+            .iter(world)
+            .next()
+            .is_some(),
+        fixed_fps: world.resource::<FixedTimestep>().0,
+        lighting: world.resource::<ActiveLightingPreset>().0,
+    };
+
+    Ok(Project {
+        version: VERSION,
+        scene,
+        model,
+        assets: asset_paths(world),
+        camera: world.resource::<OrbitCameraState>().view(),
+        camera_presets: world.resource::<CameraPresets>().0.clone(),
+        render,
+    })
+}
+
+/// Restore `project`, returning the names of its objects not found in the
+/// scene
+///
+/// A model that isn't open is loaded, reporting on `progress`, and its
+/// objects are placed once it is.
+pub fn open_project(
+    world: &mut World,
+    project: Project,
+    progress: Sender<ModelLoadProgress>,
+) -> Vec<String> {
+    let render = project.render;
+    world.insert_resource(StreamResolution {
+        width: render.stream_width,
+        height: render.stream_height,
+    });
+    world.insert_resource(StreamPixelFormat(render.pixel_format));
+    set_fixed_timestep(world, render.fixed_fps);
+    set_projection(world, render.projection);
+    set_exposure(world, Some(render.exposure_ev100));
+    set_depth_of_field(world, render.depth_of_field);
+    set_fog(world, render.fog);
+    set_debug_view(world, render.debug_view);
+    set_debug_overlay(world, render.debug_overlay);
+    set_quad_view(world, render.quad_view);
+    set_lighting_preset(world, render.lighting);
+
+    world.insert_resource(CameraPresets(project.camera_presets));
+    set_camera_view(world, project.camera, false);
+
+    let open = world
+        .query::<&LoadedModel>()
+        .iter(world)
+        .any(|model| Some(&model.0) == project.model.as_ref());
+    let (model_objects, objects): (Vec<SceneObject>, Vec<SceneObject>) = project
+        .scene
+        .into_iter()
+        .partition(|object| object.in_model && !open);
+    let (changes, missing) = place_objects(world, objects);
+    // The camera and render settings are view settings, not edits
+    record(world, "open_project", changes);
+
+    if let Some(model) = project.model.filter(|_| !open) {
+        // Opening the project is the user's choice of its model
+        if let Some(dir) = model.parent().filter(|_| model.is_absolute()) {
+            approve_model_dir(world, dir.to_path_buf());
+        }
+        world.insert_resource(ProjectModelObjects {
+            model: model.clone(),
+            objects: model_objects,
+        });
+        load_model(world, model, progress, None);
+    }
+
+    let asset_server = world.resource::<AssetServer>().clone();
+    let assets = project
+        .assets
+        .into_iter()
+        .map(|path| {
+            let handle = asset_server.load_untyped(path.as_str());
+            (path, handle)
+        })
+        .collect();
+    world.insert_resource(ProjectAssets(assets));
+
+    println!(
+        "[Bevy] Project opened, {} objects not in the scene",
+        missing.len()
+    );
+    missing
+}
+
+/// Place the objects of the open project's model once `model` is loaded,
+/// returning the changes to record with the load
+pub(crate) fn place_model_objects(world: &mut World, model: &Path) -> Vec<EditChange> {
+    let Some(pending) = world.remove_resource::<ProjectModelObjects>() else {
+        return Vec::new();
+    };
+    // Another model was opened in the meantime
+    if pending.model != model {
+        return Vec::new();
+    }
+    let (changes, missing) = place_objects(world, pending.objects);
+    if !missing.is_empty() {
+        println!(
+            "[Bevy] {} project objects not in model {}",
+            missing.len(),
+            model.display()
+        );
+    }
+    changes
+}
+
+/// How a project tells its objects apart
+#[derive(PartialEq, Eq, Hash)]
+struct ObjectKey {
+    name: String,
+    index: usize,
+    in_model: bool,
+}
+
+/// Named objects other than cameras and the overlay, in hierarchy order:
+/// roots by entity, then depth first in child order
+fn named_objects(world: &mut World) -> Vec<(ObjectKey, Entity)> {
+    let mut roots: Vec<Entity> = world
+        .query_filtered::<Entity, Without<ChildOf>>()
+        .iter(world)
+        .collect();
+    roots.sort();
+    let mut stack: Vec<(Entity, bool)> =
+        roots.into_iter().rev().map(|root| (root, false)).collect();
+    let mut counts: HashMap<(String, bool), usize> = HashMap::new();
+    let mut objects = Vec::new();
+    while let Some((entity, in_model)) = stack.pop() {
+        let entity = world.entity(entity);
+        if entity.contains::<Camera>() || entity.contains::<DebugOverlayText>() {
+            continue;
+        }
+        let in_model = in_model || entity.contains::<LoadedModel>();
+        if let Some(name) = entity.get::<Name>() {
+            let count = counts.entry((name.to_string(), in_model)).or_default();
+            let key = ObjectKey {
+                name: name.to_string(),
+                index: *count,
+                in_model,
+            };
+            *count += 1;
+            objects.push((key, entity.id()));
+        }
+        if let Some(children) = entity.get::<Children>() {
+            stack.extend(children.into_iter().rev().map(|&child| (child, in_model)));
+        }
+    }
+    objects
+}
+
+/// Move the scene's objects to where `objects` has them, returning the
+/// changes and the names of those not found
+fn place_objects(world: &mut World, objects: Vec<SceneObject>) -> (Vec<EditChange>, Vec<String>) {
+    let entities: HashMap<ObjectKey, Entity> = named_objects(world).into_iter().collect();
+    let mut changes = Vec::new();
+    let mut missing = Vec::new();
+    for object in objects {
+        let key = ObjectKey {
+            name: object.name,
+            index: object.index,
+            in_model: object.in_model,
+        };
+        let found = entities.get(&key).and_then(|&entity| {
+            let transform = world.get_mut::<Transform>(entity)?;
+            Some((entity, transform))
+        });
+        let Some((entity, mut transform)) = found else {
+            missing.push(key.name);
+            continue;
+        };
+        let after = Transform {
+            translation: Vec3::from_array(object.translation),
+            rotation: Quat::from_array(object.rotation).normalize(),
+            scale: Vec3::from_array(object.scale),
+        };
+        if *transform != after {
+            changes.push(EditChange::Transform {
+                entity,
+                before: *transform,
+                after,
+            });
+            *transform = after;
+        }
+    }
+    (changes, missing)
+}
+
+/// Paths of the meshes and images loaded from files, and of the assets of
+/// the open project
+fn asset_paths(world: &World) -> Vec<String> {
+    let asset_server = world.resource::<AssetServer>();
+    let mut paths: BTreeSet<String> = BTreeSet::new();
+    let meshes = world
+        .resource::<Assets<Mesh>>()
+        .ids()
+        .map(|id| id.untyped());
+    let images = world
+        .resource::<Assets<Image>>()
+        .ids()
+        .map(|id| id.untyped());
+    for id in meshes.chain(images) {
+        if let Some(path) = asset_server.get_path(id) {
+            paths.insert(path.to_string());
+        }
+    }
+    if let Some(project_assets) = world.get_resource::<ProjectAssets>() {
+        paths.extend(project_assets.0.iter().map(|(path, _)| path.clone()));
+    }
+    paths.into_iter().collect()
+}
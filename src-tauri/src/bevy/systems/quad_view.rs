@@ -0,0 +1,145 @@
+//! Quad view
+//!
+//! Splits the stream target into four quadrants, drawn by four cameras with
+//! their own viewports: top, front and side orthographic views next to the
+//! interactive perspective camera. All four render into the same target
+//! before the readback, so the layout streams as a single frame.
+//!
+//! ```text
+//! +-------+-------+
+//! |  top  | front |
+//! +-------+-------+
+//! | right | orbit |
+//! +-------+-------+
+//! ```
+
+use bevy::camera::Viewport;
+use bevy::core_pipeline::tonemapping::Tonemapping;
+use bevy::prelude::*;
+
+use crate::bevy::components::{CameraController, QuadViewCamera};
+use crate::bevy::resources::{OrbitCameraState, RenderSettings};
+use crate::bevy::systems::camera::{orthographic_scaling, standard_angles};
+use crate::tauri_bridge::shared_state::{OrbitPose, StandardView};
+
+/// Views of the extra cameras, in the order of their quadrants
+const QUAD_VIEWS: [StandardView; 3] = [StandardView::Top, StandardView::Front, StandardView::Right];
+
+/// Turn the quad view on or off
+///
+/// The stream camera is moved into the bottom right quadrant by
+/// [`layout_quad_view`], and back to the full target here.
+pub fn set_quad_view(world: &mut World, enabled: bool) {
+    let existing: Vec<Entity> = world
+        .query_filtered::<Entity, With<QuadViewCamera>>()
+        .iter(world)
+        .collect();
+    if enabled == !existing.is_empty() {
+        return;
+    }
+
+    if !enabled {
+        for camera in existing {
+            world.despawn(camera);
+        }
+        let mut cameras = world.query_filtered::<&mut Camera, With<CameraController>>();
+        for mut camera in cameras.iter_mut(world) {
+            camera.viewport = None;
+        }
+        println!("[Bevy] Quad view off");
+        return;
+    }
+
+    let mut cameras = world.query_filtered::<(&Camera, &Tonemapping), With<CameraController>>();
+    let Some((stream_camera, tonemapping)) = cameras
+        .iter(world)
+        .next()
+        .map(|(camera, tonemapping)| (camera.clone(), *tonemapping))
+    else {
+        return;
+    };
+    let msaa = world.resource::<RenderSettings>().msaa;
+    for (index, view) in QUAD_VIEWS.into_iter().enumerate() {
+        world.spawn((
+            Camera3d::default(),
+            Camera {
+                // Render after the stream camera, which clears the whole
+                // target, without clearing it again
+                order: stream_camera.order + 1 + index as isize,
+                clear_color: ClearColorConfig::None,
+                viewport: None,
+                ..stream_camera.clone()
+            },
+            Projection::Orthographic(OrthographicProjection::default_3d()),
+            tonemapping,
+            msaa,
+            Transform::default(),
+            QuadViewCamera(view),
+        ));
+    }
+    println!("[Bevy] Quad view on");
+}
+
+/// Keep the quad view cameras in their quadrants and on the orbit
+///
+/// Runs after the orbit camera has moved, so all four views show the same
+/// frame. The orthographic views follow the orbit center and zoom with
+/// the orbit distance.
+pub fn layout_quad_view(
+    orbit_state: Res<OrbitCameraState>,
+    mut stream_cameras: Query<&mut Camera, (With<CameraController>, Without<QuadViewCamera>)>,
+    mut quad_cameras: Query<(
+        &mut Camera,
+        &mut Transform,
+        &mut Projection,
+        &QuadViewCamera,
+    )>,
+) {
+    if quad_cameras.is_empty() {
+        return;
+    }
+    let Some(mut stream_camera) = stream_cameras.iter_mut().next() else {
+        return;
+    };
+    let Some(target_size) = stream_camera.physical_target_size() else {
+        return;
+    };
+    let half = target_size / 2;
+    set_viewport(&mut stream_camera, half, half);
+
+    for (mut camera, mut transform, mut projection, quad) in quad_cameras.iter_mut() {
+        let position = match quad.0 {
+            StandardView::Top => UVec2::ZERO,
+            StandardView::Front => UVec2::new(half.x, 0),
+            _ => UVec2::new(0, half.y),
+        };
+        set_viewport(&mut camera, position, half);
+
+        let (yaw, pitch) = standard_angles(quad.0);
+        let mut orbit = *orbit_state;
+        orbit.set_pose(OrbitPose {
+            yaw,
+            pitch,
+            distance: orbit_state.distance,
+            roll: 0.0,
+        });
+        *transform = orbit.camera_transform();
+        if let Projection::Orthographic(orthographic) = projection.as_mut() {
+            orthographic.scaling_mode = orthographic_scaling(orbit_state.distance);
+        }
+    }
+}
+
+/// Set the viewport of `camera`, leaving it unchanged if it already is
+fn set_viewport(camera: &mut Camera, position: UVec2, size: UVec2) {
+    let unchanged = camera.viewport.as_ref().is_some_and(|viewport| {
+        viewport.physical_position == position && viewport.physical_size == size
+    });
+    if !unchanged {
+        camera.viewport = Some(Viewport {
+            physical_position: position,
+            physical_size: size,
+            ..default()
+        });
+    }
+}
@@ -0,0 +1,48 @@
+//! World-space ray casts for frontend tools
+//!
+//! Unlike [`pick_surface`](super::camera::pick_surface) the ray doesn't
+//! come from the camera, so tools such as measuring or placing objects can
+//! cast from anywhere in the scene.
+
+use bevy::ecs::system::SystemState;
+use bevy::picking::mesh_picking::ray_cast::{MeshRayCast, MeshRayCastSettings, RayCastVisibility};
+use bevy::prelude::*;
+
+use crate::bevy::components::PickProxy;
+use crate::tauri_bridge::shared_state::RaycastHit;
+
+/// Every visible mesh hit by the ray from `origin` along `direction`
+/// within `max_distance`, nearest first
+///
+/// Meshes outside the camera's view are hit too, hidden ones aren't.
+pub fn raycast(
+    world: &mut World,
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+) -> Result<Vec<RaycastHit>, String> {
+    let direction = Dir3::new(direction).map_err(|e| e.to_string())?;
+    let ray = Ray3d::new(origin, direction);
+    let mut state =
+        SystemState::<(MeshRayCast, Query<&Name>, Query<(), With<PickProxy>>)>::new(world);
+    let (mut ray_cast, names, proxies) = state.get_mut(world);
+    // Copies of meshes drawn while a GPU pick runs
+    let filter = |entity: Entity| !proxies.contains(entity);
+    let settings = MeshRayCastSettings::default()
+        .with_visibility(RayCastVisibility::Visible)
+        .with_filter(&filter)
+        .never_early_exit();
+    let hits = ray_cast
+        .cast_ray(ray, &settings)
+        .iter()
+        .take_while(|(_, hit)| hit.distance <= max_distance)
+        .map(|(entity, hit)| RaycastHit {
+            entity: entity.to_bits(),
+            name: names.get(*entity).ok().map(|name| name.to_string()),
+            point: hit.point.to_array(),
+            normal: hit.normal.to_array(),
+            distance: hit.distance,
+        })
+        .collect();
+    Ok(hits)
+}
@@ -0,0 +1,355 @@
+//! Readback correctness self-test
+//!
+//! Known patterns are stored in or rendered into small textures and read
+//! back through each path of the readback pipeline: the texture itself with
+//! row padding, the pack pass into every pixel format, from a BGRA texture
+//! and from a region, and a clear color rendered into an sRGB target. Every
+//! pixel is compared, so padding, channel order and sRGB bugs that only
+//! show on some GPUs fail a check with the first wrong pixel.
+
+use bevy::{
+    camera::{visibility::RenderLayers, RenderTarget},
+    core_pipeline::tonemapping::{DebandDither, Tonemapping},
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        storage::ShaderStorageBuffer,
+    },
+};
+use crossbeam_channel::Sender;
+
+use crate::bevy::components::ReadbackCheck;
+use crate::bevy::plugins::image_copy::{CopyComplete, ImageCopier};
+use crate::bevy::plugins::pixel_pack::allow_raw_view;
+use crate::bevy::resources::{BufferPoolRes, ReadbackSelfTestRun};
+use crate::bevy::systems::frame_extraction::remove_row_padding;
+use crate::config::self_test::{EMPTY_RENDER_LAYER, PATTERN_HEIGHT, PATTERN_WIDTH, WARMUP_FRAMES};
+use crate::tauri_bridge::shared_state::{
+    PixelFormat, PixelMismatch, ReadbackCheckResult, ReadbackSelfTest,
+};
+
+/// Clear color of the rendered pattern, as stored in an sRGB target
+const CLEAR_COLOR: [u8; 4] = [200, 100, 50, 255];
+
+/// Texture holding a known pattern
+struct Pattern {
+    image: Handle<Image>,
+    format: TextureFormat,
+    /// Expected pixels as RGBA
+    pixels: Vec<[u8; 4]>,
+    /// Drawn by the GPU instead of uploaded, so colors may round
+    /// differently
+    rendered: bool,
+}
+
+/// Start reading the test patterns back
+///
+/// The results are sent to `reply` by [`finish_readback_check`] once every
+/// check has its readback.
+pub fn start_readback_self_test(
+    world: &mut World,
+    reply: Sender<Result<ReadbackSelfTest, String>>,
+) {
+    if world.contains_resource::<ReadbackSelfTestRun>() {
+        let _ = reply.send(Err("A readback self-test is already running".to_string()));
+        return;
+    }
+
+    let size = Extent3d {
+        width: PATTERN_WIDTH,
+        height: PATTERN_HEIGHT,
+        depth_or_array_layers: 1,
+    };
+    let gradient = gradient_pattern(PATTERN_WIDTH, PATTERN_HEIGHT);
+    let rgba = add_pattern_image(world, size, TextureFormat::Rgba8UnormSrgb, gradient.clone());
+    let bgra = add_pattern_image(world, size, TextureFormat::Bgra8UnormSrgb, gradient);
+    let (camera, cleared) = spawn_clear_color_camera(world, size);
+    let region = URect::new(
+        PATTERN_WIDTH / 4,
+        PATTERN_HEIGHT / 4,
+        PATTERN_WIDTH * 3 / 4,
+        PATTERN_HEIGHT * 3 / 4,
+    );
+
+    let checks = [
+        ("rgba8_direct", &rgba, PixelFormat::Rgba8, None),
+        ("rgb8_packed", &rgba, PixelFormat::Rgb8, None),
+        ("rgb565_packed", &rgba, PixelFormat::Rgb565, None),
+        ("bgra_source", &bgra, PixelFormat::Rgba8, None),
+        ("region", &rgba, PixelFormat::Rgba8, Some(region)),
+        ("srgb_clear_color", &cleared, PixelFormat::Rgba8, None),
+    ];
+    let count = checks.len();
+    for (index, (name, pattern, format, region)) in checks.into_iter().enumerate() {
+        let copier = ImageCopier::new(
+            pattern.image.clone(),
+            size,
+            region,
+            pattern.format,
+            format,
+            &mut world.resource_mut::<Assets<ShaderStorageBuffer>>(),
+        );
+        // Quantization to 5 and 6 bits, and rounding of rendered colors
+        let tolerance = match (format, pattern.rendered) {
+            (PixelFormat::Rgb565, _) => 8,
+            (_, true) => 1,
+            (_, false) => 0,
+        };
+        world.spawn((
+            copier,
+            ReadbackCheck {
+                index,
+                name,
+                source_format: pattern.format,
+                expected: crop(&pattern.pixels, PATTERN_WIDTH, region),
+                tolerance,
+                warmup_frames: WARMUP_FRAMES,
+            },
+        ));
+    }
+
+    world.insert_resource(ReadbackSelfTestRun {
+        remaining: count,
+        results: vec![None; count],
+        camera,
+        reply,
+    });
+    println!("[Bevy] Running readback self-test ({} checks)", count);
+}
+
+/// Compare a self-test readback with its pattern and reply once all are in
+pub fn finish_readback_check(
+    mut event: On<CopyComplete>,
+    mut checks: Query<(&mut ReadbackCheck, &ImageCopier)>,
+    run: Option<ResMut<ReadbackSelfTestRun>>,
+    buffer_pool: Res<BufferPoolRes>,
+    mut commands: Commands,
+) {
+    let Ok((mut check, copier)) = checks.get_mut(event.entity) else {
+        return;
+    };
+    if check.warmup_frames > 0 {
+        check.warmup_frames -= 1;
+        return;
+    }
+    commands.entity(event.entity).despawn();
+    let Some(mut run) = run else { return };
+
+    let data = std::mem::take(&mut event.data);
+    let padded = copier.is_padded(data.len());
+    let pixels = remove_row_padding(
+        data,
+        copier.size.width,
+        copier.size.height,
+        copier.pixel_format,
+        padded,
+        &buffer_pool.0,
+    );
+    let result = compare(&check, copier, padded, pixels.as_deref());
+    if let Some(pixels) = pixels {
+        buffer_pool.0.recycle(pixels);
+    }
+    if let Some(slot) = run.results.get_mut(check.index) {
+        *slot = Some(result);
+    }
+    run.remaining = run.remaining.saturating_sub(1);
+    if run.remaining > 0 {
+        return;
+    }
+
+    let checks: Vec<ReadbackCheckResult> = run.results.drain(..).flatten().collect();
+    let passed = checks.iter().all(|check| check.passed);
+    println!(
+        "[Bevy] Readback self-test {}: {}/{} checks passed",
+        if passed { "passed" } else { "failed" },
+        checks.iter().filter(|check| check.passed).count(),
+        checks.len()
+    );
+    let _ = run.reply.send(Ok(ReadbackSelfTest { passed, checks }));
+    commands.entity(run.camera).despawn();
+    commands.remove_resource::<ReadbackSelfTestRun>();
+}
+
+/// Compare tightly packed readback `pixels` with the check's pattern
+fn compare(
+    check: &ReadbackCheck,
+    copier: &ImageCopier,
+    padded: bool,
+    pixels: Option<&[u8]>,
+) -> ReadbackCheckResult {
+    let (width, height) = (copier.size.width, copier.size.height);
+    let format = copier.pixel_format;
+    let mut result = ReadbackCheckResult {
+        name: check.name,
+        source_format: format!("{:?}", check.source_format),
+        format,
+        packed: copier.packed_buffer.is_some(),
+        padded,
+        width,
+        height,
+        passed: false,
+        mismatched_pixels: 0,
+        max_error: 0,
+        tolerance: check.tolerance,
+        first_mismatch: None,
+        error: None,
+    };
+
+    let bytes_per_pixel = format.bytes_per_pixel();
+    let expected_bytes = check.expected.len() * bytes_per_pixel;
+    let pixels = match pixels {
+        Some(pixels) if pixels.len() >= expected_bytes => pixels,
+        Some(pixels) => {
+            result.error = Some(format!(
+                "Readback has {} bytes, expected {}",
+                pixels.len(),
+                expected_bytes
+            ));
+            return result;
+        }
+        None => {
+            result.error = Some("Readback was empty".to_string());
+            return result;
+        }
+    };
+
+    // Alpha is dropped by the RGB formats
+    let channels = if format == PixelFormat::Rgba8 { 4 } else { 3 };
+    let actual_pixels = pixels
+        .chunks_exact(bytes_per_pixel)
+        .map(|pixel| decode(pixel, format));
+    for (index, (expected, actual)) in check.expected.iter().zip(actual_pixels).enumerate() {
+        let error = (0..channels)
+            .map(|channel| expected[channel].abs_diff(actual[channel]))
+            .max()
+            .unwrap_or(0);
+        result.max_error = result.max_error.max(error);
+        if error <= check.tolerance {
+            continue;
+        }
+        result.mismatched_pixels += 1;
+        if result.first_mismatch.is_none() {
+            result.first_mismatch = Some(PixelMismatch {
+                x: index as u32 % width,
+                y: index as u32 / width,
+                expected: *expected,
+                actual,
+            });
+        }
+    }
+    result.passed = result.mismatched_pixels == 0;
+    result
+}
+
+/// One pixel of `format` as RGBA, opaque for formats without alpha
+fn decode(pixel: &[u8], format: PixelFormat) -> [u8; 4] {
+    match format {
+        PixelFormat::Rgba8 => [pixel[0], pixel[1], pixel[2], pixel[3]],
+        PixelFormat::Rgb8 => [pixel[0], pixel[1], pixel[2], 255],
+        PixelFormat::Rgb565 => {
+            let value = u16::from_le_bytes([pixel[0], pixel[1]]);
+            let r = (value >> 11) as u8 & 0x1F;
+            let g = (value >> 5) as u8 & 0x3F;
+            let b = value as u8 & 0x1F;
+            [
+                (r << 3) | (r >> 2),
+                (g << 2) | (g >> 4),
+                (b << 3) | (b >> 2),
+                255,
+            ]
+        }
+    }
+}
+
+/// Red rising left to right, green top to bottom, a blue checkerboard, and
+/// alpha falling left to right, so misplaced rows, swapped channels and
+/// shifted pixels all show
+fn gradient_pattern(width: u32, height: u32) -> Vec<[u8; 4]> {
+    (0..height)
+        .flat_map(|y| {
+            (0..width).map(move |x| {
+                [
+                    (x * 255 / (width - 1)) as u8,
+                    (y * 255 / (height - 1)) as u8,
+                    if (x + y) % 2 == 0 { 255 } else { 0 },
+                    (255 - x * 128 / width) as u8,
+                ]
+            })
+        })
+        .collect()
+}
+
+/// Pixels of `pattern` within `region`, all of them if `None`
+fn crop(pattern: &[[u8; 4]], width: u32, region: Option<URect>) -> Vec<[u8; 4]> {
+    let Some(region) = region else {
+        return pattern.to_vec();
+    };
+    (region.min.y..region.max.y)
+        .flat_map(|y| (region.min.x..region.max.x).map(move |x| (y * width + x) as usize))
+        .map(|index| pattern[index])
+        .collect()
+}
+
+/// Upload `pixels` as a texture of `format`
+fn add_pattern_image(
+    world: &mut World,
+    size: Extent3d,
+    format: TextureFormat,
+    pixels: Vec<[u8; 4]>,
+) -> Pattern {
+    let data = pixels
+        .iter()
+        .flat_map(|&[r, g, b, a]| match format {
+            TextureFormat::Bgra8UnormSrgb => [b, g, r, a],
+            _ => [r, g, b, a],
+        })
+        .collect();
+    let mut image = Image::new(
+        size,
+        TextureDimension::D2,
+        data,
+        format,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.texture_descriptor.usage |= TextureUsages::COPY_SRC;
+    allow_raw_view(&mut image);
+    Pattern {
+        image: world.resource_mut::<Assets<Image>>().add(image),
+        format,
+        pixels,
+        rendered: false,
+    }
+}
+
+/// Spawn a camera that only clears its target to [`CLEAR_COLOR`]
+fn spawn_clear_color_camera(world: &mut World, size: Extent3d) -> (Entity, Pattern) {
+    let format = TextureFormat::bevy_default();
+    let mut image = Image::new_target_texture(size.width, size.height, format);
+    image.texture_descriptor.usage |= TextureUsages::COPY_SRC;
+    allow_raw_view(&mut image);
+    let image = world.resource_mut::<Assets<Image>>().add(image);
+
+    let [r, g, b, _] = CLEAR_COLOR;
+    let camera = world
+        .spawn((
+            Camera3d::default(),
+            Camera {
+                target: RenderTarget::Image(image.clone().into()),
+                clear_color: ClearColorConfig::Custom(Color::srgb_u8(r, g, b)),
+                ..default()
+            },
+            Tonemapping::None,
+            DebandDither::Disabled,
+            Msaa::Off,
+            RenderLayers::layer(EMPTY_RENDER_LAYER),
+        ))
+        .id();
+    let pattern = Pattern {
+        image,
+        format,
+        pixels: vec![CLEAR_COLOR; (size.width * size.height) as usize],
+        rendered: true,
+    };
+    (camera, pattern)
+}
@@ -8,19 +8,17 @@ use bevy::{
     camera::RenderTarget,
     core_pipeline::tonemapping::Tonemapping,
     image::Image,
-    math::{primitives::Cuboid, Quat, Vec3},
+    math::{primitives::Cuboid, Vec3},
     pbr::{MeshMaterial3d, StandardMaterial},
     prelude::*,
-    render::{
-        render_resource::{Extent3d, TextureFormat, TextureUsages},
-        renderer::RenderDevice,
-    },
+    render::render_resource::{Extent3d, TextureFormat, TextureUsages},
 };
 
-use crate::config::{RENDER_WIDTH, RENDER_HEIGHT};
 use crate::bevy::components::{OffscreenCamera, CameraController, RotatingCube};
-use crate::bevy::plugins::image_copy::ImageCopier;
-use crate::bevy::resources::RenderTargetHandle;
+use crate::bevy::plugins::pixel_pack::allow_raw_view;
+use crate::bevy::resources::{RenderSettings, RenderTargetHandle};
+use crate::bevy::systems::lighting::set_lighting_preset;
+use crate::tauri_bridge::shared_state::LightingPreset;
 
 /// Setup the 3D scene with camera, objects, and lights
 pub fn setup_scene(
@@ -28,13 +26,13 @@ pub fn setup_scene(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut images: ResMut<Assets<Image>>,
-    render_device: Res<RenderDevice>,
+    render_settings: Res<RenderSettings>,
 ) {
     println!("[Bevy] Setting up scene...");
 
     let size = Extent3d {
-        width: RENDER_WIDTH,
-        height: RENDER_HEIGHT,
+        width: render_settings.width,
+        height: render_settings.height,
         depth_or_array_layers: 1,
     };
 
@@ -42,17 +40,13 @@ pub fn setup_scene(
     let mut render_target_image =
         Image::new_target_texture(size.width, size.height, TextureFormat::bevy_default());
     render_target_image.texture_descriptor.usage |= TextureUsages::COPY_SRC;
+    allow_raw_view(&mut render_target_image);
     let render_target_image_handle = images.add(render_target_image);
 
+    // The image copier for GPU-to-CPU transfer is spawned by
+    // `update_stream_target` once this handle is available
     commands.insert_resource(RenderTargetHandle(render_target_image_handle.clone()));
 
-    // Spawn image copier for GPU-to-CPU transfer
-    commands.spawn(ImageCopier::new(
-        render_target_image_handle.clone(),
-        size,
-        &render_device,
-    ));
-
     // Spawn camera with orbit controller
     commands.spawn((
         Camera3d::default(),
@@ -62,9 +56,11 @@ pub fn setup_scene(
             ..default()
         },
         Tonemapping::None,
+        render_settings.msaa,
         Transform::from_xyz(0.0, 2.5, 6.0).looking_at(Vec3::ZERO, Vec3::Y),
         OffscreenCamera,
         CameraController,
+        Name::new("camera"),
     ));
 
     // Main cube (blue)
@@ -78,6 +74,7 @@ pub fn setup_scene(
         })),
         Transform::from_xyz(0.0, 0.0, 0.0),
         RotatingCube,
+        Name::new("main_cube"),
     ));
 
     // Small cube (red)
@@ -91,38 +88,11 @@ pub fn setup_scene(
         })),
         Transform::from_xyz(2.2, 0.3, 0.0),
         RotatingCube,
+        Name::new("small_cube"),
     ));
 
-    // Primary point light
-    commands.spawn((
-        PointLight {
-            intensity: 2_000_000.0,
-            shadows_enabled: true,
-            color: Color::srgb(1.0, 0.95, 0.85),
-            ..default()
-        },
-        Transform::from_xyz(4.0, 8.0, 4.0),
-    ));
-
-    // Secondary point light (blue tint)
-    commands.spawn((
-        PointLight {
-            intensity: 800_000.0,
-            color: Color::srgb(0.4, 0.6, 1.0),
-            ..default()
-        },
-        Transform::from_xyz(-3.0, 4.0, -2.0),
-    ));
-
-    // Directional light
-    commands.spawn((
-        DirectionalLight {
-            illuminance: 3000.0,
-            shadows_enabled: true,
-            ..default()
-        },
-        Transform::from_rotation(Quat::from_euler(bevy::math::EulerRot::XYZ, -0.6, 0.4, 0.0)),
-    ));
+    // Lights, replaced as a whole by `set_lighting_preset`
+    commands.queue(|world: &mut World| set_lighting_preset(world, LightingPreset::Default));
 
     println!("[Bevy] Scene setup complete!");
 }
@@ -0,0 +1,263 @@
+//! Scene editing
+//!
+//! Adds primitives to the scene and moves existing entities, for the
+//! `spawn_primitive`, `set_entity_transform`, `set_parent` and `detach`
+//! commands and the scene endpoints of the `frame://` protocol. Entities
+//! are addressed by the ids `list_entities` reports. Moves can be snapped
+//! to a grid and to angle steps with `set_snapping`. Reparented entities
+//! keep their place in world space.
+
+use bevy::{ecs::entity_disabling::Disabled, prelude::*};
+
+use crate::bevy::resources::{EditChange, Snapping};
+use crate::bevy::systems::history::record;
+use crate::tauri_bridge::shared_state::{
+    PrimitiveShape, SnapSettings, SpawnPrimitive, TransformUpdate,
+};
+
+/// Add `primitive` to the scene and return its entity id
+pub fn spawn_primitive(world: &mut World, primitive: SpawnPrimitive) -> Result<u64, String> {
+    let mut transform = Transform::default();
+    apply_transform(&mut transform, &primitive.transform)?;
+    let base_color = match primitive.color.as_deref() {
+        None => Color::srgb(0.75, 0.75, 0.78),
+        Some(&[r, g, b]) => Color::srgb(r, g, b),
+        Some(&[r, g, b, a]) => Color::srgba(r, g, b, a),
+        Some(_) => return Err("color must have 3 or 4 components".to_string()),
+    };
+    let alpha_mode = if base_color.alpha() < 1.0 {
+        AlphaMode::Blend
+    } else {
+        AlphaMode::Opaque
+    };
+
+    let mesh = world
+        .resource_mut::<Assets<Mesh>>()
+        .add(match primitive.shape {
+            PrimitiveShape::Cube => Mesh::from(Cuboid::from_length(1.0)),
+            PrimitiveShape::Sphere => Mesh::from(Sphere::new(0.5)),
+            PrimitiveShape::Cylinder => Mesh::from(Cylinder::new(0.5, 1.0)),
+            PrimitiveShape::Plane => Mesh::from(Plane3d::default().mesh().size(1.0, 1.0)),
+        });
+    let material = world
+        .resource_mut::<Assets<StandardMaterial>>()
+        .add(StandardMaterial {
+            base_color,
+            alpha_mode,
+            perceptual_roughness: 0.6,
+            ..default()
+        });
+    let name = primitive
+        .name
+        .unwrap_or_else(|| format!("{:?}", primitive.shape).to_lowercase());
+    let entity = world
+        .spawn((
+            Mesh3d(mesh),
+            MeshMaterial3d(material),
+            transform,
+            Name::new(name.clone()),
+        ))
+        .id();
+    let change = EditChange::Spawned(vec![entity]);
+    record(world, "spawn_primitive", vec![change]);
+    println!("[Bevy] Spawned {} as {}", name, entity);
+    Ok(entity.to_bits())
+}
+
+/// Change the local transform of entity `id`
+pub fn set_entity_transform(
+    world: &mut World,
+    id: u64,
+    update: &TransformUpdate,
+) -> Result<(), String> {
+    let entity = Entity::try_from_bits(id).ok_or_else(|| format!("Invalid entity id {}", id))?;
+    let mut entity = world
+        .get_entity_mut(entity)
+        .map_err(|_| format!("No entity with id {}", id))?;
+    let mut transform = entity
+        .get_mut::<Transform>()
+        .ok_or_else(|| format!("Entity {} has no transform", id))?;
+    let before = *transform;
+    apply_transform(&mut transform, update)?;
+    let after = *transform;
+    let change = EditChange::Transform {
+        entity: entity.id(),
+        before,
+        after,
+    };
+    record(world, "set_entity_transform", vec![change]);
+    Ok(())
+}
+
+/// Make entity `child` a child of entity `parent`, keeping where it is in
+/// world space
+pub fn set_parent(world: &mut World, child: u64, parent: u64) -> Result<(), String> {
+    let child_entity = find_entity(world, child)?;
+    let parent_entity = find_entity(world, parent)?;
+    let mut ancestor = Some(parent_entity);
+    while let Some(entity) = ancestor {
+        if entity == child_entity {
+            return Err(format!(
+                "Entity {} can't be parented to itself or a descendant",
+                child
+            ));
+        }
+        ancestor = world.get::<ChildOf>(entity).map(ChildOf::parent);
+    }
+    reparent(world, "set_parent", child_entity, Some(parent_entity))
+}
+
+/// Make entity `id` a root entity, keeping where it is in world space
+pub fn detach(world: &mut World, id: u64) -> Result<(), String> {
+    let entity = find_entity(world, id)?;
+    reparent(world, "detach", entity, None)
+}
+
+/// Round transforms set with `set_entity_transform` to `settings`' steps
+pub fn set_snapping(world: &mut World, settings: SnapSettings) {
+    world.resource_mut::<Snapping>().0 = settings;
+    println!(
+        "[Bevy] Snapping translations to {:?}, rotations to {:?} degrees",
+        settings.translate_step, settings.rotate_step
+    );
+}
+
+/// Round the translation and rotation `update` sets to the snapping steps
+///
+/// Values `apply_transform` rejects are left for it to report.
+pub(crate) fn snap_transform(world: &World, update: &mut TransformUpdate) {
+    let SnapSettings {
+        translate_step,
+        rotate_step,
+    } = world.resource::<Snapping>().0;
+    if let (Some(step), Some(translation)) = (translate_step, &mut update.translation) {
+        for value in translation {
+            *value = (*value / step).round() * step;
+        }
+    }
+    if let (Some(step), Some(rotation)) = (rotate_step, &mut update.rotation) {
+        let quat = Quat::from_array(*rotation);
+        if !quat.is_finite() || quat.length_squared() == 0.0 {
+            return;
+        }
+        let step = step.to_radians();
+        let (y, x, z) = quat.normalize().to_euler(EulerRot::YXZ);
+        let [y, x, z] = [y, x, z].map(|angle| (angle / step).round() * step);
+        *rotation = Quat::from_euler(EulerRot::YXZ, y, x, z).to_array();
+    }
+}
+
+/// Apply the parts of `update` that are given, or none if one is invalid
+pub(crate) fn apply_transform(
+    transform: &mut Transform,
+    update: &TransformUpdate,
+) -> Result<(), String> {
+    let all_finite = update
+        .translation
+        .iter()
+        .chain(&update.scale)
+        .flatten()
+        .chain(update.rotation.iter().flatten())
+        .all(|value| value.is_finite());
+    if !all_finite {
+        return Err("Transform values must be finite".to_string());
+    }
+    let rotation = match update.rotation {
+        Some(rotation) => {
+            let rotation = Quat::from_array(rotation);
+            if rotation.length_squared() == 0.0 {
+                return Err("rotation must not be zero".to_string());
+            }
+            Some(rotation.normalize())
+        }
+        None => None,
+    };
+
+    if let Some(translation) = update.translation {
+        transform.translation = Vec3::from_array(translation);
+    }
+    if let Some(rotation) = rotation {
+        transform.rotation = rotation;
+    }
+    if let Some(scale) = update.scale {
+        transform.scale = Vec3::from_array(scale);
+    }
+    Ok(())
+}
+
+/// Move `entity` under `parent`, or to the root if `None`, and change its
+/// local transform so it stays where it is
+///
+/// Shear its ancestors' rotations and non-uniform scales make can't be
+/// kept in a transform and is lost.
+fn reparent(
+    world: &mut World,
+    label: &'static str,
+    entity: Entity,
+    parent: Option<Entity>,
+) -> Result<(), String> {
+    let before = world.get::<ChildOf>(entity).map(ChildOf::parent);
+    if before == parent {
+        return Ok(());
+    }
+    let Some(&transform) = world.get::<Transform>(entity) else {
+        return Err(format!("Entity {} has no transform", entity.to_bits()));
+    };
+    let global = global_transform(world, entity);
+    let reparented = match parent {
+        Some(parent) => global.reparented_to(&global_transform(world, parent)),
+        None => global.compute_transform(),
+    };
+    if !reparented.is_finite() {
+        return Err("The new parent has a zero scale".to_string());
+    }
+
+    let mut entity_mut = world.entity_mut(entity);
+    match parent {
+        Some(parent) => entity_mut.insert((ChildOf(parent), reparented)),
+        None => entity_mut.remove::<ChildOf>().insert(reparented),
+    };
+    let changes = vec![
+        EditChange::Parent {
+            entity,
+            before,
+            after: parent,
+        },
+        EditChange::Transform {
+            entity,
+            before: transform,
+            after: reparented,
+        },
+    ];
+    record(world, label, changes);
+    match parent {
+        Some(parent) => println!("[Bevy] Parented {} to {}", entity, parent),
+        None => println!("[Bevy] Detached {}", entity),
+    }
+    Ok(())
+}
+
+/// World transform of `entity` from its and its ancestors' transforms,
+/// which unlike `GlobalTransform` includes changes made this frame
+fn global_transform(world: &World, entity: Entity) -> GlobalTransform {
+    let mut global = GlobalTransform::IDENTITY;
+    let mut current = Some(entity);
+    while let Some(entity) = current {
+        if let Some(&transform) = world.get::<Transform>(entity) {
+            global = GlobalTransform::from(transform) * global;
+        }
+        current = world.get::<ChildOf>(entity).map(ChildOf::parent);
+    }
+    global
+}
+
+fn find_entity(world: &World, id: u64) -> Result<Entity, String> {
+    let entity = Entity::try_from_bits(id).ok_or_else(|| format!("Invalid entity id {}", id))?;
+    // Removed entities are only disabled while their removal can be undone
+    world
+        .get_entity(entity)
+        .ok()
+        .filter(|entity| !entity.contains::<Disabled>())
+        .map(|entity| entity.id())
+        .ok_or_else(|| format!("No entity with id {}", id))
+}
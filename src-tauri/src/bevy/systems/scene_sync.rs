@@ -0,0 +1,337 @@
+//! Scene edits shared with other instances
+//!
+//! Edit commands are published from `apply_bridge_commands` once they
+//! succeed, and edits from other instances come back as
+//! `ApplySceneOp` and go through the same functions. Their changes are
+//! kept in [`RemoteEdits`] rather than this instance's undo history, and
+//! they aren't published again. See `tauri_bridge::scene_sync` for the
+//! connections.
+
+use bevy::prelude::*;
+use crossbeam_channel::unbounded;
+
+use crate::bevy::components::SyncKey;
+use crate::bevy::resources::{EditChange, EditHistory, RemoteEdit, RemoteEdits, SceneSyncRes};
+use crate::bevy::systems::billboard::{add_billboard, clear_billboards, remove_billboard};
+use crate::bevy::systems::custom_shaders::{register_custom_shader, set_custom_shader_material};
+use crate::bevy::systems::history::{apply_changes, discard, record};
+use crate::bevy::systems::material_override::set_submesh_material;
+use crate::bevy::systems::model_loading::load_model;
+use crate::bevy::systems::morph::set_morph_weight;
+use crate::bevy::systems::scene_editing::{
+    detach, set_entity_transform, set_parent, spawn_primitive,
+};
+use crate::bevy::systems::tags::{apply_bulk, set_tags, update_tagged};
+use crate::bevy::systems::text3d::spawn_text3d;
+use crate::config::scene_sync::REMOTE_HISTORY_DEPTH;
+use crate::tauri_bridge::scene_sync::{SceneOp, SyncTarget};
+
+/// Send `op`, just made in this instance, to the other instances
+///
+/// Entities spawned by `op` are given a [`SyncKey`] to be addressed by.
+/// The edit the command recorded, if any, is sent along so the other
+/// instances can undo it with this one.
+pub(crate) fn publish(world: &mut World, op: SceneOp) {
+    let edit = world.resource_mut::<EditHistory>().last_recorded.take();
+    let sync = &world.resource::<SceneSyncRes>().0;
+    if !sync.is_active() {
+        return;
+    }
+    let instance = sync.instance();
+    if let SceneOp::SpawnPrimitive { entity, .. }
+    | SceneOp::SpawnText3d { entity, .. }
+    | SceneOp::AddBillboard { entity, .. } = op
+    {
+        insert_key(world, entity, SyncKey { instance, entity });
+    }
+    if let Some(id) = edit {
+        let mut history = world.resource_mut::<EditHistory>();
+        if let Some(edit) = history.undo.iter_mut().rev().find(|edit| edit.id == id) {
+            edit.synced = true;
+        }
+    }
+    world.resource::<SceneSyncRes>().0.publish(op, edit);
+}
+
+/// Send the undo or redo of an edit to the other instances, if they were
+/// sent the edit
+///
+/// Call after `undo` or `redo`, which left the edit on top of the redo or
+/// undo stack.
+pub(crate) fn publish_step(world: &mut World, undone: bool) {
+    let history = world.resource::<EditHistory>();
+    let edit = if undone {
+        history.redo.last()
+    } else {
+        history.undo.back()
+    };
+    let Some(edit) = edit.filter(|edit| edit.synced).map(|edit| edit.id) else {
+        return;
+    };
+    let op = if undone {
+        SceneOp::Undo { edit }
+    } else {
+        SceneOp::Redo { edit }
+    };
+    world.resource::<SceneSyncRes>().0.publish(op, None);
+}
+
+/// Send the edit `op` makes to entity `id` to the other instances
+pub(crate) fn publish_for(world: &mut World, id: u64, op: impl FnOnce(SyncTarget) -> SceneOp) {
+    if let Some(target) = target_for(world, id) {
+        publish(world, op(target));
+    }
+}
+
+/// How other instances address entity `id`, `None` if sync is off or it
+/// can't be addressed
+///
+/// Names change with the hierarchy, so edits that reparent an entity take
+/// its target before making the change.
+pub(crate) fn target_for(world: &World, id: u64) -> Option<SyncTarget> {
+    if !world.resource::<SceneSyncRes>().0.is_active() {
+        return None;
+    }
+    let target = Entity::try_from_bits(id).and_then(|entity| sync_target(world, entity));
+    if target.is_none() {
+        eprintln!(
+            "[Bevy] Entity {} has no name to sync it by, other instances keep it as it was",
+            id
+        );
+    }
+    target
+}
+
+/// Apply `op`, made in `instance` as part of its edit `edit`
+///
+/// What it changes is kept in [`RemoteEdits`] under `edit`, for when
+/// `instance` undoes it.
+pub fn apply_scene_op(
+    world: &mut World,
+    instance: u64,
+    edit: Option<u64>,
+    op: SceneOp,
+) -> Result<(), String> {
+    match op {
+        SceneOp::Undo { edit } => return step_remote_edit(world, instance, edit, true),
+        SceneOp::Redo { edit } => return step_remote_edit(world, instance, edit, false),
+        _ => {}
+    }
+
+    world.resource_mut::<RemoteEdits>().capturing = Some(Vec::new());
+    let result = apply_op(world, instance, edit, op);
+    let changes = world
+        .resource_mut::<RemoteEdits>()
+        .capturing
+        .take()
+        .unwrap_or_default();
+    if let Some(id) = edit.filter(|_| !changes.is_empty()) {
+        remember_remote_edit(world, instance, id, changes);
+    }
+    result
+}
+
+/// Undo or redo the changes edit `id` of `instance` made here
+fn step_remote_edit(world: &mut World, instance: u64, id: u64, undo: bool) -> Result<(), String> {
+    let mut remote = world.resource_mut::<RemoteEdits>();
+    let Some(edit) = remote
+        .edits
+        .iter_mut()
+        .rev()
+        .find(|edit| edit.instance == instance && edit.id == id)
+    else {
+        return Err(format!("Edit {} of instance {} is not known", id, instance));
+    };
+    if edit.undone == undo {
+        return Ok(());
+    }
+    edit.undone = undo;
+    let changes = std::mem::take(&mut edit.changes);
+    apply_changes(world, &changes, undo);
+
+    let mut remote = world.resource_mut::<RemoteEdits>();
+    if let Some(edit) = remote
+        .edits
+        .iter_mut()
+        .rev()
+        .find(|edit| edit.instance == instance && edit.id == id)
+    {
+        edit.changes = changes;
+    }
+    Ok(())
+}
+
+/// Keep what edit `id` of `instance` changed, merged into what it changed
+/// before when several ops make up one edit
+pub(crate) fn remember_remote_edit(
+    world: &mut World,
+    instance: u64,
+    id: u64,
+    changes: Vec<EditChange>,
+) {
+    let mut remote = world.resource_mut::<RemoteEdits>();
+    if let Some(edit) = remote
+        .edits
+        .iter_mut()
+        .rev()
+        .find(|edit| edit.instance == instance && edit.id == id)
+    {
+        edit.changes.extend(changes);
+        return;
+    }
+    remote.edits.push_back(RemoteEdit {
+        instance,
+        id,
+        changes,
+        undone: false,
+    });
+    loop {
+        let mut remote = world.resource_mut::<RemoteEdits>();
+        if remote.edits.len() <= REMOTE_HISTORY_DEPTH {
+            return;
+        }
+        let Some(edit) = remote.edits.pop_front() else {
+            return;
+        };
+        discard(world, edit.changes, edit.undone);
+    }
+}
+
+fn apply_op(
+    world: &mut World,
+    instance: u64,
+    edit: Option<u64>,
+    op: SceneOp,
+) -> Result<(), String> {
+    match op {
+        SceneOp::SpawnPrimitive { entity, primitive } => {
+            let id = spawn_primitive(world, primitive)?;
+            insert_key(world, id, SyncKey { instance, entity });
+        }
+        SceneOp::SpawnText3d { entity, text } => {
+            let id = spawn_text3d(world, text)?;
+            insert_key(world, id, SyncKey { instance, entity });
+        }
+        SceneOp::AddBillboard { entity, billboard } => {
+            let id = add_billboard(world, billboard)?;
+            insert_key(world, id, SyncKey { instance, entity });
+        }
+        SceneOp::RemoveBillboard { target } => {
+            remove_billboard(world, resolve(world, &target)?)?;
+        }
+        SceneOp::ClearBillboards => clear_billboards(world),
+        SceneOp::SetEntityTransform { target, transform } => {
+            set_entity_transform(world, resolve(world, &target)?, &transform)?;
+        }
+        SceneOp::SetParent { target, parent } => {
+            set_parent(world, resolve(world, &target)?, resolve(world, &parent)?)?;
+        }
+        SceneOp::Detach { target } => {
+            detach(world, resolve(world, &target)?)?;
+        }
+        SceneOp::SetSubmeshMaterial { target, material } => {
+            set_submesh_material(world, resolve(world, &target)?, material)?;
+        }
+        SceneOp::RegisterCustomShader { name, source } => {
+            register_custom_shader(world, name, source);
+        }
+        SceneOp::SetCustomShaderMaterial { target, material } => {
+            set_custom_shader_material(world, resolve(world, &target)?, material)?;
+        }
+        SceneOp::SetMorphWeight {
+            target,
+            morph_target,
+            weight,
+        } => {
+            set_morph_weight(world, resolve(world, &target)?, &morph_target, weight)?;
+        }
+        SceneOp::SetTags { target, tags } => {
+            set_tags(world, resolve(world, &target)?, tags)?;
+        }
+        SceneOp::UpdateTagged { tag, operation } => {
+            update_tagged(world, &tag, operation)?;
+        }
+        SceneOp::UpdateEntities { targets, operation } => {
+            let entities = targets
+                .iter()
+                .map(|target| resolve(world, target).map(Entity::from_bits))
+                .collect::<Result<Vec<_>, _>>()?;
+            let changes = apply_bulk(world, &entities, &operation)?;
+            record(world, "update_selection_set", changes);
+        }
+        SceneOp::LoadModel { path } => {
+            // Progress is reported in the instance that loaded it
+            let (progress, _) = unbounded();
+            // Kept under `edit` once loaded, see `model_loading`
+            load_model(world, path, progress, Some((instance, edit)));
+        }
+        // Handled by `apply_scene_op`
+        SceneOp::Undo { .. } | SceneOp::Redo { .. } => {}
+    }
+    Ok(())
+}
+
+/// How other instances address `entity`, `None` if it wasn't spawned by
+/// a synced edit and it or an ancestor has no name
+fn sync_target(world: &World, entity: Entity) -> Option<SyncTarget> {
+    if let Some(key) = world.get::<SyncKey>(entity) {
+        return Some(SyncTarget::Spawned {
+            instance: key.instance,
+            entity: key.entity,
+        });
+    }
+    name_path(world, entity).map(SyncTarget::Named)
+}
+
+/// Id of the entity `target` names in this instance
+fn resolve(world: &mut World, target: &SyncTarget) -> Result<u64, String> {
+    let found = match target {
+        SyncTarget::Spawned { instance, entity } => {
+            let key = SyncKey {
+                instance: *instance,
+                entity: *entity,
+            };
+            world
+                .query::<(Entity, &SyncKey)>()
+                .iter(world)
+                .find(|(_, other)| **other == key)
+                .map(|(entity, _)| entity)
+        }
+        SyncTarget::Named(path) => {
+            let name = path.last().map(String::as_str);
+            let named: Vec<Entity> = world
+                .query::<(Entity, &Name)>()
+                .iter(world)
+                .filter(|(_, other)| Some(other.as_str()) == name)
+                .map(|(entity, _)| entity)
+                .collect();
+            // The first of several with the same path
+            named
+                .into_iter()
+                .find(|&entity| name_path(world, entity).as_ref() == Some(path))
+        }
+    };
+    found
+        .map(Entity::to_bits)
+        .ok_or_else(|| format!("No entity matches {:?}", target))
+}
+
+/// Names of `entity` and its ancestors, root first
+fn name_path(world: &World, entity: Entity) -> Option<Vec<String>> {
+    let mut path = Vec::new();
+    let mut current = Some(entity);
+    while let Some(entity) = current {
+        path.push(world.get::<Name>(entity)?.to_string());
+        current = world.get::<ChildOf>(entity).map(ChildOf::parent);
+    }
+    path.reverse();
+    Some(path)
+}
+
+fn insert_key(world: &mut World, id: u64, key: SyncKey) {
+    if let Some(mut entity) =
+        Entity::try_from_bits(id).and_then(|entity| world.get_entity_mut(entity).ok())
+    {
+        entity.insert(key);
+    }
+}
@@ -0,0 +1,219 @@
+//! Supersampled screenshot rendering
+//!
+//! A screenshot is rendered by a temporary second camera with the same view
+//! as the stream camera, into its own target at a multiple of the render
+//! resolution. Its readback is handled here instead of going through the
+//! frame channel, so the stream keeps running at its own resolution.
+
+use bevy::{
+    camera::{visibility::RenderLayers, Exposure, ImageRenderTarget, RenderTarget},
+    core_pipeline::{tonemapping::Tonemapping, Skybox},
+    math::FloatOrd,
+    post_process::dof::DepthOfField,
+    prelude::*,
+    render::{
+        gpu_readback::{Readback, ReadbackComplete},
+        render_resource::{TextureFormat, TextureUsages},
+        renderer::RenderDevice,
+    },
+};
+use crossbeam_channel::Sender;
+
+use crate::bevy::components::{OffscreenCamera, ScreenshotCapture};
+use crate::bevy::plugins::color_lut::ColorLut;
+use crate::bevy::resources::{BufferPoolRes, RenderSettings};
+use crate::bevy::systems::frame_extraction::remove_row_padding;
+use crate::bevy::systems::ui_overlay::add_ui_overlay_to_capture;
+use crate::config::screenshot::WARMUP_FRAMES;
+use crate::tauri_bridge::shared_state::{CapturedImage, PixelFormat};
+
+/// Start rendering the current view at `scale` times the render resolution
+///
+/// The finished image is sent to `reply` by [`finish_screenshot`] a few
+/// frames later.
+pub fn start_screenshot(
+    world: &mut World,
+    scale: u32,
+    reply: Sender<Result<CapturedImage, String>>,
+) {
+    let settings = *world.resource::<RenderSettings>();
+    let (width, height) = (settings.width * scale, settings.height * scale);
+    if start_capture(world, width, height, None, reply) {
+        println!(
+            "[Bevy] Rendering {}x{} screenshot ({}x)",
+            width, height, scale
+        );
+    }
+}
+
+/// Start rendering a single `width` x `height` image from `transform`, or
+/// from the stream camera's view if `None`
+///
+/// Errors are sent to `reply` right away, in which case this returns false.
+pub fn start_capture(
+    world: &mut World,
+    width: u32,
+    height: u32,
+    transform: Option<Transform>,
+    reply: Sender<Result<CapturedImage, String>>,
+) -> bool {
+    let max_size = world
+        .resource::<RenderDevice>()
+        .limits()
+        .max_texture_dimension_2d;
+    if width.max(height) > max_size {
+        let _ = reply.send(Err(format!(
+            "{}x{} exceeds the GPU's {}px texture size limit",
+            width, height, max_size
+        )));
+        return false;
+    }
+
+    let Some((camera, image)) = spawn_capture_camera(world, width, height) else {
+        let _ = reply.send(Err("No camera to capture".to_string()));
+        return false;
+    };
+    if let Some(transform) = transform {
+        world.entity_mut(camera).insert(transform);
+    }
+    world.spawn((
+        Readback::texture(image),
+        ScreenshotCapture {
+            camera,
+            width,
+            height,
+            warmup_frames: WARMUP_FRAMES,
+            reply,
+        },
+    ));
+    true
+}
+
+/// Spawn a camera with the stream camera's view and settings, rendering
+/// into a new `width` x `height` target
+///
+/// Returns the camera and its target, `None` if there is no stream camera.
+pub(crate) fn spawn_capture_camera(
+    world: &mut World,
+    width: u32,
+    height: u32,
+) -> Option<(Entity, Handle<Image>)> {
+    let mut cameras = world.query_filtered::<(
+        &Camera,
+        &Transform,
+        &Projection,
+        &Tonemapping,
+        &Exposure,
+        Option<&DepthOfField>,
+    ), With<OffscreenCamera>>();
+    let (mut camera, transform, projection, tonemapping, exposure, depth_of_field) =
+        cameras.iter(world).next().map(
+            |(camera, transform, projection, tonemapping, exposure, depth_of_field)| {
+                (
+                    camera.clone(),
+                    *transform,
+                    projection.clone(),
+                    *tonemapping,
+                    *exposure,
+                    depth_of_field.cloned(),
+                )
+            },
+        )?;
+    let mut lighting = world
+        .query_filtered::<(Option<&EnvironmentMapLight>, Option<&Skybox>), With<OffscreenCamera>>();
+    let (environment, skybox) = lighting
+        .iter(world)
+        .next()
+        .map(|(environment, skybox)| (environment.cloned(), skybox.cloned()))?;
+    let mut effects = world.query_filtered::<(
+        Option<&DistanceFog>,
+        Option<&ColorLut>,
+        Option<&RenderLayers>,
+    ), With<OffscreenCamera>>();
+    let (fog, lut, layers) = effects
+        .iter(world)
+        .next()
+        .map(|(fog, lut, layers)| (fog.cloned(), lut.cloned(), layers.cloned()))?;
+
+    let mut image = Image::new_target_texture(width, height, TextureFormat::bevy_default());
+    image.texture_descriptor.usage |= TextureUsages::COPY_SRC;
+    let image = world.resource_mut::<Assets<Image>>().add(image);
+
+    // Scaled like the target, so the UI overlay keeps its size in the frame
+    let render_height = world.resource::<RenderSettings>().height;
+    camera.target = RenderTarget::Image(ImageRenderTarget {
+        handle: image.clone(),
+        scale_factor: FloatOrd(height as f32 / render_height as f32),
+    });
+    // The stream camera may only draw a quadrant, see `quad_view`
+    camera.viewport = None;
+    let camera = world
+        .spawn((
+            Camera3d::default(),
+            camera,
+            projection,
+            tonemapping,
+            exposure,
+            transform,
+        ))
+        .id();
+    if let Some(depth_of_field) = depth_of_field {
+        world.entity_mut(camera).insert(depth_of_field);
+    }
+    if let Some(environment) = environment {
+        world.entity_mut(camera).insert(environment);
+    }
+    if let Some(skybox) = skybox {
+        world.entity_mut(camera).insert(skybox);
+    }
+    if let Some(fog) = fog {
+        world.entity_mut(camera).insert(fog);
+    }
+    if let Some(lut) = lut {
+        world.entity_mut(camera).insert(lut);
+    }
+    // Makes the water visible, see `set_water`
+    if let Some(layers) = layers {
+        world.entity_mut(camera).insert(layers);
+    }
+    add_ui_overlay_to_capture(world, camera);
+    Some((camera, image))
+}
+
+/// Send a finished screenshot and remove its camera and target
+pub fn finish_screenshot(
+    mut event: On<ReadbackComplete>,
+    mut captures: Query<&mut ScreenshotCapture>,
+    buffer_pool: Res<BufferPoolRes>,
+    mut commands: Commands,
+) {
+    let Ok(mut capture) = captures.get_mut(event.entity) else {
+        return;
+    };
+    if capture.warmup_frames > 0 {
+        capture.warmup_frames -= 1;
+        return;
+    }
+
+    let data = std::mem::take(&mut event.data);
+    let frame_bytes = capture.width as usize * capture.height as usize * 4;
+    let padded = data.len() > frame_bytes;
+    let result = remove_row_padding(
+        data,
+        capture.width,
+        capture.height,
+        PixelFormat::Rgba8,
+        padded,
+        &buffer_pool.0,
+    )
+    .map(|data| CapturedImage {
+        width: capture.width,
+        height: capture.height,
+        data,
+    })
+    .ok_or_else(|| "Screenshot readback was empty".to_string());
+    let _ = capture.reply.send(result);
+
+    commands.entity(capture.camera).despawn();
+    commands.entity(event.entity).despawn();
+}
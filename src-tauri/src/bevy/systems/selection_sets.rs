@@ -0,0 +1,205 @@
+//! Selection sets
+//!
+//! Named, ordered sets of entities the frontend builds, such as the parts
+//! of a subassembly, to select, hide or recolor together. Unlike tags they
+//! are not stored on the entities, so one entity can be in any number of
+//! sets and a set can be built from a selection without touching the
+//! scene. Members that were despawned or removed are dropped when a set
+//! is used.
+
+use bevy::{ecs::entity_disabling::Disabled, prelude::*};
+
+use crate::bevy::resources::{Selection, SelectionSets};
+use crate::bevy::systems::history::record;
+use crate::bevy::systems::tags::apply_bulk;
+use crate::tauri_bridge::shared_state::{EntitySummary, SelectionSetInfo, TaggedOperation};
+
+/// Create the set `name` with `entities` as its members
+pub fn create_selection_set(
+    world: &mut World,
+    name: String,
+    entities: Vec<u64>,
+) -> Result<(), String> {
+    if world.resource::<SelectionSets>().0.contains_key(&name) {
+        return Err(format!("A selection set named {} already exists", name));
+    }
+    let mut members = Vec::new();
+    add_members(&mut members, resolve(world, &entities)?);
+    println!(
+        "[Bevy] Created selection set {} with {} entities",
+        name,
+        members.len()
+    );
+    world
+        .resource_mut::<SelectionSets>()
+        .0
+        .insert(name, members);
+    Ok(())
+}
+
+/// Add `entities` to the end of set `name`, skipping members, and return
+/// how many members it has
+pub fn add_to_selection_set(
+    world: &mut World,
+    name: &str,
+    entities: Vec<u64>,
+) -> Result<usize, String> {
+    let added = resolve(world, &entities)?;
+    let members = members_mut(world, name)?;
+    add_members(members, added);
+    Ok(members.len())
+}
+
+/// Remove `entities` from set `name` and return how many members it has
+/// left
+pub fn remove_from_selection_set(
+    world: &mut World,
+    name: &str,
+    entities: Vec<u64>,
+) -> Result<usize, String> {
+    // Despawned entities can be removed too
+    let removed: Vec<Entity> = entities
+        .into_iter()
+        .filter_map(Entity::try_from_bits)
+        .collect();
+    let members = members_mut(world, name)?;
+    members.retain(|member| !removed.contains(member));
+    Ok(members.len())
+}
+
+/// Delete set `name`, leaving its members as they are
+pub fn delete_selection_set(world: &mut World, name: &str) -> Result<(), String> {
+    world
+        .resource_mut::<SelectionSets>()
+        .0
+        .remove(name)
+        .map(|_| ())
+        .ok_or_else(|| format!("No selection set named {}", name))
+}
+
+/// Every set with its members, sets by name
+pub fn list_selection_sets(world: &mut World) -> Vec<SelectionSetInfo> {
+    let names: Vec<String> = world
+        .resource::<SelectionSets>()
+        .0
+        .keys()
+        .cloned()
+        .collect();
+    names
+        .into_iter()
+        .map(|name| {
+            let members = members(world, &name).unwrap_or_default();
+            SelectionSetInfo {
+                entities: summaries(world, &members),
+                name,
+            }
+        })
+        .collect()
+}
+
+/// Make the members of set `name` the selection and return them
+pub fn select_selection_set(world: &mut World, name: &str) -> Result<Vec<EntitySummary>, String> {
+    let members = members(world, name)?;
+    let selected = summaries(world, &members);
+    world.resource_mut::<Selection>().0 = members;
+    println!(
+        "[Bevy] Selected {} entities of selection set {}",
+        selected.len(),
+        name
+    );
+    Ok(selected)
+}
+
+/// The entities selected with `select_selection_set`
+pub fn selection(world: &mut World) -> Vec<EntitySummary> {
+    let mut selection = std::mem::take(&mut world.resource_mut::<Selection>().0);
+    selection.retain(|&entity| exists(world, entity));
+    let selected = summaries(world, &selection);
+    world.resource_mut::<Selection>().0 = selection;
+    selected
+}
+
+/// Apply `operation` to every member of set `name`, like `update_tagged`
+///
+/// Returns the members it was applied to.
+pub fn update_selection_set(
+    world: &mut World,
+    name: &str,
+    operation: TaggedOperation,
+) -> Result<Vec<Entity>, String> {
+    let members = members(world, name)?;
+    let changes = apply_bulk(world, &members, &operation)?;
+    record(world, "update_selection_set", changes);
+    println!(
+        "[Bevy] {:?} on {} entities of selection set {}",
+        operation,
+        members.len(),
+        name
+    );
+    Ok(members)
+}
+
+/// Members of set `name` that still exist
+fn members(world: &mut World, name: &str) -> Result<Vec<Entity>, String> {
+    members_mut(world, name).cloned()
+}
+
+/// Members of set `name`, dropping those that were despawned or removed
+fn members_mut<'w>(world: &'w mut World, name: &str) -> Result<&'w mut Vec<Entity>, String> {
+    world.resource_scope(|world, mut sets: Mut<SelectionSets>| {
+        if let Some(members) = sets.0.get_mut(name) {
+            members.retain(|&entity| exists(world, entity));
+        }
+    });
+    world
+        .resource_mut::<SelectionSets>()
+        .into_inner()
+        .0
+        .get_mut(name)
+        .ok_or_else(|| format!("No selection set named {}", name))
+}
+
+/// Append `entities` to `members`, skipping members and repeats
+fn add_members(members: &mut Vec<Entity>, entities: Vec<Entity>) {
+    for entity in entities {
+        if !members.contains(&entity) {
+            members.push(entity);
+        }
+    }
+}
+
+/// The entities of ids `ids`, failing on the first that doesn't exist
+fn resolve(world: &World, ids: &[u64]) -> Result<Vec<Entity>, String> {
+    ids.iter()
+        .map(|&id| {
+            let entity =
+                Entity::try_from_bits(id).ok_or_else(|| format!("Invalid entity id {}", id))?;
+            if exists(world, entity) {
+                Ok(entity)
+            } else {
+                Err(format!("No entity with id {}", id))
+            }
+        })
+        .collect()
+}
+
+/// Whether `entity` is in the scene, removed ones are only disabled while
+/// their removal can be undone
+fn exists(world: &World, entity: Entity) -> bool {
+    world
+        .get_entity(entity)
+        .is_ok_and(|entity| !entity.contains::<Disabled>())
+}
+
+fn summaries(world: &World, entities: &[Entity]) -> Vec<EntitySummary> {
+    entities
+        .iter()
+        .filter_map(|&entity| world.get_entity(entity).ok())
+        .filter(|entity| !entity.contains::<Disabled>())
+        .map(|entity| EntitySummary {
+            id: entity.id().to_bits(),
+            name: entity.get::<Name>().map(|name| name.to_string()),
+            components: entity.archetype().component_count(),
+        })
+        .collect()
+}
@@ -0,0 +1,95 @@
+//! Slow frame alerts
+//!
+//! Times each main world frame from `First` to `Last` and adds the GPU,
+//! readback and encode times of the last published frame. Once
+//! `SlowFrameWatch::frames` frames in a row take longer than the threshold,
+//! an alert naming the dominant stage goes to Tauri, which emits it as a
+//! `slow-frames` event. The next alert needs a frame under the threshold
+//! first.
+
+use bevy::prelude::*;
+use crossbeam_channel::Sender;
+use std::time::Instant;
+
+use crate::bevy::resources::{FrameStart, PerfStatsRes, SlowFrameWatch};
+use crate::tauri_bridge::shared_state::{FrameStages, SlowFrameAlert};
+
+/// Start watching for slow frames, replacing any earlier watch
+pub fn watch_slow_frames(
+    world: &mut World,
+    threshold_ms: f64,
+    frames: u32,
+    alerts: Sender<SlowFrameAlert>,
+) {
+    world.insert_resource(SlowFrameWatch {
+        threshold_ms,
+        frames: frames.max(1),
+        streak: 0,
+        streak_stages: FrameStages::default(),
+        alerted: false,
+        alerts,
+    });
+}
+
+/// Stop watching, which ends the `slow-frames` events of the last watch
+pub fn stop_slow_frame_alerts(world: &mut World) {
+    world.remove_resource::<SlowFrameWatch>();
+}
+
+/// Remember when this frame started
+pub fn mark_frame_start(mut start: ResMut<FrameStart>) {
+    start.0 = Some(Instant::now());
+}
+
+/// Time this frame and alert on a long enough run of slow frames
+pub fn detect_slow_frames(
+    mut commands: Commands,
+    watch: Option<ResMut<SlowFrameWatch>>,
+    start: Res<FrameStart>,
+    perf_stats: Res<PerfStatsRes>,
+) {
+    let Some(mut watch) = watch else { return };
+    let Some(start) = start.0 else { return };
+    let cpu_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let Ok(stages) = perf_stats.0 .0.lock().map(|stats| FrameStages {
+        update_ms: (cpu_ms - stats.frame_encoding_ms).max(0.0),
+        readback_ms: stats.frame_encoding_ms.min(cpu_ms),
+        gpu_render_ms: stats.gpu_render_ms,
+        gpu_copy_ms: stats.gpu_copy_ms,
+        jpeg_encode_ms: stats.jpeg_encode_ms,
+    }) else {
+        return;
+    };
+
+    if stages.total_ms() <= watch.threshold_ms {
+        watch.streak = 0;
+        watch.streak_stages = FrameStages::default();
+        watch.alerted = false;
+        return;
+    }
+
+    watch.streak += 1;
+    watch.streak_stages.accumulate(&stages);
+    if watch.alerted || watch.streak < watch.frames {
+        return;
+    }
+
+    let average = watch.streak_stages.averaged(watch.streak);
+    let alert = SlowFrameAlert {
+        threshold_ms: watch.threshold_ms,
+        frames: watch.streak,
+        frame_ms: average.total_ms(),
+        dominant_stage: average.dominant(),
+        stages: average,
+    };
+    println!(
+        "[Bevy] {} slow frames, {:.1}ms average, mostly {}",
+        alert.frames, alert.frame_ms, alert.dominant_stage
+    );
+    watch.alerted = true;
+    if watch.alerts.send(alert).is_err() {
+        // Nobody is listening anymore
+        commands.remove_resource::<SlowFrameWatch>();
+    }
+}
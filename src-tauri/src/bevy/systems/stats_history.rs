@@ -0,0 +1,30 @@
+//! Performance statistics history
+//!
+//! Snapshots the shared performance stats at a fixed wall-clock interval,
+//! so the frontend can graph them over time instead of polling single
+//! values.
+
+use bevy::prelude::*;
+use std::time::{Duration, Instant};
+
+use crate::bevy::resources::{PerfStatsRes, StatsHistoryRes};
+use crate::config::performance::STATS_HISTORY_INTERVAL;
+
+/// Record the current performance stats every `STATS_HISTORY_INTERVAL`
+pub fn record_stats_history(
+    mut last_sample: Local<Option<Instant>>,
+    perf_stats: Res<PerfStatsRes>,
+    history: Res<StatsHistoryRes>,
+) {
+    let now = Instant::now();
+    let interval = Duration::from_secs_f64(STATS_HISTORY_INTERVAL);
+    if last_sample.is_some_and(|last| now - last < interval) {
+        return;
+    }
+    *last_sample = Some(now);
+
+    let Ok(stats) = perf_stats.0 .0.lock().map(|stats| stats.clone()) else {
+        return;
+    };
+    history.0.record(stats);
+}
@@ -0,0 +1,125 @@
+//! Stream target management
+//!
+//! This module creates the texture that is read back and streamed, along
+//! with the image copier reading it, and recreates both whenever the stream
+//! resolution, pixel format or region of interest changes.
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureFormat, TextureUsages},
+        storage::ShaderStorageBuffer,
+    },
+};
+
+use crate::bevy::components::PendingViews;
+use crate::bevy::plugins::downscale::DownscalePass;
+use crate::bevy::plugins::image_copy::ImageCopier;
+use crate::bevy::plugins::pixel_pack::allow_raw_view;
+use crate::bevy::resources::{
+    RenderSettings, RenderTargetHandle, StreamPixelFormat, StreamRegion, StreamResolution,
+    StreamTarget,
+};
+
+/// Create or replace the stream target to match [`StreamResolution`],
+/// [`StreamPixelFormat`] and [`StreamRegion`]
+///
+/// At full render resolution the render target is copied directly. Below it,
+/// a smaller texture is created and filled by a GPU downscale pass. The
+/// region of interest is relative to the stream resolution.
+pub fn update_stream_target(
+    mut commands: Commands,
+    resolution: Res<StreamResolution>,
+    pixel_format: Res<StreamPixelFormat>,
+    region: Res<StreamRegion>,
+    render_settings: Res<RenderSettings>,
+    render_target: Option<Res<RenderTargetHandle>>,
+    stream_target: Option<Res<StreamTarget>>,
+    mut images: ResMut<Assets<Image>>,
+    mut storage_buffers: ResMut<Assets<ShaderStorageBuffer>>,
+) {
+    // The render target is created by the scene setup
+    let Some(render_target) = render_target else {
+        return;
+    };
+    if stream_target.is_some()
+        && !resolution.is_changed()
+        && !pixel_format.is_changed()
+        && !region.is_changed()
+    {
+        return;
+    }
+
+    // Dropping the old copier also releases its staging buffers and image
+    if let Some(old_target) = stream_target {
+        commands.entity(old_target.copier).despawn();
+    }
+
+    let size = Extent3d {
+        width: resolution.width,
+        height: resolution.height,
+        depth_or_array_layers: 1,
+    };
+    let downscale = (size.width, size.height) != (render_settings.width, render_settings.height);
+
+    let image = if downscale {
+        let mut stream_image =
+            Image::new_target_texture(size.width, size.height, TextureFormat::bevy_default());
+        stream_image.texture_descriptor.usage |= TextureUsages::COPY_SRC;
+        allow_raw_view(&mut stream_image);
+        images.add(stream_image)
+    } else {
+        render_target.0.clone()
+    };
+
+    // Clamp the region to the stream target, an empty result streams it all
+    let region = region.0.and_then(|roi| {
+        let bounds = URect::new(0, 0, size.width, size.height);
+        let roi = URect::new(
+            roi.x,
+            roi.y,
+            roi.x.saturating_add(roi.width),
+            roi.y.saturating_add(roi.height),
+        );
+        let clamped = roi.intersect(bounds);
+        (!clamped.is_empty()).then_some(clamped)
+    });
+
+    // Both the render target and the stream image use the default format
+    let mut copier = commands.spawn((
+        ImageCopier::new(
+            image.clone(),
+            size,
+            region,
+            TextureFormat::bevy_default(),
+            pixel_format.0,
+            &mut storage_buffers,
+        ),
+        PendingViews::default(),
+    ));
+    if downscale {
+        copier.insert(DownscalePass {
+            source: render_target.0.clone(),
+            target: image.clone(),
+        });
+    }
+    let copier = copier.id();
+
+    commands.insert_resource(StreamTarget { image, copier });
+    println!(
+        "[Bevy] Streaming {} at {}x{}{}",
+        pixel_format.0.as_str(),
+        size.width,
+        size.height,
+        if downscale { " (GPU downscaled)" } else { "" }
+    );
+    if let Some(region) = region {
+        println!(
+            "[Bevy] Region of interest: {}x{} at ({}, {})",
+            region.width(),
+            region.height(),
+            region.min.x,
+            region.min.y
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! Entity tags
+//!
+//! Free-form tags, such as "bolts" or "annotations", let the frontend hide,
+//! recolor or delete a whole group of entities with one command instead of
+//! tracking their ids.
+
+use bevy::prelude::*;
+use std::collections::BTreeSet;
+
+use crate::bevy::components::Tags;
+use crate::bevy::resources::EditChange;
+use crate::bevy::systems::custom_shaders::descendants;
+use crate::bevy::systems::history::{material_state, record, remove};
+use crate::bevy::systems::material_override::change_submesh_material;
+use crate::tauri_bridge::shared_state::{EntitySummary, MaterialOverride, TaggedOperation};
+
+/// Replace the tags of entity `id`, removing them all if `tags` is empty
+pub fn set_tags(world: &mut World, id: u64, tags: Vec<String>) -> Result<(), String> {
+    let entity = Entity::try_from_bits(id).ok_or_else(|| format!("Invalid entity id {}", id))?;
+    let mut entity = world
+        .get_entity_mut(entity)
+        .map_err(|_| format!("No entity with id {}", id))?;
+    let tags: BTreeSet<String> = tags.into_iter().collect();
+    let before = entity
+        .get::<Tags>()
+        .map(|tags| tags.0.clone())
+        .unwrap_or_default();
+    if before == tags {
+        return Ok(());
+    }
+    let change = EditChange::Tags {
+        entity: entity.id(),
+        before,
+        after: tags.clone(),
+    };
+    set_entity_tags(&mut entity, tags);
+    record(world, "set_tags", vec![change]);
+    Ok(())
+}
+
+/// Tag `entity` with `tags`, removing them all if empty
+pub(crate) fn set_entity_tags(entity: &mut EntityWorldMut, tags: BTreeSet<String>) {
+    if tags.is_empty() {
+        entity.remove::<Tags>();
+    } else {
+        entity.insert(Tags(tags));
+    }
+}
+
+/// Entities tagged `tag`, in id order
+pub fn query_entities(world: &mut World, tag: &str) -> Vec<EntitySummary> {
+    let mut entities: Vec<EntitySummary> = world
+        .query::<(EntityRef, &Tags)>()
+        .iter(world)
+        .filter(|(_, tags)| tags.0.contains(tag))
+        .map(|(entity, _)| EntitySummary {
+            id: entity.id().to_bits(),
+            name: entity.get::<Name>().map(|name| name.to_string()),
+            components: entity.archetype().component_count(),
+        })
+        .collect();
+    entities.sort_by_key(|entity| entity.id);
+    entities
+}
+
+/// Apply `operation` to every entity tagged `tag`
+///
+/// Returns how many entities have the tag.
+pub fn update_tagged(
+    world: &mut World,
+    tag: &str,
+    operation: TaggedOperation,
+) -> Result<usize, String> {
+    let tagged: Vec<Entity> = world
+        .query::<(Entity, &Tags)>()
+        .iter(world)
+        .filter(|(_, tags)| tags.0.contains(tag))
+        .map(|(entity, _)| entity)
+        .collect();
+
+    let changes = apply_bulk(world, &tagged, &operation)?;
+    record(world, "update_tagged", changes);
+    println!(
+        "[Bevy] {:?} on {} entities tagged {}",
+        operation,
+        tagged.len(),
+        tag
+    );
+    Ok(tagged.len())
+}
+
+/// Apply `operation` to each of `entities`, returning the edits for the
+/// undo history
+pub(crate) fn apply_bulk(
+    world: &mut World,
+    entities: &[Entity],
+    operation: &TaggedOperation,
+) -> Result<Vec<EditChange>, String> {
+    if let TaggedOperation::Color { color } = operation {
+        return recolor(world, entities, color);
+    }
+    let mut changes = Vec::new();
+    for &entity in entities {
+        match operation {
+            TaggedOperation::Hide | TaggedOperation::Show => {
+                let after = if matches!(operation, TaggedOperation::Hide) {
+                    Visibility::Hidden
+                } else {
+                    Visibility::Inherited
+                };
+                let before = world.get::<Visibility>(entity).copied().unwrap_or_default();
+                world.entity_mut(entity).insert(after);
+                changes.push(EditChange::Visibility {
+                    entity,
+                    before,
+                    after,
+                });
+            }
+            TaggedOperation::RestoreColor => {
+                changes.extend(change_submesh_material(world, entity, None)?);
+            }
+            // Recolored above, and deleted all at once below, as the
+            // entities may be nested
+            TaggedOperation::Color { .. } | TaggedOperation::Delete => {}
+        }
+    }
+    if matches!(operation, TaggedOperation::Delete) {
+        changes.push(remove(world, entities));
+    }
+    Ok(changes)
+}
+
+/// Override the base color of the standard materials below `entities`
+///
+/// Every mesh is checked before any is changed, so a failure doesn't leave
+/// some recolored without an undo entry.
+fn recolor(
+    world: &mut World,
+    entities: &[Entity],
+    color: &[f32],
+) -> Result<Vec<EditChange>, String> {
+    if !matches!(color.len(), 3 | 4) {
+        return Err("color must have 3 or 4 components".to_string());
+    }
+    let meshes: Vec<Entity> = entities
+        .iter()
+        .flat_map(|&entity| descendants(world, entity))
+        .filter(|&mesh| {
+            world
+                .entity(mesh)
+                .contains::<MeshMaterial3d<StandardMaterial>>()
+        })
+        .collect();
+    let materials = world.resource::<Assets<StandardMaterial>>();
+    for &mesh in &meshes {
+        let loaded = material_state(world, mesh)
+            .is_some_and(|state| materials.contains(&state.original.unwrap_or(state.material)));
+        if !loaded {
+            return Err(format!(
+                "Material of entity {} is not loaded",
+                mesh.to_bits()
+            ));
+        }
+    }
+
+    let mut changes = Vec::new();
+    for mesh in meshes {
+        let material = MaterialOverride {
+            color: Some(color.to_vec()),
+            ..default()
+        };
+        changes.extend(change_submesh_material(world, mesh, Some(material))?);
+    }
+    Ok(changes)
+}
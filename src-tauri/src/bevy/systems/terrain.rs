@@ -0,0 +1,381 @@
+//! Built-in demo scenes and procedural terrain
+//!
+//! Besides the rotating cubes, the app can show a generated landscape: a
+//! heightmap of summed value noise, cut into chunk meshes that are culled
+//! separately, and textured by splatting sand, grass, rock and snow by
+//! height and slope. At over half a million triangles with shadows it is
+//! a far heavier workload than the cubes for judging the streaming
+//! pipeline. The same seed always generates the same terrain.
+
+use bevy::{
+    asset::RenderAssetUsages,
+    image::{ImageAddressMode, ImageSampler, ImageSamplerDescriptor},
+    mesh::{Indices, PrimitiveTopology},
+    prelude::*,
+    render::render_resource::{
+        Extent3d, TextureDimension, TextureFormat, TextureViewDescriptor, TextureViewDimension,
+    },
+};
+use std::time::Instant;
+
+use crate::bevy::components::{LoadedModel, RotatingCube, Terrain};
+use crate::bevy::plugins::terrain::{terrain_material, TerrainMaterial};
+use crate::bevy::resources::{EditChange, TerrainSeed};
+use crate::bevy::systems::custom_shaders::descendants;
+use crate::bevy::systems::history::{record, remove};
+use crate::config::terrain::{
+    CHUNKS, CHUNK_RESOLUTION, FEATURE_SIZE, HEIGHT, OCTAVES, SIZE, TEXTURE_SIZE,
+};
+use crate::tauri_bridge::shared_state::DemoScene;
+
+/// Base color and how much noise varies it, per splat layer
+const LAYERS: [([f32; 3], f32); 4] = [
+    ([0.78, 0.70, 0.50], 0.15), // Sand
+    ([0.28, 0.45, 0.16], 0.35), // Grass
+    ([0.45, 0.43, 0.40], 0.40), // Rock
+    ([0.93, 0.95, 0.98], 0.08), // Snow
+];
+
+/// Show `scene`, replacing the current one and any open model
+pub fn set_demo_scene(world: &mut World, scene: DemoScene) {
+    let changes = show_demo_scene(world, scene);
+    record(world, "set_demo_scene", changes);
+}
+
+/// Show the terrain scene generated from `seed`, or from the seed after
+/// the current one if `None`, returning the seed used
+pub fn regenerate_terrain(world: &mut World, seed: Option<u32>) -> u32 {
+    let seed = seed.unwrap_or_else(|| world.resource::<TerrainSeed>().0.wrapping_add(1));
+    world.insert_resource(TerrainSeed(seed));
+    let changes = show_demo_scene(world, DemoScene::Terrain);
+    record(world, "regenerate_terrain", changes);
+    seed
+}
+
+/// Show `scene` and return the changes to record
+///
+/// The scene and model it replaces are removed, so undo can bring them
+/// back.
+fn show_demo_scene(world: &mut World, scene: DemoScene) -> Vec<EditChange> {
+    let previous: Vec<Entity> = world
+        .query_filtered::<Entity, Or<(With<Terrain>, With<LoadedModel>)>>()
+        .iter(world)
+        .collect();
+    let mut changes = vec![remove(world, &previous)];
+
+    let cubes = match scene {
+        DemoScene::Cubes => Visibility::Inherited,
+        DemoScene::Terrain => Visibility::Hidden,
+    };
+    let mut demo_cubes = world.query_filtered::<(Entity, &mut Visibility), With<RotatingCube>>();
+    for (entity, mut visibility) in demo_cubes.iter_mut(world) {
+        if *visibility != cubes {
+            changes.push(EditChange::Visibility {
+                entity,
+                before: *visibility,
+                after: cubes,
+            });
+            *visibility = cubes;
+        }
+    }
+
+    if scene == DemoScene::Terrain {
+        let seed = world.resource::<TerrainSeed>().0;
+        let root = spawn_terrain(world, seed);
+        changes.push(EditChange::Spawned(descendants(world, root)));
+    } else {
+        println!("[Bevy] Showing the cube scene");
+    }
+    changes
+}
+
+/// Generate the terrain from `seed` and spawn its chunks, returning the
+/// root they are under
+fn spawn_terrain(world: &mut World, seed: u32) -> Entity {
+    let started = Instant::now();
+    let heightmap = Heightmap::generate(seed);
+
+    let layers = world.resource_mut::<Assets<Image>>().add(splat_layers());
+    let material = world
+        .resource_mut::<Assets<TerrainMaterial>>()
+        .add(terrain_material(layers));
+    let root = world
+        .spawn((
+            Transform::default(),
+            Visibility::default(),
+            Terrain,
+            Name::new("terrain"),
+        ))
+        .id();
+    for z in 0..CHUNKS {
+        for x in 0..CHUNKS {
+            let mesh = world
+                .resource_mut::<Assets<Mesh>>()
+                .add(heightmap.chunk_mesh(x, z));
+            world.spawn((
+                Mesh3d(mesh),
+                MeshMaterial3d(material.clone()),
+                Transform::default(),
+                ChildOf(root),
+                Name::new(format!("terrain_chunk_{}_{}", x, z)),
+            ));
+        }
+    }
+
+    let vertices = (CHUNKS * (CHUNK_RESOLUTION + 1)).pow(2);
+    println!(
+        "[Bevy] Generated terrain from seed {}: {} chunks, {} vertices in {:.0}ms",
+        seed,
+        CHUNKS * CHUNKS,
+        vertices,
+        started.elapsed().as_secs_f64() * 1000.0
+    );
+    root
+}
+
+// =============================================================================
+// Heightmap
+// =============================================================================
+
+/// Terrain heights on a regular grid, with one extra sample around the
+/// edge so normals at the border can be computed
+struct Heightmap {
+    /// Samples per row, including the border
+    stride: usize,
+    /// Distance between samples in world units
+    step: f32,
+    heights: Vec<f32>,
+    min: f32,
+    max: f32,
+}
+
+impl Heightmap {
+    fn generate(seed: u32) -> Self {
+        let quads = (CHUNKS * CHUNK_RESOLUTION) as usize;
+        let stride = quads + 3;
+        let step = SIZE / quads as f32;
+        let mut heights = Vec::with_capacity(stride * stride);
+        for z in 0..stride {
+            for x in 0..stride {
+                let position = Vec2::new(x as f32 - 1.0, z as f32 - 1.0) * step - SIZE / 2.0;
+                let noise = fbm(seed, position / FEATURE_SIZE, OCTAVES, None) * 0.5 + 0.5;
+                // Flatter valleys and sharper peaks than the noise alone
+                heights.push(noise.clamp(0.0, 1.0).powf(1.6) * HEIGHT - HEIGHT * 0.25);
+            }
+        }
+        let min = heights.iter().copied().fold(f32::MAX, f32::min);
+        let max = heights.iter().copied().fold(f32::MIN, f32::max);
+        Self {
+            stride,
+            step,
+            heights,
+            min,
+            max,
+        }
+    }
+
+    /// Height at grid vertex `x`, `z`, not counting the border
+    fn height(&self, x: usize, z: usize) -> f32 {
+        self.heights[(z + 1) * self.stride + x + 1]
+    }
+
+    fn normal(&self, x: usize, z: usize) -> Vec3 {
+        let at = |x: usize, z: usize| self.heights[z * self.stride + x];
+        let (x, z) = (x + 1, z + 1);
+        let dx = at(x + 1, z) - at(x - 1, z);
+        let dz = at(x, z + 1) - at(x, z - 1);
+        Vec3::new(-dx, 2.0 * self.step, -dz).normalize()
+    }
+
+    /// Mesh of chunk `chunk_x`, `chunk_z`, in world coordinates
+    fn chunk_mesh(&self, chunk_x: u32, chunk_z: u32) -> Mesh {
+        let resolution = CHUNK_RESOLUTION as usize;
+        let side = resolution + 1;
+        let mut positions = Vec::with_capacity(side * side);
+        let mut normals = Vec::with_capacity(side * side);
+        let mut uvs = Vec::with_capacity(side * side);
+        let mut weights = Vec::with_capacity(side * side);
+        for j in 0..side {
+            for i in 0..side {
+                let x = chunk_x as usize * resolution + i;
+                let z = chunk_z as usize * resolution + j;
+                let height = self.height(x, z);
+                let normal = self.normal(x, z);
+                let position = Vec2::new(x as f32, z as f32) * self.step - SIZE / 2.0;
+                positions.push([position.x, height, position.y]);
+                normals.push(normal.to_array());
+                uvs.push([position.x / SIZE + 0.5, position.y / SIZE + 0.5]);
+                let relative = (height - self.min) / (self.max - self.min).max(f32::EPSILON);
+                weights.push(splat_weights(relative, normal.y));
+            }
+        }
+
+        let mut indices = Vec::with_capacity(resolution * resolution * 6);
+        for j in 0..resolution {
+            for i in 0..resolution {
+                let a = (j * side + i) as u32;
+                let (b, c, d) = (a + side as u32, a + 1, a + side as u32 + 1);
+                indices.extend_from_slice(&[a, b, c, c, b, d]);
+            }
+        }
+
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, weights)
+        .with_inserted_indices(Indices::U32(indices))
+    }
+}
+
+/// Sand, grass, rock and snow weights at `relative` height (0 lowest, 1
+/// highest) where the normal points up by `up`, summing to 1
+///
+/// Sand fills the lowest ground, snow the peaks and grass the rest, and
+/// rock covers slopes too steep for any of them.
+fn splat_weights(relative: f32, up: f32) -> [f32; 4] {
+    let smoothstep = |from: f32, to: f32, value: f32| {
+        let t = ((value - from) / (to - from)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    };
+    let sand = 1.0 - smoothstep(0.12, 0.2, relative);
+    let snow = smoothstep(0.72, 0.82, relative);
+    let rock = smoothstep(0.2, 0.35, 1.0 - up);
+    let ground = 1.0 - rock;
+    [
+        ground * sand,
+        ground * (1.0 - sand) * (1.0 - snow),
+        rock,
+        ground * (1.0 - sand) * snow,
+    ]
+}
+
+// =============================================================================
+// Splat Layers
+// =============================================================================
+
+/// Tiling ground textures of the splat layers, as an array texture with a
+/// full mip chain so distant terrain doesn't shimmer
+fn splat_layers() -> Image {
+    let size = TEXTURE_SIZE as usize;
+    let mip_levels = TEXTURE_SIZE.ilog2() + 1;
+    let mut data = Vec::new();
+    for (layer, (color, variation)) in LAYERS.iter().enumerate() {
+        // Noise repeating every 8 lattice cells, so the texture tiles
+        let mut texels = Vec::with_capacity(size * size * 4);
+        for y in 0..size {
+            for x in 0..size {
+                let position = Vec2::new(x as f32, y as f32) / size as f32 * 8.0;
+                let noise = fbm(layer as u32, position, 4, Some(8));
+                for channel in color {
+                    let value = channel * (1.0 + variation * noise);
+                    texels.push((value.clamp(0.0, 1.0) * 255.0).round() as u8);
+                }
+                texels.push(255);
+            }
+        }
+
+        // Layer-major: every mip of a layer before the next layer
+        let mut mip_size = size;
+        data.extend_from_slice(&texels);
+        for _ in 1..mip_levels {
+            texels = downsample(&texels, mip_size);
+            mip_size /= 2;
+            data.extend_from_slice(&texels);
+        }
+    }
+
+    let mut image = Image::new_uninit(
+        Extent3d {
+            width: TEXTURE_SIZE,
+            height: TEXTURE_SIZE,
+            depth_or_array_layers: LAYERS.len() as u32,
+        },
+        TextureDimension::D2,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.data = Some(data);
+    image.texture_descriptor.mip_level_count = mip_levels;
+    image.texture_view_descriptor = Some(TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::D2Array),
+        ..default()
+    });
+    image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+        address_mode_u: ImageAddressMode::Repeat,
+        address_mode_v: ImageAddressMode::Repeat,
+        anisotropy_clamp: 16,
+        ..ImageSamplerDescriptor::linear()
+    });
+    image
+}
+
+/// RGBA8 image of `size` squared halved in both directions, averaging
+/// each 2x2 block
+fn downsample(texels: &[u8], size: usize) -> Vec<u8> {
+    let half = size / 2;
+    let mut smaller = Vec::with_capacity(half * half * 4);
+    for y in 0..half {
+        for x in 0..half {
+            for channel in 0..4 {
+                let at = |dx: usize, dy: usize| {
+                    texels[((2 * y + dy) * size + 2 * x + dx) * 4 + channel] as u32
+                };
+                let sum = at(0, 0) + at(1, 0) + at(0, 1) + at(1, 1);
+                smaller.push(((sum + 2) / 4) as u8);
+            }
+        }
+    }
+    smaller
+}
+
+// =============================================================================
+// Noise
+// =============================================================================
+
+/// Fractal value noise at `position` in about `-1..1`, summing `octaves`
+/// layers of doubling frequency and halving amplitude
+///
+/// With `period`, the noise repeats every `period` lattice cells.
+fn fbm(seed: u32, position: Vec2, octaves: u32, period: Option<i32>) -> f32 {
+    let mut sum = 0.0;
+    let mut total = 0.0;
+    for octave in 0..octaves {
+        let frequency = (1 << octave) as f32;
+        let amplitude = 1.0 / frequency;
+        let period = period.map(|period| period << octave);
+        sum += value_noise(seed.wrapping_add(octave), position * frequency, period) * amplitude;
+        total += amplitude;
+    }
+    sum / total
+}
+
+/// Smoothly interpolated random values at integer lattice points
+fn value_noise(seed: u32, position: Vec2, period: Option<i32>) -> f32 {
+    let cell = position.floor();
+    let f = position - cell;
+    let (x, y) = (cell.x as i32, cell.y as i32);
+    let wrap = |i: i32| period.map_or(i, |period| i.rem_euclid(period));
+    let corner = |dx: i32, dy: i32| lattice_value(seed, wrap(x + dx), wrap(y + dy));
+    // Quintic fade, so the noise's slope is continuous across cells
+    let t = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
+    let bottom = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * t.x;
+    let top = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * t.x;
+    bottom + (top - bottom) * t.y
+}
+
+/// Random value in `-1..1` for lattice point `x`, `y`
+fn lattice_value(seed: u32, x: i32, y: i32) -> f32 {
+    let mut hash = seed.wrapping_mul(0x9e37_79b9)
+        ^ (x as u32).wrapping_mul(0x85eb_ca6b)
+        ^ (y as u32).wrapping_mul(0xc2b2_ae35);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x2c1b_3c6d);
+    hash ^= hash >> 12;
+    hash = hash.wrapping_mul(0x297a_2d39);
+    hash ^= hash >> 15;
+    hash as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
@@ -0,0 +1,303 @@
+//! Extruded 3D text
+//!
+//! Text is turned into a mesh like any other object in the scene, so it is
+//! lit, casts shadows and shows up in exports. Glyph outlines are read from
+//! the font, their curves split into straight segments, and each glyph is
+//! filled front and back and joined by side walls along its outline.
+
+use bevy::{
+    asset::RenderAssetUsages,
+    mesh::{Indices, PrimitiveTopology},
+    prelude::*,
+    text::DEFAULT_FONT_DATA,
+};
+use lyon_tessellation::{
+    math::point, path::Path as OutlinePath, BuffersBuilder, FillOptions, FillRule, FillTessellator,
+    FillVertex, VertexBuffers,
+};
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+use crate::bevy::resources::EditChange;
+use crate::bevy::systems::history::record;
+use crate::bevy::systems::scene_editing::apply_transform;
+use crate::config::text3d::{CURVE_SEGMENTS, DEPTH, SIZE};
+use crate::tauri_bridge::shared_state::SpawnText3d;
+
+/// Add `text` to the scene as an extruded mesh and return its entity id
+pub fn spawn_text3d(world: &mut World, text: SpawnText3d) -> Result<u64, String> {
+    let mut transform = Transform::default();
+    apply_transform(&mut transform, &text.transform)?;
+    let base_color = match text.color.as_deref() {
+        None => Color::srgb(0.75, 0.75, 0.78),
+        Some(&[r, g, b]) => Color::srgb(r, g, b),
+        Some(&[r, g, b, a]) => Color::srgba(r, g, b, a),
+        Some(_) => return Err("color must have 3 or 4 components".to_string()),
+    };
+    let alpha_mode = if base_color.alpha() < 1.0 {
+        AlphaMode::Blend
+    } else {
+        AlphaMode::Opaque
+    };
+
+    let font_data = match &text.font {
+        Some(path) => std::fs::read(path)
+            .map_err(|e| format!("Failed to read font {}: {}", path.display(), e))?,
+        None => DEFAULT_FONT_DATA.to_vec(),
+    };
+    let face = Face::parse(&font_data, 0).map_err(|e| format!("Invalid font: {}", e))?;
+    let size = text.size.unwrap_or(SIZE);
+    let depth = text.depth.unwrap_or(size * DEPTH);
+    let mesh = text_mesh(&face, &text.content, size, depth)?;
+
+    let mesh = world.resource_mut::<Assets<Mesh>>().add(mesh);
+    let material = world
+        .resource_mut::<Assets<StandardMaterial>>()
+        .add(StandardMaterial {
+            base_color,
+            alpha_mode,
+            perceptual_roughness: 0.5,
+            ..default()
+        });
+    let name = text
+        .name
+        .unwrap_or_else(|| format!("text: {}", text.content));
+    let entity = world
+        .spawn((
+            Mesh3d(mesh),
+            MeshMaterial3d(material),
+            transform,
+            Name::new(name.clone()),
+        ))
+        .id();
+    let change = EditChange::Spawned(vec![entity]);
+    record(world, "spawn_text3d", vec![change]);
+    println!("[Bevy] Spawned {} as {}", name, entity);
+    Ok(entity.to_bits())
+}
+
+/// Mesh of `content` set in `face` at em size `size`, extruded `depth`
+/// along Z and centered on the origin
+fn text_mesh(face: &Face, content: &str, size: f32, depth: f32) -> Result<Mesh, String> {
+    let scale = size / face.units_per_em() as f32;
+    let line_height =
+        (face.ascender() as f32 - face.descender() as f32 + face.line_gap() as f32) * scale;
+
+    let mut builder = ExtrusionBuilder::default();
+    let mut pen = Vec2::ZERO;
+    for character in content.chars() {
+        if character == '\n' {
+            pen = Vec2::new(0.0, pen.y - line_height);
+            continue;
+        }
+        // Glyph 0 is the font's placeholder for missing characters
+        let glyph = face.glyph_index(character).unwrap_or(GlyphId(0));
+        let mut outline = GlyphOutline {
+            origin: pen,
+            scale,
+            ..default()
+        };
+        if face.outline_glyph(glyph, &mut outline).is_some() {
+            outline.close();
+            builder.add_glyph(&outline.contours, depth)?;
+        }
+        pen.x += face.glyph_hor_advance(glyph).unwrap_or(0) as f32 * scale;
+    }
+    if builder.positions.is_empty() {
+        return Err("Text has no visible characters".to_string());
+    }
+    Ok(builder.build())
+}
+
+// =============================================================================
+// Glyph Outlines
+// =============================================================================
+
+/// Closed contours of a glyph as polygons, in world units
+#[derive(Default)]
+struct GlyphOutline {
+    /// Pen position the glyph is drawn at
+    origin: Vec2,
+    /// World units per font unit
+    scale: f32,
+    contours: Vec<Vec<Vec2>>,
+    current: Vec<Vec2>,
+}
+
+impl GlyphOutline {
+    fn point(&self, x: f32, y: f32) -> Vec2 {
+        self.origin + Vec2::new(x, y) * self.scale
+    }
+
+    fn last(&self) -> Vec2 {
+        self.current.last().copied().unwrap_or(self.origin)
+    }
+
+    /// Add the points of a curve from the last point, evaluated by `at`
+    /// for `t` in `0..=1`
+    fn curve(&mut self, at: impl Fn(f32) -> Vec2) {
+        for segment in 1..=CURVE_SEGMENTS {
+            let point = at(segment as f32 / CURVE_SEGMENTS as f32);
+            self.current.push(point);
+        }
+    }
+}
+
+impl OutlineBuilder for GlyphOutline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.close();
+        self.current.push(self.point(x, y));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let point = self.point(x, y);
+        self.current.push(point);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (from, control, to) = (self.last(), self.point(x1, y1), self.point(x, y));
+        self.curve(|t| from.lerp(control, t).lerp(control.lerp(to, t), t));
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (from, to) = (self.last(), self.point(x, y));
+        let (control1, control2) = (self.point(x1, y1), self.point(x2, y2));
+        self.curve(|t| {
+            let (a, b, c) = (
+                from.lerp(control1, t),
+                control1.lerp(control2, t),
+                control2.lerp(to, t),
+            );
+            a.lerp(b, t).lerp(b.lerp(c, t), t)
+        });
+    }
+
+    fn close(&mut self) {
+        let mut contour = std::mem::take(&mut self.current);
+        // Outlines often end on their starting point
+        if contour.len() > 1 && contour.first() == contour.last() {
+            contour.pop();
+        }
+        if contour.len() >= 3 {
+            self.contours.push(contour);
+        }
+    }
+}
+
+// =============================================================================
+// Extrusion
+// =============================================================================
+
+/// Unindexed triangles of the extruded glyphs, each with its face normal
+#[derive(Default)]
+struct ExtrusionBuilder {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+}
+
+impl ExtrusionBuilder {
+    /// Add the front, back and side walls of a glyph made of `contours`
+    fn add_glyph(&mut self, contours: &[Vec<Vec2>], depth: f32) -> Result<(), String> {
+        let (front, back) = (depth / 2.0, -depth / 2.0);
+
+        // Fonts wind outer contours one way and holes the other, which way
+        // depending on the format; the signed area of the whole glyph tells
+        let area: f32 = contours.iter().map(|contour| signed_area(contour)).sum();
+        let filled_left = area > 0.0;
+
+        let mut path = OutlinePath::builder();
+        for contour in contours {
+            path.begin(point(contour[0].x, contour[0].y));
+            for p in &contour[1..] {
+                path.line_to(point(p.x, p.y));
+            }
+            path.end(true);
+        }
+        let mut geometry: VertexBuffers<Vec2, u32> = VertexBuffers::new();
+        FillTessellator::new()
+            .tessellate_path(
+                &path.build(),
+                &FillOptions::default().with_fill_rule(FillRule::NonZero),
+                &mut BuffersBuilder::new(&mut geometry, |vertex: FillVertex| {
+                    Vec2::new(vertex.position().x, vertex.position().y)
+                }),
+            )
+            .map_err(|e| format!("Failed to triangulate glyph: {:?}", e))?;
+        for triangle in geometry.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| geometry.vertices[triangle[i] as usize]);
+            self.triangle([a.extend(front), b.extend(front), c.extend(front)], Vec3::Z);
+            self.triangle(
+                [a.extend(back), b.extend(back), c.extend(back)],
+                Vec3::NEG_Z,
+            );
+        }
+
+        for contour in contours {
+            for (i, &a) in contour.iter().enumerate() {
+                let b = contour[(i + 1) % contour.len()];
+                let along = b - a;
+                let outward = if filled_left {
+                    Vec2::new(along.y, -along.x)
+                } else {
+                    Vec2::new(-along.y, along.x)
+                };
+                let Some(normal) = outward.extend(0.0).try_normalize() else {
+                    continue;
+                };
+                let (a_front, b_front) = (a.extend(front), b.extend(front));
+                let (a_back, b_back) = (a.extend(back), b.extend(back));
+                self.triangle([a_front, a_back, b_front], normal);
+                self.triangle([b_front, a_back, b_back], normal);
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a triangle facing along `normal`, whatever the order of
+    /// `corners`
+    fn triangle(&mut self, mut corners: [Vec3; 3], normal: Vec3) {
+        let facing = (corners[1] - corners[0]).cross(corners[2] - corners[0]);
+        if facing.dot(normal) < 0.0 {
+            corners.swap(1, 2);
+        }
+        for corner in corners {
+            self.positions.push(corner.to_array());
+            self.normals.push(normal.to_array());
+        }
+    }
+
+    /// Mesh of the triangles, moved so the text is centered on the origin
+    fn build(mut self) -> Mesh {
+        let (min, max) = self.positions.iter().fold(
+            (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+            |(min, max), p| (min.min(Vec3::from(*p)), max.max(Vec3::from(*p))),
+        );
+        let center = (min + max) / 2.0;
+        let extent = (max - min).max(Vec3::splat(f32::EPSILON));
+        let mut uvs = Vec::with_capacity(self.positions.len());
+        for position in &mut self.positions {
+            let p = Vec3::from(*position) - center;
+            *position = p.to_array();
+            // Planar from the front, so a texture reads like the text
+            uvs.push([p.x / extent.x + 0.5, 0.5 - p.y / extent.y]);
+        }
+        let indices = (0..self.positions.len() as u32).collect();
+
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_indices(Indices::U32(indices))
+    }
+}
+
+/// Twice the signed area of `contour`, positive if counter-clockwise
+fn signed_area(contour: &[Vec2]) -> f32 {
+    contour
+        .iter()
+        .zip(contour.iter().cycle().skip(1))
+        .map(|(a, b)| a.perp_dot(*b))
+        .sum()
+}
@@ -0,0 +1,133 @@
+//! Turntable rendering
+//!
+//! A turntable export is rendered by a second camera circling the orbit
+//! center, into its own target at the render resolution, like a screenshot.
+//! The camera only moves on once the frame for the current step was read
+//! back and handed over, so every step ends up in the export however slowly
+//! it is written. Scene time is set per step instead of following the clock,
+//! which advances the animation by exactly `1 / fps` per frame.
+
+use bevy::{
+    prelude::*,
+    render::gpu_readback::{Readback, ReadbackComplete},
+};
+use crossbeam_channel::Sender;
+use std::f32::consts::TAU;
+
+use crate::bevy::components::TurntableCapture;
+use crate::bevy::resources::{BufferPoolRes, OrbitCameraState, RenderSettings, SceneTime};
+use crate::bevy::systems::frame_extraction::remove_row_padding;
+use crate::bevy::systems::screenshot::spawn_capture_camera;
+use crate::config::turntable::SETTLE_FRAMES;
+use crate::tauri_bridge::shared_state::{CapturedImage, PixelFormat};
+
+/// Start rendering `frames` views around the current orbit center
+///
+/// Each finished frame is sent to `frames_out` by [`advance_turntable`].
+/// The channel is closed after the last one.
+pub fn start_turntable(
+    world: &mut World,
+    frames: u32,
+    fps: f64,
+    frames_out: Sender<Result<CapturedImage, String>>,
+) {
+    let mut running = world.query::<&TurntableCapture>();
+    if running.iter(world).next().is_some() {
+        let _ = frames_out.send(Err("A turntable export is already running".to_string()));
+        return;
+    }
+
+    let settings = *world.resource::<RenderSettings>();
+    let orbit = *world.resource::<OrbitCameraState>();
+    let start_time_s = world.resource::<SceneTime>().0;
+    let Some((camera, image)) = spawn_capture_camera(world, settings.width, settings.height) else {
+        let _ = frames_out.send(Err("No camera to capture".to_string()));
+        return;
+    };
+
+    let capture = TurntableCapture {
+        camera,
+        width: settings.width,
+        height: settings.height,
+        orbit,
+        start_time_s,
+        fps,
+        frame: 0,
+        frames,
+        settle_frames: SETTLE_FRAMES,
+        frames_out,
+    };
+    let (transform, _) = step(&capture);
+    world.entity_mut(camera).insert(transform);
+    world.spawn((Readback::texture(image), capture));
+    println!(
+        "[Bevy] Rendering {}-frame turntable at {}x{}",
+        frames, settings.width, settings.height
+    );
+}
+
+/// Send a finished turntable frame and move the camera to the next step
+///
+/// Removes the camera and target after the last frame, or when the
+/// receiving side went away.
+pub fn advance_turntable(
+    mut event: On<ReadbackComplete>,
+    mut captures: Query<&mut TurntableCapture>,
+    mut transforms: Query<&mut Transform>,
+    mut scene_time: ResMut<SceneTime>,
+    buffer_pool: Res<BufferPoolRes>,
+    mut commands: Commands,
+) {
+    let Ok(mut capture) = captures.get_mut(event.entity) else {
+        return;
+    };
+    if capture.settle_frames > 0 {
+        capture.settle_frames -= 1;
+        return;
+    }
+    // Wait for the writer instead of dropping a step
+    if capture.frames_out.is_full() {
+        return;
+    }
+
+    let data = std::mem::take(&mut event.data);
+    let frame_bytes = capture.width as usize * capture.height as usize * 4;
+    let padded = data.len() > frame_bytes;
+    let result = remove_row_padding(
+        data,
+        capture.width,
+        capture.height,
+        PixelFormat::Rgba8,
+        padded,
+        &buffer_pool.0,
+    )
+    .map(|data| CapturedImage {
+        width: capture.width,
+        height: capture.height,
+        data,
+    })
+    .ok_or_else(|| "Turntable readback was empty".to_string());
+
+    let sent = capture.frames_out.send(result).is_ok();
+    capture.frame += 1;
+    if !sent || capture.frame >= capture.frames {
+        commands.entity(capture.camera).despawn();
+        commands.entity(event.entity).despawn();
+        return;
+    }
+
+    let (transform, time_s) = step(&capture);
+    if let Ok(mut camera_transform) = transforms.get_mut(capture.camera) {
+        *camera_transform = transform;
+    }
+    scene_time.0 = time_s;
+    capture.settle_frames = SETTLE_FRAMES;
+}
+
+/// Camera transform and scene time of the current step
+fn step(capture: &TurntableCapture) -> (Transform, f64) {
+    let mut orbit = capture.orbit;
+    orbit.yaw += TAU * capture.frame as f32 / capture.frames as f32;
+    let time_s = capture.start_time_s + capture.frame as f64 / capture.fps;
+    (orbit.camera_transform(), time_s)
+}
@@ -0,0 +1,274 @@
+//! Bevy UI overlay
+//!
+//! Labels, toolbars, legends and scale bars laid out by Bevy's UI into the
+//! stream camera's target, so they are in the live view and everything
+//! made from stream frames: recordings, clips and raw frame dumps.
+//! Screenshots, turntables and batch renders use their own cameras, which
+//! get a copy of the elements unless the overlay asks for clean captures.
+
+use bevy::prelude::*;
+
+use crate::bevy::components::{CameraController, ScaleBar, UiOverlayRoot};
+use crate::bevy::resources::{ActiveUiOverlay, OrbitCameraState};
+use crate::bevy::systems::camera::world_units_per_pixel;
+use crate::config::ui_overlay::{BACKGROUND_ALPHA, FONT_SIZE, MARGIN, SCALE_BAR_HEIGHT};
+use crate::tauri_bridge::overlay::Corner;
+use crate::tauri_bridge::shared_state::{UiElement, UiOverlaySettings};
+
+/// Draw `settings`' elements into the stream, replacing the previous ones,
+/// or remove them if `None`
+pub fn set_ui_overlay(world: &mut World, settings: Option<UiOverlaySettings>) {
+    let previous: Vec<Entity> = world
+        .query_filtered::<Entity, With<UiOverlayRoot>>()
+        .iter(world)
+        .collect();
+    for entity in previous {
+        world.despawn(entity);
+    }
+    world.resource_mut::<ActiveUiOverlay>().0 = settings.clone();
+    let Some(settings) = settings else {
+        println!("[Bevy] UI overlay removed");
+        return;
+    };
+
+    let mut cameras = world.query_filtered::<Entity, With<CameraController>>();
+    let Some(camera) = cameras.iter(world).next() else {
+        eprintln!("[Bevy] No stream camera to draw the UI overlay into");
+        return;
+    };
+    spawn_elements(world, camera, &settings.elements);
+    println!(
+        "[Bevy] UI overlay with {} elements{}",
+        settings.elements.len(),
+        if settings.clean_captures {
+            ", captures clean"
+        } else {
+            ""
+        }
+    );
+}
+
+/// Give a capture camera its own copy of the UI overlay, unless there is
+/// none or it asks for clean captures
+pub(crate) fn add_ui_overlay_to_capture(world: &mut World, camera: Entity) {
+    let Some(settings) = world.resource::<ActiveUiOverlay>().0.clone() else {
+        return;
+    };
+    if !settings.clean_captures {
+        spawn_elements(world, camera, &settings.elements);
+    }
+}
+
+/// Remove the overlay copies of capture cameras that are gone
+pub fn despawn_orphaned_ui_overlays(
+    roots: Query<(Entity, &UiOverlayRoot)>,
+    cameras: Query<(), With<Camera>>,
+    mut commands: Commands,
+) {
+    for (entity, root) in &roots {
+        if !cameras.contains(root.camera) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Resize scale bars to their length at the orbit center's distance from
+/// the camera they are drawn for
+pub fn update_scale_bars(
+    orbit: Res<OrbitCameraState>,
+    cameras: Query<(&Camera, &Transform, &Projection)>,
+    mut bars: Query<(&ScaleBar, &mut Node)>,
+) {
+    for (bar, mut node) in &mut bars {
+        let Ok((camera, transform, projection)) = cameras.get(bar.camera) else {
+            continue;
+        };
+        let depth = transform.translation.distance(orbit.center);
+        let Some(units_per_pixel) = world_units_per_pixel(camera, projection, depth) else {
+            continue;
+        };
+        let width = Val::Px(bar.length / units_per_pixel);
+        if node.width != width {
+            node.width = width;
+        }
+    }
+}
+
+// =============================================================================
+// Elements
+// =============================================================================
+
+/// Spawn `elements` into `camera`'s target, stacked in one column per
+/// corner
+fn spawn_elements(world: &mut World, camera: Entity, elements: &[UiElement]) {
+    let corners = [
+        Corner::TopLeft,
+        Corner::TopRight,
+        Corner::BottomLeft,
+        Corner::BottomRight,
+    ];
+    for corner in corners {
+        let mut in_corner = elements
+            .iter()
+            .filter(|element| element_corner(element) == corner)
+            .peekable();
+        if in_corner.peek().is_none() {
+            continue;
+        }
+
+        let (left, right) = match corner {
+            Corner::TopLeft | Corner::BottomLeft => (Val::Px(MARGIN), Val::Auto),
+            Corner::TopRight | Corner::BottomRight => (Val::Auto, Val::Px(MARGIN)),
+        };
+        let (top, bottom) = match corner {
+            Corner::TopLeft | Corner::TopRight => (Val::Px(MARGIN), Val::Auto),
+            Corner::BottomLeft | Corner::BottomRight => (Val::Auto, Val::Px(MARGIN)),
+        };
+        let align_items = match corner {
+            Corner::TopLeft | Corner::BottomLeft => AlignItems::Start,
+            Corner::TopRight | Corner::BottomRight => AlignItems::End,
+        };
+        let root = world
+            .spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    left,
+                    right,
+                    top,
+                    bottom,
+                    flex_direction: FlexDirection::Column,
+                    align_items,
+                    row_gap: Val::Px(MARGIN / 2.0),
+                    ..default()
+                },
+                UiTargetCamera(camera),
+                UiOverlayRoot { camera },
+                Name::new("ui_overlay"),
+            ))
+            .id();
+        for element in in_corner {
+            spawn_element(world, root, camera, element);
+        }
+    }
+}
+
+fn element_corner(element: &UiElement) -> Corner {
+    match element {
+        UiElement::Label { corner, .. }
+        | UiElement::Toolbar { corner, .. }
+        | UiElement::Legend { corner, .. }
+        | UiElement::ScaleBar { corner, .. } => *corner,
+    }
+}
+
+/// Spawn one element as a child of `root`
+fn spawn_element(world: &mut World, root: Entity, camera: Entity, element: &UiElement) {
+    let layout = match element {
+        UiElement::Label { .. } => Node::default(),
+        UiElement::Toolbar { .. } => Node {
+            column_gap: Val::Px(4.0),
+            ..default()
+        },
+        UiElement::Legend { .. } | UiElement::ScaleBar { .. } => Node {
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(4.0),
+            ..default()
+        },
+    };
+    let panel = world
+        .spawn((
+            Node {
+                padding: UiRect::all(Val::Px(6.0)),
+                ..layout
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, BACKGROUND_ALPHA)),
+            ChildOf(root),
+        ))
+        .id();
+
+    match element {
+        UiElement::Label {
+            text, font_size, ..
+        } => {
+            spawn_text(world, panel, text, font_size.unwrap_or(FONT_SIZE));
+        }
+        UiElement::Toolbar { items, .. } => {
+            for item in items {
+                let button = world
+                    .spawn((
+                        Node {
+                            padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgba(1.0, 1.0, 1.0, 0.15)),
+                        BorderRadius::all(Val::Px(4.0)),
+                        ChildOf(panel),
+                    ))
+                    .id();
+                spawn_text(world, button, item, FONT_SIZE);
+            }
+        }
+        UiElement::Legend { title, entries, .. } => {
+            if let Some(title) = title {
+                spawn_text(world, panel, title, FONT_SIZE);
+            }
+            for entry in entries {
+                let color = match entry.color.as_slice() {
+                    &[r, g, b] => Color::srgb(r, g, b),
+                    &[r, g, b, a] => Color::srgba(r, g, b, a),
+                    _ => Color::WHITE,
+                };
+                let row = world
+                    .spawn((
+                        Node {
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(6.0),
+                            ..default()
+                        },
+                        ChildOf(panel),
+                    ))
+                    .id();
+                world.spawn((
+                    Node {
+                        width: Val::Px(FONT_SIZE * 0.8),
+                        height: Val::Px(FONT_SIZE * 0.8),
+                        ..default()
+                    },
+                    BackgroundColor(color),
+                    ChildOf(row),
+                ));
+                spawn_text(world, row, &entry.label, FONT_SIZE);
+            }
+        }
+        UiElement::ScaleBar { length, label, .. } => {
+            // Sized by `update_scale_bars`
+            world.spawn((
+                Node {
+                    width: Val::Px(0.0),
+                    height: Val::Px(SCALE_BAR_HEIGHT),
+                    ..default()
+                },
+                BackgroundColor(Color::WHITE),
+                ScaleBar {
+                    length: *length,
+                    camera,
+                },
+                ChildOf(panel),
+            ));
+            let label = label.clone().unwrap_or_else(|| length.to_string());
+            spawn_text(world, panel, &label, FONT_SIZE);
+        }
+    }
+}
+
+fn spawn_text(world: &mut World, parent: Entity, text: &str, font_size: f32) {
+    world.spawn((
+        Text::new(text),
+        TextFont {
+            font_size,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        ChildOf(parent),
+    ));
+}
@@ -0,0 +1,382 @@
+//! Scene export to USD for AR Quick Look
+//!
+//! iOS and macOS preview USDZ files in AR straight from Files, Messages or
+//! Safari. This converts the visible scene, or the named entities and
+//! their descendants, into a USD stage in text form: triangle meshes with
+//! normals and UVs, and standard materials baked to `UsdPreviewSurface`.
+//! Quick Look has no equivalent of Bevy's base color factor times
+//! texture, so base color textures are baked with the factor multiplied
+//! in; textures in formats other than 8-bit RGBA fall back to the factor.
+//! Lights are left out since Quick Look lights scenes itself.
+//! `export_usdz` packages the stage and textures.
+
+use bevy::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
+use bevy::prelude::*;
+use bevy::render::render_resource::TextureFormat;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::bevy::systems::gltf_export::{visible_meshes, ExportMesh};
+use crate::tauri_bridge::shared_state::{BakedTexture, UsdScene, UsdzExportSummary};
+
+/// The visible scene as a USD stage, limited to entities named in `names`
+/// and their descendants if given
+///
+/// The summary's `path` is left for the caller to fill in.
+pub fn export_usd(world: &mut World, names: Option<Vec<String>>) -> Result<UsdScene, String> {
+    let visible = visible_meshes(world);
+    let world: &World = world;
+    let meshes = world.resource::<Assets<Mesh>>();
+    let materials = world.resource::<Assets<StandardMaterial>>();
+    let images = world.resource::<Assets<Image>>();
+    let names: Option<HashSet<String>> = names.map(|names| names.into_iter().collect());
+
+    let mut summary = UsdzExportSummary::default();
+    let mut textures: Vec<BakedTexture> = Vec::new();
+    let mut material_prims: HashMap<AssetId<StandardMaterial>, String> = HashMap::new();
+    let mut material_defs = String::new();
+    let mut prim_names = PrimNames::default();
+    let mut nodes = String::new();
+
+    for ExportMesh {
+        entity,
+        name: label,
+        transform,
+        mesh,
+        material,
+    } in visible
+    {
+        if let Some(names) = &names {
+            if !is_selected(world, entity, names) {
+                continue;
+            }
+        }
+        let Some(mesh) = meshes.get(mesh) else {
+            summary
+                .skipped
+                .push(format!("{}: mesh data is not kept on the CPU", label));
+            continue;
+        };
+        let mesh_def = match mesh_def(mesh) {
+            Ok(mesh_def) => mesh_def,
+            Err(e) => {
+                summary.skipped.push(format!("{}: {}", label, e));
+                continue;
+            }
+        };
+
+        let binding = material.and_then(|id| {
+            if let Some(prim) = material_prims.get(&id) {
+                return Some(prim.clone());
+            }
+            let material = materials.get(id)?;
+            let prim = format!("Material_{}", material_prims.len());
+            let texture = bake_base_color(material, images, &prim);
+            material_defs.push_str(&material_def(material, &prim, texture.as_ref()));
+            textures.extend(texture);
+            material_prims.insert(id, prim.clone());
+            Some(prim)
+        });
+
+        let prim = prim_names.unique(&label);
+        let _ = writeln!(nodes, "    def Xform \"{}\"", prim);
+        nodes.push_str("    {\n");
+        let _ = writeln!(
+            nodes,
+            "        matrix4d xformOp:transform = {}",
+            usd_matrix(&transform)
+        );
+        nodes.push_str("        uniform token[] xformOpOrder = [\"xformOp:transform\"]\n\n");
+        nodes.push_str("        def Mesh \"Mesh\" (\n");
+        nodes.push_str("            prepend apiSchemas = [\"MaterialBindingAPI\"]\n");
+        nodes.push_str("        )\n        {\n");
+        nodes.push_str(&mesh_def);
+        if let Some(binding) = binding {
+            let _ = writeln!(
+                nodes,
+                "            rel material:binding = </Scene/Materials/{}>",
+                binding
+            );
+        }
+        nodes.push_str("        }\n    }\n\n");
+        summary.meshes += 1;
+    }
+
+    if summary.meshes == 0 {
+        return Err("Nothing to export".to_string());
+    }
+    summary.materials = material_prims.len();
+    summary.textures = textures.len();
+
+    let mut usda = String::from(
+        "#usda 1.0\n(\n    defaultPrim = \"Scene\"\n    metersPerUnit = 1\n    upAxis = \"Y\"\n)\n\n",
+    );
+    usda.push_str("def Xform \"Scene\" (\n    kind = \"component\"\n)\n{\n");
+    usda.push_str(&nodes);
+    usda.push_str("    def Scope \"Materials\"\n    {\n");
+    usda.push_str(&material_defs);
+    usda.push_str("    }\n}\n");
+
+    println!(
+        "[Bevy] Converted {} meshes, {} materials and {} baked textures to USD ({} skipped)",
+        summary.meshes,
+        summary.materials,
+        summary.textures,
+        summary.skipped.len()
+    );
+    Ok(UsdScene {
+        usda,
+        textures,
+        summary,
+    })
+}
+
+/// Whether `entity` or one of its ancestors is named in `names`
+fn is_selected(world: &World, entity: Entity, names: &HashSet<String>) -> bool {
+    let mut current = Some(entity);
+    while let Some(entity) = current {
+        if world
+            .get::<Name>(entity)
+            .is_some_and(|name| names.contains(name.as_str()))
+        {
+            return true;
+        }
+        current = world.get::<ChildOf>(entity).map(ChildOf::parent);
+    }
+    false
+}
+
+/// USD prim names, which must be identifiers and unique among siblings
+#[derive(Default)]
+struct PrimNames(HashSet<String>);
+
+impl PrimNames {
+    fn unique(&mut self, name: &str) -> String {
+        let mut base: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        if !base.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            base.insert(0, '_');
+        }
+        let mut unique = base.clone();
+        let mut suffix = 1;
+        while !self.0.insert(unique.clone()) {
+            unique = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+        unique
+    }
+}
+
+/// World transform as a USD matrix, whose rows are Bevy's columns
+fn usd_matrix(transform: &GlobalTransform) -> String {
+    let rows: Vec<String> = transform
+        .to_matrix()
+        .to_cols_array_2d()
+        .iter()
+        .map(|row| format!("({}, {}, {}, {})", row[0], row[1], row[2], row[3]))
+        .collect();
+    format!("( {} )", rows.join(", "))
+}
+
+/// Attributes of a `Mesh` prim for a triangle list
+fn mesh_def(mesh: &Mesh) -> Result<String, String> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return Err("not a triangle list".to_string());
+    }
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return Err("no float positions".to_string());
+    };
+    let indices: Vec<u32> = match mesh.indices() {
+        Some(Indices::U16(indices)) => indices.iter().map(|&index| index as u32).collect(),
+        Some(Indices::U32(indices)) => indices.clone(),
+        None => (0..positions.len() as u32).collect(),
+    };
+
+    let mut def = String::new();
+    let counts = vec!["3"; indices.len() / 3].join(", ");
+    let _ = writeln!(def, "            int[] faceVertexCounts = [{}]", counts);
+    let _ = writeln!(
+        def,
+        "            int[] faceVertexIndices = [{}]",
+        join(indices.iter().map(u32::to_string))
+    );
+    let _ = writeln!(
+        def,
+        "            point3f[] points = [{}]",
+        join(
+            positions
+                .iter()
+                .map(|p| format!("({}, {}, {})", p[0], p[1], p[2]))
+        )
+    );
+    if let Some(VertexAttributeValues::Float32x3(normals)) = mesh.attribute(Mesh::ATTRIBUTE_NORMAL)
+    {
+        let _ = writeln!(
+            def,
+            "            normal3f[] normals = [{}] (\n                interpolation = \"vertex\"\n            )",
+            join(normals.iter().map(|n| format!("({}, {}, {})", n[0], n[1], n[2])))
+        );
+    }
+    if let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+        // USD has V pointing up, Bevy down
+        let _ = writeln!(
+            def,
+            "            texCoord2f[] primvars:st = [{}] (\n                interpolation = \"vertex\"\n            )",
+            join(uvs.iter().map(|uv| format!("({}, {})", uv[0], 1.0 - uv[1])))
+        );
+    }
+    def.push_str("            uniform token subdivisionScheme = \"none\"\n");
+    Ok(def)
+}
+
+fn join(values: impl Iterator<Item = String>) -> String {
+    values.collect::<Vec<_>>().join(", ")
+}
+
+/// The material's base color texture with its base color multiplied in,
+/// if it has one in 8-bit RGBA
+fn bake_base_color(
+    material: &StandardMaterial,
+    images: &Assets<Image>,
+    prim: &str,
+) -> Option<BakedTexture> {
+    let image = images.get(material.base_color_texture.as_ref()?.id())?;
+    if !matches!(
+        image.texture_descriptor.format,
+        TextureFormat::Rgba8UnormSrgb | TextureFormat::Rgba8Unorm
+    ) {
+        return None;
+    }
+    // Only the first layer and mip level
+    let size = (image.width() * image.height() * 4) as usize;
+    let data = image.data.as_ref().filter(|data| data.len() >= size)?;
+
+    // Multiplied in linear space like the renderer does, then stored as
+    // sRGB, which Quick Look reads base color textures as
+    let srgb = image.texture_descriptor.format == TextureFormat::Rgba8UnormSrgb;
+    let factor = material.base_color.to_linear();
+    let rgba = data[..size]
+        .chunks_exact(4)
+        .flat_map(|pixel| {
+            let [red, green, blue, alpha] =
+                std::array::from_fn(|channel| pixel[channel] as f32 / 255.0);
+            let texel = if srgb {
+                LinearRgba::from(Srgba::new(red, green, blue, alpha))
+            } else {
+                LinearRgba::new(red, green, blue, alpha)
+            };
+            Srgba::from(LinearRgba::new(
+                texel.red * factor.red,
+                texel.green * factor.green,
+                texel.blue * factor.blue,
+                texel.alpha * factor.alpha,
+            ))
+            .to_u8_array()
+        })
+        .collect();
+    Some(BakedTexture {
+        name: format!("textures/{}.png", prim),
+        width: image.width(),
+        height: image.height(),
+        rgba,
+    })
+}
+
+/// A `Material` prim with a `UsdPreviewSurface` matching `material`
+fn material_def(material: &StandardMaterial, prim: &str, texture: Option<&BakedTexture>) -> String {
+    let path = format!("/Scene/Materials/{}", prim);
+    let base = material.base_color.to_linear();
+    let emissive = material.emissive;
+    let transparent = !matches!(material.alpha_mode, AlphaMode::Opaque);
+
+    let mut def = String::new();
+    let _ = writeln!(def, "        def Material \"{}\"", prim);
+    def.push_str("        {\n");
+    let _ = writeln!(
+        def,
+        "            token outputs:surface.connect = <{}/Surface.outputs:surface>",
+        path
+    );
+    def.push_str("\n            def Shader \"Surface\"\n            {\n");
+    def.push_str("                uniform token info:id = \"UsdPreviewSurface\"\n");
+    match texture {
+        Some(_) => {
+            let _ = writeln!(
+                def,
+                "                color3f inputs:diffuseColor.connect = <{}/BaseColor.outputs:rgb>",
+                path
+            );
+            if transparent {
+                let _ = writeln!(
+                    def,
+                    "                float inputs:opacity.connect = <{}/BaseColor.outputs:a>",
+                    path
+                );
+            }
+        }
+        None => {
+            let _ = writeln!(
+                def,
+                "                color3f inputs:diffuseColor = ({}, {}, {})",
+                base.red, base.green, base.blue
+            );
+            if transparent {
+                let _ = writeln!(def, "                float inputs:opacity = {}", base.alpha);
+            }
+        }
+    }
+    if let AlphaMode::Mask(cutoff) = material.alpha_mode {
+        let _ = writeln!(
+            def,
+            "                float inputs:opacityThreshold = {}",
+            cutoff
+        );
+    }
+    let _ = writeln!(
+        def,
+        "                float inputs:metallic = {}",
+        material.metallic
+    );
+    let _ = writeln!(
+        def,
+        "                float inputs:roughness = {}",
+        material.perceptual_roughness
+    );
+    let _ = writeln!(
+        def,
+        "                color3f inputs:emissiveColor = ({}, {}, {})",
+        emissive.red, emissive.green, emissive.blue
+    );
+    def.push_str("                token outputs:surface\n            }\n");
+
+    if let Some(texture) = texture {
+        def.push_str("\n            def Shader \"TexCoords\"\n            {\n");
+        def.push_str("                uniform token info:id = \"UsdPrimvarReader_float2\"\n");
+        def.push_str("                string inputs:varname = \"st\"\n");
+        def.push_str("                float2 outputs:result\n            }\n");
+        def.push_str("\n            def Shader \"BaseColor\"\n            {\n");
+        def.push_str("                uniform token info:id = \"UsdUVTexture\"\n");
+        let _ = writeln!(
+            def,
+            "                asset inputs:file = @{}@",
+            texture.name
+        );
+        def.push_str("                token inputs:sourceColorSpace = \"sRGB\"\n");
+        let _ = writeln!(
+            def,
+            "                float2 inputs:st.connect = <{}/TexCoords.outputs:result>",
+            path
+        );
+        def.push_str("                token inputs:wrapS = \"repeat\"\n");
+        def.push_str("                token inputs:wrapT = \"repeat\"\n");
+        def.push_str(
+            "                float3 outputs:rgb\n                float outputs:a\n            }\n",
+        );
+    }
+    def.push_str("        }\n");
+    def
+}
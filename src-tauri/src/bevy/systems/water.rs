@@ -0,0 +1,209 @@
+//! Reflective water plane
+//!
+//! The water needs an extra render pass every frame: a reflection camera
+//! follows the stream camera mirrored below the water plane and renders
+//! into its own target, which the water samples. The water itself is on a
+//! render layer only the stream camera sees, so the reflection doesn't
+//! contain it. Anything below the water shows up in the reflection too,
+//! since the mirrored view isn't clipped at the plane. The reflection
+//! target is sized from the stream resolution, so streaming smaller makes
+//! the extra pass cheaper, and the reflection camera lights and fogs the
+//! scene like the stream camera.
+
+use bevy::{
+    camera::{visibility::RenderLayers, Exposure, RenderTarget},
+    core_pipeline::{tonemapping::Tonemapping, Skybox},
+    light::NotShadowCaster,
+    prelude::*,
+    render::render_resource::TextureFormat,
+};
+
+use crate::bevy::components::{CameraController, ReflectionCamera, WaterPlane};
+use crate::bevy::plugins::water::{water_material, WaterMaterial};
+use crate::bevy::resources::StreamResolution;
+use crate::bevy::systems::camera::{scene_bounds, show_render_layer};
+use crate::config::water::{COLOR, REFLECTION_SCALE, RENDER_LAYER, SIZE, WAVE_STRENGTH};
+use crate::tauri_bridge::shared_state::WaterSettings;
+
+/// Add a water plane under the scene, replacing the previous one, or
+/// remove it if `settings` is `None`
+pub fn set_water(world: &mut World, settings: Option<WaterSettings>) {
+    let previous: Vec<Entity> = world
+        .query_filtered::<Entity, Or<(With<WaterPlane>, With<ReflectionCamera>)>>()
+        .iter(world)
+        .collect();
+    for entity in previous {
+        world.despawn(entity);
+    }
+    let Some(settings) = settings else {
+        show_render_layer(world, RENDER_LAYER, false);
+        println!("[Bevy] Water removed");
+        return;
+    };
+
+    let mut cameras = world.query_filtered::<(
+        &Camera,
+        &Tonemapping,
+        &Exposure,
+        Option<&EnvironmentMapLight>,
+        Option<&Skybox>,
+        Option<&DistanceFog>,
+    ), With<CameraController>>();
+    let Some((stream_camera, tonemapping, exposure, environment, skybox, fog)) =
+        cameras.iter(world).next().map(
+            |(camera, tonemapping, exposure, environment, skybox, fog)| {
+                (
+                    camera.clone(),
+                    *tonemapping,
+                    *exposure,
+                    environment.cloned(),
+                    skybox.cloned(),
+                    fog.cloned(),
+                )
+            },
+        )
+    else {
+        eprintln!("[Bevy] No stream camera to reflect");
+        return;
+    };
+
+    // Centered under the scene, at its lowest point unless told otherwise
+    let (min, max) = scene_bounds(world).unwrap_or((Vec3::ZERO, Vec3::ZERO));
+    let center = (min + max) / 2.0;
+    let height = settings.height.unwrap_or(min.y);
+    let size = settings.size.unwrap_or(SIZE);
+    let color = match settings.color.as_deref() {
+        Some(&[r, g, b]) => Color::srgb(r, g, b),
+        _ => Color::srgb(COLOR[0], COLOR[1], COLOR[2]),
+    };
+    let wave_strength = settings.wave_strength.unwrap_or(WAVE_STRENGTH);
+
+    let image = reflection_image(*world.resource::<StreamResolution>());
+    let (width, height_px) = (image.width(), image.height());
+    let reflection = world.resource_mut::<Assets<Image>>().add(image);
+
+    let mut camera = world.spawn((
+        Camera3d::default(),
+        Camera {
+            target: RenderTarget::Image(reflection.clone().into()),
+            // Before the stream camera, which samples the reflection
+            order: stream_camera.order - 1,
+            viewport: None,
+            ..stream_camera
+        },
+        tonemapping,
+        exposure,
+        Msaa::Off,
+        Transform::default(),
+        RenderLayers::layer(0),
+        ReflectionCamera { height },
+        Name::new("reflection_camera"),
+    ));
+    // Changed along with the stream camera's by `set_environment_map` and
+    // `set_fog`
+    if let Some(environment) = environment {
+        camera.insert(environment);
+    }
+    if let Some(skybox) = skybox {
+        camera.insert(skybox);
+    }
+    if let Some(fog) = fog {
+        camera.insert(fog);
+    }
+    show_render_layer(world, RENDER_LAYER, true);
+
+    let mesh = world
+        .resource_mut::<Assets<Mesh>>()
+        .add(Plane3d::default().mesh().size(size, size));
+    let material = world
+        .resource_mut::<Assets<WaterMaterial>>()
+        .add(water_material(color, wave_strength, reflection));
+    world.spawn((
+        Mesh3d(mesh),
+        MeshMaterial3d(material),
+        Transform::from_xyz(center.x, height, center.z),
+        NotShadowCaster,
+        RenderLayers::layer(RENDER_LAYER),
+        WaterPlane,
+        Name::new("water"),
+    ));
+    println!(
+        "[Bevy] Water at height {:.2}, reflection {}x{}",
+        height, width, height_px
+    );
+}
+
+/// Target for the reflection pass at `REFLECTION_SCALE` of `resolution`
+fn reflection_image(resolution: StreamResolution) -> Image {
+    let width = ((resolution.width as f32 * REFLECTION_SCALE) as u32).max(1);
+    let height = ((resolution.height as f32 * REFLECTION_SCALE) as u32).max(1);
+    Image::new_target_texture(width, height, TextureFormat::bevy_default())
+}
+
+/// Replace the reflection target when the stream resolution changes
+///
+/// The water's material is pointed at the new target, and the old one is
+/// freed once neither holds it.
+pub fn resize_reflection_target(
+    resolution: Res<StreamResolution>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<WaterMaterial>>,
+    mut reflection_cameras: Query<&mut Camera, With<ReflectionCamera>>,
+    water: Query<&MeshMaterial3d<WaterMaterial>, With<WaterPlane>>,
+) {
+    if !resolution.is_changed() || reflection_cameras.is_empty() {
+        return;
+    }
+    let image = reflection_image(*resolution);
+    let (width, height) = (image.width(), image.height());
+    let reflection = images.add(image);
+    for mut camera in &mut reflection_cameras {
+        camera.target = RenderTarget::Image(reflection.clone().into());
+    }
+    for material in &water {
+        if let Some(material) = materials.get_mut(&material.0) {
+            material.extension.reflection = reflection.clone();
+        }
+    }
+    println!("[Bevy] Water reflection resized to {}x{}", width, height);
+}
+
+/// Keep the reflection camera mirrored to the stream camera
+///
+/// Mirroring flips handedness, which a camera transform can't express,
+/// so the reflection camera looks along the mirrored direction with the
+/// mirrored up and renders the reflection flipped left to right; the
+/// water shader flips it back.
+pub fn update_reflection_camera(
+    stream_cameras: Query<
+        (&Camera, &Transform, &Projection),
+        (With<CameraController>, Without<ReflectionCamera>),
+    >,
+    mut reflection_cameras: Query<
+        (
+            &ReflectionCamera,
+            &mut Camera,
+            &mut Transform,
+            &mut Projection,
+        ),
+        Without<CameraController>,
+    >,
+) {
+    let Some((stream_camera, transform, projection)) = stream_cameras.iter().next() else {
+        return;
+    };
+    let mirror = |v: Vec3| Vec3::new(v.x, -v.y, v.z);
+    for (reflection, mut camera, mut reflection_transform, mut reflection_projection) in
+        &mut reflection_cameras
+    {
+        let mut position = transform.translation;
+        position.y = 2.0 * reflection.height - position.y;
+        *reflection_transform = Transform::from_translation(position).looking_to(
+            mirror(transform.forward().as_vec3()),
+            mirror(transform.up().as_vec3()),
+        );
+        *reflection_projection = projection.clone();
+        // Follow background changes, e.g. `set_transparent_background`
+        camera.clear_color = stream_camera.clear_color;
+    }
+}
@@ -21,6 +21,13 @@ pub mod camera {
     /// Rotation speed multiplier for mouse drag
     pub const ROTATION_SPEED: f32 = 0.005;
 
+    /// Roll speed multiplier for horizontal right-button drag
+    pub const ROLL_SPEED: f32 = 0.005;
+
+    /// Pan speed multiplier for middle-button drag, per unit of orbit
+    /// distance
+    pub const PAN_SPEED: f32 = 0.0015;
+
     /// Zoom speed multiplier for scroll wheel
     pub const ZOOM_SPEED: f32 = 0.5;
 
@@ -35,6 +42,60 @@ pub mod camera {
 
     /// Minimum pitch angle (radians) to prevent camera flipping
     pub const MIN_PITCH: f32 = -1.5;
+
+    /// Duration of animated camera moves to presets, entities, standard
+    /// views and picked points (seconds), changed with
+    /// `set_camera_transition`
+    pub const TRANSITION_SECONDS: f32 = 0.6;
+
+    /// Default camera shake: peak angle (degrees), oscillation frequency
+    /// (Hz) and exponential decay rate (per second, `0` to shake until
+    /// stopped)
+    pub const SHAKE_AMPLITUDE_DEG: f32 = 0.8;
+    pub const SHAKE_FREQUENCY_HZ: f32 = 6.0;
+    pub const SHAKE_DECAY: f32 = 1.5;
+
+    /// Default push-in: fraction of the orbit distance moved in, and over
+    /// how many seconds
+    pub const PUSH_IN_AMOUNT: f32 = 0.2;
+    pub const PUSH_IN_SECONDS: f32 = 4.0;
+
+    /// Space left around the scene by `frame_all`, as a fraction of its
+    /// bounding radius
+    pub const FRAME_MARGIN: f32 = 0.15;
+
+    /// File in the app config directory camera presets are saved to
+    pub const PRESETS_FILE: &str = "camera_presets.json";
+}
+
+/// Adaptive frame rate settings
+///
+/// When clients fetch frames slower than Bevy renders them, the render and
+/// readback rate is lowered to match demand, and raised again when demand
+/// returns.
+pub mod adaptive {
+    /// Whether the frame rate adapts to consumer demand
+    pub const ENABLED: bool = true;
+
+    /// Lowest frame rate the renderer drops to when nobody is fetching
+    pub const MIN_FPS: f64 = 5.0;
+
+    /// How often demand is re-evaluated (seconds)
+    pub const EVALUATION_INTERVAL: f64 = 0.5;
+
+    /// Render this much faster than the measured fetch rate
+    pub const HEADROOM: f64 = 1.25;
+
+    /// Multiplier applied to the target rate when demand catches up
+    pub const RAMP_UP_FACTOR: f64 = 1.5;
+}
+
+/// Deterministic simulation settings, also changed with `set_fixed_timestep`
+pub mod simulation {
+    /// Advance time by exactly `1 / FIXED_FPS` per frame instead of
+    /// following the wall clock, so animation is frame-exact on every
+    /// machine. `None` follows the wall clock.
+    pub const FIXED_FPS: Option<f64> = None;
 }
 
 /// Performance monitoring settings
@@ -45,8 +106,176 @@ pub mod performance {
     /// Number of frame timing samples to keep for averaging
     pub const FRAME_TIMING_SAMPLES: usize = 60;
 
+    /// Number of frame intervals kept for the frame time percentiles
+    ///
+    /// About five seconds at 60 FPS, long enough for p99 to catch
+    /// occasional stutters.
+    pub const FRAME_INTERVAL_SAMPLES: usize = 300;
+
+    /// Interval between performance stats history samples (seconds)
+    pub const STATS_HISTORY_INTERVAL: f64 = 0.25;
+
+    /// How far back the performance stats history goes (seconds)
+    pub const STATS_HISTORY_SECONDS: f64 = 300.0;
+
+    /// Default frame time above which a frame counts as slow for
+    /// `watch_slow_frames` (milliseconds)
+    pub const SLOW_FRAME_MS: f64 = 50.0;
+
+    /// Default number of consecutive slow frames before a `slow-frames`
+    /// event is emitted
+    pub const SLOW_FRAME_COUNT: u32 = 10;
+
     /// Number of frontend performance samples to keep
     pub const FRONTEND_PERF_SAMPLES: usize = 30;
+
+    /// Interval for refreshing GPU memory statistics (frames)
+    pub const MEMORY_REPORT_INTERVAL_FRAMES: u32 = 60;
+
+    /// Skip publishing frames identical to the previous one
+    ///
+    /// Each frame is hashed after readback; a static scene with a still
+    /// camera then costs no encoding or transport.
+    pub const SKIP_IDENTICAL_FRAMES: bool = true;
+}
+
+/// Frame pacing settings for the app runner
+pub mod pacing {
+    use std::time::Duration;
+
+    /// Time before a frame deadline at which the runner stops sleeping and
+    /// spins instead, to absorb OS sleep overshoot
+    pub const SPIN_THRESHOLD: Duration = Duration::from_micros(1500);
+
+    /// Frames the runner may fall behind before it resets its schedule
+    /// instead of catching up
+    pub const MAX_LAG_FRAMES: u32 = 2;
+}
+
+/// Streaming resolution settings
+pub mod stream {
+    /// Default width of streamed frames in pixels
+    ///
+    /// When smaller than the render target, frames are downscaled on the GPU
+    /// before readback, which cuts the readback and encode cost.
+    pub const STREAM_WIDTH: u32 = super::RENDER_WIDTH;
+
+    /// Default height of streamed frames in pixels
+    pub const STREAM_HEIGHT: u32 = super::RENDER_HEIGHT;
+}
+
+/// Latency measurement settings
+pub mod latency {
+    /// Stamp the low bits of each frame id into the top-left corner
+    ///
+    /// Bits are drawn as 8x8 black/white blocks so they survive JPEG
+    /// compression, letting external tools (e.g. a camera pointed at the
+    /// screen) identify displayed frames without access to HTTP headers.
+    pub const STAMP_FRAME_ID: bool = false;
+
+    /// Number of frame id bits stamped into the corner
+    pub const STAMP_BITS: u32 = 16;
+
+    /// Size of one stamped bit in pixels
+    pub const STAMP_BLOCK_SIZE: u32 = 8;
+
+    /// Number of display latency reports kept for the percentiles
+    pub const DISPLAY_LATENCY_SAMPLES: usize = 240;
+}
+
+/// Bevy thread scheduling settings
+///
+/// On small CPUs the render threads compete with the webview's compositor.
+/// These settings trade Bevy throughput for a smoother UI.
+pub mod threading {
+    use crate::platform::ThreadPriority;
+
+    /// Priority of the thread running the Bevy schedule and render loop
+    pub const BEVY_THREAD_PRIORITY: ThreadPriority = ThreadPriority::Normal;
+
+    /// Priority of Bevy's compute, async compute and IO pool threads
+    pub const TASK_POOL_PRIORITY: ThreadPriority = ThreadPriority::Normal;
+
+    /// Logical core to pin the Bevy main thread to, `None` to let the OS decide
+    pub const BEVY_THREAD_CORE: Option<usize> = None;
+
+    /// Logical cores left out of Bevy's task pools for the webview
+    pub const RESERVED_CORES: usize = 1;
+}
+
+/// GPU selection and error reporting settings
+///
+/// Backend and power preference can be overridden with `--gpu-backend`
+/// and `--gpu-power`, see `bevy::gpu`.
+pub mod gpu {
+    use crate::bevy::gpu::{GpuBackend, GpuPower};
+
+    /// Graphics API to use, `None` to let wgpu pick the best available
+    pub const BACKEND: Option<GpuBackend> = None;
+
+    /// Adapter preference, `None` for Bevy's default (high performance)
+    pub const POWER_PREFERENCE: Option<GpuPower> = None;
+
+    /// wgpu errors kept for `get_gpu_errors`
+    pub const MAX_REPORTED_ERRORS: usize = 32;
+}
+
+/// Profiling settings
+pub mod profiling {
+    /// Maximum number of spans kept in one trace capture
+    ///
+    /// Roughly 100 bytes each, so a capture is capped at a few hundred MB.
+    pub const MAX_TRACE_EVENTS: usize = 2_000_000;
+}
+
+/// Log output settings
+pub mod logging {
+    /// Log filter at startup, changed at runtime with `set_log_level`
+    ///
+    /// Same as Bevy's `LogPlugin` default: wgpu and naga are very chatty
+    /// below these levels.
+    pub const DEFAULT_FILTER: &str = "info,wgpu=error,naga=warn";
+}
+
+/// GPU readback settings
+pub mod readback {
+    use crate::tauri_bridge::shared_state::PixelFormat;
+
+    /// Number of staging buffers per image copier
+    ///
+    /// With more than one buffer, the copy for frame N+1 can be recorded while
+    /// frame N is still being mapped and read on the CPU, so readback no longer
+    /// stalls the render loop.
+    pub const STAGING_BUFFER_COUNT: usize = 3;
+
+    /// Maximum number of idle frame buffers kept for reuse
+    ///
+    /// Enough to cover the staging ring, the published frame, and a few
+    /// in-flight protocol requests without holding on to memory forever.
+    pub const MAX_POOLED_BUFFERS: usize = 8;
+
+    /// Pixel layout frames are read back in
+    ///
+    /// `Rgb8` drops alpha in a GPU compute pass, so readback moves 25% less
+    /// data and the JPEG encoder can use the frame without converting it.
+    /// Use `Rgba8` when raw frames are drawn straight into a canvas, or
+    /// `Rgb565` to halve raw frame size on low-bandwidth links. Can be
+    /// changed at runtime with the `set_stream_format` command.
+    pub const PIXEL_FORMAT: PixelFormat = PixelFormat::Rgb8;
+
+    /// Cap on frame data held by the pipeline, in megabytes
+    ///
+    /// Covers queued readbacks, pooled buffers, the published frame and the
+    /// encoded frame. Above it, pooled buffers and then the oldest queued
+    /// readbacks are dropped, so a stalled consumer cannot grow memory
+    /// without bound.
+    pub const PIPELINE_MEMORY_LIMIT_MB: usize = 64;
+
+    /// Views remembered for readbacks still in flight
+    ///
+    /// Readbacks normally complete a frame or two after rendering, this
+    /// only bounds the queue if they stop completing.
+    pub const MAX_PENDING_VIEWS: usize = 16;
 }
 
 /// Image compression settings
@@ -54,3 +283,588 @@ pub mod compression {
     /// JPEG quality level (0-100, higher = better quality but larger size)
     pub const JPEG_QUALITY: u8 = 85;
 }
+
+/// Stream clients with their own settings, see `register_stream_client`
+pub mod stream_clients {
+    use std::time::Duration;
+
+    /// Most clients registered at once
+    pub const MAX_CLIENTS: usize = 8;
+
+    /// Clients that haven't fetched a frame for this long aren't encoded
+    /// for until they fetch again
+    pub const IDLE_AFTER: Duration = Duration::from_secs(2);
+}
+
+/// `frame://` response header defaults, overridden with
+/// `AppBuilder::protocol_headers`
+pub mod protocol {
+    /// Origins allowed to read responses, `"*"` for any
+    ///
+    /// A request's `Origin` is echoed back if it is in the list.
+    pub const ALLOW_ORIGINS: &[&str] = &["*"];
+
+    /// `Cache-Control` added to every response, none if `None`
+    pub const CACHE_CONTROL: Option<&str> = None;
+
+    /// Whether `Access-Control-Expose-Headers` is sent, letting scripts
+    /// read frame metadata headers such as `X-Frame-Id`
+    pub const EXPOSE_HEADERS: bool = true;
+}
+
+/// Video recording settings for `start_recording`
+pub mod recording {
+    use std::time::Duration;
+
+    /// Frame rate used when `start_recording` does not specify one
+    ///
+    /// Frames published faster than this are skipped; slower ones are held
+    /// on screen until the next one arrives.
+    pub const DEFAULT_FPS: f32 = 30.0;
+
+    /// Bitrate used when `start_recording` does not specify one
+    pub const DEFAULT_BITRATE: u32 = 8_000_000;
+
+    /// rav1e speed preset for WebM recordings, 10 is the fastest
+    ///
+    /// Slower presets compress better but can't keep up with a live
+    /// stream at the render resolution.
+    pub const WEBM_SPEED_PRESET: u8 = 10;
+
+    /// How often a `recording-progress` event is emitted
+    pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+}
+
+/// Clip buffer settings for `export_clip`
+pub mod clip {
+    /// Seconds of the stream kept for export
+    pub const BUFFER_SECONDS: f64 = 10.0;
+
+    /// Frame rate frames are buffered at
+    ///
+    /// GIF delays are in hundredths of a second, so rates above 50fps
+    /// would not play back any faster anyway.
+    pub const CLIP_FPS: f64 = 15.0;
+
+    /// Frames wider than this are scaled down before buffering
+    ///
+    /// 10s at 15fps and 480x270 is about 60MB of RGB data.
+    pub const MAX_WIDTH: u32 = 480;
+
+    /// GIF quantizer speed, 1 (best quality) to 30 (fastest)
+    pub const GIF_SPEED: i32 = 10;
+}
+
+/// Image sequence export settings for `export_frames`
+pub mod export {
+    use std::time::Duration;
+
+    /// Threads encoding and writing image files in parallel
+    pub const WRITER_THREADS: usize = 4;
+
+    /// Frames waiting for a writer before further frames are missed
+    pub const QUEUE_LENGTH: usize = 8;
+
+    /// Stop early when no new frame is published for this long
+    pub const FRAME_TIMEOUT: Duration = Duration::from_secs(5);
+}
+
+/// Drag-out settings for `start_frame_drag`
+pub mod drag_out {
+    /// Directory in the system temp directory holding the dragged files
+    pub const DIR_NAME: &str = "tauri-bevy-demo-drag";
+
+    /// Width of the image shown under the cursor while dragging
+    pub const ICON_WIDTH: u32 = 160;
+}
+
+/// Supersampled screenshot settings for `capture_screenshot`
+pub mod screenshot {
+    use std::time::Duration;
+
+    /// Render scale used when `capture_screenshot` does not specify one
+    pub const DEFAULT_SCALE: u32 = 2;
+
+    /// Largest render scale accepted, 4x of 800x600 is 3200x2400
+    pub const MAX_SCALE: u32 = 4;
+
+    /// Readbacks skipped before the screenshot is taken
+    ///
+    /// The first readbacks of a new target can complete before the camera
+    /// rendered into it.
+    pub const WARMUP_FRAMES: u32 = 3;
+
+    /// How long `capture_screenshot` waits for the Bevy thread
+    pub const TIMEOUT: Duration = Duration::from_secs(10);
+}
+
+/// Logo and timestamp overlay settings for `set_capture_overlay`
+pub mod overlay {
+    /// Distance in pixels between an overlay element and the frame edge
+    pub const MARGIN: u32 = 16;
+
+    /// Opacity of the dark box behind the timestamp
+    pub const TEXT_BACKGROUND_ALPHA: f32 = 0.5;
+}
+
+/// Bevy UI overlay settings for `set_ui_overlay`
+pub mod ui_overlay {
+    /// Distance in logical pixels between an element and the view's edge
+    pub const MARGIN: f32 = 12.0;
+
+    /// Text size used when an element does not specify one
+    pub const FONT_SIZE: f32 = 16.0;
+
+    /// Opacity of the dark box behind each element
+    pub const BACKGROUND_ALPHA: f32 = 0.6;
+
+    /// Height of the scale bar in logical pixels
+    pub const SCALE_BAR_HEIGHT: f32 = 4.0;
+
+    /// Largest number of elements accepted
+    pub const MAX_ELEMENTS: usize = 32;
+}
+
+/// Turntable export settings for `export_turntable`
+pub mod turntable {
+    use std::time::Duration;
+
+    /// Frame rate used when `export_turntable` does not specify one
+    pub const DEFAULT_FPS: f64 = 30.0;
+
+    /// Readbacks skipped after each camera step
+    ///
+    /// Readbacks complete a few frames after rendering, so the first ones
+    /// after a step can still show the previous view.
+    pub const SETTLE_FRAMES: u32 = 3;
+
+    /// Rendered frames waiting to be written before rendering pauses
+    pub const QUEUE_LENGTH: usize = 4;
+
+    /// How long `export_turntable` waits for the next frame
+    pub const FRAME_TIMEOUT: Duration = Duration::from_secs(10);
+}
+
+/// Batch render settings for `render_batch`
+pub mod batch {
+    use std::time::Duration;
+
+    /// How long a single job may take to render before it fails
+    pub const JOB_TIMEOUT: Duration = Duration::from_secs(30);
+}
+
+/// Model loading settings for `load_model`, `open_model_dialog` and
+/// `watch_model_folder`
+pub mod model {
+    use std::time::Duration;
+
+    /// File extensions offered by the file picker and accepted by
+    /// `load_model`
+    pub const EXTENSIONS: &[&str] = &["gltf", "glb", "obj", "stl", "xyz", "pts"];
+
+    /// Point cloud file extensions, drawn as unlit points
+    pub const POINT_CLOUD_EXTENSIONS: &[&str] = &["xyz", "pts"];
+
+    /// Time between `loading` progress events
+    pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+    /// Time between checks of a watched model folder
+    ///
+    /// A new export is loaded after two checks saw it unchanged.
+    pub const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// Vertices of a loaded glTF model shown per frame, so large models
+    /// spread their first draws over several frames
+    pub const REVEAL_VERTICES_PER_FRAME: usize = 500_000;
+
+    /// Mesh and texture bytes uploaded to the GPU per frame, so a large
+    /// model's upload is spread over several frames
+    pub const UPLOAD_BYTES_PER_FRAME: usize = 64 * 1024 * 1024;
+
+    /// Largest .gltf file read for its bounds before loading, files with
+    /// embedded buffers can be large
+    pub const MAX_HEADER_BYTES: u64 = 16 * 1024 * 1024;
+
+    /// Color of the box standing in for a glTF model while it loads
+    pub const PROXY_COLOR: [f32; 4] = [0.55, 0.65, 0.8, 0.25];
+}
+
+/// Collision mesh settings for `export_collision_meshes`
+pub mod collision {
+    /// Vertices a convex hull has at most, the limit of PhysX and so of
+    /// Unity and Unreal
+    pub const MAX_HULL_VERTICES: usize = 255;
+
+    /// Grid cells along the longest side points are merged on before
+    /// building a hull, doubled in size until the hull is small enough
+    pub const HULL_RESOLUTION: u32 = 64;
+
+    /// Grid cells along the longest side of decimated meshes, unless given
+    pub const DEFAULT_RESOLUTION: u32 = 32;
+    pub const MAX_RESOLUTION: u32 = 1024;
+}
+
+/// Prometheus metrics exporter settings
+pub mod metrics {
+    use std::time::Duration;
+
+    /// Address the metrics endpoint listens on, off if `None`
+    ///
+    /// Metrics are always available as `frame://localhost/metrics`; this
+    /// serves them over plain HTTP for scrapers outside the app, e.g.
+    /// `Some("127.0.0.1:9464")`.
+    pub const LISTEN_ADDRESS: Option<&str> = None;
+
+    /// How long a scrape may take to send its request
+    pub const READ_TIMEOUT: Duration = Duration::from_secs(5);
+}
+
+/// WebSocket the frontend sends mouse input over
+pub mod input_socket {
+    /// Address the input socket listens on, any free loopback port by
+    /// default; the frontend asks `get_input_socket_url` where it is
+    pub const LISTEN_ADDRESS: &str = "127.0.0.1:0";
+}
+
+/// Frame and control server for clients outside the webview, built with
+/// the `control_server` feature
+pub mod control_server {
+    use std::time::Duration;
+
+    /// Address the control server listens on
+    ///
+    /// Keep it on loopback: clients can move the camera and read every
+    /// frame, and nothing is authenticated.
+    pub const LISTEN_ADDRESS: &str = "127.0.0.1:9465";
+
+    /// Largest header or payload a client may send, in bytes
+    pub const MAX_MESSAGE_BYTES: usize = 1 << 20;
+
+    /// How often a frame pusher checks whether its client has gone
+    pub const FRAME_WAIT: Duration = Duration::from_millis(250);
+
+    /// How long a write may wait for a client to read before it's
+    /// disconnected
+    pub const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+}
+
+/// Scene sync settings for `start_scene_sync`
+pub mod scene_sync {
+    use std::time::Duration;
+
+    /// Address instances listen on for others when none is given
+    ///
+    /// Loopback only reaches windows on this machine; listen on a LAN
+    /// address to sync with another machine. Nothing is authenticated.
+    pub const LISTEN_ADDRESS: &str = "127.0.0.1:9466";
+
+    /// Edits kept to send to instances that connect later, oldest dropped
+    /// first
+    pub const MAX_LOG: usize = 10_000;
+
+    /// How long a write to a stalled instance may block before it is
+    /// disconnected
+    pub const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+    /// Edits from other instances kept so they can be undone there
+    pub const REMOTE_HISTORY_DEPTH: usize = 1_000;
+}
+
+/// Renderer health check settings for `get_health`
+pub mod health {
+    use std::time::Duration;
+
+    /// Time without a Bevy frame after which the renderer counts as stalled
+    ///
+    /// Well above the slowest adaptive frame interval, `1 / MIN_FPS`.
+    pub const STALL_THRESHOLD: Duration = Duration::from_secs(2);
+}
+
+/// Crash report settings
+pub mod crash {
+    /// Folder in the app data directory crash reports are written to
+    pub const DIRECTORY: &str = "crashes";
+
+    /// Log events kept for the report
+    pub const LOG_LINES: usize = 200;
+}
+
+/// Golden-image check settings
+pub mod frame_hash {
+    /// Folder in the app data directory baselines are saved to
+    pub const BASELINE_DIRECTORY: &str = "baselines";
+
+    /// Default number of perceptual hash bits that may differ from a
+    /// baseline for `compare_frame_baseline` to pass
+    pub const PERCEPTUAL_TOLERANCE: u32 = 4;
+}
+
+/// Debug view settings for `set_debug_view`
+pub mod debug_view {
+    /// Distance from the camera in world units at which the depth view
+    /// reaches black
+    pub const DEPTH_RANGE: f32 = 20.0;
+
+    /// Brightness each surface adds in the overdraw view, so ten
+    /// overlapping surfaces are white
+    pub const OVERDRAW_STEP: f32 = 0.1;
+}
+
+/// Frustum culling diagnostics settings for `get_culling_stats`
+pub mod culling {
+    /// Culled entities listed per camera at most, the count covers all
+    pub const MAX_LISTED_CULLED: usize = 100;
+}
+
+/// Image-based lighting settings for `set_environment_map` and
+/// `set_reflection_probes`
+pub mod environment {
+    use std::time::Duration;
+
+    /// Face size of the cubemap drawn as the background
+    pub const SKYBOX_SIZE: u32 = 512;
+
+    /// Face size of the sharpest level of the specular cubemap, each
+    /// following mip level is blurred for a rougher surface
+    pub const SPECULAR_SIZE: u32 = 128;
+
+    /// GGX samples taken per texel of the rough specular levels
+    pub const SPECULAR_SAMPLES: u32 = 64;
+
+    /// Face size of the diffuse irradiance cubemap
+    pub const DIFFUSE_SIZE: u32 = 32;
+
+    /// Cosine-weighted samples taken per diffuse texel
+    pub const DIFFUSE_SAMPLES: u32 = 256;
+
+    /// Brightness of an environment in cd/m² when none is given, suits
+    /// typical outdoor HDRIs at Bevy's default exposure
+    pub const DEFAULT_INTENSITY: f32 = 2000.0;
+
+    /// Most reflection probes one view can sample, Bevy's limit
+    pub const MAX_REFLECTION_PROBES: usize = 8;
+
+    /// How long the commands wait for environments to load and filter
+    pub const LOAD_TIMEOUT: Duration = Duration::from_secs(30);
+}
+
+/// Shadow catcher ground settings for `set_ground_plane`
+pub mod ground_plane {
+    /// Width and depth of the ground in world units when none is given
+    pub const SIZE: f32 = 20.0;
+
+    /// Opacity of a full shadow on the ground when none is given
+    pub const SHADOW_OPACITY: f32 = 0.6;
+}
+
+/// Reflective water plane settings for `set_water`
+pub mod water {
+    /// Width and depth of the water in world units when none is given
+    pub const SIZE: f32 = 20.0;
+
+    /// Water tint when none is given, sRGB
+    pub const COLOR: [f32; 3] = [0.05, 0.22, 0.3];
+
+    /// Wave steepness when none is given, 0 for a flat mirror
+    pub const WAVE_STRENGTH: f32 = 0.15;
+
+    /// Length of the longest waves in world units
+    pub const WAVELENGTH: f32 = 1.6;
+
+    /// Speed the waves travel at in world units per second
+    pub const WAVE_SPEED: f32 = 0.5;
+
+    /// Size of the reflection pass relative to the stream resolution
+    pub const REFLECTION_SCALE: f32 = 0.5;
+
+    /// Render layer only the water is on, so the reflection camera doesn't
+    /// draw it into its own reflection
+    pub const RENDER_LAYER: usize = 1;
+}
+
+/// Color grading LUT settings for `set_color_lut`
+pub mod color_lut {
+    use std::time::Duration;
+
+    /// Largest `.cube` table size accepted, 256³ entries take 64 MiB on the
+    /// GPU
+    pub const MAX_SIZE: u32 = 256;
+
+    /// How long `set_color_lut` waits for the file to load
+    pub const LOAD_TIMEOUT: Duration = Duration::from_secs(10);
+}
+
+/// Extruded text settings for `spawn_text3d`
+pub mod text3d {
+    /// Em size in world units when none is given
+    pub const SIZE: f32 = 0.5;
+
+    /// Extrusion depth relative to the size when none is given
+    pub const DEPTH: f32 = 0.15;
+
+    /// Straight segments each curve of a glyph outline is split into
+    pub const CURVE_SEGMENTS: u32 = 8;
+
+    /// Longest text accepted, in characters
+    pub const MAX_CHARS: usize = 1000;
+}
+
+/// Camera-facing marker settings for `add_billboard`
+pub mod billboard {
+    /// Width and height in world units when none is given
+    pub const SIZE: f32 = 0.5;
+
+    /// Width and height in logical pixels of billboards with a constant
+    /// screen size when none is given
+    pub const SCREEN_SIZE: f32 = 32.0;
+
+    /// Render layer only billboards are on, so they stay out of the
+    /// water's reflection, which they wouldn't face
+    pub const RENDER_LAYER: usize = 2;
+}
+
+/// Morph target settings for `set_morph_weight`
+pub mod morph {
+    /// How quickly weights approach the value they were set to, per
+    /// second; a weight covers 1 - e^-(rate * t) of the way in t seconds
+    pub const SMOOTHING_RATE: f32 = 12.0;
+
+    /// Distance from the set value below which a weight snaps to it
+    pub const SNAP_DISTANCE: f32 = 1e-3;
+}
+
+/// Undo history settings for scene edits
+pub mod history {
+    use std::time::Duration;
+
+    /// Edits kept for `undo` by default
+    pub const DEPTH: usize = 100;
+
+    /// Most edits `set_history_depth` accepts
+    pub const MAX_DEPTH: usize = 10_000;
+
+    /// Transform changes to one entity closer together than this are one
+    /// edit, so a drag is undone in one step
+    pub const MERGE_WINDOW: Duration = Duration::from_millis(500);
+}
+
+/// Particle emitter settings for `set_particle_emitter`
+pub mod particles {
+    /// Particles alive at once at most, emission waits below this
+    pub const MAX_PARTICLES: usize = 5000;
+
+    /// Highest emission rate accepted, in particles per second, the rate
+    /// that keeps `MAX_PARTICLES` alive
+    pub const MAX_RATE: f32 = MAX_PARTICLES as f32 / LIFETIME;
+
+    /// Seconds a particle lives, shrinking to nothing over the last half
+    pub const LIFETIME: f32 = 2.5;
+
+    /// Launch speed in world units per second
+    pub const SPEED: f32 = 4.0;
+
+    /// Widest launch angle from the emitter's up direction, in radians
+    pub const SPREAD: f32 = 0.45;
+
+    /// Particle diameter in world units
+    pub const SIZE: f32 = 0.06;
+
+    /// Emitter settings before any are given
+    pub const DEFAULT_RATE: f32 = 200.0;
+    pub const DEFAULT_COLOR: [f32; 4] = [1.0, 0.55, 0.1, 1.0];
+    pub const DEFAULT_GRAVITY: [f32; 3] = [0.0, -9.81, 0.0];
+    pub const DEFAULT_POSITION: [f32; 3] = [0.0, 1.0, 0.0];
+}
+
+/// Procedural terrain scene settings
+pub mod terrain {
+    /// Width and depth of the terrain in world units
+    pub const SIZE: f32 = 24.0;
+
+    /// Chunks along each side, each its own mesh so off-screen ones are
+    /// culled
+    pub const CHUNKS: u32 = 8;
+
+    /// Quads along each side of a chunk
+    pub const CHUNK_RESOLUTION: u32 = 64;
+
+    /// Height difference between the lowest and highest possible point
+    pub const HEIGHT: f32 = 4.0;
+
+    /// Width of the largest hills in world units
+    pub const FEATURE_SIZE: f32 = 9.0;
+
+    /// Noise octaves summed for the heightmap, each adding finer detail
+    pub const OCTAVES: u32 = 6;
+
+    /// Size of each splat layer texture, with a full mip chain
+    pub const TEXTURE_SIZE: u32 = 128;
+
+    /// World units each splat layer texture covers before repeating
+    pub const TEXTURE_TILE: f32 = 1.5;
+
+    /// Seed of the first terrain generated
+    pub const DEFAULT_SEED: u32 = 1;
+}
+
+/// Readback self-test settings for `run_readback_self_test`
+pub mod self_test {
+    /// Size of the test patterns, odd so texture rows need alignment
+    /// padding
+    pub const PATTERN_WIDTH: u32 = 61;
+    pub const PATTERN_HEIGHT: u32 = 17;
+
+    /// Readbacks skipped before checking, while the patterns are uploaded
+    /// and rendered
+    pub const WARMUP_FRAMES: u32 = 3;
+
+    /// Render layer nothing is on, so the clear color camera draws nothing
+    /// but its clear color
+    pub const EMPTY_RENDER_LAYER: usize = 31;
+}
+
+/// GPU picking settings for `pick_entity`
+pub mod picking {
+    /// Render layer only the ID-buffer copies of meshes are on, so the
+    /// stream camera doesn't draw them
+    pub const RENDER_LAYER: usize = 3;
+
+    /// Bits of a pick id stored per color channel, the rest of the 8 bits
+    /// absorb rounding in the sRGB target
+    pub const CHANNEL_BITS: u32 = 6;
+
+    /// Readbacks skipped before reading the pick id, while the copies are
+    /// drawn for the first time
+    pub const WARMUP_FRAMES: u32 = 4;
+
+    /// Further readbacks waited for the copies to show where the CPU ray
+    /// cast hit a mesh, while Bevy compiles their pipelines
+    pub const PIPELINE_WAIT_FRAMES: u32 = 60;
+}
+
+/// Project files written by `save_project`
+pub mod project {
+    /// Format version written to new projects; newer files are refused
+    pub const VERSION: u32 = 1;
+
+    /// File in the app config directory recent projects are listed in
+    pub const RECENT_FILE: &str = "recent_projects.json";
+
+    /// How many recent projects are remembered
+    pub const MAX_RECENT: usize = 10;
+}
+
+/// Headless test harness settings, see `testing::TestRenderer`
+pub mod testing {
+    /// Frame rate of the deterministic clock tests run with
+    pub const FIXED_FPS: f64 = 60.0;
+
+    /// Frames to step before giving up on a frame or command reply
+    ///
+    /// Covers `PRE_ROLL_FRAMES` and slow software adapters in CI.
+    pub const MAX_STEPS: u32 = 600;
+
+    /// Frames for a change to show up in published frames, covering the
+    /// readback latency
+    pub const SETTLE_FRAMES: u32 = 4;
+}
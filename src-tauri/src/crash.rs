@@ -0,0 +1,192 @@
+//! Crash reports
+//!
+//! A panic anywhere in the app, including Bevy's thread and its task pools,
+//! writes a JSON report to the `crashes` folder of the app data directory:
+//! the panic message and backtrace, the last log events, the GPU adapter
+//! and the stream settings at the time. The frontend is told through a
+//! `crash-report` event, so it can point the user at the file.
+//!
+//! Log events are the ones emitted through `tracing`, which includes Bevy's
+//! and wgpu's warnings and errors but not the app's own `println!` output.
+
+use serde::Serialize;
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::panic::{self, PanicHookInfo};
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::{layer::Context, Layer};
+
+use crate::config::crash::{DIRECTORY, LOG_LINES};
+use crate::tauri_bridge::shared_state::{BridgeState, GpuInfo, PixelFormat};
+
+static STATE: OnceLock<BridgeState> = OnceLock::new();
+static APP: OnceLock<AppHandle> = OnceLock::new();
+static LOG_TAIL: LazyLock<Mutex<VecDeque<String>>> = LazyLock::new(Default::default);
+
+/// Everything written to a crash report file
+#[derive(Serialize)]
+pub struct CrashReport {
+    /// Wall-clock milliseconds since the Unix epoch
+    pub timestamp_ms: f64,
+    pub version: &'static str,
+    /// Name of the thread that panicked
+    pub thread: String,
+    pub message: String,
+    /// Source location of the panic, `file:line:column`
+    pub location: Option<String>,
+    pub backtrace: String,
+    /// The last `LOG_LINES` log events, oldest first
+    pub log: Vec<String>,
+    pub gpu: Option<GpuInfo>,
+    pub settings: CrashSettings,
+}
+
+/// Settings in effect when the app crashed
+#[derive(Serialize, Default)]
+pub struct CrashSettings {
+    /// Command line arguments, e.g. GPU selection
+    pub args: Vec<String>,
+    /// Size and layout of the last published frame
+    pub stream_width: Option<u32>,
+    pub stream_height: Option<u32>,
+    pub pixel_format: Option<PixelFormat>,
+    /// Adaptive frame rate target
+    pub target_fps: Option<f64>,
+}
+
+/// Payload of the `crash-report` event
+#[derive(Serialize, Clone)]
+pub struct CrashNotice {
+    pub message: String,
+    /// Report file, `None` if it could not be written
+    pub path: Option<String>,
+}
+
+/// Install the panic hook, reading GPU info and settings from `state`
+///
+/// The previous hook still runs first, so panics are printed as before.
+pub fn install(state: BridgeState) {
+    let _ = STATE.set(state);
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        previous(info);
+        report_panic(info);
+    }));
+}
+
+/// Write reports to this app's data directory and notify its frontend
+///
+/// Until then, reports go to the system temp directory.
+pub fn attach(app: &AppHandle) {
+    let _ = APP.set(app.clone());
+}
+
+/// Directory crash reports are written to
+pub fn reports_dir() -> PathBuf {
+    APP.get()
+        .and_then(|app| app.path().app_data_dir().ok())
+        .unwrap_or_else(std::env::temp_dir)
+        .join(DIRECTORY)
+}
+
+fn report_panic(info: &PanicHookInfo) {
+    let thread = std::thread::current();
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<dyn Any>".to_string());
+
+    // The panicking thread may hold any of these locks, only try them
+    let state = STATE.get();
+    let gpu = state
+        .and_then(|state| state.gpu_info.0.try_lock().ok())
+        .and_then(|info| info.clone());
+    let mut settings = CrashSettings {
+        args: std::env::args().skip(1).collect(),
+        ..Default::default()
+    };
+    if let Some(state) = state {
+        if let Some(frame) = state.frame_buffer.latest() {
+            settings.stream_width = Some(frame.width);
+            settings.stream_height = Some(frame.height);
+            settings.pixel_format = Some(frame.format);
+        }
+        if let Ok(stats) = state.perf_stats.0.try_lock() {
+            settings.target_fps = Some(stats.target_fps);
+        }
+    }
+
+    let report = CrashReport {
+        timestamp_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64() * 1000.0)
+            .unwrap_or_default(),
+        version: env!("CARGO_PKG_VERSION"),
+        thread: thread.name().unwrap_or("unnamed").to_string(),
+        message: message.clone(),
+        location: info.location().map(|location| location.to_string()),
+        backtrace: Backtrace::force_capture().to_string(),
+        log: LOG_TAIL
+            .try_lock()
+            .map(|log| log.iter().cloned().collect())
+            .unwrap_or_default(),
+        gpu,
+        settings,
+    };
+
+    let path = match write_report(&report) {
+        Ok(path) => {
+            eprintln!("[Crash] Report written to {}", path.display());
+            Some(path.display().to_string())
+        }
+        Err(e) => {
+            eprintln!("[Crash] Failed to write report: {}", e);
+            None
+        }
+    };
+    if let Some(app) = APP.get() {
+        let _ = app.emit("crash-report", CrashNotice { message, path });
+    }
+}
+
+fn write_report(report: &CrashReport) -> Result<PathBuf, String> {
+    let dir = reports_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let path = dir.join(format!("crash-{}.json", report.timestamp_ms as u64));
+    let json = serde_json::to_vec_pretty(report).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+// =============================================================================
+// Log Tail
+// =============================================================================
+
+/// `tracing` layer keeping the last `LOG_LINES` events at info level or
+/// above for crash reports
+pub struct LogTailLayer;
+
+impl<S: Subscriber> Layer<S> for LogTailLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        // wgpu and Bevy are chatty below info, skip that before formatting
+        if *metadata.level() > Level::INFO {
+            return;
+        }
+        let line = crate::logging::event_line(event);
+
+        let Ok(mut log) = LOG_TAIL.lock() else {
+            return;
+        };
+        if log.len() >= LOG_LINES {
+            log.pop_front();
+        }
+        log.push_back(line);
+    }
+}
@@ -12,78 +12,414 @@
 //! # Module Structure
 //!
 //! - `config`: Configuration constants and settings
+//! - `platform`: OS thread priority and affinity
+//! - `profiling`: Trace capture for the `start_trace`/`stop_trace` commands
+//! - `logging`: Log output with a filter changed by `set_log_level`
+//! - `startup`: Startup phase timing for `get_startup_report`
+//! - `crash`: Crash reports written on panic
+//! - `testing`: Headless harness stepping the Bevy app for integration tests
 //! - `tauri_bridge`: Bridge layer between Tauri and Bevy
 //!   - `shared_state`: Thread-safe data structures
 //!   - `commands`: Tauri command handlers
 //!   - `protocol`: Custom protocol handlers
+//!   - `frame_header`: Binary header for frame payloads
+//!   - `input_socket`: WebSocket for high-frequency mouse input
+//!   - `encoder`: Background JPEG encoder thread
+//!   - `stream_manager`: Stream clients with their own size, quality and frame rate
+//!   - `recorder`: MP4/WebM session recording
+//!   - `webm`: AV1 encoding and WebM muxing for recordings
+//!   - `clip`: GIF/APNG export of the last few seconds
+//!   - `clipboard`: Copying frames to the OS clipboard
+//!   - `drag_out`: Dragging frames out of the window as PNG files
+//!   - `frame_export`: Numbered image sequence export
+//!   - `screenshot`: Supersampled screenshots
+//!   - `turntable`: 360° turntable video/image export
+//!   - `batch`: Sequential render jobs with their own pose and size
+//!   - `overlay`: Logo/timestamp overlay on captured output
+//!   - `capture_sink`: Hooks delivering finished captures to other storage
+//!   - `frame_output`: Live outputs such as virtual cameras
+//!   - `shared_surface`: Native presentation of the viewport without readback
+//!   - `camera_presets`: Saved camera presets kept across sessions
+//!   - `project`: Project files and recent projects
+//!   - `usdz`: USDZ packaging for AR Quick Look
+//!   - `model_watch`: Reloading the newest model exported to a folder
+//!   - `metrics`: Prometheus metrics exporter
+//!   - `frame_hash`: Frame hashes and golden-image baselines
+//!   - `wire_format`: JSON or MessagePack for stats, scene trees and the control server
+//!   - `scene_sync`: Scene edits shared with other running instances
+//!   - `control_server`: TCP frame and control server (`control_server` feature)
 //! - `bevy`: Bevy engine integration
 //!   - `components`: ECS components
 //!   - `resources`: Global resources
 //!   - `plugins`: Custom plugins
 //!   - `systems`: Game systems
+//!   - `pacing`: Frame pacing for the app runner
 //!   - `app`: Application setup
 
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 // Module declarations
-mod bevy;
-mod config;
-mod tauri_bridge;
+// Public so examples can drive the render pipeline without a Tauri window
+pub mod bevy;
+pub mod config;
+pub mod crash;
+pub mod logging;
+pub mod platform;
+pub mod profiling;
+pub mod startup;
+pub mod tauri_bridge;
+pub mod testing;
 
-use std::{thread, time::Duration};
-use tauri_bridge::{SharedFrameBuffer, SharedMouseInput, SharedPerfStats};
+use std::{sync::Arc, thread, time::Duration};
+use tauri::Manager;
+use tauri_bridge::capture_sink::{CaptureSink, CaptureSinks};
+use tauri_bridge::frame_output::FrameOutput;
+use tauri_bridge::protocol::ProtocolHeaders;
+use tauri_bridge::shared_surface::{SharedSurface, SurfacePresenter};
+use tauri_bridge::BridgeState;
+
+use crate::bevy::plugins::texture_share::TextureShare;
 
 /// Main entry point for the Tauri application
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    println!("[Tauri] Starting...");
-
-    // Create shared state
-    let buffer = SharedFrameBuffer::default();
-    let perf_stats = SharedPerfStats::default();
-    let mouse_input = SharedMouseInput::default();
-
-    // Start Bevy in background thread
-    bevy::start_bevy(buffer.clone(), perf_stats.clone(), mouse_input.clone());
-
-    // Wait for Bevy to initialize
-    thread::sleep(Duration::from_millis(1000));
-
-    // Clone for the custom protocol handler
-    let protocol_buffer = buffer.clone();
-    let protocol_perf_stats = perf_stats.clone();
-
-    // Build and run Tauri application
-    tauri::Builder::default()
-        .plugin(tauri_plugin_opener::init())
-        .manage(buffer)
-        .manage(perf_stats)
-        .manage(mouse_input)
-        // Register custom protocol "frame://" for direct binary transfer
-        // This bypasses Tauri IPC JSON serialization completely!
-        .register_asynchronous_uri_scheme_protocol("frame", move |_ctx, request, responder| {
-            let buffer = protocol_buffer.clone();
-            let perf_stats = protocol_perf_stats.clone();
-
-            // Handle the request in a separate thread to avoid blocking
-            std::thread::spawn(move || {
-                let uri = request.uri();
-                let path = uri.path();
-
-                println!("[Protocol] Request URI: {}, path: {}", uri, path);
-
-                // For Tauri v2, URL format is: http://frame.localhost/path
-                let response =
-                    tauri_bridge::protocol::handle_frame_protocol(path, &buffer, &perf_stats);
-                responder.respond(response);
-            });
-        })
-        .invoke_handler(tauri::generate_handler![
-            tauri_bridge::commands::get_frame,
-            tauri_bridge::commands::get_render_size,
-            tauri_bridge::commands::get_performance_stats,
-            tauri_bridge::commands::send_mouse_input
-        ])
-        .run(tauri::generate_context!())
-        .expect("Tauri error");
+    AppBuilder::new().run()
+}
+
+/// Builder for apps embedding the demo with their own extensions
+///
+/// ```ignore
+/// AppBuilder::new()
+///     .capture_sink(CommandSink::new("aws", ["s3", "cp", "{path}", "s3://renders/"]))
+///     .run();
+/// ```
+#[derive(Default)]
+pub struct AppBuilder {
+    capture_sinks: Vec<Box<dyn CaptureSink>>,
+    frame_outputs: Vec<Box<dyn FrameOutput>>,
+    texture_shares: Vec<Box<dyn TextureShare>>,
+    metrics_address: Option<String>,
+    protocol_headers: ProtocolHeaders,
+    surface_presenter: Option<Box<dyn SurfacePresenter>>,
+}
+
+impl AppBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deliver finished screenshots, recordings and exports to `sink`
+    ///
+    /// Sinks are called in the order they were added.
+    pub fn capture_sink(mut self, sink: impl CaptureSink) -> Self {
+        self.capture_sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Publish every stream frame to `output`, e.g. a virtual camera
+    pub fn frame_output(mut self, output: impl FrameOutput) -> Self {
+        self.frame_outputs.push(Box::new(output));
+        self
+    }
+
+    /// Hand the stream texture to `share` on the GPU, see [`TextureShare`]
+    pub fn texture_share(mut self, share: impl TextureShare) -> Self {
+        self.texture_shares.push(Box::new(share));
+        self
+    }
+
+    /// Serve Prometheus metrics over HTTP on `address`, e.g.
+    /// `"127.0.0.1:9464"`, instead of `config::metrics::LISTEN_ADDRESS`
+    pub fn metrics_endpoint(mut self, address: impl Into<String>) -> Self {
+        self.metrics_address = Some(address.into());
+        self
+    }
+
+    /// Send `headers` with `frame://` responses instead of the
+    /// `config::protocol` defaults, e.g. to allow only the app's own origin
+    pub fn protocol_headers(mut self, headers: ProtocolHeaders) -> Self {
+        self.protocol_headers = headers;
+        self
+    }
+
+    /// Present the viewport natively through `presenter` instead of
+    /// fetching frames, falling back to fetching if it can't attach
+    pub fn surface_presenter(mut self, presenter: impl SurfacePresenter) -> Self {
+        self.surface_presenter = Some(Box::new(presenter));
+        self
+    }
+
+    /// Start Bevy and run the Tauri application
+    pub fn run(self) {
+        startup::init();
+        println!("[Tauri] Starting...");
+
+        // Install the trace recorder before any spans are entered
+        profiling::init();
+
+        // Create shared state
+        let mut bridge = BridgeState::default();
+        bridge.capture_sinks = CaptureSinks::start(self.capture_sinks);
+        bridge.protocol_headers = Arc::new(self.protocol_headers);
+        bridge.shared_surface = SharedSurface::new(self.surface_presenter);
+        crash::install(bridge.clone());
+
+        // Start Bevy in background thread
+        let mut texture_shares = self.texture_shares;
+        texture_shares.extend(bridge.shared_surface.texture_share());
+        bevy::start_bevy_with(
+            bridge.clone(),
+            bevy::AppOptions {
+                texture_shares,
+                ..Default::default()
+            },
+        );
+
+        // Start the JPEG encoder that feeds the frame:// protocol
+        tauri_bridge::encoder::start_encoder(bridge.clone());
+
+        // Feed virtual cameras and other live outputs
+        tauri_bridge::frame_output::start_frame_outputs(bridge.clone(), self.frame_outputs);
+
+        // Expose metrics to external scrapers if configured
+        let metrics_address = self
+            .metrics_address
+            .as_deref()
+            .or(config::metrics::LISTEN_ADDRESS);
+        if let Some(address) = metrics_address {
+            if let Err(e) = tauri_bridge::metrics::start_metrics_server(bridge.clone(), address) {
+                eprintln!("[Metrics] Failed to listen on {}: {}", address, e);
+            }
+        }
+
+        // Let scripts and test rigs drive and watch the renderer over TCP
+        #[cfg(feature = "control_server")]
+        if let Err(e) = tauri_bridge::control_server::start_control_server(
+            bridge.clone(),
+            config::control_server::LISTEN_ADDRESS,
+        ) {
+            eprintln!(
+                "[Control] Failed to listen on {}: {}",
+                config::control_server::LISTEN_ADDRESS,
+                e
+            );
+        }
+
+        // Take mouse input over a WebSocket instead of an invoke per event
+        let input_socket = tauri_bridge::input_socket::start_input_socket(
+            bridge.mouse_input.clone(),
+            config::input_socket::LISTEN_ADDRESS,
+        )
+        .unwrap_or_else(|e| {
+            eprintln!("[Input] Failed to open the input socket: {}", e);
+            Default::default()
+        });
+
+        // Keep the last few seconds around for export_clip
+        let clip_buffer = tauri_bridge::clip::SharedClipBuffer::default();
+        tauri_bridge::clip::start_clip_buffer(bridge.clone(), clip_buffer.clone());
+
+        // Wait for Bevy to initialize
+        thread::sleep(Duration::from_millis(1000));
+
+        // Clone for the custom protocol handler
+        let protocol_state = bridge.clone();
+        let recorder = tauri_bridge::recorder::Recorder::new(bridge.clone());
+        let export_state = bridge.clone();
+        let preset_commands = bridge.commands.clone();
+        let shared_surface = bridge.shared_surface.clone();
+
+        // Build and run Tauri application
+        tauri::Builder::default()
+            .plugin(tauri_plugin_opener::init())
+            .plugin(tauri_plugin_dialog::init())
+            .setup(move |app| {
+                // Crash reports go to the app data directory from now on
+                crash::attach(app.handle());
+                // Camera presets saved in earlier sessions
+                tauri_bridge::camera_presets::restore(app.handle(), &preset_commands);
+                // Present natively below the webview if a presenter is registered
+                if let Some(window) = app.get_webview_window("main") {
+                    shared_surface.attach(&window);
+                }
+                Ok(())
+            })
+            .manage(bridge.frame_buffer)
+            .manage(bridge.perf_stats)
+            .manage(bridge.stats_history)
+            .manage(bridge.mouse_input)
+            .manage(bridge.fetch_stats)
+            .manage(bridge.display_latency)
+            .manage(bridge.gpu_info)
+            .manage(bridge.commands)
+            .manage(bridge.overlay)
+            .manage(bridge.capture_sinks)
+            .manage(bridge.shared_surface)
+            .manage(recorder)
+            .manage(clip_buffer)
+            .manage(export_state)
+            .manage(tauri_bridge::model_watch::ModelWatcher::default())
+            .manage(input_socket)
+            // Register custom protocol "frame://" for direct binary transfer
+            // This bypasses Tauri IPC JSON serialization completely!
+            .register_asynchronous_uri_scheme_protocol("frame", move |_ctx, request, responder| {
+                let state = protocol_state.clone();
+
+                // Handle the request in a separate thread to avoid blocking
+                std::thread::spawn(move || {
+                    let uri = request.uri();
+                    let path = uri.path_and_query().map_or(uri.path(), |p| p.as_str());
+
+                    println!("[Protocol] Request URI: {}, path: {}", uri, path);
+
+                    // For Tauri v2, URL format is: http://frame.localhost/path
+                    let response = tauri_bridge::protocol::handle_frame_request(&request, &state);
+                    responder.respond(response);
+                });
+            })
+            .invoke_handler(tauri::generate_handler![
+                tauri_bridge::commands::get_frame,
+                tauri_bridge::commands::get_render_size,
+                tauri_bridge::commands::get_frame_hash,
+                tauri_bridge::commands::save_frame_baseline,
+                tauri_bridge::commands::compare_frame_baseline,
+                tauri_bridge::commands::get_performance_stats,
+                tauri_bridge::commands::get_stats_history,
+                tauri_bridge::commands::get_health,
+                tauri_bridge::commands::get_gpu_errors,
+                tauri_bridge::commands::get_system_timings,
+                tauri_bridge::commands::watch_slow_frames,
+                tauri_bridge::commands::stop_slow_frame_alerts,
+                tauri_bridge::commands::list_entities,
+                tauri_bridge::commands::inspect_entity,
+                tauri_bridge::commands::set_tags,
+                tauri_bridge::commands::query_entities,
+                tauri_bridge::commands::update_tagged,
+                tauri_bridge::commands::create_selection_set,
+                tauri_bridge::commands::add_to_selection_set,
+                tauri_bridge::commands::remove_from_selection_set,
+                tauri_bridge::commands::delete_selection_set,
+                tauri_bridge::commands::list_selection_sets,
+                tauri_bridge::commands::select_selection_set,
+                tauri_bridge::commands::get_selection,
+                tauri_bridge::commands::update_selection_set,
+                tauri_bridge::commands::spawn_primitive,
+                tauri_bridge::commands::spawn_text3d,
+                tauri_bridge::commands::add_billboard,
+                tauri_bridge::commands::remove_billboard,
+                tauri_bridge::commands::clear_billboards,
+                tauri_bridge::commands::set_entity_transform,
+                tauri_bridge::commands::set_parent,
+                tauri_bridge::commands::detach,
+                tauri_bridge::commands::set_snapping,
+                tauri_bridge::commands::raycast,
+                tauri_bridge::commands::pick_entity,
+                tauri_bridge::commands::undo,
+                tauri_bridge::commands::redo,
+                tauri_bridge::commands::set_history_depth,
+                tauri_bridge::commands::set_scene_locked,
+                tauri_bridge::commands::is_scene_locked,
+                tauri_bridge::commands::start_scene_sync,
+                tauri_bridge::commands::connect_scene_sync,
+                tauri_bridge::commands::disconnect_scene_sync,
+                tauri_bridge::commands::get_scene_sync_status,
+                tauri_bridge::commands::register_custom_shader,
+                tauri_bridge::commands::set_custom_shader_material,
+                tauri_bridge::commands::list_submeshes,
+                tauri_bridge::commands::set_submesh_material,
+                tauri_bridge::commands::set_morph_weight,
+                tauri_bridge::commands::list_morph_targets,
+                tauri_bridge::commands::set_particle_emitter,
+                tauri_bridge::commands::stop_particle_emitter,
+                tauri_bridge::commands::set_demo_scene,
+                tauri_bridge::commands::regenerate_terrain,
+                tauri_bridge::commands::list_resources,
+                tauri_bridge::commands::run_readback_self_test,
+                tauri_bridge::commands::send_mouse_input,
+                tauri_bridge::commands::get_input_socket_url,
+                tauri_bridge::commands::get_presentation_mode,
+                tauri_bridge::commands::set_surface_viewport,
+                tauri_bridge::commands::set_stream_resolution,
+                tauri_bridge::commands::register_stream_client,
+                tauri_bridge::commands::update_stream_client,
+                tauri_bridge::commands::unregister_stream_client,
+                tauri_bridge::commands::list_stream_clients,
+                tauri_bridge::commands::set_stream_format,
+                tauri_bridge::commands::set_region_of_interest,
+                tauri_bridge::commands::set_fixed_timestep,
+                tauri_bridge::commands::save_project,
+                tauri_bridge::commands::open_project,
+                tauri_bridge::commands::get_recent_projects,
+                tauri_bridge::commands::load_model,
+                tauri_bridge::commands::open_model_dialog,
+                tauri_bridge::commands::watch_model_folder,
+                tauri_bridge::commands::stop_watching_model_folder,
+                tauri_bridge::commands::watch_asset_progress,
+                tauri_bridge::commands::prefetch_assets,
+                tauri_bridge::commands::get_prefetch_status,
+                tauri_bridge::commands::clear_prefetched_assets,
+                tauri_bridge::commands::export_gltf,
+                tauri_bridge::commands::export_usdz,
+                tauri_bridge::commands::export_collision_meshes,
+                tauri_bridge::commands::save_camera_preset,
+                tauri_bridge::commands::apply_camera_preset,
+                tauri_bridge::commands::look_at_entity,
+                tauri_bridge::commands::set_orbit_pivot,
+                tauri_bridge::commands::set_camera_transition,
+                tauri_bridge::commands::set_standard_view,
+                tauri_bridge::commands::set_projection,
+                tauri_bridge::commands::set_quad_view,
+                tauri_bridge::commands::set_camera_sensitivity,
+                tauri_bridge::commands::shake_camera,
+                tauri_bridge::commands::push_in,
+                tauri_bridge::commands::stop_camera_effects,
+                tauri_bridge::commands::get_camera_state,
+                tauri_bridge::commands::reset_roll,
+                tauri_bridge::commands::set_exposure,
+                tauri_bridge::commands::set_lighting_preset,
+                tauri_bridge::commands::set_environment_map,
+                tauri_bridge::commands::set_reflection_probes,
+                tauri_bridge::commands::set_ground_plane,
+                tauri_bridge::commands::set_water,
+                tauri_bridge::commands::set_transparent_background,
+                tauri_bridge::commands::set_debug_overlay,
+                tauri_bridge::commands::set_ui_overlay,
+                tauri_bridge::commands::set_debug_view,
+                tauri_bridge::commands::get_culling_stats,
+                tauri_bridge::commands::freeze_culling,
+                tauri_bridge::commands::set_depth_of_field,
+                tauri_bridge::commands::clear_depth_of_field,
+                tauri_bridge::commands::focus_depth_of_field,
+                tauri_bridge::commands::set_fog,
+                tauri_bridge::commands::clear_fog,
+                tauri_bridge::commands::set_color_lut,
+                tauri_bridge::commands::frame_all,
+                tauri_bridge::commands::play_camera_path,
+                tauri_bridge::commands::pause_camera_path,
+                tauri_bridge::commands::resume_camera_path,
+                tauri_bridge::commands::seek_camera_path,
+                tauri_bridge::commands::stop_camera_path,
+                tauri_bridge::commands::report_frame_displayed,
+                tauri_bridge::commands::reset_stats,
+                tauri_bridge::commands::get_startup_report,
+                tauri_bridge::commands::get_gpu_info,
+                tauri_bridge::commands::start_recording,
+                tauri_bridge::commands::stop_recording,
+                tauri_bridge::commands::export_clip,
+                tauri_bridge::commands::export_frames,
+                tauri_bridge::commands::copy_frame_to_clipboard,
+                tauri_bridge::commands::start_frame_drag,
+                tauri_bridge::commands::capture_screenshot,
+                tauri_bridge::commands::export_turntable,
+                tauri_bridge::commands::render_batch,
+                tauri_bridge::commands::set_capture_overlay,
+                tauri_bridge::commands::start_input_recording,
+                tauri_bridge::commands::stop_input_recording,
+                tauri_bridge::commands::start_trace,
+                tauri_bridge::commands::stop_trace,
+                tauri_bridge::commands::set_log_level
+            ])
+            .run(tauri::generate_context!())
+            .expect("Tauri error");
+    }
 }
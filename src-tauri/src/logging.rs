@@ -0,0 +1,96 @@
+//! Log output with a runtime filter
+//!
+//! `tracing` events from the app, Bevy and wgpu are printed to stderr when
+//! they pass a target filter, `config::logging::DEFAULT_FILTER` at startup.
+//! Crates logging through `log`, such as wgpu and naga, reach it through
+//! `tracing_log`, under their own targets.
+//! `set_log_level` swaps the filter while the app runs, e.g. to
+//! `"wgpu=debug,info"` while diagnosing a GPU issue.
+//!
+//! With the `bevy_log` feature, Bevy's `LogPlugin` prints events instead,
+//! filtered by `RUST_LOG` once at startup, and the filter can't be changed.
+
+use std::fmt::Write as _;
+use std::sync::{LazyLock, RwLock};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_log::NormalizeEvent;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::{layer::Context, Layer};
+
+use crate::config::logging::DEFAULT_FILTER;
+
+/// The filter as given and parsed
+static FILTER: LazyLock<RwLock<(String, Targets)>> = LazyLock::new(|| {
+    let targets = DEFAULT_FILTER
+        .parse()
+        .unwrap_or_else(|_| Targets::new().with_default(Level::INFO));
+    RwLock::new((DEFAULT_FILTER.to_string(), targets))
+});
+
+/// Current filter, in the form given to [`set_filter`]
+pub fn filter() -> String {
+    FILTER
+        .read()
+        .map(|filter| filter.0.clone())
+        .unwrap_or_default()
+}
+
+/// Replace the filter, returning the previous one
+///
+/// `filter` is a comma-separated list of `target=level` directives, e.g.
+/// `"wgpu=warn,bevy_render=info"`; a bare level applies to all other
+/// targets.
+pub fn set_filter(filter: &str) -> Result<String, String> {
+    let targets: Targets = filter
+        .parse()
+        .map_err(|e| format!("Invalid log filter {:?}: {}", filter, e))?;
+    if cfg!(feature = "bevy_log") {
+        return Err("Bevy's LogPlugin prints logs with the bevy_log feature, use RUST_LOG".into());
+    }
+    let mut current = FILTER.write().map_err(|e| e.to_string())?;
+    let previous = std::mem::replace(&mut *current, (filter.to_string(), targets));
+    Ok(previous.0)
+}
+
+/// `tracing` layer printing events that pass the filter to stderr
+pub struct LogOutputLayer;
+
+impl<S: Subscriber> Layer<S> for LogOutputLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let normalized = event.normalized_metadata();
+        let metadata = normalized.as_ref().unwrap_or_else(|| event.metadata());
+        let enabled = FILTER
+            .read()
+            .is_ok_and(|filter| filter.1.would_enable(metadata.target(), metadata.level()));
+        if enabled {
+            eprintln!("[Log] {}", event_line(event));
+        }
+    }
+}
+
+/// An event as one line: level, target and fields, the message without
+/// its name
+pub fn event_line(event: &Event<'_>) -> String {
+    // Records from `log` carry their target and level as fields
+    let normalized = event.normalized_metadata();
+    let metadata = normalized.as_ref().unwrap_or_else(|| event.metadata());
+    let mut line = format!("{} {}:", metadata.level(), metadata.target());
+    event.record(&mut LineVisitor(&mut line));
+    line
+}
+
+struct LineVisitor<'a>(&'a mut String);
+
+impl Visit for LineVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name().starts_with("log.") {
+            return;
+        }
+        if field.name() == "message" {
+            let _ = write!(self.0, " {:?}", value);
+        } else {
+            let _ = write!(self.0, " {}={:?}", field.name(), value);
+        }
+    }
+}
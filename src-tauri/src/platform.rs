@@ -0,0 +1,58 @@
+//! Platform-specific thread scheduling
+//!
+//! Thin wrappers over the OS thread priority and CPU affinity APIs, used to
+//! keep the Bevy threads from starving the webview's compositor on machines
+//! with few cores. Failures (e.g. missing privileges to raise a priority)
+//! are reported but never fatal.
+
+use thread_priority::{ThreadPriority as OsThreadPriority, ThreadPriorityValue};
+
+/// Scheduling priority of a thread, relative to normal application threads
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThreadPriority {
+    Lowest,
+    BelowNormal,
+    /// Leave the OS default untouched
+    Normal,
+    AboveNormal,
+    Highest,
+}
+
+/// Set the scheduling priority of the calling thread
+pub fn set_current_thread_priority(priority: ThreadPriority) -> Result<(), String> {
+    let os_priority = match priority {
+        ThreadPriority::Normal => return Ok(()),
+        ThreadPriority::Lowest => OsThreadPriority::Min,
+        ThreadPriority::Highest => OsThreadPriority::Max,
+        ThreadPriority::BelowNormal => crossplatform_priority(25)?,
+        ThreadPriority::AboveNormal => crossplatform_priority(75)?,
+    };
+    thread_priority::set_current_thread_priority(os_priority).map_err(|e| format!("{e:?}"))
+}
+
+fn crossplatform_priority(value: u8) -> Result<OsThreadPriority, String> {
+    ThreadPriorityValue::try_from(value)
+        .map(OsThreadPriority::Crossplatform)
+        .map_err(|e| e.to_string())
+}
+
+/// Pin the calling thread to logical core `core`
+pub fn pin_current_thread(core: usize) -> Result<(), String> {
+    let core_ids = core_affinity::get_core_ids().ok_or("Core ids unavailable")?;
+    let core_id = core_ids
+        .into_iter()
+        .find(|id| id.id == core)
+        .ok_or_else(|| format!("No logical core {core}"))?;
+    if core_affinity::set_for_current(core_id) {
+        Ok(())
+    } else {
+        Err(format!("Failed to pin thread to core {core}"))
+    }
+}
+
+/// Number of logical cores, or 1 if it cannot be determined
+pub fn logical_cores() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
@@ -0,0 +1,381 @@
+//! Trace capture for deep profiling
+//!
+//! The readback, frame extraction and encode paths are instrumented with
+//! `tracing` spans. This module records those spans on demand and writes
+//! them as a Chrome trace (open with `chrome://tracing` or Perfetto), so a
+//! capture can be taken from a running app with `start_trace`/`stop_trace`.
+//!
+//! With the `trace` cargo feature, Bevy also opens a span for every
+//! schedule and system run. Their durations are aggregated continuously for
+//! `get_system_timings`, independent of any capture.
+//!
+//! For live profiling, the `trace_tracy` and `trace_chrome` cargo features
+//! enable Bevy's own Tracy and Chrome trace output. Bevy's `LogPlugin` then
+//! owns the global subscriber and the recorder is added as a custom layer.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::BufWriter,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        LazyLock, Mutex,
+    },
+    time::Instant,
+};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id},
+    Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use crate::config::profiling::MAX_TRACE_EVENTS;
+
+static RECORDER: LazyLock<TraceRecorder> = LazyLock::new(TraceRecorder::default);
+static SYSTEM_TIMINGS: LazyLock<Mutex<TimingTables>> = LazyLock::new(Default::default);
+
+/// Global trace recorder used by the `start_trace`/`stop_trace` commands
+pub fn recorder() -> &'static TraceRecorder {
+    &RECORDER
+}
+
+/// Install the recording layer as the global `tracing` subscriber, along
+/// with the log tail kept for crash reports and the log output
+///
+/// With the `bevy_log` feature, Bevy's `LogPlugin` installs the subscriber
+/// instead and picks up the layer through [`bevy_log_layer`].
+pub fn init() {
+    #[cfg(not(feature = "bevy_log"))]
+    {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let subscriber = tracing_subscriber::registry()
+            .with(TraceLayer)
+            .with(crate::crash::LogTailLayer)
+            .with(crate::logging::LogOutputLayer);
+        if tracing::subscriber::set_global_default(subscriber).is_err() {
+            eprintln!("[Profiling] A tracing subscriber is already installed, trace capture disabled");
+        }
+        // wgpu and naga log through `log`, which only reaches the filter
+        // as tracing events
+        if let Err(e) = tracing_log::LogTracer::init() {
+            eprintln!("[Profiling] Records logged through `log` won't show: {}", e);
+        }
+    }
+}
+
+/// Custom layer for Bevy's `LogPlugin`
+#[cfg(feature = "bevy_log")]
+pub fn bevy_log_layer(_app: &mut bevy::app::App) -> Option<bevy::log::BoxedLayer> {
+    Some(Box::new(TraceLayer.and_then(crate::crash::LogTailLayer)))
+}
+
+// =============================================================================
+// Recorder
+// =============================================================================
+
+/// A completed span, in Chrome trace "complete event" form
+struct TraceEvent {
+    name: &'static str,
+    /// Start in microseconds since the capture started
+    start_us: f64,
+    duration_us: f64,
+    thread: u64,
+}
+
+/// Collects span timings while a capture is running
+#[derive(Default)]
+pub struct TraceRecorder {
+    recording: AtomicBool,
+    started: Mutex<Option<Instant>>,
+    events: Mutex<Vec<TraceEvent>>,
+    thread_names: Mutex<HashMap<u64, String>>,
+}
+
+impl TraceRecorder {
+    pub fn is_recording(&self) -> bool {
+        self.recording.load(Ordering::Relaxed)
+    }
+
+    /// Start a new capture, discarding any previous one
+    pub fn start(&self) {
+        if let Ok(mut events) = self.events.lock() {
+            events.clear();
+        }
+        if let Ok(mut started) = self.started.lock() {
+            *started = Some(Instant::now());
+        }
+        self.recording.store(true, Ordering::Relaxed);
+    }
+
+    /// Stop the capture and write it to `path`, returning the event count
+    pub fn stop(&self, path: &str) -> Result<usize, String> {
+        if !self.recording.swap(false, Ordering::Relaxed) {
+            return Err("No trace is being recorded".into());
+        }
+
+        let events = std::mem::take(&mut *self.events.lock().map_err(|e| e.to_string())?);
+        let thread_names = self.thread_names.lock().map_err(|e| e.to_string())?;
+
+        let mut trace_events: Vec<Value> = thread_names
+            .iter()
+            .map(|(thread, name)| {
+                json!({ "name": "thread_name", "ph": "M", "pid": 1, "tid": thread, "args": { "name": name } })
+            })
+            .collect();
+        trace_events.extend(events.iter().map(|event| {
+            json!({
+                "name": event.name,
+                "ph": "X",
+                "ts": event.start_us,
+                "dur": event.duration_us,
+                "pid": 1,
+                "tid": event.thread,
+            })
+        }));
+
+        let file = File::create(path).map_err(|e| e.to_string())?;
+        serde_json::to_writer(BufWriter::new(file), &json!({ "traceEvents": trace_events }))
+            .map_err(|e| e.to_string())?;
+
+        println!("[Profiling] Wrote {} trace events to {}", events.len(), path);
+        Ok(events.len())
+    }
+
+    fn record(&self, name: &'static str, start: Instant, end: Instant) {
+        let Some(started) = self.started.lock().ok().and_then(|started| *started) else {
+            return;
+        };
+        // Spans entered before the capture started are not recorded
+        let Some(offset) = start.checked_duration_since(started) else {
+            return;
+        };
+
+        let thread = current_thread_id(&self.thread_names);
+        if let Ok(mut events) = self.events.lock() {
+            if events.len() < MAX_TRACE_EVENTS {
+                events.push(TraceEvent {
+                    name,
+                    start_us: offset.as_secs_f64() * 1_000_000.0,
+                    duration_us: (end - start).as_secs_f64() * 1_000_000.0,
+                    thread,
+                });
+            }
+        }
+    }
+}
+
+/// Small stable id for the current thread, registering its name on first use
+fn current_thread_id(thread_names: &Mutex<HashMap<u64, String>>) -> u64 {
+    static NEXT_THREAD: AtomicU64 = AtomicU64::new(1);
+    thread_local! {
+        static THREAD: u64 = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+    }
+
+    let id = THREAD.with(|id| *id);
+    if let Ok(mut names) = thread_names.lock() {
+        names.entry(id).or_insert_with(|| {
+            std::thread::current()
+                .name()
+                .map(str::to_owned)
+                .unwrap_or_else(|| format!("thread-{id}"))
+        });
+    }
+    id
+}
+
+// =============================================================================
+// System Timings
+// =============================================================================
+
+/// Kind of Bevy span whose durations are aggregated
+#[derive(Clone, Copy)]
+enum TimedKind {
+    Schedule,
+    System,
+}
+
+#[derive(Default)]
+struct Timing {
+    runs: u64,
+    total_ms: f64,
+    max_ms: f64,
+}
+
+impl Timing {
+    fn entry(&self, name: &str) -> TimingEntry {
+        TimingEntry {
+            name: name.to_owned(),
+            runs: self.runs,
+            total_ms: self.total_ms,
+            avg_ms: self.total_ms / self.runs.max(1) as f64,
+            max_ms: self.max_ms,
+        }
+    }
+}
+
+#[derive(Default)]
+struct TimingTables {
+    schedules: HashMap<String, Timing>,
+    systems: HashMap<String, Timing>,
+}
+
+impl TimingTables {
+    fn table(&mut self, kind: TimedKind) -> &mut HashMap<String, Timing> {
+        match kind {
+            TimedKind::Schedule => &mut self.schedules,
+            TimedKind::System => &mut self.systems,
+        }
+    }
+}
+
+/// Time spent in one schedule or system since the last reset
+#[derive(Serialize, Clone, Debug)]
+pub struct TimingEntry {
+    pub name: String,
+    pub runs: u64,
+    pub total_ms: f64,
+    pub avg_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Per-schedule and per-system timings, most total time first
+#[derive(Serialize, Clone, Debug)]
+pub struct SystemTimings {
+    /// Whether Bevy emits the spans timed here, i.e. the `trace` feature is
+    /// on; without it both lists stay empty
+    pub enabled: bool,
+    pub schedules: Vec<TimingEntry>,
+    pub systems: Vec<TimingEntry>,
+}
+
+/// Timings aggregated since startup or the last [`reset_system_timings`]
+///
+/// Schedules include the render world's, such as `Render` and
+/// `ExtractSchedule`. Systems running in parallel overlap, so their times
+/// can add up to more than the frame time.
+pub fn system_timings() -> SystemTimings {
+    let entries = |table: &HashMap<String, Timing>| {
+        let mut entries: Vec<TimingEntry> = table
+            .iter()
+            .map(|(name, timing)| timing.entry(name))
+            .collect();
+        entries.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+        entries
+    };
+    let (schedules, systems) = SYSTEM_TIMINGS
+        .lock()
+        .map(|timings| (entries(&timings.schedules), entries(&timings.systems)))
+        .unwrap_or_default();
+
+    SystemTimings {
+        enabled: cfg!(feature = "trace"),
+        schedules,
+        systems,
+    }
+}
+
+/// Start aggregating schedule and system timings afresh
+pub fn reset_system_timings() {
+    if let Ok(mut timings) = SYSTEM_TIMINGS.lock() {
+        *timings = TimingTables::default();
+    }
+}
+
+fn record_timing(kind: TimedKind, name: &str, duration_ms: f64) {
+    let Ok(mut timings) = SYSTEM_TIMINGS.lock() else {
+        return;
+    };
+    let timing = timings.table(kind).entry(name.to_owned()).or_default();
+    timing.runs += 1;
+    timing.total_ms += duration_ms;
+    timing.max_ms = timing.max_ms.max(duration_ms);
+}
+
+/// Schedule or system a span belongs to, stored in the span's extensions
+struct TimedSpan {
+    kind: TimedKind,
+    name: String,
+}
+
+/// Time at which a timed span was entered
+struct TimedStart(Instant);
+
+/// Reads the `name` field Bevy puts on schedule and system spans
+#[derive(Default)]
+struct NameVisitor(Option<String>);
+
+impl Visit for NameVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "name" {
+            self.0 = Some(value.to_owned());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "name" {
+            self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
+
+// =============================================================================
+// Tracing Layer
+// =============================================================================
+
+/// Time at which a span was entered, stored in the span's extensions
+struct SpanStart(Instant);
+
+/// `tracing` layer feeding span timings into the global recorder
+struct TraceLayer;
+
+impl<S> Layer<S> for TraceLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let kind = match attrs.metadata().name() {
+            "schedule" => TimedKind::Schedule,
+            "system" => TimedKind::System,
+            _ => return,
+        };
+        let mut visitor = NameVisitor::default();
+        attrs.record(&mut visitor);
+        if let (Some(name), Some(span)) = (visitor.0, ctx.span(id)) {
+            span.extensions_mut().insert(TimedSpan { kind, name });
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if extensions.get_mut::<TimedSpan>().is_some() {
+            extensions.replace(TimedStart(Instant::now()));
+        }
+        if recorder().is_recording() {
+            extensions.replace(SpanStart(Instant::now()));
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(TimedStart(start)) = extensions.remove::<TimedStart>() {
+            if let Some(timed) = extensions.get_mut::<TimedSpan>() {
+                let duration_ms = start.elapsed().as_secs_f64() * 1000.0;
+                record_timing(timed.kind, &timed.name, duration_ms);
+            }
+        }
+        let Some(SpanStart(start)) = extensions.remove::<SpanStart>() else {
+            return;
+        };
+        recorder().record(span.name(), start, Instant::now());
+    }
+}
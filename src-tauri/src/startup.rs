@@ -0,0 +1,71 @@
+//! Startup phase timing
+//!
+//! The panel stays blank from launch until the first frame is delivered.
+//! Each phase along the way records when it completed, relative to
+//! [`init`], so that period can be broken down with `get_startup_report`.
+
+use serde::Serialize;
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::time::Instant;
+
+static LAUNCHED: OnceLock<Instant> = OnceLock::new();
+static REPORT: LazyLock<Mutex<StartupReport>> = LazyLock::new(Default::default);
+
+/// Milestones between launch and the first delivered frame, in order
+#[derive(Clone, Copy, Debug)]
+pub enum StartupPhase {
+    /// The Bevy thread is running
+    BevyThreadStarted,
+    /// `create_app` returned, all plugins are added
+    AppCreated,
+    /// Plugins finished, including wgpu adapter and device creation
+    RenderDeviceReady,
+    /// The first readback arrived from the GPU
+    FirstRender,
+    /// Pre-roll is over and the first frame was published
+    FirstFramePublished,
+    /// A client fetched a frame for the first time
+    FirstFrameDelivered,
+}
+
+/// Milliseconds since launch at which each phase completed
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct StartupReport {
+    pub bevy_thread_started_ms: Option<f64>,
+    pub app_created_ms: Option<f64>,
+    pub render_device_ready_ms: Option<f64>,
+    pub first_render_ms: Option<f64>,
+    pub first_frame_published_ms: Option<f64>,
+    pub first_frame_delivered_ms: Option<f64>,
+}
+
+/// Start the launch clock, call once as early as possible
+pub fn init() {
+    LAUNCHED.get_or_init(Instant::now);
+}
+
+/// Record that `phase` completed now, unless it already did
+pub fn mark(phase: StartupPhase) {
+    let elapsed_ms = LAUNCHED.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0;
+    let Ok(mut report) = REPORT.lock() else {
+        return;
+    };
+
+    let slot = match phase {
+        StartupPhase::BevyThreadStarted => &mut report.bevy_thread_started_ms,
+        StartupPhase::AppCreated => &mut report.app_created_ms,
+        StartupPhase::RenderDeviceReady => &mut report.render_device_ready_ms,
+        StartupPhase::FirstRender => &mut report.first_render_ms,
+        StartupPhase::FirstFramePublished => &mut report.first_frame_published_ms,
+        StartupPhase::FirstFrameDelivered => &mut report.first_frame_delivered_ms,
+    };
+    if slot.is_none() {
+        *slot = Some(elapsed_ms);
+        println!("[Startup] {:?} after {:.0}ms", phase, elapsed_ms);
+    }
+}
+
+/// Phases completed so far
+pub fn report() -> StartupReport {
+    REPORT.lock().map(|report| report.clone()).unwrap_or_default()
+}
@@ -0,0 +1,145 @@
+//! Batch rendering
+//!
+//! Renders a list of views, each with its own camera pose, resolution and
+//! output file, one after another: an offline render farm inside the app.
+//! Each job is rendered like a screenshot, by a temporary camera with its
+//! own target, so the stream keeps running while a batch is processed.
+
+use image::{DynamicImage, ImageFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Instant;
+use tracing::info_span;
+
+use crate::config::batch::JOB_TIMEOUT;
+use crate::config::{RENDER_HEIGHT, RENDER_WIDTH};
+use super::overlay::{self, Overlay};
+use super::shared_state::{BridgeCommand, BridgeState, CameraView};
+
+/// A single image to render
+#[derive(Deserialize, Clone, Debug)]
+pub struct RenderJob {
+    /// Camera placement, the stream camera's current view if `None`
+    pub view: Option<CameraView>,
+    /// Image size, the render resolution if `None`
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Output file, the format follows the extension
+    pub path: String,
+}
+
+/// Outcome of a [`RenderJob`]
+#[derive(Serialize, Clone, Debug)]
+pub struct RenderJobResult {
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+    /// Time from queuing the job to the file being written
+    pub duration_ms: f64,
+    /// Why the job failed, `None` on success
+    pub error: Option<String>,
+}
+
+/// Payload of `batch-progress` events
+#[derive(Serialize, Clone, Debug)]
+pub struct BatchProgress {
+    /// Jobs finished so far, including failed ones
+    pub completed: usize,
+    pub total: usize,
+    /// The job that just finished
+    pub job: RenderJobResult,
+}
+
+/// Render `jobs` in order, calling `on_progress` after each one
+///
+/// A failing job doesn't stop the batch, its error is reported in its
+/// result instead.
+pub fn render_batch(
+    state: &BridgeState,
+    jobs: &[RenderJob],
+    on_progress: impl Fn(&BatchProgress),
+) -> Vec<RenderJobResult> {
+    println!("[Batch] Rendering {} jobs", jobs.len());
+    let overlay = state.overlay.current();
+    let mut results = Vec::with_capacity(jobs.len());
+
+    for (index, job) in jobs.iter().enumerate() {
+        let _span = info_span!("render_job", index).entered();
+        let started = Instant::now();
+        let (width, height) = (
+            job.width.unwrap_or(RENDER_WIDTH),
+            job.height.unwrap_or(RENDER_HEIGHT),
+        );
+        let result = RenderJobResult {
+            path: job.path.clone(),
+            width,
+            height,
+            error: render_job(state, job, width, height, overlay.as_deref()).err(),
+            duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+        };
+        if let Some(e) = &result.error {
+            eprintln!("[Batch] Job {} ({}) failed: {}", index, job.path, e);
+        }
+
+        on_progress(&BatchProgress {
+            completed: index + 1,
+            total: jobs.len(),
+            job: result.clone(),
+        });
+        results.push(result);
+    }
+
+    let failed = results
+        .iter()
+        .filter(|result| result.error.is_some())
+        .count();
+    println!(
+        "[Batch] Finished {} jobs ({} failed)",
+        results.len(),
+        failed
+    );
+    results
+}
+
+fn render_job(
+    state: &BridgeState,
+    job: &RenderJob,
+    width: u32,
+    height: u32,
+    overlay: Option<&Overlay>,
+) -> Result<(), String> {
+    if width == 0 || height == 0 {
+        return Err("Width and height must be positive".to_string());
+    }
+    let path = Path::new(&job.path);
+    let format = ImageFormat::from_path(path).map_err(|e| format!("{}: {}", job.path, e))?;
+
+    let (reply, response) = crossbeam_channel::bounded(1);
+    state.commands.send(BridgeCommand::RenderView {
+        view: job.view,
+        width,
+        height,
+        reply,
+    })?;
+    let captured = response
+        .recv_timeout(JOB_TIMEOUT)
+        .map_err(|_| "Timed out waiting for the render".to_string())??;
+
+    let mut image = RgbaImage::from_raw(captured.width, captured.height, captured.data)
+        .ok_or_else(|| "Render data does not match its size".to_string())?;
+    if let Some(overlay) = overlay {
+        overlay.apply(&mut image, width, height, 4, overlay::now_ms());
+    }
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    // JPEG has no alpha channel
+    let image = match format {
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(image).to_rgb8()),
+        _ => DynamicImage::ImageRgba8(image),
+    };
+    image
+        .save_with_format(path, format)
+        .map_err(|e| format!("{}: {}", job.path, e))
+}
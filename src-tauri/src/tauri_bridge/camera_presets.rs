@@ -0,0 +1,55 @@
+//! Camera preset persistence
+//!
+//! Presets live in a Bevy resource so they can be applied within a frame;
+//! this module keeps a copy in the app config directory, so presets saved
+//! in one session are available in the next.
+
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+use crate::config::camera::PRESETS_FILE;
+use super::shared_state::{BridgeCommand, CameraPresetMap, SharedCommandQueue};
+
+/// File the presets are saved to
+pub fn presets_path(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(PRESETS_FILE))
+        .map_err(|e| e.to_string())
+}
+
+/// Write all presets to `presets_path`
+pub fn save(app: &AppHandle, presets: &CameraPresetMap) -> Result<(), String> {
+    let path = presets_path(app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    let json = serde_json::to_vec_pretty(presets).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Hand the presets saved in an earlier session to Bevy
+///
+/// A missing file is not an error, an unreadable one is logged and skipped.
+pub fn restore(app: &AppHandle, commands: &SharedCommandQueue) {
+    let result = presets_path(app).and_then(|path| {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let json = std::fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        serde_json::from_slice::<CameraPresetMap>(&json)
+            .map(Some)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    });
+
+    match result {
+        Ok(Some(presets)) => {
+            println!("[Tauri] Loaded {} camera presets", presets.len());
+            if let Err(e) = commands.send(BridgeCommand::LoadCameraPresets(presets)) {
+                eprintln!("[Tauri] Failed to load camera presets: {}", e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("[Tauri] Failed to load camera presets: {}", e),
+    }
+}
@@ -0,0 +1,214 @@
+//! Post-capture hooks
+//!
+//! Apps embedding the demo register [`CaptureSink`]s on the
+//! [`AppBuilder`](crate::AppBuilder) to push finished screenshots,
+//! recordings and exports to their own storage. Sinks are called on their
+//! own thread once a capture is on disk, so a slow upload never holds up
+//! the command that produced it, and a failing sink is only logged.
+
+use crossbeam_channel::Sender;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use tracing::info_span;
+
+/// What produced a [`Capture`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptureKind {
+    Screenshot,
+    Recording,
+    Clip,
+    /// A directory of numbered images from `export_frames`
+    ImageSequence,
+    /// A video file, or a directory of images
+    Turntable,
+    /// A single job of `render_batch`
+    BatchRender,
+}
+
+impl CaptureKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CaptureKind::Screenshot => "screenshot",
+            CaptureKind::Recording => "recording",
+            CaptureKind::Clip => "clip",
+            CaptureKind::ImageSequence => "image_sequence",
+            CaptureKind::Turntable => "turntable",
+            CaptureKind::BatchRender => "batch_render",
+        }
+    }
+}
+
+/// A finished capture
+#[derive(Clone, Debug)]
+pub struct Capture {
+    pub kind: CaptureKind,
+    /// File written, or directory for image sequences
+    pub path: PathBuf,
+}
+
+/// Destination for finished captures
+///
+/// Screenshots returned inline instead of saved are not delivered.
+pub trait CaptureSink: Send + 'static {
+    /// Short name for log messages
+    fn name(&self) -> &str;
+
+    /// Handle a finished capture, called from the sink thread
+    fn deliver(&mut self, capture: &Capture) -> Result<(), String>;
+}
+
+/// Handle for queuing captures to the registered sinks
+///
+/// Does nothing when no sink was registered.
+#[derive(Clone, Default)]
+pub struct CaptureSinks(Option<Sender<Capture>>);
+
+impl CaptureSinks {
+    /// Start the thread delivering captures to `sinks`, in order
+    pub fn start(mut sinks: Vec<Box<dyn CaptureSink>>) -> Self {
+        if sinks.is_empty() {
+            return Self::default();
+        }
+        let (sender, receiver) = crossbeam_channel::unbounded::<Capture>();
+        let names: Vec<String> = sinks.iter().map(|sink| sink.name().to_string()).collect();
+
+        let spawned = thread::Builder::new()
+            .name("capture-sinks".into())
+            .spawn(move || {
+                for capture in receiver {
+                    for sink in &mut sinks {
+                        let _span = info_span!("capture_sink", sink = sink.name()).entered();
+                        if let Err(e) = sink.deliver(&capture) {
+                            eprintln!(
+                                "[Capture] {} failed for {}: {}",
+                                sink.name(),
+                                capture.path.display(),
+                                e
+                            );
+                        }
+                    }
+                }
+            });
+        match spawned {
+            Ok(_) => {
+                println!("[Capture] Delivering captures to {}", names.join(", "));
+                Self(Some(sender))
+            }
+            Err(e) => {
+                eprintln!("[Capture] Failed to start the sink thread: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Queue a finished capture for delivery
+    pub fn notify(&self, kind: CaptureKind, path: impl Into<PathBuf>) {
+        if let Some(sender) = &self.0 {
+            let _ = sender.send(Capture {
+                kind,
+                path: path.into(),
+            });
+        }
+    }
+}
+
+// =============================================================================
+// Built-in Sinks
+// =============================================================================
+
+/// Copies captures into a directory, e.g. a synced or network folder
+///
+/// Image sequences are copied into a subdirectory of the same name.
+pub struct DirectorySink {
+    dir: PathBuf,
+}
+
+impl DirectorySink {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl CaptureSink for DirectorySink {
+    fn name(&self) -> &str {
+        "directory"
+    }
+
+    fn deliver(&mut self, capture: &Capture) -> Result<(), String> {
+        let name = capture
+            .path
+            .file_name()
+            .ok_or_else(|| format!("{}: no file name", capture.path.display()))?;
+        copy_path(&capture.path, &self.dir.join(name))
+    }
+}
+
+fn copy_path(from: &Path, to: &Path) -> Result<(), String> {
+    if from.is_dir() {
+        std::fs::create_dir_all(to).map_err(|e| format!("{}: {}", to.display(), e))?;
+        let entries = std::fs::read_dir(from).map_err(|e| format!("{}: {}", from.display(), e))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| e.to_string())?;
+            copy_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        if let Some(dir) = to.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        }
+        std::fs::copy(from, to)
+            .map(|_| ())
+            .map_err(|e| format!("{}: {}", to.display(), e))
+    }
+}
+
+/// Runs a program for each capture, e.g. an S3 upload or a webhook call
+///
+/// `{path}` and `{kind}` in the arguments are replaced by the capture's
+/// path and [`CaptureKind`]. The program must exit successfully.
+///
+/// ```ignore
+/// CommandSink::new("aws", ["s3", "cp", "{path}", "s3://renders/"])
+/// CommandSink::new("curl", ["-fsS", "-F", "file=@{path}", "https://example.com/hook"])
+/// ```
+pub struct CommandSink {
+    program: String,
+    args: Vec<String>,
+}
+
+impl CommandSink {
+    pub fn new<I, S>(program: impl Into<String>, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl CaptureSink for CommandSink {
+    fn name(&self) -> &str {
+        &self.program
+    }
+
+    fn deliver(&mut self, capture: &Capture) -> Result<(), String> {
+        let path = capture.path.display().to_string();
+        let args = self.args.iter().map(|arg| {
+            arg.replace("{path}", &path)
+                .replace("{kind}", capture.kind.as_str())
+        });
+        let status = Command::new(&self.program)
+            .args(args)
+            .status()
+            .map_err(|e| format!("{}: {}", self.program, e))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("{} exited with {}", self.program, status))
+        }
+    }
+}
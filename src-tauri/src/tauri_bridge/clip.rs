@@ -0,0 +1,259 @@
+//! Short clip export
+//!
+//! Keeps the last few seconds of the stream in memory so a GIF or APNG can
+//! be written after something interesting happened, e.g. for a bug report
+//! or a quick turntable. Frames are buffered at a reduced rate and size
+//! (`config::clip`), which keeps the buffer to a few tens of megabytes
+//! instead of holding on to every full-resolution frame.
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{imageops, Delay, ImageBuffer, Rgb, RgbImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tracing::info_span;
+
+use crate::config::clip::{BUFFER_SECONDS, CLIP_FPS, GIF_SPEED, MAX_WIDTH};
+use super::encoder::convert_to_rgb8;
+use super::overlay::Overlay;
+use super::shared_state::{BridgeState, Frame};
+
+/// File format written by `export_clip`
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipFormat {
+    /// 256 colors per frame, plays everywhere
+    Gif,
+    /// Full color, larger files
+    Apng,
+}
+
+/// A downscaled RGB8 frame in the clip buffer
+pub struct ClipFrame {
+    /// Publication time of the source frame, see [`Frame::timestamp_ms`]
+    pub timestamp_ms: f64,
+    pub image: RgbImage,
+}
+
+/// The most recent `BUFFER_SECONDS` of the stream, oldest first
+#[derive(Clone, Default)]
+pub struct SharedClipBuffer(pub Arc<Mutex<VecDeque<Arc<ClipFrame>>>>);
+
+impl SharedClipBuffer {
+    fn push(&self, frame: ClipFrame) {
+        let Ok(mut frames) = self.0.lock() else {
+            return;
+        };
+        let oldest_kept_ms = frame.timestamp_ms - BUFFER_SECONDS * 1000.0;
+        while frames
+            .front()
+            .is_some_and(|oldest| oldest.timestamp_ms < oldest_kept_ms)
+        {
+            frames.pop_front();
+        }
+        frames.push_back(Arc::new(frame));
+    }
+
+    /// Frames of the last `seconds`, all the size of the newest frame
+    fn last(&self, seconds: f64) -> Vec<Arc<ClipFrame>> {
+        let Ok(frames) = self.0.lock() else {
+            return Vec::new();
+        };
+        let Some(newest) = frames.back() else {
+            return Vec::new();
+        };
+        let since_ms = newest.timestamp_ms - seconds * 1000.0;
+        let size = newest.image.dimensions();
+
+        let mut clip: Vec<_> = frames
+            .iter()
+            .rev()
+            .take_while(|frame| frame.timestamp_ms >= since_ms && frame.image.dimensions() == size)
+            .cloned()
+            .collect();
+        clip.reverse();
+        clip
+    }
+}
+
+/// Result of `export_clip`
+#[derive(Serialize, Clone, Debug)]
+pub struct ClipSummary {
+    pub path: String,
+    pub frames: usize,
+    pub duration_s: f64,
+    pub width: u32,
+    pub height: u32,
+    pub size_bytes: u64,
+}
+
+/// Start the thread that fills the clip buffer
+///
+/// Wakes up on every published frame like the encoder, but only keeps one
+/// every `1 / CLIP_FPS` seconds.
+pub fn start_clip_buffer(state: BridgeState, clip: SharedClipBuffer) {
+    thread::Builder::new()
+        .name("clip-buffer".into())
+        .spawn(move || {
+            let interval_ms = 1000.0 / CLIP_FPS;
+            let mut last_seen = 0;
+            let mut last_kept_ms = f64::MIN;
+            while let Some(sequence) = state.frame_signal.wait_newer(last_seen) {
+                last_seen = sequence;
+                let Some(frame) = state.frame_buffer.latest() else {
+                    continue;
+                };
+                if frame.timestamp_ms - last_kept_ms < interval_ms {
+                    continue;
+                }
+                last_kept_ms = frame.timestamp_ms;
+                if let Some(image) = downscale(&frame, &state) {
+                    clip.push(ClipFrame {
+                        timestamp_ms: frame.timestamp_ms,
+                        image,
+                    });
+                }
+            }
+            println!("[Clip] Frame signal lost, stopping");
+        })
+        .expect("Failed to spawn clip buffer thread");
+}
+
+/// Copy `frame` as RGB8, scaled down to at most `MAX_WIDTH` pixels wide
+fn downscale(frame: &Frame, state: &BridgeState) -> Option<RgbImage> {
+    let _span = info_span!("clip_downscale").entered();
+    let converted = convert_to_rgb8(frame, &state.buffer_pool);
+    let rgb = converted.as_deref().unwrap_or(&frame.data);
+    let image = if frame.width > MAX_WIDTH {
+        let height = (frame.height as u64 * MAX_WIDTH as u64 / frame.width as u64).max(1) as u32;
+        ImageBuffer::<Rgb<u8>, &[u8]>::from_raw(frame.width, frame.height, rgb)
+            .map(|source| imageops::thumbnail(&source, MAX_WIDTH, height))
+    } else {
+        RgbImage::from_raw(frame.width, frame.height, rgb.to_vec())
+    };
+
+    if let Some(converted) = converted {
+        state.buffer_pool.recycle(converted);
+    }
+    image
+}
+
+/// Write the last `seconds` of the clip buffer to `path`, with `overlay`
+/// drawn on every frame
+pub fn export_clip(
+    clip: &SharedClipBuffer,
+    path: &Path,
+    format: ClipFormat,
+    seconds: f64,
+    overlay: Option<&Overlay>,
+) -> Result<ClipSummary, String> {
+    let frames = clip.last(seconds);
+    let Some(first) = frames.first() else {
+        return Err("No frames buffered yet".to_string());
+    };
+    let (width, height) = first.image.dimensions();
+
+    // Each frame is shown until the next one was published, the last one
+    // for a regular clip interval
+    let interval_ms = 1000.0 / CLIP_FPS;
+    let delays_ms: Vec<u32> = frames
+        .iter()
+        .enumerate()
+        .map(|(i, frame)| {
+            let next_ms = frames
+                .get(i + 1)
+                .map_or(frame.timestamp_ms + interval_ms, |next| next.timestamp_ms);
+            (next_ms - frame.timestamp_ms).round().max(10.0) as u32
+        })
+        .collect();
+
+    let _span = info_span!("export_clip", frames = frames.len()).entered();
+    let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let writer = BufWriter::new(file);
+    match format {
+        ClipFormat::Gif => write_gif(writer, &frames, &delays_ms, overlay),
+        ClipFormat::Apng => write_apng(writer, &frames, &delays_ms, width, height, overlay),
+    }?;
+
+    let summary = ClipSummary {
+        path: path.display().to_string(),
+        frames: frames.len(),
+        duration_s: delays_ms.iter().sum::<u32>() as f64 / 1000.0,
+        width,
+        height,
+        size_bytes: std::fs::metadata(path).map(|m| m.len()).unwrap_or_default(),
+    };
+    println!(
+        "[Clip] Wrote {} frames ({:.1}s, {}x{}) to {}",
+        summary.frames, summary.duration_s, width, height, summary.path
+    );
+    Ok(summary)
+}
+
+/// The frame's image, with `overlay` drawn into a copy
+///
+/// Buffered frames stay clean so a later export can use another overlay.
+fn overlaid<'a>(frame: &'a ClipFrame, overlay: Option<&Overlay>) -> Cow<'a, RgbImage> {
+    let Some(overlay) = overlay else {
+        return Cow::Borrowed(&frame.image);
+    };
+    let mut image = frame.image.clone();
+    let (width, height) = image.dimensions();
+    overlay.apply(&mut image, width, height, 3, frame.timestamp_ms);
+    Cow::Owned(image)
+}
+
+fn write_gif(
+    writer: BufWriter<File>,
+    frames: &[Arc<ClipFrame>],
+    delays_ms: &[u32],
+    overlay: Option<&Overlay>,
+) -> Result<(), String> {
+    let mut encoder = GifEncoder::new_with_speed(writer, GIF_SPEED);
+    encoder
+        .set_repeat(Repeat::Infinite)
+        .map_err(|e| e.to_string())?;
+
+    let gif_frames = frames.iter().zip(delays_ms).map(|(frame, &delay_ms)| {
+        let image = overlaid(frame, overlay);
+        let rgba = RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+            let [r, g, b] = image.get_pixel(x, y).0;
+            image::Rgba([r, g, b, 255])
+        });
+        image::Frame::from_parts(rgba, 0, 0, Delay::from_numer_denom_ms(delay_ms, 1))
+    });
+    encoder.encode_frames(gif_frames).map_err(|e| e.to_string())
+}
+
+fn write_apng(
+    writer: BufWriter<File>,
+    frames: &[Arc<ClipFrame>],
+    delays_ms: &[u32],
+    width: u32,
+    height: u32,
+    overlay: Option<&Overlay>,
+) -> Result<(), String> {
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    // 0 plays = loop forever
+    encoder
+        .set_animated(frames.len() as u32, 0)
+        .map_err(|e| e.to_string())?;
+
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    for (frame, &delay_ms) in frames.iter().zip(delays_ms) {
+        writer
+            .set_frame_delay(delay_ms.min(u16::MAX as u32) as u16, 1000)
+            .map_err(|e| e.to_string())?;
+        writer
+            .write_image_data(overlaid(frame, overlay).as_raw())
+            .map_err(|e| e.to_string())?;
+    }
+    writer.finish().map_err(|e| e.to_string())
+}
@@ -0,0 +1,71 @@
+//! Frames on the OS clipboard
+//!
+//! Images go to the clipboard as opaque RGBA8 through arboard, which
+//! converts them to what each platform expects: a DIB on Windows, TIFF and
+//! PNG on macOS, PNG on X11 and Wayland. On Linux the clipboard's owner
+//! serves its contents on request, so one thread keeps the clipboard open
+//! for the app's lifetime instead of each copy opening and dropping it.
+
+use arboard::{Clipboard, ImageData};
+use crossbeam_channel::Sender;
+use std::borrow::Cow;
+use std::sync::LazyLock;
+use std::thread;
+use tracing::info_span;
+
+use super::encoder::convert_to_rgb8;
+use super::shared_state::{Frame, SharedBufferPool};
+
+/// An image to copy and where to report the result
+type CopyRequest = (ImageData<'static>, Sender<Result<(), String>>);
+
+/// Requests to the thread owning the clipboard, started on first use
+static CLIPBOARD: LazyLock<Sender<CopyRequest>> = LazyLock::new(|| {
+    let (sender, receiver) = crossbeam_channel::unbounded::<CopyRequest>();
+    let spawned = thread::Builder::new()
+        .name("clipboard".into())
+        .spawn(move || {
+            let mut clipboard: Option<Clipboard> = None;
+            for (image, reply) in receiver {
+                let result = match &mut clipboard {
+                    Some(clipboard) => Ok(clipboard),
+                    None => Clipboard::new().map(|opened| clipboard.insert(opened)),
+                }
+                .and_then(|clipboard| clipboard.set_image(image))
+                .map_err(|e| e.to_string());
+                let _ = reply.send(result);
+            }
+        });
+    if let Err(e) = spawned {
+        eprintln!("[Tauri] Failed to start the clipboard thread: {}", e);
+    }
+    sender
+});
+
+/// Put `frame` on the clipboard, blocking until it is there
+pub fn copy_frame(frame: &Frame, pool: &SharedBufferPool) -> Result<(), String> {
+    let _span = info_span!("copy_frame_to_clipboard").entered();
+    let converted = convert_to_rgb8(frame, pool);
+    let rgb = converted.as_deref().unwrap_or(&frame.data);
+    // Alpha is left over from rendering, not transparency
+    let rgba: Vec<u8> = rgb
+        .chunks_exact(3)
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], u8::MAX])
+        .collect();
+    if let Some(converted) = converted {
+        pool.recycle(converted);
+    }
+
+    let image = ImageData {
+        width: frame.width as usize,
+        height: frame.height as usize,
+        bytes: Cow::Owned(rgba),
+    };
+    let (reply, response) = crossbeam_channel::bounded(1);
+    CLIPBOARD
+        .send((image, reply))
+        .map_err(|_| "Clipboard thread is not running".to_string())?;
+    response
+        .recv()
+        .map_err(|_| "Clipboard thread is not running".to_string())?
+}
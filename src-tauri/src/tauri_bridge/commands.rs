@@ -4,30 +4,111 @@
 //! from the frontend JavaScript/TypeScript code.
 
 use base64::{engine::general_purpose::STANDARD, Engine};
-use tauri::State;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, State, WebviewWindow};
+use tauri_plugin_dialog::DialogExt;
 
 use crate::config::{RENDER_WIDTH, RENDER_HEIGHT};
+use crate::config::camera::{SHAKE_AMPLITUDE_DEG, SHAKE_DECAY, SHAKE_FREQUENCY_HZ};
+use crate::config::clip::BUFFER_SECONDS;
+use crate::config::collision::MAX_RESOLUTION as MAX_COLLISION_RESOLUTION;
+use crate::config::color_lut::LOAD_TIMEOUT as LUT_LOAD_TIMEOUT;
+use crate::config::environment::{LOAD_TIMEOUT, MAX_REFLECTION_PROBES};
+use crate::config::frame_hash::PERCEPTUAL_TOLERANCE;
+use crate::config::history::MAX_DEPTH as MAX_HISTORY_DEPTH;
+use crate::config::model::EXTENSIONS as MODEL_EXTENSIONS;
+use crate::config::particles::MAX_RATE as MAX_PARTICLE_RATE;
+use crate::config::performance::{SLOW_FRAME_COUNT, SLOW_FRAME_MS};
+use crate::config::scene_sync::LISTEN_ADDRESS as SCENE_SYNC_ADDRESS;
+use crate::config::screenshot::{DEFAULT_SCALE, TIMEOUT};
+use crate::config::text3d::MAX_CHARS as MAX_TEXT3D_CHARS;
+use crate::config::turntable::DEFAULT_FPS as TURNTABLE_FPS;
+use crate::config::ui_overlay::MAX_ELEMENTS as MAX_UI_ELEMENTS;
+use crate::logging;
+use crate::profiling::{self, SystemTimings};
+use crate::startup::{self, StartupReport};
+use super::scene_sync::SceneSyncStatus;
+use super::screenshot::{self, Screenshot};
+use super::turntable::{self, TurntableFormat, TurntableSummary};
+use super::batch::{self, RenderJob, RenderJobResult};
+use super::camera_presets;
+use super::project;
+use super::model_watch::ModelWatcher;
+use super::capture_sink::{CaptureKind, CaptureSinks};
+use super::overlay::{Overlay, OverlaySettings, SharedOverlay};
+use super::frame_export::{self, ExportSummary, ImageFileFormat};
+use super::frame_hash::{self, BaselineComparison, FrameHash};
+use super::clipboard;
+use super::drag_out;
+use super::usdz;
+use super::input_socket::InputSocket;
+use super::shared_surface::{PresentationInfo, SharedSurface, SurfaceViewport};
+use super::stream_manager::{StreamClientInfo, StreamClientSettings, StreamClientUpdate};
+use super::clip::{self, ClipFormat, ClipSummary, SharedClipBuffer};
+use super::recorder::{Recorder, RecordingOptions, RecordingProgress};
 use super::shared_state::{
-    SharedFrameBuffer, SharedMouseInput, SharedPerfStats,
-    FrameResponse, PerformanceStats,
+    AddBillboard, AssetProgress, BridgeCommand, BridgeState, CameraCullingStats, CameraEasing,
+    CameraKeyframe, CameraPathEnd, CameraSensitivity, CameraState, CameraTransitionSettings,
+    CollisionExportSummary, CollisionMeshSettings, CustomShaderMaterialSettings, DebugView,
+    DemoScene, DepthOfFieldSettings, EntityInspection, EntitySummary, EnvironmentMapSettings,
+    FogSettings, Frame, FrameResponse, GltfExportSummary, GpuError, GpuInfo, GroundPlaneSettings,
+    HealthReport, LightingPreset, MaterialOverride, ModelLoadProgress, MorphTarget,
+    MorphTargetInfo, MouseInput, ParticleEmitterUpdate, PerformanceStats, PickMethod, PickedEntity,
+    PixelFormat, PrefetchAsset, PrefetchStatus, ProjectionKind, RaycastHit, ReadbackSelfTest,
+    ReflectionProbeSettings, RegionOfInterest, ResourceSummary, SelectionSetInfo,
+    SharedCommandQueue, SharedDisplayLatency, SharedFetchStats, SharedFrameBuffer, SharedGpuInfo,
+    SharedMouseInput, SharedPerfStats, SharedStatsHistory, SlowFrameAlert, SnapSettings,
+    SpawnPrimitive, SpawnText3d, StandardView, StatsSample, SubmeshInfo, TaggedOperation,
+    TransformUpdate, UiElement, UiOverlaySettings, UsdzExportSummary, WaterSettings,
 };
 
-/// Get the current rendered frame as Base64-encoded RGBA data
+/// Send the command built around a reply channel and wait for the Bevy
+/// thread's reply without blocking the async runtime
+async fn request<T: Send + 'static>(
+    queue: &SharedCommandQueue,
+    command: impl FnOnce(crossbeam_channel::Sender<T>) -> BridgeCommand,
+) -> Result<T, String> {
+    request_within(queue, TIMEOUT, "the Bevy thread", command).await
+}
+
+/// [`request`] for replies that can take longer than `TIMEOUT`, such as
+/// ones waiting for a file to load, failing with "Timed out waiting for
+/// `what`"
+async fn request_within<T: Send + 'static>(
+    queue: &SharedCommandQueue,
+    timeout: std::time::Duration,
+    what: &str,
+    command: impl FnOnce(crossbeam_channel::Sender<T>) -> BridgeCommand,
+) -> Result<T, String> {
+    let (reply, response) = crossbeam_channel::bounded(1);
+    queue.send(command(reply))?;
+    tauri::async_runtime::spawn_blocking(move || response.recv_timeout(timeout))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|_| format!("Timed out waiting for {}", what))
+}
+
+/// Get the current rendered frame as Base64-encoded pixel data
+///
+/// With `after`, returns `None` unless a frame newer than that id exists.
 #[tauri::command]
 pub fn get_frame(
     state: State<SharedFrameBuffer>,
     perf_state: State<SharedPerfStats>,
-) -> Result<FrameResponse, String> {
+    fetch_state: State<SharedFetchStats>,
+    after: Option<u64>,
+) -> Result<Option<FrameResponse>, String> {
     let cmd_start = std::time::Instant::now();
 
-    let guard = state.0.lock().map_err(|e| e.to_string())?;
-    let result = match &*guard {
-        Some(rgba_data) => {
+    let result = match state.latest() {
+        Some(frame) if after.is_some_and(|after| frame.id <= after) => Ok(None),
+        Some(frame) => {
+            fetch_state.record_fetch(frame.id);
             let data_fetch_time = cmd_start.elapsed().as_secs_f64() * 1000.0;
 
             // Measure Base64 encoding time
             let encode_start = std::time::Instant::now();
-            let base64_data = STANDARD.encode(rgba_data);
+            let response = frame_response(&frame);
             let encode_time = encode_start.elapsed().as_secs_f64() * 1000.0;
 
             // Update perf stats
@@ -36,11 +117,7 @@ pub fn get_frame(
                 stats.tauri_serialize_ms = encode_time;
             }
 
-            Ok(FrameResponse {
-                data: base64_data,
-                width: RENDER_WIDTH,
-                height: RENDER_HEIGHT,
-            })
+            Ok(Some(response))
         }
         None => Err("No frame yet (scene still loading)".into()),
     };
@@ -48,12 +125,62 @@ pub fn get_frame(
     result
 }
 
+/// Build the `get_frame` response for `frame`
+pub fn frame_response(frame: &Frame) -> FrameResponse {
+    FrameResponse {
+        data: STANDARD.encode(&frame.data),
+        width: frame.width,
+        height: frame.height,
+        format: frame.format,
+        frame_id: frame.id,
+        timestamp_ms: frame.timestamp_ms,
+    }
+}
+
 /// Get the render resolution
 #[tauri::command]
 pub fn get_render_size() -> (u32, u32) {
     (RENDER_WIDTH, RENDER_HEIGHT)
 }
 
+/// Get exact and perceptual hashes of the current frame
+#[tauri::command]
+pub fn get_frame_hash(state: State<SharedFrameBuffer>) -> Result<FrameHash, String> {
+    state
+        .latest()
+        .map(|frame| frame_hash::hash_frame(&frame))
+        .ok_or_else(|| "No frame yet (scene still loading)".to_string())
+}
+
+/// Save the current frame's hashes as baseline `name`, replacing an
+/// earlier one
+#[tauri::command]
+pub fn save_frame_baseline(
+    app: AppHandle,
+    state: State<SharedFrameBuffer>,
+    name: String,
+) -> Result<FrameHash, String> {
+    let hash = get_frame_hash(state)?;
+    let path = frame_hash::save_baseline(&app, &name, &hash)?;
+    println!("[Tauri] Saved frame baseline to {}", path.display());
+    Ok(hash)
+}
+
+/// Check the current frame against baseline `name`
+///
+/// Passes when the size matches and at most `tolerance` perceptual hash
+/// bits differ, `PERCEPTUAL_TOLERANCE` by default.
+#[tauri::command]
+pub fn compare_frame_baseline(
+    app: AppHandle,
+    state: State<SharedFrameBuffer>,
+    name: String,
+    tolerance: Option<u32>,
+) -> Result<BaselineComparison, String> {
+    let hash = get_frame_hash(state)?;
+    frame_hash::compare(&app, &name, hash, tolerance.unwrap_or(PERCEPTUAL_TOLERANCE))
+}
+
 /// Get performance statistics
 #[tauri::command]
 pub fn get_performance_stats(state: State<SharedPerfStats>) -> Result<PerformanceStats, String> {
@@ -61,8 +188,39 @@ pub fn get_performance_stats(state: State<SharedPerfStats>) -> Result<Performanc
     Ok(guard.clone())
 }
 
+/// Check whether the renderer is alive
+///
+/// `status` is `lost` when the Bevy thread ended or the GPU device was
+/// lost; the view will not update again without restarting the app.
+#[tauri::command]
+pub fn get_health(state: State<BridgeState>) -> HealthReport {
+    state.health.report(&state)
+}
+
+/// Get the most recent wgpu errors, oldest first
+///
+/// Errors are reported here instead of panicking the render thread. The
+/// total since startup is in `get_health`.
+#[tauri::command]
+pub fn get_gpu_errors(state: State<BridgeState>) -> Vec<GpuError> {
+    state.gpu_errors.recent()
+}
+
+/// Get performance statistics sampled over the last `seconds`, or the
+/// whole history, oldest first
+#[tauri::command]
+pub fn get_stats_history(
+    state: State<SharedStatsHistory>,
+    seconds: Option<f64>,
+) -> Vec<StatsSample> {
+    state.since(seconds)
+}
+
 /// Receive mouse input from frontend for camera control
 /// Input deltas are accumulated until consumed by Bevy
+///
+/// The frontend sends input over the socket from `get_input_socket_url`
+/// instead when it can, which skips the cost of an invoke per event.
 #[tauri::command]
 pub fn send_mouse_input(
     state: State<SharedMouseInput>,
@@ -71,14 +229,2210 @@ pub fn send_mouse_input(
     scroll_delta: f32,
     left_button: bool,
     right_button: bool,
+    middle_button: Option<bool>,
+) -> Result<(), String> {
+    state.add(&MouseInput {
+        delta_x,
+        delta_y,
+        scroll_delta,
+        left_button,
+        right_button,
+        middle_button: middle_button.unwrap_or(false),
+    })
+}
+
+/// WebSocket URL to send packed mouse input to, see `input_socket`, or
+/// `None` if the socket could not be opened
+#[tauri::command]
+pub fn get_input_socket_url(state: State<InputSocket>) -> Option<String> {
+    state.url.clone()
+}
+
+/// Whether the viewport fetches frames from `frame://` or a native shared
+/// surface presents them, see `shared_surface`
+#[tauri::command]
+pub fn get_presentation_mode(state: State<SharedSurface>) -> PresentationInfo {
+    state.info()
+}
+
+/// Move the shared surface over the viewport element, in CSS pixels
+#[tauri::command]
+pub fn set_surface_viewport(
+    state: State<SharedSurface>,
+    viewport: SurfaceViewport,
+) -> Result<(), String> {
+    if ![viewport.x, viewport.y, viewport.width, viewport.height]
+        .iter()
+        .all(|value| value.is_finite())
+    {
+        return Err("Viewport bounds must be finite".to_string());
+    }
+    state.set_viewport(viewport)
+}
+
+/// Register a stream consumer with its own size limit, JPEG quality and
+/// frame rate cap, returning the token to fetch
+/// `frame://localhost/frame.jpg?client=<token>` with
+#[tauri::command]
+pub fn register_stream_client(
+    state: State<BridgeState>,
+    settings: Option<StreamClientSettings>,
+) -> Result<String, String> {
+    state.stream_clients.register(settings.unwrap_or_default())
+}
+
+/// Change the settings of stream client `token` given in `settings`,
+/// leaving the others as they are, and return all of them
+#[tauri::command]
+pub fn update_stream_client(
+    state: State<BridgeState>,
+    token: String,
+    settings: StreamClientUpdate,
+) -> Result<StreamClientSettings, String> {
+    state.stream_clients.update(&token, settings)
+}
+
+/// Stop encoding frames for stream client `token`, returning whether it
+/// was registered
+#[tauri::command]
+pub fn unregister_stream_client(state: State<BridgeState>, token: String) -> Result<bool, String> {
+    state.stream_clients.unregister(&token)
+}
+
+/// List the registered stream clients with their settings
+#[tauri::command]
+pub fn list_stream_clients(state: State<BridgeState>) -> Result<Vec<StreamClientInfo>, String> {
+    state.stream_clients.list()
+}
+
+/// Set the resolution frames are streamed at
+/// Frames are downscaled on the GPU, so this cannot exceed the render size
+#[tauri::command]
+pub fn set_stream_resolution(
+    state: State<SharedCommandQueue>,
+    width: u32,
+    height: u32,
+) -> Result<(), String> {
+    if width == 0 || height == 0 || width > RENDER_WIDTH || height > RENDER_HEIGHT {
+        return Err(format!(
+            "Stream resolution must be between 1x1 and {}x{}",
+            RENDER_WIDTH, RENDER_HEIGHT
+        ));
+    }
+    state.send(BridgeCommand::SetStreamResolution { width, height })
+}
+
+/// Set the pixel layout frames are read back and served raw in
+/// `"rgb565"` halves raw frame size compared to `"rgba8"`
+#[tauri::command]
+pub fn set_stream_format(
+    state: State<SharedCommandQueue>,
+    format: PixelFormat,
+) -> Result<(), String> {
+    state.send(BridgeCommand::SetPixelFormat(format))
+}
+
+/// Advance animation by exactly `1 / fps` seconds per frame, independent of
+/// the wall clock, or follow the wall clock again with `null`
+/// With `reset_time`, the animation restarts from zero, so runs on
+/// different machines render identical frames
+#[tauri::command]
+pub fn set_fixed_timestep(
+    state: State<SharedCommandQueue>,
+    fps: Option<f64>,
+    reset_time: Option<bool>,
+) -> Result<(), String> {
+    if fps.is_some_and(|fps| !fps.is_finite() || fps <= 0.0) {
+        return Err("fps must be positive".to_string());
+    }
+    state.send(BridgeCommand::SetFixedTimestep {
+        fps,
+        reset_time: reset_time.unwrap_or(false),
+    })
+}
+
+/// Stream only a sub-rectangle of the stream target, given in stream pixels
+/// Pass `null` to stream the whole target again. The region is clamped to
+/// the stream resolution.
+#[tauri::command]
+pub fn set_region_of_interest(
+    state: State<SharedCommandQueue>,
+    region: Option<RegionOfInterest>,
+) -> Result<(), String> {
+    if region.is_some_and(|r| r.width == 0 || r.height == 0) {
+        return Err("Region of interest must not be empty".into());
+    }
+    state.send(BridgeCommand::SetRegionOfInterest(region))
+}
+
+/// Clear frame time percentiles, drop counters and display latencies
+#[tauri::command]
+pub fn reset_stats(
+    command_state: State<SharedCommandQueue>,
+    latency_state: State<SharedDisplayLatency>,
+    perf_state: State<SharedPerfStats>,
+    history_state: State<SharedStatsHistory>,
+) -> Result<(), String> {
+    latency_state.clear();
+    history_state.clear();
+    profiling::reset_system_timings();
+    if let Ok(mut stats) = perf_state.0.lock() {
+        stats.display_latency_ms = 0.0;
+        stats.display_latency = Default::default();
+    }
+    command_state.send(BridgeCommand::ResetStats)
+}
+
+/// Report that the frontend displayed a frame
+/// `frame_timestamp_ms` comes from the frame's `X-Frame-Timestamp` header,
+/// `displayed_at_ms` is the frontend's wall-clock time when it was drawn
+#[tauri::command]
+pub fn report_frame_displayed(
+    latency_state: State<SharedDisplayLatency>,
+    perf_state: State<SharedPerfStats>,
+    frame_timestamp_ms: f64,
+    displayed_at_ms: f64,
+) -> Result<(), String> {
+    let latency_ms = (displayed_at_ms - frame_timestamp_ms).max(0.0);
+    let summary = latency_state.record(latency_ms);
+
+    let mut stats = perf_state.0.lock().map_err(|e| e.to_string())?;
+    stats.display_latency_ms = latency_ms;
+    stats.display_latency = summary;
+    Ok(())
+}
+
+/// Get the GPU adapter the renderer runs on
+#[tauri::command]
+pub fn get_gpu_info(state: State<SharedGpuInfo>) -> Result<GpuInfo, String> {
+    state
+        .0
+        .lock()
+        .map_err(|e| e.to_string())?
+        .clone()
+        .ok_or_else(|| "GPU not initialized yet".to_string())
+}
+
+/// Get how long each startup phase took to complete since launch
+/// Phases that have not completed yet are `null`
+#[tauri::command]
+pub fn get_startup_report() -> StartupReport {
+    startup::report()
+}
+
+/// Start recording a trace of the instrumented frame pipeline
+#[tauri::command]
+pub fn start_trace() {
+    profiling::recorder().start();
+    println!("[Tauri] Trace recording started");
+}
+
+/// Stop recording and write the trace to `path` in Chrome trace format
+/// Returns the number of recorded spans
+#[tauri::command]
+pub fn stop_trace(path: String) -> Result<usize, String> {
+    profiling::recorder().stop(&path)
+}
+
+/// Change which log events are printed, e.g. `"wgpu=warn,bevy_render=info"`
+/// Returns the previous filter, to restore it after diagnosing an issue
+#[tauri::command]
+pub fn set_log_level(filter: String) -> Result<String, String> {
+    let previous = logging::set_filter(&filter)?;
+    println!("[Tauri] Log filter set to {}", filter);
+    Ok(previous)
+}
+
+/// Get time spent per Bevy schedule and system since startup or the last
+/// `reset_stats`, most total time first
+///
+/// Needs the `trace` cargo feature, `enabled` is `false` and the lists are
+/// empty without it.
+#[tauri::command]
+pub fn get_system_timings() -> SystemTimings {
+    profiling::system_timings()
+}
+
+/// Emit `slow-frames` with a [`SlowFrameAlert`] payload whenever `frames`
+/// consecutive frames take longer than `threshold_ms`, replacing any
+/// earlier watch
+///
+/// The payload names the pipeline stage that dominated, so the frontend can
+/// warn the user or lower the quality. Defaults to `SLOW_FRAME_MS` and
+/// `SLOW_FRAME_COUNT`.
+#[tauri::command]
+pub fn watch_slow_frames(
+    app: AppHandle,
+    command_state: State<SharedCommandQueue>,
+    threshold_ms: Option<f64>,
+    frames: Option<u32>,
+) -> Result<(), String> {
+    let threshold_ms = threshold_ms.unwrap_or(SLOW_FRAME_MS);
+    if !(threshold_ms.is_finite() && threshold_ms > 0.0) {
+        return Err("threshold_ms must be positive".to_string());
+    }
+    let (alerts, received) = crossbeam_channel::unbounded::<SlowFrameAlert>();
+    command_state.send(BridgeCommand::WatchSlowFrames {
+        threshold_ms,
+        frames: frames.unwrap_or(SLOW_FRAME_COUNT),
+        alerts,
+    })?;
+
+    // Ends when the watch is replaced or stopped
+    tauri::async_runtime::spawn_blocking(move || {
+        for alert in received {
+            let _ = app.emit("slow-frames", alert);
+        }
+    });
+    Ok(())
+}
+
+/// Stop the `slow-frames` events started by `watch_slow_frames`
+#[tauri::command]
+pub fn stop_slow_frame_alerts(command_state: State<SharedCommandQueue>) -> Result<(), String> {
+    command_state.send(BridgeCommand::StopSlowFrameAlerts)
+}
+
+/// List every entity in the Bevy world with its id, name and number of
+/// components, for an inspector panel
+#[tauri::command]
+pub async fn list_entities(
+    command_state: State<'_, SharedCommandQueue>,
+) -> Result<Vec<EntitySummary>, String> {
+    request(&command_state, |reply| BridgeCommand::ListEntities {
+        reply,
+    })
+    .await
+}
+
+/// Get the components of entity `id` from `list_entities`, with values
+/// serialized through Bevy's reflection where the type is registered
+#[tauri::command]
+pub async fn inspect_entity(
+    command_state: State<'_, SharedCommandQueue>,
+    id: u64,
+) -> Result<EntityInspection, String> {
+    request(&command_state, |reply| BridgeCommand::InspectEntity {
+        id,
+        reply,
+    })
+    .await?
+}
+
+/// Replace the tags of `entity`, such as `"bolts"` or `"annotations"`, to
+/// address it in bulk with `query_entities` and `update_tagged`. An empty
+/// list removes them
+#[tauri::command]
+pub async fn set_tags(
+    command_state: State<'_, SharedCommandQueue>,
+    entity: u64,
+    tags: Vec<String>,
 ) -> Result<(), String> {
-    let mut guard = state.0.lock().map_err(|e| e.to_string())?;
-    // Accumulate deltas (will be cleared when Bevy reads them)
-    guard.delta_x += delta_x;
-    guard.delta_y += delta_y;
-    guard.scroll_delta += scroll_delta;
-    // Button state is just the current state
-    guard.left_button = left_button;
-    guard.right_button = right_button;
+    if tags.iter().any(|tag| tag.trim().is_empty()) {
+        return Err("tags must not be empty".to_string());
+    }
+    request(&command_state, |reply| BridgeCommand::SetTags {
+        id: entity,
+        tags,
+        reply,
+    })
+    .await?
+}
+
+/// List the entities tagged `tag`, like `list_entities`
+#[tauri::command]
+pub async fn query_entities(
+    command_state: State<'_, SharedCommandQueue>,
+    tag: String,
+) -> Result<Vec<EntitySummary>, String> {
+    request(&command_state, |reply| BridgeCommand::QueryEntities {
+        tag,
+        reply,
+    })
+    .await
+}
+
+/// Hide, show, recolor or delete every entity tagged `tag` and return how
+/// many there were
+#[tauri::command]
+pub async fn update_tagged(
+    command_state: State<'_, SharedCommandQueue>,
+    tag: String,
+    operation: TaggedOperation,
+) -> Result<usize, String> {
+    validate_tagged_operation(&operation)?;
+    request(&command_state, |reply| BridgeCommand::UpdateTagged {
+        tag,
+        operation,
+        reply,
+    })
+    .await?
+}
+
+/// Check the color of a `Color` operation
+fn validate_tagged_operation(operation: &TaggedOperation) -> Result<(), String> {
+    if let TaggedOperation::Color { color } = operation {
+        if !matches!(color.len(), 3 | 4) {
+            return Err("color must have 3 or 4 components".to_string());
+        }
+        if !color.iter().all(|c| (0.0..=1.0).contains(c)) {
+            return Err("color components must be between 0 and 1".to_string());
+        }
+    }
     Ok(())
 }
+
+/// Create a named selection set of `entities`, such as the parts of a
+/// subassembly, to select, hide or recolor them together
+#[tauri::command]
+pub async fn create_selection_set(
+    command_state: State<'_, SharedCommandQueue>,
+    name: String,
+    entities: Vec<u64>,
+) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("name must not be empty".to_string());
+    }
+    request(&command_state, |reply| BridgeCommand::CreateSelectionSet {
+        name,
+        entities,
+        reply,
+    })
+    .await?
+}
+
+/// Add `entities` to selection set `name` and return how many members it
+/// has
+#[tauri::command]
+pub async fn add_to_selection_set(
+    command_state: State<'_, SharedCommandQueue>,
+    name: String,
+    entities: Vec<u64>,
+) -> Result<usize, String> {
+    request(&command_state, |reply| BridgeCommand::AddToSelectionSet {
+        name,
+        entities,
+        reply,
+    })
+    .await?
+}
+
+/// Remove `entities` from selection set `name` and return how many
+/// members it has left
+#[tauri::command]
+pub async fn remove_from_selection_set(
+    command_state: State<'_, SharedCommandQueue>,
+    name: String,
+    entities: Vec<u64>,
+) -> Result<usize, String> {
+    request(&command_state, |reply| {
+        BridgeCommand::RemoveFromSelectionSet {
+            name,
+            entities,
+            reply,
+        }
+    })
+    .await?
+}
+
+/// Delete selection set `name`, leaving its members in the scene
+#[tauri::command]
+pub async fn delete_selection_set(
+    command_state: State<'_, SharedCommandQueue>,
+    name: String,
+) -> Result<(), String> {
+    request(&command_state, |reply| BridgeCommand::DeleteSelectionSet {
+        name,
+        reply,
+    })
+    .await?
+}
+
+/// List the selection sets by name, each with its members in the order
+/// they were added
+#[tauri::command]
+pub async fn list_selection_sets(
+    command_state: State<'_, SharedCommandQueue>,
+) -> Result<Vec<SelectionSetInfo>, String> {
+    request(&command_state, |reply| BridgeCommand::ListSelectionSets {
+        reply,
+    })
+    .await
+}
+
+/// Make the members of selection set `name` the selection and return
+/// them, like `list_entities`
+#[tauri::command]
+pub async fn select_selection_set(
+    command_state: State<'_, SharedCommandQueue>,
+    name: String,
+) -> Result<Vec<EntitySummary>, String> {
+    request(&command_state, |reply| BridgeCommand::SelectSelectionSet {
+        name,
+        reply,
+    })
+    .await?
+}
+
+/// The entities selected with `select_selection_set`
+#[tauri::command]
+pub async fn get_selection(
+    command_state: State<'_, SharedCommandQueue>,
+) -> Result<Vec<EntitySummary>, String> {
+    request(&command_state, |reply| BridgeCommand::GetSelection {
+        reply,
+    })
+    .await
+}
+
+/// Hide, show, recolor or delete every member of selection set `name`,
+/// like `update_tagged`, and return how many there are
+#[tauri::command]
+pub async fn update_selection_set(
+    command_state: State<'_, SharedCommandQueue>,
+    name: String,
+    operation: TaggedOperation,
+) -> Result<usize, String> {
+    validate_tagged_operation(&operation)?;
+    request(&command_state, |reply| BridgeCommand::UpdateSelectionSet {
+        name,
+        operation,
+        reply,
+    })
+    .await?
+}
+
+/// Add a cube, sphere, cylinder or plane to the scene and return its
+/// entity id, also available as `POST frame://localhost/scene/spawn`
+#[tauri::command]
+pub async fn spawn_primitive(
+    command_state: State<'_, SharedCommandQueue>,
+    primitive: SpawnPrimitive,
+) -> Result<u64, String> {
+    request(&command_state, |reply| BridgeCommand::SpawnPrimitive {
+        primitive,
+        reply,
+    })
+    .await?
+}
+
+/// Add extruded 3D text to the scene, such as a label or a title, and
+/// return its entity id
+#[tauri::command]
+pub async fn spawn_text3d(
+    command_state: State<'_, SharedCommandQueue>,
+    text: SpawnText3d,
+) -> Result<u64, String> {
+    if text.content.trim().is_empty() {
+        return Err("content must not be empty".to_string());
+    }
+    if text.content.chars().count() > MAX_TEXT3D_CHARS {
+        return Err(format!(
+            "content must be at most {} characters",
+            MAX_TEXT3D_CHARS
+        ));
+    }
+    if text
+        .size
+        .is_some_and(|size| !(size.is_finite() && size > 0.0))
+    {
+        return Err("size must be positive".to_string());
+    }
+    if text
+        .depth
+        .is_some_and(|depth| !(depth.is_finite() && depth >= 0.0))
+    {
+        return Err("depth must not be negative".to_string());
+    }
+    request(&command_state, |reply| BridgeCommand::SpawnText3d {
+        text,
+        reply,
+    })
+    .await?
+}
+
+/// Add a camera-facing billboard, such as a marker or an icon, at a world
+/// position and return its entity id
+#[tauri::command]
+pub async fn add_billboard(
+    command_state: State<'_, SharedCommandQueue>,
+    billboard: AddBillboard,
+) -> Result<u64, String> {
+    if !billboard.position.iter().all(|c| c.is_finite()) {
+        return Err("position must be finite".to_string());
+    }
+    if billboard
+        .size
+        .is_some_and(|size| !(size.is_finite() && size > 0.0))
+    {
+        return Err("size must be positive".to_string());
+    }
+    if let Some(image) = &billboard.image {
+        if !image.is_file() {
+            return Err(format!("{} is not a file", image.display()));
+        }
+    }
+    request(&command_state, |reply| BridgeCommand::AddBillboard {
+        billboard,
+        reply,
+    })
+    .await?
+}
+
+/// Remove billboard `id` added with `add_billboard`
+#[tauri::command]
+pub async fn remove_billboard(
+    command_state: State<'_, SharedCommandQueue>,
+    id: u64,
+) -> Result<(), String> {
+    request(&command_state, |reply| BridgeCommand::RemoveBillboard {
+        id,
+        reply,
+    })
+    .await?
+}
+
+/// Remove all billboards
+#[tauri::command]
+pub fn clear_billboards(command_state: State<SharedCommandQueue>) -> Result<(), String> {
+    command_state.send(BridgeCommand::ClearBillboards)
+}
+
+/// Set the translation, rotation or scale of entity `id` from
+/// `list_entities`, also available as
+/// `PUT frame://localhost/scene/<id>/transform`
+#[tauri::command]
+pub async fn set_entity_transform(
+    command_state: State<'_, SharedCommandQueue>,
+    id: u64,
+    transform: TransformUpdate,
+) -> Result<(), String> {
+    request(&command_state, |reply| BridgeCommand::SetEntityTransform {
+        id,
+        transform,
+        reply,
+    })
+    .await?
+}
+
+/// Make entity `child` a child of entity `parent`, e.g. to group the parts
+/// of a model; its transform is changed so it stays where it is
+#[tauri::command]
+pub async fn set_parent(
+    command_state: State<'_, SharedCommandQueue>,
+    child: u64,
+    parent: u64,
+) -> Result<(), String> {
+    request(&command_state, |reply| BridgeCommand::SetParent {
+        child,
+        parent,
+        reply,
+    })
+    .await?
+}
+
+/// Move entity `id` out of its parent to the root, keeping where it is
+#[tauri::command]
+pub async fn detach(command_state: State<'_, SharedCommandQueue>, id: u64) -> Result<(), String> {
+    request(&command_state, |reply| BridgeCommand::Detach { id, reply }).await?
+}
+
+/// Round translations and rotations set with `set_entity_transform` to a
+/// grid and to angle steps, e.g. `{ translate_step: 0.25, rotate_step: 15 }`;
+/// `null` steps turn that snapping off
+#[tauri::command]
+pub fn set_snapping(
+    command_state: State<SharedCommandQueue>,
+    settings: SnapSettings,
+) -> Result<(), String> {
+    let steps = [settings.translate_step, settings.rotate_step];
+    if !steps
+        .iter()
+        .flatten()
+        .all(|step| step.is_finite() && *step > 0.0)
+    {
+        return Err("Snapping steps must be positive".to_string());
+    }
+    command_state.send(BridgeCommand::SetSnapping(settings))
+}
+
+/// Every mesh hit by the ray from `origin` along `direction`, nearest
+/// first, for tools such as measuring or placing objects
+///
+/// Both are in world space and `direction` needn't be normalized. Hits are
+/// limited to `max_distance` from `origin` if given. Hidden meshes aren't
+/// hit, meshes outside the camera's view are.
+#[tauri::command]
+pub async fn raycast(
+    command_state: State<'_, SharedCommandQueue>,
+    origin: [f32; 3],
+    direction: [f32; 3],
+    max_distance: Option<f32>,
+) -> Result<Vec<RaycastHit>, String> {
+    if !origin
+        .iter()
+        .chain(&direction)
+        .all(|value| value.is_finite())
+    {
+        return Err("origin and direction must be finite".to_string());
+    }
+    if max_distance.is_some_and(|distance| distance.is_nan() || distance <= 0.0) {
+        return Err("max_distance must be positive".to_string());
+    }
+    request(&command_state, |reply| BridgeCommand::Raycast {
+        origin,
+        direction,
+        max_distance: max_distance.unwrap_or(f32::INFINITY),
+        reply,
+    })
+    .await?
+}
+
+/// The mesh entity under point `(x, y)` of the streamed image, with `x`
+/// and `y` from `0` to `1`, or `None` over the background
+///
+/// `method` defaults to `auto`: a ray cast, falling back to reading the
+/// object-ID buffer on the GPU where skinned or morphed meshes could be
+/// under the cursor, which takes a few frames.
+#[tauri::command]
+pub async fn pick_entity(
+    command_state: State<'_, SharedCommandQueue>,
+    x: f32,
+    y: f32,
+    method: Option<PickMethod>,
+) -> Result<Option<PickedEntity>, String> {
+    if !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y) {
+        return Err("x and y must be between 0 and 1".to_string());
+    }
+    request(&command_state, |reply| BridgeCommand::PickEntity {
+        x,
+        y,
+        method: method.unwrap_or_default(),
+        reply,
+    })
+    .await?
+}
+
+/// Undo the last scene edit and return the command that made it, such as
+/// `set_entity_transform`, or `None` if there is nothing to undo
+///
+/// Spawning, removing, moving, recoloring and hiding entities through
+/// commands are scene edits; a drag's transform changes are one edit.
+#[tauri::command]
+pub async fn undo(command_state: State<'_, SharedCommandQueue>) -> Result<Option<String>, String> {
+    request(&command_state, |reply| BridgeCommand::Undo { reply }).await
+}
+
+/// Redo the last undone scene edit and return the command that made it,
+/// or `None` if there is nothing to redo
+///
+/// Any new scene edit clears what there is to redo.
+#[tauri::command]
+pub async fn redo(command_state: State<'_, SharedCommandQueue>) -> Result<Option<String>, String> {
+    request(&command_state, |reply| BridgeCommand::Redo { reply }).await
+}
+
+/// Keep at most `depth` scene edits to undo, 100 by default, dropping the
+/// oldest; 0 turns undo off
+#[tauri::command]
+pub fn set_history_depth(
+    command_state: State<SharedCommandQueue>,
+    depth: usize,
+) -> Result<(), String> {
+    if depth > MAX_HISTORY_DEPTH {
+        return Err(format!("depth must be at most {}", MAX_HISTORY_DEPTH));
+    }
+    command_state.send(BridgeCommand::SetHistoryDepth(depth))
+}
+
+/// Lock the scene for kiosk or review sessions: commands that change it,
+/// such as `load_model`, `set_entity_transform` or `undo`, fail with a
+/// `scene_locked` error until it is unlocked, while camera moves and
+/// captures still work
+#[tauri::command]
+pub fn set_scene_locked(command_state: State<SharedCommandQueue>, locked: bool) {
+    command_state.set_locked(locked);
+    println!(
+        "[Tauri] Scene {}",
+        if locked { "locked" } else { "unlocked" }
+    );
+}
+
+/// Whether the scene is locked with `set_scene_locked`
+#[tauri::command]
+pub fn is_scene_locked(command_state: State<SharedCommandQueue>) -> bool {
+    command_state.is_locked()
+}
+
+/// Let other instances connect on `address`,
+/// `config::scene_sync::LISTEN_ADDRESS` by default, returning the address
+/// listened on
+///
+/// Scene edits are sent to connected instances from now on; edits made
+/// before aren't.
+#[tauri::command]
+pub fn start_scene_sync(
+    state: State<BridgeState>,
+    address: Option<String>,
+) -> Result<String, String> {
+    let address = address.as_deref().unwrap_or(SCENE_SYNC_ADDRESS);
+    state
+        .scene_sync
+        .listen(address, state.commands.clone())
+        .map(|address| address.to_string())
+        .map_err(|e| format!("Failed to listen on {}: {}", address, e))
+}
+
+/// Connect to the instance listening on `address`, exchanging the scene
+/// edits made so far and all later ones
+#[tauri::command]
+pub async fn connect_scene_sync(
+    state: State<'_, BridgeState>,
+    address: String,
+) -> Result<(), String> {
+    let (sync, commands) = (state.scene_sync.clone(), state.commands.clone());
+    tauri::async_runtime::spawn_blocking(move || {
+        sync.connect(&address, commands)
+            .map_err(|e| format!("Failed to connect to {}: {}", address, e))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Close the connections to other instances, leaving this one listening
+#[tauri::command]
+pub fn disconnect_scene_sync(state: State<BridgeState>) {
+    state.scene_sync.disconnect();
+}
+
+/// This instance's sync id, listening address and connected instances
+#[tauri::command]
+pub fn get_scene_sync_status(state: State<BridgeState>) -> SceneSyncStatus {
+    state.scene_sync.status()
+}
+
+/// Register WGSL `source` as custom shader `name`, replacing the shader
+/// of that name everywhere it is used
+///
+/// `source` is a Bevy material fragment shader, see
+/// `bevy/shaders/custom_shader.wgsl`. It is compiled when a mesh first
+/// uses it; compile errors show in `get_gpu_errors`.
+#[tauri::command]
+pub fn register_custom_shader(
+    command_state: State<SharedCommandQueue>,
+    name: String,
+    source: String,
+) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("name must not be empty".to_string());
+    }
+    if source.trim().is_empty() {
+        return Err("source must not be empty".to_string());
+    }
+    command_state.send(BridgeCommand::RegisterCustomShader { name, source })
+}
+
+/// Draw entity `id` from `list_entities` and everything below it with a
+/// registered custom shader, or with their own materials again if
+/// `material` is `null`
+///
+/// Returns how many meshes changed.
+#[tauri::command]
+pub async fn set_custom_shader_material(
+    command_state: State<'_, SharedCommandQueue>,
+    id: u64,
+    material: Option<CustomShaderMaterialSettings>,
+) -> Result<usize, String> {
+    if let Some(material) = &material {
+        if !material.params.iter().all(|p| p.is_finite()) {
+            return Err("params must be finite".to_string());
+        }
+    }
+    request(&command_state, |reply| {
+        BridgeCommand::SetCustomShaderMaterial {
+            id,
+            material,
+            reply,
+        }
+    })
+    .await?
+}
+
+/// List the meshes of `entity` and its descendants, such as the parts of
+/// a loaded model, for `set_submesh_material`
+#[tauri::command]
+pub async fn list_submeshes(
+    command_state: State<'_, SharedCommandQueue>,
+    entity: u64,
+) -> Result<Vec<SubmeshInfo>, String> {
+    request(&command_state, |reply| BridgeCommand::ListSubmeshes {
+        id: entity,
+        reply,
+    })
+    .await?
+}
+
+/// Draw submesh `entity` with a changed copy of its own material, e.g. to
+/// highlight one part of an assembly, or with `None` restore the materials
+/// of `entity` and everything below it
+#[tauri::command]
+pub async fn set_submesh_material(
+    command_state: State<'_, SharedCommandQueue>,
+    entity: u64,
+    material: Option<MaterialOverride>,
+) -> Result<(), String> {
+    if let Some(material) = &material {
+        let colors = material.color.iter().chain(&material.emissive);
+        if !colors.flatten().all(|c| (0.0..=1.0).contains(c)) {
+            return Err("color components must be between 0 and 1".to_string());
+        }
+        if material
+            .metallic
+            .is_some_and(|metallic| !(0.0..=1.0).contains(&metallic))
+        {
+            return Err("metallic must be between 0 and 1".to_string());
+        }
+        if material
+            .roughness
+            .is_some_and(|roughness| !(0.0..=1.0).contains(&roughness))
+        {
+            return Err("roughness must be between 0 and 1".to_string());
+        }
+    }
+    request(&command_state, |reply| BridgeCommand::SetSubmeshMaterial {
+        id: entity,
+        material,
+        reply,
+    })
+    .await?
+}
+
+/// Set morph target `target` of `entity` and its descendants, by index or
+/// name, to `weight`, and return how many meshes have the target
+///
+/// The weight eases to the new value over a few frames rather than
+/// jumping, so it can be driven straight from a slider.
+#[tauri::command]
+pub async fn set_morph_weight(
+    command_state: State<'_, SharedCommandQueue>,
+    entity: u64,
+    target: MorphTarget,
+    weight: f32,
+) -> Result<usize, String> {
+    if !weight.is_finite() {
+        return Err("weight must be finite".to_string());
+    }
+    request(&command_state, |reply| BridgeCommand::SetMorphWeight {
+        id: entity,
+        target,
+        weight,
+        reply,
+    })
+    .await?
+}
+
+/// List the morph targets of `entity` and its descendants, with their names
+/// and current weights, e.g. to build sliders for them
+#[tauri::command]
+pub async fn list_morph_targets(
+    command_state: State<'_, SharedCommandQueue>,
+    entity: u64,
+) -> Result<Vec<MorphTargetInfo>, String> {
+    request(&command_state, |reply| BridgeCommand::ListMorphTargets {
+        id: entity,
+        reply,
+    })
+    .await?
+}
+
+/// Start the particle fountain, or change its settings while it runs
+///
+/// Fields left out keep their current value, or the default when the
+/// emitter starts. `rate` is in particles per second, `gravity` in m/s².
+#[tauri::command]
+pub fn set_particle_emitter(
+    command_state: State<SharedCommandQueue>,
+    settings: ParticleEmitterUpdate,
+) -> Result<(), String> {
+    if settings
+        .rate
+        .is_some_and(|rate| !(rate.is_finite() && (0.0..=MAX_PARTICLE_RATE).contains(&rate)))
+    {
+        return Err(format!("rate must be between 0 and {}", MAX_PARTICLE_RATE));
+    }
+    if let Some(color) = &settings.color {
+        if !(3..=4).contains(&color.len()) {
+            return Err("color must have 3 or 4 components".to_string());
+        }
+        if !color.iter().all(|c| (0.0..=1.0).contains(c)) {
+            return Err("color components must be between 0 and 1".to_string());
+        }
+    }
+    if settings
+        .gravity
+        .is_some_and(|g| !g.iter().all(|g| g.is_finite()))
+    {
+        return Err("gravity must be finite".to_string());
+    }
+    if settings
+        .position
+        .is_some_and(|p| !p.iter().all(|p| p.is_finite()))
+    {
+        return Err("position must be finite".to_string());
+    }
+    command_state.send(BridgeCommand::SetParticleEmitter(settings))
+}
+
+/// Stop the particle fountain and remove its particles
+#[tauri::command]
+pub fn stop_particle_emitter(command_state: State<SharedCommandQueue>) -> Result<(), String> {
+    command_state.send(BridgeCommand::StopParticleEmitter)
+}
+
+/// Show a built-in scene instead of the current one or the open model
+#[tauri::command]
+pub fn set_demo_scene(
+    command_state: State<SharedCommandQueue>,
+    scene: DemoScene,
+) -> Result<(), String> {
+    command_state.send(BridgeCommand::SetDemoScene(scene))
+}
+
+/// Show the terrain scene generated from `seed`, or from the seed after
+/// the current one if `None`
+///
+/// Returns the seed used.
+#[tauri::command]
+pub async fn regenerate_terrain(
+    command_state: State<'_, SharedCommandQueue>,
+    seed: Option<u32>,
+) -> Result<u32, String> {
+    request(&command_state, |reply| BridgeCommand::RegenerateTerrain {
+        seed,
+        reply,
+    })
+    .await
+}
+
+/// List the names of all resources in the Bevy world
+#[tauri::command]
+pub async fn list_resources(
+    command_state: State<'_, SharedCommandQueue>,
+) -> Result<Vec<ResourceSummary>, String> {
+    request(&command_state, |reply| BridgeCommand::ListResources {
+        reply,
+    })
+    .await
+}
+
+/// Read known patterns back through every path of the readback pipeline
+/// and report the pixels that came back wrong
+///
+/// Checks row padding removal, the pack pass into each pixel format,
+/// BGRA textures, regions and sRGB encoding, which differ between GPUs.
+#[tauri::command]
+pub async fn run_readback_self_test(
+    command_state: State<'_, SharedCommandQueue>,
+) -> Result<ReadbackSelfTest, String> {
+    request(&command_state, |reply| BridgeCommand::RunReadbackSelfTest {
+        reply,
+    })
+    .await?
+}
+
+/// Start recording the stream to an MP4 or WebM file at `path`
+/// Emits `recording-progress` events with a [`RecordingProgress`] payload
+/// while the recording runs
+#[tauri::command]
+pub fn start_recording(
+    app: AppHandle,
+    recorder: State<Recorder>,
+    path: String,
+    options: Option<RecordingOptions>,
+) -> Result<(), String> {
+    recorder.start(path.into(), options.unwrap_or_default(), move |progress| {
+        let _ = app.emit("recording-progress", progress);
+    })
+}
+
+/// Stop recording and finalize the video file
+#[tauri::command]
+pub fn stop_recording(
+    recorder: State<Recorder>,
+    sinks: State<CaptureSinks>,
+) -> Result<RecordingProgress, String> {
+    let progress = recorder.stop()?;
+    sinks.notify(CaptureKind::Recording, &progress.path);
+    Ok(progress)
+}
+
+/// Write the last `seconds` of the stream (default: all buffered) to a GIF
+/// or APNG file at `path`
+#[tauri::command]
+pub async fn export_clip(
+    clip_state: State<'_, SharedClipBuffer>,
+    overlay_state: State<'_, SharedOverlay>,
+    sinks: State<'_, CaptureSinks>,
+    path: String,
+    format: ClipFormat,
+    seconds: Option<f64>,
+) -> Result<ClipSummary, String> {
+    let seconds = seconds.unwrap_or(BUFFER_SECONDS);
+    let clip = clip_state.inner().clone();
+    let overlay = overlay_state.current();
+    let summary = tauri::async_runtime::spawn_blocking(move || {
+        clip::export_clip(&clip, path.as_ref(), format, seconds, overlay.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    sinks.notify(CaptureKind::Clip, &summary.path);
+    Ok(summary)
+}
+
+/// Write the next `count` frames to `dir` as numbered image files
+/// The stream keeps running while frames are written. With `sidecars`,
+/// each image gets a JSON file with its camera and scene time
+#[tauri::command]
+pub async fn export_frames(
+    state: State<'_, BridgeState>,
+    dir: String,
+    count: u32,
+    format: ImageFileFormat,
+    sidecars: Option<bool>,
+) -> Result<ExportSummary, String> {
+    let state = state.inner().clone();
+    let sidecars = sidecars.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || {
+        let summary = frame_export::export_frames(&state, dir.as_ref(), count, format, sidecars)?;
+        if summary.frames_written > 0 {
+            state
+                .capture_sinks
+                .notify(CaptureKind::ImageSequence, &summary.dir);
+        }
+        Ok(summary)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Put the current frame on the OS clipboard as an image
+#[tauri::command]
+pub async fn copy_frame_to_clipboard(state: State<'_, BridgeState>) -> Result<(), String> {
+    let frame = state
+        .frame_buffer
+        .latest()
+        .ok_or_else(|| "No frame published yet".to_string())?;
+    let pool = state.buffer_pool.clone();
+    tauri::async_runtime::spawn_blocking(move || clipboard::copy_frame(&frame, &pool))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Drag the current frame out of the window as a PNG file
+/// Call from a mousedown handler, the drag takes over the held button
+#[tauri::command]
+pub async fn start_frame_drag(
+    window: WebviewWindow,
+    state: State<'_, BridgeState>,
+) -> Result<(), String> {
+    let frame = state
+        .frame_buffer
+        .latest()
+        .ok_or_else(|| "No frame published yet".to_string())?;
+    let pool = state.buffer_pool.clone();
+    let files = tauri::async_runtime::spawn_blocking(move || drag_out::prepare(&frame, &pool))
+        .await
+        .map_err(|e| e.to_string())??;
+    drag_out::start(&window, files)
+}
+
+/// Render the current view at `scale` (2-4) times the render resolution
+/// and downsample it into an anti-aliased screenshot
+/// Saved as PNG to `path` if given, returned as Base64 PNG otherwise
+#[tauri::command]
+pub async fn capture_screenshot(
+    command_state: State<'_, SharedCommandQueue>,
+    overlay_state: State<'_, SharedOverlay>,
+    sinks: State<'_, CaptureSinks>,
+    scale: Option<u32>,
+    path: Option<String>,
+) -> Result<Screenshot, String> {
+    let commands = command_state.inner().clone();
+    let overlay = overlay_state.current();
+    let screenshot = tauri::async_runtime::spawn_blocking(move || {
+        screenshot::capture_screenshot(
+            &commands,
+            overlay.as_deref(),
+            scale.unwrap_or(DEFAULT_SCALE),
+            path.as_deref().map(Path::new),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    if let Some(path) = &screenshot.path {
+        sinks.notify(CaptureKind::Screenshot, path);
+    }
+    Ok(screenshot)
+}
+
+/// Spin the camera once around the orbit center over `seconds` and write
+/// the frames to a video file, or numbered images in the directory `path`
+/// Scene animation advances by exactly `1 / fps` per frame
+#[tauri::command]
+pub async fn export_turntable(
+    state: State<'_, BridgeState>,
+    path: String,
+    seconds: f64,
+    format: TurntableFormat,
+    fps: Option<f64>,
+) -> Result<TurntableSummary, String> {
+    let state = state.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let summary = turntable::export_turntable(
+            &state,
+            path.as_ref(),
+            seconds,
+            fps.unwrap_or(TURNTABLE_FPS),
+            format,
+        )?;
+        state
+            .capture_sinks
+            .notify(CaptureKind::Turntable, &summary.path);
+        Ok(summary)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Render `jobs` one after another, each with its own camera view, size
+/// and output file
+/// Emits a `batch-progress` event with a [`batch::BatchProgress`] payload
+/// after each job; failed jobs carry an error instead of stopping the batch
+#[tauri::command]
+pub async fn render_batch(
+    app: AppHandle,
+    state: State<'_, BridgeState>,
+    jobs: Vec<RenderJob>,
+) -> Result<Vec<RenderJobResult>, String> {
+    let state = state.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        batch::render_batch(&state, &jobs, |progress| {
+            if progress.job.error.is_none() {
+                state
+                    .capture_sinks
+                    .notify(CaptureKind::BatchRender, &progress.job.path);
+            }
+            let _ = app.emit("batch-progress", progress);
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Set the logo/timestamp overlay drawn on recordings, clips, screenshots
+/// and batch renders, or remove it with `null`
+/// The live stream and image sequence exports are never overlaid
+#[tauri::command]
+pub fn set_capture_overlay(
+    overlay_state: State<SharedOverlay>,
+    settings: Option<OverlaySettings>,
+) -> Result<(), String> {
+    let overlay = settings.map(Overlay::load).transpose()?;
+    println!(
+        "[Tauri] Capture overlay {}",
+        if overlay.is_some() { "set" } else { "removed" }
+    );
+    overlay_state.set(overlay);
+    Ok(())
+}
+
+/// Save the scene, asset references, camera, camera presets and render
+/// settings to the project file `path`
+#[tauri::command]
+pub async fn save_project(
+    app: AppHandle,
+    command_state: State<'_, SharedCommandQueue>,
+    path: String,
+) -> Result<(), String> {
+    let project = request(&command_state, |reply| BridgeCommand::GetProject { reply }).await??;
+
+    let path = Path::new(&path);
+    project::save(path, &project)?;
+    project::add_recent(&app, path)?;
+    println!("[Tauri] Project saved to {}", path.display());
+    Ok(())
+}
+
+/// Restore a project saved with `save_project`
+/// Returns the names of saved objects the scene no longer has. A saved
+/// model that isn't open is loaded again, with `model-load-progress` events
+#[tauri::command]
+pub async fn open_project(
+    app: AppHandle,
+    command_state: State<'_, SharedCommandQueue>,
+    path: String,
+) -> Result<Vec<String>, String> {
+    let path = Path::new(&path);
+    let project = project::load(path)?;
+    let (progress, updates) = crossbeam_channel::unbounded::<ModelLoadProgress>();
+    forward_model_progress(app.clone(), updates);
+    let missing = request(&command_state, |reply| BridgeCommand::OpenProject {
+        project: Box::new(project),
+        progress,
+        reply,
+    })
+    .await?;
+
+    project::add_recent(&app, path)?;
+    println!("[Tauri] Project opened from {}", path.display());
+    Ok(missing)
+}
+
+/// Load a glTF, OBJ or STL file in place of the current model
+/// `path` is absolute or relative to the assets folder. Returns once
+/// loading started; `model-load-progress` events with a
+/// [`ModelLoadProgress`] payload follow until it is loaded or failed
+#[tauri::command]
+pub fn load_model(
+    app: AppHandle,
+    command_state: State<SharedCommandQueue>,
+    path: String,
+) -> Result<(), String> {
+    start_model_load(app, &command_state, path.into())
+}
+
+/// Pick a model with the native file dialog and load it like `load_model`
+/// Returns the picked path, or `None` if the dialog was cancelled
+#[tauri::command]
+pub async fn open_model_dialog(
+    app: AppHandle,
+    command_state: State<'_, SharedCommandQueue>,
+) -> Result<Option<String>, String> {
+    let dialog = app
+        .dialog()
+        .file()
+        .set_title("Open Model")
+        .add_filter("3D models", MODEL_EXTENSIONS);
+    let picked = tauri::async_runtime::spawn_blocking(move || dialog.blocking_pick_file())
+        .await
+        .map_err(|e| e.to_string())?;
+    let Some(picked) = picked else {
+        return Ok(None);
+    };
+    let path = picked.into_path().map_err(|e| e.to_string())?;
+    if let Some(dir) = path.parent() {
+        command_state.send(BridgeCommand::ApproveModelDir(dir.to_path_buf()))?;
+    }
+    start_model_load(app, &command_state, path.clone())?;
+    Ok(Some(path.display().to_string()))
+}
+
+/// Load the newest model in `dir` and again whenever a newer one is
+/// exported there, like `load_model`
+/// Replaces the folder watched so far
+#[tauri::command]
+pub fn watch_model_folder(
+    app: AppHandle,
+    command_state: State<SharedCommandQueue>,
+    watcher: State<ModelWatcher>,
+    dir: String,
+) -> Result<(), String> {
+    let command_state = command_state.inner().clone();
+    command_state.send(BridgeCommand::ApproveModelDir(dir.clone().into()))?;
+    watcher.start(dir.into(), move |path| {
+        if let Err(e) = start_model_load(app.clone(), &command_state, path) {
+            eprintln!("[Tauri] Failed to load a watched model: {}", e);
+        }
+    })
+}
+
+/// Stop watching the model folder, returning the folder if one was watched
+#[tauri::command]
+pub fn stop_watching_model_folder(watcher: State<ModelWatcher>) -> Result<Option<String>, String> {
+    Ok(watcher.stop()?.map(|dir| dir.display().to_string()))
+}
+
+/// Emit `asset-progress` events while models, environments, color LUTs or
+/// projects load, with the share loaded, the file being loaded and errors
+#[tauri::command]
+pub fn watch_asset_progress(
+    app: AppHandle,
+    command_state: State<SharedCommandQueue>,
+) -> Result<(), String> {
+    let (progress, updates) = crossbeam_channel::unbounded::<AssetProgress>();
+    command_state.send(BridgeCommand::WatchAssetProgress(progress))?;
+
+    // Ends when the watch is replaced
+    tauri::async_runtime::spawn_blocking(move || {
+        for update in updates {
+            let _ = app.emit("asset-progress", update);
+        }
+    });
+    Ok(())
+}
+
+/// Queue models, `.hdr` environments and textures to load in the
+/// background, so switching to a scene or preset using them is instant
+///
+/// Files load one at a time while nothing else is loading and stay in
+/// memory until `clear_prefetched_assets`.
+#[tauri::command]
+pub fn prefetch_assets(
+    command_state: State<SharedCommandQueue>,
+    assets: Vec<PrefetchAsset>,
+) -> Result<(), String> {
+    command_state.send(BridgeCommand::PrefetchAssets(assets))
+}
+
+/// Get the files queued with `prefetch_assets` and how far they got
+#[tauri::command]
+pub async fn get_prefetch_status(
+    command_state: State<'_, SharedCommandQueue>,
+) -> Result<PrefetchStatus, String> {
+    request(&command_state, |reply| BridgeCommand::GetPrefetchStatus {
+        reply,
+    })
+    .await
+}
+
+/// Drop the prefetch queue and the prefetched files no scene uses
+#[tauri::command]
+pub fn clear_prefetched_assets(command_state: State<SharedCommandQueue>) -> Result<(), String> {
+    command_state.send(BridgeCommand::ClearPrefetchedAssets)
+}
+
+/// Hand `path` to the Bevy thread and forward its progress as events
+fn start_model_load(
+    app: AppHandle,
+    command_state: &SharedCommandQueue,
+    path: PathBuf,
+) -> Result<(), String> {
+    let (progress, updates) = crossbeam_channel::unbounded::<ModelLoadProgress>();
+    command_state.send(BridgeCommand::LoadModel { path, progress })?;
+    forward_model_progress(app, updates);
+    Ok(())
+}
+
+/// Emit `updates` as `model-load-progress` events until the load ends, or
+/// none if no model is loaded
+fn forward_model_progress(app: AppHandle, updates: crossbeam_channel::Receiver<ModelLoadProgress>) {
+    tauri::async_runtime::spawn_blocking(move || {
+        for update in updates {
+            let done = update.is_final();
+            let _ = app.emit("model-load-progress", update);
+            if done {
+                break;
+            }
+        }
+    });
+}
+
+/// Write the visible scene to `path` as a binary glTF (.glb) file
+/// Meshes, materials, lights and transforms are exported, textures are not
+#[tauri::command]
+pub async fn export_gltf(
+    command_state: State<'_, SharedCommandQueue>,
+    path: String,
+) -> Result<GltfExportSummary, String> {
+    let (glb, mut summary) =
+        request(&command_state, |reply| BridgeCommand::ExportGltf { reply }).await??;
+
+    let path = Path::new(&path);
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    std::fs::write(path, glb).map_err(|e| format!("{}: {}", path.display(), e))?;
+    summary.path = path.display().to_string();
+    println!("[Tauri] Scene exported to {}", summary.path);
+    Ok(summary)
+}
+
+/// Write the visible scene to `path` as a .usdz file for AR Quick Look,
+/// limited to the entities named in `names` and their children if given
+/// Base color textures are baked with their material's color
+#[tauri::command]
+pub async fn export_usdz(
+    command_state: State<'_, SharedCommandQueue>,
+    path: String,
+    names: Option<Vec<String>>,
+) -> Result<UsdzExportSummary, String> {
+    let scene = request(&command_state, |reply| BridgeCommand::ExportUsdz {
+        names,
+        reply,
+    })
+    .await??;
+
+    let path = Path::new(&path);
+    let package = usdz::package(&scene)?;
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    std::fs::write(path, package).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut summary = scene.summary;
+    summary.path = path.display().to_string();
+    println!("[Tauri] Scene exported to {}", summary.path);
+    Ok(summary)
+}
+
+/// Write collision meshes for the loaded model to `path` as a .glb file,
+/// for game engines to collide against
+///
+/// Builds convex hulls of at most 255 vertices by default, or decimated
+/// copies of the meshes with `shape: "decimated"`, one per mesh or one for
+/// the whole model with `merge`. Meshes are in the model's space and named
+/// after their source with a `_collision` suffix.
+#[tauri::command]
+pub async fn export_collision_meshes(
+    command_state: State<'_, SharedCommandQueue>,
+    path: String,
+    settings: Option<CollisionMeshSettings>,
+) -> Result<CollisionExportSummary, String> {
+    let settings = settings.unwrap_or_default();
+    if settings
+        .resolution
+        .is_some_and(|resolution| !(1..=MAX_COLLISION_RESOLUTION).contains(&resolution))
+    {
+        return Err(format!(
+            "resolution must be between 1 and {}",
+            MAX_COLLISION_RESOLUTION
+        ));
+    }
+    let (glb, mut summary) = request(&command_state, |reply| {
+        BridgeCommand::GenerateCollisionMeshes { settings, reply }
+    })
+    .await??;
+
+    let path = Path::new(&path);
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    std::fs::write(path, glb).map_err(|e| format!("{}: {}", path.display(), e))?;
+    summary.path = path.display().to_string();
+    println!("[Tauri] Collision meshes exported to {}", summary.path);
+    Ok(summary)
+}
+
+/// Paths of recently saved or opened projects, newest first
+#[tauri::command]
+pub fn get_recent_projects(app: AppHandle) -> Vec<String> {
+    project::recent(&app)
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect()
+}
+
+/// Save the current camera view as preset `name`, replacing any preset of
+/// that name, and keep it on disk for later sessions
+/// Returns the names of all presets
+#[tauri::command]
+pub async fn save_camera_preset(
+    app: AppHandle,
+    command_state: State<'_, SharedCommandQueue>,
+    name: String,
+) -> Result<Vec<String>, String> {
+    if name.trim().is_empty() {
+        return Err("Preset name must not be empty".to_string());
+    }
+    let presets = request(&command_state, |reply| BridgeCommand::SaveCameraPreset {
+        name,
+        reply,
+    })
+    .await?;
+
+    camera_presets::save(&app, &presets)?;
+    Ok(presets.into_keys().collect())
+}
+
+/// Move the camera to preset `name`, smoothly unless `animate` is false
+#[tauri::command]
+pub async fn apply_camera_preset(
+    command_state: State<'_, SharedCommandQueue>,
+    name: String,
+    animate: Option<bool>,
+) -> Result<(), String> {
+    request(&command_state, |reply| BridgeCommand::ApplyCameraPreset {
+        name,
+        animate: animate.unwrap_or(true),
+        reply,
+    })
+    .await?
+}
+
+/// Center the orbit camera on a named scene entity, e.g. `small_cube`
+///
+/// With `track` the camera keeps following the entity until another preset,
+/// entity or camera path is chosen. Moves smoothly unless `animate` is false.
+#[tauri::command]
+pub async fn look_at_entity(
+    command_state: State<'_, SharedCommandQueue>,
+    name: String,
+    track: Option<bool>,
+    animate: Option<bool>,
+) -> Result<(), String> {
+    request(&command_state, |reply| BridgeCommand::LookAtEntity {
+        name,
+        track: track.unwrap_or(false),
+        animate: animate.unwrap_or(true),
+        reply,
+    })
+    .await?
+}
+
+/// Orbit around the model surface under a point of the stream
+///
+/// `x` and `y` are fractions of the displayed image, from its top left.
+/// The camera keeps its position and turns to the picked point, which is
+/// returned. Moves smoothly unless `animate` is false.
+#[tauri::command]
+pub async fn set_orbit_pivot(
+    command_state: State<'_, SharedCommandQueue>,
+    x: f32,
+    y: f32,
+    animate: Option<bool>,
+) -> Result<[f32; 3], String> {
+    if !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y) {
+        return Err("x and y must be between 0 and 1".to_string());
+    }
+    request(&command_state, |reply| BridgeCommand::SetOrbitPivot {
+        x,
+        y,
+        animate: animate.unwrap_or(true),
+        reply,
+    })
+    .await?
+}
+
+/// Set how long animated camera moves take and how they ease
+///
+/// Applies to moves to presets, entities, standard views and picked points
+/// started afterwards. A `duration_s` of `0` makes them jump.
+#[tauri::command]
+pub fn set_camera_transition(
+    command_state: State<SharedCommandQueue>,
+    duration_s: f32,
+    easing: Option<CameraEasing>,
+) -> Result<(), String> {
+    if !(duration_s.is_finite() && duration_s >= 0.0) {
+        return Err("duration_s must be zero or positive".to_string());
+    }
+    command_state.send(BridgeCommand::SetCameraTransition(CameraTransitionSettings {
+        duration_s,
+        easing: easing.unwrap_or_default(),
+    }))
+}
+
+/// Turn the camera to a standard view, e.g. `top`, keeping its center and
+/// distance
+/// Moves smoothly unless `animate` is false. `orthographic` switches the
+/// projection at the same time, for CAD-style inspection.
+#[tauri::command]
+pub fn set_standard_view(
+    command_state: State<SharedCommandQueue>,
+    view: StandardView,
+    animate: Option<bool>,
+    orthographic: Option<bool>,
+) -> Result<(), String> {
+    command_state.send(BridgeCommand::SetStandardView {
+        view,
+        animate: animate.unwrap_or(true),
+        projection: orthographic.map(|orthographic| {
+            if orthographic {
+                ProjectionKind::Orthographic
+            } else {
+                ProjectionKind::Perspective
+            }
+        }),
+    })
+}
+
+/// Switch the camera between `perspective` and `orthographic` projection
+#[tauri::command]
+pub fn set_projection(
+    command_state: State<SharedCommandQueue>,
+    projection: ProjectionKind,
+) -> Result<(), String> {
+    command_state.send(BridgeCommand::SetProjection(projection))
+}
+
+/// Shake the camera like a handheld shot, layered over orbit control,
+/// camera paths and animated moves
+///
+/// `amplitude_deg` is the peak rotation, `decay` how fast the shake dies
+/// down per second, `0` to keep shaking until `stop_camera_effects`.
+/// Defaults come from `config::camera`.
+#[tauri::command]
+pub fn shake_camera(
+    command_state: State<SharedCommandQueue>,
+    amplitude_deg: Option<f32>,
+    frequency_hz: Option<f32>,
+    decay: Option<f32>,
+) -> Result<(), String> {
+    let amplitude_deg = amplitude_deg.unwrap_or(SHAKE_AMPLITUDE_DEG);
+    let frequency_hz = frequency_hz.unwrap_or(SHAKE_FREQUENCY_HZ);
+    let decay = decay.unwrap_or(SHAKE_DECAY);
+    if !(amplitude_deg.is_finite() && amplitude_deg > 0.0) {
+        return Err("amplitude_deg must be positive".to_string());
+    }
+    if !(frequency_hz.is_finite() && frequency_hz > 0.0) {
+        return Err("frequency_hz must be positive".to_string());
+    }
+    if !(decay.is_finite() && decay >= 0.0) {
+        return Err("decay must be zero or positive".to_string());
+    }
+    command_state.send(BridgeCommand::ShakeCamera {
+        amplitude_deg,
+        frequency_hz,
+        decay,
+    })
+}
+
+/// Slowly move the camera toward the orbit center
+///
+/// `amount` is the fraction of the distance to cover, over `duration_s`.
+/// Dragging or scrolling stops it.
+#[tauri::command]
+pub fn push_in(
+    command_state: State<SharedCommandQueue>,
+    amount: Option<f32>,
+    duration_s: Option<f32>,
+) -> Result<(), String> {
+    if amount.is_some_and(|amount| !(amount > 0.0 && amount < 1.0)) {
+        return Err("amount must be between 0 and 1".to_string());
+    }
+    if duration_s.is_some_and(|duration_s| !(duration_s.is_finite() && duration_s > 0.0)) {
+        return Err("duration_s must be positive".to_string());
+    }
+    command_state.send(BridgeCommand::PushIn { amount, duration_s })
+}
+
+/// End camera shake and push-in
+#[tauri::command]
+pub fn stop_camera_effects(command_state: State<SharedCommandQueue>) -> Result<(), String> {
+    command_state.send(BridgeCommand::StopCameraEffects)
+}
+
+/// Scale how fast dragging and scrolling move the camera
+///
+/// Fields left out stay at their defaults of `1`, `invert_y` at `false`.
+/// An input recording keeps the sensitivity it was started with.
+#[tauri::command]
+pub fn set_camera_sensitivity(
+    command_state: State<SharedCommandQueue>,
+    sensitivity: CameraSensitivity,
+) -> Result<(), String> {
+    let speeds = [sensitivity.rotate, sensitivity.zoom, sensitivity.pan];
+    if speeds
+        .iter()
+        .any(|speed| !(speed.is_finite() && *speed > 0.0))
+    {
+        return Err("rotate, zoom and pan must be positive".to_string());
+    }
+    command_state.send(BridgeCommand::SetCameraSensitivity(sensitivity))
+}
+
+/// Show top, front and side orthographic views next to the perspective view,
+/// each in a quadrant of the stream
+#[tauri::command]
+pub fn set_quad_view(
+    command_state: State<SharedCommandQueue>,
+    enabled: bool,
+) -> Result<(), String> {
+    command_state.send(BridgeCommand::SetQuadView(enabled))
+}
+
+/// Current camera placement, projection and view-projection matrix
+///
+/// For drawing HTML overlays over points of the scene: multiply a world
+/// point by `view_projection`, divide by `w` and map `-1..1` to the
+/// stream's width and height, flipping `y`.
+#[tauri::command]
+pub async fn get_camera_state(
+    command_state: State<'_, SharedCommandQueue>,
+) -> Result<CameraState, String> {
+    request(&command_state, |reply| BridgeCommand::GetCameraState {
+        reply,
+    })
+    .await?
+}
+
+/// Level the camera after rolling it with a right-button drag
+#[tauri::command]
+pub fn reset_roll(command_state: State<SharedCommandQueue>) -> Result<(), String> {
+    command_state.send(BridgeCommand::ResetRoll)
+}
+
+/// Show or hide the debug overlay: FPS, frame number, render and stream
+/// resolution, and camera position
+///
+/// The overlay is drawn by Bevy into the stream frames, so it is also in
+/// recordings, clips and exported frames.
+#[tauri::command]
+pub fn set_debug_overlay(
+    command_state: State<SharedCommandQueue>,
+    enabled: bool,
+) -> Result<(), String> {
+    command_state.send(BridgeCommand::SetDebugOverlay(enabled))
+}
+
+/// Draw Bevy UI elements into the stream: labels, toolbars, legends and
+/// scale bars, each in a corner of the view, replacing the previous ones.
+/// `None` removes them
+///
+/// Like the debug overlay they are part of the stream frames, so also of
+/// recordings and clips. Screenshots, turntables and batch renders get them
+/// too unless `clean_captures` is set.
+#[tauri::command]
+pub fn set_ui_overlay(
+    command_state: State<SharedCommandQueue>,
+    settings: Option<UiOverlaySettings>,
+) -> Result<(), String> {
+    if let Some(settings) = &settings {
+        if settings.elements.len() > MAX_UI_ELEMENTS {
+            return Err(format!("At most {} elements are allowed", MAX_UI_ELEMENTS));
+        }
+        for element in &settings.elements {
+            match element {
+                UiElement::Label { font_size, .. } => {
+                    if font_size.is_some_and(|size| !size.is_finite() || size <= 0.0) {
+                        return Err("font_size must be positive".to_string());
+                    }
+                }
+                UiElement::Toolbar { .. } => {}
+                UiElement::Legend { entries, .. } => {
+                    for entry in entries {
+                        if !matches!(entry.color.len(), 3 | 4) {
+                            return Err("Legend colors must have 3 or 4 components".to_string());
+                        }
+                    }
+                }
+                UiElement::ScaleBar { length, .. } => {
+                    if !length.is_finite() || *length <= 0.0 {
+                        return Err("Scale bar length must be positive".to_string());
+                    }
+                }
+            }
+        }
+    }
+    command_state.send(BridgeCommand::SetUiOverlay(settings))
+}
+
+/// Show a debug view of the scene in the stream: `"depth"`, `"normals"`,
+/// `"uvs"`, `"overdraw"` or `"shadow_cascades"`, or `"off"` for the lit
+/// scene
+///
+/// Only meshes with Bevy's standard material are affected.
+#[tauri::command]
+pub fn set_debug_view(
+    command_state: State<SharedCommandQueue>,
+    mode: DebugView,
+) -> Result<(), String> {
+    command_state.send(BridgeCommand::SetDebugView(mode))
+}
+
+/// Count the meshes each camera drew, hid or culled last frame, for
+/// tracking down objects that disappear
+///
+/// Meshes outside a camera's render layers aren't counted for it.
+#[tauri::command]
+pub async fn get_culling_stats(
+    command_state: State<'_, SharedCommandQueue>,
+) -> Result<Vec<CameraCullingStats>, String> {
+    request(&command_state, |reply| BridgeCommand::GetCullingStats {
+        reply,
+    })
+    .await
+}
+
+/// Freeze the stream camera's culling frustum where it is, or let it
+/// follow the camera again
+///
+/// While frozen the camera still moves and renders, culling as if it
+/// hadn't, so orbiting away shows which objects the frozen view culls.
+#[tauri::command]
+pub fn freeze_culling(
+    command_state: State<SharedCommandQueue>,
+    frozen: bool,
+) -> Result<(), String> {
+    command_state.send(BridgeCommand::FreezeCulling(frozen))
+}
+
+/// Set the camera exposure in EV100, or Bevy's default if `ev100` is `null`
+///
+/// Lower values brighten the image, each step of `1` doubles the
+/// brightness. Screenshots and exports use the same exposure.
+#[tauri::command]
+pub fn set_exposure(
+    command_state: State<SharedCommandQueue>,
+    ev100: Option<f32>,
+) -> Result<(), String> {
+    if ev100.is_some_and(|ev100| !ev100.is_finite()) {
+        return Err("ev100 must be finite".to_string());
+    }
+    command_state.send(BridgeCommand::SetExposure(ev100))
+}
+
+/// Replace the scene's lights with a built-in rig: `default`, `studio`
+/// (three-point), `outdoor` (sun and sky), `night` or `flat` (ambient
+/// only)
+///
+/// The old rig is removed and the new one added between two frames, so
+/// no frame shows a mix of both. The preset is saved with projects.
+#[tauri::command]
+pub fn set_lighting_preset(
+    command_state: State<SharedCommandQueue>,
+    name: LightingPreset,
+) -> Result<(), String> {
+    command_state.send(BridgeCommand::SetLightingPreset(name))
+}
+
+/// Light the scene from an equirectangular `.hdr` environment, so
+/// metallic and glossy surfaces have something to reflect, or stop if
+/// `environment` is `null`
+///
+/// Resolves once the environment is loaded and filtered, which takes a
+/// moment for large files. Screenshots and exports use it too.
+#[tauri::command]
+pub async fn set_environment_map(
+    command_state: State<'_, SharedCommandQueue>,
+    environment: Option<EnvironmentMapSettings>,
+) -> Result<(), String> {
+    if let Some(environment) = &environment {
+        if environment
+            .intensity
+            .is_some_and(|i| !i.is_finite() || i < 0.0)
+        {
+            return Err("intensity must be a positive number".to_string());
+        }
+        if !environment.rotation_deg.is_finite() {
+            return Err("rotation_deg must be finite".to_string());
+        }
+    }
+    request_within(
+        &command_state,
+        LOAD_TIMEOUT,
+        "the environment to load",
+        |reply| BridgeCommand::SetEnvironmentMap { environment, reply },
+    )
+    .await?
+}
+
+/// Replace the reflection probes: boxes lit by their own `.hdr`
+/// environment instead of the one set with `set_environment_map`
+///
+/// An empty list removes them. Resolves once every environment is loaded.
+#[tauri::command]
+pub async fn set_reflection_probes(
+    command_state: State<'_, SharedCommandQueue>,
+    probes: Vec<ReflectionProbeSettings>,
+) -> Result<(), String> {
+    if probes.len() > MAX_REFLECTION_PROBES {
+        return Err(format!(
+            "At most {} reflection probes are supported",
+            MAX_REFLECTION_PROBES
+        ));
+    }
+    for probe in &probes {
+        if !probe.center.iter().all(|c| c.is_finite())
+            || !probe.size.iter().all(|s| s.is_finite() && *s > 0.0)
+        {
+            return Err("Probe center must be finite and size positive".to_string());
+        }
+        if probe.intensity.is_some_and(|i| !i.is_finite() || i < 0.0) {
+            return Err("intensity must be a positive number".to_string());
+        }
+    }
+    request_within(
+        &command_state,
+        LOAD_TIMEOUT,
+        "the environments to load",
+        |reply| BridgeCommand::SetReflectionProbes { probes, reply },
+    )
+    .await?
+}
+
+/// Put a shadow catcher ground under the scene, or remove it if
+/// `settings` is `null`
+///
+/// The ground is invisible except for the shadows on it, so models stand
+/// on the background instead of floating. It goes at the bottom of what is
+/// in the scene now unless `height` is given; set it again after opening
+/// another model.
+#[tauri::command]
+pub fn set_ground_plane(
+    command_state: State<SharedCommandQueue>,
+    settings: Option<GroundPlaneSettings>,
+) -> Result<(), String> {
+    if let Some(settings) = settings {
+        if settings.height.is_some_and(|height| !height.is_finite()) {
+            return Err("height must be finite".to_string());
+        }
+        if settings
+            .size
+            .is_some_and(|size| !size.is_finite() || size <= 0.0)
+        {
+            return Err("size must be positive".to_string());
+        }
+        if settings
+            .shadow_opacity
+            .is_some_and(|opacity| !(0.0..=1.0).contains(&opacity))
+        {
+            return Err("shadow_opacity must be between 0 and 1".to_string());
+        }
+    }
+    command_state.send(BridgeCommand::SetGroundPlane(settings))
+}
+
+/// Put an animated, reflective water plane under the scene, or remove it
+/// if `settings` is `null`
+///
+/// The reflection is an extra render pass every frame, at
+/// `config::water::REFLECTION_SCALE` of the render resolution. Like the
+/// ground plane, the water goes at the bottom of the scene unless `height`
+/// is given.
+#[tauri::command]
+pub fn set_water(
+    command_state: State<SharedCommandQueue>,
+    settings: Option<WaterSettings>,
+) -> Result<(), String> {
+    if let Some(settings) = &settings {
+        if settings.height.is_some_and(|height| !height.is_finite()) {
+            return Err("height must be finite".to_string());
+        }
+        if settings
+            .size
+            .is_some_and(|size| !size.is_finite() || size <= 0.0)
+        {
+            return Err("size must be positive".to_string());
+        }
+        if let Some(color) = &settings.color {
+            if color.len() != 3 {
+                return Err("color must have 3 components".to_string());
+            }
+            if !color.iter().all(|c| (0.0..=1.0).contains(c)) {
+                return Err("color components must be between 0 and 1".to_string());
+            }
+        }
+        if settings
+            .wave_strength
+            .is_some_and(|strength| !(0.0..=1.0).contains(&strength))
+        {
+            return Err("wave_strength must be between 0 and 1".to_string());
+        }
+    }
+    command_state.send(BridgeCommand::SetWater(settings))
+}
+
+/// Render the background transparent instead of its color
+///
+/// Only RGBA raw frames (see `set_stream_format`) and PNG captures keep
+/// the transparency; JPEG frames show black.
+#[tauri::command]
+pub fn set_transparent_background(
+    command_state: State<SharedCommandQueue>,
+    enabled: bool,
+) -> Result<(), String> {
+    command_state.send(BridgeCommand::SetTransparentBackground(enabled))
+}
+
+/// Blur what is nearer or farther than `focal_distance` from the camera
+///
+/// Lower `aperture` f-stops give a shallower focus. Screenshots and exports
+/// use the same setting.
+#[tauri::command]
+pub fn set_depth_of_field(
+    command_state: State<SharedCommandQueue>,
+    focal_distance: f32,
+    aperture: f32,
+) -> Result<(), String> {
+    if !(focal_distance.is_finite() && focal_distance > 0.0) {
+        return Err("focal_distance must be positive".to_string());
+    }
+    if !(aperture.is_finite() && aperture > 0.0) {
+        return Err("aperture must be positive".to_string());
+    }
+    command_state.send(BridgeCommand::SetDepthOfField(Some(DepthOfFieldSettings {
+        focal_distance,
+        aperture,
+    })))
+}
+
+/// Turn depth of field off
+#[tauri::command]
+pub fn clear_depth_of_field(command_state: State<SharedCommandQueue>) -> Result<(), String> {
+    command_state.send(BridgeCommand::SetDepthOfField(None))
+}
+
+/// Focus depth of field on the surface under a point of the stream
+///
+/// `x` and `y` are fractions of the displayed image, from its top left.
+/// Returns the new focal distance.
+#[tauri::command]
+pub async fn focus_depth_of_field(
+    command_state: State<'_, SharedCommandQueue>,
+    x: f32,
+    y: f32,
+) -> Result<f32, String> {
+    if !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y) {
+        return Err("x and y must be between 0 and 1".to_string());
+    }
+    request(&command_state, |reply| BridgeCommand::FocusDepthOfField {
+        x,
+        y,
+        reply,
+    })
+    .await?
+}
+
+/// Fade distant surfaces into a fog color
+///
+/// Linear fog goes from none at `start` to full at `end`; exponential
+/// falloffs start at the camera and hide 95% of the scene at `end`.
+/// Screenshots and exports use the same fog.
+#[tauri::command]
+pub fn set_fog(command_state: State<SharedCommandQueue>, fog: FogSettings) -> Result<(), String> {
+    if !fog.color.iter().all(|c| (0.0..=1.0).contains(c)) {
+        return Err("color components must be between 0 and 1".to_string());
+    }
+    if !(fog.start.is_finite() && fog.start >= 0.0) {
+        return Err("start must not be negative".to_string());
+    }
+    if !(fog.end.is_finite() && fog.end > fog.start) {
+        return Err("end must be beyond start".to_string());
+    }
+    command_state.send(BridgeCommand::SetFog(Some(fog)))
+}
+
+/// Turn distance fog off
+#[tauri::command]
+pub fn clear_fog(command_state: State<SharedCommandQueue>) -> Result<(), String> {
+    command_state.send(BridgeCommand::SetFog(None))
+}
+
+/// Grade the stream through a `.cube` 3D LUT exported from a grading tool,
+/// or stop if `path` is `null`
+///
+/// The LUT is applied after tonemapping, to the sRGB image, like grading
+/// tools expect. Resolves once it is loaded. Screenshots and exports use
+/// it too.
+#[tauri::command]
+pub async fn set_color_lut(
+    command_state: State<'_, SharedCommandQueue>,
+    path: Option<PathBuf>,
+) -> Result<(), String> {
+    if let Some(path) = &path {
+        if !path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("cube"))
+        {
+            return Err("Color LUTs must be .cube files".to_string());
+        }
+    }
+    request_within(
+        &command_state,
+        LUT_LOAD_TIMEOUT,
+        "the LUT to load",
+        |reply| BridgeCommand::SetColorLut { path, reply },
+    )
+    .await?
+}
+
+/// Center and zoom the orbit camera to fit everything visible in the scene
+///
+/// Moves smoothly unless `animate` is false.
+#[tauri::command]
+pub async fn frame_all(
+    command_state: State<'_, SharedCommandQueue>,
+    animate: Option<bool>,
+) -> Result<(), String> {
+    request(&command_state, |reply| BridgeCommand::FrameAll {
+        animate: animate.unwrap_or(true),
+        reply,
+    })
+    .await?
+}
+
+/// Fly the camera through `keyframes` along a smooth curve, replacing any
+/// running path
+/// Emits `camera-path-finished` with a [`CameraPathEnd`] payload when the
+/// path ends, was stopped or was replaced
+#[tauri::command]
+pub async fn play_camera_path(
+    app: AppHandle,
+    command_state: State<'_, SharedCommandQueue>,
+    keyframes: Vec<CameraKeyframe>,
+) -> Result<(), String> {
+    let (finished, ended) = crossbeam_channel::bounded::<CameraPathEnd>(1);
+    request(&command_state, |reply| BridgeCommand::PlayCameraPath {
+        keyframes,
+        reply,
+        finished,
+    })
+    .await??;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Ok(end) = ended.recv() {
+            let _ = app.emit("camera-path-finished", end);
+        }
+    });
+    Ok(())
+}
+
+/// Pause the running camera path, keeping the camera where it is
+#[tauri::command]
+pub fn pause_camera_path(command_state: State<SharedCommandQueue>) -> Result<(), String> {
+    command_state.send(BridgeCommand::SetCameraPathPaused(true))
+}
+
+/// Continue a paused camera path
+#[tauri::command]
+pub fn resume_camera_path(command_state: State<SharedCommandQueue>) -> Result<(), String> {
+    command_state.send(BridgeCommand::SetCameraPathPaused(false))
+}
+
+/// Jump to `time_s` on the running camera path, clamped to its keyframes
+#[tauri::command]
+pub fn seek_camera_path(
+    command_state: State<SharedCommandQueue>,
+    time_s: f64,
+) -> Result<(), String> {
+    if !time_s.is_finite() {
+        return Err("time_s must be finite".to_string());
+    }
+    command_state.send(BridgeCommand::SeekCameraPath(time_s))
+}
+
+/// End the running camera path, leaving the camera where it is
+#[tauri::command]
+pub fn stop_camera_path(command_state: State<SharedCommandQueue>) -> Result<(), String> {
+    command_state.send(BridgeCommand::StopCameraPath)
+}
+
+/// Start recording camera input for a later deterministic replay
+#[tauri::command]
+pub fn start_input_recording(command_state: State<SharedCommandQueue>) -> Result<(), String> {
+    command_state.send(BridgeCommand::StartInputRecording)
+}
+
+/// Stop recording camera input and save the recording as JSON to `path`
+/// Returns the number of recorded input samples
+#[tauri::command]
+pub async fn stop_input_recording(
+    command_state: State<'_, SharedCommandQueue>,
+    path: String,
+) -> Result<usize, String> {
+    let recording = request(&command_state, |reply| BridgeCommand::StopInputRecording {
+        reply,
+    })
+    .await?
+    .ok_or_else(|| "No input recording is running".to_string())?;
+
+    let file = std::fs::File::create(&path).map_err(|e| format!("{}: {}", path, e))?;
+    serde_json::to_writer(std::io::BufWriter::new(file), &recording).map_err(|e| e.to_string())?;
+    println!(
+        "[Tauri] Saved {} input samples ({:.1}s) to {}",
+        recording.samples.len(),
+        recording.end_time_s - recording.start_time_s,
+        path
+    );
+    Ok(recording.samples.len())
+}
@@ -0,0 +1,509 @@
+//! Frame and control server for clients outside the webview
+//!
+//! With the `control_server` feature, a TCP server on a local port streams
+//! frames and accepts commands, so Python scripts and test rigs can drive
+//! and observe the renderer without a browser. Every message, in either
+//! direction, is a JSON header followed by a binary payload, each prefixed
+//! with its length as a big-endian `u32`:
+//!
+//! ```text
+//! [header length][JSON header][payload length][payload]
+//! ```
+//!
+//! Requests name a `command` next to its arguments and may carry an `id`
+//! that is echoed in the reply, e.g.
+//! `{"id": 1, "command": "set_exposure", "ev100": 12.0}` with an empty
+//! payload. Replies are `{"type": "reply", "id": 1, "result": ...}` or
+//! `{"type": "error", "id": 1, "message": "..."}`. After
+//! `subscribe_frames`, every new frame is pushed as
+//! `{"type": "frame", "frame": {...}}` with its pixels as payload.
+//!
+//! Headers can be MessagePack instead of JSON, e.g. for large scene trees
+//! from `list_entities`. Replies use the format of their request, and
+//! pushed frames that of the `subscribe_frames` request.
+
+use crossbeam_channel::Sender;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::io::{self, BufReader, ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::config::control_server::{FRAME_WAIT, MAX_MESSAGE_BYTES, WRITE_TIMEOUT};
+use crate::config::screenshot::TIMEOUT;
+use super::shared_state::{
+    animate_default, BridgeCommand, BridgeState, CameraState, DebugView, EntityInspection,
+    EntitySummary, Frame, FrameMetadata, MouseInput, PerformanceStats, PixelFormat, ProjectionKind,
+    StandardView,
+};
+use super::frame_header::FrameHeader;
+use super::wire_format::WireFormat;
+
+/// A request header
+#[derive(Deserialize)]
+struct Request {
+    /// Echoed in the reply so clients can match replies to requests
+    #[serde(default)]
+    id: Option<Value>,
+    #[serde(flatten)]
+    command: Command,
+}
+
+/// Commands a client can send, with the arguments of the Tauri command of
+/// the same name
+#[derive(Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum Command {
+    /// Push every new frame to this connection, with `header` prefixing
+    /// pixels with a binary frame header
+    SubscribeFrames {
+        #[serde(default)]
+        header: bool,
+    },
+    /// Stop pushing frames
+    UnsubscribeFrames,
+    /// Reply with the latest frame, pixels as payload
+    GetFrame {
+        #[serde(default)]
+        header: bool,
+    },
+    GetStats,
+    GetCameraState,
+    /// Add mouse input as `send_mouse_input` does
+    MouseInput(MouseInput),
+    SetStandardView {
+        view: StandardView,
+        #[serde(default = "animate_default")]
+        animate: bool,
+        #[serde(default)]
+        projection: Option<ProjectionKind>,
+    },
+    SetProjection {
+        projection: ProjectionKind,
+    },
+    SetPixelFormat {
+        format: PixelFormat,
+    },
+    SetExposure {
+        ev100: Option<f32>,
+    },
+    SetDebugView {
+        mode: DebugView,
+    },
+    SetQuadView {
+        enabled: bool,
+    },
+    SetDebugOverlay {
+        enabled: bool,
+    },
+    ResetRoll,
+    FrameAll {
+        #[serde(default = "animate_default")]
+        animate: bool,
+    },
+    ApplyCameraPreset {
+        name: String,
+        #[serde(default = "animate_default")]
+        animate: bool,
+    },
+    ListEntities,
+    InspectEntity {
+        id: u64,
+    },
+}
+
+/// How frames are pushed to a connection, `None` while it isn't
+/// subscribed
+type Subscription = Mutex<Option<FrameSubscription>>;
+
+#[derive(Clone, Copy)]
+struct FrameSubscription {
+    format: WireFormat,
+    /// Prefix pixels with a binary frame header
+    header: bool,
+}
+
+/// What a handled command replies with
+enum Reply {
+    Done,
+    Value(ReplyValue),
+    Frame { frame: Arc<Frame>, header: bool },
+}
+
+/// Result of a command that replies with data, serialized as the data
+/// itself in either format
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ReplyValue {
+    Stats(PerformanceStats),
+    CameraState(CameraState),
+    Entities(Vec<EntitySummary>),
+    Entity(EntityInspection),
+}
+
+/// A reply or pushed frame header
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Header<'a, T> {
+    Reply {
+        id: &'a Option<Value>,
+        result: T,
+    },
+    Error {
+        id: &'a Option<Value>,
+        message: String,
+    },
+    Frame {
+        frame: T,
+    },
+}
+
+/// Everything about a frame but its pixels
+#[derive(Serialize)]
+struct FrameInfo<'a> {
+    id: u64,
+    timestamp_ms: f64,
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    metadata: &'a Option<FrameMetadata>,
+}
+
+impl<'a> From<&'a Frame> for FrameInfo<'a> {
+    fn from(frame: &'a Frame) -> Self {
+        Self {
+            id: frame.id,
+            timestamp_ms: frame.timestamp_ms,
+            width: frame.width,
+            height: frame.height,
+            format: frame.format,
+            metadata: &frame.metadata,
+        }
+    }
+}
+
+/// Listen on `address` and serve each client on its own thread
+pub fn start_control_server(state: BridgeState, address: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    println!("[Control] Listening on {}", listener.local_addr()?);
+    thread::Builder::new()
+        .name("control-server".into())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let state = state.clone();
+                let result = stream.and_then(|stream| {
+                    thread::Builder::new()
+                        .name("control-client".into())
+                        .spawn(move || {
+                            if let Err(e) = serve(stream, state) {
+                                eprintln!("[Control] Connection failed: {}", e);
+                            }
+                        })
+                        .map(drop)
+                });
+                if let Err(e) = result {
+                    eprintln!("[Control] Failed to accept a client: {}", e);
+                }
+            }
+        })?;
+    Ok(())
+}
+
+/// Answer requests until the client disconnects, pushing frames from a
+/// second thread while subscribed
+fn serve(stream: TcpStream, state: BridgeState) -> io::Result<()> {
+    // A client that stops reading fails the write instead of blocking it
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let writer = Arc::new(Mutex::new(stream.try_clone()?));
+    let subscribed = Arc::new(Subscription::default());
+    let closed = Arc::new(AtomicBool::new(false));
+
+    let pusher = {
+        let (state, writer) = (state.clone(), writer.clone());
+        let (subscribed, closed) = (subscribed.clone(), closed.clone());
+        thread::Builder::new()
+            .name("control-frames".into())
+            .spawn(move || {
+                let result = push_frames(&state, &writer, &subscribed, &closed);
+                // A failed or timed out write may have left half a message,
+                // so the connection can't be used anymore
+                if result.is_err() {
+                    if let Ok(stream) = writer.lock() {
+                        let _ = stream.shutdown(Shutdown::Both);
+                    }
+                }
+                result
+            })?
+    };
+
+    let result = answer_requests(stream, &state, &writer, &subscribed);
+    closed.store(true, Ordering::Relaxed);
+    // The pusher only fails to write once the client is gone or stalled
+    let _ = pusher.join();
+    result
+}
+
+fn answer_requests(
+    stream: TcpStream,
+    state: &BridgeState,
+    writer: &Mutex<TcpStream>,
+    subscribed: &Subscription,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    loop {
+        // The payload is unused by all current commands
+        let (header, _payload) = match read_message(&mut reader) {
+            Ok(message) => message,
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let format = WireFormat::detect(&header);
+        let (id, reply) = match format.from_slice::<Request>(&header) {
+            Ok(request) => (
+                request.id,
+                handle(request.command, format, state, subscribed),
+            ),
+            Err(e) => (None, Err(format!("Invalid request: {}", e))),
+        };
+        write_reply(writer, format, id, reply)?;
+    }
+}
+
+/// Write each new frame to the client while it is subscribed
+fn push_frames(
+    state: &BridgeState,
+    writer: &Mutex<TcpStream>,
+    subscribed: &Subscription,
+    closed: &AtomicBool,
+) -> io::Result<()> {
+    let mut sequence = 0;
+    let mut last_id = 0;
+    while !closed.load(Ordering::Relaxed) {
+        let Some(newer) = state.frame_signal.wait_newer_timeout(sequence, FRAME_WAIT) else {
+            continue;
+        };
+        sequence = newer;
+        let Some(subscription) = subscribed
+            .lock()
+            .ok()
+            .and_then(|subscription| *subscription)
+        else {
+            continue;
+        };
+        if let Some(frame) = state
+            .frame_buffer
+            .latest()
+            .filter(|frame| frame.id > last_id)
+        {
+            last_id = frame.id;
+            let header = Header::Frame {
+                frame: FrameInfo::from(&*frame),
+            };
+            write_message(
+                writer,
+                subscription.format,
+                &header,
+                &frame_payload(&frame, subscription.header),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn handle(
+    command: Command,
+    format: WireFormat,
+    state: &BridgeState,
+    subscribed: &Subscription,
+) -> Result<Reply, String> {
+    let send = |command: BridgeCommand| state.commands.send(command).map(|()| Reply::Done);
+    match command {
+        Command::SubscribeFrames { header } => {
+            *subscribed.lock().map_err(|e| e.to_string())? =
+                Some(FrameSubscription { format, header });
+            Ok(Reply::Done)
+        }
+        Command::UnsubscribeFrames => {
+            *subscribed.lock().map_err(|e| e.to_string())? = None;
+            Ok(Reply::Done)
+        }
+        Command::GetFrame { header } => state
+            .frame_buffer
+            .latest()
+            .map(|frame| Reply::Frame { frame, header })
+            .ok_or_else(|| "No frame published yet".to_string()),
+        Command::GetStats => {
+            let stats = state
+                .perf_stats
+                .0
+                .lock()
+                .map_err(|e| e.to_string())?
+                .clone();
+            Ok(Reply::Value(ReplyValue::Stats(stats)))
+        }
+        Command::GetCameraState => request(state, |reply| BridgeCommand::GetCameraState { reply })?
+            .map(|camera| Reply::Value(ReplyValue::CameraState(camera))),
+        Command::MouseInput(input) => {
+            state.mouse_input.add(&input)?;
+            Ok(Reply::Done)
+        }
+        Command::SetStandardView {
+            view,
+            animate,
+            projection,
+        } => send(BridgeCommand::SetStandardView {
+            view,
+            animate,
+            projection,
+        }),
+        Command::SetProjection { projection } => send(BridgeCommand::SetProjection(projection)),
+        Command::SetPixelFormat { format } => send(BridgeCommand::SetPixelFormat(format)),
+        Command::SetExposure { ev100 } => {
+            if ev100.is_some_and(|ev100| !ev100.is_finite()) {
+                return Err("ev100 must be finite".to_string());
+            }
+            send(BridgeCommand::SetExposure(ev100))
+        }
+        Command::SetDebugView { mode } => send(BridgeCommand::SetDebugView(mode)),
+        Command::SetQuadView { enabled } => send(BridgeCommand::SetQuadView(enabled)),
+        Command::SetDebugOverlay { enabled } => send(BridgeCommand::SetDebugOverlay(enabled)),
+        Command::ResetRoll => send(BridgeCommand::ResetRoll),
+        Command::FrameAll { animate } => {
+            request(state, |reply| BridgeCommand::FrameAll { animate, reply })??;
+            Ok(Reply::Done)
+        }
+        Command::ApplyCameraPreset { name, animate } => {
+            request(state, |reply| BridgeCommand::ApplyCameraPreset {
+                name,
+                animate,
+                reply,
+            })??;
+            Ok(Reply::Done)
+        }
+        Command::ListEntities => request(state, |reply| BridgeCommand::ListEntities { reply })
+            .map(|entities| Reply::Value(ReplyValue::Entities(entities))),
+        Command::InspectEntity { id } => {
+            request(state, |reply| BridgeCommand::InspectEntity { id, reply })?
+                .map(|entity| Reply::Value(ReplyValue::Entity(entity)))
+        }
+    }
+}
+
+/// Apply one request header as the server would, for hosts embedding the
+/// renderer in-process
+///
+/// `id` is ignored, `get_frame` replies with the frame's metadata only and
+/// frame subscriptions have no effect, since there is no connection to
+/// push frames to.
+pub fn execute(request: &str, state: &BridgeState) -> Result<Value, String> {
+    let request: Request = serde_json::from_str(request).map_err(|e| e.to_string())?;
+    let subscribed = Subscription::default();
+    let value = match handle(request.command, WireFormat::Json, state, &subscribed)? {
+        Reply::Done => Ok(Value::Null),
+        Reply::Value(value) => serde_json::to_value(value),
+        Reply::Frame { frame, .. } => serde_json::to_value(FrameInfo::from(&*frame)),
+    };
+    value.map_err(|e| e.to_string())
+}
+
+/// Send the command built around a reply channel and wait for the reply
+fn request<T>(
+    state: &BridgeState,
+    command: impl FnOnce(Sender<T>) -> BridgeCommand,
+) -> Result<T, String> {
+    let (reply, response) = crossbeam_channel::bounded(1);
+    state.commands.send(command(reply))?;
+    response
+        .recv_timeout(TIMEOUT)
+        .map_err(|_| "Timed out waiting for the Bevy thread".to_string())
+}
+
+/// Pixels of `frame`, after a binary frame header if `header`
+fn frame_payload(frame: &Frame, header: bool) -> Cow<'_, [u8]> {
+    if header {
+        Cow::Owned(FrameHeader::raw(frame).prepend(&frame.data))
+    } else {
+        Cow::Borrowed(&frame.data)
+    }
+}
+
+fn write_reply(
+    writer: &Mutex<TcpStream>,
+    format: WireFormat,
+    id: Option<Value>,
+    reply: Result<Reply, String>,
+) -> io::Result<()> {
+    match reply {
+        Ok(Reply::Done) => write_message(
+            writer,
+            format,
+            &Header::Reply {
+                id: &id,
+                result: (),
+            },
+            &[],
+        ),
+        Ok(Reply::Value(result)) => {
+            write_message(writer, format, &Header::Reply { id: &id, result }, &[])
+        }
+        Ok(Reply::Frame { frame, header }) => write_message(
+            writer,
+            format,
+            &Header::Reply {
+                id: &id,
+                result: FrameInfo::from(&*frame),
+            },
+            &frame_payload(&frame, header),
+        ),
+        Err(message) => write_message(
+            writer,
+            format,
+            &Header::<()>::Error { id: &id, message },
+            &[],
+        ),
+    }
+}
+
+/// Read a header and its payload
+fn read_message(reader: &mut impl Read) -> io::Result<(Vec<u8>, Vec<u8>)> {
+    let header = read_part(reader)?;
+    let payload = read_part(reader)?;
+    Ok((header, payload))
+}
+
+fn read_part(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut length = [0; 4];
+    reader.read_exact(&mut length)?;
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_MESSAGE_BYTES {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("Message part of {} bytes is too large", length),
+        ));
+    }
+    let mut data = vec![0; length];
+    reader.read_exact(&mut data)?;
+    Ok(data)
+}
+
+/// Write a header in `format` and its payload in one go, so replies and
+/// pushed frames don't interleave
+fn write_message(
+    writer: &Mutex<TcpStream>,
+    format: WireFormat,
+    header: &impl Serialize,
+    payload: &[u8],
+) -> io::Result<()> {
+    let header = format.to_vec(header).map_err(io::Error::other)?;
+    let mut stream = writer
+        .lock()
+        .map_err(|_| io::Error::other("Connection writer poisoned"))?;
+    stream.write_all(&(header.len() as u32).to_be_bytes())?;
+    stream.write_all(&header)?;
+    stream.write_all(&(payload.len() as u32).to_be_bytes())?;
+    stream.write_all(payload)?;
+    stream.flush()
+}
@@ -0,0 +1,102 @@
+//! Dragging frames out of the window
+//!
+//! Other apps accept dropped images as files, so a drag first writes the
+//! current frame to a PNG in the temp directory, along with a thumbnail
+//! shown under the cursor, and then hands the file to the platform's drag
+//! and drop through the `drag` crate. Each drag replaces the files of the
+//! previous one; by then the drop target has copied or opened them.
+
+use image::{imageops, ExtendedColorType, RgbImage};
+use std::path::PathBuf;
+use tauri::WebviewWindow;
+use tracing::info_span;
+
+use crate::config::drag_out::{DIR_NAME, ICON_WIDTH};
+use super::encoder::convert_to_rgb8;
+use super::shared_state::{Frame, SharedBufferPool};
+
+/// Files written for one drag
+pub struct DragFiles {
+    /// The frame at full resolution, what the drop target receives
+    pub image: PathBuf,
+    /// Thumbnail following the cursor
+    pub icon: PathBuf,
+}
+
+/// Write `frame` and its thumbnail to the drag directory
+pub fn prepare(frame: &Frame, pool: &SharedBufferPool) -> Result<DragFiles, String> {
+    let _span = info_span!("prepare_frame_drag").entered();
+    let dir = std::env::temp_dir().join(DIR_NAME);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let files = DragFiles {
+        image: dir.join(format!("frame-{}.png", frame.id)),
+        icon: dir.join("icon.png"),
+    };
+
+    let converted = convert_to_rgb8(frame, pool);
+    let rgb = converted.as_deref().unwrap_or(&frame.data);
+    let result = write_files(&files, rgb, frame.width, frame.height);
+    if let Some(converted) = converted {
+        pool.recycle(converted);
+    }
+    result.map(|()| files)
+}
+
+fn write_files(files: &DragFiles, rgb: &[u8], width: u32, height: u32) -> Result<(), String> {
+    image::save_buffer_with_format(
+        &files.image,
+        rgb,
+        width,
+        height,
+        ExtendedColorType::Rgb8,
+        image::ImageFormat::Png,
+    )
+    .map_err(|e| format!("{}: {}", files.image.display(), e))?;
+
+    let frame = RgbImage::from_raw(width, height, rgb.to_vec())
+        .ok_or_else(|| "Frame data does not match its size".to_string())?;
+    let icon_width = ICON_WIDTH.min(width);
+    let icon_height = (height * icon_width / width.max(1)).max(1);
+    imageops::thumbnail(&frame, icon_width, icon_height)
+        .save_with_format(&files.icon, image::ImageFormat::Png)
+        .map_err(|e| format!("{}: {}", files.icon.display(), e))
+}
+
+/// Start dragging `files` from `window`
+///
+/// Must be called while a mouse button is held over the window, which
+/// the platform hands over to the drag. Drags start on the main thread,
+/// so failing to start one is logged rather than returned.
+pub fn start(window: &WebviewWindow, files: DragFiles) -> Result<(), String> {
+    let target = window.clone();
+    window
+        .run_on_main_thread(move || {
+            // GTK drags start from the GTK window, not the raw handle
+            #[cfg(target_os = "linux")]
+            let source = target.gtk_window();
+            #[cfg(not(target_os = "linux"))]
+            let source = tauri::Result::Ok(target);
+
+            let started = source.map_err(|e| e.to_string()).and_then(|source| {
+                drag::start_drag(
+                    &source,
+                    drag::DragItem::Files(vec![files.image]),
+                    drag::Image::File(files.icon),
+                    |result, _cursor| {
+                        if let drag::DragResult::Dropped = result {
+                            println!("[Tauri] Frame dropped outside the window");
+                        }
+                    },
+                    drag::Options::default(),
+                )
+                .map_err(|e| e.to_string())
+            });
+            if let Err(e) = started {
+                eprintln!("[Tauri] Failed to start dragging the frame: {}", e);
+            }
+        })
+        .map_err(|e| e.to_string())
+}
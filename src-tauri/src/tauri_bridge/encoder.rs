@@ -0,0 +1,137 @@
+//! Background frame encoder
+//!
+//! JPEG compression used to run inside every `frame://` request, so a slow
+//! encode delayed the response and concurrent requests encoded the same frame
+//! more than once. A single long-lived worker now encodes the newest frame
+//! into a shared slot, and protocol handlers only copy the finished bytes.
+
+use image::{codecs::jpeg::JpegEncoder, ImageEncoder};
+use std::thread;
+use tracing::info_span;
+
+use crate::config::compression::JPEG_QUALITY;
+use super::shared_state::{BridgeState, EncodedFrame, Frame, PixelFormat, SharedBufferPool};
+
+/// Start the encoder thread
+///
+/// The thread sleeps until Bevy publishes a frame. Frames that arrive while
+/// an encode is in progress are skipped, so the slot always converges on the
+/// latest frame instead of building up a queue.
+pub fn start_encoder(state: BridgeState) {
+    thread::Builder::new()
+        .name("frame-encoder".into())
+        .spawn(move || {
+            println!("[Encoder] Thread started");
+            let mut last_seen = 0;
+            while let Some(sequence) = state.frame_signal.wait_newer(last_seen) {
+                last_seen = sequence;
+                encode_latest_frame(&state);
+            }
+            println!("[Encoder] Frame signal lost, stopping");
+        })
+        .expect("Failed to spawn encoder thread");
+}
+
+/// Encode the current frame buffer contents into the encoded frame slot
+fn encode_latest_frame(state: &BridgeState) {
+    let _span = info_span!("encode_frame").entered();
+    let pool = &state.buffer_pool;
+    let encode_start = std::time::Instant::now();
+
+    let Some(frame) = state.frame_buffer.latest() else {
+        return;
+    };
+
+    // JPEG has no alpha channel. Frames are normally packed to RGB on the
+    // GPU already; other formats are converted here as a fallback.
+    let converted = convert_to_rgb8(&frame, pool);
+    let rgb_data = converted.as_deref().unwrap_or(&frame.data);
+
+    // A presenting shared surface replaces the viewport's JPEG, only stream
+    // clients still need one
+    if state.shared_surface.is_presenting() {
+        state.stream_clients.encode(&frame, rgb_data);
+        if let Some(converted) = converted {
+            pool.recycle(converted);
+        }
+        return;
+    }
+
+    // Compress RGB to JPEG - reduces ~1.8MB to ~50-100KB!
+    let previous_size = state
+        .encoded_frame
+        .0
+        .lock()
+        .ok()
+        .and_then(|slot| slot.as_ref().map(|encoded| encoded.data.len()))
+        .unwrap_or_default();
+    let mut jpeg_data = Vec::with_capacity(previous_size);
+    let encoder = JpegEncoder::new_with_quality(&mut jpeg_data, JPEG_QUALITY);
+    let result = info_span!("jpeg_encode").in_scope(|| {
+        encoder.write_image(
+            rgb_data,
+            frame.width,
+            frame.height,
+            image::ExtendedColorType::Rgb8,
+        )
+    });
+    match result {
+        Ok(()) => {
+            if let Ok(mut slot) = state.encoded_frame.0.lock() {
+                *slot = Some(EncodedFrame {
+                    frame_id: frame.id,
+                    timestamp_ms: frame.timestamp_ms,
+                    width: frame.width,
+                    height: frame.height,
+                    data: jpeg_data,
+                });
+            }
+            if let Ok(mut stats) = state.perf_stats.0.lock() {
+                stats.jpeg_encode_ms = encode_start.elapsed().as_secs_f64() * 1000.0;
+            }
+        }
+        Err(e) => eprintln!("[Encoder] JPEG encoding failed: {}", e),
+    }
+
+    // Copies for stream clients with their own size and quality, once the
+    // main stream has its frame
+    state.stream_clients.encode(&frame, rgb_data);
+    if let Some(converted) = converted {
+        pool.recycle(converted);
+    }
+}
+
+/// Convert a frame to tightly packed RGB8
+///
+/// Returns `None` when the frame already is RGB8. Converted buffers come
+/// from `pool` and should be recycled by the caller.
+pub fn convert_to_rgb8(frame: &Frame, pool: &SharedBufferPool) -> Option<Vec<u8>> {
+    match frame.format {
+        PixelFormat::Rgb8 => None,
+        PixelFormat::Rgba8 => {
+            let _span = info_span!("rgba_to_rgb").entered();
+            let mut rgb_data = pool.take(frame.data.len() / 4 * 3);
+            for pixel in frame.data.chunks_exact(4) {
+                rgb_data.extend_from_slice(&pixel[..3]);
+            }
+            Some(rgb_data)
+        }
+        PixelFormat::Rgb565 => {
+            let _span = info_span!("rgb565_to_rgb").entered();
+            let mut rgb_data = pool.take(frame.data.len() / 2 * 3);
+            for pixel in frame.data.chunks_exact(2) {
+                let value = u16::from_le_bytes([pixel[0], pixel[1]]);
+                // Replicate the high bits into the low ones so 0x1F maps to 0xFF
+                let r = (value >> 11) as u8 & 0x1F;
+                let g = (value >> 5) as u8 & 0x3F;
+                let b = value as u8 & 0x1F;
+                rgb_data.extend_from_slice(&[
+                    (r << 3) | (r >> 2),
+                    (g << 2) | (g >> 4),
+                    (b << 3) | (b >> 2),
+                ]);
+            }
+            Some(rgb_data)
+        }
+    }
+}
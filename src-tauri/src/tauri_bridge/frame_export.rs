@@ -0,0 +1,213 @@
+//! Image sequence export
+//!
+//! Writes upcoming frames to numbered image files while the stream keeps
+//! running, for assembling a video offline (`ffmpeg -i %05d.png`) or for
+//! regression baselines. Frames are picked up as they are published and
+//! handed to a few writer threads, since PNG encoding is slower than the
+//! render loop at full resolution.
+//!
+//! With sidecars enabled, every image gets a JSON file of the same name
+//! describing the camera and scene time it was rendered with, for
+//! compositing or as labels for training data.
+
+use crossbeam_channel::bounded;
+use image::ExtendedColorType;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tracing::info_span;
+
+use crate::config::export::{FRAME_TIMEOUT, QUEUE_LENGTH, WRITER_THREADS};
+use super::encoder::convert_to_rgb8;
+use super::shared_state::{BridgeState, Frame, FrameMetadata, SharedBufferPool};
+
+/// Image file format for exported frames
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFileFormat {
+    /// Lossless, use for baselines
+    Png,
+    /// Much smaller and faster to write, lossy
+    Jpeg,
+}
+
+impl ImageFileFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFileFormat::Png => "png",
+            ImageFileFormat::Jpeg => "jpg",
+        }
+    }
+}
+
+/// Result of `export_frames`
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct ExportSummary {
+    pub dir: String,
+    /// Files written, fewer than requested if publishing stopped
+    pub frames_written: u32,
+    /// Frames published during the export that could not be picked up
+    /// because the writers fell behind
+    pub frames_missed: u64,
+    pub first_frame_id: Option<u64>,
+    pub last_frame_id: Option<u64>,
+}
+
+/// Contents of a frame's JSON sidecar
+#[derive(Serialize, Clone, Debug)]
+pub struct FrameSidecar {
+    pub frame_id: u64,
+    /// Publication time in milliseconds since the Unix epoch
+    pub timestamp_ms: f64,
+    pub width: u32,
+    pub height: u32,
+    /// Camera and scene time, left out when unknown
+    #[serde(flatten)]
+    pub metadata: Option<FrameMetadata>,
+}
+
+impl FrameSidecar {
+    pub fn new(frame: &Frame) -> Self {
+        Self {
+            frame_id: frame.id,
+            timestamp_ms: frame.timestamp_ms,
+            width: frame.width,
+            height: frame.height,
+            metadata: frame.metadata,
+        }
+    }
+}
+
+/// Write the sidecar of `frame` to `path`
+pub fn save_sidecar(frame: &Frame, path: &Path) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(&FrameSidecar::new(frame)).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Path of the `number`th image in a sequence, e.g. `00042.png`
+pub fn frame_path(dir: &Path, number: u32, extension: &str) -> PathBuf {
+    dir.join(format!("{:05}.{}", number, extension))
+}
+
+/// Write a single frame to `path`
+pub fn save_frame(
+    frame: &Frame,
+    pool: &SharedBufferPool,
+    path: &Path,
+    format: ImageFileFormat,
+) -> Result<(), String> {
+    let _span = info_span!("save_frame").entered();
+    let converted = convert_to_rgb8(frame, pool);
+    let rgb = converted.as_deref().unwrap_or(&frame.data);
+    let result = image::save_buffer_with_format(
+        path,
+        rgb,
+        frame.width,
+        frame.height,
+        ExtendedColorType::Rgb8,
+        match format {
+            ImageFileFormat::Png => image::ImageFormat::Png,
+            ImageFileFormat::Jpeg => image::ImageFormat::Jpeg,
+        },
+    );
+    if let Some(converted) = converted {
+        pool.recycle(converted);
+    }
+    result.map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Write the next `count` published frames to `dir` as `00000.png`,
+/// `00001.png`, ..., along with `00000.json`, ... if `sidecars` is set
+///
+/// Blocks until all frames are written, or until no frame was published
+/// for `FRAME_TIMEOUT` (e.g. a static scene with identical frames skipped).
+pub fn export_frames(
+    state: &BridgeState,
+    dir: &Path,
+    count: u32,
+    format: ImageFileFormat,
+    sidecars: bool,
+) -> Result<ExportSummary, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    println!("[Export] Writing {} frames to {}", count, dir.display());
+
+    let mut summary = ExportSummary {
+        dir: dir.display().to_string(),
+        ..Default::default()
+    };
+    let error = Mutex::new(None);
+    let (sender, receiver) = bounded::<(u32, Arc<Frame>)>(QUEUE_LENGTH);
+
+    thread::scope(|scope| {
+        for _ in 0..WRITER_THREADS {
+            let receiver = receiver.clone();
+            let error = &error;
+            scope.spawn(move || {
+                for (number, frame) in receiver {
+                    let path = frame_path(dir, number, format.extension());
+                    let mut result = save_frame(&frame, &state.buffer_pool, &path, format);
+                    if sidecars && result.is_ok() {
+                        result = save_sidecar(&frame, &path.with_extension("json"));
+                    }
+                    if let Err(e) = result {
+                        if let Ok(mut error) = error.lock() {
+                            error.get_or_insert(e);
+                        }
+                        break;
+                    }
+                }
+            });
+        }
+
+        // Only frames published from now on
+        let mut last_seen = 0;
+        let mut last_id = state.frame_buffer.latest().map_or(0, |frame| frame.id);
+        while summary.frames_written < count {
+            if error.lock().map_or(true, |error| error.is_some()) {
+                break;
+            }
+            let Some(sequence) = state
+                .frame_signal
+                .wait_newer_timeout(last_seen, FRAME_TIMEOUT)
+            else {
+                eprintln!(
+                    "[Export] No frame published for {:?}, stopping early",
+                    FRAME_TIMEOUT
+                );
+                break;
+            };
+            last_seen = sequence;
+            let Some(frame) = state.frame_buffer.latest() else {
+                continue;
+            };
+
+            if frame.id <= last_id {
+                continue;
+            }
+            if summary.first_frame_id.is_some() {
+                summary.frames_missed += frame.id - last_id - 1;
+            }
+            last_id = frame.id;
+            summary.first_frame_id.get_or_insert(frame.id);
+            summary.last_frame_id = Some(frame.id);
+
+            // Blocks while the writers are behind, later frames are then
+            // counted as missed
+            if sender.send((summary.frames_written, frame)).is_err() {
+                break;
+            }
+            summary.frames_written += 1;
+        }
+        drop(sender);
+    });
+
+    if let Some(e) = error.into_inner().ok().flatten() {
+        return Err(e);
+    }
+    println!(
+        "[Export] Wrote {} frames to {} ({} missed)",
+        summary.frames_written, summary.dir, summary.frames_missed
+    );
+    Ok(summary)
+}
@@ -0,0 +1,174 @@
+//! Frame hashes for golden-image checks
+//!
+//! Every frame gets two hashes: an exact one over its pixel bytes, which
+//! changes with any pixel, and a perceptual one, a 64-bit difference hash
+//! of a 9x8 luma thumbnail that survives small differences such as
+//! driver-dependent rounding. Perceptual hashes are compared by the number
+//! of differing bits.
+//!
+//! Baselines are saved as JSON in the `baselines` folder of the app data
+//! directory, so users can check that an update or a different GPU still
+//! renders the scene they expect, without a CI setup.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::config::frame_hash::BASELINE_DIRECTORY;
+use super::shared_state::{Frame, PixelFormat};
+
+/// Thumbnail size of the perceptual hash, one column more than bits per row
+const THUMB_WIDTH: usize = 9;
+const THUMB_HEIGHT: usize = 8;
+
+/// Hashes of one frame
+///
+/// Hashes are hex strings, JavaScript numbers can't hold 64 bits.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FrameHash {
+    pub frame_id: u64,
+    pub width: u32,
+    pub height: u32,
+    /// Exact hashes only match frames of the same format
+    pub format: PixelFormat,
+    /// xxh3 of the pixel data
+    pub exact: String,
+    /// Difference hash of the frame's luma
+    pub perceptual: String,
+}
+
+/// Result of checking the current frame against a baseline
+#[derive(Serialize, Clone, Debug)]
+pub struct BaselineComparison {
+    /// Same size and `distance` within `tolerance`
+    pub passed: bool,
+    pub exact_match: bool,
+    /// Differing perceptual hash bits, 0 to 64
+    pub distance: u32,
+    pub tolerance: u32,
+    pub baseline: FrameHash,
+    pub current: FrameHash,
+}
+
+/// Hash `frame`
+pub fn hash_frame(frame: &Frame) -> FrameHash {
+    FrameHash {
+        frame_id: frame.id,
+        width: frame.width,
+        height: frame.height,
+        format: frame.format,
+        exact: format!("{:016x}", xxh3_64(&frame.data)),
+        perceptual: format!("{:016x}", difference_hash(frame)),
+    }
+}
+
+/// Compare `current` with the saved baseline `name`
+pub fn compare(
+    app: &AppHandle,
+    name: &str,
+    current: FrameHash,
+    tolerance: u32,
+) -> Result<BaselineComparison, String> {
+    let path = baseline_path(app, name)?;
+    let json = std::fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let baseline: FrameHash =
+        serde_json::from_slice(&json).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let distance = match (
+        u64::from_str_radix(&baseline.perceptual, 16),
+        u64::from_str_radix(&current.perceptual, 16),
+    ) {
+        (Ok(baseline), Ok(current)) => (baseline ^ current).count_ones(),
+        _ => return Err(format!("{}: invalid perceptual hash", path.display())),
+    };
+    let same_size = baseline.width == current.width && baseline.height == current.height;
+    Ok(BaselineComparison {
+        passed: same_size && distance <= tolerance,
+        exact_match: same_size
+            && baseline.format == current.format
+            && baseline.exact == current.exact,
+        distance,
+        tolerance,
+        baseline,
+        current,
+    })
+}
+
+/// Save `hash` as baseline `name`, replacing an earlier one
+pub fn save_baseline(app: &AppHandle, name: &str, hash: &FrameHash) -> Result<PathBuf, String> {
+    let path = baseline_path(app, name)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    let json = serde_json::to_vec_pretty(hash).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// File baseline `name` is saved to
+pub fn baseline_path(app: &AppHandle, name: &str) -> Result<PathBuf, String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        && !name.starts_with('.');
+    if !valid {
+        return Err(format!(
+            "Invalid baseline name {:?}, use letters, digits, '-', '_' and '.'",
+            name
+        ));
+    }
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(BASELINE_DIRECTORY).join(format!("{}.json", name)))
+        .map_err(|e| e.to_string())
+}
+
+/// 64-bit difference hash: each bit tells whether a thumbnail pixel is
+/// brighter than its right neighbour
+fn difference_hash(frame: &Frame) -> u64 {
+    let width = frame.width as usize;
+    let height = frame.height as usize;
+    let mut sums = [[0u64; THUMB_WIDTH]; THUMB_HEIGHT];
+    let mut counts = [[0u64; THUMB_WIDTH]; THUMB_HEIGHT];
+    let bytes_per_pixel = frame.format.bytes_per_pixel();
+
+    for (index, pixel) in frame.data.chunks_exact(bytes_per_pixel).enumerate() {
+        let (x, y) = (index % width, index / width);
+        if y >= height {
+            break;
+        }
+        let (cell_x, cell_y) = (x * THUMB_WIDTH / width, y * THUMB_HEIGHT / height);
+        sums[cell_y][cell_x] += u64::from(luma(pixel, frame.format));
+        counts[cell_y][cell_x] += 1;
+    }
+
+    let mut hash = 0u64;
+    for (row_sums, row_counts) in sums.iter().zip(&counts) {
+        let mean = |x: usize| row_sums[x] / row_counts[x].max(1);
+        for x in 0..THUMB_WIDTH - 1 {
+            hash = (hash << 1) | u64::from(mean(x) > mean(x + 1));
+        }
+    }
+    hash
+}
+
+/// Rec. 601 luma of one pixel, 0 to 255
+fn luma(pixel: &[u8], format: PixelFormat) -> u32 {
+    let (r, g, b) = match format {
+        PixelFormat::Rgba8 | PixelFormat::Rgb8 => (pixel[0], pixel[1], pixel[2]),
+        PixelFormat::Rgb565 => {
+            let value = u16::from_le_bytes([pixel[0], pixel[1]]);
+            let r = (value >> 11) as u8 & 0x1F;
+            let g = (value >> 5) as u8 & 0x3F;
+            let b = value as u8 & 0x1F;
+            (
+                (r << 3) | (r >> 2),
+                (g << 2) | (g >> 4),
+                (b << 3) | (b >> 2),
+            )
+        }
+    };
+    (299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b)) / 1000
+}
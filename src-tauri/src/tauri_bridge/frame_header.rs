@@ -0,0 +1,171 @@
+//! Binary frame header for binary transports
+//!
+//! HTTP responses describe frames with `X-Frame-*` headers, but clients
+//! reading frames from a socket or an `ArrayBuffer` have no headers to go
+//! by. On request, `frame://` and the control server prepend this header to
+//! frame payloads instead, 40 bytes, little-endian:
+//!
+//! ```text
+//! offset  size  field
+//!      0     4  magic, "TBFR"
+//!      4     2  version, 1
+//!      6     2  header length, 40
+//!      8     8  frame id
+//!     16     8  timestamp, f64 milliseconds since the Unix epoch
+//!     24     4  width
+//!     28     4  height
+//!     32     1  format: 0 RGBA8, 1 RGB8, 2 RGB565, 3 JPEG
+//!     33     3  reserved, zero
+//!     36     4  payload length
+//! ```
+//!
+//! Clients should reject versions they don't know and find the payload
+//! `header length` bytes in, rather than at a fixed offset.
+
+use super::shared_state::{EncodedFrame, Frame, PixelFormat};
+
+/// First bytes of every header
+pub const MAGIC: [u8; 4] = *b"TBFR";
+/// Layout version written by this build
+pub const VERSION: u16 = 1;
+/// Size of a version 1 header
+pub const HEADER_BYTES: usize = 40;
+
+/// Encoding of the payload after a header
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PayloadFormat {
+    Rgba8 = 0,
+    Rgb8 = 1,
+    Rgb565 = 2,
+    Jpeg = 3,
+}
+
+impl From<PixelFormat> for PayloadFormat {
+    fn from(format: PixelFormat) -> Self {
+        match format {
+            PixelFormat::Rgba8 => PayloadFormat::Rgba8,
+            PixelFormat::Rgb8 => PayloadFormat::Rgb8,
+            PixelFormat::Rgb565 => PayloadFormat::Rgb565,
+        }
+    }
+}
+
+impl TryFrom<u8> for PayloadFormat {
+    type Error = String;
+
+    fn try_from(code: u8) -> Result<Self, String> {
+        match code {
+            0 => Ok(PayloadFormat::Rgba8),
+            1 => Ok(PayloadFormat::Rgb8),
+            2 => Ok(PayloadFormat::Rgb565),
+            3 => Ok(PayloadFormat::Jpeg),
+            _ => Err(format!("Unknown payload format {}", code)),
+        }
+    }
+}
+
+/// Description of a frame payload
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameHeader {
+    pub frame_id: u64,
+    pub timestamp_ms: f64,
+    pub width: u32,
+    pub height: u32,
+    pub format: PayloadFormat,
+    pub payload_len: u32,
+}
+
+impl FrameHeader {
+    /// Header of a raw frame's pixels
+    pub fn raw(frame: &Frame) -> Self {
+        Self {
+            frame_id: frame.id,
+            timestamp_ms: frame.timestamp_ms,
+            width: frame.width,
+            height: frame.height,
+            format: frame.format.into(),
+            payload_len: frame.data.len() as u32,
+        }
+    }
+
+    /// Header of an encoded frame's JPEG
+    pub fn jpeg(encoded: &EncodedFrame) -> Self {
+        Self {
+            frame_id: encoded.frame_id,
+            timestamp_ms: encoded.timestamp_ms,
+            width: encoded.width,
+            height: encoded.height,
+            format: PayloadFormat::Jpeg,
+            payload_len: encoded.data.len() as u32,
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; HEADER_BYTES] {
+        let mut bytes = [0; HEADER_BYTES];
+        bytes[0..4].copy_from_slice(&MAGIC);
+        bytes[4..6].copy_from_slice(&VERSION.to_le_bytes());
+        bytes[6..8].copy_from_slice(&(HEADER_BYTES as u16).to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.frame_id.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.timestamp_ms.to_le_bytes());
+        bytes[24..28].copy_from_slice(&self.width.to_le_bytes());
+        bytes[28..32].copy_from_slice(&self.height.to_le_bytes());
+        bytes[32] = self.format as u8;
+        bytes[36..40].copy_from_slice(&self.payload_len.to_le_bytes());
+        bytes
+    }
+
+    /// The header followed by `payload`
+    pub fn prepend(&self, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_BYTES + payload.len());
+        bytes.extend_from_slice(&self.to_bytes());
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    /// Split `message` into its header and payload, checking the magic,
+    /// version and payload length
+    pub fn parse(message: &[u8]) -> Result<(Self, &[u8]), String> {
+        if message.len() < HEADER_BYTES || message[0..4] != MAGIC {
+            return Err("Not a frame header".to_string());
+        }
+        let u16_at = |offset: usize| u16::from_le_bytes([message[offset], message[offset + 1]]);
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes([
+                message[offset],
+                message[offset + 1],
+                message[offset + 2],
+                message[offset + 3],
+            ])
+        };
+        let u64_at = |offset: usize| {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(&message[offset..offset + 8]);
+            u64::from_le_bytes(bytes)
+        };
+        let version = u16_at(4);
+        if version != VERSION {
+            return Err(format!("Unsupported frame header version {}", version));
+        }
+        let header_len = u16_at(6) as usize;
+        if !(HEADER_BYTES..=message.len()).contains(&header_len) {
+            return Err(format!("Invalid frame header length {}", header_len));
+        }
+        let header = Self {
+            frame_id: u64_at(8),
+            timestamp_ms: f64::from_bits(u64_at(16)),
+            width: u32_at(24),
+            height: u32_at(28),
+            format: PayloadFormat::try_from(message[32])?,
+            payload_len: u32_at(36),
+        };
+        let payload = &message[header_len..];
+        if payload.len() != header.payload_len as usize {
+            return Err(format!(
+                "Payload is {} bytes, header says {}",
+                payload.len(),
+                header.payload_len
+            ));
+        }
+        Ok((header, payload))
+    }
+}
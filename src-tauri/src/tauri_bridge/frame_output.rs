@@ -0,0 +1,207 @@
+//! Live frame outputs
+//!
+//! Apps embedding the demo register [`FrameOutput`]s on the
+//! [`AppBuilder`](crate::AppBuilder) to publish the stream outside the
+//! webview, e.g. as a virtual camera for OBS, Zoom or Teams. Each output runs on its own thread and always gets the newest
+//! frame, so a slow output skips frames instead of holding up the stream
+//! or the other outputs.
+//!
+//! [`PipeOutput`] writes raw frames to a program's stdin, e.g. ffmpeg,
+//! whose `v4l2` output feeds a v4l2loopback device on Linux. NDI needs
+//! the NDI SDK, since FFmpeg dropped its NDI output in 4.2, so publishing
+//! an NDI source means implementing [`FrameOutput`] on its bindings.
+
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use tracing::info_span;
+
+use super::shared_state::{BridgeState, Frame, PixelFormat};
+
+/// Destination for every published frame
+pub trait FrameOutput: Send + 'static {
+    /// Short name for log messages
+    fn name(&self) -> &str;
+
+    /// Publish `frame`, called from the output's thread
+    fn send(&mut self, frame: &Frame) -> Result<(), String>;
+}
+
+/// Start one thread per output, feeding it the newest frame
+pub fn start_frame_outputs(state: BridgeState, outputs: Vec<Box<dyn FrameOutput>>) {
+    for mut output in outputs {
+        let state = state.clone();
+        let name = output.name().to_string();
+        let spawned = thread::Builder::new()
+            .name(format!("frame-output-{}", name))
+            .spawn(move || {
+                let mut sequence = 0;
+                let mut failing = false;
+                while let Some(newer) = state.frame_signal.wait_newer(sequence) {
+                    sequence = newer;
+                    let Some(frame) = state.frame_buffer.latest() else {
+                        continue;
+                    };
+                    let _span = info_span!("frame_output", output = output.name()).entered();
+                    // Log when an output starts and stops failing, not every frame
+                    match output.send(&frame) {
+                        Ok(()) if failing => {
+                            println!("[Output] {} recovered", output.name());
+                            failing = false;
+                        }
+                        Err(e) if !failing => {
+                            eprintln!("[Output] {} failed: {}", output.name(), e);
+                            failing = true;
+                        }
+                        _ => {}
+                    }
+                }
+            });
+        match spawned {
+            Ok(_) => println!("[Output] Publishing frames to {}", name),
+            Err(e) => eprintln!("[Output] Failed to start {}: {}", name, e),
+        }
+    }
+}
+
+/// Name of `format` in ffmpeg's `-pix_fmt`
+pub fn ffmpeg_pixel_format(format: PixelFormat) -> &'static str {
+    match format {
+        PixelFormat::Rgba8 => "rgba",
+        PixelFormat::Rgb8 => "rgb24",
+        PixelFormat::Rgb565 => "rgb565le",
+    }
+}
+
+// =============================================================================
+// Built-in Outputs
+// =============================================================================
+
+/// Writes raw frames to a program's stdin
+///
+/// `{width}`, `{height}` and `{pix_fmt}` in the arguments are replaced by
+/// the frame size and [`ffmpeg_pixel_format`]. The program is started on
+/// the first frame, restarted when the size or format changes, and
+/// restarted on the next frame when it exits.
+///
+/// ```ignore
+/// PipeOutput::new("gst-launch-1.0", ["fdsrc", "!", "rawvideoparse", "width={width}", ...])
+/// ```
+pub struct PipeOutput {
+    program: String,
+    args: Vec<String>,
+    running: Option<Running>,
+}
+
+/// The program and the frame layout it was started for
+struct Running {
+    child: Child,
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+}
+
+impl PipeOutput {
+    pub fn new<I, S>(program: impl Into<String>, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+            running: None,
+        }
+    }
+
+    /// ffmpeg feeding a v4l2loopback `device`, e.g. `"/dev/video10"`,
+    /// which video call apps list as a camera
+    pub fn v4l2loopback(device: impl Into<String>) -> Self {
+        let mut args = raw_input_args();
+        args.extend(["-f", "v4l2", "-pix_fmt", "yuv420p"].map(String::from));
+        args.push(device.into());
+        Self::new("ffmpeg", args)
+    }
+
+    fn spawn(&self, frame: &Frame) -> Result<Child, String> {
+        let args = self.args.iter().map(|arg| {
+            arg.replace("{width}", &frame.width.to_string())
+                .replace("{height}", &frame.height.to_string())
+                .replace("{pix_fmt}", ffmpeg_pixel_format(frame.format))
+        });
+        Command::new(&self.program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("{}: {}", self.program, e))
+    }
+
+    fn stop(&mut self) {
+        if let Some(mut running) = self.running.take() {
+            let _ = running.child.kill();
+            let _ = running.child.wait();
+        }
+    }
+}
+
+/// ffmpeg arguments reading raw frames from stdin, timestamped on arrival
+/// since the stream's frame rate varies
+fn raw_input_args() -> Vec<String> {
+    [
+        "-loglevel",
+        "error",
+        "-use_wallclock_as_timestamps",
+        "1",
+        "-f",
+        "rawvideo",
+        "-pix_fmt",
+        "{pix_fmt}",
+        "-video_size",
+        "{width}x{height}",
+        "-i",
+        "-",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+impl FrameOutput for PipeOutput {
+    fn name(&self) -> &str {
+        &self.program
+    }
+
+    fn send(&mut self, frame: &Frame) -> Result<(), String> {
+        let changed = self.running.as_ref().is_some_and(|running| {
+            (running.width, running.height, running.format)
+                != (frame.width, frame.height, frame.format)
+        });
+        if changed {
+            self.stop();
+        }
+        if self.running.is_none() {
+            self.running = Some(Running {
+                child: self.spawn(frame)?,
+                width: frame.width,
+                height: frame.height,
+                format: frame.format,
+            });
+        }
+        let written = self
+            .running
+            .as_mut()
+            .and_then(|running| running.child.stdin.as_mut())
+            .ok_or_else(|| "stdin is closed".to_string())
+            .and_then(|stdin| stdin.write_all(&frame.data).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            self.stop();
+            return Err(format!("{}: {}", self.program, e));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PipeOutput {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,139 @@
+//! WebSocket input channel
+//!
+//! Invoking `send_mouse_input` for every mousemove costs an IPC round trip
+//! and a promise and JSON object per event in the frontend. Instead, the
+//! frontend opens a WebSocket to a loopback port and sends input as
+//! binary messages of packed events, 13 bytes each, little-endian:
+//!
+//! ```text
+//! [delta_x: f32][delta_y: f32][scroll_delta: f32][buttons: u8]
+//! ```
+//!
+//! `buttons` has bit 0 set for the left button, bit 1 for the right and
+//! bit 2 for the middle one. A message may carry several events, which are
+//! applied in order. The URL carries a per-session token, so other local
+//! processes can't move the camera.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::Message;
+
+use super::shared_state::{MouseInput, SharedMouseInput};
+
+/// Size of one packed event
+const EVENT_BYTES: usize = 13;
+
+/// Where the input socket listens, managed as Tauri state
+#[derive(Default)]
+pub struct InputSocket {
+    /// `ws://` URL including the token, `None` if the socket isn't open
+    pub url: Option<String>,
+}
+
+/// Listen on `address` and apply input from each client on its own thread
+pub fn start_input_socket(mouse: SharedMouseInput, address: &str) -> io::Result<InputSocket> {
+    let listener = TcpListener::bind(address)?;
+    let address = listener.local_addr()?;
+    let token = format!("{:016x}", RandomState::new().build_hasher().finish());
+    let url = format!("ws://{}/input?token={}", address, token);
+    println!("[Input] Listening on ws://{}/input", address);
+
+    thread::Builder::new()
+        .name("input-socket".into())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let mouse = mouse.clone();
+                let token = token.clone();
+                let spawned = thread::Builder::new()
+                    .name("input-client".into())
+                    .spawn(move || {
+                        if let Err(e) = serve(stream, &mouse, &token) {
+                            eprintln!("[Input] Connection closed: {}", e);
+                        }
+                    });
+                if let Err(e) = spawned {
+                    eprintln!("[Input] Failed to serve a client: {}", e);
+                }
+            }
+        })?;
+    Ok(InputSocket { url: Some(url) })
+}
+
+/// Accept a client with the right token and apply its input until it
+/// disconnects
+fn serve(stream: TcpStream, mouse: &SharedMouseInput, token: &str) -> Result<(), String> {
+    stream.set_nodelay(true).map_err(|e| e.to_string())?;
+    let expected = format!("token={}", token);
+    let check_token = |request: &Request, response: Response| {
+        let authorized = request
+            .uri()
+            .query()
+            .is_some_and(|query| query.split('&').any(|pair| pair == expected));
+        if authorized {
+            Ok(response)
+        } else {
+            let mut rejection = ErrorResponse::new(Some("Invalid token".to_string()));
+            *rejection.status_mut() = StatusCode::FORBIDDEN;
+            Err(rejection)
+        }
+    };
+    let mut socket = tungstenite::accept_hdr(stream, check_token).map_err(|e| e.to_string())?;
+
+    loop {
+        match socket.read() {
+            Ok(Message::Binary(events)) => {
+                if events.len() % EVENT_BYTES != 0 {
+                    return Err(format!(
+                        "{} bytes is not a whole number of events",
+                        events.len()
+                    ));
+                }
+                for event in events.chunks_exact(EVENT_BYTES) {
+                    mouse.add(&unpack(event)?)?;
+                }
+            }
+            Ok(Message::Close(_)) => return Ok(()),
+            // Pings are answered by the next read
+            Ok(_) => {}
+            Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+}
+
+/// Decode one packed event
+fn unpack(event: &[u8]) -> Result<MouseInput, String> {
+    let float = |offset: usize| {
+        f32::from_le_bytes([
+            event[offset],
+            event[offset + 1],
+            event[offset + 2],
+            event[offset + 3],
+        ])
+    };
+    let (delta_x, delta_y, scroll_delta) = (float(0), float(4), float(8));
+    if ![delta_x, delta_y, scroll_delta]
+        .iter()
+        .all(|value| value.is_finite())
+    {
+        return Err("Input deltas must be finite".to_string());
+    }
+    let buttons = event[12];
+    Ok(MouseInput {
+        delta_x,
+        delta_y,
+        scroll_delta,
+        left_button: buttons & 1 != 0,
+        right_button: buttons & 2 != 0,
+        middle_button: buttons & 4 != 0,
+    })
+}
@@ -0,0 +1,208 @@
+//! Prometheus metrics exporter
+//!
+//! Renders frame rate, latency, frame drops and memory use in the Prometheus
+//! text exposition format, for monitoring long-running deployments from
+//! outside the app. Served as the `metrics` resource of the `frame://`
+//! protocol and, optionally, over plain HTTP on a local port.
+
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use crate::config::metrics::READ_TIMEOUT;
+use super::shared_state::{BridgeState, PerformanceStats};
+
+/// Content type of the Prometheus text format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Render the current metrics in the Prometheus text format
+pub fn render(state: &BridgeState) -> String {
+    let stats = state
+        .perf_stats
+        .0
+        .lock()
+        .map(|stats| stats.clone())
+        .unwrap_or_default();
+    render_stats(&stats)
+}
+
+fn render_stats(stats: &PerformanceStats) -> String {
+    let mut out = String::new();
+
+    gauge(
+        &mut out,
+        "fps",
+        "Published frames per second",
+        stats.bevy_fps,
+    );
+    gauge(
+        &mut out,
+        "target_fps",
+        "Adaptive frame rate target",
+        stats.target_fps,
+    );
+    quantiles(
+        &mut out,
+        "frame_interval_seconds",
+        "Time between consecutive published frames",
+        [
+            stats.frame_time.p50_ms,
+            stats.frame_time.p95_ms,
+            stats.frame_time.p99_ms,
+        ],
+    );
+    quantiles(
+        &mut out,
+        "display_latency_seconds",
+        "Time from frame publication to display in the frontend",
+        [
+            stats.display_latency.p50_ms,
+            stats.display_latency.p95_ms,
+            stats.display_latency.p99_ms,
+        ],
+    );
+    gauge(
+        &mut out,
+        "gpu_render_seconds",
+        "GPU time spent in the camera pass",
+        stats.gpu_render_ms / 1000.0,
+    );
+    gauge(
+        &mut out,
+        "jpeg_encode_seconds",
+        "Time spent JPEG-encoding the last frame",
+        stats.jpeg_encode_ms / 1000.0,
+    );
+
+    counter(
+        &mut out,
+        "frames_published_total",
+        "Frames published to the frontend",
+        stats.frame_count as u64,
+    );
+    counter(
+        &mut out,
+        "frames_fetched_total",
+        "Frame fetches served to clients",
+        stats.frames_fetched,
+    );
+    header(
+        &mut out,
+        "frames_dropped_total",
+        "Frames lost in the pipeline",
+        "counter",
+    );
+    for (reason, count) in [
+        ("stale", stats.frames_dropped_stale),
+        ("readback", stats.frames_skipped_readback),
+        ("unfetched", stats.frames_unfetched),
+        ("identical", stats.frames_skipped_identical),
+    ] {
+        let _ = writeln!(
+            out,
+            "tauri_bevy_frames_dropped_total{{reason=\"{reason}\"}} {count}"
+        );
+    }
+    gauge(
+        &mut out,
+        "readback_channel_depth",
+        "Frames waiting in the readback channel",
+        stats.channel_depth as f64,
+    );
+
+    header(
+        &mut out,
+        "memory_bytes",
+        "Memory used by the render pipeline",
+        "gauge",
+    );
+    for (kind, mb) in [
+        ("gpu_allocated", stats.gpu_allocated_mb),
+        ("gpu_reserved", stats.gpu_reserved_mb),
+        ("readback_buffers", stats.readback_buffers_mb),
+        ("render_targets", stats.render_targets_mb),
+        ("pipeline", stats.pipeline_memory_mb),
+    ] {
+        let bytes = mb * 1024.0 * 1024.0;
+        let _ = writeln!(out, "tauri_bevy_memory_bytes{{kind=\"{kind}\"}} {bytes}");
+    }
+    counter(
+        &mut out,
+        "memory_limit_exceeded_total",
+        "Times the pipeline memory limit was exceeded",
+        stats.memory_limit_exceeded,
+    );
+
+    out
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP tauri_bevy_{name} {help}");
+    let _ = writeln!(out, "# TYPE tauri_bevy_{name} {kind}");
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    header(out, name, help, "gauge");
+    let _ = writeln!(out, "tauri_bevy_{name} {value}");
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    header(out, name, help, "counter");
+    let _ = writeln!(out, "tauri_bevy_{name} {value}");
+}
+
+/// Percentiles given in milliseconds, as a gauge in seconds per quantile
+fn quantiles(out: &mut String, name: &str, help: &str, [p50, p95, p99]: [f64; 3]) {
+    header(out, name, help, "gauge");
+    for (quantile, ms) in [("0.5", p50), ("0.95", p95), ("0.99", p99)] {
+        let seconds = ms / 1000.0;
+        let _ = writeln!(
+            out,
+            "tauri_bevy_{name}{{quantile=\"{quantile}\"}} {seconds}"
+        );
+    }
+}
+
+/// Serve metrics over HTTP on `address`
+///
+/// Every request gets the current metrics, whatever its path, one
+/// connection at a time; scrapers poll every few seconds at most.
+pub fn start_metrics_server(state: BridgeState, address: &str) -> std::io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    println!(
+        "[Metrics] Serving on http://{}/metrics",
+        listener.local_addr()?
+    );
+    thread::Builder::new()
+        .name("metrics-server".into())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let result = stream.and_then(|stream| serve(stream, &state));
+                if let Err(e) = result {
+                    eprintln!("[Metrics] Request failed: {}", e);
+                }
+            }
+        })?;
+    Ok(())
+}
+
+/// Answer one HTTP request with the current metrics
+fn serve(mut stream: TcpStream, state: &BridgeState) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    // Read the request line and headers up to the blank line
+    let mut reader = BufReader::new(&stream);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && line != "\r\n" && line != "\n" {
+        line.clear();
+    }
+
+    let body = render(state);
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        CONTENT_TYPE,
+        body.len()
+    )?;
+    stream.write_all(body.as_bytes())
+}
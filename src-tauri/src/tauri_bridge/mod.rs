@@ -2,13 +2,44 @@
 //!
 //! This module handles all communication between the Tauri frontend and
 //! the Bevy rendering backend, including command handlers, custom protocols,
-//! and shared state management.
+//! the input socket, binary frame headers, frame encoding, per-client
+//! stream settings, recording, the clipboard and drag-out, capture overlays
+//! and sinks, live frame outputs, shared-surface presentation, project
+//! files, USDZ packaging, watched model folders, metrics export,
+//! golden-image hashes, JSON and MessagePack serialization, the optional
+//! TCP control server, scene sync between instances, and shared state
+//! management.
 
 pub mod shared_state;
 pub mod commands;
+pub mod input_socket;
 pub mod protocol;
+pub mod frame_header;
+pub mod encoder;
+pub mod stream_manager;
+pub mod recorder;
+pub mod webm;
+pub mod clip;
+pub mod clipboard;
+pub mod drag_out;
+pub mod frame_export;
+pub mod screenshot;
+pub mod turntable;
+pub mod batch;
+pub mod overlay;
+pub mod capture_sink;
+pub mod frame_output;
+pub mod shared_surface;
+pub mod camera_presets;
+pub mod project;
+pub mod usdz;
+pub mod model_watch;
+pub mod metrics;
+pub mod frame_hash;
+pub mod wire_format;
+pub mod scene_sync;
+#[cfg(feature = "control_server")]
+pub mod control_server;
 
 // Re-export commonly used types
-pub use shared_state::{
-    SharedFrameBuffer, SharedMouseInput, SharedPerfStats,
-};
+pub use shared_state::BridgeState;
@@ -0,0 +1,126 @@
+//! Watched model folders
+//!
+//! Pointing the viewer at the folder a DCC tool exports to gives an
+//! export-and-look loop: the folder is polled for the newest glTF, OBJ or
+//! STL file, which is loaded whenever a different file becomes the newest
+//! or the newest one is exported again. A file is only loaded once it was
+//! unchanged for a whole poll, so exports still being written are skipped.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::SystemTime;
+
+use crate::config::model::{EXTENSIONS, WATCH_INTERVAL};
+
+/// A model file as seen by one poll
+#[derive(Clone, PartialEq, Debug)]
+struct ModelFile {
+    path: PathBuf,
+    modified: SystemTime,
+    len: u64,
+}
+
+struct ActiveWatch {
+    dir: PathBuf,
+    stop: Arc<AtomicBool>,
+}
+
+/// Owner of the folder watching thread, managed as Tauri state
+#[derive(Default)]
+pub struct ModelWatcher {
+    active: Mutex<Option<ActiveWatch>>,
+}
+
+impl ModelWatcher {
+    /// Watch `dir`, calling `on_change` from the watching thread with each
+    /// model file to load, starting with the newest one already there
+    ///
+    /// Replaces the folder watched so far.
+    pub fn start(
+        &self,
+        dir: PathBuf,
+        on_change: impl Fn(PathBuf) + Send + 'static,
+    ) -> Result<(), String> {
+        if !dir.is_dir() {
+            return Err(format!("{}: not a directory", dir.display()));
+        }
+        let mut active = self.active.lock().map_err(|e| e.to_string())?;
+        if let Some(previous) = active.take() {
+            previous.stop.store(true, Ordering::Relaxed);
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread_dir = dir.clone();
+        thread::Builder::new()
+            .name("model-watch".into())
+            .spawn(move || watch(&thread_dir, &thread_stop, on_change))
+            .map_err(|e| e.to_string())?;
+        println!("[Tauri] Watching {} for models", dir.display());
+        *active = Some(ActiveWatch { dir, stop });
+        Ok(())
+    }
+
+    /// Stop watching, returning the folder that was watched
+    pub fn stop(&self) -> Result<Option<PathBuf>, String> {
+        let mut active = self.active.lock().map_err(|e| e.to_string())?;
+        Ok(active.take().map(|watch| {
+            watch.stop.store(true, Ordering::Relaxed);
+            println!("[Tauri] Stopped watching {}", watch.dir.display());
+            watch.dir
+        }))
+    }
+}
+
+fn watch(dir: &Path, stop: &AtomicBool, on_change: impl Fn(PathBuf)) {
+    let mut previous: Option<ModelFile> = None;
+    let mut loaded: Option<ModelFile> = None;
+    while !stop.load(Ordering::Relaxed) {
+        let newest = newest_model(dir);
+        let settled = newest.is_some() && newest == previous;
+        if settled && newest != loaded {
+            if let Some(file) = &newest {
+                println!(
+                    "[Tauri] Loading {} from the watched folder",
+                    file.path.display()
+                );
+                on_change(file.path.clone());
+            }
+            loaded = newest.clone();
+        }
+        previous = newest;
+        thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+/// Most recently modified model file directly in `dir`
+fn newest_model(dir: &Path) -> Option<ModelFile> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| is_model(&entry.path()))
+        .filter_map(|entry| {
+            let metadata = entry
+                .metadata()
+                .ok()
+                .filter(|metadata| metadata.is_file())?;
+            Some(ModelFile {
+                path: entry.path(),
+                modified: metadata.modified().ok()?,
+                len: metadata.len(),
+            })
+        })
+        .max_by_key(|file| file.modified)
+}
+
+fn is_model(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            EXTENSIONS
+                .iter()
+                .any(|model| extension.eq_ignore_ascii_case(model))
+        })
+}
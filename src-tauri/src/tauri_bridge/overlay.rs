@@ -0,0 +1,270 @@
+//! Overlay compositing for captured output
+//!
+//! Recordings, clips, screenshots and batch renders can carry a logo and a
+//! timestamp, drawn on the CPU into the copy being saved. The live stream
+//! is never touched. The timestamp uses a small built-in bitmap font, so no
+//! font file is needed.
+
+use image::RgbaImage;
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::overlay::{MARGIN, TEXT_BACKGROUND_ALPHA};
+
+/// Corner of the frame an overlay element is anchored to
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Corner {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Overlay configuration, as passed to `set_capture_overlay`
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct OverlaySettings {
+    /// PNG (or any format `image` reads) drawn at its native size
+    pub logo_path: Option<String>,
+    pub logo_corner: Corner,
+    /// 0.0 to 1.0, multiplied with the logo's own alpha
+    pub logo_opacity: Option<f32>,
+    /// Draw the frame's publication time, UTC
+    pub timestamp: bool,
+    pub timestamp_corner: Corner,
+}
+
+/// Loaded overlay, ready to be drawn
+pub struct Overlay {
+    settings: OverlaySettings,
+    logo: Option<RgbaImage>,
+}
+
+impl Overlay {
+    /// Load the logo referenced by `settings`
+    pub fn load(settings: OverlaySettings) -> Result<Self, String> {
+        let logo = match &settings.logo_path {
+            Some(path) => Some(
+                image::open(path)
+                    .map_err(|e| format!("{}: {}", path, e))?
+                    .to_rgba8(),
+            ),
+            None => None,
+        };
+        Ok(Self { settings, logo })
+    }
+
+    /// Draw the overlay into tightly packed RGB8 or RGBA8 `pixels`
+    ///
+    /// `timestamp_ms` is the time shown, in milliseconds since the Unix
+    /// epoch.
+    pub fn apply(
+        &self,
+        pixels: &mut [u8],
+        width: u32,
+        height: u32,
+        bytes_per_pixel: usize,
+        timestamp_ms: f64,
+    ) {
+        let mut canvas = Canvas {
+            pixels,
+            width: width as i64,
+            height: height as i64,
+            bytes_per_pixel,
+        };
+
+        if let Some(logo) = &self.logo {
+            let opacity = self.settings.logo_opacity.unwrap_or(1.0).clamp(0.0, 1.0);
+            let (x, y) = canvas.anchor(
+                self.settings.logo_corner,
+                logo.width() as i64,
+                logo.height() as i64,
+            );
+            for (lx, ly, pixel) in logo.enumerate_pixels() {
+                let [r, g, b, a] = pixel.0;
+                let alpha = a as f32 / 255.0 * opacity;
+                canvas.blend(x + lx as i64, y + ly as i64, [r, g, b], alpha);
+            }
+        }
+
+        if self.settings.timestamp {
+            let text = format_utc(timestamp_ms);
+            // Readable without dominating small clips or 4K screenshots
+            let scale = (height as i64 / 240).max(1);
+            let (text_width, text_height) = text_size(&text, scale);
+            let padding = 2 * scale;
+            let (x, y) = canvas.anchor(
+                self.settings.timestamp_corner,
+                text_width + 2 * padding,
+                text_height + 2 * padding,
+            );
+            canvas.fill(
+                x,
+                y,
+                text_width + 2 * padding,
+                text_height + 2 * padding,
+                [0, 0, 0],
+                TEXT_BACKGROUND_ALPHA,
+            );
+            draw_text(&mut canvas, &text, x + padding, y + padding, scale);
+        }
+    }
+}
+
+/// Overlay applied to captured output, `None` when disabled
+#[derive(Clone, Default)]
+pub struct SharedOverlay(pub Arc<Mutex<Option<Arc<Overlay>>>>);
+
+impl SharedOverlay {
+    pub fn set(&self, overlay: Option<Overlay>) {
+        if let Ok(mut slot) = self.0.lock() {
+            *slot = overlay.map(Arc::new);
+        }
+    }
+
+    pub fn current(&self) -> Option<Arc<Overlay>> {
+        self.0.lock().ok().and_then(|slot| slot.clone())
+    }
+}
+
+/// Milliseconds since the Unix epoch
+pub fn now_ms() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64() * 1000.0)
+        .unwrap_or_default()
+}
+
+// =============================================================================
+// Drawing
+// =============================================================================
+
+struct Canvas<'a> {
+    pixels: &'a mut [u8],
+    width: i64,
+    height: i64,
+    bytes_per_pixel: usize,
+}
+
+impl Canvas<'_> {
+    /// Top-left position of a `width` x `height` box in `corner`
+    fn anchor(&self, corner: Corner, width: i64, height: i64) -> (i64, i64) {
+        let margin = MARGIN as i64;
+        let right = self.width - width - margin;
+        let bottom = self.height - height - margin;
+        match corner {
+            Corner::TopLeft => (margin, margin),
+            Corner::TopRight => (right, margin),
+            Corner::BottomLeft => (margin, bottom),
+            Corner::BottomRight => (right, bottom),
+        }
+    }
+
+    /// Blend `color` over the pixel at (`x`, `y`), ignoring pixels outside
+    fn blend(&mut self, x: i64, y: i64, color: [u8; 3], alpha: f32) {
+        if alpha <= 0.0 || x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return;
+        }
+        let offset = (y * self.width + x) as usize * self.bytes_per_pixel;
+        for (channel, &value) in self.pixels[offset..offset + 3].iter_mut().zip(&color) {
+            *channel = (*channel as f32 * (1.0 - alpha) + value as f32 * alpha).round() as u8;
+        }
+    }
+
+    fn fill(&mut self, x: i64, y: i64, width: i64, height: i64, color: [u8; 3], alpha: f32) {
+        for py in y..y + height {
+            for px in x..x + width {
+                self.blend(px, py, color, alpha);
+            }
+        }
+    }
+}
+
+/// 5x7 glyphs, one byte per row, most significant of the low 5 bits left
+fn glyph(c: char) -> [u8; 7] {
+    match c {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        _ => [0; 7],
+    }
+}
+
+/// Glyph cell including one column of spacing
+const GLYPH_ADVANCE: i64 = 6;
+const GLYPH_HEIGHT: i64 = 7;
+
+fn text_size(text: &str, scale: i64) -> (i64, i64) {
+    let chars = text.chars().count() as i64;
+    let width = (chars * GLYPH_ADVANCE - 1).max(0);
+    (width * scale, GLYPH_HEIGHT * scale)
+}
+
+fn draw_text(canvas: &mut Canvas, text: &str, x: i64, y: i64, scale: i64) {
+    for (i, c) in text.chars().enumerate() {
+        let glyph_x = x + i as i64 * GLYPH_ADVANCE * scale;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..5 {
+                if bits & (0x10 >> column) == 0 {
+                    continue;
+                }
+                canvas.fill(
+                    glyph_x + column * scale,
+                    y + row as i64 * scale,
+                    scale,
+                    scale,
+                    [255, 255, 255],
+                    1.0,
+                );
+            }
+        }
+    }
+}
+
+/// `YYYY-MM-DD HH:MM:SS.mmm` in UTC
+fn format_utc(timestamp_ms: f64) -> String {
+    let total_ms = timestamp_ms.max(0.0) as i64;
+    let days = total_ms.div_euclid(86_400_000);
+    let ms_of_day = total_ms.rem_euclid(86_400_000);
+    let (year, month, day) = civil_from_days(days);
+    let seconds = ms_of_day / 1000;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        ms_of_day % 1000
+    )
+}
+
+/// Gregorian date of a day count since 1970-01-01
+///
+/// Howard Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
@@ -0,0 +1,99 @@
+//! Project files
+//!
+//! Projects are JSON files anywhere the user picks, written by
+//! `save_project` and read by `open_project`. The paths of recently saved
+//! or opened projects are kept in the app config directory, newest first,
+//! so a frontend can offer them on startup.
+
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::config::project::{MAX_RECENT, RECENT_FILE, VERSION};
+use crate::config::{RENDER_HEIGHT, RENDER_WIDTH};
+use super::shared_state::Project;
+
+/// Write `project` to `path`
+pub fn save(path: &Path, project: &Project) -> Result<(), String> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    let json = serde_json::to_vec_pretty(project).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Read and check the project at `path`
+pub fn load(path: &Path) -> Result<Project, String> {
+    let json = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let project: Project =
+        serde_json::from_slice(&json).map_err(|e| format!("{}: {}", path.display(), e))?;
+    validate(&project).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(project)
+}
+
+/// Reject settings the matching commands would refuse
+fn validate(project: &Project) -> Result<(), String> {
+    if project.version > VERSION {
+        return Err(format!(
+            "saved by a newer version (format {}, this build reads up to {})",
+            project.version, VERSION
+        ));
+    }
+    let render = &project.render;
+    if render.stream_width == 0
+        || render.stream_height == 0
+        || render.stream_width > RENDER_WIDTH
+        || render.stream_height > RENDER_HEIGHT
+    {
+        return Err(format!(
+            "stream resolution must be between 1x1 and {}x{}",
+            RENDER_WIDTH, RENDER_HEIGHT
+        ));
+    }
+    if !render.exposure_ev100.is_finite() {
+        return Err("ev100 must be finite".to_string());
+    }
+    if render
+        .fixed_fps
+        .is_some_and(|fps| !fps.is_finite() || fps <= 0.0)
+    {
+        return Err("fps must be positive".to_string());
+    }
+    Ok(())
+}
+
+/// File the recent projects are listed in
+pub fn recent_path(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(RECENT_FILE))
+        .map_err(|e| e.to_string())
+}
+
+/// Recently saved or opened projects that still exist, newest first
+///
+/// A missing or unreadable list is empty.
+pub fn recent(app: &AppHandle) -> Vec<PathBuf> {
+    let paths: Vec<PathBuf> = recent_path(app)
+        .and_then(|path| std::fs::read(&path).map_err(|e| e.to_string()))
+        .and_then(|json| serde_json::from_slice(&json).map_err(|e| e.to_string()))
+        .unwrap_or_default();
+    paths.into_iter().filter(|path| path.is_file()).collect()
+}
+
+/// Move `project` to the top of the recent projects
+pub fn add_recent(app: &AppHandle, project: &Path) -> Result<(), String> {
+    let project = project
+        .canonicalize()
+        .map_err(|e| format!("{}: {}", project.display(), e))?;
+    let mut paths = recent(app);
+    paths.retain(|path| *path != project);
+    paths.insert(0, project);
+    paths.truncate(MAX_RECENT);
+
+    let path = recent_path(app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    let json = serde_json::to_vec_pretty(&paths).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("{}: {}", path.display(), e))
+}
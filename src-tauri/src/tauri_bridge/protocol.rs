@@ -2,12 +2,28 @@
 //!
 //! This module implements the `frame://` custom protocol for direct binary
 //! transfer of render frames, bypassing Tauri's IPC JSON serialization.
+//! The same protocol takes POST and PUT requests that edit the scene, for
+//! clients that prefer plain HTTP to invoke commands.
 
-use image::{codecs::jpeg::JpegEncoder, ImageBuffer, ImageEncoder, Rgba};
-use tauri::http::Response as HttpResponse;
+use crossbeam_channel::Sender;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::sync::Arc;
+use tauri::http::{header, HeaderValue, Method, Request, Response as HttpResponse};
+use tracing::info_span;
 
-use crate::config::{RENDER_WIDTH, RENDER_HEIGHT, compression::JPEG_QUALITY};
-use super::shared_state::{SharedFrameBuffer, SharedPerfStats};
+use crate::config::protocol::{ALLOW_ORIGINS, CACHE_CONTROL, EXPOSE_HEADERS};
+use crate::config::screenshot::TIMEOUT;
+use super::frame_hash;
+use super::frame_header::FrameHeader;
+use super::metrics;
+use super::wire_format::WireFormat;
+use super::shared_state::{
+    animate_default, BridgeCommand, BridgeState, Frame, SceneLockedError, SharedEncodedFrame,
+    SharedFetchStats, SharedFrameBuffer, SharedPerfStats, SharedStatsHistory, SpawnPrimitive,
+    TransformUpdate,
+};
 
 type Response = HttpResponse<Vec<u8>>;
 
@@ -15,26 +31,105 @@ type Response = HttpResponse<Vec<u8>>;
 ///
 /// Supported endpoints:
 /// - `frame` or `frame.jpg`: JPEG-compressed frame (~50-100KB)
-/// - `frame.raw`: Raw RGBA frame (~1.8MB)
+/// - `frame.raw`: Raw frame (~0.9MB RGB565, ~1.4MB RGB, ~1.8MB RGBA, see `X-Frame-Format`)
 /// - `stats`: Performance statistics as JSON
-pub fn handle_frame_protocol(
-    uri_path: &str,
-    buffer: &SharedFrameBuffer,
-    perf_stats: &SharedPerfStats,
-) -> Response {
+/// - `stats/history`: Timestamped performance statistics as a JSON array,
+///   oldest first, limited to the last `?seconds=<n>` if given
+/// - `metrics`: Prometheus text format metrics, see [`metrics`]
+/// - `hash`: Exact and perceptual hashes of the current frame as JSON, see
+///   [`frame_hash`]
+/// - `scene/entities`: Every entity as `list_entities` returns them
+/// - `scene/<id>`: Components of an entity as `inspect_entity` returns them
+///
+/// The JSON endpoints answer in MessagePack with `?format=msgpack`, see
+/// [`super::wire_format`].
+///
+/// Frame endpoints accept `?after=<frame id>` and answer `204 No Content`
+/// when no newer frame has been published, e.g. because the scene is static.
+/// `frame.jpg?client=<token>` serves the copy encoded for a stream client
+/// with its own settings, see [`super::stream_manager`]. With `?header=1`,
+/// the body starts with a binary frame header, see
+/// [`super::frame_header`].
+///
+/// Requests through [`handle_frame_request`] can also fetch parts of
+/// `frame.raw` and edit the scene, see there.
+///
+/// CORS and caching headers follow `state.protocol_headers`, see
+/// [`ProtocolHeaders`].
+pub fn handle_frame_protocol(uri_path: &str, state: &BridgeState) -> Response {
+    let response = handle_resource(uri_path, None, state);
+    state.protocol_headers.apply(response, None)
+}
+
+/// Handle a `frame://` request, including its headers
+///
+/// `frame.raw` accepts a single `Range: bytes=<start>-<end>` header and
+/// answers `206 Partial Content` with that part of the frame, so large
+/// frames can be fetched in pieces. Later pieces of the same frame are
+/// requested with `?frame=<X-Frame-Id>`, which serves them even after a
+/// newer frame was published, or answers `410 Gone` once it is gone too.
+///
+/// POST and PUT requests with JSON bodies edit the scene like the invoke
+/// commands of the same purpose, see [`handle_scene_request`].
+pub fn handle_frame_request(request: &Request<Vec<u8>>, state: &BridgeState) -> Response {
+    let origin = request
+        .headers()
+        .get(header::ORIGIN)
+        .and_then(|origin| origin.to_str().ok());
+    let response = route_request(request, state);
+    state.protocol_headers.apply(response, origin)
+}
+
+fn route_request(request: &Request<Vec<u8>>, state: &BridgeState) -> Response {
+    if request.method() == Method::OPTIONS {
+        return preflight();
+    }
+    let uri = request.uri();
+    let uri_path = uri.path_and_query().map_or(uri.path(), |p| p.as_str());
+    if request.method() == Method::POST || request.method() == Method::PUT {
+        return handle_scene_request(request.method(), uri.path(), request.body(), state);
+    }
+    let range = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|range| range.to_str().ok());
+    handle_resource(uri_path, range, state)
+}
+
+fn handle_resource(uri_path: &str, range: Option<&str>, state: &BridgeState) -> Response {
+    let _span = info_span!("frame_protocol").entered();
     let resource = uri_path.trim_start_matches('/');
+    let (resource, query) = resource.split_once('?').unwrap_or((resource, ""));
+    let after = parse_after(query);
+    let header = parse_header(query);
     
     println!("[Protocol] Resolved resource: {}", resource);
 
     match resource {
         // JPEG compressed frame - much smaller data size!
-        "frame" | "frame.jpg" => handle_jpeg_frame(buffer),
+        "frame" | "frame.jpg" => match parse_client(query) {
+            Some(client) => handle_client_frame(state, client, after, header),
+            None => handle_jpeg_frame(&state.encoded_frame, &state.fetch_stats, after, header),
+        },
         
-        // Raw RGBA frame (for comparison/debugging)
-        "frame.raw" => handle_raw_frame(buffer),
+        // Raw pixel frame (for comparison/debugging)
+        "frame.raw" => handle_raw_frame(state, after, parse_frame(query), range, header),
         
-        // Performance stats as JSON
-        "stats" => handle_stats(perf_stats),
+        // Performance stats as JSON, or MessagePack with `format=msgpack`
+        "stats" => with_format(query, |format| handle_stats(&state.perf_stats, format)),
+        "metrics" => handle_metrics(state),
+        "stats/history" => with_format(query, |format| {
+            handle_stats_history(&state.stats_history, parse_seconds(query), format)
+        }),
+        "hash" => with_format(query, |format| {
+            handle_frame_hash(&state.frame_buffer, format)
+        }),
+
+        // Scene tree, as `list_entities` and `inspect_entity` return it
+        "scene/entities" => with_format(query, |format| handle_entities(state, format)),
+        scene if scene.starts_with("scene/") => with_format(query, |format| {
+            handle_inspect_entity(&scene["scene/".len()..], state, format)
+        }),
         
         _ => HttpResponse::builder()
             .status(404)
@@ -44,46 +139,143 @@ pub fn handle_frame_protocol(
     }
 }
 
+/// Frame id from an `after=<id>` query parameter
+fn parse_after(query: &str) -> Option<u64> {
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("after="))
+        .and_then(|id| id.parse().ok())
+}
+
+/// Stream client token from a `client=<token>` query parameter
+fn parse_client(query: &str) -> Option<&str> {
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("client="))
+}
+
+/// Whether a `header=1` query parameter asks for the binary frame header
+/// in front of the body, see [`super::frame_header`]
+fn parse_header(query: &str) -> bool {
+    query.split('&').any(|pair| pair == "header=1")
+}
+
+/// Frame id from a `frame=<id>` query parameter
+fn parse_frame(query: &str) -> Option<u64> {
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("frame="))
+        .and_then(|id| id.parse().ok())
+}
+
+/// Part of a body a `Range` header asks for
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    /// No header, or one this handler ignores, such as multiple ranges
+    Full,
+    Part(Range<usize>),
+    /// Starts at or past the end of the body
+    Unsatisfiable,
+}
+
+/// Parse a `bytes=<start>-<end>`, `bytes=<start>-` or `bytes=-<suffix>`
+/// header for a body of `len` bytes
+fn parse_range(header: Option<&str>, len: usize) -> ByteRange {
+    let Some(spec) = header.and_then(|header| header.trim().strip_prefix("bytes=")) else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((start, end)) = spec.split_once('-') else {
+        return ByteRange::Full;
+    };
+    let (start, end) = (start.trim(), end.trim());
+
+    if start.is_empty() {
+        return match end.parse::<usize>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(suffix) if len > 0 => ByteRange::Part(len.saturating_sub(suffix)..len),
+            Ok(_) => ByteRange::Unsatisfiable,
+            Err(_) => ByteRange::Full,
+        };
+    }
+    let Ok(start) = start.parse::<usize>() else {
+        return ByteRange::Full;
+    };
+    let end = if end.is_empty() {
+        len
+    } else {
+        match end.parse::<usize>() {
+            Ok(last) if last >= start => last.saturating_add(1).min(len),
+            _ => return ByteRange::Full,
+        }
+    };
+    if start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Part(start..end)
+}
+
+/// Time span from a `seconds=<n>` query parameter
+fn parse_seconds(query: &str) -> Option<f64> {
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("seconds="))
+        .and_then(|seconds| seconds.parse().ok())
+        .filter(|seconds: &f64| seconds.is_finite() && *seconds >= 0.0)
+}
+
+/// Answer to a CORS preflight, needed for `Range` headers from the webview
+fn preflight() -> Response {
+    HttpResponse::builder()
+        .status(204)
+        .header("Access-Control-Allow-Methods", "GET, POST, PUT, OPTIONS")
+        .header("Access-Control-Allow-Headers", "Range, Content-Type")
+        .body(Vec::new())
+        .unwrap()
+}
+
+/// Response telling the client it already has the latest frame
+fn no_new_frame() -> Response {
+    HttpResponse::builder()
+        .status(204)
+        .body(Vec::new())
+        .unwrap()
+}
+
 /// Handle JPEG-compressed frame request
-fn handle_jpeg_frame(buffer: &SharedFrameBuffer) -> Response {
-    let guard = buffer.0.lock().unwrap();
+///
+/// The frame is already encoded by the encoder thread, so this only copies
+/// the finished bytes into the response.
+fn handle_jpeg_frame(
+    encoded_frame: &SharedEncodedFrame,
+    fetch_stats: &SharedFetchStats,
+    after: Option<u64>,
+    header: bool,
+) -> Response {
+    let guard = encoded_frame.0.lock().unwrap();
     
     match &*guard {
-        Some(rgba_data) => {
-            // Compress RGBA to JPEG - reduces ~1.8MB to ~50-100KB!
-            let img: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::from_raw(
-                RENDER_WIDTH,
-                RENDER_HEIGHT,
-                rgba_data.clone(),
-            )
-            .unwrap();
-
-            // Convert RGBA to RGB for JPEG (no alpha channel)
-            let rgb_img = image::DynamicImage::ImageRgba8(img).to_rgb8();
-
-            // Encode to JPEG with quality setting
-            let mut jpeg_data = Vec::new();
-            let encoder = JpegEncoder::new_with_quality(&mut jpeg_data, JPEG_QUALITY);
-            encoder
-                .write_image(
-                    rgb_img.as_raw(),
-                    RENDER_WIDTH,
-                    RENDER_HEIGHT,
-                    image::ExtendedColorType::Rgb8,
-                )
-                .unwrap();
-
+        Some(encoded) if after.is_some_and(|after| encoded.frame_id <= after) => no_new_frame(),
+        Some(encoded) => {
+            fetch_stats.record_fetch(encoded.frame_id);
             HttpResponse::builder()
                 .status(200)
                 .header("Content-Type", "image/jpeg")
-                .header("X-Frame-Width", RENDER_WIDTH.to_string())
-                .header("X-Frame-Height", RENDER_HEIGHT.to_string())
-                .header("Access-Control-Allow-Origin", "*")
+                .header("X-Frame-Width", encoded.width.to_string())
+                .header("X-Frame-Height", encoded.height.to_string())
+                .header("X-Frame-Id", encoded.frame_id.to_string())
+                .header("X-Frame-Timestamp", encoded.timestamp_ms.to_string())
                 .header(
                     "Access-Control-Expose-Headers",
-                    "X-Frame-Width, X-Frame-Height",
+                    "X-Frame-Width, X-Frame-Height, X-Frame-Id, X-Frame-Timestamp",
                 )
-                .body(jpeg_data)
+                .body(if header {
+                    FrameHeader::jpeg(encoded).prepend(&encoded.data)
+                } else {
+                    encoded.data.clone()
+                })
                 .unwrap()
         }
         None => HttpResponse::builder()
@@ -94,40 +286,425 @@ fn handle_jpeg_frame(buffer: &SharedFrameBuffer) -> Response {
     }
 }
 
-/// Handle raw RGBA frame request
-fn handle_raw_frame(buffer: &SharedFrameBuffer) -> Response {
-    let guard = buffer.0.lock().unwrap();
-    
-    match &*guard {
-        Some(rgba_data) => HttpResponse::builder()
+/// Handle a JPEG frame request of a stream client
+fn handle_client_frame(
+    state: &BridgeState,
+    client: &str,
+    after: Option<u64>,
+    header: bool,
+) -> Response {
+    match state.stream_clients.fetch(client, after) {
+        // Counted per client, not in the main stream's fetch stats
+        Ok(Some(encoded)) => HttpResponse::builder()
             .status(200)
-            .header("Content-Type", "application/octet-stream")
-            .header("X-Frame-Width", RENDER_WIDTH.to_string())
-            .header("X-Frame-Height", RENDER_HEIGHT.to_string())
-            .header("Access-Control-Allow-Origin", "*")
+            .header("Content-Type", "image/jpeg")
+            .header("X-Frame-Width", encoded.width.to_string())
+            .header("X-Frame-Height", encoded.height.to_string())
+            .header("X-Frame-Id", encoded.frame_id.to_string())
+            .header("X-Frame-Timestamp", encoded.timestamp_ms.to_string())
             .header(
                 "Access-Control-Expose-Headers",
-                "X-Frame-Width, X-Frame-Height",
+                "X-Frame-Width, X-Frame-Height, X-Frame-Id, X-Frame-Timestamp",
             )
-            .body(rgba_data.clone())
+            .body(if header {
+                FrameHeader::jpeg(&encoded).prepend(&encoded.data)
+            } else {
+                encoded.data
+            })
             .unwrap(),
-        None => HttpResponse::builder()
-            .status(503)
+        // Nothing encoded yet for a new client, or nothing newer
+        Ok(None) => no_new_frame(),
+        Err(e) => HttpResponse::builder()
+            .status(404)
             .header("Content-Type", "text/plain")
-            .body("Frame not ready".as_bytes().to_vec())
+            .body(e.into_bytes())
             .unwrap(),
     }
 }
 
+/// Handle raw frame request
+///
+/// A frame served in part is kept as `state.ranged_frame`, so its other
+/// parts can still be requested with `frame` after a newer frame was
+/// published. Ranges address the pixels alone, so with `header` the whole
+/// frame is always served.
+fn handle_raw_frame(
+    state: &BridgeState,
+    after: Option<u64>,
+    frame_id: Option<u64>,
+    range: Option<&str>,
+    header: bool,
+) -> Response {
+    let frame = match frame_id {
+        Some(id) => {
+            let frames = [state.frame_buffer.latest(), state.ranged_frame.latest()];
+            match frames.into_iter().flatten().find(|frame| frame.id == id) {
+                Some(frame) => frame,
+                None => {
+                    return HttpResponse::builder()
+                        .status(410)
+                        .header("Content-Type", "text/plain")
+                        .body(format!("Frame {} is no longer available", id).into_bytes())
+                        .unwrap()
+                }
+            }
+        }
+        None => match state.frame_buffer.latest() {
+            Some(frame) if after.is_some_and(|after| frame.id <= after) => return no_new_frame(),
+            Some(frame) => frame,
+            None => {
+                return HttpResponse::builder()
+                    .status(503)
+                    .header("Content-Type", "text/plain")
+                    .body("Frame not ready".as_bytes().to_vec())
+                    .unwrap()
+            }
+        },
+    };
+
+    let len = frame.data.len();
+    let range = range.filter(|_| !header);
+    let (status, body_range) = match parse_range(range, len) {
+        ByteRange::Full => (200, 0..len),
+        ByteRange::Part(part) => (206, part),
+        ByteRange::Unsatisfiable => {
+            return HttpResponse::builder()
+                .status(416)
+                .header("Content-Range", format!("bytes */{}", len))
+                .body(Vec::new())
+                .unwrap()
+        }
+    };
+    // Count each frame once, not once per part
+    if body_range.start == 0 {
+        state.fetch_stats.record_fetch(frame.id);
+    }
+
+    let body = if header {
+        FrameHeader::raw(&frame).prepend(&frame.data)
+    } else {
+        frame.data[body_range.clone()].to_vec()
+    };
+    let mut response = frame_headers(HttpResponse::builder().status(status), &frame)
+        .header("Accept-Ranges", "bytes")
+        .header("Content-Length", body.len().to_string());
+    if status == 206 {
+        response = response.header(
+            "Content-Range",
+            format!("bytes {}-{}/{}", body_range.start, body_range.end - 1, len),
+        );
+        state.ranged_frame.0.store(Some(Arc::clone(&frame)));
+    }
+    response.body(body).unwrap()
+}
+
+/// Content type and `X-Frame-*` headers of a raw frame response
+fn frame_headers(
+    builder: tauri::http::response::Builder,
+    frame: &Frame,
+) -> tauri::http::response::Builder {
+    builder
+        .header("Content-Type", "application/octet-stream")
+        .header("X-Frame-Width", frame.width.to_string())
+        .header("X-Frame-Height", frame.height.to_string())
+        .header("X-Frame-Format", frame.format.as_str())
+        .header("X-Frame-Id", frame.id.to_string())
+        .header("X-Frame-Timestamp", frame.timestamp_ms.to_string())
+        .header(
+            "Access-Control-Expose-Headers",
+            "X-Frame-Width, X-Frame-Height, X-Frame-Format, X-Frame-Id, X-Frame-Timestamp, Content-Range, Accept-Ranges",
+        )
+}
+
 /// Handle performance stats request
-fn handle_stats(perf_stats: &SharedPerfStats) -> Response {
+fn handle_stats(perf_stats: &SharedPerfStats, format: WireFormat) -> Response {
     let guard = perf_stats.0.lock().unwrap();
-    let json = serde_json::to_vec(&*guard).unwrap_or_default();
-    
+    serialized(&*guard, format)
+}
+
+/// Handle performance stats history request
+fn handle_stats_history(
+    history: &SharedStatsHistory,
+    seconds: Option<f64>,
+    format: WireFormat,
+) -> Response {
+    serialized(&history.since(seconds), format)
+}
+
+/// Handle frame hash request
+fn handle_frame_hash(buffer: &SharedFrameBuffer, format: WireFormat) -> Response {
+    let Some(frame) = buffer.latest() else {
+        return HttpResponse::builder()
+            .status(503)
+            .header("Content-Type", "text/plain")
+            .body("Frame not ready".as_bytes().to_vec())
+            .unwrap();
+    };
+    serialized(&frame_hash::hash_frame(&frame), format)
+}
+
+/// Handle scene tree request
+fn handle_entities(state: &BridgeState, format: WireFormat) -> Response {
+    match query(state, |reply| BridgeCommand::ListEntities { reply }) {
+        Ok(entities) => serialized(&entities, format),
+        Err(response) => response,
+    }
+}
+
+/// Handle entity components request for `scene/<id>`
+fn handle_inspect_entity(id: &str, state: &BridgeState, format: WireFormat) -> Response {
+    let inspection = id
+        .parse()
+        .map_err(|_| error(400, format!("Invalid entity id {}", id)))
+        .and_then(|id| query(state, |reply| BridgeCommand::InspectEntity { id, reply }))
+        .and_then(|inspection| inspection.map_err(|e| error(404, e)));
+    match inspection {
+        Ok(inspection) => serialized(&inspection, format),
+        Err(response) => response,
+    }
+}
+
+/// Respond in the format asked for by `query`, or with a 400 for an
+/// unknown one
+///
+/// Only endpoints serving structured data take a format, so others ignore
+/// the parameter.
+fn with_format(query: &str, respond: impl FnOnce(WireFormat) -> Response) -> Response {
+    match WireFormat::from_query(query) {
+        Ok(format) => respond(format),
+        Err(e) => error(400, e),
+    }
+}
+
+/// `value` as a 200 response in `format`
+fn serialized(value: &impl Serialize, format: WireFormat) -> Response {
+    match format.to_vec(value) {
+        Ok(body) => HttpResponse::builder()
+            .status(200)
+            .header("Content-Type", format.content_type())
+            .body(body)
+            .unwrap(),
+        Err(e) => error(500, e),
+    }
+}
+
+/// Handle Prometheus metrics request
+fn handle_metrics(state: &BridgeState) -> Response {
     HttpResponse::builder()
         .status(200)
-        .header("Content-Type", "application/json")
-        .header("Access-Control-Allow-Origin", "*")
-        .body(json)
+        .header("Content-Type", metrics::CONTENT_TYPE)
+        .body(metrics::render(state).into_bytes())
+        .unwrap()
+}
+
+// =============================================================================
+// Response Headers
+// =============================================================================
+
+/// CORS and caching headers of every `frame://` response
+///
+/// Defaults to `config::protocol`; embedders that can't ship a wildcard
+/// origin set their own through `AppBuilder::protocol_headers`.
+#[derive(Clone, Debug)]
+pub struct ProtocolHeaders {
+    /// Origins allowed to read responses; a request's `Origin` is echoed
+    /// back if listed, `"*"` allows any, and no origin is allowed if empty
+    pub allow_origins: Vec<String>,
+    /// `Cache-Control` of every response, none if `None`
+    pub cache_control: Option<String>,
+    /// Whether scripts may read the `X-Frame-*`, `Content-Range` and
+    /// `Accept-Ranges` headers
+    pub expose_headers: bool,
+}
+
+impl Default for ProtocolHeaders {
+    fn default() -> Self {
+        Self {
+            allow_origins: ALLOW_ORIGINS
+                .iter()
+                .map(|origin| origin.to_string())
+                .collect(),
+            cache_control: CACHE_CONTROL.map(str::to_string),
+            expose_headers: EXPOSE_HEADERS,
+        }
+    }
+}
+
+impl ProtocolHeaders {
+    /// Set the configured headers on `response` to a request from `origin`
+    fn apply(&self, mut response: Response, origin: Option<&str>) -> Response {
+        let headers = response.headers_mut();
+        let allowed = if self.allow_origins.iter().any(|allowed| allowed == "*") {
+            Some("*")
+        } else {
+            origin.filter(|origin| self.allow_origins.iter().any(|allowed| allowed == origin))
+        };
+        if let Some(value) = allowed.and_then(|allowed| HeaderValue::from_str(allowed).ok()) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, value);
+        }
+        // The answer depends on the origin unless it's a wildcard
+        if allowed != Some("*") && !self.allow_origins.is_empty() {
+            headers.insert(header::VARY, HeaderValue::from_static("Origin"));
+        }
+        if !self.expose_headers {
+            headers.remove(header::ACCESS_CONTROL_EXPOSE_HEADERS);
+        }
+        if let Some(value) = self
+            .cache_control
+            .as_deref()
+            .and_then(|value| HeaderValue::from_str(value).ok())
+        {
+            headers.insert(header::CACHE_CONTROL, value);
+        }
+        response
+    }
+}
+
+// =============================================================================
+// Scene API
+// =============================================================================
+
+/// Body of `PUT camera/preset`
+#[derive(Deserialize)]
+struct PresetRequest {
+    name: String,
+    #[serde(default = "animate_default")]
+    animate: bool,
+}
+
+/// Handle a POST or PUT request editing the scene
+///
+/// Endpoints, each taking a JSON body with the arguments of the invoke
+/// command of the same purpose:
+/// - `POST scene/spawn`: add a primitive, see `spawn_primitive`; answers
+///   `201 Created` with `{"id": <entity id>}`
+/// - `PUT scene/<id>/transform`: move entity `id`, see
+///   `set_entity_transform`
+/// - `PUT camera/preset`: move the camera to preset `{"name": ...}`,
+///   smoothly, or instantly with `"animate": false`
+///
+/// Malformed bodies answer `400 Bad Request`, operations the Bevy thread
+/// rejects `422 Unprocessable Entity`, edits while the scene is locked
+/// `423 Locked` and a Bevy thread that doesn't answer
+/// `504 Gateway Timeout`, each with the error as plain text.
+fn handle_scene_request(method: &Method, path: &str, body: &[u8], state: &BridgeState) -> Response {
+    let _span = info_span!("scene_api").entered();
+    let resource = path.trim_start_matches('/');
+    println!("[Protocol] {} {}", method, resource);
+
+    let segments: Vec<&str> = resource.split('/').collect();
+    let result = match segments.as_slice() {
+        ["scene", "spawn"] if *method == Method::POST => spawn(body, state),
+        ["scene", id, "transform"] if *method == Method::PUT => set_transform(id, body, state),
+        ["camera", "preset"] if *method == Method::PUT => apply_preset(body, state),
+        ["scene", "spawn"] => Err(method_not_allowed("POST")),
+        ["scene", _, "transform"] | ["camera", "preset"] => Err(method_not_allowed("PUT")),
+        _ => Err(error(404, "Not Found".to_string())),
+    };
+
+    match result {
+        Ok((status, body)) => {
+            let mut response = HttpResponse::builder().status(status);
+            if !body.is_empty() {
+                response = response.header("Content-Type", "application/json");
+            }
+            response.body(body).unwrap()
+        }
+        Err(response) => response,
+    }
+}
+
+/// Status and JSON body of a successful scene request, or the error
+/// response
+type SceneResult = Result<(u16, Vec<u8>), Response>;
+
+fn spawn(body: &[u8], state: &BridgeState) -> SceneResult {
+    let primitive: SpawnPrimitive = parse_body(body)?;
+    let id = request(state, |reply| BridgeCommand::SpawnPrimitive {
+        primitive,
+        reply,
+    })?;
+    Ok((201, json_body(&IdResponse { id })))
+}
+
+fn set_transform(id: &str, body: &[u8], state: &BridgeState) -> SceneResult {
+    let id: u64 = id
+        .parse()
+        .map_err(|_| error(400, format!("Invalid entity id {}", id)))?;
+    let transform: TransformUpdate = parse_body(body)?;
+    request(state, |reply| BridgeCommand::SetEntityTransform {
+        id,
+        transform,
+        reply,
+    })?;
+    Ok((204, Vec::new()))
+}
+
+fn apply_preset(body: &[u8], state: &BridgeState) -> SceneResult {
+    let preset: PresetRequest = parse_body(body)?;
+    request(state, |reply| BridgeCommand::ApplyCameraPreset {
+        name: preset.name,
+        animate: preset.animate,
+        reply,
+    })?;
+    Ok((204, Vec::new()))
+}
+
+/// Body of `POST scene/spawn` responses
+#[derive(Serialize)]
+struct IdResponse {
+    id: u64,
+}
+
+fn parse_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, Response> {
+    serde_json::from_slice(body).map_err(|e| error(400, format!("Invalid body: {}", e)))
+}
+
+fn json_body(value: &impl Serialize) -> Vec<u8> {
+    serde_json::to_vec(value).unwrap_or_default()
+}
+
+/// Send the command built around a reply channel and wait for the
+/// operation's result
+fn request<T>(
+    state: &BridgeState,
+    command: impl FnOnce(Sender<Result<T, String>>) -> BridgeCommand,
+) -> Result<T, Response> {
+    query(state, command)?.map_err(|e| error(422, e))
+}
+
+/// Send the command built around a reply channel and wait for the reply
+fn query<T>(
+    state: &BridgeState,
+    command: impl FnOnce(Sender<T>) -> BridgeCommand,
+) -> Result<T, Response> {
+    let (reply, response) = crossbeam_channel::bounded(1);
+    state.commands.send(command(reply)).map_err(|e| {
+        // Edits are rejected before they reach the Bevy thread
+        let status = if SceneLockedError::matches(&e) {
+            423
+        } else {
+            503
+        };
+        error(status, e)
+    })?;
+    response
+        .recv_timeout(TIMEOUT)
+        .map_err(|_| error(504, "Timed out waiting for the Bevy thread".to_string()))
+}
+
+fn error(status: u16, message: String) -> Response {
+    HttpResponse::builder()
+        .status(status)
+        .header("Content-Type", "text/plain")
+        .body(message.into_bytes())
+        .unwrap()
+}
+
+fn method_not_allowed(allow: &str) -> Response {
+    HttpResponse::builder()
+        .status(405)
+        .header("Allow", allow)
+        .body(Vec::new())
         .unwrap()
 }
@@ -0,0 +1,647 @@
+//! Session recording
+//!
+//! Records published frames into an H.264 MP4 file, or an AV1 WebM file
+//! (see `webm`), so interaction sessions can be captured straight from the
+//! headless renderer. Like the JPEG
+//! encoder, the recorder runs on its own thread and wakes up on the frame
+//! signal, so encoding never holds up Bevy or the `frame://` protocol.
+//!
+//! Frames are timestamped with their publication time rather than assumed
+//! to arrive at a fixed rate: a frame stays on screen until the next one
+//! was published, which keeps playback speed right when the renderer
+//! adapts its frame rate or skips identical frames.
+//!
+//! Optionally a JSON Lines sidecar next to the video gets one line per
+//! video frame, with the camera and scene time it was rendered with.
+
+use bytes::Bytes;
+use mp4::{
+    AvcConfig, FourCC, MediaConfig, Mp4Config, Mp4Sample, Mp4Writer, TrackConfig, TrackType,
+};
+use openh264::encoder::{BitRate, Encoder, EncoderConfig, FrameRate};
+use openh264::formats::{RgbSliceU8, YUVBuffer, YUVSource};
+use openh264::OpenH264API;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::info_span;
+
+use crate::config::recording::{DEFAULT_BITRATE, DEFAULT_FPS, PROGRESS_INTERVAL};
+use super::encoder::convert_to_rgb8;
+use super::frame_export::FrameSidecar;
+use super::overlay::Overlay;
+use super::shared_state::{BridgeState, Frame, SharedBufferPool};
+use super::webm::WebmRecording;
+
+/// MP4 timescale, sample times are in milliseconds
+const TIMESCALE: u32 = 1000;
+
+/// Track id of the only track in the file
+const VIDEO_TRACK: u32 = 1;
+
+/// How long the thread waits for a frame before checking for a stop request
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Container and codec of a recording
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingFormat {
+    /// H.264 in MP4, plays everywhere
+    Mp4,
+    /// AV1 in WebM, encoded in pure Rust; smaller files, more CPU
+    Webm,
+}
+
+impl RecordingFormat {
+    /// Format matching the extension of `path`, MP4 unless it is `.webm`
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("webm") => RecordingFormat::Webm,
+            _ => RecordingFormat::Mp4,
+        }
+    }
+}
+
+/// Options for `start_recording`, all optional
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+pub struct RecordingOptions {
+    /// Container and codec, defaults to the one matching the file extension
+    pub format: Option<RecordingFormat>,
+    /// Maximum frame rate of the video, defaults to `DEFAULT_FPS`
+    pub fps: Option<f32>,
+    /// Target bitrate in bits per second, defaults to `DEFAULT_BITRATE`
+    pub bitrate: Option<u32>,
+    /// Also write per-frame metadata to a `.jsonl` file next to the video
+    #[serde(default)]
+    pub sidecar: bool,
+}
+
+/// State of a recording, sent with `recording-progress` events and
+/// returned by `stop_recording`
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct RecordingProgress {
+    pub path: String,
+    /// Frames written to the file
+    pub frames: u64,
+    /// Frames left out: above the requested frame rate, dropped by the
+    /// encoder's rate control, or published at a different size
+    pub frames_skipped: u64,
+    /// Video duration so far in seconds
+    pub duration_s: f64,
+    /// Encoded video data written so far
+    pub size_bytes: u64,
+}
+
+struct ActiveRecording {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<Result<RecordingProgress, String>>,
+}
+
+/// Owner of the recording thread, managed as Tauri state
+pub struct Recorder {
+    bridge: BridgeState,
+    active: Mutex<Option<ActiveRecording>>,
+}
+
+impl Recorder {
+    pub fn new(bridge: BridgeState) -> Self {
+        Self {
+            bridge,
+            active: Mutex::new(None),
+        }
+    }
+
+    /// Start recording published frames to `path`
+    ///
+    /// `on_progress` is called from the recording thread every
+    /// `PROGRESS_INTERVAL`. Fails if a recording is already running.
+    pub fn start(
+        &self,
+        path: PathBuf,
+        options: RecordingOptions,
+        on_progress: impl Fn(&RecordingProgress) + Send + 'static,
+    ) -> Result<(), String> {
+        let mut active = self.active.lock().map_err(|e| e.to_string())?;
+        if active
+            .as_ref()
+            .is_some_and(|recording| !recording.thread.is_finished())
+        {
+            return Err("A recording is already running".to_string());
+        }
+
+        let format = options
+            .format
+            .unwrap_or_else(|| RecordingFormat::from_path(&path));
+        let fps = options.fps.unwrap_or(DEFAULT_FPS);
+        let bitrate = options.bitrate.unwrap_or(DEFAULT_BITRATE);
+        if !fps.is_finite() || fps <= 0.0 || bitrate == 0 {
+            return Err("fps and bitrate must be positive".to_string());
+        }
+
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let bridge = self.bridge.clone();
+
+        let thread = thread::Builder::new()
+            .name("recorder".into())
+            .spawn(move || {
+                // Created on this thread, the encoder handle stays here
+                let mut recording = Recording::create(&path, format, fps, bitrate);
+                if options.sidecar {
+                    let sidecar = path.with_extension("jsonl");
+                    recording = recording.and_then(|recording| recording.with_sidecar(&sidecar));
+                }
+                let mut recording = match recording {
+                    Ok(recording) => {
+                        let _ = ready_tx.send(Ok(()));
+                        recording
+                    }
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e.clone()));
+                        return Err(e);
+                    }
+                };
+                println!(
+                    "[Recorder] Recording {:?} to {} at up to {}fps, {}kbps",
+                    format,
+                    path.display(),
+                    fps,
+                    bitrate / 1000
+                );
+                // The last frame lasts until the recording was stopped
+                let result = record(&bridge, &mut recording, &thread_stop, on_progress)
+                    .and_then(|()| recording.finish(now_ms()));
+                match &result {
+                    Ok(progress) => println!(
+                        "[Recorder] Saved {} frames ({:.1}s) to {}",
+                        progress.frames, progress.duration_s, progress.path
+                    ),
+                    Err(e) => eprintln!("[Recorder] Recording failed: {}", e),
+                }
+                result
+            })
+            .map_err(|e| e.to_string())?;
+        ready_rx
+            .recv()
+            .map_err(|_| "Recording thread exited during setup".to_string())??;
+
+        *active = Some(ActiveRecording { stop, thread });
+        Ok(())
+    }
+
+    /// Stop the running recording and wait for the file to be finalized
+    pub fn stop(&self) -> Result<RecordingProgress, String> {
+        let recording = self
+            .active
+            .lock()
+            .map_err(|e| e.to_string())?
+            .take()
+            .ok_or_else(|| "No recording is running".to_string())?;
+
+        recording.stop.store(true, Ordering::Relaxed);
+        recording
+            .thread
+            .join()
+            .map_err(|_| "Recording thread panicked".to_string())?
+    }
+}
+
+/// Feed published frames into `recording` until `stop` is set
+fn record(
+    bridge: &BridgeState,
+    recording: &mut Recording,
+    stop: &AtomicBool,
+    on_progress: impl Fn(&RecordingProgress),
+) -> Result<(), String> {
+    let mut last_seen = 0;
+    let mut last_progress = Instant::now();
+
+    while !stop.load(Ordering::Relaxed) {
+        if let Some(sequence) = bridge
+            .frame_signal
+            .wait_newer_timeout(last_seen, STOP_POLL_INTERVAL)
+        {
+            last_seen = sequence;
+            if let Some(frame) = bridge.frame_buffer.latest() {
+                let overlay = bridge.overlay.current();
+                recording.push_frame(&frame, &bridge.buffer_pool, overlay.as_deref())?;
+            }
+        }
+
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            on_progress(&recording.progress);
+        }
+    }
+    Ok(())
+}
+
+/// Encoder and container writer behind a recording
+pub trait VideoSink {
+    /// Encode a frame, shown from `timestamp_ms` until the next one
+    fn write_frame(
+        &mut self,
+        yuv: &YUVBuffer,
+        timestamp_ms: f64,
+        progress: &mut RecordingProgress,
+    ) -> Result<(), String>;
+
+    /// Flush the encoder and finalize the file
+    ///
+    /// The last frame lasts until `end_ms`.
+    fn finish(self: Box<Self>, end_ms: f64, progress: &mut RecordingProgress)
+        -> Result<(), String>;
+}
+
+/// Frame selection and conversion in front of a [`VideoSink`]
+pub struct Recording {
+    sink: Box<dyn VideoSink>,
+    frame_interval_ms: f64,
+    /// Video size, fixed by the first frame
+    size: Option<(u32, u32)>,
+    resize_warned: bool,
+    last_frame_ms: Option<f64>,
+    sidecar: Option<SidecarLog>,
+    progress: RecordingProgress,
+}
+
+impl Recording {
+    pub fn create(
+        path: &Path,
+        format: RecordingFormat,
+        fps: f32,
+        bitrate: u32,
+    ) -> Result<Self, String> {
+        let sink: Box<dyn VideoSink> = match format {
+            RecordingFormat::Mp4 => Box::new(Mp4Recording::create(path, fps, bitrate)?),
+            RecordingFormat::Webm => Box::new(WebmRecording::create(path, fps, bitrate)?),
+        };
+        Ok(Self {
+            sink,
+            frame_interval_ms: 1000.0 / fps as f64,
+            size: None,
+            resize_warned: false,
+            last_frame_ms: None,
+            sidecar: None,
+            progress: RecordingProgress {
+                path: path.display().to_string(),
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Log the metadata of every written frame to `path` as JSON Lines
+    pub fn with_sidecar(mut self, path: &Path) -> Result<Self, String> {
+        self.sidecar = Some(SidecarLog::create(path)?);
+        Ok(self)
+    }
+
+    /// Encode `frame` unless it came too soon after the previous one
+    pub fn push_frame(
+        &mut self,
+        frame: &Frame,
+        pool: &SharedBufferPool,
+        overlay: Option<&Overlay>,
+    ) -> Result<(), String> {
+        // Some slack for publish jitter, so a 30fps stream isn't halved
+        // when recorded at 30fps
+        if let Some(last_frame_ms) = self.last_frame_ms {
+            if frame.timestamp_ms - last_frame_ms < self.frame_interval_ms * 0.9 {
+                self.progress.frames_skipped += 1;
+                return Ok(());
+            }
+        }
+
+        // 4:2:0 chroma subsampling needs even dimensions
+        let size = (frame.width & !1, frame.height & !1);
+        if size.0 == 0 || size.1 == 0 {
+            return Ok(());
+        }
+        match self.size {
+            None => self.size = Some(size),
+            Some(recorded) if recorded != size => {
+                if !self.resize_warned {
+                    self.resize_warned = true;
+                    eprintln!(
+                        "[Recorder] Stream resized to {}x{}, skipping frames until it is {}x{} again",
+                        size.0, size.1, recorded.0, recorded.1
+                    );
+                }
+                self.progress.frames_skipped += 1;
+                return Ok(());
+            }
+            Some(_) => {}
+        }
+
+        let _span = info_span!("record_frame").entered();
+        let mut converted = convert_to_rgb8(frame, pool);
+        if let Some(overlay) = overlay {
+            // Drawn into a copy, the published frame is shared with the stream
+            let rgb = converted.get_or_insert_with(|| {
+                let mut copy = pool.take(frame.data.len());
+                copy.extend_from_slice(&frame.data);
+                copy
+            });
+            overlay.apply(rgb, frame.width, frame.height, 3, frame.timestamp_ms);
+        }
+        let rgb = converted.as_deref().unwrap_or(&frame.data);
+        let (width, height) = (size.0 as usize, size.1 as usize);
+        let yuv = if width == frame.width as usize {
+            YUVBuffer::from_rgb_source(RgbSliceU8::new(&rgb[..width * height * 3], (width, height)))
+        } else {
+            let row_bytes = frame.width as usize * 3;
+            let mut cropped = Vec::with_capacity(width * height * 3);
+            for row in rgb.chunks_exact(row_bytes).take(height) {
+                cropped.extend_from_slice(&row[..width * 3]);
+            }
+            YUVBuffer::from_rgb_source(RgbSliceU8::new(&cropped, (width, height)))
+        };
+        if let Some(converted) = converted {
+            pool.recycle(converted);
+        }
+
+        self.last_frame_ms = Some(frame.timestamp_ms);
+        let skipped = self.progress.frames_skipped;
+        self.sink
+            .write_frame(&yuv, frame.timestamp_ms, &mut self.progress)?;
+        // Frames dropped by the encoder are not in the video either
+        match &mut self.sidecar {
+            Some(sidecar) if self.progress.frames_skipped == skipped => sidecar.write(frame),
+            _ => Ok(()),
+        }
+    }
+
+    /// Finalize the file, the last frame lasts until `end_ms`
+    pub fn finish(mut self, end_ms: f64) -> Result<RecordingProgress, String> {
+        self.sink.finish(end_ms, &mut self.progress)?;
+        if let Some(sidecar) = self.sidecar {
+            sidecar.finish()?;
+        }
+        Ok(self.progress)
+    }
+}
+
+/// Line of a recording's sidecar
+#[derive(Serialize)]
+struct RecordedFrame {
+    /// Position of the frame in the video
+    index: u64,
+    /// Presentation time in the video in seconds
+    video_time_s: f64,
+    #[serde(flatten)]
+    frame: FrameSidecar,
+}
+
+/// JSON Lines file with the metadata of each frame in a recording
+struct SidecarLog {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    frames: u64,
+    start_ms: Option<f64>,
+}
+
+impl SidecarLog {
+    fn create(path: &Path) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+            frames: 0,
+            start_ms: None,
+        })
+    }
+
+    fn write(&mut self, frame: &Frame) -> Result<(), String> {
+        let start_ms = *self.start_ms.get_or_insert(frame.timestamp_ms);
+        let line = RecordedFrame {
+            index: self.frames,
+            video_time_s: (frame.timestamp_ms - start_ms) / 1000.0,
+            frame: FrameSidecar::new(frame),
+        };
+        serde_json::to_writer(&mut self.writer, &line).map_err(|e| e.to_string())?;
+        self.writer
+            .write_all(b"\n")
+            .map_err(|e| format!("{}: {}", self.path.display(), e))?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), String> {
+        self.writer
+            .flush()
+            .map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+}
+
+// =============================================================================
+// MP4
+// =============================================================================
+
+/// Encoded frame waiting for the next one to know its duration
+struct PendingSample {
+    timestamp_ms: f64,
+    is_sync: bool,
+    data: Vec<u8>,
+}
+
+/// H.264 encoder and MP4 writer for one recording
+struct Mp4Recording {
+    writer: Mp4Writer<BufWriter<File>>,
+    encoder: Encoder,
+    sps: Option<Vec<u8>>,
+    pps: Option<Vec<u8>>,
+    track_added: bool,
+    start_ms: f64,
+    pending: Option<PendingSample>,
+}
+
+impl Mp4Recording {
+    fn create(path: &Path, fps: f32, bitrate: u32) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let config = Mp4Config {
+            major_brand: brand("isom"),
+            minor_version: 512,
+            compatible_brands: vec![brand("isom"), brand("iso2"), brand("avc1"), brand("mp41")],
+            timescale: TIMESCALE,
+        };
+        let writer =
+            Mp4Writer::write_start(BufWriter::new(file), &config).map_err(|e| e.to_string())?;
+
+        let encoder_config = EncoderConfig::new()
+            .bitrate(BitRate::from_bps(bitrate))
+            .max_frame_rate(FrameRate::from_hz(fps));
+        let encoder = Encoder::with_api_config(OpenH264API::from_source(), encoder_config)
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            writer,
+            encoder,
+            sps: None,
+            pps: None,
+            track_added: false,
+            start_ms: 0.0,
+            pending: None,
+        })
+    }
+
+    /// Write the pending sample, shown until `end_ms`
+    fn write_pending(
+        &mut self,
+        end_ms: f64,
+        progress: &mut RecordingProgress,
+    ) -> Result<(), String> {
+        let Some(pending) = self.pending.take() else {
+            return Ok(());
+        };
+
+        let start_time = (pending.timestamp_ms - self.start_ms).max(0.0) as u64;
+        let duration = ((end_ms - pending.timestamp_ms).round() as u32).max(1);
+        progress.size_bytes += pending.data.len() as u64;
+        self.writer
+            .write_sample(
+                VIDEO_TRACK,
+                &Mp4Sample {
+                    start_time,
+                    duration,
+                    rendering_offset: 0,
+                    is_sync: pending.is_sync,
+                    bytes: Bytes::from(pending.data),
+                },
+            )
+            .map_err(|e| e.to_string())?;
+
+        progress.frames += 1;
+        progress.duration_s = (start_time + duration as u64) as f64 / 1000.0;
+        Ok(())
+    }
+}
+
+impl VideoSink for Mp4Recording {
+    fn write_frame(
+        &mut self,
+        yuv: &YUVBuffer,
+        timestamp_ms: f64,
+        progress: &mut RecordingProgress,
+    ) -> Result<(), String> {
+        let annex_b = self
+            .encoder
+            .encode(yuv)
+            .map_err(|e| e.to_string())?
+            .to_vec();
+
+        // MP4 stores parameter sets in the track header and the remaining
+        // NAL units length-prefixed instead of with start codes
+        let mut data = Vec::with_capacity(annex_b.len());
+        let mut is_sync = false;
+        for nal in split_nal_units(&annex_b) {
+            match nal[0] & 0x1F {
+                7 => {
+                    self.sps.get_or_insert_with(|| nal.to_vec());
+                }
+                8 => {
+                    self.pps.get_or_insert_with(|| nal.to_vec());
+                }
+                nal_type => {
+                    is_sync |= nal_type == 5;
+                    data.extend_from_slice(&(nal.len() as u32).to_be_bytes());
+                    data.extend_from_slice(nal);
+                }
+            }
+        }
+        // The encoder may drop a frame for rate control, the previous one
+        // then stays on screen longer
+        if data.is_empty() {
+            progress.frames_skipped += 1;
+            return Ok(());
+        }
+
+        if !self.track_added {
+            let (Some(sps), Some(pps), true) = (&self.sps, &self.pps, is_sync) else {
+                return Ok(());
+            };
+            let (width, height) = yuv.dimensions();
+            self.writer
+                .add_track(&TrackConfig {
+                    track_type: TrackType::Video,
+                    timescale: TIMESCALE,
+                    language: "und".to_string(),
+                    media_conf: MediaConfig::AvcConfig(AvcConfig {
+                        width: width as u16,
+                        height: height as u16,
+                        seq_param_set: sps.clone(),
+                        pic_param_set: pps.clone(),
+                    }),
+                })
+                .map_err(|e| e.to_string())?;
+            self.track_added = true;
+            self.start_ms = timestamp_ms;
+        }
+
+        self.write_pending(timestamp_ms, progress)?;
+        self.pending = Some(PendingSample {
+            timestamp_ms,
+            is_sync,
+            data,
+        });
+        Ok(())
+    }
+
+    /// Write the last frame and the file index
+    fn finish(
+        mut self: Box<Self>,
+        end_ms: f64,
+        progress: &mut RecordingProgress,
+    ) -> Result<(), String> {
+        self.write_pending(end_ms, progress)?;
+
+        self.writer.write_end().map_err(|e| e.to_string())?;
+        self.writer.into_writer().flush().map_err(|e| e.to_string())
+    }
+}
+
+/// Milliseconds since the Unix epoch
+fn now_ms() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64() * 1000.0)
+        .unwrap_or_default()
+}
+
+fn brand(name: &str) -> FourCC {
+    name.parse().expect("brand is a four-character code")
+}
+
+/// Split an Annex B byte stream into NAL units, without start codes
+fn split_nal_units(stream: &[u8]) -> Vec<&[u8]> {
+    let mut units = Vec::new();
+    let mut unit_start = None;
+    let mut i = 0;
+    while i + 3 <= stream.len() {
+        if stream[i..i + 3] != [0, 0, 1] {
+            i += 1;
+            continue;
+        }
+        if let Some(start) = unit_start {
+            // Trailing zeros belong to a 4-byte start code, NAL units
+            // never end in a zero byte
+            let mut end = i;
+            while end > start && stream[end - 1] == 0 {
+                end -= 1;
+            }
+            units.push(&stream[start..end]);
+        }
+        i += 3;
+        unit_start = Some(i);
+    }
+    if let Some(start) = unit_start {
+        units.push(&stream[start..]);
+    }
+    units.retain(|unit| !unit.is_empty());
+    units
+}
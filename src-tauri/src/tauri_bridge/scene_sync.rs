@@ -0,0 +1,408 @@
+//! Scene synchronization between instances
+//!
+//! Scene edits, such as spawning, moving, recoloring or removing entities,
+//! are sent to other running instances over TCP and applied there, so
+//! several windows or machines show the same live scene, each with its own
+//! camera. Instances connect to each other with `start_scene_sync` and
+//! `connect_scene_sync`; every connection carries edits both ways, one
+//! JSON [`SyncMessage`] per line.
+//!
+//! Edits are forwarded to the other connections of the instance receiving
+//! them, so instances can be chained or all connect to one hub. Each
+//! message carries its instance and a sequence number, and ones already
+//! applied are dropped, which stops edits from going round in circles.
+//! Instances connecting later are sent the edits made so far.
+//!
+//! Entity ids differ between instances, so edits name entities by
+//! [`SyncTarget`] instead: entities spawned by a synced edit by the
+//! instance and id they were spawned with, others, such as the nodes of a
+//! loaded model, by their names from the root down.
+//!
+//! Undo and redo only step through the edits made in an instance. They are
+//! sent as the id of the edit undone or redone, and the other instances
+//! reverse or reapply what that edit changed there.
+
+use crossbeam_channel::Sender;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+use super::shared_state::{
+    AddBillboard, BridgeCommand, CustomShaderMaterialSettings, MaterialOverride, MorphTarget,
+    SharedCommandQueue, SpawnPrimitive, SpawnText3d, TaggedOperation, TransformUpdate,
+};
+use crate::config::scene_sync::{MAX_LOG, WRITE_TIMEOUT};
+
+/// Entity an edit applies to, valid in every instance
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncTarget {
+    /// Spawned by a synced edit in `instance` as entity `entity`
+    Spawned { instance: u64, entity: u64 },
+    /// Names of the entity and its ancestors, root first
+    Named(Vec<String>),
+}
+
+/// A scene edit sent to other instances, with the arguments of the command
+/// that made it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum SceneOp {
+    /// `entity` is the id in the instance that spawned it
+    SpawnPrimitive {
+        entity: u64,
+        primitive: SpawnPrimitive,
+    },
+    SpawnText3d {
+        entity: u64,
+        text: SpawnText3d,
+    },
+    AddBillboard {
+        entity: u64,
+        billboard: AddBillboard,
+    },
+    RemoveBillboard {
+        target: SyncTarget,
+    },
+    ClearBillboards,
+    SetEntityTransform {
+        target: SyncTarget,
+        transform: TransformUpdate,
+    },
+    /// Targets as they were before the change
+    SetParent {
+        target: SyncTarget,
+        parent: SyncTarget,
+    },
+    Detach {
+        target: SyncTarget,
+    },
+    SetSubmeshMaterial {
+        target: SyncTarget,
+        material: Option<MaterialOverride>,
+    },
+    RegisterCustomShader {
+        name: String,
+        source: String,
+    },
+    SetCustomShaderMaterial {
+        target: SyncTarget,
+        material: Option<CustomShaderMaterialSettings>,
+    },
+    SetMorphWeight {
+        target: SyncTarget,
+        morph_target: MorphTarget,
+        weight: f32,
+    },
+    SetTags {
+        target: SyncTarget,
+        tags: Vec<String>,
+    },
+    UpdateTagged {
+        tag: String,
+        operation: TaggedOperation,
+    },
+    /// `update_selection_set`, on the members of the set
+    UpdateEntities {
+        targets: Vec<SyncTarget>,
+        operation: TaggedOperation,
+    },
+    /// Sent once the model has loaded
+    LoadModel {
+        path: PathBuf,
+    },
+    /// `edit` is the id of the edit in the instance that made it
+    Undo {
+        edit: u64,
+    },
+    Redo {
+        edit: u64,
+    },
+}
+
+/// One line on a sync connection
+#[derive(Serialize, Deserialize)]
+pub struct SyncMessage {
+    /// Instance the edit was made in
+    pub instance: u64,
+    /// Counts up from 1 for each edit the instance makes
+    pub seq: u64,
+    /// Id of the undoable edit `op` made, which `Undo` and `Redo` refer to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edit: Option<u64>,
+    #[serde(flatten)]
+    pub op: SceneOp,
+}
+
+/// Where this instance listens and who it is connected to, for
+/// `get_scene_sync_status`
+#[derive(Serialize, Clone, Debug)]
+pub struct SceneSyncStatus {
+    /// This instance's id, as it appears in `SyncTarget::Spawned`
+    pub instance: u64,
+    pub listening: Option<String>,
+    pub peers: Vec<String>,
+    /// Edits kept for instances that connect later
+    pub logged_edits: usize,
+}
+
+/// Connections to other instances, shared by the Bevy thread and the
+/// connection threads
+#[derive(Clone)]
+pub struct SceneSync(Arc<SyncHub>);
+
+struct SyncHub {
+    instance: u64,
+    state: Mutex<SyncState>,
+}
+
+#[derive(Default)]
+struct SyncState {
+    listening: Option<SocketAddr>,
+    peers: Vec<Peer>,
+    next_peer: u64,
+    next_seq: u64,
+    /// Highest sequence number applied from each other instance
+    applied: HashMap<u64, u64>,
+    /// Lines sent and received so far, oldest first
+    log: VecDeque<Arc<str>>,
+}
+
+struct Peer {
+    id: u64,
+    address: SocketAddr,
+    /// Lines for the writer thread, which ends when this is dropped
+    outgoing: Sender<Arc<str>>,
+    stream: TcpStream,
+}
+
+impl Default for SceneSync {
+    fn default() -> Self {
+        Self(Arc::new(SyncHub {
+            instance: RandomState::new().build_hasher().finish(),
+            state: Mutex::default(),
+        }))
+    }
+}
+
+impl SyncState {
+    /// Log `line` and send it to every peer but `except`
+    fn send(&mut self, line: Arc<str>, except: Option<u64>) {
+        if self.log.len() == MAX_LOG {
+            self.log.pop_front();
+        }
+        self.log.push_back(line.clone());
+        for peer in &self.peers {
+            if Some(peer.id) != except {
+                let _ = peer.outgoing.send(line.clone());
+            }
+        }
+    }
+}
+
+impl SceneSync {
+    /// This instance's id
+    pub fn instance(&self) -> u64 {
+        self.0.instance
+    }
+
+    /// Whether edits are being sent anywhere, checked before working out
+    /// what to send
+    pub fn is_active(&self) -> bool {
+        self.0
+            .state
+            .lock()
+            .is_ok_and(|state| state.listening.is_some() || !state.peers.is_empty())
+    }
+
+    /// Accept instances connecting to `address` from now on, returning
+    /// the address listened on
+    pub fn listen(&self, address: &str, commands: SharedCommandQueue) -> io::Result<SocketAddr> {
+        let mut state = self.lock()?;
+        if let Some(listening) = state.listening {
+            return Err(io::Error::other(format!(
+                "Already listening on {}",
+                listening
+            )));
+        }
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        state.listening = Some(address);
+        drop(state);
+        println!("[Sync] Listening on {}", address);
+
+        let sync = self.clone();
+        thread::Builder::new()
+            .name("scene-sync".into())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    let result = stream.and_then(|stream| sync.add_peer(stream, commands.clone()));
+                    if let Err(e) = result {
+                        eprintln!("[Sync] Failed to accept an instance: {}", e);
+                    }
+                }
+            })?;
+        Ok(address)
+    }
+
+    /// Connect to the instance listening on `address`
+    pub fn connect(&self, address: &str, commands: SharedCommandQueue) -> io::Result<()> {
+        let stream = TcpStream::connect(address)?;
+        self.add_peer(stream, commands)
+    }
+
+    /// Close every connection, leaving the listener open
+    pub fn disconnect(&self) {
+        let Ok(mut state) = self.lock() else {
+            return;
+        };
+        for peer in state.peers.drain(..) {
+            let _ = peer.stream.shutdown(Shutdown::Both);
+        }
+        println!("[Sync] Disconnected from all instances");
+    }
+
+    /// Send `op`, made in this instance as part of undoable edit `edit`,
+    /// to every connected instance
+    pub fn publish(&self, op: SceneOp, edit: Option<u64>) {
+        let Ok(mut state) = self.lock() else {
+            return;
+        };
+        if state.listening.is_none() && state.peers.is_empty() {
+            return;
+        }
+        state.next_seq += 1;
+        let message = SyncMessage {
+            instance: self.0.instance,
+            seq: state.next_seq,
+            edit,
+            op,
+        };
+        match serde_json::to_string(&message) {
+            Ok(line) => state.send(format!("{}\n", line).into(), None),
+            Err(e) => eprintln!("[Sync] Failed to encode an edit: {}", e),
+        }
+    }
+
+    pub fn status(&self) -> SceneSyncStatus {
+        let state = self.lock().ok();
+        SceneSyncStatus {
+            instance: self.0.instance,
+            listening: state
+                .as_ref()
+                .and_then(|state| state.listening)
+                .map(|address| address.to_string()),
+            peers: state
+                .as_ref()
+                .map(|state| {
+                    state
+                        .peers
+                        .iter()
+                        .map(|peer| peer.address.to_string())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            logged_edits: state.as_ref().map_or(0, |state| state.log.len()),
+        }
+    }
+
+    fn lock(&self) -> io::Result<MutexGuard<'_, SyncState>> {
+        self.0
+            .state
+            .lock()
+            .map_err(|_| io::Error::other("Scene sync state is poisoned"))
+    }
+
+    /// Send the edits so far to a new connection, then keep exchanging
+    /// edits on it until it closes
+    fn add_peer(&self, stream: TcpStream, commands: SharedCommandQueue) -> io::Result<()> {
+        let address = stream.peer_addr()?;
+        stream.set_nodelay(true)?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let (outgoing, queued) = crossbeam_channel::unbounded::<Arc<str>>();
+        let mut writer = stream.try_clone()?;
+        let peer_stream = stream.try_clone()?;
+
+        let id = {
+            let mut state = self.lock()?;
+            let id = state.next_peer;
+            state.next_peer += 1;
+            for line in &state.log {
+                let _ = outgoing.send(line.clone());
+            }
+            state.peers.push(Peer {
+                id,
+                address,
+                outgoing,
+                stream: peer_stream,
+            });
+            id
+        };
+        println!("[Sync] Connected to {}", address);
+
+        thread::Builder::new()
+            .name("scene-sync-writer".into())
+            .spawn(move || {
+                for line in queued {
+                    if writer.write_all(line.as_bytes()).is_err() {
+                        // Ends the reader too, which removes the peer
+                        let _ = writer.shutdown(Shutdown::Both);
+                        return;
+                    }
+                }
+            })?;
+        let sync = self.clone();
+        thread::Builder::new()
+            .name("scene-sync-reader".into())
+            .spawn(move || {
+                for line in BufReader::new(stream).lines() {
+                    let result = line
+                        .map_err(|e| e.to_string())
+                        .and_then(|line| sync.receive(id, line, &commands));
+                    if let Err(e) = result {
+                        eprintln!("[Sync] Connection to {} failed: {}", address, e);
+                        break;
+                    }
+                }
+                if let Ok(mut state) = sync.lock() {
+                    state.peers.retain(|peer| peer.id != id);
+                }
+                println!("[Sync] Disconnected from {}", address);
+            })?;
+        Ok(())
+    }
+
+    /// Apply an edit from peer `peer` unless it was applied before, and
+    /// pass it on to the other peers
+    fn receive(
+        &self,
+        peer: u64,
+        line: String,
+        commands: &SharedCommandQueue,
+    ) -> Result<(), String> {
+        let message: SyncMessage = serde_json::from_str(&line).map_err(|e| e.to_string())?;
+        if message.instance == self.0.instance {
+            return Ok(());
+        }
+        let mut state = self.lock().map_err(|e| e.to_string())?;
+        let applied = state.applied.entry(message.instance).or_default();
+        if message.seq <= *applied {
+            return Ok(());
+        }
+        *applied = message.seq;
+        state.send(format!("{}\n", line).into(), Some(peer));
+        drop(state);
+        // Accepted while the scene is locked, Bevy holds it until unlocked
+        commands.send(BridgeCommand::ApplySceneOp {
+            instance: message.instance,
+            edit: message.edit,
+            op: message.op,
+        })
+    }
+}
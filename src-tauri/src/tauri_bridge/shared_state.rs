@@ -3,25 +3,674 @@
 //! This module defines thread-safe data structures that allow bidirectional
 //! communication between the Tauri frontend and the Bevy render backend.
 
+use arc_swap::ArcSwapOption;
+use crossbeam_channel::{Receiver, Sender};
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, Mutex};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, Condvar, Mutex,
+};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::config::gpu::MAX_REPORTED_ERRORS;
+use crate::config::health::STALL_THRESHOLD;
+use crate::config::latency::DISPLAY_LATENCY_SAMPLES;
+use crate::config::performance::{STATS_HISTORY_INTERVAL, STATS_HISTORY_SECONDS};
+use crate::startup::{self, StartupPhase};
+use crate::config::readback::MAX_POOLED_BUFFERS;
+use super::capture_sink::CaptureSinks;
+use super::overlay::{Corner, SharedOverlay};
+use super::protocol::ProtocolHeaders;
+use super::scene_sync::{SceneOp, SceneSync};
+use super::shared_surface::SharedSurface;
+use super::stream_manager::StreamManager;
 
 // =============================================================================
 // Frame Buffer
 // =============================================================================
 
-/// Thread-safe RGBA frame buffer shared between Bevy and Tauri
-/// Stores raw RGBA8 pixel data (4 bytes per pixel)
+/// Pixel layout of frame data
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PixelFormat {
+    /// 4 bytes per pixel, R G B A
+    Rgba8,
+    /// 3 bytes per pixel, R G B, alpha dropped
+    Rgb8,
+    /// 2 bytes per pixel, little-endian 5-6-5 bit R G B
+    Rgb565,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgba8 => 4,
+            PixelFormat::Rgb8 => 3,
+            PixelFormat::Rgb565 => 2,
+        }
+    }
+
+    /// Name used in HTTP headers and JSON
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PixelFormat::Rgba8 => "rgba8",
+            PixelFormat::Rgb8 => "rgb8",
+            PixelFormat::Rgb565 => "rgb565",
+        }
+    }
+}
+
+/// Sub-rectangle of the stream target to read back, in stream pixels
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegionOfInterest {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Camera and scene state a frame was rendered with
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameMetadata {
+    /// Camera position in world space
+    pub camera_translation: [f32; 3],
+    /// Camera orientation as an `[x, y, z, w]` quaternion
+    pub camera_rotation: [f32; 4],
+    /// Vertical field of view in degrees, `None` for orthographic cameras
+    pub fov_y_deg: Option<f32>,
+    /// Clipping planes
+    pub near: f32,
+    pub far: f32,
+    /// Animation time in seconds, see `SceneTime`
+    pub scene_time_s: f64,
+}
+
+/// A rendered frame published by Bevy
+pub struct Frame {
+    /// Sequential frame id, starting at 1
+    pub id: u64,
+    /// Publication time in milliseconds since the Unix epoch
+    pub timestamp_ms: f64,
+    pub width: u32,
+    pub height: u32,
+    /// Layout of `data`
+    pub format: PixelFormat,
+    /// Tightly packed pixel data, rows without padding
+    pub data: Vec<u8>,
+    /// View the frame was rendered from, if known
+    pub metadata: Option<FrameMetadata>,
+}
+
+impl Frame {
+    /// Create a frame stamped with the current wall-clock time
+    pub fn new(id: u64, width: u32, height: u32, format: PixelFormat, data: Vec<u8>) -> Self {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64() * 1000.0)
+            .unwrap_or_default();
+        Self {
+            id,
+            timestamp_ms,
+            width,
+            height,
+            format,
+            data,
+            metadata: None,
+        }
+    }
+}
+
+/// Thread-safe frame buffer shared between Bevy and Tauri
+///
+/// Frames are swapped in atomically, so readers (`get_frame`, the protocol
+/// handler, the encoder) never block the Bevy writer or each other. A reader
+/// keeps its `Arc<Frame>` alive even after a newer frame is published.
 #[derive(Clone, Default)]
-pub struct SharedFrameBuffer(pub Arc<Mutex<Option<Vec<u8>>>>);
+pub struct SharedFrameBuffer(pub Arc<ArcSwapOption<Frame>>);
+
+impl SharedFrameBuffer {
+    /// Get the most recently published frame
+    pub fn latest(&self) -> Option<Arc<Frame>> {
+        self.0.load_full()
+    }
+
+    /// Publish a new frame, returning the one it replaced
+    pub fn publish(&self, frame: Frame) -> Option<Arc<Frame>> {
+        self.0.swap(Some(Arc::new(frame)))
+    }
+}
+
+/// Notifies waiting threads whenever Bevy publishes a new frame
+///
+/// Holds a monotonically increasing frame sequence number, so a waiter can
+/// tell whether it has already seen the latest frame.
+#[derive(Clone, Default)]
+pub struct SharedFrameSignal(pub Arc<(Mutex<u64>, Condvar)>);
+
+impl SharedFrameSignal {
+    /// Announce that a new frame is available
+    pub fn notify(&self) {
+        let (sequence, condvar) = &*self.0;
+        if let Ok(mut sequence) = sequence.lock() {
+            *sequence += 1;
+            condvar.notify_all();
+        }
+    }
+
+    /// Block until a frame newer than `last_seen` is published
+    ///
+    /// Returns the sequence number of that frame, or `None` if the lock
+    /// was poisoned.
+    pub fn wait_newer(&self, last_seen: u64) -> Option<u64> {
+        let (sequence, condvar) = &*self.0;
+        let guard = sequence.lock().ok()?;
+        let guard = condvar
+            .wait_while(guard, |sequence| *sequence <= last_seen)
+            .ok()?;
+        Some(*guard)
+    }
+
+    /// Like [`Self::wait_newer`], but gives up after `timeout`
+    ///
+    /// Returns `None` on timeout as well, so callers can check for other
+    /// work between frames.
+    pub fn wait_newer_timeout(&self, last_seen: u64, timeout: Duration) -> Option<u64> {
+        let (sequence, condvar) = &*self.0;
+        let guard = sequence.lock().ok()?;
+        let (guard, result) = condvar
+            .wait_timeout_while(guard, timeout, |sequence| *sequence <= last_seen)
+            .ok()?;
+        (!result.timed_out()).then_some(*guard)
+    }
+}
+
+/// A JPEG-encoded copy of a published frame
+#[derive(Clone)]
+pub struct EncodedFrame {
+    /// Id of the source frame, see [`Frame::id`]
+    pub frame_id: u64,
+    /// Publication time of the source frame, see [`Frame::timestamp_ms`]
+    pub timestamp_ms: f64,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+/// Most recent JPEG-encoded frame, produced by the encoder thread
+#[derive(Clone, Default)]
+pub struct SharedEncodedFrame(pub Arc<Mutex<Option<EncodedFrame>>>);
+
+/// Counters of frames handed out to clients
+///
+/// Bevy compares these against the frames it publishes to detect frames
+/// that were rendered but never fetched by anyone.
+#[derive(Default)]
+pub struct FetchStats {
+    /// Total number of frame fetches served
+    pub fetch_count: AtomicU64,
+    /// Highest frame id that has been fetched
+    pub last_fetched_id: AtomicU64,
+}
+
+/// Thread-safe fetch counters shared between Tauri and Bevy
+#[derive(Clone, Default)]
+pub struct SharedFetchStats(pub Arc<FetchStats>);
+
+impl SharedFetchStats {
+    /// Record that frame `frame_id` was delivered to a client
+    pub fn record_fetch(&self, frame_id: u64) {
+        if self.0.fetch_count.fetch_add(1, Ordering::Relaxed) == 0 {
+            startup::mark(StartupPhase::FirstFrameDelivered);
+        }
+        self.0.last_fetched_id.fetch_max(frame_id, Ordering::Relaxed);
+    }
+
+    pub fn fetch_count(&self) -> u64 {
+        self.0.fetch_count.load(Ordering::Relaxed)
+    }
+
+    pub fn last_fetched_id(&self) -> u64 {
+        self.0.last_fetched_id.load(Ordering::Relaxed)
+    }
+}
+
+// =============================================================================
+// Statistics
+// =============================================================================
+
+/// Sliding window of the most recent samples
+///
+/// Samples are kept both in arrival order and sorted, and the sum is kept
+/// up to date, so each push costs one sorted insert and remove instead of
+/// re-summing or re-sorting the whole window.
+pub struct SampleWindow {
+    capacity: usize,
+    samples: VecDeque<f64>,
+    sorted: Vec<f64>,
+    sum: f64,
+}
+
+impl SampleWindow {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            samples: VecDeque::with_capacity(capacity),
+            sorted: Vec::with_capacity(capacity),
+            sum: 0.0,
+        }
+    }
+
+    /// Add a sample, evicting the oldest one when the window is full
+    pub fn push(&mut self, sample: f64) {
+        if self.samples.len() == self.capacity {
+            if let Some(oldest) = self.samples.pop_front() {
+                let index = self.sorted.partition_point(|s| s.total_cmp(&oldest).is_lt());
+                self.sorted.remove(index);
+                self.sum -= oldest;
+            }
+        }
+
+        let index = self.sorted.partition_point(|s| s.total_cmp(&sample).is_le());
+        self.sorted.insert(index, sample);
+        self.samples.push_back(sample);
+        self.sum += sample;
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.sorted.clear();
+        self.sum = 0.0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.is_empty() {
+            0.0
+        } else {
+            self.sum / self.len() as f64
+        }
+    }
+
+    pub fn min(&self) -> f64 {
+        self.sorted.first().copied().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> f64 {
+        self.sorted.last().copied().unwrap_or_default()
+    }
+
+    /// Nearest-rank percentile, `p` in `0.0..=1.0`
+    pub fn percentile(&self, p: f64) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let rank = ((self.len() - 1) as f64 * p.clamp(0.0, 1.0)).round() as usize;
+        self.sorted[rank]
+    }
+}
+
+/// Percentiles of the measured publication-to-display latency
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub struct LatencySummary {
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+}
+
+impl LatencySummary {
+    pub fn from_window(window: &SampleWindow) -> Self {
+        Self {
+            p50_ms: window.percentile(0.50),
+            p95_ms: window.percentile(0.95),
+            p99_ms: window.percentile(0.99),
+        }
+    }
+}
+
+/// Distribution of the time between consecutive published frames
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub struct FrameTimeSummary {
+    pub avg_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl FrameTimeSummary {
+    pub fn from_window(window: &SampleWindow) -> Self {
+        Self {
+            avg_ms: window.mean(),
+            p50_ms: window.percentile(0.50),
+            p95_ms: window.percentile(0.95),
+            p99_ms: window.percentile(0.99),
+            max_ms: window.max(),
+        }
+    }
+}
 
-/// Frame response containing Base64-encoded RGBA pixel data
+/// Recent frame display latencies reported by the frontend
+///
+/// Latency runs from frame publication in Bevy to the frontend drawing the
+/// frame. Both ends use wall-clock milliseconds since the Unix epoch.
+#[derive(Clone)]
+pub struct SharedDisplayLatency(pub Arc<Mutex<SampleWindow>>);
+
+impl Default for SharedDisplayLatency {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(SampleWindow::new(DISPLAY_LATENCY_SAMPLES))))
+    }
+}
+
+impl SharedDisplayLatency {
+    /// Record one latency sample and summarize the recent window
+    pub fn record(&self, latency_ms: f64) -> LatencySummary {
+        let Ok(mut samples) = self.0.lock() else {
+            return LatencySummary::default();
+        };
+        samples.push(latency_ms);
+        LatencySummary::from_window(&samples)
+    }
+
+    /// Forget all recorded samples
+    pub fn clear(&self) {
+        if let Ok(mut samples) = self.0.lock() {
+            samples.clear();
+        }
+    }
+}
+
+/// Time one frame spent in each pipeline stage
+///
+/// GPU, readback and encode times are those of the last published frame.
+#[derive(Serialize, Clone, Copy, Debug, Default)]
+pub struct FrameStages {
+    /// Main world CPU time, excluding readback
+    pub update_ms: f64,
+    /// Receiving and processing the readback
+    pub readback_ms: f64,
+    pub gpu_render_ms: f64,
+    pub gpu_copy_ms: f64,
+    pub jpeg_encode_ms: f64,
+}
+
+impl FrameStages {
+    /// Total time across all stages
+    pub fn total_ms(&self) -> f64 {
+        self.update_ms
+            + self.readback_ms
+            + self.gpu_render_ms
+            + self.gpu_copy_ms
+            + self.jpeg_encode_ms
+    }
+
+    /// Name of the stage taking the longest
+    pub fn dominant(&self) -> &'static str {
+        [
+            ("update", self.update_ms),
+            ("readback", self.readback_ms),
+            ("gpu_render", self.gpu_render_ms),
+            ("gpu_copy", self.gpu_copy_ms),
+            ("jpeg_encode", self.jpeg_encode_ms),
+        ]
+        .into_iter()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or("update", |(name, _)| name)
+    }
+
+    /// Add `other`'s times to these
+    pub fn accumulate(&mut self, other: &FrameStages) {
+        self.update_ms += other.update_ms;
+        self.readback_ms += other.readback_ms;
+        self.gpu_render_ms += other.gpu_render_ms;
+        self.gpu_copy_ms += other.gpu_copy_ms;
+        self.jpeg_encode_ms += other.jpeg_encode_ms;
+    }
+
+    /// These times divided by `count`
+    pub fn averaged(&self, count: u32) -> FrameStages {
+        let count = f64::from(count.max(1));
+        FrameStages {
+            update_ms: self.update_ms / count,
+            readback_ms: self.readback_ms / count,
+            gpu_render_ms: self.gpu_render_ms / count,
+            gpu_copy_ms: self.gpu_copy_ms / count,
+            jpeg_encode_ms: self.jpeg_encode_ms / count,
+        }
+    }
+}
+
+/// Payload of `slow-frames` events
+#[derive(Serialize, Clone, Debug)]
+pub struct SlowFrameAlert {
+    /// Threshold every frame of the run exceeded
+    pub threshold_ms: f64,
+    /// Consecutive slow frames so far
+    pub frames: u32,
+    /// Average frame time over those frames
+    pub frame_ms: f64,
+    /// Stage with the largest average time, a field name of `stages`
+    pub dominant_stage: &'static str,
+    /// Average time per stage over those frames
+    pub stages: FrameStages,
+}
+
+/// Frame response containing Base64-encoded pixel data
 #[derive(Serialize, Deserialize)]
 pub struct FrameResponse {
-    /// Base64-encoded RGBA pixel data (avoids slow JSON array serialization)
+    /// Base64-encoded pixel data (avoids slow JSON array serialization)
     pub data: String,
     pub width: u32,
     pub height: u32,
+    /// Layout of `data`, see [`PixelFormat`]
+    pub format: PixelFormat,
+    /// Id of the frame, see [`Frame::id`]
+    pub frame_id: u64,
+    /// Publication time of the frame, see [`Frame::timestamp_ms`]
+    pub timestamp_ms: f64,
+}
+
+// =============================================================================
+// Buffer Pool
+// =============================================================================
+
+/// Pool of reusable byte buffers shared by the render thread and Tauri
+///
+/// A raw 800x600 frame is ~1.8MB and passes through several buffers on its
+/// way to the frontend. Recycling them avoids allocating megabytes per frame.
+#[derive(Clone, Default)]
+pub struct SharedBufferPool(pub Arc<Mutex<Vec<Vec<u8>>>>);
+
+impl SharedBufferPool {
+    /// Take an empty buffer with room for at least `capacity` bytes
+    pub fn take(&self, capacity: usize) -> Vec<u8> {
+        let mut buffer = self
+            .0
+            .lock()
+            .ok()
+            .and_then(|mut pool| pool.pop())
+            .unwrap_or_default();
+        buffer.clear();
+        buffer.reserve(capacity);
+        buffer
+    }
+
+    /// Return a buffer to the pool so a later frame can reuse its allocation
+    pub fn recycle(&self, buffer: Vec<u8>) {
+        if buffer.capacity() == 0 {
+            return;
+        }
+        if let Ok(mut pool) = self.0.lock() {
+            if pool.len() < MAX_POOLED_BUFFERS {
+                pool.push(buffer);
+            }
+        }
+    }
+
+    /// Total capacity of the idle buffers in bytes
+    pub fn pooled_bytes(&self) -> usize {
+        self.0
+            .lock()
+            .map(|pool| pool.iter().map(Vec::capacity).sum())
+            .unwrap_or_default()
+    }
+
+    /// Free idle buffers, oldest first, until at most `max_bytes` remain
+    ///
+    /// Returns the number of bytes freed.
+    pub fn shrink_to(&self, max_bytes: usize) -> usize {
+        let Ok(mut pool) = self.0.lock() else {
+            return 0;
+        };
+        let mut pooled: usize = pool.iter().map(Vec::capacity).sum();
+        let mut freed = 0;
+        while pooled > max_bytes && !pool.is_empty() {
+            let oldest = pool.remove(0);
+            pooled -= oldest.capacity();
+            freed += oldest.capacity();
+        }
+        freed
+    }
+}
+
+// =============================================================================
+// GPU Info
+// =============================================================================
+
+/// Adapter the render device was created on
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GpuInfo {
+    pub name: String,
+    /// Graphics API, e.g. "Vulkan"
+    pub backend: String,
+    /// e.g. "DiscreteGpu" or "IntegratedGpu"
+    pub device_type: String,
+    pub driver: String,
+    pub driver_info: String,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    /// Limits of the render device, which may be lower than the adapter's
+    #[serde(default)]
+    pub limits: GpuLimits,
+    /// Optional wgpu features enabled on the render device
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// Support for the texture formats the renderer may use
+    #[serde(default)]
+    pub texture_formats: Vec<TextureFormatSupport>,
+}
+
+/// Render device limits relevant to render size and readback
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GpuLimits {
+    pub max_texture_dimension_2d: u32,
+    pub max_buffer_size: u64,
+    pub max_storage_buffer_binding_size: u32,
+    pub max_uniform_buffer_binding_size: u32,
+    pub max_bind_groups: u32,
+    pub max_compute_workgroup_size_x: u32,
+    pub max_compute_invocations_per_workgroup: u32,
+}
+
+/// What the adapter can do with one texture format
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TextureFormatSupport {
+    /// wgpu name, e.g. "Rgba8UnormSrgb"
+    pub format: String,
+    pub render_attachment: bool,
+    pub storage: bool,
+    pub filterable: bool,
+    /// MSAA sample counts usable when rendering to it
+    pub sample_counts: Vec<u32>,
+}
+
+/// GPU adapter info, filled in by Bevy once the render device exists
+#[derive(Clone, Default)]
+pub struct SharedGpuInfo(pub Arc<Mutex<Option<GpuInfo>>>);
+
+/// Class of a wgpu error
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GpuErrorKind {
+    Validation,
+    OutOfMemory,
+    Internal,
+    /// A custom shader that failed to compile
+    Shader,
+}
+
+/// wgpu error caught by an error scope or the device's uncaptured error
+/// handler
+#[derive(Serialize, Clone, Debug)]
+pub struct GpuError {
+    /// Wall-clock milliseconds since the Unix epoch
+    pub timestamp_ms: f64,
+    pub kind: GpuErrorKind,
+    /// What was being done, e.g. `pixel_pack`, or `uncaptured` for errors
+    /// outside our error scopes
+    pub source: String,
+    pub message: String,
+}
+
+#[derive(Default)]
+pub struct GpuErrors {
+    /// Errors reported since startup
+    pub count: u64,
+    /// The last `MAX_REPORTED_ERRORS` errors, oldest first
+    pub recent: VecDeque<GpuError>,
+}
+
+/// wgpu errors reported instead of panicking the render thread
+#[derive(Clone, Default)]
+pub struct SharedGpuErrors(pub Arc<Mutex<GpuErrors>>);
+
+impl SharedGpuErrors {
+    /// Log and keep an error
+    pub fn report(&self, kind: GpuErrorKind, source: &str, message: String) {
+        eprintln!("[GPU] {:?} error in {}: {}", kind, source, message);
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64() * 1000.0)
+            .unwrap_or_default();
+        let Ok(mut errors) = self.0.lock() else {
+            return;
+        };
+        errors.count += 1;
+        if errors.recent.len() >= MAX_REPORTED_ERRORS {
+            errors.recent.pop_front();
+        }
+        errors.recent.push_back(GpuError {
+            timestamp_ms,
+            kind,
+            source: source.to_string(),
+            message,
+        });
+    }
+
+    /// Errors reported since startup, and the most recent one
+    pub fn summary(&self) -> (u64, Option<GpuError>) {
+        self.0
+            .lock()
+            .map(|errors| (errors.count, errors.recent.back().cloned()))
+            .unwrap_or_default()
+    }
+
+    /// The last `MAX_REPORTED_ERRORS` errors, oldest first
+    pub fn recent(&self) -> Vec<GpuError> {
+        self.0
+            .lock()
+            .map(|errors| errors.recent.iter().cloned().collect())
+            .unwrap_or_default()
+    }
 }
 
 // =============================================================================
@@ -29,7 +678,7 @@ pub struct FrameResponse {
 // =============================================================================
 
 /// Mouse input state received from frontend
-#[derive(Serialize, Deserialize, Clone, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct MouseInput {
     /// Accumulated X movement delta
     pub delta_x: f32,
@@ -41,12 +690,302 @@ pub struct MouseInput {
     pub left_button: bool,
     /// Right mouse button is pressed
     pub right_button: bool,
+    /// Middle mouse button is pressed, missing in older recordings
+    #[serde(default)]
+    pub middle_button: bool,
+}
+
+/// Multipliers of the mouse camera speeds in `config::camera`, `1` for the
+/// defaults
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct CameraSensitivity {
+    pub rotate: f32,
+    pub zoom: f32,
+    pub pan: f32,
+    /// Dragging up tilts the camera up instead of down
+    pub invert_y: bool,
+}
+
+impl Default for CameraSensitivity {
+    fn default() -> Self {
+        Self {
+            rotate: 1.0,
+            zoom: 1.0,
+            pan: 1.0,
+            invert_y: false,
+        }
+    }
 }
 
 /// Thread-safe mouse input shared between Tauri and Bevy
 #[derive(Clone, Default)]
 pub struct SharedMouseInput(pub Arc<Mutex<MouseInput>>);
 
+impl SharedMouseInput {
+    /// Add the deltas of `input` to those not yet consumed by Bevy and take
+    /// over its button state
+    pub fn add(&self, input: &MouseInput) -> Result<(), String> {
+        let mut mouse = self.0.lock().map_err(|e| e.to_string())?;
+        mouse.delta_x += input.delta_x;
+        mouse.delta_y += input.delta_y;
+        mouse.scroll_delta += input.scroll_delta;
+        mouse.left_button = input.left_button;
+        mouse.right_button = input.right_button;
+        mouse.middle_button = input.middle_button;
+        Ok(())
+    }
+}
+
+// =============================================================================
+// Input Recording
+// =============================================================================
+
+/// Input applied to the camera in one frame
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InputSample {
+    /// Scene time of the frame the input was applied in, in seconds
+    pub time_s: f64,
+    pub input: MouseInput,
+}
+
+/// Orbit camera angles and distance
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct OrbitPose {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+    /// Missing in presets and recordings saved before roll existed
+    #[serde(default)]
+    pub roll: f32,
+}
+
+/// Easing curve of animated camera moves
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CameraEasing {
+    Linear,
+    /// Eases in and out gently
+    #[default]
+    SmoothStep,
+    /// Eases in and out, quicker in the middle
+    EaseInOutCubic,
+    /// Starts fast and settles, feels most responsive
+    EaseOutCubic,
+}
+
+impl CameraEasing {
+    /// Progress of the move at `t` of its duration, both in `0..=1`
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            CameraEasing::Linear => t,
+            CameraEasing::SmoothStep => t * t * (3.0 - 2.0 * t),
+            CameraEasing::EaseInOutCubic => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (2.0 - 2.0 * t).powi(3) / 2.0
+                }
+            }
+            CameraEasing::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+        }
+    }
+}
+
+/// Duration and easing of animated camera moves
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct CameraTransitionSettings {
+    /// Seconds, `0` to jump
+    pub duration_s: f32,
+    pub easing: CameraEasing,
+}
+
+/// Default of `animate` in requests: camera moves are smooth unless turned
+/// off
+pub(crate) fn animate_default() -> bool {
+    true
+}
+
+/// Fixed camera angles, keeping the current center and distance
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StandardView {
+    /// Looking along -Z
+    Front,
+    Back,
+    /// Looking along +X
+    Left,
+    Right,
+    /// Looking straight down, along -Y
+    Top,
+    Bottom,
+    /// Isometric, from the front right and above
+    Isometric,
+}
+
+/// Projection of the stream camera
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectionKind {
+    Perspective,
+    /// Parallel lines stay parallel, sized to match the perspective view at
+    /// the orbit center
+    Orthographic,
+}
+
+/// Stream camera placement and projection, for aligning HTML overlays with
+/// the rendered scene
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct CameraState {
+    /// Orbit the camera is on, including its center
+    #[serde(flatten)]
+    pub view: CameraView,
+    /// Camera position in world space
+    pub translation: [f32; 3],
+    /// Camera orientation as an `[x, y, z, w]` quaternion
+    pub rotation: [f32; 4],
+    /// Vertical field of view in degrees, `None` for orthographic cameras
+    pub fov_y_deg: Option<f32>,
+    /// Clipping planes
+    pub near: f32,
+    pub far: f32,
+    /// Render target size in pixels
+    pub viewport: [u32; 2],
+    /// Column-major matrix from world space to clip space
+    ///
+    /// Divide by `w` for normalized device coordinates, `-1..1` with `y`
+    /// up, depth reversed.
+    pub view_projection: [f32; 16],
+}
+
+/// Depth of field on the stream camera
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct DepthOfFieldSettings {
+    /// Distance along the view direction that is in focus, in world units
+    pub focal_distance: f32,
+    /// Aperture in f-stops, lower values blur more
+    pub aperture: f32,
+}
+
+/// Distance fog on the stream camera
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct FogSettings {
+    /// sRGB `[r, g, b]` in `0..=1`
+    pub color: [f32; 3],
+    /// Distance where linear fog begins, in world units
+    #[serde(default)]
+    pub start: f32,
+    /// Distance where linear fog is complete, or where exponential fog
+    /// hides 95% of the scene
+    pub end: f32,
+    #[serde(default)]
+    pub falloff: FogFalloffMode,
+}
+
+/// How fog thickens with distance
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FogFalloffMode {
+    /// Evenly from `start` to `end`
+    #[default]
+    Linear,
+    /// Quickly up close, then more and more slowly
+    Exponential,
+    /// Slowly up close, then quickly, like haze
+    ExponentialSquared,
+}
+
+/// What the stream shows instead of the lit scene, for diagnosing models
+/// that look wrong
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DebugView {
+    /// The normally lit scene
+    #[default]
+    Off,
+    /// Distance from the camera, white near and black far
+    Depth,
+    /// World-space normals as RGB
+    Normals,
+    /// Texture coordinates as red and green, magenta without any
+    Uvs,
+    /// Surfaces drawn on top of each other, brighter where more overlap
+    Overdraw,
+    /// Shadow cascade of the directional light covering each surface
+    ShadowCascades,
+}
+
+/// How many meshes a camera drew or skipped last frame, for diagnosing
+/// objects that disappear
+#[derive(Serialize, Clone, Debug)]
+pub struct CameraCullingStats {
+    pub camera: u64,
+    pub name: Option<String>,
+    /// Inactive cameras keep the counts of the last frame they rendered
+    pub active: bool,
+    /// Meshes on the camera's render layers
+    pub meshes: usize,
+    pub visible: usize,
+    /// Hidden by `Visibility`, their own or an ancestor's
+    pub hidden: usize,
+    /// Outside the camera's frustum, or the frozen one
+    pub culled: usize,
+    /// Culling with a frozen frustum, see `freeze_culling`
+    pub frozen: bool,
+    /// Ids of the first culled meshes, up to `MAX_LISTED_CULLED`
+    pub culled_entities: Vec<u64>,
+}
+
+/// Camera placement on an orbit, for rendering a specific view
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct CameraView {
+    #[serde(flatten)]
+    pub pose: OrbitPose,
+    /// Point the camera orbits around, the current center if `None`
+    pub center: Option<[f32; 3]>,
+}
+
+/// Named camera views saved with `save_camera_preset`
+pub type CameraPresetMap = BTreeMap<String, CameraView>;
+
+/// Point of a camera flythrough path
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct CameraKeyframe {
+    /// Camera position in world space
+    pub position: [f32; 3],
+    /// Point the camera looks at
+    pub target: [f32; 3],
+    /// Seconds from the start of the path
+    pub time_s: f64,
+}
+
+/// Payload of `camera-path-finished` events
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct CameraPathEnd {
+    /// Whether the path was played to the end, rather than stopped or
+    /// replaced by another one
+    pub completed: bool,
+    /// Path time the camera stopped at
+    pub time_s: f64,
+}
+
+/// A live session's input, replayable into the same camera path
+///
+/// Times are scene times, which only advance while frames are streamed, so
+/// a replay starting at `start_time_s` sees the scene as it was.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InputRecording {
+    pub start_time_s: f64,
+    pub end_time_s: f64,
+    /// Camera orbit when the recording started
+    pub start_orbit: OrbitPose,
+    /// Frames in which there was any input, in order
+    pub samples: Vec<InputSample>,
+    /// Sensitivity the input was applied with when recording started
+    #[serde(default)]
+    pub sensitivity: CameraSensitivity,
+}
+
 // =============================================================================
 // Performance Statistics
 // =============================================================================
@@ -55,17 +994,1578 @@ pub struct SharedMouseInput(pub Arc<Mutex<MouseInput>>);
 #[derive(Serialize, Deserialize, Clone, Default)]
 pub struct PerformanceStats {
     // Backend (Bevy/Rust) timings
+    /// Time spent draining the render world channel (CPU side)
     pub gpu_transfer_ms: f64,
     pub data_processing_ms: f64,
     pub frame_encoding_ms: f64,
+    /// Published frames per second, from the average frame time
     pub bevy_fps: f64,
     pub frame_count: u32,
+    /// Time between consecutive published frames over the last
+    /// `FRAME_INTERVAL_SAMPLES` frames
+    pub frame_time: FrameTimeSummary,
     pub data_size_kb: f64,
+    /// Current adaptive frame rate target
+    pub target_fps: f64,
+    // GPU timings from timestamp queries (zero if unsupported)
+    /// GPU time spent in the camera pass
+    pub gpu_render_ms: f64,
+    /// GPU time spent copying the render target to the staging buffer
+    pub gpu_copy_ms: f64,
+    // Frame drop and backpressure counters
+    /// Frames superseded by a newer frame before they were processed
+    pub frames_dropped_stale: u64,
+    /// Frames not copied because every staging buffer was still in flight
+    pub frames_skipped_readback: u64,
+    /// Published frames replaced before any client fetched them
+    pub frames_unfetched: u64,
+    /// Frames identical to the previous one, not published
+    pub frames_skipped_identical: u64,
+    /// Total frame fetches served to clients
+    pub frames_fetched: u64,
+    /// Frames waiting in the render world channel at the last receive
+    pub channel_depth: u32,
+    /// Highest channel depth seen since startup
+    pub max_channel_depth: u32,
+    // GPU memory usage
+    /// Memory allocated by the wgpu allocator (zero if the backend doesn't report it)
+    pub gpu_allocated_mb: f64,
+    /// Memory reserved by the wgpu allocator (zero if the backend doesn't report it)
+    pub gpu_reserved_mb: f64,
+    /// Size of the staging buffers owned by the image copy plugin
+    pub readback_buffers_mb: f64,
+    /// Size of the offscreen render targets
+    pub render_targets_mb: f64,
+    /// Frame data held by the pipeline, see `PIPELINE_MEMORY_LIMIT_MB`
+    pub pipeline_memory_mb: f64,
+    /// Times the pipeline memory limit was exceeded
+    pub memory_limit_exceeded: u64,
     // Tauri command timings
     pub tauri_get_frame_ms: f64,
     pub tauri_serialize_ms: f64,
+    // Encoder thread timings
+    pub jpeg_encode_ms: f64,
+    // Display latency reported by the frontend
+    /// Publication-to-display latency of the last reported frame
+    pub display_latency_ms: f64,
+    /// Distribution over the last `DISPLAY_LATENCY_SAMPLES` reports
+    pub display_latency: LatencySummary,
 }
 
 /// Thread-safe performance statistics
 #[derive(Clone, Default)]
 pub struct SharedPerfStats(pub Arc<Mutex<PerformanceStats>>);
+
+/// Performance statistics at one point in time
+#[derive(Serialize, Clone)]
+pub struct StatsSample {
+    /// Wall-clock milliseconds since the Unix epoch
+    pub timestamp_ms: f64,
+    #[serde(flatten)]
+    pub stats: PerformanceStats,
+}
+
+/// Ring buffer of performance statistics sampled every
+/// `STATS_HISTORY_INTERVAL`, going back `STATS_HISTORY_SECONDS`
+#[derive(Clone)]
+pub struct SharedStatsHistory(pub Arc<Mutex<VecDeque<StatsSample>>>);
+
+impl Default for SharedStatsHistory {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(VecDeque::with_capacity(
+            Self::capacity(),
+        ))))
+    }
+}
+
+impl SharedStatsHistory {
+    fn capacity() -> usize {
+        (STATS_HISTORY_SECONDS / STATS_HISTORY_INTERVAL).ceil() as usize
+    }
+
+    /// Add a sample stamped with the current wall-clock time, evicting the
+    /// oldest one when the history is full
+    pub fn record(&self, stats: PerformanceStats) {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64() * 1000.0)
+            .unwrap_or_default();
+        if let Ok(mut samples) = self.0.lock() {
+            if samples.len() >= Self::capacity() {
+                samples.pop_front();
+            }
+            samples.push_back(StatsSample {
+                timestamp_ms,
+                stats,
+            });
+        }
+    }
+
+    /// Samples from the last `seconds`, or the whole history with `None`,
+    /// oldest first
+    pub fn since(&self, seconds: Option<f64>) -> Vec<StatsSample> {
+        let Ok(samples) = self.0.lock() else {
+            return Vec::new();
+        };
+        let Some(seconds) = seconds else {
+            return samples.iter().cloned().collect();
+        };
+        let cutoff_ms = samples.back().map_or(0.0, |last| last.timestamp_ms) - seconds * 1000.0;
+        samples
+            .iter()
+            .filter(|sample| sample.timestamp_ms >= cutoff_ms)
+            .cloned()
+            .collect()
+    }
+
+    /// Forget all recorded samples
+    pub fn clear(&self) {
+        if let Ok(mut samples) = self.0.lock() {
+            samples.clear();
+        }
+    }
+}
+
+// =============================================================================
+// World Inspector
+// =============================================================================
+
+/// An entity in `list_entities`
+#[derive(Serialize, Clone, Debug)]
+pub struct EntitySummary {
+    /// `Entity::to_bits`, valid for the current session only
+    pub id: u64,
+    pub name: Option<String>,
+    pub components: usize,
+}
+
+/// A named set of entities, as returned by `list_selection_sets`
+#[derive(Serialize, Clone, Debug)]
+pub struct SelectionSetInfo {
+    pub name: String,
+    /// In the order they were added
+    pub entities: Vec<EntitySummary>,
+}
+
+/// Bulk operation on every entity with a tag or in a selection set, for
+/// `update_tagged` and `update_selection_set`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TaggedOperation {
+    Hide,
+    Show,
+    /// Override the base color of the meshes below them, see
+    /// `set_submesh_material`
+    Color {
+        color: Vec<f32>,
+    },
+    /// Put back the materials `Color` changed
+    RestoreColor,
+    /// Remove them and everything below them from the scene
+    Delete,
+}
+
+/// An entity's components, as returned by `inspect_entity`
+#[derive(Serialize, Clone, Debug)]
+pub struct EntityInspection {
+    pub id: u64,
+    pub name: Option<String>,
+    pub components: Vec<InspectedComponent>,
+}
+
+/// One component of an inspected entity
+#[derive(Serialize, Clone, Debug)]
+pub struct InspectedComponent {
+    /// Full type path if registered for reflection, else the type name
+    pub name: String,
+    /// Whether the component is registered for reflection, otherwise
+    /// `value` is always `None`
+    pub reflected: bool,
+    /// Serialized through reflection
+    pub value: Option<serde_json::Value>,
+    /// Why a reflected component could not be serialized
+    pub error: Option<String>,
+}
+
+/// A resource in `list_resources`
+#[derive(Serialize, Clone, Debug)]
+pub struct ResourceSummary {
+    pub name: String,
+    /// Whether the resource is registered for reflection
+    pub reflected: bool,
+}
+
+// =============================================================================
+// Readback Self-Test
+// =============================================================================
+
+/// Result of `run_readback_self_test`
+#[derive(Serialize, Clone, Debug)]
+pub struct ReadbackSelfTest {
+    /// Whether every check passed
+    pub passed: bool,
+    pub checks: Vec<ReadbackCheckResult>,
+}
+
+/// One known pattern read back through one path of the readback pipeline
+#[derive(Serialize, Clone, Debug)]
+pub struct ReadbackCheckResult {
+    pub name: &'static str,
+    /// Texture format the pattern was stored or rendered in
+    pub source_format: String,
+    pub format: PixelFormat,
+    /// Whether the pattern went through the pack pass
+    pub packed: bool,
+    /// Whether the readback carried row padding that had to be removed
+    pub padded: bool,
+    pub width: u32,
+    pub height: u32,
+    pub passed: bool,
+    /// Pixels off by more than `tolerance` in any channel
+    pub mismatched_pixels: u32,
+    /// Largest difference of any channel, 0 to 255
+    pub max_error: u8,
+    /// Difference allowed per channel, for quantization and rounding
+    pub tolerance: u8,
+    pub first_mismatch: Option<PixelMismatch>,
+    /// Why the check could not compare pixels, e.g. a short readback
+    pub error: Option<String>,
+}
+
+/// A pixel that did not come back as expected, both as RGBA
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct PixelMismatch {
+    pub x: u32,
+    pub y: u32,
+    pub expected: [u8; 4],
+    pub actual: [u8; 4],
+}
+
+// =============================================================================
+// Projects
+// =============================================================================
+
+/// A scene with its camera and render settings, saved by `save_project`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Project {
+    /// Format version, see `config::project::VERSION`
+    pub version: u32,
+    /// Placement of the scene's named objects
+    pub scene: Vec<SceneObject>,
+    /// Model opened with `load_model`, loaded again with the project
+    #[serde(default)]
+    pub model: Option<PathBuf>,
+    /// Files loaded through the asset server, relative to the assets folder
+    #[serde(default)]
+    pub assets: Vec<String>,
+    /// Stream camera view
+    pub camera: CameraView,
+    #[serde(default)]
+    pub camera_presets: CameraPresetMap,
+    pub render: ProjectRenderSettings,
+}
+
+/// A named scene entity and its transform
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SceneObject {
+    pub name: String,
+    /// Which of the objects with this name it is, in hierarchy order, since
+    /// names repeat, e.g. in models
+    #[serde(default)]
+    pub index: usize,
+    /// Set for objects of the project's model, counted within it
+    #[serde(default)]
+    pub in_model: bool,
+    pub translation: [f32; 3],
+    /// Orientation as an `[x, y, z, w]` quaternion
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+}
+
+/// Stream and camera settings saved with a project
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct ProjectRenderSettings {
+    pub stream_width: u32,
+    pub stream_height: u32,
+    pub pixel_format: PixelFormat,
+    pub projection: ProjectionKind,
+    /// Stream camera exposure in EV100
+    pub exposure_ev100: f32,
+    pub depth_of_field: Option<DepthOfFieldSettings>,
+    /// Missing from projects saved before fog
+    #[serde(default)]
+    pub fog: Option<FogSettings>,
+    pub debug_view: DebugView,
+    pub debug_overlay: bool,
+    pub quad_view: bool,
+    /// Deterministic frame rate, see `set_fixed_timestep`
+    pub fixed_fps: Option<f64>,
+    /// Missing from projects saved before lighting presets
+    #[serde(default)]
+    pub lighting: LightingPreset,
+}
+
+// =============================================================================
+// Models
+// =============================================================================
+
+/// Payload of `model-load-progress` events
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum ModelLoadProgress {
+    /// The file and the buffers and textures it references are being read,
+    /// sent when loading starts and then periodically
+    Loading { path: String, elapsed_ms: f64 },
+    /// The model is in the scene and its meshes are being shown a chunk
+    /// per frame, sent after each chunk
+    Showing {
+        path: String,
+        shown: usize,
+        meshes: usize,
+        elapsed_ms: f64,
+    },
+    /// The model replaced the previous one in the scene
+    Loaded { path: String, elapsed_ms: f64 },
+    /// The model could not be loaded, or another one was opened first
+    Failed { path: String, error: String },
+}
+
+impl ModelLoadProgress {
+    /// Whether no further progress follows
+    pub fn is_final(&self) -> bool {
+        !matches!(
+            self,
+            ModelLoadProgress::Loading { .. } | ModelLoadProgress::Showing { .. }
+        )
+    }
+}
+
+/// Payload of `asset-progress` events
+#[derive(Serialize, Clone, Debug)]
+pub struct AssetProgress {
+    /// Share of the pending assets loaded, from 0 to 100; an asset whose
+    /// file was read counts half until what it references loaded too
+    pub percent: f32,
+    /// Assets loaded or failed
+    pub finished: usize,
+    /// Assets added since progress last reached 100
+    pub total: usize,
+    /// Path of the first asset still loading, `None` once all finished
+    pub current: Option<String>,
+    /// Assets that failed since the last event
+    pub errors: Vec<AssetLoadError>,
+}
+
+/// An asset that failed to load, in `asset-progress` events
+#[derive(Serialize, Clone, Debug)]
+pub struct AssetLoadError {
+    pub path: String,
+    pub error: String,
+}
+
+/// What a prefetched file is loaded as, so later uses find it loaded
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PrefetchKind {
+    /// A glTF, OBJ or STL file for `load_model`
+    Model,
+    /// An `.hdr` file for `set_environment_map` or reflection probes
+    Environment,
+    /// An image or `.cube` LUT
+    Texture,
+}
+
+/// A file queued with `prefetch_assets`, absolute or relative to the
+/// assets folder
+#[derive(Deserialize, Clone, Debug)]
+pub struct PrefetchAsset {
+    pub path: PathBuf,
+    pub kind: PrefetchKind,
+}
+
+/// Reply of `get_prefetch_status`
+#[derive(Serialize, Clone, Debug)]
+pub struct PrefetchStatus {
+    /// Files waiting for their turn, next first
+    pub queued: Vec<String>,
+    pub loading: Option<String>,
+    /// Files loaded and kept in memory
+    pub ready: Vec<String>,
+    pub failed: Vec<AssetLoadError>,
+}
+
+/// Result of `export_gltf`
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct GltfExportSummary {
+    pub path: String,
+    /// Meshes and lights written, each a root node
+    pub nodes: usize,
+    pub meshes: usize,
+    pub materials: usize,
+    pub lights: usize,
+    /// Entities left out and why, e.g. meshes that aren't triangle lists
+    pub skipped: Vec<String>,
+}
+
+/// A texture baked for a USD material, as 8-bit sRGB RGBA
+pub struct BakedTexture {
+    /// Path inside the package, relative to the stage
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// A scene converted to USD, ready to be packaged as .usdz
+pub struct UsdScene {
+    /// The stage in USD's text format
+    pub usda: String,
+    pub textures: Vec<BakedTexture>,
+    pub summary: UsdzExportSummary,
+}
+
+/// Result of `export_usdz`
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct UsdzExportSummary {
+    pub path: String,
+    pub meshes: usize,
+    pub materials: usize,
+    /// Base color textures baked with their material's color
+    pub textures: usize,
+    /// Entities left out and why, e.g. meshes that aren't triangle lists
+    pub skipped: Vec<String>,
+}
+
+/// Shape of the meshes written by `export_collision_meshes`
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionShape {
+    /// Convex hulls, which physics engines collide fastest
+    #[default]
+    ConvexHull,
+    /// The meshes with vertices in the same grid cell merged, for concave
+    /// static geometry
+    Decimated,
+}
+
+/// What `export_collision_meshes` builds
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(default)]
+pub struct CollisionMeshSettings {
+    pub shape: CollisionShape,
+    /// One collision mesh for the whole model instead of one per mesh
+    pub merge: bool,
+    /// Grid cells along the longest side of decimated meshes,
+    /// `DEFAULT_RESOLUTION` if `None`
+    pub resolution: Option<u32>,
+}
+
+/// Result of `export_collision_meshes`
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct CollisionExportSummary {
+    pub path: String,
+    pub meshes: usize,
+    pub vertices: usize,
+    pub triangles: usize,
+    /// Meshes left out and why, e.g. flat ones that have no hull
+    pub skipped: Vec<String>,
+}
+
+// =============================================================================
+// Scene Editing
+// =============================================================================
+
+/// Shape of a primitive added with `spawn_primitive`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PrimitiveShape {
+    /// Unit cube
+    #[default]
+    Cube,
+    /// Sphere of diameter 1
+    Sphere,
+    /// Cylinder of diameter and height 1
+    Cylinder,
+    /// Unit square facing up
+    Plane,
+}
+
+/// A primitive to add to the scene
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct SpawnPrimitive {
+    pub shape: PrimitiveShape,
+    pub name: Option<String>,
+    pub transform: TransformUpdate,
+    /// Base color as sRGB `[r, g, b]` or `[r, g, b, a]` in `0..=1`
+    pub color: Option<Vec<f32>>,
+}
+
+/// Extruded 3D text to add with `spawn_text3d`
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct SpawnText3d {
+    /// Text to show, lines separated by `\n`
+    pub content: String,
+    /// TrueType or OpenType font file, Bevy's built-in font if `None`
+    pub font: Option<PathBuf>,
+    /// Em size in world units, `config::text3d::SIZE` if `None`
+    pub size: Option<f32>,
+    /// Extrusion depth in world units, `config::text3d::DEPTH` times the
+    /// size if `None`
+    pub depth: Option<f32>,
+    pub name: Option<String>,
+    /// Placement of the text's center
+    pub transform: TransformUpdate,
+    /// Base color as sRGB `[r, g, b]` or `[r, g, b, a]` in `0..=1`
+    pub color: Option<Vec<f32>>,
+}
+
+/// Camera-facing marker to add with `add_billboard`
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct AddBillboard {
+    /// World position of the billboard's center
+    pub position: [f32; 3],
+    /// Image drawn on the billboard, a plain disc if `None`
+    pub image: Option<PathBuf>,
+    /// Width and height, in logical pixels if `screen_size` is set and
+    /// world units otherwise. `config::billboard::SCREEN_SIZE` or
+    /// `config::billboard::SIZE` if `None`
+    pub size: Option<f32>,
+    /// Keep the same size on screen however far away the camera is
+    pub screen_size: bool,
+    /// Tint as sRGB `[r, g, b]` or `[r, g, b, a]` in `0..=1`, multiplied
+    /// with the image
+    pub color: Option<Vec<f32>>,
+    pub name: Option<String>,
+}
+
+/// A registered custom shader to draw meshes with
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CustomShaderMaterialSettings {
+    /// Name the shader was registered under
+    pub shader: String,
+    /// `color` uniform as sRGB `[r, g, b]` or `[r, g, b, a]` in `0..=1`,
+    /// white if `None`
+    #[serde(default)]
+    pub color: Option<Vec<f32>>,
+    /// `params` uniform, free for the shader to use
+    #[serde(default)]
+    pub params: [f32; 4],
+}
+
+/// A mesh below the entity passed to `list_submeshes`, one primitive of a
+/// glTF mesh
+#[derive(Serialize, Clone, Debug)]
+pub struct SubmeshInfo {
+    pub id: u64,
+    pub name: Option<String>,
+    /// Name of the entity above it, the mesh node of a glTF model
+    pub parent: Option<String>,
+    pub vertices: usize,
+    /// Whether `set_submesh_material` replaced its material
+    pub overridden: bool,
+}
+
+/// Changes to a submesh's own material for `set_submesh_material`, `None`
+/// fields keep the original's value
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct MaterialOverride {
+    /// Base color as sRGB `[r, g, b]` or `[r, g, b, a]` in `0..=1`,
+    /// replacing the base color texture too
+    pub color: Option<Vec<f32>>,
+    /// Emitted light as sRGB `[r, g, b]`, e.g. to make a highlight glow
+    pub emissive: Option<Vec<f32>>,
+    pub metallic: Option<f32>,
+    pub roughness: Option<f32>,
+}
+
+/// Morph target of a mesh, by index or by the name the model gives it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum MorphTarget {
+    Index(usize),
+    Name(String),
+}
+
+/// A morph target of an entity below the one passed to
+/// `list_morph_targets`
+#[derive(Serialize, Clone, Debug)]
+pub struct MorphTargetInfo {
+    /// Entity with the weights, the mesh node of a glTF model
+    pub entity: u64,
+    pub index: usize,
+    /// Name from the model, if it has one
+    pub name: Option<String>,
+    /// Current weight, moving towards the one last set
+    pub weight: f32,
+}
+
+/// Changes to the particle emitter, `None` fields are left as they are
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct ParticleEmitterUpdate {
+    /// Particles emitted per second
+    pub rate: Option<f32>,
+    /// sRGB `[r, g, b]` or `[r, g, b, a]` in `0..=1`
+    pub color: Option<Vec<f32>>,
+    /// Acceleration of every particle in world units per second²
+    pub gravity: Option<[f32; 3]>,
+    /// Where particles are emitted from
+    pub position: Option<[f32; 3]>,
+}
+
+/// Built-in scene shown when no model is open, set with `set_demo_scene`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DemoScene {
+    /// The two rotating cubes the app starts with
+    #[default]
+    Cubes,
+    /// Procedurally generated hills, a much heavier scene to stream
+    Terrain,
+}
+
+/// Light rig set with `set_lighting_preset`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LightingPreset {
+    /// Warm key, blue fill and a sun, the rig the demo scene starts with
+    #[default]
+    Default,
+    /// Three-point rig: key, fill and rim lights around the origin
+    Studio,
+    /// Strong low sun with a sky-blue ambient
+    Outdoor,
+    /// Dim moonlight and a single warm lamp
+    Night,
+    /// Bright ambient light only, no shadows
+    Flat,
+}
+
+/// Environment lighting the scene, set with `set_environment_map`
+#[derive(Deserialize, Clone, Debug)]
+pub struct EnvironmentMapSettings {
+    /// Equirectangular `.hdr` image
+    pub path: PathBuf,
+    /// Brightness in cd/m², `config::environment::DEFAULT_INTENSITY` if
+    /// `None`
+    #[serde(default)]
+    pub intensity: Option<f32>,
+    /// Also draw the environment behind the scene
+    #[serde(default)]
+    pub skybox: bool,
+    /// Turn around the vertical axis, in degrees
+    #[serde(default)]
+    pub rotation_deg: f32,
+}
+
+/// A box lit by its own environment, see `set_reflection_probes`
+#[derive(Deserialize, Clone, Debug)]
+pub struct ReflectionProbeSettings {
+    /// Entity name, `reflection_probe_<index>` if `None`
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Equirectangular `.hdr` image
+    pub path: PathBuf,
+    pub center: [f32; 3],
+    /// Size of the box along each axis
+    pub size: [f32; 3],
+    /// Brightness in cd/m², `config::environment::DEFAULT_INTENSITY` if
+    /// `None`
+    #[serde(default)]
+    pub intensity: Option<f32>,
+}
+
+/// Shadow catcher ground set with `set_ground_plane`
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(default)]
+pub struct GroundPlaneSettings {
+    /// Height of the ground, the bottom of the scene if `None`
+    pub height: Option<f32>,
+    /// Width and depth, `config::ground_plane::SIZE` if `None`
+    pub size: Option<f32>,
+    /// Opacity of a full shadow in `0..=1`,
+    /// `config::ground_plane::SHADOW_OPACITY` if `None`
+    pub shadow_opacity: Option<f32>,
+}
+
+/// Reflective water plane set with `set_water`
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct WaterSettings {
+    /// Height of the water, the bottom of the scene if `None`
+    pub height: Option<f32>,
+    /// Width and depth, `config::water::SIZE` if `None`
+    pub size: Option<f32>,
+    /// Tint as sRGB `[r, g, b]` in `0..=1`, `config::water::COLOR` if
+    /// `None`
+    pub color: Option<Vec<f32>>,
+    /// Wave steepness in `0..=1`, 0 for a flat mirror,
+    /// `config::water::WAVE_STRENGTH` if `None`
+    pub wave_strength: Option<f32>,
+}
+
+/// Bevy UI drawn into the stream camera's target, set with
+/// `set_ui_overlay`
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct UiOverlaySettings {
+    pub elements: Vec<UiElement>,
+    /// Leave screenshots, turntables and batch renders without the UI
+    pub clean_captures: bool,
+}
+
+/// One panel of the UI overlay, anchored to a corner of the view
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum UiElement {
+    /// A line or block of text
+    Label {
+        text: String,
+        #[serde(default)]
+        corner: Corner,
+        /// `config::ui_overlay::FONT_SIZE` if `None`
+        #[serde(default)]
+        font_size: Option<f32>,
+    },
+    /// A row of buttons, drawn for show; the UI doesn't take input
+    Toolbar {
+        items: Vec<String>,
+        #[serde(default)]
+        corner: Corner,
+    },
+    /// Color swatches with their meaning
+    Legend {
+        #[serde(default)]
+        title: Option<String>,
+        entries: Vec<LegendEntry>,
+        #[serde(default)]
+        corner: Corner,
+    },
+    /// A bar as long as `length` world units at the orbit center's
+    /// distance, following the camera
+    ScaleBar {
+        length: f32,
+        /// Caption below the bar, `length` itself if `None`
+        #[serde(default)]
+        label: Option<String>,
+        #[serde(default)]
+        corner: Corner,
+    },
+}
+
+/// Row of a `UiElement::Legend`
+#[derive(Deserialize, Clone, Debug)]
+pub struct LegendEntry {
+    pub label: String,
+    /// sRGB `[r, g, b]` or `[r, g, b, a]` in `0..=1`
+    pub color: Vec<f32>,
+}
+
+/// New values for the parts of a transform that are given
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct TransformUpdate {
+    pub translation: Option<[f32; 3]>,
+    /// Quaternion as `[x, y, z, w]`
+    pub rotation: Option<[f32; 4]>,
+    pub scale: Option<[f32; 3]>,
+}
+
+/// Steps `set_entity_transform` rounds to, for `set_snapping`; `None`
+/// leaves that part as given
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+#[serde(default)]
+pub struct SnapSettings {
+    /// Grid spacing of translations, in the parent's units
+    pub translate_step: Option<f32>,
+    /// Step of rotations about each axis, in degrees
+    pub rotate_step: Option<f32>,
+}
+
+/// A mesh hit by `raycast`
+#[derive(Serialize, Clone, Debug)]
+pub struct RaycastHit {
+    pub entity: u64,
+    pub name: Option<String>,
+    /// Where the ray hit, in world space
+    pub point: [f32; 3],
+    /// Surface normal at `point`, in world space
+    pub normal: [f32; 3],
+    /// From the ray's origin, in world units
+    pub distance: f32,
+}
+
+/// How `pick_entity` finds the entity under the cursor
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PickMethod {
+    /// Ray cast, falling back to the GPU where skinned or morphed meshes
+    /// may have moved away from their vertices at rest
+    #[default]
+    Auto,
+    /// Ray cast against the meshes as loaded, answered the same frame
+    Cpu,
+    /// Object-ID buffer, matches what is drawn but takes a few frames
+    Gpu,
+}
+
+/// Entity under the cursor, found by `pick_entity`
+#[derive(Serialize, Clone, Debug)]
+pub struct PickedEntity {
+    pub entity: u64,
+    pub name: Option<String>,
+    /// `cpu` or `gpu`, whichever found it
+    pub method: PickMethod,
+}
+
+// =============================================================================
+// Command Queue
+// =============================================================================
+
+/// Commands sent from Tauri to the Bevy thread
+///
+/// Commands are applied at the start of the next Bevy frame, with full
+/// access to the Bevy world.
+pub enum BridgeCommand {
+    /// Change the resolution frames are downscaled to before readback
+    SetStreamResolution { width: u32, height: u32 },
+    /// Change the pixel layout frames are read back in
+    SetPixelFormat(PixelFormat),
+    /// Read back only part of the stream target, or all of it with `None`
+    SetRegionOfInterest(Option<RegionOfInterest>),
+    /// Clear frame timings and drop counters
+    ResetStats,
+    /// Render the current view once at `scale` times the render resolution
+    CaptureScreenshot {
+        scale: u32,
+        reply: Sender<Result<CapturedImage, String>>,
+    },
+    /// Start recording the input applied to the camera
+    StartInputRecording,
+    /// Stop recording input, replying with the recording if one was running
+    StopInputRecording {
+        reply: Sender<Option<InputRecording>>,
+    },
+    /// Render a single `width` x `height` image from `view`, or from the
+    /// stream camera's view if `None`
+    RenderView {
+        view: Option<CameraView>,
+        width: u32,
+        height: u32,
+        reply: Sender<Result<CapturedImage, String>>,
+    },
+    /// Render `frames` views around the orbit center, one scene time step
+    /// of `1 / fps` apart, sending each finished image to `frames_out`
+    StartTurntable {
+        frames: u32,
+        fps: f64,
+        frames_out: Sender<Result<CapturedImage, String>>,
+    },
+    /// Advance time by exactly `1 / fps` per frame, or follow the wall
+    /// clock again with `None`, restarting the animation if `reset_time`
+    SetFixedTimestep { fps: Option<f64>, reset_time: bool },
+    /// Replace all camera presets, e.g. with the ones saved to disk
+    LoadCameraPresets(CameraPresetMap),
+    /// Reply with the scene, camera and render settings as a project
+    GetProject {
+        reply: Sender<Result<Project, String>>,
+    },
+    /// Restore a saved project, replying with the names of its objects
+    /// missing from the scene
+    OpenProject {
+        project: Box<Project>,
+        /// Progress of loading the project's model, if it isn't open
+        progress: Sender<ModelLoadProgress>,
+        reply: Sender<Vec<String>>,
+    },
+    /// Let models outside the assets folder be opened from this folder,
+    /// one the user picked a model in or watches
+    ApproveModelDir(PathBuf),
+    /// Load a glTF, OBJ or STL file in place of the current model,
+    /// reporting on `progress` until it is loaded or failed
+    LoadModel {
+        path: PathBuf,
+        progress: Sender<ModelLoadProgress>,
+    },
+    /// Reply with the visible scene as a .glb file
+    ExportGltf {
+        reply: Sender<Result<(Vec<u8>, GltfExportSummary), String>>,
+    },
+    /// Reply with the visible scene as USD, limited to the entities named
+    /// in `names` and their descendants if given
+    ExportUsdz {
+        names: Option<Vec<String>>,
+        reply: Sender<Result<UsdScene, String>>,
+    },
+    /// Reply with collision meshes for the loaded model as a .glb file
+    GenerateCollisionMeshes {
+        settings: CollisionMeshSettings,
+        reply: Sender<Result<(Vec<u8>, CollisionExportSummary), String>>,
+    },
+    /// Add a primitive to the scene, replying with its entity id
+    SpawnPrimitive {
+        primitive: SpawnPrimitive,
+        reply: Sender<Result<u64, String>>,
+    },
+    /// Add extruded text to the scene, replying with its entity id
+    SpawnText3d {
+        text: SpawnText3d,
+        reply: Sender<Result<u64, String>>,
+    },
+    /// Add a camera-facing billboard, replying with its entity id
+    AddBillboard {
+        billboard: AddBillboard,
+        reply: Sender<Result<u64, String>>,
+    },
+    /// Remove billboard `id`
+    RemoveBillboard {
+        id: u64,
+        reply: Sender<Result<(), String>>,
+    },
+    /// Remove all billboards
+    ClearBillboards,
+    /// Change the transform of entity `id`, relative to its parent
+    SetEntityTransform {
+        id: u64,
+        transform: TransformUpdate,
+        reply: Sender<Result<(), String>>,
+    },
+    /// Make entity `child` a child of entity `parent`, keeping its world
+    /// transform
+    SetParent {
+        child: u64,
+        parent: u64,
+        reply: Sender<Result<(), String>>,
+    },
+    /// Make entity `id` a root entity, keeping its world transform
+    Detach {
+        id: u64,
+        reply: Sender<Result<(), String>>,
+    },
+    /// Round transforms set with `SetEntityTransform` to these steps
+    SetSnapping(SnapSettings),
+    /// Reply with the meshes hit by a world-space ray, nearest first
+    Raycast {
+        origin: [f32; 3],
+        direction: [f32; 3],
+        max_distance: f32,
+        reply: Sender<Result<Vec<RaycastHit>, String>>,
+    },
+    /// Reply with the mesh entity under point `(x, y)` of the streamed
+    /// image, `None` over the background
+    PickEntity {
+        x: f32,
+        y: f32,
+        method: PickMethod,
+        reply: Sender<Result<Option<PickedEntity>, String>>,
+    },
+    /// Undo the last scene edit, replying with the command that made it
+    /// or `None` if there was nothing to undo
+    Undo { reply: Sender<Option<String>> },
+    /// Redo the last undone scene edit, replying like `Undo`
+    Redo { reply: Sender<Option<String>> },
+    /// Keep at most this many scene edits to undo
+    SetHistoryDepth(usize),
+    /// Apply an edit made in another instance, see `scene_sync`
+    ApplySceneOp {
+        instance: u64,
+        edit: Option<u64>,
+        op: SceneOp,
+    },
+    /// Compile-on-use WGSL `source` as custom shader `name`, replacing
+    /// the shader of that name
+    RegisterCustomShader { name: String, source: String },
+    /// Draw the meshes of entity `id` and its descendants with a custom
+    /// shader, or their own materials again if `None`, replying with how
+    /// many meshes changed
+    SetCustomShaderMaterial {
+        id: u64,
+        material: Option<CustomShaderMaterialSettings>,
+        reply: Sender<Result<usize, String>>,
+    },
+    /// Reply with the meshes of entity `id` and its descendants
+    ListSubmeshes {
+        id: u64,
+        reply: Sender<Result<Vec<SubmeshInfo>, String>>,
+    },
+    /// Draw mesh `id` with a changed copy of its material, or restore the
+    /// materials of `id` and its descendants if `None`
+    SetSubmeshMaterial {
+        id: u64,
+        material: Option<MaterialOverride>,
+        reply: Sender<Result<(), String>>,
+    },
+    /// Ease morph `target` of entity `id` and its descendants towards
+    /// `weight`, replying with how many meshes have the target
+    SetMorphWeight {
+        id: u64,
+        target: MorphTarget,
+        weight: f32,
+        reply: Sender<Result<usize, String>>,
+    },
+    /// Reply with the morph targets of entity `id` and its descendants
+    ListMorphTargets {
+        id: u64,
+        reply: Sender<Result<Vec<MorphTargetInfo>, String>>,
+    },
+    /// Start the particle emitter or change its settings
+    SetParticleEmitter(ParticleEmitterUpdate),
+    /// Stop the particle emitter and remove its particles
+    StopParticleEmitter,
+    /// Replace the scene with a built-in one, removing any open model
+    SetDemoScene(DemoScene),
+    /// Generate the terrain scene again from `seed`, or the seed after the
+    /// current one if `None`, replying with the seed used
+    RegenerateTerrain {
+        seed: Option<u32>,
+        reply: Sender<u32>,
+    },
+    /// Save the current camera view as preset `name`, replying with all
+    /// presets
+    SaveCameraPreset {
+        name: String,
+        reply: Sender<CameraPresetMap>,
+    },
+    /// Move the camera to preset `name`, smoothly if `animate`
+    ApplyCameraPreset {
+        name: String,
+        animate: bool,
+        reply: Sender<Result<(), String>>,
+    },
+    /// Center the camera on the entity called `name`, smoothly if `animate`,
+    /// following it as it moves if `track`
+    LookAtEntity {
+        name: String,
+        track: bool,
+        animate: bool,
+        reply: Sender<Result<(), String>>,
+    },
+    /// Orbit around the surface under `(x, y)` of the streamed image, in
+    /// `0..=1`, smoothly if `animate`, and reply with the picked point
+    SetOrbitPivot {
+        x: f32,
+        y: f32,
+        animate: bool,
+        reply: Sender<Result<[f32; 3], String>>,
+    },
+    /// Set the duration and easing of animated camera moves
+    SetCameraTransition(CameraTransitionSettings),
+    /// Move the camera to a fixed angle, smoothly if `animate`, switching
+    /// the projection too if `projection` is set
+    SetStandardView {
+        view: StandardView,
+        animate: bool,
+        projection: Option<ProjectionKind>,
+    },
+    /// Switch between perspective and orthographic projection
+    SetProjection(ProjectionKind),
+    /// Shake the camera, replacing any running shake
+    ShakeCamera {
+        amplitude_deg: f32,
+        frequency_hz: f32,
+        decay: f32,
+    },
+    /// Move the camera toward the orbit center, by a fraction of the
+    /// distance over seconds, defaults from `config::camera` if `None`
+    PushIn {
+        amount: Option<f32>,
+        duration_s: Option<f32>,
+    },
+    /// End camera shake and push-in
+    StopCameraEffects,
+    /// Scale how fast mouse input moves the camera
+    SetCameraSensitivity(CameraSensitivity),
+    /// Split the stream into top, front, side and perspective quadrants, or
+    /// back to the perspective view alone
+    SetQuadView(bool),
+    /// Reply with the current stream camera placement and projection
+    GetCameraState {
+        reply: Sender<Result<CameraState, String>>,
+    },
+    /// Level the camera again after rolling it
+    ResetRoll,
+    /// Show or hide the diagnostics overlay rendered into stream frames
+    SetDebugOverlay(bool),
+    /// Draw Bevy UI elements into stream frames, replacing the previous
+    /// ones, or remove them if `None`
+    SetUiOverlay(Option<UiOverlaySettings>),
+    /// Replace the scene's materials with a debug view, or restore them
+    SetDebugView(DebugView),
+    /// Reply with the mesh counts of every camera
+    GetCullingStats {
+        reply: Sender<Vec<CameraCullingStats>>,
+    },
+    /// Keep culling with the stream camera's current frustum while it
+    /// moves, or follow it again
+    FreezeCulling(bool),
+    /// Read known patterns back through every readback path and reply
+    /// with what came back wrong
+    RunReadbackSelfTest {
+        reply: Sender<Result<ReadbackSelfTest, String>>,
+    },
+    /// Set the stream camera's exposure in EV100, Bevy's default if `None`
+    SetExposure(Option<f32>),
+    /// Replace the scene's lights with a preset rig
+    SetLightingPreset(LightingPreset),
+    /// Light the scene from an environment, or stop if `None`, and reply
+    /// once its maps loaded
+    SetEnvironmentMap {
+        environment: Option<EnvironmentMapSettings>,
+        reply: Sender<Result<(), String>>,
+    },
+    /// Replace the reflection probes and reply once their maps loaded
+    SetReflectionProbes {
+        probes: Vec<ReflectionProbeSettings>,
+        reply: Sender<Result<(), String>>,
+    },
+    /// Add a shadow catcher ground, replacing any previous one, or remove
+    /// it if `None`
+    SetGroundPlane(Option<GroundPlaneSettings>),
+    /// Add a reflective water plane, replacing any previous one, or remove
+    /// it if `None`
+    SetWater(Option<WaterSettings>),
+    /// Clear the stream camera to transparent instead of its background
+    /// color
+    SetTransparentBackground(bool),
+    /// Turn depth of field on with these settings, or off if `None`
+    SetDepthOfField(Option<DepthOfFieldSettings>),
+    /// Focus on the surface under `(x, y)` of the streamed image, in
+    /// `0..=1`, and reply with the focal distance
+    FocusDepthOfField {
+        x: f32,
+        y: f32,
+        reply: Sender<Result<f32, String>>,
+    },
+    /// Turn distance fog on with these settings, or off if `None`
+    SetFog(Option<FogSettings>),
+    /// Grade the stream through the `.cube` LUT at `path`, or stop if
+    /// `None`, and reply once it loaded
+    SetColorLut {
+        path: Option<PathBuf>,
+        reply: Sender<Result<(), String>>,
+    },
+    /// Zoom the camera to fit every visible mesh, smoothly if `animate`
+    FrameAll {
+        animate: bool,
+        reply: Sender<Result<(), String>>,
+    },
+    /// Fly the camera along `keyframes`, replacing any running path, and
+    /// send to `finished` when it ends
+    PlayCameraPath {
+        keyframes: Vec<CameraKeyframe>,
+        reply: Sender<Result<(), String>>,
+        finished: Sender<CameraPathEnd>,
+    },
+    /// Pause or resume the running camera path
+    SetCameraPathPaused(bool),
+    /// Jump to a time on the running camera path
+    SeekCameraPath(f64),
+    /// End the running camera path, leaving the camera where it is
+    StopCameraPath,
+    /// Send to `alerts` once `frames` consecutive frames took longer than
+    /// `threshold_ms`, replacing any earlier watch
+    WatchSlowFrames {
+        threshold_ms: f64,
+        frames: u32,
+        alerts: Sender<SlowFrameAlert>,
+    },
+    /// Stop watching for slow frames
+    StopSlowFrameAlerts,
+    /// Send asset loading progress whenever it changes from now on
+    WatchAssetProgress(Sender<AssetProgress>),
+    /// Queue files to load in the background while nothing else loads
+    PrefetchAssets(Vec<PrefetchAsset>),
+    GetPrefetchStatus {
+        reply: Sender<PrefetchStatus>,
+    },
+    /// Drop the queue and let prefetched files go unless in use
+    ClearPrefetchedAssets,
+    /// Reply with every entity's id, name and component count
+    ListEntities { reply: Sender<Vec<EntitySummary>> },
+    /// Reply with the components of entity `id`, serialized through
+    /// reflection where registered
+    InspectEntity {
+        id: u64,
+        reply: Sender<Result<EntityInspection, String>>,
+    },
+    /// Reply with the names of all resources
+    ListResources { reply: Sender<Vec<ResourceSummary>> },
+    /// Replace the tags of entity `id`
+    SetTags {
+        id: u64,
+        tags: Vec<String>,
+        reply: Sender<Result<(), String>>,
+    },
+    /// Reply with the entities tagged `tag`
+    QueryEntities {
+        tag: String,
+        reply: Sender<Vec<EntitySummary>>,
+    },
+    /// Apply `operation` to every entity tagged `tag`, replying with how
+    /// many there were
+    UpdateTagged {
+        tag: String,
+        operation: TaggedOperation,
+        reply: Sender<Result<usize, String>>,
+    },
+    /// Create a selection set of entities `entities`
+    CreateSelectionSet {
+        name: String,
+        entities: Vec<u64>,
+        reply: Sender<Result<(), String>>,
+    },
+    /// Add entities to a selection set, replying with its size
+    AddToSelectionSet {
+        name: String,
+        entities: Vec<u64>,
+        reply: Sender<Result<usize, String>>,
+    },
+    /// Remove entities from a selection set, replying with its size
+    RemoveFromSelectionSet {
+        name: String,
+        entities: Vec<u64>,
+        reply: Sender<Result<usize, String>>,
+    },
+    DeleteSelectionSet {
+        name: String,
+        reply: Sender<Result<(), String>>,
+    },
+    ListSelectionSets {
+        reply: Sender<Vec<SelectionSetInfo>>,
+    },
+    /// Select the members of a selection set, replying with them
+    SelectSelectionSet {
+        name: String,
+        reply: Sender<Result<Vec<EntitySummary>, String>>,
+    },
+    GetSelection {
+        reply: Sender<Vec<EntitySummary>>,
+    },
+    /// Apply `operation` to every member of a selection set, replying with
+    /// how many there are
+    UpdateSelectionSet {
+        name: String,
+        operation: TaggedOperation,
+        reply: Sender<Result<usize, String>>,
+    },
+    /// Stop the render loop, ending the Bevy thread after this frame
+    Exit,
+}
+
+/// A single image rendered outside the stream, tightly packed RGBA8
+pub struct CapturedImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+/// Thread-safe queue of commands waiting to be applied by Bevy
+#[derive(Clone)]
+pub struct SharedCommandQueue {
+    pub sender: Sender<BridgeCommand>,
+    pub receiver: Receiver<BridgeCommand>,
+    /// Whether commands that change the scene are rejected
+    locked: Arc<AtomicBool>,
+}
+
+impl Default for SharedCommandQueue {
+    fn default() -> Self {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        Self {
+            sender,
+            receiver,
+            locked: Arc::default(),
+        }
+    }
+}
+
+impl SharedCommandQueue {
+    /// Queue a command for the Bevy thread
+    ///
+    /// While the scene is locked, commands that change it fail with a
+    /// [`SceneLockedError`].
+    pub fn send(&self, command: BridgeCommand) -> Result<(), String> {
+        if self.is_locked() {
+            if let Some(edit) = command.scene_edit() {
+                return Err(SceneLockedError::new(edit).to_json());
+            }
+        }
+        self.sender
+            .send(command)
+            .map_err(|_| "Bevy thread is not running".to_string())
+    }
+
+    /// Reject commands that change the scene until unlocked, leaving the
+    /// camera and captures to use
+    pub fn set_locked(&self, locked: bool) {
+        self.locked.store(locked, Ordering::Relaxed);
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::Relaxed)
+    }
+}
+
+impl BridgeCommand {
+    /// Name of the scene edit this command makes, `None` if it leaves the
+    /// scene as it is
+    ///
+    /// Camera moves, captures, exports and view settings such as exposure,
+    /// debug views or the UI overlay aren't edits and run while the scene is
+    /// locked. Edits from synced instances are held by Bevy until it is
+    /// unlocked instead.
+    pub fn scene_edit(&self) -> Option<&'static str> {
+        let name = match self {
+            Self::OpenProject { .. } => "open_project",
+            Self::LoadModel { .. } => "load_model",
+            Self::SpawnPrimitive { .. } => "spawn_primitive",
+            Self::SpawnText3d { .. } => "spawn_text3d",
+            Self::AddBillboard { .. } => "add_billboard",
+            Self::RemoveBillboard { .. } => "remove_billboard",
+            Self::ClearBillboards => "clear_billboards",
+            Self::SetEntityTransform { .. } => "set_entity_transform",
+            Self::SetParent { .. } => "set_parent",
+            Self::Detach { .. } => "detach",
+            Self::Undo { .. } => "undo",
+            Self::Redo { .. } => "redo",
+            Self::RegisterCustomShader { .. } => "register_custom_shader",
+            Self::SetCustomShaderMaterial { .. } => "set_custom_shader_material",
+            Self::SetSubmeshMaterial { .. } => "set_submesh_material",
+            Self::SetMorphWeight { .. } => "set_morph_weight",
+            Self::SetParticleEmitter(_) => "set_particle_emitter",
+            Self::StopParticleEmitter => "stop_particle_emitter",
+            Self::SetDemoScene(_) => "set_demo_scene",
+            Self::RegenerateTerrain { .. } => "regenerate_terrain",
+            Self::SetLightingPreset(_) => "set_lighting_preset",
+            Self::SetEnvironmentMap { .. } => "set_environment_map",
+            Self::SetReflectionProbes { .. } => "set_reflection_probes",
+            Self::SetGroundPlane(_) => "set_ground_plane",
+            Self::SetWater(_) => "set_water",
+            Self::SetFog(_) => "set_fog",
+            Self::SetTags { .. } => "set_tags",
+            Self::UpdateTagged { .. } => "update_tagged",
+            Self::CreateSelectionSet { .. } => "create_selection_set",
+            Self::AddToSelectionSet { .. } => "add_to_selection_set",
+            Self::RemoveFromSelectionSet { .. } => "remove_from_selection_set",
+            Self::DeleteSelectionSet { .. } => "delete_selection_set",
+            Self::UpdateSelectionSet { .. } => "update_selection_set",
+            _ => return None,
+        };
+        Some(name)
+    }
+}
+
+/// Error a command that changes the scene fails with while it is locked
+///
+/// Sent as a JSON string so the frontend can tell it from other errors,
+/// e.g. `{"code":"scene_locked","command":"load_model","message":"..."}`.
+#[derive(Serialize, Clone, Debug)]
+pub struct SceneLockedError {
+    /// Always `scene_locked`
+    pub code: &'static str,
+    /// The rejected command
+    pub command: &'static str,
+    pub message: String,
+}
+
+impl SceneLockedError {
+    pub const CODE: &'static str = "scene_locked";
+
+    fn new(command: &'static str) -> Self {
+        Self {
+            code: Self::CODE,
+            command,
+            message: format!("The scene is locked, {} isn't allowed", command),
+        }
+    }
+
+    /// The error as the string commands fail with
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| self.message.clone())
+    }
+
+    /// Whether `error` is a `SceneLockedError` sent as a string
+    pub fn matches(error: &str) -> bool {
+        serde_json::from_str::<serde_json::Value>(error)
+            .is_ok_and(|value| value["code"] == Self::CODE)
+    }
+}
+
+// =============================================================================
+// Renderer Health
+// =============================================================================
+
+/// Lifecycle of the Bevy thread
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BevyThreadState {
+    /// Started, no frame run yet
+    #[default]
+    Starting,
+    Running,
+    /// Returned from the render loop
+    Exited,
+    Panicked,
+}
+
+/// Overall verdict of `get_health`
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// Bevy has not run its first frame yet
+    Starting,
+    Ok,
+    /// Bevy has not started a frame for `STALL_THRESHOLD`, it may recover
+    Stalled,
+    /// The Bevy thread is gone or the GPU device was lost, the app needs a
+    /// restart
+    Lost,
+}
+
+/// Renderer state reported by `get_health`
+#[derive(Serialize, Clone, Debug)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub bevy_thread: BevyThreadState,
+    /// Time since Bevy last started a frame, `None` before the first
+    pub last_update_age_ms: Option<f64>,
+    /// Time since the newest frame was published, `None` before the first
+    ///
+    /// Grows while the scene is static and identical frames are skipped,
+    /// so on its own it does not mean the renderer is stuck.
+    pub last_frame_age_ms: Option<f64>,
+    /// Whether the render device was created, see `get_gpu_info`
+    pub gpu_ready: bool,
+    /// Why the render device was lost, `None` while it works
+    pub gpu_device_lost: Option<String>,
+    /// wgpu errors reported since startup, see `get_gpu_errors`
+    pub gpu_errors: u64,
+    pub last_gpu_error: Option<GpuError>,
+    /// Frames waiting in the readback channel at the last receive
+    pub readback_channel_depth: u32,
+    /// Commands queued for Bevy and not applied yet
+    pub command_queue_depth: usize,
+}
+
+#[derive(Default)]
+pub struct RendererHealth {
+    pub thread: BevyThreadState,
+    /// Start of the last Bevy frame
+    pub last_update: Option<Instant>,
+    pub device_lost: Option<String>,
+}
+
+/// Liveness of the Bevy thread and the render device
+#[derive(Clone, Default)]
+pub struct SharedRendererHealth(pub Arc<Mutex<RendererHealth>>);
+
+impl SharedRendererHealth {
+    /// Record that Bevy started a frame
+    pub fn heartbeat(&self) {
+        if let Ok(mut health) = self.0.lock() {
+            health.thread = BevyThreadState::Running;
+            health.last_update = Some(Instant::now());
+        }
+    }
+
+    pub fn set_thread_state(&self, state: BevyThreadState) {
+        if let Ok(mut health) = self.0.lock() {
+            health.thread = state;
+        }
+    }
+
+    pub fn set_device_lost(&self, reason: String) {
+        if let Ok(mut health) = self.0.lock() {
+            health.device_lost = Some(reason);
+        }
+    }
+
+    /// Summarize the renderer's health from this and the rest of the
+    /// shared state
+    pub fn report(&self, state: &BridgeState) -> HealthReport {
+        let (thread, last_update, device_lost) = match self.0.lock() {
+            Ok(health) => (
+                health.thread,
+                health.last_update,
+                health.device_lost.clone(),
+            ),
+            Err(_) => (BevyThreadState::Panicked, None, None),
+        };
+        let last_update_age = last_update.map(|last_update| last_update.elapsed());
+        let last_frame_age_ms = state.frame_buffer.latest().map(|frame| {
+            let now_ms = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs_f64() * 1000.0)
+                .unwrap_or_default();
+            (now_ms - frame.timestamp_ms).max(0.0)
+        });
+
+        let status = if matches!(thread, BevyThreadState::Exited | BevyThreadState::Panicked)
+            || device_lost.is_some()
+        {
+            HealthStatus::Lost
+        } else if last_update_age.is_some_and(|age| age > STALL_THRESHOLD) {
+            HealthStatus::Stalled
+        } else if thread == BevyThreadState::Starting {
+            HealthStatus::Starting
+        } else {
+            HealthStatus::Ok
+        };
+
+        let (gpu_errors, last_gpu_error) = state.gpu_errors.summary();
+        HealthReport {
+            status,
+            bevy_thread: thread,
+            last_update_age_ms: last_update_age.map(|age| age.as_secs_f64() * 1000.0),
+            last_frame_age_ms,
+            gpu_ready: state.gpu_info.0.lock().is_ok_and(|info| info.is_some()),
+            gpu_device_lost: device_lost,
+            gpu_errors,
+            last_gpu_error,
+            readback_channel_depth: state
+                .perf_stats
+                .0
+                .lock()
+                .map_or(0, |stats| stats.channel_depth),
+            command_queue_depth: state.commands.receiver.len(),
+        }
+    }
+}
+
+// =============================================================================
+// Bridge State
+// =============================================================================
+
+/// Bundle of all shared state handed to the Bevy thread and the protocol
+///
+/// Cloning is cheap: every field is an `Arc` around the actual data.
+#[derive(Clone, Default)]
+pub struct BridgeState {
+    pub frame_buffer: SharedFrameBuffer,
+    /// Frame last served in part by `frame.raw`, see `protocol`
+    pub ranged_frame: SharedFrameBuffer,
+    pub frame_signal: SharedFrameSignal,
+    pub encoded_frame: SharedEncodedFrame,
+    pub fetch_stats: SharedFetchStats,
+    pub display_latency: SharedDisplayLatency,
+    pub gpu_info: SharedGpuInfo,
+    pub gpu_errors: SharedGpuErrors,
+    pub buffer_pool: SharedBufferPool,
+    pub perf_stats: SharedPerfStats,
+    pub health: SharedRendererHealth,
+    pub stats_history: SharedStatsHistory,
+    pub mouse_input: SharedMouseInput,
+    pub commands: SharedCommandQueue,
+    pub overlay: SharedOverlay,
+    pub capture_sinks: CaptureSinks,
+    pub stream_clients: StreamManager,
+    /// CORS and caching headers added to `frame://` responses
+    pub protocol_headers: Arc<ProtocolHeaders>,
+    pub shared_surface: SharedSurface,
+    /// Connections to other instances showing the same scene
+    pub scene_sync: SceneSync,
+}
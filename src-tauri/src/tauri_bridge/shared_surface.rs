@@ -0,0 +1,163 @@
+//! Shared GPU surface presentation
+//!
+//! The default pipeline reads every frame back to the CPU, encodes it and
+//! has the webview fetch and draw it. None of the webviews Tauri uses can
+//! import a GPU texture, so skipping the readback means presenting the
+//! render target natively instead: a child surface below the webview,
+//! backed by a DXGI shared handle on Windows, an IOSurface on macOS or a
+//! dmabuf on Linux, shows through the viewport while the webview draws
+//! the UI around and over it.
+//!
+//! A [`SurfacePresenter`] does that for one platform, through the platform
+//! bindings it brings, as texture shares do for exported textures. It is
+//! registered with `AppBuilder::surface_presenter`. When none is
+//! registered, or attaching it to the window fails, the viewport falls
+//! back to fetching frames from `frame://`; `get_presentation_mode` tells
+//! the frontend which one to use. Frames are still read back for
+//! recording, stream clients and the other CPU consumers either way; only
+//! the viewport's JPEG encode and fetch are skipped while presenting.
+//!
+//! No presenter ships with the app, so without one registered by the
+//! embedder the viewport always runs on the readback pipeline.
+
+use bevy::render::{renderer::RenderContext, texture::GpuImage};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tauri::WebviewWindow;
+
+use crate::bevy::plugins::texture_share::TextureShare;
+
+/// Presents the stream texture on a native surface below the webview
+pub trait SurfacePresenter: Send + Sync + 'static {
+    /// Short name for log messages
+    fn name(&self) -> &str;
+
+    /// Create the surface in `window`, below its webview, and make the
+    /// webview's background transparent where the viewport is
+    fn attach(&self, window: &WebviewWindow) -> Result<(), String>;
+
+    /// Move the surface over the viewport element
+    fn set_viewport(&self, viewport: SurfaceViewport) -> Result<(), String>;
+
+    /// Record commands copying `texture` into the surface and presenting
+    /// it, called in the render graph like [`TextureShare::share`]
+    fn present(&self, render_context: &mut RenderContext, texture: &GpuImage)
+        -> Result<(), String>;
+}
+
+/// Viewport element bounds in CSS pixels, from `getBoundingClientRect`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct SurfaceViewport {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// How the viewport is displayed
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PresentationMode {
+    /// Frames fetched from `frame://` and drawn by the webview
+    Readback,
+    /// Render target presented natively, nothing to fetch
+    SharedSurface,
+}
+
+/// Result of `get_presentation_mode`
+#[derive(Serialize, Clone, Debug)]
+pub struct PresentationInfo {
+    pub mode: PresentationMode,
+    /// Name of the presenter, if one is registered
+    pub presenter: Option<String>,
+}
+
+/// The registered presenter and whether it is presenting, managed as Tauri
+/// state and read by the adaptive frame rate
+#[derive(Clone, Default)]
+pub struct SharedSurface {
+    presenter: Option<Arc<dyn SurfacePresenter>>,
+    presenting: Arc<AtomicBool>,
+}
+
+impl SharedSurface {
+    pub fn new(presenter: Option<Box<dyn SurfacePresenter>>) -> Self {
+        Self {
+            presenter: presenter.map(Arc::from),
+            presenting: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Texture share feeding the presenter, for the render graph
+    pub fn texture_share(&self) -> Option<Box<dyn TextureShare>> {
+        self.presenter.clone().map(|presenter| {
+            Box::new(PresenterShare {
+                presenter,
+                presenting: self.presenting.clone(),
+            }) as Box<dyn TextureShare>
+        })
+    }
+
+    /// Attach the presenter to `window`, staying on readback if that fails
+    pub fn attach(&self, window: &WebviewWindow) {
+        let Some(presenter) = &self.presenter else {
+            return;
+        };
+        match presenter.attach(window) {
+            Ok(()) => {
+                println!("[Tauri] Presenting through {}", presenter.name());
+                self.presenting.store(true, Ordering::Relaxed);
+            }
+            Err(e) => eprintln!(
+                "[Tauri] {} unavailable, falling back to readback: {}",
+                presenter.name(),
+                e
+            ),
+        }
+    }
+
+    pub fn is_presenting(&self) -> bool {
+        self.presenting.load(Ordering::Relaxed)
+    }
+
+    pub fn info(&self) -> PresentationInfo {
+        PresentationInfo {
+            mode: if self.is_presenting() {
+                PresentationMode::SharedSurface
+            } else {
+                PresentationMode::Readback
+            },
+            presenter: self
+                .presenter
+                .as_ref()
+                .map(|presenter| presenter.name().to_string()),
+        }
+    }
+
+    pub fn set_viewport(&self, viewport: SurfaceViewport) -> Result<(), String> {
+        match &self.presenter {
+            Some(presenter) if self.is_presenting() => presenter.set_viewport(viewport),
+            _ => Err("Not presenting through a shared surface".to_string()),
+        }
+    }
+}
+
+/// Hands the stream texture to the presenter once it is attached
+struct PresenterShare {
+    presenter: Arc<dyn SurfacePresenter>,
+    presenting: Arc<AtomicBool>,
+}
+
+impl TextureShare for PresenterShare {
+    fn name(&self) -> &str {
+        self.presenter.name()
+    }
+
+    fn share(&self, render_context: &mut RenderContext, texture: &GpuImage) -> Result<(), String> {
+        if !self.presenting.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        self.presenter.present(render_context, texture)
+    }
+}
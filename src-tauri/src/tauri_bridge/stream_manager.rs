@@ -0,0 +1,317 @@
+//! Per-client stream settings
+//!
+//! The main viewport fetches `frame://localhost/frame.jpg` at the stream
+//! resolution and full JPEG quality. Other consumers, such as a small
+//! preview panel, register with their own size limit, quality and frame
+//! rate cap and fetch `frame.jpg?client=<token>` instead. The encoder
+//! thread encodes each registered client's copy of a frame after the main
+//! one, downscaled and at its quality, skipping clients whose frame rate
+//! cap hasn't allowed a new frame yet and clients that stopped fetching.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::{self, FilterType};
+use image::{ImageEncoder, RgbImage};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::info_span;
+
+use crate::config::compression::JPEG_QUALITY;
+use crate::config::stream_clients::{IDLE_AFTER, MAX_CLIENTS};
+use super::shared_state::{EncodedFrame, Frame};
+
+/// What a stream client receives
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct StreamClientSettings {
+    /// Largest width to send, frames are scaled down to fit
+    pub max_width: Option<u32>,
+    /// Largest height to send, frames are scaled down to fit
+    pub max_height: Option<u32>,
+    /// JPEG quality, 1 to 100
+    pub quality: u8,
+    /// Most frames per second to encode for the client
+    pub max_fps: Option<f64>,
+}
+
+impl Default for StreamClientSettings {
+    fn default() -> Self {
+        Self {
+            max_width: None,
+            max_height: None,
+            quality: JPEG_QUALITY,
+            max_fps: None,
+        }
+    }
+}
+
+impl StreamClientSettings {
+    fn validate(&self) -> Result<(), String> {
+        if !(1..=100).contains(&self.quality) {
+            return Err(format!("quality must be 1 to 100, got {}", self.quality));
+        }
+        if self.max_width == Some(0) || self.max_height == Some(0) {
+            return Err("max_width and max_height must be positive".to_string());
+        }
+        if self
+            .max_fps
+            .is_some_and(|fps| !fps.is_finite() || fps <= 0.0)
+        {
+            return Err("max_fps must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    /// Size a `width` x `height` frame is sent at, keeping its aspect ratio
+    fn fit(&self, width: u32, height: u32) -> (u32, u32) {
+        let scale = [
+            self.max_width.map(|max| max as f64 / width as f64),
+            self.max_height.map(|max| max as f64 / height as f64),
+        ]
+        .into_iter()
+        .flatten()
+        .fold(1.0, f64::min);
+        (
+            ((width as f64 * scale).round() as u32).max(1),
+            ((height as f64 * scale).round() as u32).max(1),
+        )
+    }
+}
+
+/// Changes to a client's settings, `None` fields are left as they are
+///
+/// 0 lifts a size or frame rate limit.
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(default)]
+pub struct StreamClientUpdate {
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    pub quality: Option<u8>,
+    pub max_fps: Option<f64>,
+}
+
+impl StreamClientUpdate {
+    fn apply(self, settings: StreamClientSettings) -> StreamClientSettings {
+        StreamClientSettings {
+            max_width: self
+                .max_width
+                .map_or(settings.max_width, |max| Some(max).filter(|&max| max > 0)),
+            max_height: self
+                .max_height
+                .map_or(settings.max_height, |max| Some(max).filter(|&max| max > 0)),
+            quality: self.quality.unwrap_or(settings.quality),
+            max_fps: self
+                .max_fps
+                .map_or(settings.max_fps, |fps| Some(fps).filter(|&fps| fps != 0.0)),
+        }
+    }
+}
+
+/// A registered client, as listed by `list_stream_clients`
+#[derive(Serialize, Clone, Debug)]
+pub struct StreamClientInfo {
+    pub token: String,
+    pub settings: StreamClientSettings,
+    /// Size of the last frame encoded for the client
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Frames fetched so far
+    pub fetches: u64,
+}
+
+struct StreamClient {
+    settings: StreamClientSettings,
+    encoded: Option<EncodedFrame>,
+    /// When the last frame was encoded, for the frame rate cap
+    last_encoded: Option<Instant>,
+    /// When the client last fetched a frame, or registered
+    last_fetch: Instant,
+    fetches: u64,
+}
+
+/// Registered stream clients, shared by the encoder thread, the protocol
+/// and the commands
+#[derive(Clone, Default)]
+pub struct StreamManager(Arc<Mutex<StreamClients>>);
+
+#[derive(Default)]
+struct StreamClients {
+    clients: BTreeMap<String, StreamClient>,
+    next_id: u64,
+}
+
+impl StreamManager {
+    /// Register a client, returning the token it fetches frames with
+    pub fn register(&self, settings: StreamClientSettings) -> Result<String, String> {
+        settings.validate()?;
+        let mut state = self.0.lock().map_err(|e| e.to_string())?;
+        if state.clients.len() >= MAX_CLIENTS {
+            return Err(format!("At most {} stream clients", MAX_CLIENTS));
+        }
+        state.next_id += 1;
+        let token = format!("client-{}", state.next_id);
+        state.clients.insert(
+            token.clone(),
+            StreamClient {
+                settings,
+                encoded: None,
+                last_encoded: None,
+                last_fetch: Instant::now(),
+                fetches: 0,
+            },
+        );
+        println!("[Stream] Registered {}: {:?}", token, settings);
+        Ok(token)
+    }
+
+    /// Change a client's settings, applied from the next frame, and return
+    /// them
+    pub fn update(
+        &self,
+        token: &str,
+        update: StreamClientUpdate,
+    ) -> Result<StreamClientSettings, String> {
+        let mut state = self.0.lock().map_err(|e| e.to_string())?;
+        let client = state
+            .clients
+            .get_mut(token)
+            .ok_or_else(|| format!("No stream client {}", token))?;
+        let settings = update.apply(client.settings);
+        settings.validate()?;
+        client.settings = settings;
+        client.last_encoded = None;
+        Ok(settings)
+    }
+
+    /// Forget a client, returning whether it was registered
+    pub fn unregister(&self, token: &str) -> Result<bool, String> {
+        let mut state = self.0.lock().map_err(|e| e.to_string())?;
+        Ok(state.clients.remove(token).is_some())
+    }
+
+    pub fn list(&self) -> Result<Vec<StreamClientInfo>, String> {
+        let state = self.0.lock().map_err(|e| e.to_string())?;
+        Ok(state
+            .clients
+            .iter()
+            .map(|(token, client)| StreamClientInfo {
+                token: token.clone(),
+                settings: client.settings,
+                width: client.encoded.as_ref().map(|encoded| encoded.width),
+                height: client.encoded.as_ref().map(|encoded| encoded.height),
+                fetches: client.fetches,
+            })
+            .collect())
+    }
+
+    /// Encode `frame` for every client due a new frame, with `rgb` its
+    /// pixels as tightly packed RGB8
+    ///
+    /// The clients aren't locked while encoding, so fetches and the
+    /// commands don't wait for it.
+    pub fn encode(&self, frame: &Frame, rgb: &[u8]) {
+        let due = self.take_due();
+        if due.is_empty() {
+            return;
+        }
+        let _span = info_span!("encode_stream_clients").entered();
+        let mut source: Option<RgbImage> = None;
+        let mut encoded = Vec::with_capacity(due.len());
+        for (token, settings) in due {
+            let (width, height) = settings.fit(frame.width, frame.height);
+            let mut jpeg = Vec::new();
+            let encoder = JpegEncoder::new_with_quality(&mut jpeg, settings.quality);
+            let result = if (width, height) == (frame.width, frame.height) {
+                encoder.write_image(rgb, width, height, image::ExtendedColorType::Rgb8)
+            } else {
+                // Scaled from one copy of the frame, shared by all clients
+                if source.is_none() {
+                    source = RgbImage::from_raw(frame.width, frame.height, rgb.to_vec());
+                }
+                let Some(source) = &source else {
+                    continue;
+                };
+                let scaled = imageops::resize(source, width, height, FilterType::Triangle);
+                encoder.write_image(
+                    scaled.as_raw(),
+                    width,
+                    height,
+                    image::ExtendedColorType::Rgb8,
+                )
+            };
+            if let Err(e) = result {
+                eprintln!("[Stream] Encoding for {} failed: {}", token, e);
+                continue;
+            }
+            let frame = EncodedFrame {
+                frame_id: frame.id,
+                timestamp_ms: frame.timestamp_ms,
+                width,
+                height,
+                data: jpeg,
+            };
+            encoded.push((token, settings, frame));
+        }
+
+        let Ok(mut state) = self.0.lock() else {
+            return;
+        };
+        for (token, settings, frame) in encoded {
+            // Dropped if the client was updated or unregistered meanwhile
+            if let Some(client) = state.clients.get_mut(&token) {
+                if client.settings == settings {
+                    client.encoded = Some(frame);
+                }
+            }
+        }
+    }
+
+    /// Tokens and settings of the clients due a new frame, counted as
+    /// encoded now for their frame rate caps
+    fn take_due(&self) -> Vec<(String, StreamClientSettings)> {
+        let Ok(mut state) = self.0.lock() else {
+            return Vec::new();
+        };
+        let now = Instant::now();
+        let mut due = Vec::new();
+        for (token, client) in &mut state.clients {
+            if now.duration_since(client.last_fetch) > IDLE_AFTER {
+                continue;
+            }
+            let ready = match (client.settings.max_fps, client.last_encoded) {
+                (Some(fps), Some(last)) => {
+                    now.duration_since(last) >= Duration::from_secs_f64(1.0 / fps)
+                }
+                _ => true,
+            };
+            if ready {
+                client.last_encoded = Some(now);
+                due.push((token.clone(), client.settings));
+            }
+        }
+        due
+    }
+
+    /// The latest frame encoded for client `token`, unless it is `after`
+    /// or older
+    ///
+    /// `Err` if the client isn't registered, `Ok(None)` if there is no
+    /// newer frame yet.
+    pub fn fetch(&self, token: &str, after: Option<u64>) -> Result<Option<EncodedFrame>, String> {
+        let mut state = self.0.lock().map_err(|e| e.to_string())?;
+        let client = state
+            .clients
+            .get_mut(token)
+            .ok_or_else(|| format!("No stream client {}", token))?;
+        client.last_fetch = Instant::now();
+        let Some(encoded) = &client.encoded else {
+            return Ok(None);
+        };
+        if after.is_some_and(|after| encoded.frame_id <= after) {
+            return Ok(None);
+        }
+        client.fetches += 1;
+        Ok(Some(encoded.clone()))
+    }
+}
@@ -0,0 +1,143 @@
+//! Turntable export
+//!
+//! One-click marketing renders: the camera circles the current orbit center
+//! once (see `bevy::systems::turntable`) and every step is written to a
+//! video or an image sequence. The renderer waits for each frame to be
+//! written before moving on, so none are dropped, and frames are spaced
+//! exactly `1 / fps` apart however long rendering took.
+
+use crossbeam_channel::bounded;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::info_span;
+
+use crate::config::recording::DEFAULT_BITRATE;
+use crate::config::turntable::{FRAME_TIMEOUT, QUEUE_LENGTH};
+use super::frame_export::{frame_path, save_frame, ImageFileFormat};
+use super::overlay::now_ms;
+use super::recorder::{Recording, RecordingFormat};
+use super::shared_state::{BridgeCommand, BridgeState, Frame, PixelFormat};
+
+/// Output of `export_turntable`
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TurntableFormat {
+    /// H.264 video file
+    Mp4,
+    /// AV1 video file
+    Webm,
+    /// Numbered PNG files in a directory
+    Png,
+    /// Numbered JPEG files in a directory
+    Jpeg,
+}
+
+/// Result of `export_turntable`
+#[derive(Serialize, Clone, Debug)]
+pub struct TurntableSummary {
+    /// Video file or image directory
+    pub path: String,
+    pub frames: u32,
+    pub duration_s: f64,
+}
+
+enum Output {
+    Video(Recording),
+    Images(ImageFileFormat),
+}
+
+/// Render one revolution lasting `seconds` at `fps` and write it to `path`,
+/// a video file or a directory for image formats
+///
+/// Blocks until every frame is written.
+pub fn export_turntable(
+    state: &BridgeState,
+    path: &Path,
+    seconds: f64,
+    fps: f64,
+    format: TurntableFormat,
+) -> Result<TurntableSummary, String> {
+    if !seconds.is_finite() || seconds <= 0.0 || !fps.is_finite() || fps <= 0.0 {
+        return Err("seconds and fps must be positive".to_string());
+    }
+    let frames = (seconds * fps).round().max(1.0) as u32;
+
+    let mut output = match format {
+        TurntableFormat::Mp4 => Output::Video(Recording::create(
+            path,
+            RecordingFormat::Mp4,
+            fps as f32,
+            DEFAULT_BITRATE,
+        )?),
+        TurntableFormat::Webm => Output::Video(Recording::create(
+            path,
+            RecordingFormat::Webm,
+            fps as f32,
+            DEFAULT_BITRATE,
+        )?),
+        TurntableFormat::Png | TurntableFormat::Jpeg => {
+            std::fs::create_dir_all(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            Output::Images(if format == TurntableFormat::Png {
+                ImageFileFormat::Png
+            } else {
+                ImageFileFormat::Jpeg
+            })
+        }
+    };
+
+    // Dropping the receiver on an error stops the render
+    let (frames_out, frames_in) = bounded(QUEUE_LENGTH);
+    state.commands.send(BridgeCommand::StartTurntable {
+        frames,
+        fps,
+        frames_out,
+    })?;
+    println!(
+        "[Turntable] Writing {} frames ({}s at {}fps) to {}",
+        frames,
+        seconds,
+        fps,
+        path.display()
+    );
+
+    let overlay = state.overlay.current();
+    let start_ms = now_ms();
+    let interval_ms = 1000.0 / fps;
+    for number in 0..frames {
+        let image = frames_in
+            .recv_timeout(FRAME_TIMEOUT)
+            .map_err(|_| "Timed out waiting for the next turntable frame".to_string())??;
+
+        let _span = info_span!("turntable_frame", number).entered();
+        let frame = Frame {
+            id: number as u64 + 1,
+            timestamp_ms: start_ms + number as f64 * interval_ms,
+            width: image.width,
+            height: image.height,
+            format: PixelFormat::Rgba8,
+            data: image.data,
+            metadata: None,
+        };
+        match &mut output {
+            Output::Video(recording) => {
+                recording.push_frame(&frame, &state.buffer_pool, overlay.as_deref())?
+            }
+            Output::Images(format) => {
+                let file = frame_path(path, number, format.extension());
+                save_frame(&frame, &state.buffer_pool, &file, *format)?
+            }
+        }
+        state.buffer_pool.recycle(frame.data);
+    }
+
+    let duration_s = frames as f64 / fps;
+    if let Output::Video(recording) = output {
+        recording.finish(start_ms + duration_s * 1000.0)?;
+    }
+    println!("[Turntable] Wrote {} frames to {}", frames, path.display());
+    Ok(TurntableSummary {
+        path: path.display().to_string(),
+        frames,
+        duration_s,
+    })
+}
@@ -0,0 +1,95 @@
+//! USDZ packaging
+//!
+//! A .usdz file is a zip archive with the stage as its first file and
+//! every file stored uncompressed, starting at a multiple of 64 bytes, so
+//! viewers can map it straight from memory. Zip writers don't usually
+//! offer that alignment, so the archive is written here; each local
+//! header's extra field is padded to push the data that follows onto the
+//! next boundary.
+
+use std::io::Cursor;
+
+use image::{ImageFormat, RgbaImage};
+
+use crate::tauri_bridge::shared_state::UsdScene;
+
+/// Alignment of file data required by the USDZ spec
+const ALIGNMENT: usize = 64;
+/// Extra field ID used for the padding, the one Pixar's usdzip uses
+const PADDING_FIELD: u16 = 0x1986;
+/// Name of the stage in the package
+const STAGE_NAME: &str = "scene.usda";
+
+/// Package `scene` with its textures as PNG files
+pub fn package(scene: &UsdScene) -> Result<Vec<u8>, String> {
+    let mut files = vec![(STAGE_NAME.to_string(), scene.usda.as_bytes().to_vec())];
+    for texture in &scene.textures {
+        let image = RgbaImage::from_raw(texture.width, texture.height, texture.rgba.clone())
+            .ok_or_else(|| format!("{}: wrong pixel count", texture.name))?;
+        let mut png = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .map_err(|e| format!("{}: {}", texture.name, e))?;
+        files.push((texture.name.clone(), png));
+    }
+    write_zip(&files)
+}
+
+/// An uncompressed zip archive with 64-byte aligned file data
+fn write_zip(files: &[(String, Vec<u8>)]) -> Result<Vec<u8>, String> {
+    let mut zip = Vec::new();
+    let mut central = Vec::new();
+    for (name, data) in files {
+        let offset = u32::try_from(zip.len()).map_err(|_| "Package over 4 GiB".to_string())?;
+        let size = u32::try_from(data.len()).map_err(|_| format!("{}: over 4 GiB", name))?;
+        let crc = crc32fast::hash(data);
+        let header_end = zip.len() + 30 + name.len() + 4;
+        let padding = (ALIGNMENT - header_end % ALIGNMENT) % ALIGNMENT;
+
+        // Local file header
+        zip.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        push_entry_fields(&mut zip, crc, size);
+        zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&(4 + padding as u16).to_le_bytes());
+        zip.extend_from_slice(name.as_bytes());
+        zip.extend_from_slice(&PADDING_FIELD.to_le_bytes());
+        zip.extend_from_slice(&(padding as u16).to_le_bytes());
+        zip.resize(zip.len() + padding, 0);
+        zip.extend_from_slice(data);
+
+        // Central directory entry
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes());
+        push_entry_fields(&mut central, crc, size);
+        central.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        // Extra field, comment, disk number, internal and external
+        // attributes
+        central.extend_from_slice(&[0; 12]);
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let central_offset = u32::try_from(zip.len()).map_err(|_| "Package over 4 GiB".to_string())?;
+    let count = files.len() as u16;
+    zip.extend_from_slice(&central);
+    // End of central directory
+    zip.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    zip.extend_from_slice(&[0; 4]);
+    zip.extend_from_slice(&count.to_le_bytes());
+    zip.extend_from_slice(&count.to_le_bytes());
+    zip.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    zip.extend_from_slice(&central_offset.to_le_bytes());
+    zip.extend_from_slice(&0u16.to_le_bytes());
+    Ok(zip)
+}
+
+/// Fields shared by local headers and central directory entries, from
+/// the version needed to the sizes
+fn push_entry_fields(out: &mut Vec<u8>, crc: u32, size: u32) {
+    // Version needed, flags, method (stored), modification time and date
+    out.extend_from_slice(&20u16.to_le_bytes());
+    out.extend_from_slice(&[0; 8]);
+    out.extend_from_slice(&crc.to_le_bytes());
+    out.extend_from_slice(&size.to_le_bytes());
+    out.extend_from_slice(&size.to_le_bytes());
+}
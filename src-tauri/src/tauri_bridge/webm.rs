@@ -0,0 +1,438 @@
+//! WebM recording
+//!
+//! AV1 encoding with rav1e and a minimal WebM writer, both pure Rust, as
+//! an alternative to MP4 that needs no ffmpeg. OpenH264 is still linked,
+//! for MP4 and for the YUV conversion here. rav1e runs at its fastest preset to keep up with the stream
+//! and still needs noticeably more CPU than H.264; frames it can't keep up
+//! with are skipped by the recorder like any other late frame.
+
+use openh264::formats::{YUVBuffer, YUVSource};
+use rav1e::prelude::{
+    ChromaSampling, Config, Context, EncoderConfig, EncoderStatus, FrameType, Rational,
+};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::config::recording::WEBM_SPEED_PRESET;
+use super::recorder::{RecordingProgress, VideoSink};
+
+/// AV1 encoder and WebM writer for one recording
+pub struct WebmRecording {
+    muxer: WebmMuxer<BufWriter<File>>,
+    /// Created for the size of the first frame
+    encoder: Option<Context<u8>>,
+    fps: f32,
+    bitrate: u32,
+    start_ms: f64,
+    /// Timestamps of frames sent to the encoder but not received back,
+    /// starting with frame number `first_pending`
+    timestamps: VecDeque<f64>,
+    first_pending: u64,
+}
+
+impl WebmRecording {
+    pub fn create(path: &Path, fps: f32, bitrate: u32) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let muxer = WebmMuxer::new(BufWriter::new(file)).map_err(|e| e.to_string())?;
+        Ok(Self {
+            muxer,
+            encoder: None,
+            fps,
+            bitrate,
+            start_ms: 0.0,
+            timestamps: VecDeque::new(),
+            first_pending: 0,
+        })
+    }
+
+    /// Write every packet the encoder has ready
+    fn receive_packets(&mut self, progress: &mut RecordingProgress) -> Result<(), String> {
+        let Some(encoder) = self.encoder.as_mut() else {
+            return Ok(());
+        };
+        loop {
+            let packet = match encoder.receive_packet() {
+                Ok(packet) => packet,
+                Err(EncoderStatus::Encoded) => continue,
+                Err(EncoderStatus::NeedMoreData | EncoderStatus::LimitReached) => return Ok(()),
+                Err(e) => return Err(format!("AV1 encoder: {:?}", e)),
+            };
+
+            while self.first_pending < packet.input_frameno && self.timestamps.len() > 1 {
+                self.timestamps.pop_front();
+                self.first_pending += 1;
+            }
+            let timestamp_ms = self.timestamps.front().copied().unwrap_or(self.start_ms);
+            let time_ms = (timestamp_ms - self.start_ms).max(0.0).round() as u64;
+            self.muxer
+                .write_block(time_ms, packet.frame_type == FrameType::KEY, &packet.data)
+                .map_err(|e| e.to_string())?;
+
+            progress.frames += 1;
+            progress.size_bytes += packet.data.len() as u64;
+            progress.duration_s = time_ms as f64 / 1000.0;
+        }
+    }
+}
+
+impl VideoSink for WebmRecording {
+    fn write_frame(
+        &mut self,
+        yuv: &YUVBuffer,
+        timestamp_ms: f64,
+        progress: &mut RecordingProgress,
+    ) -> Result<(), String> {
+        if self.encoder.is_none() {
+            let (width, height) = yuv.dimensions();
+            let encoder = create_encoder(width, height, self.fps, self.bitrate)?;
+            self.muxer
+                .write_tracks(
+                    width as u64,
+                    height as u64,
+                    &encoder.container_sequence_header(),
+                )
+                .map_err(|e| e.to_string())?;
+            self.encoder = Some(encoder);
+            self.start_ms = timestamp_ms;
+        }
+        let Some(encoder) = self.encoder.as_mut() else {
+            return Ok(());
+        };
+
+        let mut frame = encoder.new_frame();
+        let (y_stride, u_stride, v_stride) = yuv.strides();
+        frame.planes[0].copy_from_raw_u8(yuv.y(), y_stride, 1);
+        frame.planes[1].copy_from_raw_u8(yuv.u(), u_stride, 1);
+        frame.planes[2].copy_from_raw_u8(yuv.v(), v_stride, 1);
+        encoder
+            .send_frame(frame)
+            .map_err(|e| format!("AV1 encoder: {:?}", e))?;
+        self.timestamps.push_back(timestamp_ms);
+
+        self.receive_packets(progress)
+    }
+
+    /// Encode the frames still in the encoder and write the file index
+    fn finish(
+        mut self: Box<Self>,
+        end_ms: f64,
+        progress: &mut RecordingProgress,
+    ) -> Result<(), String> {
+        if let Some(encoder) = self.encoder.as_mut() {
+            encoder.flush();
+        }
+        self.receive_packets(progress)?;
+
+        let duration_ms = (end_ms - self.start_ms).max(0.0);
+        if self.encoder.is_some() {
+            progress.duration_s = duration_ms / 1000.0;
+        }
+        self.muxer.finish(duration_ms).map_err(|e| e.to_string())
+    }
+}
+
+fn create_encoder(
+    width: usize,
+    height: usize,
+    fps: f32,
+    bitrate: u32,
+) -> Result<Context<u8>, String> {
+    let mut config = EncoderConfig::with_speed_preset(WEBM_SPEED_PRESET);
+    config.width = width;
+    config.height = height;
+    config.chroma_sampling = ChromaSampling::Cs420;
+    config.time_base = Rational::new(1, fps.round().max(1.0) as u64);
+    config.bitrate = bitrate.min(i32::MAX as u32) as i32;
+    // No frame reordering: one packet per frame, in order, which maps
+    // packets straight back to their timestamps
+    config.low_latency = true;
+
+    Config::new()
+        .with_encoder_config(config)
+        .with_threads(0)
+        .new_context()
+        .map_err(|e| format!("AV1 encoder: {}", e))
+}
+
+// =============================================================================
+// Muxer
+// =============================================================================
+
+// Element ids, including their length marker bits
+const EBML: u32 = 0x1A45_DFA3;
+const EBML_VERSION: u32 = 0x4286;
+const EBML_READ_VERSION: u32 = 0x42F7;
+const EBML_MAX_ID_LENGTH: u32 = 0x42F2;
+const EBML_MAX_SIZE_LENGTH: u32 = 0x42F3;
+const DOC_TYPE: u32 = 0x4282;
+const DOC_TYPE_VERSION: u32 = 0x4287;
+const DOC_TYPE_READ_VERSION: u32 = 0x4285;
+const SEGMENT: u32 = 0x1853_8067;
+const SEEK_HEAD: u32 = 0x114D_9B74;
+const SEEK: u32 = 0x4DBB;
+const SEEK_ID: u32 = 0x53AB;
+const SEEK_POSITION: u32 = 0x53AC;
+const INFO: u32 = 0x1549_A966;
+const TIMESTAMP_SCALE: u32 = 0x2AD7B1;
+const MUXING_APP: u32 = 0x4D80;
+const WRITING_APP: u32 = 0x5741;
+const DURATION: u32 = 0x4489;
+const TRACKS: u32 = 0x1654_AE6B;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_NUMBER: u32 = 0xD7;
+const TRACK_UID: u32 = 0x73C5;
+const TRACK_TYPE: u32 = 0x83;
+const CODEC_ID: u32 = 0x86;
+const CODEC_PRIVATE: u32 = 0x63A2;
+const VIDEO: u32 = 0xE0;
+const PIXEL_WIDTH: u32 = 0xB0;
+const PIXEL_HEIGHT: u32 = 0xBA;
+const CLUSTER: u32 = 0x1F43_B675;
+const TIMESTAMP: u32 = 0xE7;
+const SIMPLE_BLOCK: u32 = 0xA3;
+const CUES: u32 = 0x1C53_BB6B;
+const CUE_POINT: u32 = 0xBB;
+const CUE_TIME: u32 = 0xB3;
+const CUE_TRACK_POSITIONS: u32 = 0xB7;
+const CUE_TRACK: u32 = 0xF7;
+const CUE_CLUSTER_POSITION: u32 = 0xF1;
+
+const APP_NAME: &[u8] = b"tauri-bevy-demo";
+
+/// Timestamps are in milliseconds
+const TIMESTAMP_SCALE_NS: u64 = 1_000_000;
+
+/// Track number of the only track in the file
+const VIDEO_TRACK: u64 = 1;
+
+/// 8-byte size marking the segment size as unknown until `finish`
+const UNKNOWN_SIZE: u64 = 0x01FF_FFFF_FFFF_FFFF;
+
+/// Blocks within a cluster until it is written
+struct Cluster {
+    timestamp_ms: u64,
+    starts_with_keyframe: bool,
+    blocks: Vec<u8>,
+}
+
+/// Minimal WebM writer: a single video track, a cluster per keyframe and
+/// a cue per cluster for seeking
+///
+/// Values only known at the end (segment size, duration, position of the
+/// cues) are written as placeholders and patched by `finish`.
+struct WebmMuxer<W: Write + Seek> {
+    writer: W,
+    /// Bytes written so far
+    position: u64,
+    /// File offset of the segment's payload, positions in the seek head
+    /// and cues are relative to it
+    segment_start: u64,
+    cues_position_offset: Option<u64>,
+    duration_offset: Option<u64>,
+    cluster: Option<Cluster>,
+    /// Timestamp and position of every cluster starting with a keyframe
+    cues: Vec<(u64, u64)>,
+}
+
+impl<W: Write + Seek> WebmMuxer<W> {
+    /// Write the file header and open the segment
+    fn new(writer: W) -> io::Result<Self> {
+        let mut header = Vec::new();
+        uint(&mut header, EBML_VERSION, 1);
+        uint(&mut header, EBML_READ_VERSION, 1);
+        uint(&mut header, EBML_MAX_ID_LENGTH, 4);
+        uint(&mut header, EBML_MAX_SIZE_LENGTH, 8);
+        element(&mut header, DOC_TYPE, b"webm");
+        uint(&mut header, DOC_TYPE_VERSION, 4);
+        uint(&mut header, DOC_TYPE_READ_VERSION, 2);
+
+        let mut bytes = Vec::new();
+        element(&mut bytes, EBML, &header);
+        write_id(&mut bytes, SEGMENT);
+        bytes.extend_from_slice(&UNKNOWN_SIZE.to_be_bytes());
+
+        let mut muxer = Self {
+            writer,
+            position: 0,
+            segment_start: 0,
+            cues_position_offset: None,
+            duration_offset: None,
+            cluster: None,
+            cues: Vec::new(),
+        };
+        muxer.write(&bytes)?;
+        muxer.segment_start = muxer.position;
+        Ok(muxer)
+    }
+
+    /// Write the seek head, segment info and the AV1 track
+    fn write_tracks(&mut self, width: u64, height: u64, codec_private: &[u8]) -> io::Result<()> {
+        let mut info_body = Vec::new();
+        uint(&mut info_body, TIMESTAMP_SCALE, TIMESTAMP_SCALE_NS);
+        element(&mut info_body, MUXING_APP, APP_NAME);
+        element(&mut info_body, WRITING_APP, APP_NAME);
+        // Last, so the placeholder is the last 8 bytes of the element
+        element(&mut info_body, DURATION, &0f64.to_be_bytes());
+        let mut info = Vec::new();
+        element(&mut info, INFO, &info_body);
+
+        let mut video = Vec::new();
+        uint(&mut video, PIXEL_WIDTH, width);
+        uint(&mut video, PIXEL_HEIGHT, height);
+        let mut entry = Vec::new();
+        uint(&mut entry, TRACK_NUMBER, VIDEO_TRACK);
+        uint(&mut entry, TRACK_UID, VIDEO_TRACK);
+        uint(&mut entry, TRACK_TYPE, 1);
+        element(&mut entry, CODEC_ID, b"V_AV1");
+        element(&mut entry, CODEC_PRIVATE, codec_private);
+        element(&mut entry, VIDEO, &video);
+        let mut track_entry = Vec::new();
+        element(&mut track_entry, TRACK_ENTRY, &entry);
+        let mut tracks = Vec::new();
+        element(&mut tracks, TRACKS, &track_entry);
+
+        // Positions are written at a fixed width, so the seek head's size
+        // doesn't depend on them
+        let seek_head_len = seek_head(&[(INFO, 0), (TRACKS, 0), (CUES, 0)]).len() as u64;
+        let info_position = self.position - self.segment_start + seek_head_len;
+        let tracks_position = info_position + info.len() as u64;
+        let seek_head = seek_head(&[(INFO, info_position), (TRACKS, tracks_position), (CUES, 0)]);
+
+        self.cues_position_offset = Some(self.position + seek_head_len - 8);
+        self.duration_offset = Some(self.position + seek_head_len + info.len() as u64 - 8);
+        self.write(&seek_head)?;
+        self.write(&info)?;
+        self.write(&tracks)
+    }
+
+    /// Add a frame shown from `time_ms`
+    fn write_block(&mut self, time_ms: u64, keyframe: bool, data: &[u8]) -> io::Result<()> {
+        // Block timestamps are 16-bit offsets from the cluster's
+        let fits = self.cluster.as_ref().is_some_and(|cluster| {
+            !keyframe && time_ms.saturating_sub(cluster.timestamp_ms) <= i16::MAX as u64
+        });
+        if !fits {
+            self.flush_cluster()?;
+        }
+        let cluster = self.cluster.get_or_insert_with(|| Cluster {
+            timestamp_ms: time_ms,
+            starts_with_keyframe: keyframe,
+            blocks: Vec::new(),
+        });
+
+        let mut block = Vec::with_capacity(data.len() + 4);
+        write_size(&mut block, VIDEO_TRACK);
+        let offset = time_ms.saturating_sub(cluster.timestamp_ms) as i16;
+        block.extend_from_slice(&offset.to_be_bytes());
+        block.push(if keyframe { 0x80 } else { 0 });
+        block.extend_from_slice(data);
+        element(&mut cluster.blocks, SIMPLE_BLOCK, &block);
+        Ok(())
+    }
+
+    fn flush_cluster(&mut self) -> io::Result<()> {
+        let Some(cluster) = self.cluster.take() else {
+            return Ok(());
+        };
+        if cluster.starts_with_keyframe {
+            self.cues
+                .push((cluster.timestamp_ms, self.position - self.segment_start));
+        }
+        let mut body = Vec::with_capacity(cluster.blocks.len() + 8);
+        uint(&mut body, TIMESTAMP, cluster.timestamp_ms);
+        body.extend_from_slice(&cluster.blocks);
+        let mut bytes = Vec::with_capacity(body.len() + 12);
+        element(&mut bytes, CLUSTER, &body);
+        self.write(&bytes)
+    }
+
+    /// Write the last cluster and the cues, then fill in the placeholders
+    fn finish(mut self, duration_ms: f64) -> io::Result<()> {
+        self.flush_cluster()?;
+
+        let cues_position = self.position - self.segment_start;
+        let mut points = Vec::new();
+        for &(time_ms, cluster_position) in &self.cues {
+            let mut positions = Vec::new();
+            uint(&mut positions, CUE_TRACK, VIDEO_TRACK);
+            uint(&mut positions, CUE_CLUSTER_POSITION, cluster_position);
+            let mut point = Vec::new();
+            uint(&mut point, CUE_TIME, time_ms);
+            element(&mut point, CUE_TRACK_POSITIONS, &positions);
+            element(&mut points, CUE_POINT, &point);
+        }
+        if !points.is_empty() {
+            let mut cues = Vec::new();
+            element(&mut cues, CUES, &points);
+            self.write(&cues)?;
+            if let Some(offset) = self.cues_position_offset {
+                self.patch(offset, &cues_position.to_be_bytes())?;
+            }
+        }
+        if let Some(offset) = self.duration_offset {
+            self.patch(offset, &duration_ms.to_be_bytes())?;
+        }
+        let segment_size = self.position - self.segment_start;
+        self.patch(
+            self.segment_start - 8,
+            &(segment_size | (1 << 56)).to_be_bytes(),
+        )?;
+        self.writer.flush()
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes)?;
+        self.position += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn patch(&mut self, offset: u64, bytes: &[u8]) -> io::Result<()> {
+        self.writer.seek(SeekFrom::Start(offset))?;
+        self.writer.write_all(bytes)?;
+        self.writer.seek(SeekFrom::Start(self.position))?;
+        Ok(())
+    }
+}
+
+fn seek_head(entries: &[(u32, u64)]) -> Vec<u8> {
+    let mut seeks = Vec::new();
+    for &(id, position) in entries {
+        let mut id_bytes = Vec::new();
+        write_id(&mut id_bytes, id);
+        let mut seek = Vec::new();
+        element(&mut seek, SEEK_ID, &id_bytes);
+        element(&mut seek, SEEK_POSITION, &position.to_be_bytes());
+        element(&mut seeks, SEEK, &seek);
+    }
+    let mut bytes = Vec::new();
+    element(&mut bytes, SEEK_HEAD, &seeks);
+    bytes
+}
+
+fn write_id(out: &mut Vec<u8>, id: u32) {
+    let skip = (id.leading_zeros() / 8) as usize;
+    out.extend_from_slice(&id.to_be_bytes()[skip..]);
+}
+
+/// Variable-length size, in as few bytes as possible
+fn write_size(out: &mut Vec<u8>, size: u64) {
+    // All ones is reserved for "unknown"
+    let length = (1..8)
+        .find(|&length| size < (1 << (7 * length)) - 1)
+        .unwrap_or(8);
+    let marked = size | (1 << (7 * length));
+    out.extend_from_slice(&marked.to_be_bytes()[8 - length..]);
+}
+
+fn element(out: &mut Vec<u8>, id: u32, body: &[u8]) {
+    write_id(out, id);
+    write_size(out, body.len() as u64);
+    out.extend_from_slice(body);
+}
+
+fn uint(out: &mut Vec<u8>, id: u32, value: u64) {
+    let skip = ((value.leading_zeros() / 8) as usize).min(7);
+    element(out, id, &value.to_be_bytes()[skip..]);
+}
@@ -0,0 +1,69 @@
+//! Serialization of stats, scene queries and control server messages
+//!
+//! Everything is JSON by default. Scene trees of large models make JSON
+//! slow to build and parse, so clients can ask for MessagePack instead:
+//! `frame://` endpoints with `?format=msgpack`, and control server clients
+//! by sending their request headers as MessagePack, which replies to them
+//! match.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Encoding of a structured message
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WireFormat {
+    #[default]
+    Json,
+    MessagePack,
+}
+
+impl WireFormat {
+    /// Format asked for with a `format=json` or `format=msgpack` query
+    /// parameter, JSON if there is none
+    pub fn from_query(query: &str) -> Result<Self, String> {
+        match query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("format="))
+        {
+            None | Some("json") => Ok(WireFormat::Json),
+            Some("msgpack") => Ok(WireFormat::MessagePack),
+            Some(other) => Err(format!(
+                "Unknown format {}, expected json or msgpack",
+                other
+            )),
+        }
+    }
+
+    /// Format of a message holding a map, told apart by its first byte:
+    /// JSON objects start with `{` (possibly after whitespace), which no
+    /// MessagePack map does
+    pub fn detect(message: &[u8]) -> Self {
+        match message.iter().find(|byte| !byte.is_ascii_whitespace()) {
+            Some(b'{') | None => WireFormat::Json,
+            Some(_) => WireFormat::MessagePack,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            WireFormat::Json => "application/json",
+            WireFormat::MessagePack => "application/msgpack",
+        }
+    }
+
+    /// Structs become maps with field names in both formats, so clients
+    /// see the same shape
+    pub fn to_vec(self, value: &impl Serialize) -> Result<Vec<u8>, String> {
+        match self {
+            WireFormat::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+            WireFormat::MessagePack => rmp_serde::to_vec_named(value).map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn from_slice<T: DeserializeOwned>(self, data: &[u8]) -> Result<T, String> {
+        match self {
+            WireFormat::Json => serde_json::from_slice(data).map_err(|e| e.to_string()),
+            WireFormat::MessagePack => rmp_serde::from_slice(data).map_err(|e| e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Headless test harness
+//!
+//! [`TestRenderer`] runs the same Bevy app as the Tauri frontend, but on
+//! the calling thread and one frame per [`TestRenderer::step`], in
+//! deterministic mode. Commands and mouse input go through the same shared
+//! state the Tauri commands use, so integration tests exercise the real
+//! render pipeline:
+//!
+//! ```ignore
+//! let mut renderer = TestRenderer::new();
+//! let before = renderer.settled_frame()?;
+//! renderer.mouse_input(MouseInput { delta_x: 40.0, left_button: true, ..Default::default() });
+//! let after = renderer.settled_frame()?;
+//! assert_ne!(frame_hash::hash_frame(&before).perceptual, frame_hash::hash_frame(&after).perceptual);
+//! ```
+//!
+//! A GPU adapter is still needed; in CI without one, a software adapter
+//! such as lavapipe or WARP works.
+
+use bevy::app::App;
+use bevy::ecs::world::World;
+use crossbeam_channel::Sender;
+use std::sync::Arc;
+
+use crate::bevy::app::{create_app_with, finish_plugins};
+use crate::bevy::AppOptions;
+use crate::config::testing::{FIXED_FPS, MAX_STEPS, SETTLE_FRAMES};
+use crate::tauri_bridge::shared_state::{BridgeCommand, Frame, MouseInput};
+use crate::tauri_bridge::BridgeState;
+
+/// The Bevy app stepped one frame at a time, without Tauri
+pub struct TestRenderer {
+    app: App,
+    bridge: BridgeState,
+}
+
+impl Default for TestRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestRenderer {
+    /// Create the app at the default render size, with time advancing
+    /// `1 / FIXED_FPS` per frame
+    pub fn new() -> Self {
+        Self::with_options(AppOptions::default())
+    }
+
+    /// Create the app with non-default [`AppOptions`], deterministic at
+    /// `FIXED_FPS` unless `fixed_fps` says otherwise
+    pub fn with_options(mut options: AppOptions) -> Self {
+        options.fixed_fps = options.fixed_fps.or(Some(FIXED_FPS));
+        let bridge = BridgeState::default();
+        let mut app = create_app_with(bridge.clone(), options);
+        finish_plugins(&mut app);
+        Self { app, bridge }
+    }
+
+    /// Shared state, as seen by the Tauri commands
+    pub fn bridge(&self) -> &BridgeState {
+        &self.bridge
+    }
+
+    pub fn world(&self) -> &World {
+        self.app.world()
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        self.app.world_mut()
+    }
+
+    /// Run one frame
+    pub fn step(&mut self) {
+        self.app.update();
+    }
+
+    /// Run `frames` frames
+    pub fn step_frames(&mut self, frames: u32) {
+        for _ in 0..frames {
+            self.step();
+        }
+    }
+
+    /// Queue a command, applied at the start of the next frame
+    pub fn send(&self, command: BridgeCommand) -> Result<(), String> {
+        self.bridge.commands.send(command)
+    }
+
+    /// Send the command built around a reply channel and step until the
+    /// reply arrives
+    ///
+    /// ```ignore
+    /// let camera = renderer.request(|reply| BridgeCommand::GetCameraState { reply })??;
+    /// ```
+    pub fn request<T>(
+        &mut self,
+        command: impl FnOnce(Sender<T>) -> BridgeCommand,
+    ) -> Result<T, String> {
+        let (reply, response) = crossbeam_channel::bounded(1);
+        self.send(command(reply))?;
+        for _ in 0..MAX_STEPS {
+            self.step();
+            if let Ok(value) = response.try_recv() {
+                return Ok(value);
+            }
+        }
+        Err(format!("No reply within {} frames", MAX_STEPS))
+    }
+
+    /// Add mouse input as `send_mouse_input` does, applied next frame
+    pub fn mouse_input(&self, input: MouseInput) {
+        if let Err(e) = self.bridge.mouse_input.add(&input) {
+            eprintln!("[Test] Failed to add mouse input: {}", e);
+        }
+    }
+
+    /// The newest published frame, if any
+    pub fn latest_frame(&self) -> Option<Arc<Frame>> {
+        self.bridge.frame_buffer.latest()
+    }
+
+    /// Step until a frame newer than the current one is published
+    ///
+    /// Identical frames are not published, so this fails on a static scene
+    /// with a still camera. The frame may have been rendered a few frames
+    /// before it arrived, see [`Self::settled_frame`].
+    pub fn next_frame(&mut self) -> Result<Arc<Frame>, String> {
+        let last_id = self.latest_frame().map_or(0, |frame| frame.id);
+        for _ in 0..MAX_STEPS {
+            self.step();
+            if let Some(frame) = self.latest_frame().filter(|frame| frame.id > last_id) {
+                return Ok(frame);
+            }
+        }
+        Err(format!("No new frame within {} frames", MAX_STEPS))
+    }
+
+    /// The next frame rendered after everything sent so far took effect
+    pub fn settled_frame(&mut self) -> Result<Arc<Frame>, String> {
+        self.step_frames(SETTLE_FRAMES);
+        self.next_frame()
+    }
+}
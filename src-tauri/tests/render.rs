@@ -0,0 +1,41 @@
+//! Render tests on a real GPU adapter
+//!
+//! Ignored by default, since CI machines may have no adapter. Run them
+//! with `cargo test --test render -- --ignored`; a software adapter such
+//! as lavapipe or WARP is enough.
+
+use tauri_bevy_demo_lib::tauri_bridge::frame_hash::hash_frame;
+use tauri_bevy_demo_lib::tauri_bridge::shared_state::MouseInput;
+use tauri_bevy_demo_lib::testing::TestRenderer;
+
+#[test]
+#[ignore = "needs a GPU adapter"]
+fn renders_the_scene_and_orbits_on_drag() {
+    let mut renderer = TestRenderer::new();
+    let before = renderer.settled_frame().expect("no frame rendered");
+
+    let pixel_bytes = before.format.bytes_per_pixel();
+    assert_eq!(
+        before.data.len(),
+        (before.width * before.height) as usize * pixel_bytes
+    );
+    let first = &before.data[..pixel_bytes];
+    assert!(
+        before
+            .data
+            .chunks_exact(pixel_bytes)
+            .any(|pixel| pixel != first),
+        "frame is a single color, the scene wasn't drawn"
+    );
+
+    renderer.mouse_input(MouseInput {
+        delta_x: 40.0,
+        left_button: true,
+        ..Default::default()
+    });
+    let after = renderer.settled_frame().expect("no frame after the drag");
+    assert_ne!(
+        hash_frame(&before).perceptual,
+        hash_frame(&after).perceptual
+    );
+}
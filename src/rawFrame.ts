@@ -0,0 +1,55 @@
+/**
+ * Raw frame decoding
+ *
+ * Frames served from `frame.raw` are tightly packed pixels whose layout is
+ * given by the `X-Frame-Format` header. This converts them to the RGBA
+ * layout canvas ImageData expects.
+ */
+
+/** Pixel layouts the backend can serve, see `PixelFormat` in Rust */
+export type PixelFormat = "rgba8" | "rgb8" | "rgb565";
+
+/**
+ * Convert a raw frame to ImageData
+ *
+ * RGB565 pixels are little-endian 16-bit words. Each channel has its high
+ * bits replicated into the low ones so full intensity maps to 255.
+ */
+export function decodeRawFrame(
+  buffer: ArrayBuffer,
+  width: number,
+  height: number,
+  format: PixelFormat,
+): ImageData {
+  const pixelCount = width * height;
+
+  if (format === "rgba8") {
+    return new ImageData(new Uint8ClampedArray(buffer, 0, pixelCount * 4), width, height);
+  }
+
+  const rgba = new Uint8ClampedArray(pixelCount * 4);
+
+  if (format === "rgb8") {
+    const src = new Uint8Array(buffer);
+    for (let i = 0; i < pixelCount; i++) {
+      rgba[i * 4] = src[i * 3];
+      rgba[i * 4 + 1] = src[i * 3 + 1];
+      rgba[i * 4 + 2] = src[i * 3 + 2];
+      rgba[i * 4 + 3] = 255;
+    }
+  } else {
+    const src = new DataView(buffer);
+    for (let i = 0; i < pixelCount; i++) {
+      const value = src.getUint16(i * 2, true);
+      const r = (value >> 11) & 0x1f;
+      const g = (value >> 5) & 0x3f;
+      const b = value & 0x1f;
+      rgba[i * 4] = (r << 3) | (r >> 2);
+      rgba[i * 4 + 1] = (g << 2) | (g >> 4);
+      rgba[i * 4 + 2] = (b << 3) | (b >> 2);
+      rgba[i * 4 + 3] = 255;
+    }
+  }
+
+  return new ImageData(rgba, width, height);
+}